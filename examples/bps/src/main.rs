@@ -11,7 +11,24 @@ use petsc_rs::prelude::*;
 // ----------------------------------------------------------------------------
 fn main() -> meles::Result<()> {
     let petsc = petsc_rs::Petsc::init_no_args()?;
-    let mut meles = meles::Meles::new(&petsc, "./bps1.yml", meles::MethodType::BenchmarkProblem)?;
+    meles::install_shutdown_handler()?;
+    let meles = meles::Meles::new(&petsc, "./bps1.yml", meles::MethodType::BenchmarkProblem)?;
+
+    // Validate the setup only
+    if meles.is_dry_run(&petsc)? {
+        println!("{}", meles.setup_report(&petsc)?);
+        return Ok(());
+    }
+
+    // Benchmark only
+    if meles.is_benchmark(&petsc)? {
+        println!("{}", meles.benchmark_from_options(&petsc)?);
+        return Ok(());
+    }
+
+    // Solve
+    let result = meles.solve(&petsc)?;
+    println!("{}", result);
     Ok(())
 }
 
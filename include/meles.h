@@ -0,0 +1,35 @@
+// -----------------------------------------------------------------------------
+//                               Meles C API
+//
+// Build the shared library with
+//   cargo rustc --release --features capi --crate-type cdylib
+// and link against it.
+// The host application owns PETSc initialization and finalization, and must
+// initialize PETSc before meles_create().
+// All functions return 0 on success; see meles_last_error() on failure.
+// -----------------------------------------------------------------------------
+#ifndef MELES_H
+#define MELES_H
+
+#include <petscdm.h>
+#include <petscmat.h>
+#include <petscvec.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct MelesHandle *Meles;
+
+const char *meles_last_error(void);
+int meles_create(const char *yml, Meles *meles);
+int meles_dm(Meles meles, DM *dm);
+int meles_mat_shell(Meles meles, Mat *mat);
+int meles_solve(Meles meles, Vec b, Vec x, PetscInt *iterations);
+int meles_destroy(Meles *meles);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif  // MELES_H
@@ -0,0 +1,310 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Advection-diffusion operator splitting
+// -----------------------------------------------------------------------------
+/// Diffusive, advective, and combined operators for advection-diffusion
+///
+/// All three operators share the same geometric setup data. The diffusive
+/// operator is the symmetric part; for a divergence free wind with essential
+/// boundary conditions the advective operator is the skew part. Separate
+/// MatShells allow operator-split or physics-based preconditioners.
+///
+/// Note: Requires a scalar problem on a plex mesh with a single hexahedral
+/// cell type.
+pub struct AdvectionDiffusion<'a> {
+    pub diffusive: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    pub advective: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    pub combined: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+}
+
+// -----------------------------------------------------------------------------
+// QFunctions
+//   qdata holds w det(J) followed by the entries of J^{-1}
+// -----------------------------------------------------------------------------
+pub(crate) const Q_DATA_SIZE: usize = 10;
+
+// Physical gradient from the reference gradient and J^{-1}
+fn physical_gradient(du: &[f64], q_data: &[f64], q: usize, i: usize) -> [f64; 3] {
+    let mut grad = [0.0; 3];
+    for (j, g) in grad.iter_mut().enumerate() {
+        *g = (0..3)
+            .map(|d| q_data[(1 + d * 3 + j) * q + i] * du[d * q + i])
+            .sum();
+    }
+    grad
+}
+
+pub(crate) fn setup_q_function<'b>(ceed: &libceed::Ceed) -> crate::Result<QFunction<'b>> {
+    let setup = |[dx, weights, ..]: QFunctionInputs, [q_data, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            let j = |c: usize, d: usize| dx[(d * 3 + c) * q + i];
+            // -- Cofactors and determinant
+            let cof = |r: usize, c: usize| {
+                let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+                let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+                j(r1, c1) * j(r2, c2) - j(r1, c2) * j(r2, c1)
+            };
+            let det_j = (0..3).map(|c| j(0, c) * cof(0, c)).sum::<f64>();
+            q_data[i] = weights[i] * det_j;
+            // -- J^{-1}[d][c] = cof(c, d) / det(J)
+            for d in 0..3 {
+                for c in 0..3 {
+                    q_data[(1 + d * 3 + c) * q + i] = cof(c, d) / det_j;
+                }
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(setup))?
+        .input("dx", 9, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("qdata", Q_DATA_SIZE, EvalMode::None)?;
+    Ok(qf)
+}
+
+fn diffusive_q_function<'b>(ceed: &libceed::Ceed) -> crate::Result<QFunction<'b>> {
+    let apply = |[du, q_data, ..]: QFunctionInputs, [dv, ..]: QFunctionOutputs| {
+        let q = q_data.len() / Q_DATA_SIZE;
+        for i in 0..q {
+            let grad = physical_gradient(du, q_data, q, i);
+            for d in 0..3 {
+                dv[d * q + i] = q_data[i]
+                    * (0..3)
+                        .map(|c| q_data[(1 + d * 3 + c) * q + i] * grad[c])
+                        .sum::<f64>();
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("du", 3, EvalMode::Grad)?
+        .input("qdata", Q_DATA_SIZE, EvalMode::None)?
+        .output("dv", 3, EvalMode::Grad)?;
+    Ok(qf)
+}
+
+fn advective_q_function<'b>(ceed: &libceed::Ceed, wind: [f64; 3]) -> crate::Result<QFunction<'b>> {
+    let apply = move |[du, q_data, ..]: QFunctionInputs, [v, ..]: QFunctionOutputs| {
+        let q = q_data.len() / Q_DATA_SIZE;
+        for i in 0..q {
+            let grad = physical_gradient(du, q_data, q, i);
+            v[i] = q_data[i] * (0..3).map(|c| wind[c] * grad[c]).sum::<f64>();
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("du", 3, EvalMode::Grad)?
+        .input("qdata", Q_DATA_SIZE, EvalMode::None)?
+        .output("v", 1, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+fn combined_q_function<'b>(ceed: &libceed::Ceed, wind: [f64; 3]) -> crate::Result<QFunction<'b>> {
+    let apply = move |[du, q_data, ..]: QFunctionInputs, [v, dv, ..]: QFunctionOutputs| {
+        let q = q_data.len() / Q_DATA_SIZE;
+        for i in 0..q {
+            let grad = physical_gradient(du, q_data, q, i);
+            v[i] = q_data[i] * (0..3).map(|c| wind[c] * grad[c]).sum::<f64>();
+            for d in 0..3 {
+                dv[d * q + i] = q_data[i]
+                    * (0..3)
+                        .map(|c| q_data[(1 + d * 3 + c) * q + i] * grad[c])
+                        .sum::<f64>();
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("du", 3, EvalMode::Grad)?
+        .input("qdata", Q_DATA_SIZE, EvalMode::None)?
+        .output("v", 1, EvalMode::Interp)?
+        .output("dv", 3, EvalMode::Grad)?;
+    Ok(qf)
+}
+
+// -----------------------------------------------------------------------------
+// Setup advection-diffusion operators
+// -----------------------------------------------------------------------------
+pub(crate) fn advection_diffusion<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    wind: [f64; 3],
+) -> crate::Result<AdvectionDiffusion<'a>> {
+    let crate::ceed_bps::Opt {
+        problem: _,
+        order,
+        q_extra,
+        on_the_fly_geometry: _,
+        emulate_single_precision: _,
+        mesh_type,
+        refine: _,
+        local_dofs: _,
+        periodic: _,
+        mesh_transform: _,
+        kershaw_eps: _,
+        neumann: _,
+        essential_boundaries: _,
+        boundary_functions: _,
+        boundary_conditions: _,
+        neumann_boundaries: _,
+        neumann_flux: _,
+        u_bc: _,
+        coefficient: _,
+        operator: _,
+        diffusion_tensor: _,
+        diffusion_label: _,
+        wavenumber: _,
+        solution: _,
+    } = *options;
+    crate::backend::require_closure_q_functions(meles, "advection-diffusion operators")?;
+
+    // Duplicate DM
+    let mut dm = meles.dm.borrow().clone();
+    crate::ceed_bps::setup_dm(&mut dm, options)?;
+    if mesh_type != crate::dm::MeshType::Plex || crate::dm::plex_cell_types(&dm)?.len() > 1 {
+        return Err(crate::Error {
+            message: "advection-diffusion requires a plex mesh with a single cell type".to_string(),
+        });
+    }
+    let num_components = crate::ceed_bps::num_components(options)?;
+    if num_components != 1 {
+        return Err(crate::Error {
+            message: format!(
+                "advection-diffusion requires a scalar problem, not {} components",
+                num_components
+            ),
+        });
+    }
+
+    // Basis and restrictions
+    let p = order + 1;
+    let q = p + q_extra;
+    let dimension = dm.dimension()?;
+    let basis_x = meles.ceed.basis_tensor_H1_Lagrange(
+        dimension,
+        dimension,
+        2,
+        q,
+        libceed::QuadMode::Gauss,
+    )?;
+    let basis_u =
+        meles
+            .ceed
+            .basis_tensor_H1_Lagrange(dimension, 1, p, q, libceed::QuadMode::Gauss)?;
+    let restr_u = crate::dm::create_restriction_from_dm_plex(&dm, &meles.ceed, 0, None, 0)?;
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+    };
+    let num_elements = restr_u.num_elements();
+    let num_quadrature_points = basis_u.num_quadrature_points();
+    let restr_qdata = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        num_elements,
+        num_quadrature_points,
+        Q_DATA_SIZE,
+    )?;
+
+    // Shared setup data
+    let mut qdata = restr_qdata.create_lvector()?;
+    {
+        let mut coord_loc = {
+            let mut dm = meles.dm.borrow_mut();
+            dm.coordinates_local()?
+        };
+        let mut coord_loc_ceed = meles.ceed.vector(coord_loc.local_size()?)?;
+        let qf_setup = setup_q_function(&meles.ceed)?;
+        let op_setup = meles
+            .ceed
+            .operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("dx", &restr_x, &basis_x, VectorOpt::Active)?
+            .field(
+                "weights",
+                ElemRestrictionOpt::None,
+                &basis_x,
+                VectorOpt::None,
+            )?
+            .field(
+                "qdata",
+                &restr_qdata,
+                BasisOpt::Collocated,
+                VectorOpt::Active,
+            )?
+            .check()?;
+        crate::transfer::with_wrapped(&mut coord_loc, &mut coord_loc_ceed, |coord_loc_ceed| {
+            Ok(op_setup.apply(coord_loc_ceed, &mut qdata)?)
+        })?;
+    }
+    let qdata = std::rc::Rc::new(qdata);
+
+    // Operators
+    let qf_diffusive = diffusive_q_function(&meles.ceed)?;
+    let qf_advective = advective_q_function(&meles.ceed, wind)?;
+    let qf_combined = combined_q_function(&meles.ceed, wind)?;
+    let op_diffusive = meles
+        .ceed
+        .operator(&qf_diffusive, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("du", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &*qdata)?
+        .field("dv", &restr_u, &basis_u, VectorOpt::Active)?
+        .check()?;
+    let op_advective = meles
+        .ceed
+        .operator(&qf_advective, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("du", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &*qdata)?
+        .field("v", &restr_u, &basis_u, VectorOpt::Active)?
+        .check()?;
+    let op_combined = meles
+        .ceed
+        .operator(&qf_combined, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("du", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &*qdata)?
+        .field("v", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("dv", &restr_u, &basis_u, VectorOpt::Active)?
+        .check()?;
+
+    // MatShells
+    let mat_shell = |op_ceed: libceed::operator::Operator<'a>| -> crate::Result<_> {
+        let x_loc = dm.create_local_vector()?;
+        let y_loc = dm.create_local_vector()?;
+        let x_loc_size = x_loc.local_size()?;
+        let mut rhs = dm.create_global_vector()?;
+        rhs.zero_entries()?;
+        crate::petsc_ops::create_mat_shell(crate::MelesMatShellContext {
+            ceed: meles.ceed.clone(),
+            dm: RefCell::new(dm.clone()),
+            x_loc: RefCell::new(x_loc),
+            y_loc: RefCell::new(y_loc),
+            x_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+            y_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+            op_ceed: RefCell::new(op_ceed.into()),
+            diagonal: RefCell::new(None),
+            num_components: 1,
+            point_block_inverse: RefCell::new(None),
+            rhs,
+            qdata: vec![crate::qdata::QDataVector {
+                vector: qdata.clone(),
+                num_elements,
+                num_quadrature_points,
+                size: Q_DATA_SIZE,
+            }],
+            true_solution: vec![],
+            error_ops: vec![],
+            single_precision: false,
+        })
+    };
+    Ok(AdvectionDiffusion {
+        diffusive: mat_shell(op_diffusive)?,
+        advective: mat_shell(op_advective)?,
+        combined: mat_shell(op_combined)?,
+    })
+}
+
+// -----------------------------------------------------------------------------
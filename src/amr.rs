@@ -0,0 +1,558 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Marking strategies
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Strategy for marking elements for refinement from their error indicators
+pub enum AmrMarking {
+    /// Mark every element with an indicator of at least this fraction of the
+    ///   largest indicator
+    Maximum(f64),
+    /// Dörfler bulk marking, marking the elements with the largest indicators
+    ///   until their squares sum to at least this fraction of the total
+    Dorfler(f64),
+}
+
+// -----------------------------------------------------------------------------
+// Adaptive refinement results
+// -----------------------------------------------------------------------------
+/// Results of an adaptive refinement step
+pub struct AmrStep<'a> {
+    /// Solution interpolated onto the refined mesh
+    pub solution: petsc::vector::Vector<'a>,
+    /// Global error estimate, the l2 norm of the element indicators on the
+    ///   mesh before refinement
+    pub estimate: f64,
+    /// Number of elements marked for refinement
+    pub marked_elements: usize,
+    /// Number of elements of the refined mesh
+    pub global_elements: usize,
+}
+
+impl<'a> fmt::Display for AmrStep<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "AMR Step:")?;
+        writeln!(f, "  Error estimate  : {:e}", self.estimate)?;
+        writeln!(f, "  Marked elements : {}", self.marked_elements)?;
+        write!(f, "  Global elements : {}", self.global_elements)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Adaptive refinement command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) transform: String,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let transform = pob.options_string(
+            "-amr_transform",
+            "DMPlex transform refining the marked elements, conforming on simplices",
+            "",
+            "refine_alfeld",
+        )?;
+        Ok(Opt { transform })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Element error indicators
+//   The Kelly estimator, eta_K^2 = h_K / 24 sum_F |F| |[grad u . n]|^2 over the
+//   interior faces F of each element, with the gradients evaluated at the
+//   element centers; faces on the domain boundary, or on partition boundaries
+//   of meshes without overlap, have one adjacent element and are skipped
+// -----------------------------------------------------------------------------
+pub(crate) fn error_indicators<'a>(
+    meles: &'a crate::Meles<'a>,
+    solution: &petsc::vector::Vector<'a>,
+) -> crate::Result<Vec<f64>> {
+    let dm = meles.dm.borrow();
+    let dimension = dm.dimension()?;
+    let is_simplex = crate::dm::plex_is_simplex(&dm)?;
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(&dm)?;
+    let has_essential_boundary = crate::ceed_bps::has_essential_boundary(&meles.bp_options)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+
+        // Solution with essential boundary values
+        let mut solution_loc = std::ptr::null_mut();
+        check(
+            DMGetLocalVector(dm.as_raw(), &mut solution_loc),
+            "DMGetLocalVector",
+        )?;
+        let result = (|| {
+            check(VecZeroEntries(solution_loc), "VecZeroEntries")?;
+            check(
+                DMGlobalToLocal(
+                    dm.as_raw(),
+                    solution.as_raw(),
+                    InsertMode::INSERT_VALUES,
+                    solution_loc,
+                ),
+                "DMGlobalToLocal",
+            )?;
+            if has_essential_boundary {
+                check(
+                    DMPlexInsertBoundaryValues(
+                        dm.as_raw(),
+                        PetscBool::PETSC_TRUE,
+                        solution_loc,
+                        0.0,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    ),
+                    "DMPlexInsertBoundaryValues",
+                )?;
+            }
+            let (gradients, diameters) = cell_gradients(
+                dm.as_raw(),
+                solution_loc,
+                dimension,
+                is_simplex,
+                (cell_start, cell_end),
+            )?;
+
+            // Gradient jumps over interior faces
+            let (mut face_start, mut face_end) = (0, 0);
+            check(
+                DMPlexGetHeightStratum(dm.as_raw(), 1, &mut face_start, &mut face_end),
+                "DMPlexGetHeightStratum",
+            )?;
+            let mut jump_sums = vec![0.0; diameters.len()];
+            for face in face_start..face_end {
+                let mut support_size = 0;
+                check(
+                    DMPlexGetSupportSize(dm.as_raw(), face, &mut support_size),
+                    "DMPlexGetSupportSize",
+                )?;
+                if support_size != 2 {
+                    continue;
+                }
+                let mut support = std::ptr::null();
+                check(
+                    DMPlexGetSupport(dm.as_raw(), face, &mut support),
+                    "DMPlexGetSupport",
+                )?;
+                let support = std::slice::from_raw_parts(support, 2);
+                let (left, right) = (
+                    (support[0] - cell_start) as usize,
+                    (support[1] - cell_start) as usize,
+                );
+                let mut area = 0.0;
+                let mut normal = [0.0; 3];
+                check(
+                    DMPlexComputeCellGeometryFVM(
+                        dm.as_raw(),
+                        face,
+                        &mut area,
+                        std::ptr::null_mut(),
+                        normal.as_mut_ptr(),
+                    ),
+                    "DMPlexComputeCellGeometryFVM",
+                )?;
+                let jump_squared: f64 = gradients[left]
+                    .chunks(dimension)
+                    .zip(gradients[right].chunks(dimension))
+                    .map(|(gradient_left, gradient_right)| {
+                        let jump: f64 = gradient_left
+                            .iter()
+                            .zip(gradient_right)
+                            .zip(&normal)
+                            .map(|((l, r), n)| (l - r) * n)
+                            .sum();
+                        jump * jump
+                    })
+                    .sum();
+                jump_sums[left] += area * jump_squared;
+                jump_sums[right] += area * jump_squared;
+            }
+            Ok(jump_sums
+                .iter()
+                .zip(&diameters)
+                .map(|(jump_sum, diameter)| (diameter / 24.0 * jump_sum).sqrt())
+                .collect())
+        })();
+        check(
+            DMRestoreLocalVector(dm.as_raw(), &mut solution_loc),
+            "DMRestoreLocalVector",
+        )?;
+        result
+    }
+}
+
+// Gradients of each component of the solution at the element centers, and
+//   the element diameters, from the element volumes
+unsafe fn cell_gradients(
+    dm: petsc::petsc_raw::DM,
+    solution_loc: petsc::petsc_raw::Vec,
+    dimension: usize,
+    is_simplex: bool,
+    (cell_start, cell_end): (petsc::Int, petsc::Int),
+) -> crate::Result<(Vec<Vec<f64>>, Vec<f64>)> {
+    use petsc::petsc_raw::*;
+    let check = crate::check_petsc_raw;
+    let mut fe = std::ptr::null_mut();
+    check(
+        DMGetField(dm, 0, std::ptr::null_mut(), &mut fe),
+        "DMGetField",
+    )?;
+
+    // One point rules, exact for linears, so the point is the element center
+    let mut quadrature = std::ptr::null_mut();
+    if is_simplex {
+        check(
+            PetscDTStroudConicalQuadrature(
+                dimension as petsc::Int,
+                1,
+                1,
+                -1.0,
+                1.0,
+                &mut quadrature,
+            ),
+            "PetscDTStroudConicalQuadrature",
+        )?;
+    } else {
+        check(
+            PetscDTGaussTensorQuadrature(dimension as petsc::Int, 1, 1, -1.0, 1.0, &mut quadrature),
+            "PetscDTGaussTensorQuadrature",
+        )?;
+    }
+    let mut tabulation = std::ptr::null_mut();
+    let result = (|| {
+        let mut points = std::ptr::null();
+        check(
+            PetscQuadratureGetData(
+                quadrature,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &mut points,
+                std::ptr::null_mut(),
+            ),
+            "PetscQuadratureGetData",
+        )?;
+        check(
+            PetscFECreateTabulation(fe as PetscFE, 1, 1, points, 1, &mut tabulation),
+            "PetscFECreateTabulation",
+        )?;
+        let (num_basis, num_components) = ((*tabulation).Nb as usize, (*tabulation).Nc as usize);
+        let basis_derivatives = std::slice::from_raw_parts(
+            *(*tabulation).T.add(1),
+            num_basis * num_components * dimension,
+        );
+
+        let num_cells = (cell_end - cell_start) as usize;
+        let (mut gradients, mut diameters) =
+            (Vec::with_capacity(num_cells), Vec::with_capacity(num_cells));
+        let mut v = vec![0.0; dimension];
+        let mut jacobian = vec![0.0; dimension * dimension];
+        let mut jacobian_inverse = vec![0.0; dimension * dimension];
+        for cell in cell_start..cell_end {
+            let (mut det, mut volume) = (0.0, 0.0);
+            check(
+                DMPlexComputeCellGeometryFEM(
+                    dm,
+                    cell,
+                    quadrature,
+                    v.as_mut_ptr(),
+                    jacobian.as_mut_ptr(),
+                    jacobian_inverse.as_mut_ptr(),
+                    &mut det,
+                ),
+                "DMPlexComputeCellGeometryFEM",
+            )?;
+            check(
+                DMPlexComputeCellGeometryFVM(
+                    dm,
+                    cell,
+                    &mut volume,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                ),
+                "DMPlexComputeCellGeometryFVM",
+            )?;
+            diameters.push(volume.abs().powf(1.0 / dimension as f64));
+
+            // grad u = sum_b u_b dphi_b/dxi dxi/dx
+            let mut closure_size = 0;
+            let mut values = std::ptr::null_mut();
+            check(
+                DMPlexVecGetClosure(
+                    dm,
+                    std::ptr::null_mut(),
+                    solution_loc,
+                    cell,
+                    &mut closure_size,
+                    &mut values,
+                ),
+                "DMPlexVecGetClosure",
+            )?;
+            let closure = std::slice::from_raw_parts(values, closure_size as usize);
+            let mut gradient = vec![0.0; num_components * dimension];
+            for (b, u) in closure.iter().enumerate().take(num_basis) {
+                for c in 0..num_components {
+                    for i in 0..dimension {
+                        let dphi = basis_derivatives[(b * num_components + c) * dimension + i];
+                        for j in 0..dimension {
+                            gradient[c * dimension + j] +=
+                                u * dphi * jacobian_inverse[i * dimension + j];
+                        }
+                    }
+                }
+            }
+            check(
+                DMPlexVecRestoreClosure(
+                    dm,
+                    std::ptr::null_mut(),
+                    solution_loc,
+                    cell,
+                    &mut closure_size,
+                    &mut values,
+                ),
+                "DMPlexVecRestoreClosure",
+            )?;
+            if closure_size as usize != num_basis {
+                return Err(crate::Error {
+                    message: format!(
+                        "element closure of {} values does not match {} basis functions",
+                        closure_size, num_basis
+                    ),
+                });
+            }
+            gradients.push(gradient);
+        }
+        Ok((gradients, diameters))
+    })();
+    if !tabulation.is_null() {
+        check(
+            PetscTabulationDestroy(&mut tabulation),
+            "PetscTabulationDestroy",
+        )?;
+    }
+    check(
+        PetscQuadratureDestroy(&mut quadrature),
+        "PetscQuadratureDestroy",
+    )?;
+    result
+}
+
+// -----------------------------------------------------------------------------
+// Mark elements
+//   Thresholds are global, so every rank marks consistently; the Dörfler
+//   threshold is found by bisection on the indicator value
+// -----------------------------------------------------------------------------
+fn global_sum(comm: &mpi::topology::UserCommunicator, value: f64) -> f64 {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    let mut sum = 0.0;
+    comm.all_reduce_into(&value, &mut sum, SystemOperation::sum());
+    sum
+}
+
+fn global_max(comm: &mpi::topology::UserCommunicator, value: f64) -> f64 {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    let mut max = 0.0;
+    comm.all_reduce_into(&value, &mut max, SystemOperation::max());
+    max
+}
+
+pub(crate) fn mark_elements(
+    comm: &mpi::topology::UserCommunicator,
+    indicators: &[f64],
+    marking: AmrMarking,
+) -> crate::Result<Vec<usize>> {
+    let max_indicator = global_max(comm, indicators.iter().cloned().fold(0.0, f64::max));
+    let threshold = match marking {
+        AmrMarking::Maximum(fraction) | AmrMarking::Dorfler(fraction)
+            if !(fraction > 0.0 && fraction <= 1.0) =>
+        {
+            return Err(crate::Error {
+                message: format!("AMR marking fraction must be in (0, 1], not {}", fraction),
+            });
+        }
+        AmrMarking::Maximum(fraction) => fraction * max_indicator,
+        AmrMarking::Dorfler(fraction) => {
+            let sum_above = |threshold: f64| {
+                let local: f64 = indicators
+                    .iter()
+                    .filter(|&&indicator| indicator >= threshold)
+                    .map(|indicator| indicator * indicator)
+                    .sum();
+                global_sum(comm, local)
+            };
+            let target = fraction * sum_above(0.0);
+            let (mut low, mut high) = (0.0, max_indicator);
+            for _ in 0..50 {
+                let middle = 0.5 * (low + high);
+                if sum_above(middle) >= target {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            low
+        }
+    };
+    Ok(indicators
+        .iter()
+        .enumerate()
+        .filter(|&(_, &indicator)| indicator >= threshold && indicator > 0.0)
+        .map(|(cell, _)| cell)
+        .collect())
+}
+
+// -----------------------------------------------------------------------------
+// Refine marked cells in place
+//   The DM refines itself in DMSetFromOptions with an options prefix, so only
+//   the refinement options are applied; the marked cells are the active label
+//   of the transform, and the label, prefix, and options are removed even if
+//   refinement fails
+// -----------------------------------------------------------------------------
+const AMR_LABEL: &str = "amr";
+const AMR_PREFIX: &str = "amr_";
+
+fn refine_marked(dm: &mut DM, marked: &[usize], transform: &str) -> crate::Result<()> {
+    let label_name = std::ffi::CString::new(AMR_LABEL).unwrap();
+    let prefix = std::ffi::CString::new(AMR_PREFIX).unwrap();
+    let (cell_start, _) = crate::dm::plex_cell_range(dm)?;
+    let result = (|| {
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(
+                DMCreateLabel(dm.as_raw(), label_name.as_ptr()),
+                "DMCreateLabel",
+            )?;
+            let mut label = std::ptr::null_mut();
+            check(
+                DMGetLabel(dm.as_raw(), label_name.as_ptr(), &mut label),
+                "DMGetLabel",
+            )?;
+            for &cell in marked {
+                check(
+                    DMLabelSetValue(label, cell_start + cell as petsc::Int, 1),
+                    "DMLabelSetValue",
+                )?;
+            }
+            check(
+                DMSetOptionsPrefix(dm.as_raw(), prefix.as_ptr()),
+                "DMSetOptionsPrefix",
+            )?;
+        }
+        let mut overrides = crate::config::OptionOverrides::new();
+        overrides.set("-amr_dm_refine", Some("1"))?;
+        overrides.set("-amr_dm_plex_transform_type", Some(transform))?;
+        overrides.set("-amr_dm_plex_transform_active", Some(AMR_LABEL))?;
+        dm.set_from_options()?;
+        Ok(())
+    })();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        check(
+            DMSetOptionsPrefix(dm.as_raw(), std::ptr::null()),
+            "DMSetOptionsPrefix",
+        )?;
+        check(
+            DMRemoveLabel(dm.as_raw(), label_name.as_ptr(), std::ptr::null_mut()),
+            "DMRemoveLabel",
+        )?;
+    }
+    result
+}
+
+// -----------------------------------------------------------------------------
+// Adapt the mesh of a problem
+//   Meshes from DMClone do not refine in DMSetFromOptions, so the adapted mesh
+//   is created from the options and every refinement, recorded as marked
+//   cells, is replayed; cell numberings are deterministic, so each replayed
+//   step marks the same cells
+// -----------------------------------------------------------------------------
+pub(crate) fn adapt<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+    solution: &petsc::vector::Vector<'a>,
+    marking: AmrMarking,
+) -> crate::Result<AmrStep<'a>> {
+    let options = &meles.bp_options;
+    if options.mesh_type != crate::dm::MeshType::Plex
+        || !crate::dm::plex_is_simplex(&meles.dm.borrow())?
+    {
+        return Err(crate::Error {
+            message: "adaptive refinement requires a plex mesh of simplices".to_string(),
+        });
+    }
+
+    // Estimate and mark
+    let indicators = error_indicators(meles, solution)?;
+    let estimate = global_sum(
+        meles.comm,
+        indicators
+            .iter()
+            .map(|indicator| indicator * indicator)
+            .sum(),
+    )
+    .sqrt();
+    let marked = mark_elements(meles.comm, &indicators, marking)?;
+    let marked_elements = global_sum(meles.comm, marked.len() as f64) as usize;
+    meles.amr_history.borrow_mut().push(marked);
+
+    // Replay every refinement on a new mesh
+    let mesh_setup_start = std::time::Instant::now();
+    let mut dm = {
+        let _options_lock = crate::config::options_lock();
+        let Opt { transform } = petsc.options()?;
+        let mut dm = crate::dm::create_plex(meles.comm, options)?;
+        crate::dm::transform_mesh(&mut dm, options)?;
+        for marked in meles.amr_history.borrow().iter() {
+            refine_marked(&mut dm, marked, &transform)?;
+        }
+        dm
+    };
+    crate::ceed_bps::setup_dm(&mut dm, options)?;
+    let local_elements = crate::dm::local_num_cells(&dm, options.mesh_type)?;
+    meles.progress.report(
+        crate::progress::SetupPhase::Mesh,
+        local_elements,
+        mesh_setup_start,
+    );
+    let global_elements = global_sum(meles.comm, local_elements.unwrap_or(0) as f64) as usize;
+
+    // Interpolate the solution onto the new mesh
+    let mut new_solution = dm.create_global_vector()?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut interpolation = std::ptr::null_mut();
+        check(
+            DMCreateInterpolation(
+                meles.dm.borrow().as_raw(),
+                dm.as_raw(),
+                &mut interpolation,
+                std::ptr::null_mut(),
+            ),
+            "DMCreateInterpolation",
+        )?;
+        let result = check(
+            MatInterpolate(interpolation, solution.as_raw(), new_solution.as_raw()),
+            "MatInterpolate",
+        );
+        check(MatDestroy(&mut interpolation), "MatDestroy")?;
+        result?;
+    }
+    *meles.dm.borrow_mut() = dm;
+    meles.operator_cache.borrow_mut().clear();
+
+    Ok(AmrStep {
+        solution: new_solution,
+        estimate,
+        marked_elements,
+        global_elements,
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,352 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Assembled preconditioners
+//   PETSc and external package preconditioners for assembled matrices, checked
+//   against the PETSc build before they are selected
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AssembledPC {
+    None,
+    Gamg,
+    Hypre,
+    Mumps,
+    SuperluDist,
+}
+
+impl std::str::FromStr for AssembledPC {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<AssembledPC> {
+        match s {
+            "none" => Ok(AssembledPC::None),
+            "gamg" => Ok(AssembledPC::Gamg),
+            "hypre" => Ok(AssembledPC::Hypre),
+            "mumps" => Ok(AssembledPC::Mumps),
+            "superlu_dist" => Ok(AssembledPC::SuperluDist),
+            _ => Err(crate::Error {
+                message: "failed to parse assembled preconditioner option".to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for AssembledPC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssembledPC::None => write!(f, "none"),
+            AssembledPC::Gamg => write!(f, "gamg"),
+            AssembledPC::Hypre => write!(f, "hypre"),
+            AssembledPC::Mumps => write!(f, "mumps"),
+            AssembledPC::SuperluDist => write!(f, "superlu_dist"),
+        }
+    }
+}
+
+impl AssembledPC {
+    // External package providing the preconditioner, if any
+    fn external_package(&self) -> Option<&'static str> {
+        match self {
+            AssembledPC::None | AssembledPC::Gamg => None,
+            AssembledPC::Hypre => Some("hypre"),
+            AssembledPC::Mumps => Some("mumps"),
+            AssembledPC::SuperluDist => Some("superlu_dist"),
+        }
+    }
+
+    // Error if PETSc was built without the external package
+    pub(crate) fn check_available(&self) -> crate::Result<()> {
+        if let Some(package) = self.external_package() {
+            if !crate::has_petsc_package(package)? {
+                return Err(crate::Error {
+                    message: format!(
+                        "assembled preconditioner {} requires PETSc configured with {}; use gamg instead",
+                        self, package
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Set the type of a PC for an assembled matrix
+    //   Hypre uses BoomerAMG, and MUMPS and SuperLU_dist are LU factorizations
+    pub(crate) fn set_up_pc(&self, pc: petsc::petsc_raw::PC) -> crate::Result<()> {
+        self.check_available()?;
+        let (pc_type, solver_type) = match self {
+            AssembledPC::None => return Ok(()),
+            AssembledPC::Gamg => ("gamg", None),
+            AssembledPC::Hypre => ("hypre", None),
+            AssembledPC::Mumps => ("lu", Some("mumps")),
+            AssembledPC::SuperluDist => ("lu", Some("superlu_dist")),
+        };
+        let pc_type = std::ffi::CString::new(pc_type).unwrap();
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(PCSetType(pc, pc_type.as_ptr()), "PCSetType")?;
+            if *self == AssembledPC::Hypre {
+                let boomeramg = std::ffi::CString::new("boomeramg").unwrap();
+                check(PCHYPRESetType(pc, boomeramg.as_ptr()), "PCHYPRESetType")?;
+            }
+            if let Some(solver_type) = solver_type {
+                let solver_type = std::ffi::CString::new(solver_type).unwrap();
+                check(
+                    PCFactorSetMatSolverType(pc, solver_type.as_ptr()),
+                    "PCFactorSetMatSolverType",
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Assembled preconditioner command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) assembled_pc: AssembledPC,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let assembled_pc = pob.options_from_string(
+            "-assembled_pc",
+            "Preconditioner for the assembled matrix, none, gamg, hypre, mumps, or superlu_dist",
+            "",
+            AssembledPC::None,
+        )?;
+        Ok(Opt { assembled_pc })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Assembled operator
+// -----------------------------------------------------------------------------
+/// AIJ assembly of the operator with cached symbolic data
+///
+/// The sparsity pattern, as COO indices, is computed and handed to PETSc once.
+/// Each numeric re-assembly only recomputes element matrices and inserts
+/// their values, so repeated assembly with changing coefficients, such as in
+/// Newton iterations or time stepping, skips the symbolic phase.
+///
+/// When assembled for a region, only cells with the given label value
+/// contribute, so the rest of the operator may stay matrix-free.
+///
+/// Note: Requires a plex mesh with a single cell type.
+pub struct AssembledOperator<'a> {
+    pub(crate) ceed: libceed::Ceed,
+    pub(crate) dm: DM<'a, 'a>,
+    pub(crate) op_elem: libceed::operator::Operator<'a>,
+    pub(crate) num_elements: usize,
+    pub(crate) elem_size: usize,
+    pub(crate) mat: petsc::mat::Mat<'a, 'a>,
+    pub(crate) num_numeric_assemblies: usize,
+}
+
+impl<'a> AssembledOperator<'a> {
+    /// Returns the assembled matrix
+    pub fn mat(&self) -> &petsc::mat::Mat<'a, 'a> {
+        &self.mat
+    }
+
+    /// Returns the DM the operator acts on
+    pub fn dm(&self) -> &DM<'a, 'a> {
+        &self.dm
+    }
+
+    /// Returns the number of numeric assemblies performed
+    pub fn num_numeric_assemblies(&self) -> usize {
+        self.num_numeric_assemblies
+    }
+
+    /// Recompute the numeric values of the matrix, reusing the cached
+    /// sparsity pattern
+    pub fn reassemble(&mut self) -> crate::Result<()> {
+        let values = crate::ceed_bps::probe_element_matrices(
+            &self.ceed,
+            &self.op_elem,
+            self.num_elements,
+            self.elem_size,
+        )?;
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::MatSetValuesCOO(
+                    self.mat.as_raw(),
+                    values.as_ptr(),
+                    InsertMode::INSERT_VALUES,
+                )
+            },
+            "MatSetValuesCOO",
+        )?;
+        self.num_numeric_assemblies += 1;
+        Ok(())
+    }
+
+    /// Replace the element operator, e.g. after updating coefficients, and
+    /// reassemble the numeric values
+    ///
+    /// The new operator must use the same element restriction layout as the
+    /// operator it replaces.
+    ///
+    /// # arguments
+    ///
+    /// * `op_elem` - libCEED operator acting on element vectors
+    pub fn set_element_operator(
+        &mut self,
+        op_elem: libceed::operator::Operator<'a>,
+    ) -> crate::Result<()> {
+        self.op_elem = op_elem;
+        self.reassemble()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Global DoFs of each element
+//   Ordered by element, then component, then node, as the element matrices,
+//   with negative indices for constrained DoFs
+// -----------------------------------------------------------------------------
+pub(crate) fn element_global_dofs(
+    dm: &DM,
+    num_components: usize,
+    region: Option<(&str, usize)>,
+) -> crate::Result<Vec<petsc::Int>> {
+    let DMPlexLocalOffsets {
+        num_cells,
+        cell_size,
+        offsets,
+        ..
+    } = match region {
+        Some((name, value)) => {
+            let label = dm.label(name)?.ok_or_else(|| crate::Error {
+                message: format!("missing label {} for region", name),
+            })?;
+            dm.plex_local_offsets(&label, value, 0, 0)?
+        }
+        None => dm.plex_local_offsets(None, 0, 0, 0)?,
+    };
+
+    // Local to global mapping
+    let local_to_global = crate::dm::local_to_global_indices(dm)?;
+
+    // Element DoFs are ordered by component, then node
+    let mut element_dofs = Vec::with_capacity(num_cells * cell_size * num_components);
+    for e in 0..num_cells {
+        for comp in 0..num_components {
+            for node in 0..cell_size {
+                let local = offsets[e * cell_size + node] as usize + comp;
+                element_dofs.push(local_to_global[local]);
+            }
+        }
+    }
+    Ok(element_dofs)
+}
+
+// -----------------------------------------------------------------------------
+// COO indices for element matrices
+//   Rows and columns follow the element matrix layout, row major by element,
+//   with negative indices for constrained DoFs, which PETSc ignores
+// -----------------------------------------------------------------------------
+pub(crate) fn element_coo_indices(
+    dm: &DM,
+    num_components: usize,
+    region: Option<(&str, usize)>,
+    elem_size: usize,
+) -> crate::Result<(Vec<petsc::Int>, Vec<petsc::Int>)> {
+    let element_dofs = element_global_dofs(dm, num_components, region)?;
+    let num_cells = element_dofs.len() / elem_size;
+    let mut rows = Vec::with_capacity(num_cells * elem_size * elem_size);
+    let mut cols = Vec::with_capacity(num_cells * elem_size * elem_size);
+    for dofs in element_dofs.chunks_exact(elem_size) {
+        for &row in dofs {
+            for &col in dofs {
+                rows.push(row);
+                cols.push(col);
+            }
+        }
+    }
+    Ok((rows, cols))
+}
+
+// -----------------------------------------------------------------------------
+// Setup assembled operator
+// -----------------------------------------------------------------------------
+pub(crate) fn assembled_operator<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    region: Option<(&str, usize)>,
+) -> crate::Result<AssembledOperator<'a>> {
+    let crate::ceed_bps::ElementOperator {
+        dm,
+        restr_u: _,
+        op_elem,
+        num_elements,
+        num_nodes_1d,
+        num_components,
+    } = crate::ceed_bps::element_operator(meles, options, region)?;
+    let dimension = dm.dimension()? as u32;
+    let elem_size = num_nodes_1d.pow(dimension) * num_components;
+
+    // Symbolic assembly, performed once
+    let (mut rows, mut cols) = element_coo_indices(&dm, num_components, region, elem_size)?;
+    let mat = dm.create_matrix()?;
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::MatSetPreallocationCOO(
+                mat.as_raw(),
+                rows.len() as petsc::petsc_raw::PetscCount,
+                rows.as_mut_ptr(),
+                cols.as_mut_ptr(),
+            )
+        },
+        "MatSetPreallocationCOO",
+    )?;
+
+    let mut assembled = AssembledOperator {
+        ceed: meles.ceed.clone(),
+        dm,
+        op_elem,
+        num_elements,
+        elem_size,
+        mat,
+        num_numeric_assemblies: 0,
+    };
+    assembled.reassemble()?;
+    Ok(assembled)
+}
+
+// -----------------------------------------------------------------------------
+// Setup an AIJ assembly of the operator, for use as a preconditioning matrix
+//   The operator is symmetric, so the flag is kept through re-assembly
+// -----------------------------------------------------------------------------
+pub(crate) fn assembled_pmat<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<AssembledOperator<'a>> {
+    let assembled = assembled_operator(meles, options, None)?;
+    for option in [
+        petsc::petsc_raw::MatOption::MAT_SYMMETRIC,
+        petsc::petsc_raw::MatOption::MAT_SYMMETRY_ETERNAL,
+    ] {
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::MatSetOption(
+                    assembled.mat.as_raw(),
+                    option,
+                    petsc::petsc_raw::PetscBool::PETSC_TRUE,
+                )
+            },
+            "MatSetOption",
+        )?;
+    }
+    Ok(assembled)
+}
+
+pub(crate) fn assembled_matrix<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<petsc::mat::Mat<'a, 'a>> {
+    let AssembledOperator { mat, .. } = assembled_pmat(meles, options)?;
+    Ok(mat)
+}
+
+// -----------------------------------------------------------------------------
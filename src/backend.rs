@@ -0,0 +1,456 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Backend capabilities
+//   Determined from the requested libCEED resource, so unsupported feature
+//   combinations are reported before any backend object is created
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Capabilities {
+    // Rust closure QFunctions run on the host, and device backends require
+    //   QFunction source for JiT compilation
+    pub(crate) closure_q_functions: bool,
+    // Collocated gradients for Gauss-Lobatto quadrature on the basis nodes
+    pub(crate) collocated_gradients: bool,
+}
+
+pub(crate) fn capabilities(resource: &str) -> Capabilities {
+    let device = resource.starts_with("/gpu");
+    let fused = device && resource.ends_with("/gen");
+    Capabilities {
+        closure_q_functions: !device,
+        collocated_gradients: !fused,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Feature checks
+// -----------------------------------------------------------------------------
+// Error if a feature built on Rust closure QFunctions is used on a device
+//   backend, instead of failing inside the backend at operator apply
+pub(crate) fn require_closure_q_functions(
+    meles: &crate::Meles,
+    feature: &str,
+) -> crate::Result<()> {
+    if capabilities(&meles.ceed_resource).closure_q_functions {
+        Ok(())
+    } else {
+        Err(crate::Error {
+            message: format!(
+                "{} use Rust closure QFunctions, which run on the host; the libCEED resource {} requires QFunction source for device compilation, use a /cpu resource instead",
+                feature, meles.ceed_resource
+            ),
+        })
+    }
+}
+
+// Ceed for Rust closure QFunctions, such as the forcing and error operators
+//   Device resources use a host Ceed, created on first use and shared by the
+//   operators of the context
+pub(crate) fn host_ceed(meles: &crate::Meles) -> libceed::Ceed {
+    if capabilities(&meles.ceed_resource).closure_q_functions {
+        return meles.ceed.clone();
+    }
+    meles
+        .host_ceed
+        .borrow_mut()
+        .get_or_insert_with(|| libceed::Ceed::init("/cpu/self"))
+        .clone()
+}
+
+// Quadrature mode supported by the backend
+//   Gauss-Lobatto quadrature collocated with the basis nodes falls back to
+//   Gauss quadrature, with a warning, on backends without collocated
+//   gradients
+pub(crate) fn quadrature_mode(
+    meles: &crate::Meles,
+    q_mode: libceed::QuadMode,
+) -> libceed::QuadMode {
+    use mpi::traits::Communicator;
+    match q_mode {
+        libceed::QuadMode::GaussLobatto
+            if !capabilities(&meles.ceed_resource).collocated_gradients =>
+        {
+            if meles.comm.rank() == 0 {
+                eprintln!(
+                    "Meles warning: libCEED resource {} does not support collocated gradients, using Gauss quadrature for {}",
+                    meles.ceed_resource, meles.bp_options.problem
+                );
+            }
+            libceed::QuadMode::Gauss
+        }
+        q_mode => q_mode,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// PETSc vector and matrix types
+//   DMs on device resources produce device vectors and assembled matrices, so
+//   the solve stays resident on the device; explicit -dm_vec_type and
+//   -dm_mat_type options take precedence
+// -----------------------------------------------------------------------------
+// PETSc package and vector and matrix types for a libCEED resource
+fn device_types(resource: &str) -> Option<[(&'static str, &'static str, &'static str); 2]> {
+    let kokkos = ("kokkos", "kokkos", "aijkokkos");
+    if resource.starts_with("/gpu/cuda") {
+        Some([("cuda", "cuda", "aijcusparse"), kokkos])
+    } else if resource.starts_with("/gpu/hip") {
+        Some([("hip", "hip", "aijhipsparse"), kokkos])
+    } else if resource.starts_with("/gpu") {
+        Some([kokkos, kokkos])
+    } else {
+        None
+    }
+}
+
+// Set the vector and matrix types of a DM for a libCEED resource
+pub(crate) fn set_dm_types(
+    comm: &mpi::topology::UserCommunicator,
+    dm: &mut DM,
+    resource: &str,
+) -> crate::Result<()> {
+    use mpi::traits::Communicator;
+    let option_set = |name: &str| -> crate::Result<bool> {
+        let name = std::ffi::CString::new(name).unwrap();
+        let mut set = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::PetscOptionsHasName(
+                    std::ptr::null_mut(),
+                    std::ptr::null(),
+                    name.as_ptr(),
+                    &mut set,
+                )
+            },
+            "PetscOptionsHasName",
+        )?;
+        Ok(set == petsc::petsc_raw::PetscBool::PETSC_TRUE)
+    };
+    let (vec_type, mat_type) = match device_types(resource) {
+        Some(candidates) => {
+            let mut types = None;
+            for (package, vec_type, mat_type) in candidates.iter() {
+                if crate::has_petsc_package(package)? {
+                    types = Some((*vec_type, *mat_type));
+                    break;
+                }
+            }
+            types.unwrap_or_else(|| {
+                if comm.rank() == 0 {
+                    eprintln!(
+                        "Meles warning: PETSc was not configured with device support for libCEED resource {}, using host vectors",
+                        resource
+                    );
+                }
+                ("standard", "aij")
+            })
+        }
+        None => ("standard", "aij"),
+    };
+    let vec_type = std::ffi::CString::new(vec_type).unwrap();
+    let mat_type = std::ffi::CString::new(mat_type).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        if !option_set("-dm_vec_type")? {
+            // Cached work vectors have the previous type
+            check(DMClearLocalVectors(dm.as_raw()), "DMClearLocalVectors")?;
+            check(DMClearGlobalVectors(dm.as_raw()), "DMClearGlobalVectors")?;
+            check(DMSetVecType(dm.as_raw(), vec_type.as_ptr()), "DMSetVecType")?;
+        }
+        if !option_set("-dm_mat_type")? {
+            check(DMSetMatType(dm.as_raw(), mat_type.as_ptr()), "DMSetMatType")?;
+        }
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Backend information
+//   Queried from the libCEED context of the problem once and cached until the
+//   Ceed is replaced, since the resolved resource may differ from the
+//   requested one when libCEED delegates
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Capabilities of the libCEED backend of a Meles context
+pub struct BackendInfo {
+    /// libCEED resource requested in the configuration
+    pub requested_resource: String,
+    /// libCEED resource resolved by libCEED
+    pub resource: String,
+    /// Preferred memory type of the backend
+    pub memory_type: libceed::MemType,
+    /// Backend results are bitwise reproducible run to run
+    pub deterministic: bool,
+    /// Operators from Rust closure QFunctions are supported
+    pub closure_q_functions: bool,
+    /// Operators from user C QFunction source are supported
+    pub source_q_functions: bool,
+    /// Gauss-Lobatto quadrature with collocated gradients is supported
+    pub collocated_gradients: bool,
+    /// Device bound to this rank, for device resources
+    pub device: Option<crate::DeviceBinding>,
+}
+
+impl fmt::Display for BackendInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let memory_type = match self.memory_type {
+            libceed::MemType::Host => "host",
+            libceed::MemType::Device => "device",
+        };
+        writeln!(f, "libCEED Backend:")?;
+        writeln!(f, "  Requested Resource: {}", self.requested_resource)?;
+        writeln!(f, "  Resolved Resource: {}", self.resource)?;
+        writeln!(f, "  Memory Type: {}", memory_type)?;
+        writeln!(f, "  Deterministic: {}", self.deterministic)?;
+        writeln!(f, "  Closure QFunctions: {}", self.closure_q_functions)?;
+        writeln!(f, "  Source QFunctions: {}", self.source_q_functions)?;
+        writeln!(f, "  Collocated Gradients: {}", self.collocated_gradients)?;
+        match &self.device {
+            Some(device) => writeln!(f, "  Device: {}", device),
+            None => writeln!(f, "  Device: none"),
+        }
+    }
+}
+
+pub(crate) fn backend_info(meles: &crate::Meles) -> crate::Result<BackendInfo> {
+    if let Some(info) = meles.backend_info.borrow().as_ref() {
+        return Ok(info.clone());
+    }
+    use libceed_sys::bind_ceed;
+
+    // Query the Ceed of the context
+    let ceed = meles.ceed.as_raw();
+    let mut resource = std::ptr::null();
+    let mut memory_type = bind_ceed::CeedMemType_CEED_MEM_HOST;
+    let mut deterministic = false;
+    let resource = unsafe {
+        crate::check_ceed_raw(
+            bind_ceed::CeedGetResource(ceed, &mut resource),
+            "CeedGetResource",
+        )?;
+        crate::check_ceed_raw(
+            bind_ceed::CeedGetPreferredMemType(ceed, &mut memory_type),
+            "CeedGetPreferredMemType",
+        )?;
+        crate::check_ceed_raw(
+            bind_ceed::CeedIsDeterministic(ceed, &mut deterministic),
+            "CeedIsDeterministic",
+        )?;
+        std::ffi::CStr::from_ptr(resource)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    // Features follow the preferred memory type and the resolved resource,
+    //   since libCEED may delegate to another backend than the one requested
+    let memory_type = if memory_type == bind_ceed::CeedMemType_CEED_MEM_DEVICE {
+        libceed::MemType::Device
+    } else {
+        libceed::MemType::Host
+    };
+    let closure_q_functions = memory_type == libceed::MemType::Host;
+    let collocated_gradients = capabilities(&resource).collocated_gradients;
+    let info = BackendInfo {
+        requested_resource: meles.ceed_resource.clone(),
+        resource,
+        memory_type,
+        deterministic,
+        closure_q_functions,
+        source_q_functions: !closure_q_functions,
+        collocated_gradients,
+        device: meles.device.clone(),
+    };
+    *meles.backend_info.borrow_mut() = Some(info.clone());
+    Ok(info)
+}
+
+// -----------------------------------------------------------------------------
+// Backend audition
+//   Each candidate resource gets its own Ceed, mesh, and operator, built
+//   from the options of the problem, and backend specific setup such as JiT
+//   compilation happens in an untimed warm up apply
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Operator apply timing of one libCEED resource
+pub struct BackendTiming {
+    /// libCEED resource
+    pub resource: String,
+    /// Wall-clock seconds per operator apply, the maximum over ranks, or
+    ///   None if the resource failed
+    pub seconds_per_apply: Option<f64>,
+    /// Error of a failed resource
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Operator apply timings of candidate libCEED resources on a problem
+pub struct BackendAudition {
+    /// Number of global DoFs of the problem
+    pub global_dofs: usize,
+    /// Timing of each candidate, in the order given
+    pub timings: Vec<BackendTiming>,
+}
+
+impl BackendAudition {
+    /// Returns the timing of the fastest resource, if any succeeded
+    pub fn fastest(&self) -> Option<&BackendTiming> {
+        self.timings
+            .iter()
+            .filter(|timing| timing.seconds_per_apply.is_some())
+            .min_by(|a, b| {
+                a.seconds_per_apply
+                    .partial_cmp(&b.seconds_per_apply)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+}
+
+impl fmt::Display for BackendAudition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Backend Audition:")?;
+        writeln!(f, "  Global DoFs: {}", self.global_dofs)?;
+        for timing in &self.timings {
+            match (timing.seconds_per_apply, &timing.error) {
+                (Some(seconds), _) => writeln!(
+                    f,
+                    "  {:<24} {:e} s/apply, {:e} DoF/s",
+                    timing.resource,
+                    seconds,
+                    self.global_dofs as f64 / seconds
+                )?,
+                (None, error) => writeln!(
+                    f,
+                    "  {:<24} failed: {}",
+                    timing.resource,
+                    error.as_deref().unwrap_or("unknown error")
+                )?,
+            }
+        }
+        match self.fastest() {
+            Some(fastest) => write!(f, "  Fastest: {}", fastest.resource),
+            None => write!(f, "  Fastest: none"),
+        }
+    }
+}
+
+// Check that a resource matches a registered libCEED backend
+//   CeedInit aborts on resources without a backend, so the resource is
+//   matched against the registry as CeedInit does, with the part before any
+//   ':' a prefix of a backend prefix
+extern "C" {
+    fn free(pointer: *mut std::os::raw::c_void);
+}
+
+fn resource_available(resource: &str) -> crate::Result<()> {
+    use libceed_sys::bind_ceed;
+    let mut num_backends = 0;
+    let mut prefixes = std::ptr::null_mut();
+    crate::check_ceed_raw(
+        unsafe {
+            bind_ceed::CeedRegistryGetList(&mut num_backends, &mut prefixes, std::ptr::null_mut())
+        },
+        "CeedRegistryGetList",
+    )?;
+    let prefixes = unsafe {
+        let list = std::slice::from_raw_parts(prefixes, num_backends)
+            .iter()
+            .map(|&prefix| {
+                std::ffi::CStr::from_ptr(prefix)
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+        free(prefixes as *mut std::os::raw::c_void);
+        list
+    };
+    let stem = resource.split(':').next().unwrap_or_default();
+    if stem.len() > 1 && prefixes.iter().any(|prefix| prefix.starts_with(stem)) {
+        Ok(())
+    } else {
+        Err(crate::Error {
+            message: format!(
+                "no libCEED backend for resource {}; available backends are {}",
+                resource,
+                prefixes.join(", ")
+            ),
+        })
+    }
+}
+
+// Seconds per operator apply on one resource, the maximum over ranks
+fn time_resource(
+    meles: &crate::Meles,
+    petsc: &Petsc,
+    resource: &str,
+    num_applies: usize,
+) -> crate::Result<f64> {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    resource_available(resource)?;
+
+    // Problem on the candidate resource
+    let (dm, progress) = {
+        let _options_lock = crate::config::options_lock();
+        let progress = crate::progress::ProgressReporter::new(meles.comm, petsc.options()?);
+        let mut dm = crate::ceed_bps::create_dm(meles.comm, &meles.bp_options)?;
+        set_dm_types(meles.comm, &mut dm, resource)?;
+        (dm, progress)
+    };
+    let (ceed, device) = crate::device::init_ceed(meles.comm, resource)?;
+    let candidate = meles.derived(
+        petsc,
+        (ceed, resource.to_string(), device),
+        meles.bp_options.clone(),
+        dm,
+        progress,
+        meles.mesh_setup_time,
+    );
+    let mat = candidate.mat_shell(petsc)?;
+    let mut x = mat.mat_data().unwrap().rhs().duplicate()?;
+    x.set_all(1.0)?;
+    let mut y = x.duplicate()?;
+
+    // Untimed warm up, then timed applies
+    mat.mult(&x, &mut y)?;
+    meles.comm.barrier();
+    let start = std::time::Instant::now();
+    for _ in 0..num_applies {
+        mat.mult(&x, &mut y)?;
+    }
+    let local_seconds = start.elapsed().as_secs_f64() / num_applies as f64;
+    let mut seconds = 0.0;
+    meles
+        .comm
+        .all_reduce_into(&local_seconds, &mut seconds, SystemOperation::max());
+    Ok(seconds)
+}
+
+pub(crate) fn audition(
+    meles: &crate::Meles,
+    petsc: &Petsc,
+    resources: &[&str],
+    num_applies: usize,
+) -> crate::Result<BackendAudition> {
+    let global_dofs = meles.dm.borrow().create_global_vector()?.global_size()? as usize;
+    let timings = resources
+        .iter()
+        .map(|&resource| {
+            let (seconds_per_apply, error) =
+                match time_resource(meles, petsc, resource, num_applies.max(1)) {
+                    Ok(seconds) => (Some(seconds), None),
+                    Err(error) => (None, Some(error.to_string())),
+                };
+            BackendTiming {
+                resource: resource.to_string(),
+                seconds_per_apply,
+                error,
+            }
+        })
+        .collect();
+    Ok(BackendAudition {
+        global_dofs,
+        timings,
+    })
+}
+
+// -----------------------------------------------------------------------------
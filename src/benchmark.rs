@@ -0,0 +1,284 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Benchmark report
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Throughput of operator applies and full solves of the benchmark problem
+pub struct BenchmarkReport {
+    /// libCEED resource
+    pub ceed_resource: String,
+    /// Number of MPI ranks
+    pub num_ranks: usize,
+    /// Number of global DoFs of the problem
+    pub global_dofs: usize,
+    /// Number of timed operator applies
+    pub num_applies: usize,
+    /// Wall-clock seconds per operator apply, the maximum over ranks
+    pub seconds_per_apply: f64,
+    /// Number of timed solves
+    pub num_solves: usize,
+    /// Wall-clock seconds per KSPSolve, after operator and PC setup, the
+    ///   maximum over ranks
+    pub seconds_per_solve: f64,
+    /// Krylov iterations per solve
+    pub iterations: usize,
+}
+
+impl BenchmarkReport {
+    /// Operator apply throughput, in millions of DoFs per second
+    pub fn apply_mdofs_per_second(&self) -> f64 {
+        1e-6 * self.global_dofs as f64 / self.seconds_per_apply
+    }
+
+    /// Solve throughput, in millions of DoFs times iterations per second, as
+    ///   reported for the CEED benchmark problems
+    pub fn solve_mdofs_per_second(&self) -> f64 {
+        1e-6 * (self.global_dofs * self.iterations.max(1)) as f64 / self.seconds_per_solve
+    }
+
+    fn fields(&self) -> [(&'static str, String); 10] {
+        [
+            ("ceed_resource", self.ceed_resource.clone()),
+            ("num_ranks", self.num_ranks.to_string()),
+            ("global_dofs", self.global_dofs.to_string()),
+            ("num_applies", self.num_applies.to_string()),
+            ("seconds_per_apply", format!("{:e}", self.seconds_per_apply)),
+            (
+                "apply_mdofs_per_second",
+                format!("{:e}", self.apply_mdofs_per_second()),
+            ),
+            ("num_solves", self.num_solves.to_string()),
+            ("seconds_per_solve", format!("{:e}", self.seconds_per_solve)),
+            ("iterations", self.iterations.to_string()),
+            (
+                "solve_mdofs_per_second",
+                format!("{:e}", self.solve_mdofs_per_second()),
+            ),
+        ]
+    }
+
+    /// Returns the report as a JSON object on one line
+    pub fn to_json(&self) -> String {
+        let fields = self
+            .fields()
+            .iter()
+            .map(|(key, value)| match key {
+                &"ceed_resource" => format!("\"{}\": \"{}\"", key, value),
+                _ => format!("\"{}\": {}", key, value),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{{{}}}", fields)
+    }
+
+    /// Returns the report as a CSV header line and row
+    pub fn to_csv(&self) -> String {
+        let fields = self.fields();
+        let header = fields
+            .iter()
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>()
+            .join(",");
+        let row = fields
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{}\n{}\n", header, row)
+    }
+
+    /// Write the report, as JSON for a .json extension and CSV otherwise
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the report to write
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        let path = path.as_ref();
+        let contents = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            self.to_json() + "\n"
+        } else {
+            self.to_csv()
+        };
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Benchmark:")?;
+        writeln!(f, "  libCEED resource : {}", self.ceed_resource)?;
+        writeln!(f, "  MPI ranks        : {}", self.num_ranks)?;
+        writeln!(f, "  Global DoFs      : {}", self.global_dofs)?;
+        writeln!(
+            f,
+            "  Operator apply   : {:e} s, {:.3} MDoF/s over {} applies",
+            self.seconds_per_apply,
+            self.apply_mdofs_per_second(),
+            self.num_applies
+        )?;
+        write!(
+            f,
+            "  Solve            : {:e} s, {} iterations, {:.3} MDoF/s over {} solves",
+            self.seconds_per_solve,
+            self.iterations,
+            self.solve_mdofs_per_second(),
+            self.num_solves
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Benchmark command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) benchmark: bool,
+    pub(crate) benchmark_applies: usize,
+    pub(crate) benchmark_solves: usize,
+    pub(crate) benchmark_report: String,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let benchmark = pob.options_bool(
+            "-benchmark",
+            "Benchmark operator applies and solves and report them instead of solving",
+            "",
+            false,
+        )?;
+        let benchmark_applies = pob.options_usize(
+            "-benchmark_applies",
+            "Number of timed operator applies of a benchmark",
+            "",
+            10,
+        )?;
+        let benchmark_solves = pob.options_usize(
+            "-benchmark_solves",
+            "Number of timed solves of a benchmark",
+            "",
+            3,
+        )?;
+        let benchmark_report = pob.options_string(
+            "-benchmark_report",
+            "File the benchmark report is written to, JSON for a .json extension and CSV otherwise",
+            "",
+            "",
+        )?;
+        Ok(Opt {
+            benchmark,
+            benchmark_applies,
+            benchmark_solves,
+            benchmark_report,
+        })
+    }
+}
+
+fn options(petsc: &Petsc) -> crate::Result<Opt> {
+    let _options_lock = crate::config::options_lock();
+    Ok(petsc.options()?)
+}
+
+pub(crate) fn is_benchmark(petsc: &Petsc) -> crate::Result<bool> {
+    Ok(options(petsc)?.benchmark)
+}
+
+// -----------------------------------------------------------------------------
+// Benchmark
+//   The operator, KSP, and PC are set up once, in a solve session, and only
+//   the operator applies and KSPSolve calls are timed; device work is
+//   asynchronous, so each timed region ends with a norm of its output, which
+//   waits on pending device work, before the clock is read, and backend
+//   specific setup such as JiT compilation happens in an untimed warm up
+// -----------------------------------------------------------------------------
+// Maximum over ranks of the local seconds per repetition
+fn max_seconds(meles: &crate::Meles, local_seconds: f64, repetitions: usize) -> f64 {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    let local_seconds = local_seconds / repetitions as f64;
+    let mut seconds = 0.0;
+    meles
+        .comm
+        .all_reduce_into(&local_seconds, &mut seconds, SystemOperation::max());
+    seconds
+}
+
+pub(crate) fn benchmark<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+    num_applies: usize,
+    num_solves: usize,
+) -> crate::Result<BenchmarkReport> {
+    use mpi::collective::CommunicatorCollectives;
+    use mpi::traits::Communicator;
+    let (num_applies, num_solves) = (num_applies.max(1), num_solves.max(1));
+    let session = crate::solve::solve_session(meles, petsc)?;
+
+    // Operator applies
+    let mat = session.mat_shell();
+    let mut x = session.rhs().duplicate()?;
+    x.set_all(1.0)?;
+    let mut y = x.duplicate()?;
+    let global_dofs = x.global_size()? as usize;
+    mat.mult(&x, &mut y)?;
+    y.norm(NormType::NORM_2)?;
+    meles.comm.barrier();
+    let start = std::time::Instant::now();
+    for _ in 0..num_applies {
+        mat.mult(&x, &mut y)?;
+    }
+    y.norm(NormType::NORM_2)?;
+    let seconds_per_apply = max_seconds(meles, start.elapsed().as_secs_f64(), num_applies);
+
+    // Solves from a zero initial guess, with the PC set up by the warm up
+    let rhs = session.rhs();
+    let mut solution = rhs.duplicate()?;
+    solution.zero_entries()?;
+    session.ksp.solve(rhs, &mut solution)?;
+    let iterations = session.ksp.get_iteration_number()? as usize;
+    let mut local_seconds = 0.0;
+    for _ in 0..num_solves {
+        solution.zero_entries()?;
+        meles.comm.barrier();
+        let start = std::time::Instant::now();
+        session.ksp.solve(rhs, &mut solution)?;
+        solution.norm(NormType::NORM_2)?;
+        local_seconds += start.elapsed().as_secs_f64();
+    }
+    let seconds_per_solve = max_seconds(meles, local_seconds, num_solves);
+
+    Ok(BenchmarkReport {
+        ceed_resource: meles.ceed_resource.clone(),
+        num_ranks: meles.comm.size() as usize,
+        global_dofs,
+        num_applies,
+        seconds_per_apply,
+        num_solves,
+        seconds_per_solve,
+        iterations,
+    })
+}
+
+// Benchmark with the counts from the options database, writing the report on
+//   rank 0 if a report file is set
+pub(crate) fn benchmark_from_options<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<BenchmarkReport> {
+    use mpi::traits::Communicator;
+    let Opt {
+        benchmark_applies,
+        benchmark_solves,
+        benchmark_report,
+        ..
+    } = options(petsc)?;
+    let report = benchmark(meles, petsc, benchmark_applies, benchmark_solves)?;
+    if !benchmark_report.is_empty() && meles.comm.rank() == 0 {
+        report.write(benchmark_report)?;
+    }
+    Ok(report)
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,219 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Boundary condition types
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BoundaryConditionType {
+    Dirichlet,
+    Neumann,
+    None,
+}
+
+impl std::str::FromStr for BoundaryConditionType {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "dirichlet" => Ok(BoundaryConditionType::Dirichlet),
+            "neumann" => Ok(BoundaryConditionType::Neumann),
+            "none" => Ok(BoundaryConditionType::None),
+            _ => Err(crate::Error {
+                message: format!(
+                    "failed to parse boundary condition type {}, expected dirichlet, neumann, or none",
+                    s
+                ),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for BoundaryConditionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoundaryConditionType::Dirichlet => write!(f, "dirichlet"),
+            BoundaryConditionType::Neumann => write!(f, "neumann"),
+            BoundaryConditionType::None => write!(f, "none"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Boundary conditions by label
+//   The value is an expression in x, y, z, and t, the essential value of
+//   Dirichlet boundaries or the flux of Neumann boundaries
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BoundaryCondition {
+    pub(crate) boundary: String,
+    pub(crate) kind: BoundaryConditionType,
+    pub(crate) value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct BoundaryConditions(pub(crate) Vec<BoundaryCondition>);
+
+impl BoundaryConditions {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Boundaries of one type
+    pub(crate) fn of_type(
+        &self,
+        kind: BoundaryConditionType,
+    ) -> impl Iterator<Item = &BoundaryCondition> {
+        self.0
+            .iter()
+            .filter(move |condition| condition.kind == kind)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Boundary conditions section of the yml configuration
+//   boundary_conditions:
+//     Face Sets:1:
+//       type: dirichlet
+//       value: 1 + x*y
+//     Face Sets:2:
+//       type: neumann
+//       value: -x*(1-x)
+//   Boundaries are keys ending in a colon, so `label:value` boundaries need no
+//   quoting, and the section is removed before the remaining flat options are
+//   inserted into the options database
+// -----------------------------------------------------------------------------
+const SECTION: &str = "boundary_conditions";
+
+// Indentation of a line, in characters
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+// Split yml into the lines of the section, excluding its header, and the
+//   remaining lines
+fn split_section(yml: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut section = vec![];
+    let mut rest = vec![];
+    let mut in_section = false;
+    for line in yml.lines() {
+        let trimmed = line.trim();
+        if indentation(line) == 0 && !trimmed.is_empty() && !trimmed.starts_with('#') {
+            in_section = trimmed
+                .strip_suffix(':')
+                .is_some_and(|key| key.trim() == SECTION);
+            if in_section {
+                continue;
+            }
+        }
+        if in_section {
+            section.push(line);
+        } else {
+            rest.push(line);
+        }
+    }
+    (section, rest)
+}
+
+// Configuration without the boundary conditions section
+pub(crate) fn strip_section(yml: &str) -> String {
+    split_section(yml).1.join("\n")
+}
+
+impl BoundaryConditions {
+    pub(crate) fn from_yml(yml: &str) -> crate::Result<Self> {
+        let (section, _) = split_section(yml);
+        let lines = section
+            .into_iter()
+            .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
+            .collect::<Vec<_>>();
+        let boundary_indentation = lines.first().map_or(0, |line| indentation(line));
+
+        // Group the entries of each boundary
+        let mut entries: Vec<(String, Vec<(&str, &str)>)> = vec![];
+        for line in lines {
+            let trimmed = line.trim();
+            if indentation(line) == boundary_indentation {
+                let boundary = trimmed.strip_suffix(':').ok_or_else(|| crate::Error {
+                    message: format!(
+                        "invalid boundary {} in {}, expected a label followed by a colon",
+                        trimmed, SECTION
+                    ),
+                })?;
+                entries.push((boundary.trim().to_string(), vec![]));
+            } else {
+                let entry = trimmed
+                    .split_once(':')
+                    .map(|(key, value)| (key.trim(), unquote(value.trim())))
+                    .ok_or_else(|| crate::Error {
+                        message: format!("invalid entry {} in {}", trimmed, SECTION),
+                    })?;
+                match entries.last_mut() {
+                    Some((_, boundary_entries)) if indentation(line) > boundary_indentation => {
+                        boundary_entries.push(entry)
+                    }
+                    _ => {
+                        return Err(crate::Error {
+                            message: format!("entry {} in {} outside a boundary", trimmed, SECTION),
+                        })
+                    }
+                }
+            }
+        }
+
+        // Boundary conditions
+        let mut conditions = vec![];
+        for (boundary, boundary_entries) in entries {
+            let mut kind = None;
+            let mut value = None;
+            for (key, entry) in boundary_entries {
+                match key {
+                    "type" => kind = Some(entry.parse::<BoundaryConditionType>()?),
+                    "value" if !entry.is_empty() => {
+                        crate::Expression::parse(entry)?;
+                        value = Some(entry.to_string());
+                    }
+                    "value" => (),
+                    _ => {
+                        return Err(crate::Error {
+                            message: format!(
+                                "unknown key {} for boundary {}, expected type or value",
+                                key, boundary
+                            ),
+                        })
+                    }
+                }
+            }
+            let kind = kind.ok_or_else(|| crate::Error {
+                message: format!("missing type for boundary {}", boundary),
+            })?;
+            if kind == BoundaryConditionType::None && value.is_some() {
+                return Err(crate::Error {
+                    message: format!("boundary {} of type none has a value", boundary),
+                });
+            }
+            if kind == BoundaryConditionType::Neumann && !boundary.contains(':') {
+                return Err(crate::Error {
+                    message: format!(
+                        "invalid Neumann boundary {}, expected label:value such as Face Sets:1",
+                        boundary
+                    ),
+                });
+            }
+            conditions.push(BoundaryCondition {
+                boundary,
+                kind,
+                value,
+            });
+        }
+        Ok(BoundaryConditions(conditions))
+    }
+}
+
+// Remove matching quotes around a yml scalar
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+// -----------------------------------------------------------------------------
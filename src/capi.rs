@@ -0,0 +1,260 @@
+use crate::prelude::*;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+// -----------------------------------------------------------------------------
+// C API
+//
+// Opaque handles wrapping a Meles context for use from existing C PETSc
+// applications. The host application owns PETSc initialization and
+// finalization; destroying a handle never finalizes PETSc.
+//
+// All functions return 0 on success and nonzero on failure, with the error
+// message available from `meles_last_error`. Panics are caught at the API
+// boundary and reported as errors, as unwinding into C is undefined behavior.
+// See `include/meles.h`.
+// -----------------------------------------------------------------------------
+pub struct MelesHandle {
+    petsc: *mut Petsc,
+    meles: *mut crate::Meles<'static>,
+    mat: Option<petsc::mat::MatShell<'static, 'static, crate::MelesMatShellContext<'static>>>,
+}
+
+thread_local! {
+    static LAST_ERROR: std::cell::RefCell<CString> = std::cell::RefCell::new(CString::default());
+}
+
+// Record an error message for `meles_last_error`
+fn set_last_error(message: impl Into<String>) -> c_int {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+    1
+}
+
+// Convert a result into a C error code
+fn error_code(result: crate::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(error) => set_last_error(error.message),
+    }
+}
+
+// Run the body of an entry point, reporting a panic as an error
+fn catch_panic(body: impl FnOnce() -> c_int) -> c_int {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)) {
+        Ok(ierr) => ierr,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic in meles: {}", message))
+        }
+    }
+}
+
+/// Returns the message for the last error on this thread
+#[no_mangle]
+pub extern "C" fn meles_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
+
+/// Create a Meles context for a benchmark problem from a yml filepath
+///
+/// # Safety
+///
+/// `yml` must be a valid nul terminated string, or null to use the embedded
+/// default configuration, and `handle` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn meles_create(yml: *const c_char, handle: *mut *mut MelesHandle) -> c_int {
+    catch_panic(|| create(yml, handle))
+}
+
+unsafe fn create(yml: *const c_char, handle: *mut *mut MelesHandle) -> c_int {
+    if handle.is_null() {
+        return set_last_error("null handle pointer");
+    }
+    let config = if yml.is_null() {
+        crate::Config::new()
+    } else {
+        match CStr::from_ptr(yml).to_str() {
+            Ok(yml) => crate::Config::from(yml),
+            Err(_) => return set_last_error("yml filepath is not valid UTF-8"),
+        }
+    };
+    let result = (|| -> crate::Result<*mut MelesHandle> {
+        // PETSc belongs to the host application, so initialization only wraps
+        //   the existing state; PetscInitialize is a no-op once PETSc is
+        //   initialized, and the wrapper is forgotten rather than finalized
+        let mut initialized = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscInitialized(&mut initialized),
+            "PetscInitialized",
+        )?;
+        if initialized != petsc::petsc_raw::PetscBool::PETSC_TRUE {
+            return Err(crate::Error {
+                message: "PETSc must be initialized by the host application".to_string(),
+            });
+        }
+        let petsc_ptr = Box::into_raw(Box::new(Petsc::init_no_args()?));
+        let petsc: &'static Petsc = &*petsc_ptr;
+        let meles = match crate::Meles::new(petsc, config, crate::MethodType::BenchmarkProblem) {
+            Ok(meles) => meles,
+            Err(error) => {
+                // PETSc belongs to the host application
+                std::mem::forget(*Box::from_raw(petsc_ptr));
+                return Err(error);
+            }
+        };
+        Ok(Box::into_raw(Box::new(MelesHandle {
+            petsc: petsc_ptr,
+            meles: Box::into_raw(Box::new(meles)),
+            mat: None,
+        })))
+    })();
+    match result {
+        Ok(new_handle) => {
+            *handle = new_handle;
+            0
+        }
+        Err(error) => set_last_error(error.message),
+    }
+}
+
+/// Borrow the mesh DM of a Meles context
+///
+/// The DM remains owned by the context and must not be destroyed.
+///
+/// # Safety
+///
+/// `handle` must come from `meles_create` and `dm` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn meles_dm(
+    handle: *mut MelesHandle,
+    dm: *mut petsc::petsc_raw::DM,
+) -> c_int {
+    catch_panic(|| {
+        if handle.is_null() || dm.is_null() {
+            return set_last_error("null pointer argument");
+        }
+        *dm = (*(*handle).meles).dm.borrow().as_raw();
+        0
+    })
+}
+
+/// Borrow the MatShell of a Meles context, creating it on first use
+///
+/// The Mat remains owned by the context and must not be destroyed.
+///
+/// # Safety
+///
+/// `handle` must come from `meles_create` and `mat` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn meles_mat_shell(
+    handle: *mut MelesHandle,
+    mat: *mut petsc::petsc_raw::Mat,
+) -> c_int {
+    catch_panic(|| {
+        if handle.is_null() || mat.is_null() {
+            return set_last_error("null pointer argument");
+        }
+        let handle = &mut *handle;
+        if handle.mat.is_none() {
+            let meles: &'static crate::Meles<'static> = &*handle.meles;
+            let petsc: &'static Petsc = &*handle.petsc;
+            match meles.mat_shell(petsc) {
+                Ok(mat_shell) => handle.mat = Some(mat_shell),
+                Err(error) => return set_last_error(error.message),
+            }
+        }
+        match &handle.mat {
+            Some(mat_shell) => {
+                *mat = mat_shell.as_raw();
+                0
+            }
+            None => set_last_error("MatShell was not created"),
+        }
+    })
+}
+
+/// Solve with the MatShell using a KSP configured from the options database
+///
+/// # Safety
+///
+/// `handle` must come from `meles_create`, `b` and `x` must be valid PETSc
+/// Vecs compatible with the mesh DM, and `iterations` must be a valid pointer
+/// or null
+#[no_mangle]
+pub unsafe extern "C" fn meles_solve(
+    handle: *mut MelesHandle,
+    b: petsc::petsc_raw::Vec,
+    x: petsc::petsc_raw::Vec,
+    iterations: *mut petsc::Int,
+) -> c_int {
+    let mut mat = std::ptr::null_mut();
+    let ierr = meles_mat_shell(handle, &mut mat);
+    if ierr != 0 {
+        return ierr;
+    }
+    catch_panic(|| solve(mat, b, x, iterations))
+}
+
+unsafe fn solve(
+    mat: petsc::petsc_raw::Mat,
+    b: petsc::petsc_raw::Vec,
+    x: petsc::petsc_raw::Vec,
+    iterations: *mut petsc::Int,
+) -> c_int {
+    error_code((|| -> crate::Result<()> {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(mat as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut ksp = std::ptr::null_mut();
+        check(KSPCreate(comm, &mut ksp), "KSPCreate")?;
+        let result = (|| {
+            check(KSPSetOperators(ksp, mat, mat), "KSPSetOperators")?;
+            {
+                let _options_lock = crate::config::options_lock();
+                check(KSPSetFromOptions(ksp), "KSPSetFromOptions")?;
+            }
+            check(KSPSolve(ksp, b, x), "KSPSolve")?;
+            if !iterations.is_null() {
+                check(
+                    KSPGetIterationNumber(ksp, iterations),
+                    "KSPGetIterationNumber",
+                )?;
+            }
+            Ok(())
+        })();
+        check(KSPDestroy(&mut ksp), "KSPDestroy")?;
+        result
+    })())
+}
+
+/// Destroy a Meles context and its MatShell, leaving PETSc initialized
+///
+/// # Safety
+///
+/// `handle` must come from `meles_create` and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn meles_destroy(handle: *mut *mut MelesHandle) -> c_int {
+    catch_panic(|| {
+        if handle.is_null() || (*handle).is_null() {
+            return 0;
+        }
+        let MelesHandle { petsc, meles, mat } = *Box::from_raw(*handle);
+        *handle = std::ptr::null_mut();
+        drop(mat);
+        drop(Box::from_raw(meles));
+        // PETSc belongs to the host application
+        std::mem::forget(*Box::from_raw(petsc));
+        0
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -1,12 +1,73 @@
 use crate::prelude::*;
 
+// -----------------------------------------------------------------------------
+// User Dirichlet functions by boundary label
+//   Registered through the API rather than the options database, and compared
+//   by identity so a changed function rebuilds the discretization
+// -----------------------------------------------------------------------------
+pub(crate) type BoundaryFunction = dyn Fn(&[f64], f64, &mut [f64]);
+
+#[derive(Clone, Default)]
+pub(crate) struct BoundaryFunctions(pub(crate) Vec<(String, std::rc::Rc<BoundaryFunction>)>);
+
+impl PartialEq for BoundaryFunctions {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|((a, f), (b, g))| a == b && std::rc::Rc::ptr_eq(f, g))
+    }
+}
+
+impl Eq for BoundaryFunctions {}
+
+// -----------------------------------------------------------------------------
+// Real option values
+//   Compared by bits, so the options are Eq and any changed value, including
+//   NaN, rebuilds the discretization
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Real(pub(crate) f64);
+
+impl PartialEq for Real {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Real {}
+
 // -----------------------------------------------------------------------------
 // BP command line options
 // -----------------------------------------------------------------------------
-struct Opt {
-    problem: CeedBP,
-    order: usize,
-    q_extra: usize,
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Opt {
+    pub(crate) problem: CeedBP,
+    pub(crate) order: usize,
+    pub(crate) q_extra: usize,
+    pub(crate) on_the_fly_geometry: bool,
+    pub(crate) emulate_single_precision: bool,
+    pub(crate) mesh_type: crate::dm::MeshType,
+    pub(crate) refine: usize,
+    pub(crate) local_dofs: Real,
+    pub(crate) periodic: bool,
+    pub(crate) mesh_transform: crate::dm::MeshTransform,
+    pub(crate) kershaw_eps: Real,
+    pub(crate) neumann: bool,
+    pub(crate) essential_boundaries: Vec<String>,
+    pub(crate) boundary_functions: BoundaryFunctions,
+    pub(crate) boundary_conditions: crate::boundary_conditions::BoundaryConditions,
+    pub(crate) neumann_boundaries: Vec<String>,
+    pub(crate) neumann_flux: Option<String>,
+    pub(crate) u_bc: Option<String>,
+    pub(crate) coefficient: Option<String>,
+    pub(crate) operator: Option<String>,
+    pub(crate) diffusion_tensor: Option<String>,
+    pub(crate) diffusion_label: String,
+    pub(crate) wavenumber: Real,
+    pub(crate) solution: crate::manufactured::ManufacturedSolution,
 }
 
 impl petsc::Opt for Opt {
@@ -20,14 +81,301 @@ impl petsc::Opt for Opt {
         let order =
             pob.options_usize("-order", "Polynomial order of tensor product basis", "", 3)?;
         let q_extra = pob.options_usize("-qextra", "Number of extra quadrature points", "", 1)?;
+        let on_the_fly_geometry = pob.options_bool(
+            "-on_the_fly_geometry",
+            "Compute geometric factors from the coordinates at every application instead of storing quadrature data",
+            "",
+            false,
+        )?;
+        let emulate_single_precision = pob.options_bool(
+            "-emulate_single_precision",
+            "Emulate single precision operators by rounding quadrature data and operator inputs and outputs, keeping the Krylov solve in double",
+            "",
+            false,
+        )?;
+        let mesh_type = pob.options_from_string(
+            "-mesh_type",
+            "Mesh type, plex, stag, or da",
+            "",
+            crate::dm::MeshType::Plex,
+        )?;
+        let refine = pob.options_usize(
+            "-refine",
+            "Number of uniform refinements of plex meshes, after any -dm_refine",
+            "",
+            0,
+        )?;
+        let local_dofs = pob.options_real(
+            "-local_dofs",
+            "Target number of DoFs per rank, setting -dm_plex_box_faces from the number of ranks, order, and components, or 0 to use -dm_plex_box_faces",
+            "",
+            0.0,
+        )?;
+        let periodic = pob.options_bool(
+            "-periodic",
+            "Fully periodic box mesh, without essential boundary conditions",
+            "",
+            false,
+        )?;
+        let mesh_transform = pob.options_from_string(
+            "-mesh_transform",
+            "Transformation of the mesh coordinates, none or kershaw",
+            "",
+            crate::dm::MeshTransform::None,
+        )?;
+        let kershaw_eps = pob.options_real(
+            "-kershaw_eps",
+            "Kershaw mesh distortion parameter in (0, 1], with 1 uniform",
+            "",
+            1.0,
+        )?;
+        let neumann = pob.options_bool(
+            "-neumann",
+            "Omit essential boundary conditions, giving a pure Neumann problem",
+            "",
+            false,
+        )?;
+        let u_bc = pob.options_string(
+            "-u_bc",
+            "Expression for essential boundary values, in x, y, z, and t",
+            "",
+            "",
+        )?;
+        let coefficient = pob.options_string(
+            "-coefficient",
+            "Expression for a coefficient, evaluated at cell centroids",
+            "",
+            "",
+        )?;
+        let operator = pob.options_string(
+            "-operator",
+            "Expression combining the mass and stiffness operators of the problem, such as mass + 0.1*stiffness",
+            "",
+            "",
+        )?;
+        let diffusion_tensor = pob.options_string(
+            "-diffusion_tensor",
+            "Symmetric diffusion tensor for BP3-BP6 and helmholtz, xx,yy,zz,yz,xz,xy or a single isotropic value",
+            "",
+            "",
+        )?;
+        let essential_boundaries = pob.options_string(
+            "-essential_boundaries",
+            "Comma separated labels of essential boundaries, such as Gmsh physical names, or label:value for one value, such as Face Sets:1 for an Exodus sideset, instead of the whole boundary",
+            "",
+            "",
+        )?;
+        let neumann_boundaries = pob.options_string(
+            "-neumann_boundaries",
+            "Comma separated label:value faces with the natural boundary condition set by -neumann_flux, such as Face Sets:1",
+            "",
+            "",
+        )?;
+        let neumann_flux = pob.options_string(
+            "-neumann_flux",
+            "Expression for the boundary flux on -neumann_boundaries, in x, y, z, and t",
+            "",
+            "",
+        )?;
+        let diffusion_label = pob.options_string(
+            "-diffusion_label",
+            "Label of material regions with tensors set by -diffusion_tensor_[value]",
+            "",
+            "Cell Sets",
+        )?;
+        let wavenumber = pob.options_real(
+            "-wavenumber",
+            "Wavenumber k of the Helmholtz problem, K - k^2 M",
+            "",
+            1.0,
+        )?;
+        let solution = pob.options_from_string(
+            "-solution",
+            "Manufactured solution, default, radial, trig, polynomial, gaussian, or periodic",
+            "",
+            crate::manufactured::ManufacturedSolution::Default,
+        )?;
+        let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
         Ok(Opt {
             problem,
             order,
             q_extra,
+            on_the_fly_geometry,
+            emulate_single_precision,
+            mesh_type,
+            refine,
+            local_dofs: Real(local_dofs),
+            periodic,
+            mesh_transform,
+            kershaw_eps: Real(kershaw_eps),
+            neumann,
+            essential_boundaries: essential_boundaries
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            boundary_functions: BoundaryFunctions::default(),
+            boundary_conditions: crate::boundary_conditions::BoundaryConditions::default(),
+            neumann_boundaries: neumann_boundaries
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            neumann_flux: non_empty(neumann_flux),
+            u_bc: non_empty(u_bc),
+            coefficient: non_empty(coefficient),
+            operator: non_empty(operator),
+            diffusion_tensor: non_empty(diffusion_tensor),
+            diffusion_label,
+            wavenumber: Real(wavenumber),
+            solution,
         })
     }
 }
 
+impl Opt {
+    // Manufactured solution, resolving the default for the problem
+    pub(crate) fn manufactured_solution(&self) -> crate::manufactured::ManufacturedSolution {
+        self.solution.resolve(self.problem, self.periodic)
+    }
+
+    // Exact solution, the essential boundary value expression if given
+    pub(crate) fn exact_solution(&self) -> crate::Result<crate::manufactured::ExactSolution> {
+        Ok(match self.u_bc_expression()? {
+            Some(u_bc) => crate::manufactured::ExactSolution::Expression(u_bc),
+            None => crate::manufactured::ExactSolution::Manufactured(self.manufactured_solution()),
+        })
+    }
+
+    // Parsed expression for essential boundary values
+    pub(crate) fn u_bc_expression(&self) -> crate::Result<Option<crate::Expression>> {
+        self.u_bc
+            .as_deref()
+            .map(crate::Expression::parse)
+            .transpose()
+    }
+
+    // Parsed expression for the composite operator
+    pub(crate) fn operator_expression(
+        &self,
+    ) -> crate::Result<Option<crate::composition::OperatorExpression>> {
+        self.operator
+            .as_deref()
+            .map(crate::composition::OperatorExpression::parse)
+            .transpose()
+    }
+
+    // Coefficients and problems whose forcing terms sum to the forcing of
+    //   the operator
+    pub(crate) fn forcing_terms(&self) -> crate::Result<Vec<(f64, CeedBP)>> {
+        match self.operator_expression()? {
+            Some(expression) => expression.kernels(self),
+            None => Ok(vec![(1.0, self.problem)]),
+        }
+    }
+
+    // Essential boundaries, from -essential_boundaries and the Dirichlet
+    //   boundaries of the boundary conditions section
+    //   With a section, boundaries not listed are natural, so the boundaries
+    //   of API functions are listed as well
+    pub(crate) fn essential_labels(&self) -> Vec<String> {
+        let mut labels = self.essential_boundaries.clone();
+        if !self.boundary_conditions.is_empty() {
+            labels.extend(
+                self.boundary_conditions
+                    .of_type(crate::boundary_conditions::BoundaryConditionType::Dirichlet)
+                    .map(|condition| condition.boundary.clone()),
+            );
+            labels.extend(
+                self.boundary_functions
+                    .0
+                    .iter()
+                    .map(|(boundary, _)| boundary.clone()),
+            );
+        }
+        labels
+    }
+
+    // Essential boundary functions by label, the Dirichlet values of the
+    //   boundary conditions section followed by the functions set through the
+    //   API, so the latter take precedence on shared points
+    pub(crate) fn labeled_boundary_functions(
+        &self,
+    ) -> crate::Result<Vec<(String, std::rc::Rc<BoundaryFunction>)>> {
+        let mut functions = vec![];
+        for condition in self
+            .boundary_conditions
+            .of_type(crate::boundary_conditions::BoundaryConditionType::Dirichlet)
+        {
+            if let Some(value) = &condition.value {
+                let expression = crate::Expression::parse(value)?;
+                let function: std::rc::Rc<BoundaryFunction> =
+                    std::rc::Rc::new(move |x: &[f64], t: f64, u: &mut [f64]| {
+                        u.fill(expression.evaluate(x, t))
+                    });
+                functions.push((condition.boundary.clone(), function));
+            }
+        }
+        functions.extend(self.boundary_functions.0.iter().cloned());
+        Ok(functions)
+    }
+
+    // Natural boundary fluxes with their face label values, grouped by label,
+    //   from -neumann_boundaries with -neumann_flux and the Neumann boundaries
+    //   of the boundary conditions section
+    pub(crate) fn neumann_fluxes(
+        &self,
+    ) -> crate::Result<Vec<(String, Vec<usize>, crate::Expression)>> {
+        let mut boundaries = vec![];
+        if let Some(flux) = &self.neumann_flux {
+            for boundary in &self.neumann_boundaries {
+                boundaries.push((boundary.as_str(), flux.as_str()));
+            }
+        }
+        for condition in self
+            .boundary_conditions
+            .of_type(crate::boundary_conditions::BoundaryConditionType::Neumann)
+        {
+            // Homogeneous fluxes add nothing to the right hand side
+            if let Some(flux) = &condition.value {
+                boundaries.push((condition.boundary.as_str(), flux.as_str()));
+            }
+        }
+        let mut fluxes: Vec<(String, Vec<usize>, crate::Expression)> = vec![];
+        for (boundary, flux) in boundaries {
+            let (name, value) = boundary
+                .rsplit_once(':')
+                .and_then(|(name, value)| Some((name.trim(), value.trim().parse::<usize>().ok()?)))
+                .ok_or_else(|| crate::Error {
+                    message: format!(
+                        "invalid Neumann boundary {}, expected label:value such as Face Sets:1",
+                        boundary
+                    ),
+                })?;
+            match fluxes
+                .iter_mut()
+                .find(|(label, _, expression)| label == name && expression.source() == flux)
+            {
+                Some((_, values, _)) => values.push(value),
+                None => fluxes.push((
+                    name.to_string(),
+                    vec![value],
+                    crate::Expression::parse(flux)?,
+                )),
+            }
+        }
+        Ok(fluxes)
+    }
+
+    // Parsed expression for the coefficient
+    pub(crate) fn coefficient_expression(&self) -> crate::Result<Option<crate::Expression>> {
+        self.coefficient
+            .as_deref()
+            .map(crate::Expression::parse)
+            .transpose()
+    }
+}
+
 // -----------------------------------------------------------------------------
 // BP enum
 // -----------------------------------------------------------------------------
@@ -39,6 +387,7 @@ pub(crate) enum CeedBP {
     BP4 = 4,
     BP5 = 5,
     BP6 = 6,
+    Helmholtz = 7,
 }
 
 impl std::str::FromStr for CeedBP {
@@ -51,6 +400,7 @@ impl std::str::FromStr for CeedBP {
             "bp4" => Ok(CeedBP::BP4),
             "bp5" => Ok(CeedBP::BP5),
             "bp6" => Ok(CeedBP::BP6),
+            "helmholtz" => Ok(CeedBP::Helmholtz),
             _ => Err(crate::Error {
                 message: "failed to parse problem option".to_string(),
             }),
@@ -60,7 +410,10 @@ impl std::str::FromStr for CeedBP {
 
 impl std::fmt::Display for CeedBP {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "bp{}", *self as usize)
+        match self {
+            CeedBP::Helmholtz => write!(f, "helmholtz"),
+            _ => write!(f, "bp{}", *self as usize),
+        }
     }
 }
 
@@ -74,7 +427,7 @@ pub(crate) struct BPData {
     apply_name: String,
     input_name: String,
     output_name: String,
-    q_mode: libceed::QuadMode,
+    pub(crate) q_mode: libceed::QuadMode,
     set_boundary_conditions: bool,
 }
 
@@ -140,36 +493,311 @@ pub(crate) fn bp_data(bp: CeedBP) -> crate::Result<BPData> {
             q_mode: libceed::QuadMode::GaussLobatto,
             set_boundary_conditions: true,
         }),
+        // Stiffness term, the shifted mass term is added to the operator
+        CeedBP::Helmholtz => Ok(BPData {
+            num_components: 1,
+            q_data_size: 6,
+            setup_name: "Poisson3DBuild".to_string(),
+            apply_name: "Poisson3DApply".to_string(),
+            input_name: "du".to_string(),
+            output_name: "dv".to_string(),
+            q_mode: libceed::QuadMode::Gauss,
+            set_boundary_conditions: true,
+        }),
     }
 }
 
-// Boundary function
-pub(crate) fn boundary_function_diff(
-    _dim: petsc::Int,
-    _t: Real,
-    x: &[Real],
-    num_components: petsc::Int,
-    u: &mut [petsc::Scalar],
-) -> petsc::Result<()> {
-    let c = [0., 1., 2.];
-    let k = [1., 2., 3.];
-    for i in 0..num_components as usize {
-        u[i] = (std::f64::consts::PI * (c[0] + k[0] * x[0])).sin()
-            * (std::f64::consts::PI * (c[1] + k[1] * x[1])).sin()
-            * (std::f64::consts::PI * (c[2] + k[2] * x[2])).sin();
+// Number of components of a problem
+pub(crate) fn num_components(options: &Opt) -> crate::Result<usize> {
+    Ok(bp_data(options.problem)?.num_components)
+}
+
+// Quadrature data components per quadrature point of a problem, including the
+//   mass term of Helmholtz problems
+pub(crate) fn qdata_size(options: &Opt) -> crate::Result<usize> {
+    let size = bp_data(options.problem)?.q_data_size;
+    match options.problem {
+        CeedBP::Helmholtz => Ok(size + bp_data(mass_problem(options)?)?.q_data_size),
+        _ => Ok(size),
     }
-    Ok(())
+}
+
+// Check if a problem imposes essential boundary conditions
+//   A boundary conditions section without Dirichlet boundaries leaves the
+//   whole boundary natural
+pub(crate) fn has_essential_boundary(options: &Opt) -> crate::Result<bool> {
+    Ok(bp_data(options.problem)?.set_boundary_conditions
+        && !options.neumann
+        && !options.periodic
+        && (options.boundary_conditions.is_empty() || !options.essential_labels().is_empty()))
+}
+
+// Mass matrix problem with the same number of components
+pub(crate) fn mass_problem(options: &Opt) -> crate::Result<CeedBP> {
+    match bp_data(options.problem)?.num_components {
+        1 => Ok(CeedBP::BP1),
+        _ => Ok(CeedBP::BP2),
+    }
+}
+
+// Number of constant nullspace components, if the operator is singular
+//   Diffusion operators without essential boundary conditions annihilate
+//   constants in each component
+pub(crate) fn constant_nullspace_components(options: &Opt) -> crate::Result<Option<usize>> {
+    let diffusion = match options.operator_expression()? {
+        Some(expression) => !expression.has_mass(),
+        None => matches!(
+            options.problem,
+            CeedBP::BP3 | CeedBP::BP4 | CeedBP::BP5 | CeedBP::BP6
+        ),
+    };
+    if diffusion && !has_essential_boundary(options)? {
+        Ok(Some(bp_data(options.problem)?.num_components))
+    } else {
+        Ok(None)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// RHS QFunction
+//   rhs = w det(J) f(x), with the same forcing for every component, and the
+//   exact solution at quadrature points; the forcing of an operator
+//   expression is the combination of the forcing of its terms, and a
+//   coefficient expression enters the forcing of each term
+// -----------------------------------------------------------------------------
+pub(crate) fn rhs_q_function<'b>(
+    ceed: &libceed::Ceed,
+    dim: usize,
+    forcing_terms: Vec<(f64, CeedBP)>,
+    wavenumber: f64,
+    solution: crate::manufactured::ExactSolution,
+    coefficient: Option<crate::Expression>,
+    num_components: usize,
+) -> crate::Result<QFunction<'b>> {
+    let setup_rhs = move |[x, dx, weights, ..]: QFunctionInputs,
+                          [rhs, true_soln, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            // -- Jacobian determinant
+            let det_j = crate::jacobian::Jacobian::at_point(dx, dim, q, i).det;
+            // -- Forcing and exact solution
+            let x_i = (0..dim).map(|d| x[d * q + i]).collect::<Vec<_>>();
+            let f: f64 = forcing_terms
+                .iter()
+                .map(|&(weight, problem)| {
+                    weight * solution.forcing(problem, wavenumber, coefficient.as_ref(), &x_i, 0.0)
+                })
+                .sum();
+            let u = solution.value(&x_i, 0.0);
+            for c in 0..num_components {
+                rhs[c * q + i] = weights[i] * det_j * f;
+                true_soln[c * q + i] = u;
+            }
+        }
+        0
+    };
+    let qf_rhs = ceed
+        .q_function_interior(1, Box::new(setup_rhs))?
+        .input("x", dim, EvalMode::Interp)?
+        .input("dx", dim * dim, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("rhs", num_components, EvalMode::Interp)?
+        .output("true_soln", num_components, EvalMode::None)?;
+    Ok(qf_rhs)
+}
+
+// -----------------------------------------------------------------------------
+// Error QFunction
+//   error = [w det(J) |u - u_exact|^2, w det(J) |grad u - grad u_exact|^2],
+//   summed over components
+// -----------------------------------------------------------------------------
+pub(crate) fn error_q_function<'b>(
+    ceed: &libceed::Ceed,
+    dim: usize,
+    solution: crate::manufactured::ExactSolution,
+    num_components: usize,
+) -> crate::Result<QFunction<'b>> {
+    let squared_error = move |[u, du, x, dx, weights, ..]: QFunctionInputs,
+                              [error, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            // -- Jacobian, determinant, and inverse
+            let jacobian = crate::jacobian::Jacobian::at_point(dx, dim, q, i);
+            // -- Exact solution
+            let x_i = (0..dim).map(|d| x[d * q + i]).collect::<Vec<_>>();
+            let u_exact = solution.value(&x_i, 0.0);
+            let grad_exact = solution.gradient(&x_i, 0.0);
+            // -- Squared errors
+            let mut l2 = 0.0;
+            let mut h1 = 0.0;
+            for c in 0..num_components {
+                l2 += (u[c * q + i] - u_exact).powi(2);
+                for (k, grad_exact_k) in grad_exact.iter().enumerate().take(dim) {
+                    let grad_u: f64 = (0..dim)
+                        .map(|d| du[(d * num_components + c) * q + i] * jacobian.inverse[d][k])
+                        .sum();
+                    h1 += (grad_u - grad_exact_k).powi(2);
+                }
+            }
+            error[i] = weights[i] * jacobian.det * l2;
+            error[q + i] = weights[i] * jacobian.det * h1;
+        }
+        0
+    };
+    let qf_error = ceed
+        .q_function_interior(1, Box::new(squared_error))?
+        .input("u", num_components, EvalMode::Interp)?
+        .input("du", num_components * dim, EvalMode::Grad)?
+        .input("x", dim, EvalMode::Interp)?
+        .input("dx", dim * dim, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("error", 2, EvalMode::None)?;
+    Ok(qf_error)
+}
+
+// -----------------------------------------------------------------------------
+// On-the-fly apply QFunction
+//   The geometric factors are recomputed from the coordinate gradient at every
+//   application instead of read from stored quadrature data, with the
+//   Jacobian inverted once per quadrature point
+//   mass: v = w det(J) u
+//   diffusion: dv = w det(J) J^{-1} J^{-T} du
+// -----------------------------------------------------------------------------
+pub(crate) fn on_the_fly_q_function<'b>(
+    ceed: &libceed::Ceed,
+    dim: usize,
+    kernel: CeedBP,
+    num_components: usize,
+) -> crate::Result<QFunction<'b>> {
+    let mass = matches!(kernel, CeedBP::BP1 | CeedBP::BP2);
+    let apply = move |[u, dx, weights, ..]: QFunctionInputs, [v, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            // -- Jacobian, determinant, and inverse
+            let jacobian = crate::jacobian::Jacobian::at_point(dx, dim, q, i);
+            let w_det_j = weights[i] * jacobian.det;
+            if mass {
+                for c in 0..num_components {
+                    v[c * q + i] = w_det_j * u[c * q + i];
+                }
+                continue;
+            }
+            let j_inv = &jacobian.inverse;
+            // -- Physical gradient, mapped back to reference directions
+            for c in 0..num_components {
+                let mut grad_u = [0.0; 3];
+                for (k, grad_u_k) in grad_u.iter_mut().take(dim).enumerate() {
+                    *grad_u_k = (0..dim)
+                        .map(|d| u[(d * num_components + c) * q + i] * j_inv[d][k])
+                        .sum();
+                }
+                for (d, j_inv_d) in j_inv.iter().take(dim).enumerate() {
+                    v[(d * num_components + c) * q + i] =
+                        w_det_j * (0..dim).map(|k| j_inv_d[k] * grad_u[k]).sum::<f64>();
+                }
+            }
+        }
+        0
+    };
+    let (input_name, output_name, size, eval_mode) = if mass {
+        ("u", "v", num_components, EvalMode::Interp)
+    } else {
+        ("du", "dv", num_components * dim, EvalMode::Grad)
+    };
+    let qf_apply = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input(input_name, size, eval_mode)?
+        .input("dx", dim * dim, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output(output_name, size, eval_mode)?;
+    Ok(qf_apply)
 }
 
 // -----------------------------------------------------------------------------
 // Setup dm and libCEED operator
 // -----------------------------------------------------------------------------
-pub(crate) fn create_dm(petsc: &Petsc) -> crate::Result<DM<'_, '_>> {
+pub(crate) fn create_dm<'a>(
+    comm: &'a mpi::topology::UserCommunicator,
+    options: &Opt,
+) -> crate::Result<DM<'a, 'a>> {
+    // Create DM
+    let mut dm = match options.mesh_type {
+        crate::dm::MeshType::Plex => {
+            // Only the periodic manufactured solution is continuous across
+            //   the periodic faces
+            if options.periodic
+                && (options.u_bc.is_some()
+                    || options.manufactured_solution()
+                        != crate::manufactured::ManufacturedSolution::Periodic)
+            {
+                return Err(crate::Error {
+                    message: format!(
+                        "-periodic requires the periodic manufactured solution, not {}",
+                        if options.u_bc.is_some() {
+                            "-u_bc".to_string()
+                        } else {
+                            options.manufactured_solution().to_string()
+                        }
+                    ),
+                });
+            }
+            let mut dm = crate::dm::create_plex(comm, options)?;
+            crate::dm::transform_mesh(&mut dm, options)?;
+            dm
+        }
+        _ if options.refine > 0 || options.periodic || options.local_dofs.0 > 0.0 => {
+            return Err(crate::Error {
+                message: format!(
+                    "-refine, -periodic, and -local_dofs require a plex mesh, not {}",
+                    options.mesh_type
+                ),
+            });
+        }
+        crate::dm::MeshType::Stag => {
+            let num_components = bp_data(options.problem)?.num_components;
+            crate::dm::create_dm_stag(comm, 3, num_components)?
+        }
+        crate::dm::MeshType::Da => {
+            let num_components = bp_data(options.problem)?.num_components;
+            crate::dm::create_dm_da(comm, 3, num_components, options.order)?
+        }
+    };
+    setup_dm(&mut dm, options)?;
+
+    Ok(dm)
+}
+
+// -----------------------------------------------------------------------------
+// Setup discretization on a mesh DM
+// -----------------------------------------------------------------------------
+pub(crate) fn setup_dm<'a>(dm: &mut DM<'a, 'a>, options: &Opt) -> crate::Result<()> {
+    let _event = crate::events::Event::DmSetup.begin();
     let Opt {
         problem,
         order,
         q_extra: _,
-    } = petsc.options()?;
+        on_the_fly_geometry: _,
+        emulate_single_precision: _,
+        mesh_type,
+        refine: _,
+        local_dofs: _,
+        periodic: _,
+        mesh_transform: _,
+        kershaw_eps: _,
+        neumann: _,
+        essential_boundaries: _,
+        boundary_functions: _,
+        boundary_conditions: _,
+        neumann_boundaries: _,
+        neumann_flux: _,
+        u_bc: _,
+        coefficient: _,
+        operator: _,
+        diffusion_tensor: _,
+        diffusion_label: _,
+        wavenumber: _,
+        solution: _,
+    } = *options;
     let BPData {
         num_components,
         q_data_size: _,
@@ -178,28 +806,69 @@ pub(crate) fn create_dm(petsc: &Petsc) -> crate::Result<DM<'_, '_>> {
         input_name: _,
         output_name: _,
         q_mode: _,
-        set_boundary_conditions,
+        set_boundary_conditions: _,
     } = bp_data(problem)?;
+    let set_boundary_conditions = has_essential_boundary(options)?;
 
-    // Create DM
-    let mut dm = DM::create(petsc.world())?;
-    dm.set_type(DMType::DMPLEX)?;
-    dm.set_from_options()?;
+    // DMStag and DMDA DoFs are fixed at creation
+    match mesh_type {
+        crate::dm::MeshType::Plex if crate::dm::plex_cell_types(dm)?.len() > 1 => {
+            // Mixed topology meshes use linear elements with vertex DoFs
+            if order != 1 || set_boundary_conditions {
+                return Err(crate::Error {
+                    message: format!(
+                        "mixed topology meshes support order 1 without essential boundary conditions, not {} with order {}",
+                        problem, order
+                    ),
+                });
+            }
+            crate::dm::setup_dm_vertex_section(dm, num_components)?;
+            return Ok(());
+        }
+        crate::dm::MeshType::Plex => {}
+        crate::dm::MeshType::Stag if order != 1 => {
+            return Err(crate::Error {
+                message: format!("stag meshes only support order 1, not order {}", order),
+            });
+        }
+        _ if set_boundary_conditions => {
+            return Err(crate::Error {
+                message: format!(
+                    "{} meshes do not support essential boundary conditions required by {}; use -neumann to omit them",
+                    mesh_type, problem
+                ),
+            });
+        }
+        _ => return Ok(()),
+    }
 
+    // Boundary values from the exact solution
+    let solution = options.exact_solution()?;
     let user_boundary_function = if set_boundary_conditions {
-        Some(boundary_function_diff)
+        Some(
+            move |_dim: petsc::Int,
+                  t: Real,
+                  x: &[Real],
+                  num_components: petsc::Int,
+                  u: &mut [petsc::Scalar]| {
+                u[..num_components as usize].fill(solution.value(x, t));
+                Ok(())
+            },
+        )
     } else {
         None
     };
     crate::dm::setup_dm_by_order(
-        &mut dm,
+        dm,
         order,
         num_components,
         set_boundary_conditions,
+        &options.essential_labels(),
         user_boundary_function,
+        &options.labeled_boundary_functions()?,
     )?;
 
-    Ok(dm)
+    Ok(())
 }
 
 // -----------------------------------------------------------------------------
@@ -207,13 +876,172 @@ pub(crate) fn create_dm(petsc: &Petsc) -> crate::Result<DM<'_, '_>> {
 // -----------------------------------------------------------------------------
 pub(crate) fn mat_shell_context<'a>(
     meles: &'a crate::Meles<'a>,
-    petsc: &'a Petsc,
+    options: &Opt,
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    let _stage = crate::events::Stage::Setup.push();
+    if let Some(feature) = crate::qdata::stored_qdata_feature(options) {
+        crate::qdata::require_stored_qdata(options, feature)?;
+    }
+    let mut context = match options.operator_expression()? {
+        Some(_) if meles.split.is_some() => {
+            return Err(crate::Error {
+                message: "split execution does not support operator expressions".to_string(),
+            })
+        }
+        Some(expression) => crate::composition::mat_shell_context(meles, options, &expression)?,
+        // Elements split between Ceeds
+        None => mat_shell_context_with_q_function(
+            meles,
+            options,
+            options.problem,
+            None,
+            true,
+            meles.split.as_ref(),
+        )?,
+    };
+
+    // Anisotropic diffusion tensors, by material region
+    if options.mesh_type == crate::dm::MeshType::Plex {
+        let tensors = crate::coefficients::diffusion_tensors(&context.dm.borrow(), options)?;
+        if let Some(tensors) = tensors {
+            crate::coefficients::apply_diffusion_tensors(meles, &mut context, &tensors)?;
+        }
+    } else if options.diffusion_tensor.is_some() {
+        return Err(crate::Error {
+            message: "diffusion tensors require a plex mesh".to_string(),
+        });
+    }
+
+    // Coefficient from an expression, piecewise constant by cell
+    if let Some(coefficient) = options.coefficient_expression()? {
+        let coefficient = crate::coefficients::cell_vector_from_expression(
+            meles,
+            &context.dm.borrow(),
+            &coefficient,
+        )?;
+        crate::coefficients::scale_qdata_by_cell(&mut context, &coefficient)?;
+    }
+
+    // Helmholtz shift, added after any coefficients so they scale only the
+    //   stiffness term
+    if options.problem == CeedBP::Helmholtz {
+        add_helmholtz_shift(meles, options, &mut context)?;
+    }
+
+    // Natural boundary conditions, the flux integrated over labeled faces
+    for (label, values, flux) in options.neumann_fluxes()? {
+        let neumann =
+            crate::surface::boundary_integral(meles, options, &label, &values, &flux, 0.0)?;
+        context.rhs.axpy(1.0, &neumann)?;
+    }
+
+    // Single precision quadrature data, after any scaling
+    if options.emulate_single_precision {
+        for qdata in &mut context.qdata {
+            let vector = qdata.vector_mut()?;
+            crate::petsc_ops::round_to_single(vector)?;
+        }
+        context.single_precision = true;
+    }
+    Ok(context)
+}
+
+// Replace the stiffness operator K of a context with K - k^2 M
+//   The mass operator is set up on the problem DM, so both act on the same
+//   local vectors, and its quadrature data is scaled by -k^2 and kept after
+//   the stiffness quadrature data
+fn add_helmholtz_shift<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &Opt,
+    context: &mut crate::MelesMatShellContext<'a>,
+) -> crate::Result<()> {
+    let mut mass = operator_context_with_kernel(meles, options, mass_problem(options)?)?;
+    let shift = -options.wavenumber.0 * options.wavenumber.0;
+    for qdata in &mut mass.qdata {
+        let vector = qdata.vector_mut()?;
+        let mut qdata_view = vector.view_mut()?;
+        for value in qdata_view.iter_mut() {
+            *value *= shift;
+        }
+    }
+    let stiffness_op = context
+        .op_ceed
+        .replace(crate::OperatorSum::new(&meles.ceed, vec![]).into());
+    context.set_operator(crate::OperatorSum::new(
+        &meles.ceed,
+        vec![stiffness_op, mass.op_ceed.into_inner()],
+    ));
+    context.qdata.append(&mut mass.qdata);
+    Ok(())
+}
+
+// Setup dm for the problem and a libCEED operator using the QFunctions of
+//   another benchmark problem, such as the mass matrix for the problem DM
+pub(crate) fn mat_shell_context_with_kernel<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &Opt,
+    kernel: CeedBP,
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    mat_shell_context_with_q_function(meles, options, kernel, None, true, None)
+}
+
+// Setup dm for the problem and a libCEED operator using the QFunctions of
+//   another benchmark problem, without the forcing and error operators, for
+//   additional terms of an operator, so the RHS is zero and holds no error
+pub(crate) fn operator_context_with_kernel<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &Opt,
+    kernel: CeedBP,
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    mat_shell_context_with_q_function(meles, options, kernel, None, false, None)
+}
+
+// Coordinate and solution bases and restrictions
+type Discretization<'a> = (
+    Basis<'a>,
+    Basis<'a>,
+    ElemRestriction<'a>,
+    ElemRestriction<'a>,
+);
+
+// Setup dm for the problem and a libCEED operator applying a user QFunction,
+//   or the QFunctions of the kernel if none is given, with the forcing and
+//   error operators if requested, and the elements split between Ceeds if a
+//   split is given
+pub(crate) fn mat_shell_context_with_q_function<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &Opt,
+    kernel: CeedBP,
+    user_q_function: Option<crate::UserQFunction>,
+    forcing: bool,
+    split: Option<&crate::split::CeedSplit>,
 ) -> crate::Result<crate::MelesMatShellContext<'a>> {
     let Opt {
-        problem,
+        problem: _,
         order,
         q_extra,
-    } = petsc.options()?;
+        on_the_fly_geometry,
+        emulate_single_precision: _,
+        mesh_type,
+        refine: _,
+        local_dofs: _,
+        periodic: _,
+        mesh_transform: _,
+        kershaw_eps: _,
+        neumann: _,
+        essential_boundaries: _,
+        boundary_functions: _,
+        boundary_conditions: _,
+        neumann_boundaries: _,
+        neumann_flux: _,
+        u_bc: _,
+        coefficient: _,
+        operator: _,
+        diffusion_tensor: _,
+        diffusion_label: _,
+        wavenumber: _,
+        solution: _,
+    } = *options;
     let BPData {
         num_components,
         q_data_size,
@@ -222,23 +1050,27 @@ pub(crate) fn mat_shell_context<'a>(
         input_name,
         output_name,
         q_mode,
-        set_boundary_conditions,
-    } = bp_data(problem)?;
+        set_boundary_conditions: _,
+    } = bp_data(kernel)?;
+    let q_mode = crate::backend::quadrature_mode(meles, q_mode);
+    if on_the_fly_geometry && user_q_function.is_some() {
+        return Err(crate::Error {
+            message: "on-the-fly geometric factors do not support user QFunctions".to_string(),
+        });
+    }
 
     // Duplicate DM
+    let section_setup_start = std::time::Instant::now();
     let mut dm = meles.dm.borrow().clone();
-    let user_boundary_function = if set_boundary_conditions {
-        Some(boundary_function_diff)
-    } else {
-        None
-    };
-    crate::dm::setup_dm_by_order(
-        &mut dm,
-        order,
-        num_components,
-        set_boundary_conditions,
-        user_boundary_function,
-    )?;
+    setup_dm(&mut dm, options)?;
+    meles.progress.report(
+        crate::progress::SetupPhase::Section,
+        None,
+        section_setup_start,
+    );
+    if let Some(split) = split {
+        crate::split::check_split(meles, options, &dm, split)?;
+    }
 
     // Create work vectors
     let x_loc = dm.create_local_vector()?;
@@ -248,89 +1080,826 @@ pub(crate) fn mat_shell_context<'a>(
     let y_loc_ceed = meles.ceed.vector(x_loc_size)?;
 
     // Create libCEED operator
-    // -- Basis
-    let p = order + 1;
-    let q = p + q_extra;
-    let dimension = dm.dimension()?;
-    let basis_x = meles
-        .ceed
-        .basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?;
-    let basis_u = meles
-        .ceed
-        .basis_tensor_H1_Lagrange(dimension, num_components, p, q, q_mode)?;
-    // -- Restrictions
-    let restr_u = crate::dm::create_restriction_from_dm_plex(&dm, &meles.ceed, 0, None, 0)?;
-    let restr_x = {
-        let mesh_coord_dm = dm.coordinate_dm()?;
-        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+    // -- QFunction
+    let dim = dm.dimension()?;
+    let (setup_name, q_data_size) = match &user_q_function {
+        Some(user_q_function) => user_q_function.geometric_factors.setup(dim),
+        None => (setup_name, q_data_size),
+    };
+    let qf_setup = meles.ceed.q_function_interior_by_name(&setup_name)?;
+    let (qf_apply, input_names, output_names) = match user_q_function {
+        Some(user_q_function) => {
+            let (qf, input_names, output_names) =
+                user_q_function.create(&meles.ceed, num_components, dim)?;
+            (ApplyQFunction::User(qf), input_names, output_names)
+        }
+        None => (
+            ApplyQFunction::Gallery(meles.ceed.q_function_interior_by_name(&apply_name)?),
+            vec![input_name],
+            vec![output_name],
+        ),
+    };
+    // -- Forcing and error QFunctions
+    //      Rust closures run on the host, so device resources build the
+    //      forcing and error operators on the host Ceed
+    let host_ceed = crate::backend::host_ceed(meles);
+    let on_host = crate::backend::capabilities(&meles.ceed_resource).closure_q_functions;
+    let forcing_q_functions = if forcing {
+        let solution = options.exact_solution()?;
+        Some((
+            rhs_q_function(
+                &host_ceed,
+                dim,
+                options.forcing_terms()?,
+                options.wavenumber.0,
+                solution.clone(),
+                options.coefficient_expression()?,
+                num_components,
+            )?,
+            error_q_function(&host_ceed, dim, solution, num_components)?,
+        ))
+    } else {
+        None
+    };
+    // -- On-the-fly geometric factors, from C source on device resources
+    let qf_on_the_fly = if on_the_fly_geometry && on_host {
+        Some(on_the_fly_q_function(
+            &meles.ceed,
+            dim,
+            kernel,
+            num_components,
+        )?)
+    } else {
+        None
+    };
+    let on_the_fly_source = on_the_fly_geometry && !on_host;
+    // -- Coordinates
+    let coord_loc = {
+        let mut dm = meles.dm.borrow_mut();
+        dm.coordinates_local()?
+    };
+    // -- Owned copies of the coordinates, also read by operators after setup
+    let coord_loc_owned = crate::transfer::owned_copy(&meles.ceed, &coord_loc)?;
+    let coord_loc_host = if on_host {
+        None
+    } else {
+        Some(crate::transfer::owned_copy(&host_ceed, &coord_loc)?)
     };
-    let restr_qdata = {
+    let coord_loc_host = coord_loc_host.as_ref().unwrap_or(&coord_loc_owned);
+    let bp_operator = |basis_x: &Basis<'a>,
+                       basis_u: &Basis<'a>,
+                       restr_x: &ElemRestriction<'a>,
+                       restr_u: &ElemRestriction<'a>|
+     -> crate::Result<(
+        libceed::operator::Operator<'a>,
+        Option<crate::qdata::QDataVector<'a>>,
+    )> {
+        match &qf_on_the_fly {
+            // -- Geometric factors from the passive coordinates, without qdata
+            Some(qf) => Ok((
+                meles
+                    .ceed
+                    .operator(qf, QFunctionOpt::None, QFunctionOpt::None)?
+                    .field(&input_names[0], restr_u, basis_u, VectorOpt::Active)?
+                    .field("dx", restr_x, basis_x, &coord_loc_owned)?
+                    .field(
+                        "weights",
+                        ElemRestrictionOpt::None,
+                        basis_x,
+                        VectorOpt::None,
+                    )?
+                    .field(&output_names[0], restr_u, basis_u, VectorOpt::Active)?
+                    .check()?,
+                None,
+            )),
+            None => {
+                let (op, qdata) = create_bp_operator(
+                    &meles.ceed,
+                    &qf_setup,
+                    &qf_apply,
+                    q_data_size,
+                    &input_names,
+                    &output_names,
+                    basis_x,
+                    basis_u,
+                    restr_x,
+                    restr_u,
+                    &coord_loc_owned,
+                )?;
+                Ok((op, Some(qdata)))
+            }
+        }
+    };
+    // -- RHS, accumulated over sub-operators on the host Ceed
+    let mut rhs_loc_ceed = host_ceed.vector(x_loc_size)?;
+    rhs_loc_ceed.set_value(0.0)?;
+    let mut true_solution = vec![];
+    let mut apply_rhs = |qf_rhs: &QFunction<'a>,
+                         basis_x: &Basis<'a>,
+                         basis_u: &Basis<'a>,
+                         restr_x: &ElemRestriction<'a>,
+                         restr_u: &ElemRestriction<'a>|
+     -> crate::Result<()> {
+        let _event = crate::events::Event::Rhs.begin();
         let num_elements = restr_u.num_elements();
         let num_quadrature_points = basis_u.num_quadrature_points();
-        meles.ceed.strided_elem_restriction(
+        let restr_true_soln = crate::qdata::QDataVector::restriction(
+            &host_ceed,
             num_elements,
             num_quadrature_points,
-            q_data_size,
-            num_elements * num_quadrature_points * q_data_size,
-            CEED_STRIDES_BACKEND,
-        )?
-    };
-    // -- Vector
-    let mut qdata = restr_qdata.create_lvector()?;
-    let mut coord_loc = {
-        let mut dm = meles.dm.borrow_mut();
-        dm.coordinates_local()?
+            num_components,
+        )?;
+        let true_soln = restr_true_soln.create_lvector()?;
+        host_ceed
+            .operator(qf_rhs, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("x", restr_x, basis_x, VectorOpt::Active)?
+            .field("dx", restr_x, basis_x, VectorOpt::Active)?
+            .field(
+                "weights",
+                ElemRestrictionOpt::None,
+                basis_x,
+                VectorOpt::None,
+            )?
+            .field("rhs", restr_u, basis_u, VectorOpt::Active)?
+            .field(
+                "true_soln",
+                &restr_true_soln,
+                BasisOpt::Collocated,
+                &true_soln,
+            )?
+            .check()?
+            .apply_add(coord_loc_host, &mut rhs_loc_ceed)?;
+        true_solution.push(crate::qdata::QDataVector {
+            vector: std::rc::Rc::new(true_soln),
+            num_elements,
+            num_quadrature_points,
+            size: num_components,
+        });
+        Ok(())
     };
-    let mut coord_loc_ceed = meles.ceed.vector(coord_loc.local_size()?)?;
-    // -- QFunction
-    let qf_setup = meles.ceed.q_function_interior_by_name(&setup_name)?;
-    let qf_apply = meles.ceed.q_function_interior_by_name(&apply_name)?;
-    // -- Apply setup operator
-    {
-        let mut coord_loc_view = coord_loc.view_mut()?;
-        let mut coord_loc_view_slice = coord_loc_view
-            .as_slice_mut()
-            .expect("failed to deref to slice");
-        let _coord_loc_wrapper = coord_loc_ceed
-            .wrap_slice_mut(&mut coord_loc_view_slice)
-            .expect("failed to wrap slice");
-        meles
-            .ceed
-            .operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
-            .field("dx", &restr_x, &basis_x, VectorOpt::Active)?
+    // -- Error against the exact solution, ordered by error component so the
+    //      squared L2 and H1 seminorm contributions are contiguous
+    let mut error_ops = vec![];
+    let mut error_operator = |qf_error: &QFunction<'a>,
+                              basis_x: &Basis<'a>,
+                              basis_u: &Basis<'a>,
+                              restr_x: &ElemRestriction<'a>,
+                              restr_u: &ElemRestriction<'a>|
+     -> crate::Result<()> {
+        let num_elements = restr_u.num_elements();
+        let num_quadrature_points = basis_u.num_quadrature_points();
+        let num_values = 2 * num_elements * num_quadrature_points;
+        let restr_error = host_ceed.strided_elem_restriction(
+            num_elements,
+            num_quadrature_points,
+            2,
+            num_values,
+            [
+                1,
+                (num_elements * num_quadrature_points) as i32,
+                num_quadrature_points as i32,
+            ],
+        )?;
+        let op = host_ceed
+            .operator(qf_error, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("u", restr_u, basis_u, VectorOpt::Active)?
+            .field("du", restr_u, basis_u, VectorOpt::Active)?
+            .field("x", restr_x, basis_x, coord_loc_host)?
+            .field("dx", restr_x, basis_x, coord_loc_host)?
             .field(
                 "weights",
                 ElemRestrictionOpt::None,
-                &basis_x,
+                basis_x,
                 VectorOpt::None,
             )?
             .field(
-                "qdata",
-                &restr_qdata,
+                "error",
+                &restr_error,
                 BasisOpt::Collocated,
                 VectorOpt::Active,
             )?
-            .check()?
-            .apply(&coord_loc_ceed, &mut qdata)?;
+            .check()?;
+        error_ops.push(crate::norms::ErrorOperator { op, num_values });
+        Ok(())
+    };
+    // -- Forcing and error operators, on copies of the bases and restrictions
+    //      for the host Ceed when the operator Ceed is a device resource
+    let mut forcing_and_error =
+        |discretization: &dyn Fn(&libceed::Ceed) -> crate::Result<Discretization<'a>>,
+         device: &Discretization<'a>|
+         -> crate::Result<()> {
+            let (qf_rhs, qf_error) = match &forcing_q_functions {
+                Some(q_functions) => q_functions,
+                None => return Ok(()),
+            };
+            let host = if on_host {
+                None
+            } else {
+                Some(discretization(&host_ceed)?)
+            };
+            let (basis_x, basis_u, restr_x, restr_u) = host.as_ref().unwrap_or(device);
+            apply_rhs(qf_rhs, basis_x, basis_u, restr_x, restr_u)?;
+            error_operator(qf_error, basis_x, basis_u, restr_x, restr_u)
+        };
+    // -- Basis and restrictions
+    let p = order + 1;
+    let q = p + q_extra;
+    let dimension = dm.dimension()?;
+    let mut qdata = vec![];
+    let cell_types = match mesh_type {
+        crate::dm::MeshType::Plex => crate::dm::plex_cell_types(&dm)?,
+        _ => vec![],
+    };
+    if on_the_fly_source && (mesh_type != crate::dm::MeshType::Plex || cell_types.len() > 1) {
+        return Err(crate::Error {
+            message: format!(
+                "on-the-fly geometric factors on libCEED resource {} require a plex mesh with a single cell type",
+                meles.ceed_resource
+            ),
+        });
     }
-    // -- Operator
-    let op_ceed = meles
-        .ceed
-        .operator(&qf_apply, QFunctionOpt::None, QFunctionOpt::None)?
-        .field(&input_name, &restr_u, &basis_u, VectorOpt::Active)?
-        .field("qdata", &restr_qdata, BasisOpt::Collocated, &qdata)?
-        .field(&output_name, &restr_u, &basis_u, VectorOpt::Active)?
-        .check()?;
+    let op_ceed: crate::MelesOperator = if cell_types.len() > 1 {
+        // Mixed topology, one sub-operator per cell type
+        let cell_type_label = dm.label("celltype")?.ok_or_else(|| crate::Error {
+            message: "missing celltype label for mixed mesh".to_string(),
+        })?;
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        let mut op_ceed = meles.ceed.composite_operator()?;
+        for cell_type in cell_types {
+            use petsc::petsc_raw::DMPolytopeType::*;
+            let discretization = |ceed: &libceed::Ceed| -> crate::Result<Discretization<'a>> {
+                let (basis_x, basis_u) = match cell_type {
+                    DM_POLYTOPE_HEXAHEDRON | DM_POLYTOPE_QUADRILATERAL => (
+                        ceed.basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?,
+                        ceed.basis_tensor_H1_Lagrange(dimension, num_components, 2, q, q_mode)?,
+                    ),
+                    DM_POLYTOPE_TETRAHEDRON => (
+                        crate::dm::create_basis_tet_p1(ceed, dimension)?,
+                        crate::dm::create_basis_tet_p1(ceed, num_components)?,
+                    ),
+                    DM_POLYTOPE_TRIANGLE => (
+                        crate::dm::create_basis_simplex(ceed, dimension, dimension, 1, q)?,
+                        crate::dm::create_basis_simplex(ceed, dimension, num_components, 1, q)?,
+                    ),
+                    _ => {
+                        return Err(crate::Error {
+                            message: format!(
+                                "unsupported cell type {:?} in mixed mesh, only hexahedra, tetrahedra, quadrilaterals, and triangles are supported",
+                                cell_type
+                            ),
+                        })
+                    }
+                };
+                let restr_u = crate::dm::create_restriction_from_dm_plex(
+                    &dm,
+                    ceed,
+                    0,
+                    &cell_type_label,
+                    cell_type as usize,
+                )?;
+                let restr_x = crate::dm::create_restriction_from_dm_plex(
+                    &mesh_coord_dm,
+                    ceed,
+                    0,
+                    &cell_type_label,
+                    cell_type as usize,
+                )?;
+                Ok((basis_x, basis_u, restr_x, restr_u))
+            };
+            let restrictions_start = std::time::Instant::now();
+            let device = discretization(&meles.ceed)?;
+            let (basis_x, basis_u, restr_x, restr_u) = &device;
+            let num_elements = restr_u.num_elements();
+            meles.progress.report(
+                crate::progress::SetupPhase::Restrictions,
+                num_elements,
+                restrictions_start,
+            );
+            let qdata_start = std::time::Instant::now();
+            let (sub_op_ceed, sub_qdata) = bp_operator(basis_x, basis_u, restr_x, restr_u)?;
+            op_ceed = op_ceed.sub_operator(&sub_op_ceed)?;
+            qdata.extend(sub_qdata);
+            meles.progress.report(
+                crate::progress::SetupPhase::QData,
+                num_elements,
+                qdata_start,
+            );
+            let rhs_start = std::time::Instant::now();
+            forcing_and_error(&discretization, &device)?;
+            meles
+                .progress
+                .report(crate::progress::SetupPhase::Rhs, num_elements, rhs_start);
+        }
+        op_ceed.into()
+    } else {
+        let discretization = |ceed: &libceed::Ceed| -> crate::Result<Discretization<'a>> {
+            // Simplex cells use non-tensor bases with simplex quadrature
+            let (basis_x, basis_u) =
+                if mesh_type == crate::dm::MeshType::Plex && crate::dm::plex_is_simplex(&dm)? {
+                    (
+                        crate::dm::create_basis_simplex(ceed, dimension, dimension, 1, q)?,
+                        crate::dm::create_basis_simplex(ceed, dimension, num_components, order, q)?,
+                    )
+                } else {
+                    (
+                        ceed.basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?,
+                        ceed.basis_tensor_H1_Lagrange(dimension, num_components, p, q, q_mode)?,
+                    )
+                };
+            let (restr_u, restr_x) = match mesh_type {
+                crate::dm::MeshType::Plex => (
+                    crate::dm::create_restriction_from_dm_plex(&dm, ceed, 0, None, 0)?,
+                    {
+                        let mesh_coord_dm = dm.coordinate_dm()?;
+                        crate::dm::create_restriction_from_dm_plex(
+                            &mesh_coord_dm,
+                            ceed,
+                            0,
+                            None,
+                            0,
+                        )?
+                    },
+                ),
+                crate::dm::MeshType::Stag => {
+                    let vertices = crate::dm::stag_element_vertices(dimension);
+                    (
+                        crate::dm::create_restriction_from_dm_stag(
+                            &dm,
+                            ceed,
+                            &vertices,
+                            num_components,
+                        )?,
+                        {
+                            let mesh_coord_dm = dm.coordinate_dm()?;
+                            crate::dm::create_restriction_from_dm_stag(
+                                &mesh_coord_dm,
+                                ceed,
+                                &vertices,
+                                dimension,
+                            )?
+                        },
+                    )
+                }
+                crate::dm::MeshType::Da => (
+                    crate::dm::create_restriction_from_dm_da(&dm, ceed, order, false)?,
+                    {
+                        let mesh_coord_dm = dm.coordinate_dm()?;
+                        crate::dm::create_restriction_from_dm_da(&mesh_coord_dm, ceed, order, true)?
+                    },
+                ),
+            };
+            Ok((basis_x, basis_u, restr_x, restr_u))
+        };
+        let restrictions_start = std::time::Instant::now();
+        let device = discretization(&meles.ceed)?;
+        let (basis_x, basis_u, restr_x, restr_u) = &device;
+        let num_elements = restr_u.num_elements();
+        meles.progress.report(
+            crate::progress::SetupPhase::Restrictions,
+            num_elements,
+            restrictions_start,
+        );
+        let rhs_start = std::time::Instant::now();
+        forcing_and_error(&discretization, &device)?;
+        meles
+            .progress
+            .report(crate::progress::SetupPhase::Rhs, num_elements, rhs_start);
+        let qdata_start = std::time::Instant::now();
+        let op_ceed = if let Some(split) = split {
+            let (op_ceed, split_qdata) =
+                crate::split::split_operator(meles, options, split, &dm, &coord_loc_owned.view()?)?;
+            qdata.extend(split_qdata);
+            op_ceed
+        } else if on_the_fly_source {
+            crate::MelesOperator::Source(crate::qfunction_source::on_the_fly_operator(
+                meles,
+                &dm,
+                kernel,
+                num_components,
+                (p, q, q_mode),
+            )?)
+        } else {
+            let (op_ceed, op_qdata) = bp_operator(basis_x, basis_u, restr_x, restr_u)?;
+            qdata.extend(op_qdata);
+            op_ceed.into()
+        };
+        meles.progress.report(
+            crate::progress::SetupPhase::QData,
+            num_elements,
+            qdata_start,
+        );
+        op_ceed
+    };
+
+    // Assemble global RHS
+    let mut rhs_loc = dm.create_local_vector()?;
+    rhs_loc
+        .view_mut()?
+        .as_slice_mut()
+        .ok_or_else(|| crate::Error {
+            message: "failed to access local RHS values".to_string(),
+        })?
+        .copy_from_slice(&rhs_loc_ceed.view()?);
+    let mut rhs = dm.create_global_vector()?;
+    rhs.zero_entries()?;
+    dm.local_to_global(&rhs_loc, InsertMode::ADD_VALUES, &mut rhs)?;
 
     // Return object
     Ok(crate::MelesMatShellContext {
+        ceed: meles.ceed.clone(),
         dm: RefCell::new(dm),
         x_loc: RefCell::new(x_loc),
         y_loc: RefCell::new(y_loc),
         x_loc_ceed: RefCell::new(x_loc_ceed),
         y_loc_ceed: RefCell::new(y_loc_ceed),
         op_ceed: RefCell::new(op_ceed),
+        diagonal: RefCell::new(None),
+        num_components,
+        point_block_inverse: RefCell::new(None),
+        rhs,
+        qdata,
+        true_solution,
+        error_ops,
+        single_precision: false,
+    })
+}
+
+// -----------------------------------------------------------------------------
+
+// -----------------------------------------------------------------------------
+// libCEED operator of the problem on the cells with one value of a label,
+//   with its own Ceed, for operators split by element blocks between Ceeds
+//   The DM is the problem DM, already set up, and the coordinates are the
+//   local mesh coordinates
+// -----------------------------------------------------------------------------
+pub(crate) fn label_operator<'a, 'b>(
+    ceed: &libceed::Ceed,
+    options: &Opt,
+    dm: &DM<'b, '_>,
+    coordinates: &[f64],
+    q_mode: libceed::QuadMode,
+    label: &'b DMLabel<'b>,
+    value: usize,
+) -> crate::Result<(
+    libceed::operator::Operator<'a>,
+    Option<crate::qdata::QDataVector<'a>>,
+)> {
+    let BPData {
+        num_components,
+        q_data_size,
+        setup_name,
+        apply_name,
+        input_name,
+        output_name,
+        q_mode: _,
+        set_boundary_conditions: _,
+    } = bp_data(options.problem)?;
+
+    // -- Bases and restrictions
+    let dimension = dm.dimension()?;
+    let q = options.order + 1 + options.q_extra;
+    let (basis_x, basis_u) = if crate::dm::plex_is_simplex(dm)? {
+        (
+            crate::dm::create_basis_simplex(ceed, dimension, dimension, 1, q)?,
+            crate::dm::create_basis_simplex(ceed, dimension, num_components, options.order, q)?,
+        )
+    } else {
+        (
+            ceed.basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?,
+            ceed.basis_tensor_H1_Lagrange(dimension, num_components, options.order + 1, q, q_mode)?,
+        )
+    };
+    let restr_u = crate::dm::create_restriction_from_dm_plex(dm, ceed, 0, label, value)?;
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, ceed, 0, label, value)?
+    };
+    let coord_loc_ceed = ceed.vector_from_slice(coordinates)?;
+
+    // -- Operator, with its qdata, if any
+    if options.on_the_fly_geometry {
+        let qf_apply = on_the_fly_q_function(ceed, dimension, options.problem, num_components)?;
+        let op = ceed
+            .operator(&qf_apply, QFunctionOpt::None, QFunctionOpt::None)?
+            .field(&input_name, &restr_u, &basis_u, VectorOpt::Active)?
+            .field("dx", &restr_x, &basis_x, &coord_loc_ceed)?
+            .field(
+                "weights",
+                ElemRestrictionOpt::None,
+                &basis_x,
+                VectorOpt::None,
+            )?
+            .field(&output_name, &restr_u, &basis_u, VectorOpt::Active)?
+            .check()?;
+        return Ok((op, None));
+    }
+    let qf_setup = ceed.q_function_interior_by_name(&setup_name)?;
+    let qf_apply = ApplyQFunction::Gallery(ceed.q_function_interior_by_name(&apply_name)?);
+    let (op, qdata) = create_bp_operator(
+        ceed,
+        &qf_setup,
+        &qf_apply,
+        q_data_size,
+        &[input_name],
+        &[output_name],
+        &basis_x,
+        &basis_u,
+        &restr_x,
+        &restr_u,
+        &coord_loc_ceed,
+    )?;
+    Ok((op, Some(qdata)))
+}
+
+// -----------------------------------------------------------------------------
+// Setup dm and a libCEED operator acting on element vectors
+//   The element operator maps E-vectors to E-vectors, ordered by element, then
+//   component, then node, so element matrices can be probed directly
+// -----------------------------------------------------------------------------
+pub(crate) struct ElementOperator<'a> {
+    pub(crate) dm: DM<'a, 'a>,
+    pub(crate) restr_u: ElemRestriction<'a>,
+    pub(crate) op_elem: libceed::operator::Operator<'a>,
+    pub(crate) num_elements: usize,
+    pub(crate) num_nodes_1d: usize,
+    pub(crate) num_components: usize,
+}
+
+pub(crate) fn element_operator<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &Opt,
+    region: Option<(&str, usize)>,
+) -> crate::Result<ElementOperator<'a>> {
+    let Opt {
+        problem,
+        order,
+        q_extra,
+        on_the_fly_geometry: _,
+        emulate_single_precision: _,
+        mesh_type,
+        refine: _,
+        local_dofs: _,
+        periodic: _,
+        mesh_transform: _,
+        kershaw_eps: _,
+        neumann: _,
+        essential_boundaries: _,
+        boundary_functions: _,
+        boundary_conditions: _,
+        neumann_boundaries: _,
+        neumann_flux: _,
+        u_bc: _,
+        coefficient: _,
+        operator: _,
+        diffusion_tensor: _,
+        diffusion_label: _,
+        wavenumber: _,
+        solution: _,
+    } = *options;
+    let BPData {
+        num_components,
+        q_data_size,
+        setup_name,
+        apply_name,
+        input_name,
+        output_name,
+        q_mode,
+        set_boundary_conditions: _,
+    } = bp_data(problem)?;
+    let q_mode = crate::backend::quadrature_mode(meles, q_mode);
+
+    // Duplicate DM
+    let mut dm = meles.dm.borrow().clone();
+    setup_dm(&mut dm, options)?;
+    if mesh_type != crate::dm::MeshType::Plex
+        || crate::dm::plex_cell_types(&dm)?.len() > 1
+        || crate::dm::plex_is_simplex(&dm)?
+    {
+        return Err(crate::Error {
+            message: "element operators require a plex mesh with a single tensor product cell type"
+                .to_string(),
+        });
+    }
+
+    // Create libCEED operator
+    // -- QFunction
+    let qf_setup = meles.ceed.q_function_interior_by_name(&setup_name)?;
+    let qf_apply = ApplyQFunction::Gallery(meles.ceed.q_function_interior_by_name(&apply_name)?);
+    // -- Coordinates
+    let mut coord_loc = {
+        let mut dm = meles.dm.borrow_mut();
+        dm.coordinates_local()?
+    };
+    let mut coord_loc_ceed = meles.ceed.vector(coord_loc.local_size()?)?;
+    // -- Basis and restrictions
+    let p = order + 1;
+    let q = p + q_extra;
+    let dimension = dm.dimension()?;
+    let basis_x = meles
+        .ceed
+        .basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?;
+    let basis_u = meles
+        .ceed
+        .basis_tensor_H1_Lagrange(dimension, num_components, p, q, q_mode)?;
+    // -- Only cells with the label value when a region is given
+    let region_label = match region {
+        Some((name, _)) => Some(dm.label(name)?.ok_or_else(|| crate::Error {
+            message: format!("missing label {} for region", name),
+        })?),
+        None => None,
+    };
+    let region_value = region.map(|(_, value)| value).unwrap_or(0);
+    let restr_u = crate::dm::create_restriction_from_dm_plex(
+        &dm,
+        &meles.ceed,
+        0,
+        region_label.as_ref(),
+        region_value,
+    )?;
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(
+            &mesh_coord_dm,
+            &meles.ceed,
+            0,
+            region_label.as_ref(),
+            region_value,
+        )?
+    };
+    let num_elements = restr_u.num_elements();
+    let num_nodes = basis_u.num_nodes();
+    let restr_elem = meles.ceed.strided_elem_restriction(
+        num_elements,
+        num_nodes,
+        num_components,
+        num_elements * num_nodes * num_components,
+        [1, num_nodes as i32, (num_nodes * num_components) as i32],
+    )?;
+    let (op_elem, _) =
+        crate::transfer::with_wrapped(&mut coord_loc, &mut coord_loc_ceed, |coord_loc_ceed| {
+            create_bp_operator(
+                &meles.ceed,
+                &qf_setup,
+                &qf_apply,
+                q_data_size,
+                &[input_name],
+                &[output_name],
+                &basis_x,
+                &basis_u,
+                &restr_x,
+                &restr_elem,
+                coord_loc_ceed,
+            )
+        })?;
+
+    Ok(ElementOperator {
+        dm,
+        restr_u,
+        op_elem,
+        num_elements,
+        num_nodes_1d: p,
+        num_components,
     })
 }
 
+// Probe the element matrices of an element operator, one column for all
+//   elements at once, returning row major matrices ordered by element
+pub(crate) fn probe_element_matrices(
+    ceed: &libceed::Ceed,
+    op_elem: &libceed::operator::Operator,
+    num_elements: usize,
+    elem_size: usize,
+) -> crate::Result<Vec<f64>> {
+    let mut element_matrices = vec![0.0; num_elements * elem_size * elem_size];
+    let mut x_elem = ceed.vector(num_elements * elem_size)?;
+    let mut y_elem = ceed.vector(num_elements * elem_size)?;
+    for col in 0..elem_size {
+        {
+            let mut x_elem_view = x_elem.view_mut()?;
+            for e in 0..num_elements {
+                for dof in 0..elem_size {
+                    x_elem_view[e * elem_size + dof] = if dof == col { 1.0 } else { 0.0 };
+                }
+            }
+        }
+        op_elem.apply(&x_elem, &mut y_elem)?;
+        let y_elem_view = y_elem.view()?;
+        for e in 0..num_elements {
+            for row in 0..elem_size {
+                element_matrices[(e * elem_size + row) * elem_size + col] =
+                    y_elem_view[e * elem_size + row];
+            }
+        }
+    }
+    Ok(element_matrices)
+}
+
+// -----------------------------------------------------------------------------
+// QFunction applying a benchmark problem operator
+// -----------------------------------------------------------------------------
+enum ApplyQFunction<'b> {
+    Gallery(QFunctionByName<'b>),
+    User(QFunction<'b>),
+}
+
+// -----------------------------------------------------------------------------
+// Build qdata with the setup operator
+// -----------------------------------------------------------------------------
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_qdata<'b>(
+    ceed: &libceed::Ceed,
+    qf_setup: &QFunctionByName<'b>,
+    q_data_size: usize,
+    basis_x: &Basis<'b>,
+    basis_u: &Basis<'b>,
+    restr_x: &ElemRestriction<'b>,
+    restr_u: &ElemRestriction<'b>,
+    coord_loc_ceed: &libceed::vector::Vector<'b>,
+) -> crate::Result<(ElemRestriction<'b>, crate::qdata::QDataVector<'b>)> {
+    let _event = crate::events::Event::QDataSetup.begin();
+
+    // -- Restriction
+    let num_elements = restr_u.num_elements();
+    let num_quadrature_points = basis_u.num_quadrature_points();
+    let restr_qdata = crate::qdata::QDataVector::restriction(
+        ceed,
+        num_elements,
+        num_quadrature_points,
+        q_data_size,
+    )?;
+    // -- Apply setup operator
+    let mut qdata = restr_qdata.create_lvector()?;
+    ceed.operator(qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("dx", restr_x, basis_x, VectorOpt::Active)?
+        .field(
+            "weights",
+            ElemRestrictionOpt::None,
+            basis_x,
+            VectorOpt::None,
+        )?
+        .field(
+            "qdata",
+            &restr_qdata,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?
+        .apply(coord_loc_ceed, &mut qdata)?;
+    Ok((
+        restr_qdata,
+        crate::qdata::QDataVector {
+            vector: std::rc::Rc::new(qdata),
+            num_elements,
+            num_quadrature_points,
+            size: q_data_size,
+        },
+    ))
+}
+
+// -----------------------------------------------------------------------------
+// Build qdata with the setup operator and return the apply operator and qdata
+// -----------------------------------------------------------------------------
+#[allow(clippy::too_many_arguments)]
+fn create_bp_operator<'b>(
+    ceed: &libceed::Ceed,
+    qf_setup: &QFunctionByName<'b>,
+    qf_apply: &ApplyQFunction<'b>,
+    q_data_size: usize,
+    input_names: &[String],
+    output_names: &[String],
+    basis_x: &Basis<'b>,
+    basis_u: &Basis<'b>,
+    restr_x: &ElemRestriction<'b>,
+    restr_u: &ElemRestriction<'b>,
+    coord_loc_ceed: &libceed::vector::Vector<'b>,
+) -> crate::Result<(
+    libceed::operator::Operator<'b>,
+    crate::qdata::QDataVector<'b>,
+)> {
+    let (restr_qdata, qdata) = create_qdata(
+        ceed,
+        qf_setup,
+        q_data_size,
+        basis_x,
+        basis_u,
+        restr_x,
+        restr_u,
+        coord_loc_ceed,
+    )?;
+    // -- Operator
+    let mut op_ceed = match qf_apply {
+        ApplyQFunction::Gallery(qf) => ceed.operator(qf, QFunctionOpt::None, QFunctionOpt::None)?,
+        ApplyQFunction::User(qf) => ceed.operator(qf, QFunctionOpt::None, QFunctionOpt::None)?,
+    };
+    for input_name in input_names {
+        op_ceed = op_ceed.field(input_name, restr_u, basis_u, VectorOpt::Active)?;
+    }
+    op_ceed = op_ceed.field("qdata", &restr_qdata, BasisOpt::Collocated, &*qdata.vector)?;
+    for output_name in output_names {
+        op_ceed = op_ceed.field(output_name, restr_u, basis_u, VectorOpt::Active)?;
+    }
+    let op_ceed = op_ceed.check()?;
+    Ok((op_ceed, qdata))
+}
+
 // -----------------------------------------------------------------------------
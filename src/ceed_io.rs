@@ -0,0 +1,212 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// CEED binary files
+//   A meles format, not one libCEED reads itself: an 8 byte magic, the number
+//   of dimensions and each dimension as u64, then the values as f64 in row
+//   major order, all little endian, so C code can read a file with fread into
+//   a 64 bit integer header and a CeedScalar array
+//   Vectors and quadrature data are rank local, so on more than one rank each
+//   rank writes and reads its own file with the rank appended to the path
+// -----------------------------------------------------------------------------
+const CEED_BINARY_MAGIC: &[u8; 8] = b"CEEDVEC1";
+
+// Path for this rank
+fn rank_path(path: &std::path::Path) -> std::path::PathBuf {
+    use mpi::traits::Communicator;
+    let world = mpi::topology::SimpleCommunicator::world();
+    if world.size() == 1 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", world.rank()));
+    name.into()
+}
+
+// Number of values, if it fits
+fn num_values(dimensions: &[usize]) -> Option<usize> {
+    dimensions
+        .iter()
+        .try_fold(1usize, |length, &dimension| length.checked_mul(dimension))
+}
+
+// Write dimensions and values
+fn write_binary(path: &std::path::Path, dimensions: &[usize], values: &[f64]) -> crate::Result<()> {
+    if num_values(dimensions) != Some(values.len()) {
+        return Err(crate::Error {
+            message: format!(
+                "CEED binary dimensions {:?} do not match {} values",
+                dimensions,
+                values.len()
+            ),
+        });
+    }
+    let mut bytes = Vec::with_capacity(16 + 8 * dimensions.len() + 8 * values.len());
+    bytes.extend_from_slice(CEED_BINARY_MAGIC);
+    bytes.extend_from_slice(&(dimensions.len() as u64).to_le_bytes());
+    for &dimension in dimensions {
+        bytes.extend_from_slice(&(dimension as u64).to_le_bytes());
+    }
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    std::fs::write(rank_path(path), bytes)?;
+    Ok(())
+}
+
+// Next little endian 8 byte word, if any
+fn take_word(bytes: &[u8], offset: &mut usize) -> Option<[u8; 8]> {
+    let word = bytes.get(*offset..*offset + 8)?;
+    *offset += 8;
+    let mut buffer = [0; 8];
+    buffer.copy_from_slice(word);
+    Some(buffer)
+}
+
+// Read dimensions and values
+fn read_binary(path: &std::path::Path) -> crate::Result<(Vec<usize>, Vec<f64>)> {
+    let path = &rank_path(path);
+    let bytes = std::fs::read(path)?;
+    let corrupt = || crate::Error {
+        message: format!("corrupt CEED binary file {}", path.display()),
+    };
+    let mut offset = 0;
+    if take_word(&bytes, &mut offset) != Some(*CEED_BINARY_MAGIC) {
+        return Err(corrupt());
+    }
+    let num_dimensions = u64::from_le_bytes(take_word(&bytes, &mut offset).ok_or_else(corrupt)?);
+    let mut dimensions = vec![];
+    for _ in 0..num_dimensions {
+        dimensions
+            .push(u64::from_le_bytes(take_word(&bytes, &mut offset).ok_or_else(corrupt)?) as usize);
+    }
+    let expected = num_values(&dimensions)
+        .and_then(|length| length.checked_mul(8))
+        .and_then(|length| length.checked_add(offset));
+    if expected != Some(bytes.len()) {
+        return Err(corrupt());
+    }
+    let values = bytes[offset..]
+        .chunks_exact(8)
+        .map(|chunk| {
+            let mut buffer = [0; 8];
+            buffer.copy_from_slice(chunk);
+            f64::from_le_bytes(buffer)
+        })
+        .collect();
+    Ok((dimensions, values))
+}
+
+// -----------------------------------------------------------------------------
+// CeedVector I/O
+// -----------------------------------------------------------------------------
+/// Write a CeedVector to a CEED binary file
+///
+/// The file holds an 8 byte `CEEDVEC1` magic, the number of dimensions and
+/// each dimension as 64 bit integers, one for a vector, and the values as
+/// 64 bit floats, all little endian.
+/// This is a meles format that C code can read with `fread`, not a libCEED
+/// one.
+/// On more than one rank, each rank writes its own file with the rank
+/// appended to the path.
+///
+/// # arguments
+///
+/// * `vector` - CeedVector to write
+/// * `path` - Filepath of the file to write
+pub fn write_ceed_vector(
+    vector: &libceed::vector::Vector,
+    path: impl AsRef<std::path::Path>,
+) -> crate::Result<()> {
+    let view = vector.view()?;
+    write_binary(path.as_ref(), &[view.len()], &view)
+}
+
+/// Read a CeedVector from a CEED binary file
+///
+/// Files with more than one dimension, such as quadrature data, are read as
+/// a flat vector.
+///
+/// # arguments
+///
+/// * `ceed` - Ceed context for the new vector
+/// * `path` - Filepath of the file to read
+pub fn read_ceed_vector<'a>(
+    ceed: &'a libceed::Ceed,
+    path: impl AsRef<std::path::Path>,
+) -> crate::Result<libceed::vector::Vector<'a>> {
+    let (_, values) = read_binary(path.as_ref())?;
+    Ok(ceed.vector_from_slice(&values)?)
+}
+
+// -----------------------------------------------------------------------------
+// QData I/O
+//   Dimensions are elements, components, and quadrature points, matching the
+//   strided layout of quadrature data in operators
+// -----------------------------------------------------------------------------
+impl crate::QData {
+    /// Write the quadrature data to a CEED binary file
+    ///
+    /// The dimensions are the number of elements, components, and quadrature
+    /// points, so the values are in the strided layout of libCEED operators.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let qdata = mat.mat_data().unwrap().qdata()?;
+    ///
+    /// // round trip
+    /// let path = std::env::temp_dir().join("meles_qdata.bin");
+    /// qdata[0].write_binary(&path)?;
+    /// let restored = meles::QData::read_binary(&path)?;
+    /// assert_eq!(restored, qdata[0], "incorrect quadrature data");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the file to write
+    pub fn write_binary(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        write_binary(
+            path.as_ref(),
+            &[self.num_elements, self.size, self.num_quadrature_points],
+            &self.values,
+        )
+    }
+
+    /// Read quadrature data from a CEED binary file
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the file to read
+    pub fn read_binary(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let (dimensions, values) = read_binary(path)?;
+        match dimensions[..] {
+            [num_elements, size, num_quadrature_points] => Ok(Self {
+                num_elements,
+                num_quadrature_points,
+                size,
+                values,
+            }),
+            _ => Err(crate::Error {
+                message: format!(
+                    "CEED binary file {} has dimensions {:?}, not elements, components, and quadrature points",
+                    path.display(),
+                    dimensions
+                ),
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
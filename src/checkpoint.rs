@@ -0,0 +1,339 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Solver state
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Scalar state of a time integrator or load stepping loop, stored with the
+///   solution in a checkpoint
+pub struct SolverState {
+    /// Current time of the time integrator
+    pub time: f64,
+    /// Current time step size
+    pub time_step: f64,
+    /// Number of completed time steps
+    pub step: usize,
+    /// Number of completed load steps
+    pub load_step: usize,
+    /// Current load factor
+    pub load_factor: f64,
+}
+
+impl SolverState {
+    /// Returns the time integrator state of a TS, with no load stepping
+    pub fn from_ts(ts: &TS) -> crate::Result<Self> {
+        let mut time = 0.0;
+        let mut time_step = 0.0;
+        let mut step = 0;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(TSGetTime(ts.as_raw(), &mut time), "TSGetTime")?;
+            check(TSGetTimeStep(ts.as_raw(), &mut time_step), "TSGetTimeStep")?;
+            check(TSGetStepNumber(ts.as_raw(), &mut step), "TSGetStepNumber")?;
+        }
+        Ok(Self {
+            time,
+            time_step,
+            step: step as usize,
+            ..Self::default()
+        })
+    }
+
+    /// Restore the time, time step size, and step number of a TS, so
+    ///   `TS::solve` resumes from this state
+    pub fn restore_ts(&self, ts: &mut TS) -> crate::Result<()> {
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(TSSetTime(ts.as_raw(), self.time), "TSSetTime")?;
+            check(TSSetTimeStep(ts.as_raw(), self.time_step), "TSSetTimeStep")?;
+            check(
+                TSSetStepNumber(ts.as_raw(), self.step as petsc::Int),
+                "TSSetStepNumber",
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// HDF5 checkpoints
+//   The solution and each block of each quadrature field are datasets, and
+//   the solver state is stored as attributes of the root group
+// -----------------------------------------------------------------------------
+const SOLUTION_NAME: &str = "solution";
+
+fn cstring(s: &str) -> crate::Result<std::ffi::CString> {
+    std::ffi::CString::new(s).map_err(|_| crate::Error {
+        message: format!("invalid checkpoint name {}", s),
+    })
+}
+
+fn field_dataset_name(name: &str, block: usize) -> String {
+    format!("quadrature_field_{}_{}", name, block)
+}
+
+// Open an HDF5 viewer on the communicator of a vector
+unsafe fn open_viewer(
+    vector: &petsc::vector::Vector,
+    path: &std::path::Path,
+    mode: petsc::petsc_raw::PetscFileMode,
+) -> crate::Result<petsc::petsc_raw::PetscViewer> {
+    use petsc::petsc_raw::*;
+    let filename = cstring(&path.to_string_lossy())?;
+    let mut comm = std::mem::zeroed();
+    crate::check_petsc_raw(
+        PetscObjectGetComm(vector.as_raw() as PetscObject, &mut comm),
+        "PetscObjectGetComm",
+    )?;
+    let mut viewer = std::ptr::null_mut();
+    crate::check_petsc_raw(
+        PetscViewerHDF5Open(comm, filename.as_ptr(), mode, &mut viewer),
+        "PetscViewerHDF5Open",
+    )?;
+    Ok(viewer)
+}
+
+// Duplicate of a vector with the dataset name, so the name of the vector of
+//   the caller is unchanged
+pub(crate) unsafe fn named_duplicate(
+    vector: &petsc::vector::Vector,
+    name: &std::ffi::CStr,
+) -> crate::Result<petsc::petsc_raw::Vec> {
+    use petsc::petsc_raw::*;
+    let check = crate::check_petsc_raw;
+    let mut duplicate = std::ptr::null_mut();
+    check(
+        VecDuplicate(vector.as_raw(), &mut duplicate),
+        "VecDuplicate",
+    )?;
+    let result = check(
+        PetscObjectSetName(duplicate as PetscObject, name.as_ptr()),
+        "PetscObjectSetName",
+    );
+    if let Err(err) = result {
+        check(VecDestroy(&mut duplicate), "VecDestroy")?;
+        return Err(err);
+    }
+    Ok(duplicate)
+}
+
+// Vector with one entry per local value, on the communicator of a template
+fn local_values_vector<'a>(
+    template: &petsc::vector::Vector<'a>,
+    name: &str,
+    length: usize,
+) -> crate::Result<petsc::petsc_raw::Vec> {
+    let name = cstring(name)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(template.as_raw() as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut vector = std::ptr::null_mut();
+        check(
+            VecCreateMPI(comm, length as petsc::Int, PETSC_DETERMINE, &mut vector),
+            "VecCreateMPI",
+        )?;
+        check(
+            PetscObjectSetName(vector as PetscObject, name.as_ptr()),
+            "PetscObjectSetName",
+        )?;
+        Ok(vector)
+    }
+}
+
+pub(crate) fn write_checkpoint(
+    meles: &crate::Meles,
+    path: &std::path::Path,
+    solution: &petsc::vector::Vector,
+    state: &SolverState,
+    fields: Option<&crate::QuadratureFields>,
+) -> crate::Result<()> {
+    let solution_name = cstring(SOLUTION_NAME)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut viewer = open_viewer(solution, path, PetscFileMode::FILE_MODE_WRITE)?;
+        let result = (|| {
+            // Solution
+            let mut named = named_duplicate(solution, &solution_name)?;
+            let result = check(VecCopy(solution.as_raw(), named), "VecCopy")
+                .and_then(|_| check(VecView(named, viewer), "VecView"));
+            check(VecDestroy(&mut named), "VecDestroy")?;
+            result?;
+
+            // Provenance
+            crate::manifest::write_hdf5_provenance(meles, viewer)?;
+
+            // Solver state
+            let reals = [
+                ("time", state.time),
+                ("time_step", state.time_step),
+                ("load_factor", state.load_factor),
+            ];
+            for (name, value) in &reals {
+                let name = cstring(name)?;
+                check(
+                    PetscViewerHDF5WriteAttribute(
+                        viewer,
+                        std::ptr::null(),
+                        name.as_ptr(),
+                        PetscDataType::PETSC_REAL,
+                        value as *const f64 as *const std::os::raw::c_void,
+                    ),
+                    "PetscViewerHDF5WriteAttribute",
+                )?;
+            }
+            let ints = [("step", state.step), ("load_step", state.load_step)];
+            for (name, value) in &ints {
+                let name = cstring(name)?;
+                let value = *value as petsc::Int;
+                check(
+                    PetscViewerHDF5WriteAttribute(
+                        viewer,
+                        std::ptr::null(),
+                        name.as_ptr(),
+                        PetscDataType::PETSC_INT,
+                        &value as *const petsc::Int as *const std::os::raw::c_void,
+                    ),
+                    "PetscViewerHDF5WriteAttribute",
+                )?;
+            }
+
+            // Accepted values of each quadrature field
+            if let Some(fields) = fields {
+                for name in fields.names() {
+                    let (_, blocks) = fields.accepted(name)?;
+                    for (block, values) in blocks.iter().enumerate() {
+                        let mut vector = local_values_vector(
+                            solution,
+                            &field_dataset_name(name, block),
+                            values.len(),
+                        )?;
+                        let mut array = std::ptr::null_mut();
+                        let result = check(VecGetArray(vector, &mut array), "VecGetArray")
+                            .and_then(|_| {
+                                std::slice::from_raw_parts_mut(array, values.len())
+                                    .copy_from_slice(values);
+                                check(VecRestoreArray(vector, &mut array), "VecRestoreArray")
+                            })
+                            .and_then(|_| check(VecView(vector, viewer), "VecView"));
+                        check(VecDestroy(&mut vector), "VecDestroy")?;
+                        result?;
+                    }
+                }
+            }
+            Ok(())
+        })();
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+pub(crate) fn read_checkpoint(
+    path: &std::path::Path,
+    solution: &mut petsc::vector::Vector,
+    fields: Option<&mut crate::QuadratureFields>,
+) -> crate::Result<SolverState> {
+    let solution_name = cstring(SOLUTION_NAME)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut viewer = open_viewer(solution, path, PetscFileMode::FILE_MODE_READ)?;
+        let result = (|| {
+            // Solution
+            let mut named = named_duplicate(solution, &solution_name)?;
+            let result = check(VecLoad(named, viewer), "VecLoad")
+                .and_then(|_| check(VecCopy(named, solution.as_raw()), "VecCopy"));
+            check(VecDestroy(&mut named), "VecDestroy")?;
+            result?;
+
+            // Solver state
+            let mut state = SolverState::default();
+            for (name, value) in [
+                ("time", &mut state.time),
+                ("time_step", &mut state.time_step),
+                ("load_factor", &mut state.load_factor),
+            ] {
+                let name = cstring(name)?;
+                check(
+                    PetscViewerHDF5ReadAttribute(
+                        viewer,
+                        std::ptr::null(),
+                        name.as_ptr(),
+                        PetscDataType::PETSC_REAL,
+                        std::ptr::null(),
+                        value as *mut f64 as *mut std::os::raw::c_void,
+                    ),
+                    "PetscViewerHDF5ReadAttribute",
+                )?;
+            }
+            for (name, value) in [
+                ("step", &mut state.step),
+                ("load_step", &mut state.load_step),
+            ] {
+                let name = cstring(name)?;
+                let mut int_value: petsc::Int = 0;
+                check(
+                    PetscViewerHDF5ReadAttribute(
+                        viewer,
+                        std::ptr::null(),
+                        name.as_ptr(),
+                        PetscDataType::PETSC_INT,
+                        std::ptr::null(),
+                        &mut int_value as *mut petsc::Int as *mut std::os::raw::c_void,
+                    ),
+                    "PetscViewerHDF5ReadAttribute",
+                )?;
+                *value = int_value as usize;
+            }
+
+            // Declared quadrature fields
+            if let Some(fields) = fields {
+                let layout = fields.layout().to_vec();
+                let names: Vec<String> = fields.names().iter().map(|s| s.to_string()).collect();
+                for name in names {
+                    let (size, _) = fields.accepted(&name)?;
+                    let mut blocks = vec![];
+                    for (block, &(num_elements, num_quadrature_points)) in layout.iter().enumerate()
+                    {
+                        let length = num_elements * num_quadrature_points * size;
+                        let mut vector = local_values_vector(
+                            solution,
+                            &field_dataset_name(&name, block),
+                            length,
+                        )?;
+                        let mut array = std::ptr::null_mut();
+                        let result = check(VecLoad(vector, viewer), "VecLoad")
+                            .and_then(|_| check(VecGetArray(vector, &mut array), "VecGetArray"))
+                            .map(|_| std::slice::from_raw_parts(array, length).to_vec())
+                            .and_then(|values| {
+                                check(VecRestoreArray(vector, &mut array), "VecRestoreArray")
+                                    .map(|_| values)
+                            });
+                        check(VecDestroy(&mut vector), "VecDestroy")?;
+                        blocks.push(result.map_err(|err| crate::Error {
+                            message: format!(
+                                "failed to read quadrature field {} from checkpoint {}: {}",
+                                name,
+                                path.display(),
+                                err
+                            ),
+                        })?);
+                    }
+                    fields.restore(&name, blocks)?;
+                }
+            }
+            Ok(state)
+        })();
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+// -----------------------------------------------------------------------------
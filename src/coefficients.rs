@@ -0,0 +1,732 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Create a cell-wise vector with one entry per local cell of the mesh
+// -----------------------------------------------------------------------------
+pub(crate) fn create_cell_vector<'a>(
+    meles: &'a crate::Meles<'a>,
+) -> crate::Result<petsc::vector::Vector<'a>> {
+    if meles.bp_options.mesh_type != crate::dm::MeshType::Plex {
+        return Err(crate::Error {
+            message: "cell-wise coefficients require a plex mesh".to_string(),
+        });
+    }
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(&meles.dm.borrow())?;
+    let mut vector = petsc::vector::Vector::create(meles.comm)?;
+    vector.set_sizes(Some(cell_end - cell_start), None)?;
+    vector.set_up()?;
+    vector.set_all(1.0)?;
+    Ok(vector)
+}
+
+// -----------------------------------------------------------------------------
+// Create a cell-wise vector from an expression evaluated at cell centroids
+// -----------------------------------------------------------------------------
+pub(crate) fn cell_vector_from_expression<'a>(
+    meles: &'a crate::Meles<'a>,
+    dm: &DM,
+    expression: &crate::Expression,
+) -> crate::Result<petsc::vector::Vector<'a>> {
+    let mut vector = create_cell_vector(meles)?;
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(dm)?;
+    let mut view = vector.view_mut()?;
+    for cell in cell_start..cell_end {
+        let mut volume = 0.0;
+        let mut centroid = [0.0; 3];
+        let mut normal = [0.0; 3];
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::DMPlexComputeCellGeometryFVM(
+                    dm.as_raw(),
+                    cell,
+                    &mut volume,
+                    centroid.as_mut_ptr(),
+                    normal.as_mut_ptr(),
+                )
+            },
+            "DMPlexComputeCellGeometryFVM",
+        )?;
+        view[(cell - cell_start) as usize] = expression.evaluate(&centroid, 0.0);
+    }
+    drop(view);
+    Ok(vector)
+}
+
+// -----------------------------------------------------------------------------
+// Scale the quadrature data of each element by a piecewise constant
+//   coefficient, expanding the cell value to every quadrature point
+// -----------------------------------------------------------------------------
+pub(crate) fn scale_qdata_by_cell(
+    context: &mut crate::MelesMatShellContext,
+    coefficient: &petsc::vector::Vector,
+) -> crate::Result<()> {
+    if context.qdata.len() != 1 {
+        return Err(crate::Error {
+            message: "cell-wise coefficients require a mesh with a single cell type".to_string(),
+        });
+    }
+    let qdata = &mut context.qdata[0];
+    let num_cells = coefficient.local_size()? as usize;
+    if num_cells != qdata.num_elements {
+        return Err(crate::Error {
+            message: format!(
+                "cell-wise coefficient has {} local cells, not {}",
+                num_cells, qdata.num_elements
+            ),
+        });
+    }
+
+    let element_size = qdata.num_quadrature_points * qdata.size;
+    let coefficient_view = coefficient.view()?;
+    let vector = qdata.vector_mut()?;
+    let mut qdata_view = vector.view_mut()?;
+    for (element, values) in qdata_view.chunks_exact_mut(element_size).enumerate() {
+        for value in values {
+            *value *= coefficient_view[element];
+        }
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Coefficient fields
+//   Evaluated at the quadrature points of the operator, so the coefficient
+//   varies within each cell rather than by cell
+// -----------------------------------------------------------------------------
+/// Spatially varying coefficient, evaluated at quadrature points
+pub enum CoefficientField<'c> {
+    /// Function of the physical coordinates, `f(x) -> kappa`
+    Function(Box<dyn Fn(&[f64; 3]) -> f64>),
+    /// Global vector on the problem DM of a scalar problem, interpolated with
+    ///   the problem basis
+    Vector(&'c petsc::vector::Vector<'c>),
+}
+
+// Coefficient values at each quadrature point of each element, with the
+//   layout of single component quadrature data
+pub(crate) fn quadrature_coefficient(
+    meles: &crate::Meles,
+    context: &crate::MelesMatShellContext,
+    coefficient: CoefficientField,
+) -> crate::Result<Vec<f64>> {
+    let options = &meles.bp_options;
+    crate::backend::require_closure_q_functions(meles, "coefficient fields")?;
+    if options.mesh_type != crate::dm::MeshType::Plex || context.qdata.len() != 1 {
+        return Err(crate::Error {
+            message: "coefficient fields require a plex mesh with a single cell type".to_string(),
+        });
+    }
+    let qdata = &context.qdata[0];
+    let dm = context.dm.borrow();
+    let dimension = dm.dimension()?;
+
+    // Basis matching the operator quadrature
+    let p = options.order + 1;
+    let q = p + options.q_extra;
+    let q_mode =
+        crate::backend::quadrature_mode(meles, crate::ceed_bps::bp_data(options.problem)?.q_mode);
+    let restr_coefficient = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        qdata.num_elements,
+        qdata.num_quadrature_points,
+        1,
+    )?;
+    let mut values = restr_coefficient.create_lvector()?;
+
+    match coefficient {
+        CoefficientField::Function(f) => {
+            let basis_x = meles
+                .ceed
+                .basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?;
+            let restr_x = {
+                let mesh_coord_dm = dm.coordinate_dm()?;
+                crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+            };
+            let coord_loc_ceed = {
+                let coord_loc = {
+                    let mut dm = meles.dm.borrow_mut();
+                    dm.coordinates_local()?
+                };
+                crate::transfer::owned_copy(&meles.ceed, &coord_loc)?
+            };
+            let evaluate = move |[x, ..]: QFunctionInputs, [kappa, ..]: QFunctionOutputs| {
+                let num_points = kappa.len();
+                for (i, kappa_i) in kappa.iter_mut().enumerate() {
+                    let mut x_i = [0.0; 3];
+                    for (d, x_d) in x_i.iter_mut().take(dimension).enumerate() {
+                        *x_d = x[d * num_points + i];
+                    }
+                    *kappa_i = f(&x_i);
+                }
+                0
+            };
+            let qf = meles
+                .ceed
+                .q_function_interior(1, Box::new(evaluate))?
+                .input("x", dimension, EvalMode::Interp)?
+                .output("kappa", 1, EvalMode::None)?;
+            meles
+                .ceed
+                .operator(&qf, QFunctionOpt::None, QFunctionOpt::None)?
+                .field("x", &restr_x, &basis_x, VectorOpt::Active)?
+                .field(
+                    "kappa",
+                    &restr_coefficient,
+                    BasisOpt::Collocated,
+                    VectorOpt::Active,
+                )?
+                .check()?
+                .apply(&coord_loc_ceed, &mut values)?;
+        }
+        CoefficientField::Vector(vector) => {
+            if context.num_components != 1 {
+                return Err(crate::Error {
+                    message: format!(
+                        "coefficient vectors require a scalar problem, not {} components",
+                        context.num_components
+                    ),
+                });
+            }
+            let mut vector_loc = dm.create_local_vector()?;
+            dm.global_to_local(vector, InsertMode::INSERT_VALUES, &mut vector_loc)?;
+            let vector_loc_ceed = {
+                let vector_loc_view = vector_loc.view()?;
+                meles.ceed.vector_from_slice(&vector_loc_view)?
+            };
+            let basis_u = meles
+                .ceed
+                .basis_tensor_H1_Lagrange(dimension, 1, p, q, q_mode)?;
+            let restr_u = crate::dm::create_restriction_from_dm_plex(&dm, &meles.ceed, 0, None, 0)?;
+            let copy = |[u, ..]: QFunctionInputs, [kappa, ..]: QFunctionOutputs| {
+                let num_points = kappa.len();
+                kappa.copy_from_slice(&u[..num_points]);
+                0
+            };
+            let qf = meles
+                .ceed
+                .q_function_interior(1, Box::new(copy))?
+                .input("u", 1, EvalMode::Interp)?
+                .output("kappa", 1, EvalMode::None)?;
+            meles
+                .ceed
+                .operator(&qf, QFunctionOpt::None, QFunctionOpt::None)?
+                .field("u", &restr_u, &basis_u, VectorOpt::Active)?
+                .field(
+                    "kappa",
+                    &restr_coefficient,
+                    BasisOpt::Collocated,
+                    VectorOpt::Active,
+                )?
+                .check()?
+                .apply(&vector_loc_ceed, &mut values)?;
+        }
+    }
+    let values = values.view()?.to_vec();
+    Ok(values)
+}
+
+// -----------------------------------------------------------------------------
+// Scale the quadrature data at each quadrature point by a coefficient
+// -----------------------------------------------------------------------------
+pub(crate) fn scale_qdata_by_quadrature_point(
+    context: &mut crate::MelesMatShellContext,
+    coefficient: &[f64],
+) -> crate::Result<()> {
+    let qdata = &mut context.qdata[0];
+    let num_quadrature_points = qdata.num_quadrature_points;
+    if coefficient.len() != qdata.num_elements * num_quadrature_points {
+        return Err(crate::Error {
+            message: format!(
+                "coefficient has {} quadrature point values, not {}",
+                coefficient.len(),
+                qdata.num_elements * num_quadrature_points
+            ),
+        });
+    }
+
+    let element_size = num_quadrature_points * qdata.size;
+    let vector = qdata.vector_mut()?;
+    let mut qdata_view = vector.view_mut()?;
+    for (values, element_coefficient) in qdata_view
+        .chunks_exact_mut(element_size)
+        .zip(coefficient.chunks_exact(num_quadrature_points))
+    {
+        for component in values.chunks_exact_mut(num_quadrature_points) {
+            for (value, kappa) in component.iter_mut().zip(element_coefficient) {
+                *value *= kappa;
+            }
+        }
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Anisotropic diffusion tensors
+//   Symmetric tensors in the order xx, yy, zz, yz, xz, xy, matching the order
+//   of the diffusion quadrature data. The quadrature data for a tensor K is
+//   w det(J) J^{-1} K J^{-T}, so it is rebuilt from the coordinates rather
+//   than scaled.
+// -----------------------------------------------------------------------------
+pub(crate) struct DiffusionTensors {
+    default: [f64; 6],
+    // Label value of each local cell, in cell order
+    cell_regions: Vec<i32>,
+    regions: Vec<(i32, [f64; 6])>,
+}
+
+impl DiffusionTensors {
+    fn tensor(&self, cell: usize) -> &[f64; 6] {
+        let region = self.cell_regions[cell];
+        self.regions
+            .iter()
+            .find(|(value, _)| *value == region)
+            .map_or(&self.default, |(_, tensor)| tensor)
+    }
+}
+
+// Parse a symmetric positive definite tensor, or an isotropic value
+fn parse_diffusion_tensor(name: &str, tensor: &str) -> crate::Result<[f64; 6]> {
+    let values = tensor
+        .split(',')
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<std::result::Result<Vec<f64>, _>>()
+        .map_err(|err| crate::Error {
+            message: format!("invalid diffusion tensor {} for {}: {}", tensor, name, err),
+        })?;
+    let k = match values[..] {
+        [k] => [k, k, k, 0.0, 0.0, 0.0],
+        [xx, yy, zz, yz, xz, xy] => [xx, yy, zz, yz, xz, xy],
+        _ => {
+            return Err(crate::Error {
+                message: format!(
+                    "diffusion tensor for {} requires 1 or 6 values, not {}",
+                    name,
+                    values.len()
+                ),
+            })
+        }
+    };
+    // Sylvester's criterion
+    let minor_2 = k[0] * k[1] - k[5] * k[5];
+    let det = k[0] * (k[1] * k[2] - k[3] * k[3]) - k[5] * (k[5] * k[2] - k[3] * k[4])
+        + k[4] * (k[5] * k[3] - k[1] * k[4]);
+    if k[0] <= 0.0 || minor_2 <= 0.0 || det <= 0.0 {
+        return Err(crate::Error {
+            message: format!(
+                "diffusion tensor {} for {} is not positive definite",
+                tensor, name
+            ),
+        });
+    }
+    Ok(k)
+}
+
+// Diffusion tensors from the options database, or None if none are set
+//   Each region of the label may set -diffusion_tensor_[value], and other
+//   cells use -diffusion_tensor, or the identity
+pub(crate) fn diffusion_tensors(
+    dm: &DM,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<Option<DiffusionTensors>> {
+    let default = match &options.diffusion_tensor {
+        Some(tensor) => Some(parse_diffusion_tensor("-diffusion_tensor", tensor)?),
+        None => None,
+    };
+
+    // Region of each cell
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(dm)?;
+    let label_name =
+        std::ffi::CString::new(options.diffusion_label.as_str()).map_err(|_| crate::Error {
+            message: format!("invalid label name {}", options.diffusion_label),
+        })?;
+    let has_label = dm.label(&options.diffusion_label)?.is_some();
+    let mut cell_regions = Vec::with_capacity((cell_end - cell_start) as usize);
+    for cell in cell_start..cell_end {
+        let mut value = -1;
+        if has_label {
+            crate::check_petsc_raw(
+                unsafe {
+                    petsc::petsc_raw::DMGetLabelValue(
+                        dm.as_raw(),
+                        label_name.as_ptr(),
+                        cell,
+                        &mut value,
+                    )
+                },
+                "DMGetLabelValue",
+            )?;
+        }
+        cell_regions.push(value as i32);
+    }
+
+    // Tensors set for the regions present
+    let mut values = cell_regions.clone();
+    values.sort_unstable();
+    values.dedup();
+    let mut regions = vec![];
+    {
+        let _options_lock = crate::config::options_lock();
+        for value in values.into_iter().filter(|&value| value >= 0) {
+            let name = format!("-diffusion_tensor_{}", value);
+            let option = std::ffi::CString::new(name.as_str()).unwrap();
+            let mut buffer = [0 as std::os::raw::c_char; 256];
+            let mut set = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+            crate::check_petsc_raw(
+                unsafe {
+                    petsc::petsc_raw::PetscOptionsGetString(
+                        std::ptr::null_mut(),
+                        std::ptr::null(),
+                        option.as_ptr(),
+                        buffer.as_mut_ptr(),
+                        buffer.len(),
+                        &mut set,
+                    )
+                },
+                "PetscOptionsGetString",
+            )?;
+            if set == petsc::petsc_raw::PetscBool::PETSC_TRUE {
+                let tensor = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+                regions.push((
+                    value as i32,
+                    parse_diffusion_tensor(&name, &tensor.to_string_lossy())?,
+                ));
+            }
+        }
+    }
+
+    if default.is_none() && regions.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(DiffusionTensors {
+        default: default.unwrap_or([1.0, 1.0, 1.0, 0.0, 0.0, 0.0]),
+        cell_regions,
+        regions,
+    }))
+}
+
+// Rebuild the diffusion quadrature data with the tensor of each cell
+pub(crate) fn apply_diffusion_tensors(
+    meles: &crate::Meles,
+    context: &mut crate::MelesMatShellContext,
+    tensors: &DiffusionTensors,
+) -> crate::Result<()> {
+    let options = &meles.bp_options;
+    crate::backend::require_closure_q_functions(meles, "diffusion tensors")?;
+    let bp_data = crate::ceed_bps::bp_data(options.problem)?;
+    if options.mesh_type != crate::dm::MeshType::Plex
+        || context.qdata.len() != 1
+        || context.qdata[0].size != 6
+    {
+        return Err(crate::Error {
+            message: "diffusion tensors require a diffusion problem, BP3-BP6 or helmholtz, on a plex mesh with a single cell type".to_string(),
+        });
+    }
+    let dm = context.dm.borrow();
+    let dimension = dm.dimension()?;
+    if dimension != 3 {
+        return Err(crate::Error {
+            message: "diffusion tensors require a 3D mesh".to_string(),
+        });
+    }
+    let (num_elements, num_quadrature_points) = {
+        let qdata = &context.qdata[0];
+        (qdata.num_elements, qdata.num_quadrature_points)
+    };
+    if tensors.cell_regions.len() != num_elements {
+        return Err(crate::Error {
+            message: format!(
+                "diffusion tensor regions have {} local cells, not {}",
+                tensors.cell_regions.len(),
+                num_elements
+            ),
+        });
+    }
+
+    // Tensor at each quadrature point
+    let restr_tensor = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        num_elements,
+        num_quadrature_points,
+        6,
+    )?;
+    let mut tensor_values = vec![0.0; num_elements * num_quadrature_points * 6];
+    for (element, values) in tensor_values
+        .chunks_exact_mut(num_quadrature_points * 6)
+        .enumerate()
+    {
+        let tensor = tensors.tensor(element);
+        for (component, values) in values.chunks_exact_mut(num_quadrature_points).enumerate() {
+            values.fill(tensor[component]);
+        }
+    }
+    let tensor_ceed = meles.ceed.vector_from_slice(&tensor_values)?;
+
+    // Setup operator
+    let p = options.order + 1;
+    let q = p + options.q_extra;
+    let q_mode = crate::backend::quadrature_mode(meles, bp_data.q_mode);
+    let basis_x = meles
+        .ceed
+        .basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?;
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+    };
+    let coord_loc_ceed = {
+        let coord_loc = {
+            let mut dm = meles.dm.borrow_mut();
+            dm.coordinates_local()?
+        };
+        crate::transfer::owned_copy(&meles.ceed, &coord_loc)?
+    };
+    let setup = |[dx, weights, k, ..]: QFunctionInputs, [q_data, ..]: QFunctionOutputs| {
+        let num_points = weights.len();
+        for (i, weight) in weights.iter().enumerate() {
+            let j = |c: usize, d: usize| dx[(d * 3 + c) * num_points + i];
+            // Adjugate of J
+            let mut adj = [[0.0; 3]; 3];
+            for (r, row) in adj.iter_mut().enumerate() {
+                for (c, value) in row.iter_mut().enumerate() {
+                    let (r1, r2) = ((c + 1) % 3, (c + 2) % 3);
+                    let (c1, c2) = ((r + 1) % 3, (r + 2) % 3);
+                    *value = j(r1, c1) * j(r2, c2) - j(r1, c2) * j(r2, c1);
+                }
+            }
+            let det = j(0, 0) * adj[0][0] + j(0, 1) * adj[1][0] + j(0, 2) * adj[2][0];
+            let k_i = |a: usize, b: usize| {
+                let index = match (a.min(b), a.max(b)) {
+                    (0, 0) => 0,
+                    (1, 1) => 1,
+                    (2, 2) => 2,
+                    (1, 2) => 3,
+                    (0, 2) => 4,
+                    _ => 5,
+                };
+                k[index * num_points + i]
+            };
+            // w det(J) J^{-1} K J^{-T} = w adj(J) K adj(J)^T / det(J)
+            let scale = weight / det;
+            for (index, (a, b)) in [(0, 0), (1, 1), (2, 2), (1, 2), (0, 2), (0, 1)]
+                .iter()
+                .enumerate()
+            {
+                let mut value = 0.0;
+                for (c, adj_ac) in adj[*a].iter().enumerate() {
+                    for (d, adj_bd) in adj[*b].iter().enumerate() {
+                        value += adj_ac * k_i(c, d) * adj_bd;
+                    }
+                }
+                q_data[index * num_points + i] = scale * value;
+            }
+        }
+        0
+    };
+    let qf_setup = meles
+        .ceed
+        .q_function_interior(1, Box::new(setup))?
+        .input("dx", 9, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .input("tensor", 6, EvalMode::None)?
+        .output("qdata", 6, EvalMode::None)?;
+    let mut tensor_qdata = restr_tensor.create_lvector()?;
+    meles
+        .ceed
+        .operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("dx", &restr_x, &basis_x, VectorOpt::Active)?
+        .field(
+            "weights",
+            ElemRestrictionOpt::None,
+            &basis_x,
+            VectorOpt::None,
+        )?
+        .field("tensor", &restr_tensor, BasisOpt::Collocated, &tensor_ceed)?
+        .field(
+            "qdata",
+            &restr_tensor,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?
+        .apply(&coord_loc_ceed, &mut tensor_qdata)?;
+    drop(dm);
+
+    // Replace the operator quadrature data
+    let qdata = &mut context.qdata[0];
+    let vector = qdata.vector_mut()?;
+    let mut qdata_view = vector.view_mut()?;
+    qdata_view.copy_from_slice(&tensor_qdata.view()?);
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Coefficient files
+//   PETSc binary files, or HDF5 files with a `.h5` extension, holding a vector
+//   named `coefficient`
+// -----------------------------------------------------------------------------
+const COEFFICIENT_NAME: &str = "coefficient";
+
+// Open a viewer for a coefficient file
+unsafe fn open_viewer(
+    comm: petsc::petsc_raw::MPI_Comm,
+    path: &std::path::Path,
+    mode: petsc::petsc_raw::PetscFileMode,
+) -> crate::Result<petsc::petsc_raw::PetscViewer> {
+    let filename =
+        std::ffi::CString::new(path.to_string_lossy().as_bytes()).map_err(|_| crate::Error {
+            message: format!("invalid coefficient file path {}", path.display()),
+        })?;
+    let mut viewer = std::ptr::null_mut();
+    if path.extension().is_some_and(|extension| extension == "h5") {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscViewerHDF5Open(comm, filename.as_ptr(), mode, &mut viewer),
+            "PetscViewerHDF5Open",
+        )?;
+    } else {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscViewerBinaryOpen(comm, filename.as_ptr(), mode, &mut viewer),
+            "PetscViewerBinaryOpen",
+        )?;
+    }
+    Ok(viewer)
+}
+
+// Load a vector from a coefficient file, using the layout of the vector if set
+fn load_vector(vector: petsc::petsc_raw::Vec, path: &std::path::Path) -> crate::Result<()> {
+    let name = std::ffi::CString::new(COEFFICIENT_NAME).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(vector as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        check(
+            PetscObjectSetName(vector as PetscObject, name.as_ptr()),
+            "PetscObjectSetName",
+        )?;
+        let mut viewer = open_viewer(comm, path, PetscFileMode::FILE_MODE_READ)?;
+        let result = check(VecLoad(vector, viewer), "VecLoad");
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+// Global size of the vector in a coefficient file
+fn file_vector_size(
+    template: &petsc::vector::Vector,
+    path: &std::path::Path,
+) -> crate::Result<usize> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(template.as_raw() as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut vector = std::ptr::null_mut();
+        check(VecCreate(comm, &mut vector), "VecCreate")?;
+        let mut size = 0;
+        let result = load_vector(vector, path)
+            .and_then(|_| check(VecGetSize(vector, &mut size), "VecGetSize"));
+        check(VecDestroy(&mut vector), "VecDestroy")?;
+        result?;
+        Ok(size as usize)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Write a coefficient vector to a file
+// -----------------------------------------------------------------------------
+pub(crate) fn save_coefficient(
+    coefficient: &petsc::vector::Vector,
+    path: &std::path::Path,
+) -> crate::Result<()> {
+    let name = std::ffi::CString::new(COEFFICIENT_NAME).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let vector = coefficient.as_raw();
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(vector as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        check(
+            PetscObjectSetName(vector as PetscObject, name.as_ptr()),
+            "PetscObjectSetName",
+        )?;
+        let mut viewer = open_viewer(comm, path, PetscFileMode::FILE_MODE_WRITE)?;
+        let result = check(VecView(vector, viewer), "VecView");
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Load a cell-wise coefficient from a file
+//   Cell-wise files are used directly; vertex fields, such as a linear
+//   coefficient from another discretization, are projected onto piecewise
+//   constants by averaging the vertex values of each cell
+// -----------------------------------------------------------------------------
+pub(crate) fn load_cell_coefficient<'a>(
+    meles: &'a crate::Meles<'a>,
+    path: &std::path::Path,
+) -> crate::Result<petsc::vector::Vector<'a>> {
+    let mut coefficient = create_cell_vector(meles)?;
+    let num_cells = coefficient.global_size()? as usize;
+    let file_size = file_vector_size(&coefficient, path)?;
+
+    // Cell-wise coefficient
+    if file_size == num_cells {
+        load_vector(coefficient.as_raw(), path)?;
+        return Ok(coefficient);
+    }
+
+    // Vertex coefficient
+    let mut vertex_dm = meles.dm.borrow().clone();
+    crate::dm::setup_dm_vertex_section(&mut vertex_dm, 1)?;
+    let vertex_coefficient = vertex_dm.create_global_vector()?;
+    if file_size != vertex_coefficient.global_size()? as usize {
+        return Err(crate::Error {
+            message: format!(
+                "coefficient file {} has {} values, expected {} cells or {} vertices",
+                path.display(),
+                file_size,
+                num_cells,
+                vertex_coefficient.global_size()?
+            ),
+        });
+    }
+    load_vector(vertex_coefficient.as_raw(), path)?;
+    let mut vertex_coefficient_loc = vertex_dm.create_local_vector()?;
+    vertex_dm.global_to_local(
+        &vertex_coefficient,
+        InsertMode::INSERT_VALUES,
+        &mut vertex_coefficient_loc,
+    )?;
+
+    // Project onto piecewise constants
+    let restr_vertex =
+        crate::dm::create_restriction_from_dm_plex(&vertex_dm, &meles.ceed, 0, None, 0)?;
+    let mut coefficient_elem = restr_vertex.create_evector()?;
+    {
+        let mut loc_ceed = meles
+            .ceed
+            .vector(vertex_coefficient_loc.local_size()? as usize)?;
+        crate::transfer::with_wrapped(&mut vertex_coefficient_loc, &mut loc_ceed, |loc_ceed| {
+            Ok(restr_vertex.apply(TransposeMode::NoTranspose, loc_ceed, &mut coefficient_elem)?)
+        })?;
+    }
+    let elem_view = coefficient_elem.view()?;
+    let elem_size = elem_view.len() / coefficient.local_size()? as usize;
+    let mut coefficient_view = coefficient.view_mut()?;
+    for (cell, vertex_values) in elem_view.chunks_exact(elem_size).enumerate() {
+        coefficient_view[cell] = vertex_values.iter().sum::<f64>() / elem_size as f64;
+    }
+    drop(coefficient_view);
+    Ok(coefficient)
+}
+
+// -----------------------------------------------------------------------------
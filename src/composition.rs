@@ -0,0 +1,442 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Operator expressions
+//   operator := term (('+' | '-') term)*
+//   term     := ('+' | '-')? factor ('*' factor)*
+//   factor   := number | name
+//   Each term names exactly one sub-operator, and repeated names are combined
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OperatorTerm {
+    Mass,
+    Stiffness,
+}
+
+impl std::str::FromStr for OperatorTerm {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "mass" => Ok(OperatorTerm::Mass),
+            "stiffness" => Ok(OperatorTerm::Stiffness),
+            _ => Err(crate::Error {
+                message: format!("unknown operator {}, expected mass or stiffness", s),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for OperatorTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OperatorTerm::Mass => write!(f, "mass"),
+            OperatorTerm::Stiffness => write!(f, "stiffness"),
+        }
+    }
+}
+
+impl OperatorTerm {
+    // Benchmark problem QFunctions of the term, with the number of components
+    //   of the problem; diffusion problems keep their own quadrature
+    fn kernel(self, options: &crate::ceed_bps::Opt) -> crate::Result<crate::ceed_bps::CeedBP> {
+        use crate::ceed_bps::CeedBP;
+        match self {
+            OperatorTerm::Mass => crate::ceed_bps::mass_problem(options),
+            OperatorTerm::Stiffness => match options.problem {
+                CeedBP::BP3 | CeedBP::BP4 | CeedBP::BP5 | CeedBP::BP6 => Ok(options.problem),
+                _ => match crate::ceed_bps::mass_problem(options)? {
+                    CeedBP::BP1 => Ok(CeedBP::BP3),
+                    _ => Ok(CeedBP::BP4),
+                },
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OperatorExpression {
+    terms: Vec<(f64, OperatorTerm)>,
+}
+
+impl OperatorExpression {
+    pub(crate) fn parse(source: &str) -> crate::Result<Self> {
+        use crate::expression::Token;
+        let error = |detail: &str| crate::Error {
+            message: format!("invalid operator expression {}: {}", source, detail),
+        };
+        let tokens = crate::expression::tokenize(source)?;
+        let mut terms: Vec<(f64, OperatorTerm)> = vec![];
+        let mut tokens = tokens.iter().peekable();
+        while tokens.peek().is_some() {
+            // Sign, required between terms
+            let mut coefficient = match tokens.peek() {
+                Some(Token::Operator('-')) => {
+                    tokens.next();
+                    -1.0
+                }
+                Some(Token::Operator('+')) => {
+                    tokens.next();
+                    1.0
+                }
+                _ if terms.is_empty() => 1.0,
+                _ => return Err(error("expected + or - between terms")),
+            };
+            // Factors
+            let mut term = None;
+            loop {
+                match tokens.next() {
+                    Some(Token::Number(value)) => coefficient *= value,
+                    Some(Token::Identifier(name)) if term.is_none() => {
+                        term = Some(name.parse::<OperatorTerm>()?)
+                    }
+                    Some(Token::Identifier(_)) => {
+                        return Err(error("terms name one operator each"))
+                    }
+                    _ => return Err(error("expected a number or operator name")),
+                }
+                match tokens.peek() {
+                    Some(Token::Operator('*')) => {
+                        tokens.next();
+                    }
+                    _ => break,
+                }
+            }
+            let term = term.ok_or_else(|| error("each term must name an operator"))?;
+            match terms.iter_mut().find(|(_, existing)| *existing == term) {
+                Some((existing, _)) => *existing += coefficient,
+                None => terms.push((coefficient, term)),
+            }
+        }
+        if terms.is_empty() {
+            return Err(error("no terms"));
+        }
+        Ok(Self { terms })
+    }
+
+    // Returns true if the expression has a nonzero mass term
+    pub(crate) fn has_mass(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|&(coefficient, term)| term == OperatorTerm::Mass && coefficient != 0.0)
+    }
+
+    // Returns true if the expression has a nonzero stiffness term
+    pub(crate) fn has_stiffness(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|&(coefficient, term)| term == OperatorTerm::Stiffness && coefficient != 0.0)
+    }
+
+    // Coefficients and benchmark problem QFunctions of each term, also used
+    //   for the forcing of the manufactured solution
+    //   Essential boundary conditions follow the problem, and the forcing has
+    //   no boundary flux, so stiffness terms need a problem with essential
+    //   boundaries, or a periodic mesh
+    pub(crate) fn kernels(
+        &self,
+        options: &crate::ceed_bps::Opt,
+    ) -> crate::Result<Vec<(f64, crate::ceed_bps::CeedBP)>> {
+        if self.has_stiffness()
+            && !options.periodic
+            && !crate::ceed_bps::has_essential_boundary(options)?
+        {
+            return Err(crate::Error {
+                message: format!(
+                    "operator expressions with stiffness terms require essential boundary conditions or a periodic mesh, as the forcing has no boundary flux; {} sets none",
+                    options.problem
+                ),
+            });
+        }
+        self.terms
+            .iter()
+            .map(|&(coefficient, term)| Ok((coefficient, term.kernel(options)?)))
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Composite operator from an expression
+//   Each term is set up on the problem DM, so all act on the same local
+//   vectors, with its quadrature data scaled by its coefficient; the terms are
+//   fused into one libCEED composite operator, which applies each term in
+//   turn and assembles the combined diagonal, unless a term is itself
+//   composite
+// -----------------------------------------------------------------------------
+// Set up the operator of each term, returning the context of the first term,
+//   which holds the quadrature data of every term, and the term operators;
+//   only the first term is set up with the forcing and error operators, if
+//   requested
+fn term_operators<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    kernels: &[(f64, crate::ceed_bps::CeedBP)],
+    scale_qdata: bool,
+    forcing: bool,
+) -> crate::Result<(
+    crate::MelesMatShellContext<'a>,
+    Vec<crate::MelesOperator<'a>>,
+)> {
+    if let Some(feature) = [
+        (options.coefficient.is_some(), "coefficients"),
+        (options.diffusion_tensor.is_some(), "diffusion tensors"),
+        (
+            options.problem == crate::ceed_bps::CeedBP::Helmholtz,
+            "the Helmholtz problem",
+        ),
+        (meles.split.is_some(), "split execution"),
+    ]
+    .iter()
+    .find_map(|&(used, feature)| if used { Some(feature) } else { None })
+    {
+        return Err(crate::Error {
+            message: format!("operator expressions do not support {}", feature),
+        });
+    }
+
+    let mut context = None;
+    let mut ops = vec![];
+    for &(coefficient, kernel) in kernels {
+        let mut term = match context {
+            None if forcing => {
+                crate::ceed_bps::mat_shell_context_with_kernel(meles, options, kernel)?
+            }
+            _ => crate::ceed_bps::operator_context_with_kernel(meles, options, kernel)?,
+        };
+        if scale_qdata {
+            for qdata in &mut term.qdata {
+                let vector = qdata.vector_mut()?;
+                let mut qdata_view = vector.view_mut()?;
+                for value in qdata_view.iter_mut() {
+                    *value *= coefficient;
+                }
+            }
+        }
+        let op = term
+            .op_ceed
+            .replace(crate::OperatorSum::new(&meles.ceed, vec![]).into());
+        ops.push(op);
+        match &mut context {
+            None => context = Some(term),
+            Some(context) => context.qdata.append(&mut term.qdata),
+        }
+    }
+    let context = context.expect("operator expression without terms");
+    Ok((context, ops))
+}
+
+pub(crate) fn mat_shell_context<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    expression: &OperatorExpression,
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    let (context, ops) = term_operators(meles, options, &expression.kernels(options)?, true, true)?;
+
+    // Fused composite, if every term is a single operator
+    if ops
+        .iter()
+        .all(|op| matches!(op, crate::MelesOperator::Single(_)))
+    {
+        let mut composite = meles.ceed.composite_operator()?;
+        for op in &ops {
+            if let crate::MelesOperator::Single(op) = op {
+                composite = composite.sub_operator(op)?;
+            }
+        }
+        context.set_operator(composite);
+    } else {
+        context.set_operator(crate::OperatorSum::new(&meles.ceed, ops));
+    }
+    Ok(context)
+}
+
+// -----------------------------------------------------------------------------
+// Weighted sum of operators
+//   The terms keep unscaled quadrature data and the weights are applied to
+//   their actions, so the weights, such as the shift of an implicit time
+//   step, can change without setting up the terms again; the forcing of the
+//   problem does not match the weighted sum, so the terms are set up without
+//   forcing and error operators and the RHS is zero
+// -----------------------------------------------------------------------------
+pub(crate) fn weighted_mat_shell_context<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    terms: &[(f64, OperatorTerm)],
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    let kernels = terms
+        .iter()
+        .map(|&(weight, term)| Ok((weight, term.kernel(options)?)))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let (context, ops) = term_operators(meles, options, &kernels, false, false)?;
+    let weighted = terms
+        .iter()
+        .map(|&(weight, _)| weight)
+        .zip(ops)
+        .collect::<Vec<_>>();
+    context.set_operator(crate::OperatorSum::weighted(&meles.ceed, weighted));
+    Ok(context)
+}
+
+// -----------------------------------------------------------------------------
+
+// -----------------------------------------------------------------------------
+// Sum of operators
+//   Terms are applied one at a time into a work vector, so composite and
+//   source operators can be combined, which libCEED composite operators do
+//   not allow
+// -----------------------------------------------------------------------------
+/// Weighted sum of libCEED operators acting on the same local vectors,
+/// `y = sum w_i A_i x`
+///
+/// The `helmholtz` problem applies `K - k^2 M` as the sum of the stiffness
+/// operator and a shifted mass operator. Weights default to one and can be
+/// changed after setup, such as for the shift of an implicit time step.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+/// let meles = meles::Meles::new(
+///     &petsc,
+///     "./examples/helmholtz.yml",
+///     meles::MethodType::BenchmarkProblem,
+/// )?;
+///
+/// // solve and check error
+/// let result = meles.solve(&petsc)?;
+/// let error = meles.compute_error(&petsc, &result.solution)?;
+/// assert!(error.l2 < 1e-2, "incorrect L2 error");
+/// # Ok(())
+/// # }
+/// ```
+pub struct OperatorSum<'a> {
+    ceed: libceed::Ceed,
+    terms: Vec<MelesOperator<'a>>,
+    weights: RefCell<Vec<f64>>,
+    work: RefCell<Option<libceed::vector::Vector<'a>>>,
+}
+
+impl<'a> OperatorSum<'a> {
+    /// Returns the sum of the given operators
+    ///
+    /// # arguments
+    ///
+    /// * `ceed` - libCEED context for work vectors
+    /// * `terms` - Operators to sum, acting on the same local vectors
+    pub fn new(ceed: &libceed::Ceed, terms: Vec<MelesOperator<'a>>) -> Self {
+        let weights = vec![1.0; terms.len()];
+        Self {
+            ceed: ceed.clone(),
+            terms,
+            weights: RefCell::new(weights),
+            work: RefCell::new(None),
+        }
+    }
+
+    /// Returns the weighted sum of the given operators
+    ///
+    /// # arguments
+    ///
+    /// * `ceed` - libCEED context for work vectors
+    /// * `terms` - Weights and operators to sum, acting on the same local
+    ///     vectors
+    pub fn weighted(ceed: &libceed::Ceed, terms: Vec<(f64, MelesOperator<'a>)>) -> Self {
+        let (weights, terms) = terms.into_iter().unzip();
+        Self {
+            ceed: ceed.clone(),
+            terms,
+            weights: RefCell::new(weights),
+            work: RefCell::new(None),
+        }
+    }
+
+    /// Returns the weight of each term
+    pub fn weights(&self) -> Vec<f64> {
+        self.weights.borrow().clone()
+    }
+
+    /// Set the weight of each term
+    ///
+    /// # arguments
+    ///
+    /// * `weights` - Weights of the terms, in order
+    pub fn set_weights(&self, weights: &[f64]) -> crate::Result<()> {
+        if weights.len() != self.terms.len() {
+            return Err(crate::Error {
+                message: format!(
+                    "expected {} operator weights, found {}",
+                    self.terms.len(),
+                    weights.len()
+                ),
+            });
+        }
+        self.weights.borrow_mut().copy_from_slice(weights);
+        Ok(())
+    }
+
+    // Evaluate each term into the output, accumulating through a work vector
+    //   of the same length
+    fn accumulate(
+        &self,
+        y: &mut libceed::vector::Vector,
+        term: impl Fn(&MelesOperator<'a>, &mut libceed::vector::Vector) -> libceed::Result<i32>,
+    ) -> libceed::Result<i32> {
+        let (first, rest) = match self.terms.split_first() {
+            Some(terms) => terms,
+            None => {
+                y.set_value(0.0)?;
+                return Ok(0);
+            }
+        };
+        let weights = self.weights.borrow();
+        term(first, y)?;
+        if weights[0] != 1.0 {
+            y.scale(weights[0])?;
+        }
+        let mut work = self.work.borrow_mut();
+        if work.as_ref().map(|work| work.len()) != Some(y.len()) {
+            *work = Some(self.ceed.vector(y.len())?);
+        }
+        let work = work.as_mut().unwrap();
+        for (op, &weight) in rest.iter().zip(&weights[1..]) {
+            term(op, work)?;
+            y.axpy(weight, work)?;
+        }
+        Ok(0)
+    }
+
+    /// Apply the operator, `y = A x`
+    pub fn apply(
+        &self,
+        x: &libceed::vector::Vector,
+        y: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.accumulate(y, |op, y| op.apply(x, y))
+    }
+
+    /// Assemble the diagonal of the operator
+    pub fn linear_assemble_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.accumulate(assembled, |op, assembled| {
+            op.linear_assemble_diagonal(assembled)
+        })
+    }
+
+    /// Assemble the point block diagonal of the operator
+    pub fn linear_assemble_point_block_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.accumulate(assembled, |op, assembled| {
+            op.linear_assemble_point_block_diagonal(assembled)
+        })
+    }
+}
+
+impl<'a> From<OperatorSum<'a>> for crate::MelesOperator<'a> {
+    fn from(op: OperatorSum<'a>) -> Self {
+        Self::Sum(op)
+    }
+}
@@ -0,0 +1,518 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Static condensation
+// -----------------------------------------------------------------------------
+/// Element-wise static condensation of interior (bubble) DoFs
+///
+/// Interior DoFs of each element couple only to DoFs of the same element, so
+/// they are eliminated element-by-element. The reduced operator acts on the
+/// trace space of vertex, edge, and face DoFs, and interior DoFs are recovered
+/// by local back-substitution.
+///
+/// To solve `A x = b`, solve `S x_b = condense_rhs(b)` with the trace space
+/// MatShell and recover `x = back_substitute(x_b, b)`.
+///
+/// Note: Requires a plex mesh with a single hexahedral cell type and order of
+/// at least two.
+pub struct StaticCondensation<'a> {
+    pub(crate) comm: &'a mpi::topology::UserCommunicator,
+    pub(crate) ceed: libceed::Ceed,
+    pub(crate) dm: DM<'a, 'a>,
+    pub(crate) restr_u: ElemRestriction<'a>,
+    pub(crate) num_elements: usize,
+    pub(crate) interior: Vec<usize>,
+    pub(crate) trace: Vec<usize>,
+    pub(crate) trace_offsets: Vec<usize>,
+    pub(crate) trace_global_size: usize,
+    pub(crate) elements: Vec<CondensedElement>,
+}
+
+// Condensed element matrices
+//   S = K_bb - K_bi K_ii^{-1} K_ib and the factors needed for back-substitution
+pub(crate) struct CondensedElement {
+    schur: Vec<f64>,
+    k_bi: Vec<f64>,
+    k_ii_lu: DenseLU,
+    k_ii_inv_k_ib: Vec<f64>,
+}
+
+// -----------------------------------------------------------------------------
+// Dense LU factorization with partial pivoting
+// -----------------------------------------------------------------------------
+pub(crate) struct DenseLU {
+    n: usize,
+    lu: Vec<f64>,
+    pivots: Vec<usize>,
+}
+
+impl DenseLU {
+    // Factor a row major n x n matrix
+    fn factor(n: usize, mut lu: Vec<f64>) -> crate::Result<Self> {
+        let mut pivots = (0..n).collect::<Vec<_>>();
+        for k in 0..n {
+            let pivot = (k..n)
+                .max_by(|&i, &j| lu[i * n + k].abs().total_cmp(&lu[j * n + k].abs()))
+                .unwrap();
+            if lu[pivot * n + k] == 0.0 || !lu[pivot * n + k].is_finite() {
+                return Err(crate::Error {
+                    message: "singular interior element matrix in static condensation".to_string(),
+                });
+            }
+            if pivot != k {
+                for j in 0..n {
+                    lu.swap(k * n + j, pivot * n + j);
+                }
+                pivots.swap(k, pivot);
+            }
+            for i in k + 1..n {
+                lu[i * n + k] /= lu[k * n + k];
+                for j in k + 1..n {
+                    lu[i * n + j] -= lu[i * n + k] * lu[k * n + j];
+                }
+            }
+        }
+        Ok(Self { n, lu, pivots })
+    }
+
+    // Solve in place for a single right hand side
+    fn solve(&self, b: &mut [f64]) {
+        let n = self.n;
+        let mut x = self.pivots.iter().map(|&i| b[i]).collect::<Vec<_>>();
+        for i in 0..n {
+            for j in 0..i {
+                x[i] -= self.lu[i * n + j] * x[j];
+            }
+        }
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                x[i] -= self.lu[i * n + j] * x[j];
+            }
+            x[i] /= self.lu[i * n + i];
+        }
+        b.copy_from_slice(&x);
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Condensed operator
+// -----------------------------------------------------------------------------
+impl<'a> StaticCondensation<'a> {
+    /// Returns a global vector on the trace space
+    pub fn create_trace_vector(&self) -> petsc::Result<petsc::vector::Vector<'a>> {
+        let mut vector = petsc::vector::Vector::create(self.comm)?;
+        vector.set_sizes(
+            Some(self.trace_offsets.len() as petsc::Int),
+            Some(self.trace_global_size as petsc::Int),
+        )?;
+        vector.set_up()?;
+        Ok(vector)
+    }
+
+    /// Returns the DM of the full problem
+    pub fn dm(&self) -> &DM<'a, 'a> {
+        &self.dm
+    }
+
+    // Scatter between a full global vector and the trace space
+    //   Trace vectors are raw, so the MatShell callback passes the vectors
+    //   PETSc gives it
+    fn trace_to_full(
+        &self,
+        x_b: petsc::petsc_raw::Vec,
+        x: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        x.zero_entries()?;
+        let mut x_view = x.view_mut()?;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut array = std::ptr::null();
+            check(VecGetArrayRead(x_b, &mut array), "VecGetArrayRead")?;
+            let x_b_view = std::slice::from_raw_parts(array, self.trace_offsets.len());
+            for (i, &offset) in self.trace_offsets.iter().enumerate() {
+                x_view[offset] = x_b_view[i];
+            }
+            check(VecRestoreArrayRead(x_b, &mut array), "VecRestoreArrayRead")
+        }
+    }
+
+    fn full_to_trace(
+        &self,
+        x: &petsc::vector::Vector<'a>,
+        x_b: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        let x_view = x.view()?;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut array = std::ptr::null_mut();
+            check(VecGetArray(x_b, &mut array), "VecGetArray")?;
+            let x_b_view = std::slice::from_raw_parts_mut(array, self.trace_offsets.len());
+            for (i, &offset) in self.trace_offsets.iter().enumerate() {
+                x_b_view[i] = x_view[offset];
+            }
+            check(VecRestoreArray(x_b, &mut array), "VecRestoreArray")
+        }
+    }
+
+    // Apply an element-wise kernel, mapping a global vector to a global
+    //   vector through element vectors
+    fn apply_elementwise(
+        &self,
+        x: &petsc::vector::Vector<'a>,
+        y: &mut petsc::vector::Vector<'a>,
+        kernel: impl Fn(&CondensedElement, &[f64], &mut [f64]),
+    ) -> crate::Result<()> {
+        let mut x_loc = self.dm.create_local_vector()?;
+        let mut y_loc = x_loc.duplicate()?;
+        self.dm
+            .global_to_local(x, InsertMode::INSERT_VALUES, &mut x_loc)?;
+        let elem_size = self.interior.len() + self.trace.len();
+        let mut x_elem = self.ceed.vector(self.num_elements * elem_size)?;
+        let mut y_elem = self.ceed.vector(self.num_elements * elem_size)?;
+        let mut loc_ceed = self.ceed.vector(x_loc.local_size()? as usize)?;
+        crate::transfer::with_wrapped(&mut x_loc, &mut loc_ceed, |x_loc_ceed| {
+            Ok(self
+                .restr_u
+                .apply(TransposeMode::NoTranspose, x_loc_ceed, &mut x_elem)?)
+        })?;
+        {
+            let x_elem_view = x_elem.view()?;
+            let mut y_elem_view = y_elem.view_mut()?;
+            for (e, element) in self.elements.iter().enumerate() {
+                let range = e * elem_size..(e + 1) * elem_size;
+                kernel(
+                    element,
+                    &x_elem_view[range.clone()],
+                    &mut y_elem_view[range],
+                );
+            }
+        }
+        crate::transfer::with_wrapped(&mut y_loc, &mut loc_ceed, |y_loc_ceed| {
+            y_loc_ceed.set_value(0.0)?;
+            Ok(self
+                .restr_u
+                .apply(TransposeMode::Transpose, &y_elem, y_loc_ceed)?)
+        })?;
+        y.zero_entries()?;
+        self.dm.local_to_global(&y_loc, InsertMode::ADD_VALUES, y)?;
+        Ok(())
+    }
+
+    /// Apply the condensed operator on the trace space, `y_b = S x_b`
+    ///
+    /// # arguments
+    ///
+    /// * `x_b` - Global trace input vector
+    /// * `y_b` - Global trace output vector
+    pub fn apply(
+        &self,
+        x_b: &petsc::vector::Vector<'a>,
+        y_b: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.apply_raw(x_b.as_raw(), y_b.as_raw())
+    }
+
+    fn apply_raw(
+        &self,
+        x_b: petsc::petsc_raw::Vec,
+        y_b: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        let mut x = self.dm.create_global_vector()?;
+        let mut y = x.duplicate()?;
+        self.trace_to_full(x_b, &mut x)?;
+        let n_b = self.trace.len();
+        self.apply_elementwise(&x, &mut y, |element, x_e, y_e| {
+            y_e.iter_mut().for_each(|y| *y = 0.0);
+            for (i, &row) in self.trace.iter().enumerate() {
+                y_e[row] = self
+                    .trace
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &col)| element.schur[i * n_b + j] * x_e[col])
+                    .sum();
+            }
+        })?;
+        self.full_to_trace(&y, y_b)
+    }
+
+    // Assemble the diagonal of the condensed operator from the element Schur
+    //   complements
+    fn get_diagonal_raw(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        let mut x = self.dm.create_global_vector()?;
+        x.zero_entries()?;
+        let mut y = x.duplicate()?;
+        let n_b = self.trace.len();
+        self.apply_elementwise(&x, &mut y, |element, _, y_e| {
+            y_e.iter_mut().for_each(|y| *y = 0.0);
+            for (i, &row) in self.trace.iter().enumerate() {
+                y_e[row] = element.schur[i * n_b + i];
+            }
+        })?;
+        self.full_to_trace(&y, d)
+    }
+
+    /// Returns the condensed right hand side, `b_b - K_bi K_ii^{-1} b_i`
+    ///
+    /// # arguments
+    ///
+    /// * `b` - Global right hand side of the full problem
+    pub fn condense_rhs(
+        &self,
+        b: &petsc::vector::Vector<'a>,
+    ) -> crate::Result<petsc::vector::Vector<'a>> {
+        // Interior DoFs belong to a single element
+        let mut correction = b.duplicate()?;
+        self.apply_elementwise(b, &mut correction, |element, b_e, y_e| {
+            y_e.iter_mut().for_each(|y| *y = 0.0);
+            let mut b_i = self.interior.iter().map(|&i| b_e[i]).collect::<Vec<_>>();
+            element.k_ii_lu.solve(&mut b_i);
+            let n_i = self.interior.len();
+            for (i, &row) in self.trace.iter().enumerate() {
+                y_e[row] = (0..n_i).map(|j| element.k_bi[i * n_i + j] * b_i[j]).sum();
+            }
+        })?;
+        let mut b_condensed = b.duplicate()?;
+        b_condensed.copy_data_from(b)?;
+        b_condensed.axpy(-1.0, &correction)?;
+        let b_b = self.create_trace_vector()?;
+        self.full_to_trace(&b_condensed, b_b.as_raw())?;
+        Ok(b_b)
+    }
+
+    /// Returns the full solution, recovering interior DoFs by local
+    /// back-substitution, `x_i = K_ii^{-1} (b_i - K_ib x_b)`
+    ///
+    /// # arguments
+    ///
+    /// * `x_b` - Global trace solution vector
+    /// * `b` - Global right hand side of the full problem
+    pub fn back_substitute(
+        &self,
+        x_b: &petsc::vector::Vector<'a>,
+        b: &petsc::vector::Vector<'a>,
+    ) -> crate::Result<petsc::vector::Vector<'a>> {
+        let mut x = self.dm.create_global_vector()?;
+        self.trace_to_full(x_b.as_raw(), &mut x)?;
+
+        // Interior values, stored in b's interior slots for one element pass
+        let mut combined = b.duplicate()?;
+        combined.copy_data_from(b)?;
+        {
+            let x_view = x.view()?;
+            let mut combined_view = combined.view_mut()?;
+            for &offset in &self.trace_offsets {
+                combined_view[offset] = x_view[offset];
+            }
+        }
+        let mut x_i = b.duplicate()?;
+        let n_b = self.trace.len();
+        self.apply_elementwise(&combined, &mut x_i, |element, v_e, y_e| {
+            y_e.iter_mut().for_each(|y| *y = 0.0);
+            let mut b_i = self.interior.iter().map(|&i| v_e[i]).collect::<Vec<_>>();
+            element.k_ii_lu.solve(&mut b_i);
+            for (i, &row) in self.interior.iter().enumerate() {
+                let coupling: f64 = self
+                    .trace
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &col)| element.k_ii_inv_k_ib[i * n_b + j] * v_e[col])
+                    .sum();
+                y_e[row] = b_i[i] - coupling;
+            }
+        })?;
+        x.axpy(1.0, &x_i)?;
+        Ok(x)
+    }
+
+    /// Returns a MatShell applying the condensed operator on the trace space
+    pub fn mat_shell(
+        self: &std::rc::Rc<Self>,
+    ) -> crate::Result<petsc::mat::MatShell<'a, 'a, std::rc::Rc<StaticCondensation<'a>>>> {
+        let mut mat = petsc::mat::Mat::create(self.comm)?;
+        let local_size = self.trace_offsets.len() as petsc::Int;
+        let global_size = self.trace_global_size as petsc::Int;
+        mat.set_sizes(
+            Some(local_size),
+            Some(local_size),
+            Some(global_size),
+            Some(global_size),
+        )?;
+        let mat = mat.into_shell(Box::new(self.clone()))?;
+
+        // Set operations
+        crate::petsc_ops::set_shell_operations(
+            &mat,
+            &[MatOperation::MATOP_MULT, MatOperation::MATOP_GET_DIAGONAL],
+        )?;
+
+        Ok(mat)
+    }
+}
+
+impl crate::petsc_ops::ShellOperations for StaticCondensation<'_> {
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        self.apply_raw(x, y)
+    }
+
+    fn get_diagonal(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        self.get_diagonal_raw(d)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup static condensation
+// -----------------------------------------------------------------------------
+pub(crate) fn static_condensation<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<StaticCondensation<'a>> {
+    let crate::ceed_bps::ElementOperator {
+        dm,
+        restr_u,
+        op_elem,
+        num_elements,
+        num_nodes_1d,
+        num_components,
+    } = crate::ceed_bps::element_operator(meles, options, None)?;
+    if num_nodes_1d < 3 {
+        return Err(crate::Error {
+            message: "static condensation requires order 2 or higher".to_string(),
+        });
+    }
+
+    // Split element DoFs, ordered by component then node, into interior and
+    //   trace DoFs
+    let dimension = dm.dimension()? as u32;
+    let num_nodes = num_nodes_1d.pow(dimension);
+    let elem_size = num_nodes * num_components;
+    let is_interior = |node: usize| {
+        (0..dimension).all(|d| {
+            let index = (node / num_nodes_1d.pow(d)) % num_nodes_1d;
+            index > 0 && index < num_nodes_1d - 1
+        })
+    };
+    let (interior, trace): (Vec<usize>, Vec<usize>) =
+        (0..elem_size).partition(|&dof| is_interior(dof % num_nodes));
+    let (n_i, n_b) = (interior.len(), trace.len());
+
+    // Element matrices, row major per element
+    let element_matrices =
+        crate::ceed_bps::probe_element_matrices(&meles.ceed, &op_elem, num_elements, elem_size)?;
+
+    // Condense each element
+    let mut elements = Vec::with_capacity(num_elements);
+    for e in 0..num_elements {
+        let k = |row: usize, col: usize| element_matrices[(e * elem_size + row) * elem_size + col];
+        let k_ii = interior
+            .iter()
+            .flat_map(|&row| interior.iter().map(move |&col| (row, col)))
+            .map(|(row, col)| k(row, col))
+            .collect::<Vec<_>>();
+        let k_ii_lu = DenseLU::factor(n_i, k_ii)?;
+        let k_bi = trace
+            .iter()
+            .flat_map(|&row| interior.iter().map(move |&col| (row, col)))
+            .map(|(row, col)| k(row, col))
+            .collect::<Vec<_>>();
+
+        // K_ii^{-1} K_ib, one column at a time
+        let mut k_ii_inv_k_ib = vec![0.0; n_i * n_b];
+        for (j, &col) in trace.iter().enumerate() {
+            let mut column = interior.iter().map(|&row| k(row, col)).collect::<Vec<_>>();
+            k_ii_lu.solve(&mut column);
+            for i in 0..n_i {
+                k_ii_inv_k_ib[i * n_b + j] = column[i];
+            }
+        }
+
+        // S = K_bb - K_bi K_ii^{-1} K_ib
+        let mut schur = vec![0.0; n_b * n_b];
+        for (i, &row) in trace.iter().enumerate() {
+            for (j, &col) in trace.iter().enumerate() {
+                schur[i * n_b + j] = k(row, col)
+                    - (0..n_i)
+                        .map(|l| k_bi[i * n_i + l] * k_ii_inv_k_ib[l * n_b + j])
+                        .sum::<f64>();
+            }
+        }
+        elements.push(CondensedElement {
+            schur,
+            k_bi,
+            k_ii_lu,
+            k_ii_inv_k_ib,
+        });
+    }
+
+    // Owned trace DoFs, excluding the interior DoFs of each cell
+    let (range_start, mut is_trace) = {
+        let global = dm.create_global_vector()?;
+        (
+            global.get_ownership_range()?.start,
+            vec![true; global.local_size()? as usize],
+        )
+    };
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let (mut cell_start, mut cell_end) = (0, 0);
+        check(
+            DMPlexGetHeightStratum(dm.as_raw(), 0, &mut cell_start, &mut cell_end),
+            "DMPlexGetHeightStratum",
+        )?;
+        let mut global_section = std::ptr::null_mut();
+        check(
+            DMGetGlobalSection(dm.as_raw(), &mut global_section),
+            "DMGetGlobalSection",
+        )?;
+        for cell in cell_start..cell_end {
+            let (mut dof, mut offset) = (0, 0);
+            check(
+                PetscSectionGetDof(global_section, cell, &mut dof),
+                "PetscSectionGetDof",
+            )?;
+            check(
+                PetscSectionGetOffset(global_section, cell, &mut offset),
+                "PetscSectionGetOffset",
+            )?;
+            // Unowned points have negative offsets
+            if offset >= 0 {
+                for i in 0..dof {
+                    is_trace[(offset + i - range_start) as usize] = false;
+                }
+            }
+        }
+    }
+    let trace_offsets = is_trace
+        .iter()
+        .enumerate()
+        .filter(|(_, &is_trace)| is_trace)
+        .map(|(offset, _)| offset)
+        .collect::<Vec<_>>();
+    let trace_global_size = {
+        use mpi::collective::{CommunicatorCollectives, SystemOperation};
+        let mut global_size = 0;
+        meles.comm.all_reduce_into(
+            &trace_offsets.len(),
+            &mut global_size,
+            SystemOperation::sum(),
+        );
+        global_size
+    };
+
+    Ok(StaticCondensation {
+        comm: meles.comm,
+        ceed: meles.ceed.clone(),
+        dm,
+        restr_u,
+        num_elements,
+        interior,
+        trace,
+        trace_offsets,
+        trace_global_size,
+        elements,
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,442 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Embedded default configuration
+// -----------------------------------------------------------------------------
+pub(crate) const DEFAULT_CONFIG: &str = "\
+ceed: /cpu/self
+problem: bp1
+order: 3
+qextra: 1
+dm_plex_dim: 3
+dm_plex_box_faces: 5,5,5
+dm_plex_simplex: 0
+";
+
+// Environment variable holding additional search directories
+pub(crate) const CONFIG_PATH_VAR: &str = "MELES_CONFIG_PATH";
+
+// -----------------------------------------------------------------------------
+// Options database lock
+// -----------------------------------------------------------------------------
+// The PETSc options database is global, so contexts created from different
+// threads must not insert, read, or clear options concurrently
+static OPTIONS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+pub(crate) fn options_lock() -> std::sync::MutexGuard<'static, ()> {
+    OPTIONS_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// -----------------------------------------------------------------------------
+// Raw options access
+//   Callers hold the options lock
+// -----------------------------------------------------------------------------
+// Get an integer option, if set
+pub(crate) fn options_get_int(name: &std::ffi::CStr) -> crate::Result<Option<petsc::Int>> {
+    let mut value: petsc::Int = 0;
+    let mut has_value = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::PetscOptionsGetInt(
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                name.as_ptr(),
+                &mut value,
+                &mut has_value,
+            )
+        },
+        "PetscOptionsGetInt",
+    )?;
+    Ok(if has_value == petsc::petsc_raw::PetscBool::PETSC_TRUE {
+        Some(value)
+    } else {
+        None
+    })
+}
+
+// Get a string option, if set
+pub(crate) fn options_get_string(name: &std::ffi::CStr) -> crate::Result<Option<String>> {
+    let mut value = [0 as std::os::raw::c_char; 4096];
+    let mut has_value = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscOptionsGetString(
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                name.as_ptr(),
+                value.as_mut_ptr(),
+                value.len(),
+                &mut has_value,
+            ),
+            "PetscOptionsGetString",
+        )?;
+        Ok(if has_value == petsc::petsc_raw::PetscBool::PETSC_TRUE {
+            Some(
+                std::ffi::CStr::from_ptr(value.as_ptr())
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        } else {
+            None
+        })
+    }
+}
+
+// Set or clear an option
+pub(crate) fn options_set(name: &std::ffi::CStr, value: Option<&str>) -> crate::Result<()> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        match value {
+            Some("") => crate::check_petsc_raw(
+                PetscOptionsSetValue(std::ptr::null_mut(), name.as_ptr(), std::ptr::null()),
+                "PetscOptionsSetValue",
+            ),
+            Some(value) => {
+                let value = std::ffi::CString::new(value).unwrap();
+                crate::check_petsc_raw(
+                    PetscOptionsSetValue(std::ptr::null_mut(), name.as_ptr(), value.as_ptr()),
+                    "PetscOptionsSetValue",
+                )
+            }
+            None => crate::check_petsc_raw(
+                PetscOptionsClearValue(std::ptr::null_mut(), name.as_ptr()),
+                "PetscOptionsClearValue",
+            ),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Option overrides
+//   Options set for one setup step, such as DMSetFromOptions, with the prior
+//   values restored when the guard is dropped, including on early returns;
+//   the guard is created and dropped while holding the options lock
+// -----------------------------------------------------------------------------
+pub(crate) struct OptionOverrides {
+    previous: Vec<(std::ffi::CString, Option<String>)>,
+}
+
+impl OptionOverrides {
+    pub(crate) fn new() -> Self {
+        Self { previous: vec![] }
+    }
+
+    // Set an option, or clear it for None, remembering the prior value
+    pub(crate) fn set(&mut self, name: &str, value: Option<&str>) -> crate::Result<()> {
+        let name = std::ffi::CString::new(name).unwrap();
+        if !self.previous.iter().any(|(existing, _)| *existing == name) {
+            let previous = options_get_string(&name)?;
+            self.previous.push((name.clone(), previous));
+        }
+        options_set(&name, value)
+    }
+}
+
+impl Drop for OptionOverrides {
+    fn drop(&mut self) {
+        for (name, value) in self.previous.iter().rev() {
+            // Errors cannot be propagated from drop
+            let _ = options_set(name, value.as_deref());
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Config source
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Location the configuration was loaded from
+pub enum ConfigSource {
+    File(std::path::PathBuf),
+    Default,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Default => write!(f, "embedded default"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Config loader
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Specification of the yml configuration to load
+///
+/// Relative filepaths are searched for in the user provided search
+/// directories, then the current directory, then each directory listed in
+/// the `MELES_CONFIG_PATH` environment variable. If no filepath is given, the
+/// embedded default configuration is used.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let config = meles::Config::new()
+///     .search_path("./examples")
+///     .file("meles.yml");
+/// assert_eq!(
+///     config.resolve()?,
+///     meles::ConfigSource::File("./examples/meles.yml".into())
+/// );
+///
+/// // no filepath uses the embedded default
+/// assert_eq!(
+///     meles::Config::new().resolve()?,
+///     meles::ConfigSource::Default
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct Config {
+    pub(crate) file: Option<std::path::PathBuf>,
+    pub(crate) search_paths: Vec<std::path::PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for Config {
+    fn from(file: &str) -> Self {
+        Self::new().file(file)
+    }
+}
+
+impl From<String> for Config {
+    fn from(file: String) -> Self {
+        Self::new().file(file)
+    }
+}
+
+impl From<&std::path::Path> for Config {
+    fn from(file: &std::path::Path) -> Self {
+        Self::new().file(file)
+    }
+}
+
+impl Config {
+    /// Returns a configuration using the embedded default
+    pub fn new() -> Self {
+        Self {
+            file: None,
+            search_paths: vec![],
+        }
+    }
+
+    /// Set the yml filepath to load
+    pub fn file(mut self, file: impl Into<std::path::PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Add a directory to search for the yml file
+    pub fn search_path(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.search_paths.push(dir.into());
+        self
+    }
+
+    // Candidate filepaths, in search order
+    fn candidates(&self, file: &std::path::Path) -> Vec<std::path::PathBuf> {
+        if file.is_absolute() {
+            return vec![file.to_path_buf()];
+        }
+        let mut dirs = self.search_paths.clone();
+        dirs.push(std::path::PathBuf::from("."));
+        if let Some(env_paths) = std::env::var_os(CONFIG_PATH_VAR) {
+            dirs.extend(std::env::split_paths(&env_paths));
+        }
+        dirs.iter()
+            .map(|dir| {
+                if dir == std::path::Path::new(".") {
+                    file.to_path_buf()
+                } else {
+                    dir.join(file)
+                }
+            })
+            .collect()
+    }
+
+    /// Locate the configuration, reporting the attempted filepaths on failure
+    pub fn resolve(&self) -> crate::Result<ConfigSource> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(ConfigSource::Default),
+        };
+        let candidates = self.candidates(file);
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Ok(ConfigSource::File(path.clone())),
+            None => Err(crate::Error {
+                message: format!(
+                    "failed to find config file {}; attempted: {}",
+                    file.display(),
+                    candidates
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }),
+        }
+    }
+
+    // Insert the resolved configuration into the options database, returning
+    // a guard that removes the newly inserted options when dropped
+    pub(crate) fn insert<'a>(
+        &self,
+        petsc: &'a Petsc,
+    ) -> crate::Result<(ConfigSource, ScopedOptions<'a>)> {
+        let source = self.resolve()?;
+        let yml = match &source {
+            ConfigSource::File(path) => std::fs::read_to_string(path)?,
+            ConfigSource::Default => DEFAULT_CONFIG.to_string(),
+        };
+
+        // Boundary conditions are parsed here, not by the options database
+        let boundary_conditions = crate::boundary_conditions::BoundaryConditions::from_yml(&yml)?;
+        let yml = crate::boundary_conditions::strip_section(&yml);
+
+        // Options not already set are removed on drop, and options already
+        //   set are restored to their prior values
+        let values = yml_option_values(&yml);
+        let mut names = vec![];
+        let mut previous = vec![];
+        for (name, _) in &values {
+            match options_get_string(&std::ffi::CString::new(name.as_str()).unwrap())? {
+                Some(value) => previous.push((name.clone(), value)),
+                None => names.push(name.clone()),
+            }
+        }
+
+        match &source {
+            ConfigSource::File(path) if boundary_conditions.is_empty() => {
+                petsc.options_insert_file(&path.to_string_lossy())?;
+            }
+            _ => {
+                petsc.options_insert_string(&yml_to_options(&yml))?;
+            }
+        }
+        Ok((
+            source,
+            ScopedOptions {
+                petsc,
+                names,
+                previous,
+                values,
+                boundary_conditions,
+            },
+        ))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Scoped options
+// -----------------------------------------------------------------------------
+// Options inserted by a Meles context, cleared from the global options
+// database or restored to their prior values on drop so they do not leak
+// into subsequent contexts
+pub(crate) struct ScopedOptions<'a> {
+    petsc: &'a Petsc,
+    names: Vec<String>,
+    previous: Vec<(String, String)>,
+    pub(crate) values: Vec<(String, String)>,
+    pub(crate) boundary_conditions: crate::boundary_conditions::BoundaryConditions,
+}
+
+impl<'a> ScopedOptions<'a> {
+    // Guard that clears nothing
+    pub(crate) fn empty(petsc: &'a Petsc) -> Self {
+        Self {
+            petsc,
+            names: vec![],
+            previous: vec![],
+            values: vec![],
+            boundary_conditions: Default::default(),
+        }
+    }
+
+    // Set option values, as the options of a configuration, returning a guard
+    //   that removes them when dropped; the caller holds the options lock
+    pub(crate) fn set_values(
+        petsc: &'a Petsc,
+        values: Vec<(String, String)>,
+        boundary_conditions: crate::boundary_conditions::BoundaryConditions,
+    ) -> crate::Result<Self> {
+        let mut names = vec![];
+        let mut previous = vec![];
+        for (name, value) in &values {
+            let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+            match options_get_string(&c_name)? {
+                Some(value) => previous.push((name.clone(), value)),
+                None => names.push(name.clone()),
+            }
+            // Quotes only group tokens of an options string
+            options_set(&c_name, Some(value.trim_matches('"')))?;
+        }
+        Ok(Self {
+            petsc,
+            names,
+            previous,
+            values,
+            boundary_conditions,
+        })
+    }
+
+    // Value set by the configuration for an option name, with leading dash
+    pub(crate) fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl<'a> Drop for ScopedOptions<'a> {
+    fn drop(&mut self) {
+        if self.names.is_empty() && self.previous.is_empty() {
+            return;
+        }
+        let _options_lock = options_lock();
+        // Errors cannot be propagated from drop
+        for name in &self.names {
+            let _ = self.petsc.options_clear_value(name);
+        }
+        for (name, value) in &self.previous {
+            let _ = options_set(&std::ffi::CString::new(name.as_str()).unwrap(), Some(value));
+        }
+    }
+}
+
+// Option names, with leading dash, and values set by flat `key: value` yml
+pub(crate) fn yml_option_values(yml: &str) -> Vec<(String, String)> {
+    yml.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (format!("-{}", key.trim()), value.trim().to_string()))
+        .collect()
+}
+
+// Convert flat `key: value` yml into a PETSc options string, quoting values
+//   with whitespace, such as `Face Sets:1`, so they remain one token
+pub(crate) fn yml_to_options(yml: &str) -> String {
+    yml_option_values(yml)
+        .iter()
+        .map(|(name, value)| {
+            if value.contains(char::is_whitespace) && !value.starts_with('"') {
+                format!("{} \"{}\"", name, value)
+            } else {
+                format!("{} {}", name, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,256 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Convergence study results
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Solution error for a single level of uniform refinement
+pub struct ConvergenceEntry {
+    pub refinement: usize,
+    pub global_size: usize,
+    pub error: crate::SolutionError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Solution errors over successive uniform refinements of a mesh
+///
+/// Each refinement halves the mesh size, so the observed rate between two
+/// levels is `log2(e_coarse / e_fine)`.
+pub struct ConvergenceStudy {
+    pub entries: Vec<ConvergenceEntry>,
+}
+
+impl ConvergenceStudy {
+    /// Returns the observed L2 error rates between successive refinements
+    pub fn l2_rates(&self) -> Vec<f64> {
+        self.entries
+            .windows(2)
+            .map(|pair| (pair[0].error.l2 / pair[1].error.l2).log2())
+            .collect()
+    }
+
+    /// Returns the observed H1 error rates between successive refinements
+    pub fn h1_rates(&self) -> Vec<f64> {
+        self.entries
+            .windows(2)
+            .map(|pair| (pair[0].error.h1 / pair[1].error.h1).log2())
+            .collect()
+    }
+}
+
+impl fmt::Display for ConvergenceStudy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Convergence Study:")?;
+        writeln!(
+            f,
+            "  {:>10} {:>12} {:>14} {:>8} {:>14} {:>8}",
+            "Refinement", "Global DoFs", "L2 error", "Rate", "H1 error", "Rate"
+        )?;
+        let (l2_rates, h1_rates) = (self.l2_rates(), self.h1_rates());
+        for (i, entry) in self.entries.iter().enumerate() {
+            let rate = |rates: &[f64]| match i {
+                0 => "-".to_string(),
+                _ => format!("{:.2}", rates[i - 1]),
+            };
+            writeln!(
+                f,
+                "  {:>10} {:>12} {:>14.6e} {:>8} {:>14.6e} {:>8}",
+                entry.refinement,
+                entry.global_size,
+                entry.error.l2,
+                rate(&l2_rates),
+                entry.error.h1,
+                rate(&h1_rates)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Run a convergence study over uniform refinements
+//   Each level is a fresh context created with `-dm_refine` overridden, so the
+//   study works for any problem, order, and mesh given by the configuration,
+//   and the options database is restored once the mesh is created
+// -----------------------------------------------------------------------------
+const REFINE_OPTION: &str = "-dm_refine";
+
+pub(crate) fn convergence_study(
+    petsc: &Petsc,
+    config: crate::Config,
+    num_refinements: usize,
+) -> crate::Result<ConvergenceStudy> {
+    // Refinement is controlled by the study
+    {
+        let _options_lock = crate::config::options_lock();
+        if petsc.options_has_name(REFINE_OPTION)? {
+            return Err(crate::Error {
+                message: format!("convergence studies require {} to be unset", REFINE_OPTION),
+            });
+        }
+    }
+    if let crate::ConfigSource::File(path) = config.resolve()? {
+        let yml = std::fs::read_to_string(path)?;
+        if crate::config::yml_option_values(&yml)
+            .iter()
+            .any(|(name, _)| name == REFINE_OPTION)
+        {
+            return Err(crate::Error {
+                message: format!(
+                    "convergence studies require {} to be unset in the configuration",
+                    REFINE_OPTION
+                ),
+            });
+        }
+    }
+
+    let mut entries = Vec::with_capacity(num_refinements);
+    for refinement in 0..num_refinements {
+        // The refinement is only set while the mesh of the level is created
+        let overrides = {
+            let _options_lock = crate::config::options_lock();
+            let mut overrides = crate::config::OptionOverrides::new();
+            overrides.set(REFINE_OPTION, Some(&refinement.to_string()))?;
+            overrides
+        };
+        let meles = crate::Meles::new(petsc, config.clone(), crate::MethodType::BenchmarkProblem);
+        {
+            let _options_lock = crate::config::options_lock();
+            drop(overrides);
+        }
+        let meles = meles?;
+        let result = meles.solve(petsc)?;
+        let error = meles.compute_error(petsc, &result.solution)?;
+        entries.push(ConvergenceEntry {
+            refinement,
+            global_size: result.solution.global_size()? as usize,
+            error,
+        });
+    }
+    Ok(ConvergenceStudy { entries })
+}
+
+// -----------------------------------------------------------------------------
+// Sequence of uniformly refined problems
+//   Each level shares the Ceed context, configuration, and options of the base
+//   problem, and only its mesh and discretization are created, with additional
+//   uniform refinements through -refine, so -dm_refine is only overridden while
+//   the mesh is created; setup progress is reported by each level
+// -----------------------------------------------------------------------------
+pub(crate) fn refined_sequence<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+    num_levels: usize,
+) -> crate::Result<Vec<crate::Meles<'a>>> {
+    if meles.bp_options.mesh_type != crate::dm::MeshType::Plex {
+        return Err(crate::Error {
+            message: format!(
+                "refined sequences require a plex mesh, not {}",
+                meles.bp_options.mesh_type
+            ),
+        });
+    }
+
+    let mut sequence = Vec::with_capacity(num_levels);
+    for level in 1..=num_levels {
+        let bp_options = crate::ceed_bps::Opt {
+            refine: meles.bp_options.refine + level,
+            ..meles.bp_options.clone()
+        };
+        let mesh_setup_start = std::time::Instant::now();
+        let (dm, progress) = {
+            let _options_lock = crate::config::options_lock();
+            let progress = crate::progress::ProgressReporter::new(meles.comm, petsc.options()?);
+            let mut dm = match meles.method {
+                crate::MethodType::BenchmarkProblem => {
+                    crate::ceed_bps::create_dm(meles.comm, &bp_options)?
+                }
+                crate::MethodType::RatelQuasistatic => crate::quasistatic::create_dm(
+                    meles.comm,
+                    &bp_options,
+                    &meles.quasistatic_options,
+                )?,
+            };
+            crate::backend::set_dm_types(meles.comm, &mut dm, &meles.ceed_resource)?;
+            (dm, progress)
+        };
+        let mesh_setup_time = mesh_setup_start.elapsed();
+        progress.report(
+            crate::progress::SetupPhase::Mesh,
+            crate::dm::local_num_cells(&dm, bp_options.mesh_type)?,
+            mesh_setup_start,
+        );
+
+        sequence.push(meles.derived(
+            petsc,
+            (
+                meles.ceed.clone(),
+                meles.ceed_resource.clone(),
+                meles.device.clone(),
+            ),
+            bp_options,
+            dm,
+            progress,
+            mesh_setup_time,
+        ));
+    }
+    Ok(sequence)
+}
+
+// -----------------------------------------------------------------------------
+// Order of accuracy assertion
+// -----------------------------------------------------------------------------
+/// Run a convergence study and assert the observed L2 error rate
+///
+/// The problem in the configuration is solved on the given mesh and two
+/// uniform refinements, and the L2 error rate between the two finest meshes is
+/// compared to the expected rate. The solver tolerance in the configuration
+/// should be tight enough that the discretization error dominates, such as
+/// `ksp_rtol: 1e-10`.
+///
+/// # arguments
+///
+/// * `petsc` - PETSc context to use
+/// * `problem_config` - Filepath to specification yml or `Config` with search
+///                        paths
+/// * `expected_rate` - Expected L2 error rate, `order + 1` for smooth
+///                       solutions
+/// * `tol` - Allowed difference between the observed and expected rates
+///
+/// # panics
+///
+/// Panics if the observed rate differs from the expected rate by more than
+/// `tol`.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+///
+/// // quadratic elements converge at third order in L2
+/// let study = meles::assert_convergence_rate(&petsc, "./examples/convergence.yml", 3.0, 0.3)?;
+/// println!("{}", study);
+/// # Ok(())
+/// # }
+/// ```
+pub fn assert_convergence_rate(
+    petsc: &Petsc,
+    problem_config: impl Into<crate::Config>,
+    expected_rate: f64,
+    tol: f64,
+) -> crate::Result<ConvergenceStudy> {
+    let study = convergence_study(petsc, problem_config.into(), 3)?;
+    let observed_rate = *study.l2_rates().last().unwrap();
+    assert!(
+        (observed_rate - expected_rate).abs() <= tol,
+        "observed L2 convergence rate {:.3} differs from expected rate {} by more than {}\n{}",
+        observed_rate,
+        expected_rate,
+        tol,
+        study
+    );
+    Ok(study)
+}
+
+// -----------------------------------------------------------------------------
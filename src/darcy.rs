@@ -0,0 +1,797 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Mixed Darcy problem
+// -----------------------------------------------------------------------------
+/// Mixed formulation of the Poisson problem as a Darcy problem,
+/// `u + grad p = 0` and `div u = f`, with a lowest order Raviart-Thomas flux
+/// and a piecewise constant pressure
+///
+/// The DM has two fields, the flux with one DoF per face, the normal flux
+/// through the face, and the pressure with one DoF per cell. The block
+/// operator `[M B^T; B 0]` is symmetric indefinite and applied matrix-free,
+/// so MINRES or GMRES is required. Pressure boundary values from the
+/// manufactured solution are natural boundary conditions.
+///
+/// Note: Requires a plex mesh with a single hexahedral cell type. The
+/// Raviart-Thomas basis is evaluated in Rust closure QFunctions, so a /cpu
+/// libCEED resource is required.
+pub struct DarcyProblem<'a> {
+    pub(crate) comm: &'a mpi::topology::UserCommunicator,
+    pub(crate) dm: DM<'a, 'a>,
+    pub(crate) op_ceed: libceed::operator::Operator<'a>,
+    pub(crate) op_error: libceed::operator::Operator<'a>,
+    pub(crate) num_elements: usize,
+    pub(crate) face_offsets: Vec<usize>,
+    pub(crate) face_signs: Vec<f64>,
+    pub(crate) cell_offsets: Vec<usize>,
+    pub(crate) rhs: petsc::vector::Vector<'a>,
+    pub(crate) x_loc: RefCell<petsc::vector::Vector<'a>>,
+    pub(crate) y_loc: RefCell<petsc::vector::Vector<'a>>,
+    pub(crate) x_elem: RefCell<libceed::vector::Vector<'a>>,
+    pub(crate) y_elem: RefCell<libceed::vector::Vector<'a>>,
+    pub(crate) error_qpts: RefCell<libceed::vector::Vector<'a>>,
+}
+
+// -----------------------------------------------------------------------------
+// Element DoFs
+//   Element vectors hold the six face fluxes of every element, followed by the
+//   pressure of every element. Reference face 2 d + s is the face normal to
+//   the d-th reference direction at xi_d = -1 for s = 0 and xi_d = 1 for s = 1.
+// -----------------------------------------------------------------------------
+const NUM_FACES: usize = 6;
+const Q_DATA_SIZE: usize = 7;
+
+// Gauss-Legendre quadrature on [-1, 1] by Newton iteration
+fn gauss_quadrature(q: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut points = vec![0.; q];
+    let mut weights = vec![0.; q];
+    for i in 0..q {
+        let mut x = -(std::f64::consts::PI * (i as f64 + 0.75) / (q as f64 + 0.5)).cos();
+        let mut dp = 1.;
+        for _ in 0..100 {
+            let (mut p_prev, mut p) = (1., x);
+            for k in 2..=q {
+                let p_next = ((2 * k - 1) as f64 * x * p - (k - 1) as f64 * p_prev) / k as f64;
+                p_prev = p;
+                p = p_next;
+            }
+            dp = q as f64 * (x * p - p_prev) / (x * x - 1.);
+            let dx = p / dp;
+            x -= dx;
+            if dx.abs() < 1e-15 {
+                break;
+            }
+        }
+        points[i] = x;
+        weights[i] = 2. / ((1. - x * x) * dp * dp);
+    }
+    (points, weights)
+}
+
+// Hexahedral basis from values and reference gradients at tensor product
+//   Gauss points, ordered with the first direction fastest
+fn create_basis_hex<'b>(
+    ceed: &libceed::Ceed,
+    num_components: usize,
+    num_nodes: usize,
+    num_qpts_1d: usize,
+    eval: impl Fn([f64; 3], usize) -> (f64, [f64; 3]),
+) -> crate::Result<Basis<'b>> {
+    const DIM: usize = 3;
+    let (points, weights) = gauss_quadrature(num_qpts_1d);
+    let num_qpts = num_qpts_1d.pow(DIM as u32);
+    let mut q_ref = vec![0.; DIM * num_qpts];
+    let mut q_weight = vec![0.; num_qpts];
+    let mut interp = vec![0.; num_qpts * num_nodes];
+    let mut grad = vec![0.; DIM * num_qpts * num_nodes];
+    for q in 0..num_qpts {
+        let index = [
+            q % num_qpts_1d,
+            (q / num_qpts_1d) % num_qpts_1d,
+            q / (num_qpts_1d * num_qpts_1d),
+        ];
+        let xi = [points[index[0]], points[index[1]], points[index[2]]];
+        q_weight[q] = index.iter().map(|&i| weights[i]).product();
+        for d in 0..DIM {
+            q_ref[d * num_qpts + q] = xi[d];
+        }
+        for node in 0..num_nodes {
+            let (value, gradient) = eval(xi, node);
+            interp[q * num_nodes + node] = value;
+            for d in 0..DIM {
+                grad[(d * num_qpts + q) * num_nodes + node] = gradient[d];
+            }
+        }
+    }
+
+    let basis = ceed.basis_H1(
+        ElemTopology::Hex,
+        num_components,
+        num_nodes,
+        num_qpts,
+        &interp,
+        &grad,
+        &q_ref,
+        &q_weight,
+    )?;
+    Ok(basis)
+}
+
+// Trilinear geometry basis, with vertices ordered with the first direction
+//   fastest
+fn create_basis_geometry<'b>(ceed: &libceed::Ceed, num_qpts_1d: usize) -> crate::Result<Basis<'b>> {
+    create_basis_hex(ceed, 3, 8, num_qpts_1d, |xi, node| {
+        let factor = |d: usize| {
+            let side = if (node >> d) & 1 == 1 { 1. } else { -1. };
+            ((1. + side * xi[d]) / 2., side / 2.)
+        };
+        let factors = [factor(0), factor(1), factor(2)];
+        let value = factors.iter().map(|(f, _)| f).product();
+        let mut gradient = [0.; 3];
+        for (d, g) in gradient.iter_mut().enumerate() {
+            *g = (0..3)
+                .map(|e| if e == d { factors[e].1 } else { factors[e].0 })
+                .product();
+        }
+        (value, gradient)
+    })
+}
+
+// Lowest order Raviart-Thomas basis
+//   The reference vector values are evaluated as gradients and the reference
+//   divergence as values. Each basis function has unit outward flux through
+//   its face and no flux through the other faces.
+fn create_basis_raviart_thomas<'b>(
+    ceed: &libceed::Ceed,
+    num_qpts_1d: usize,
+) -> crate::Result<Basis<'b>> {
+    create_basis_hex(ceed, 1, NUM_FACES, num_qpts_1d, |xi, face| {
+        let d = face / 2;
+        let mut value = [0.; 3];
+        value[d] = if face % 2 == 1 {
+            (1. + xi[d]) / 8.
+        } else {
+            -(1. - xi[d]) / 8.
+        };
+        (1. / 8., value)
+    })
+}
+
+// Piecewise constant basis
+fn create_basis_constant<'b>(ceed: &libceed::Ceed, num_qpts_1d: usize) -> crate::Result<Basis<'b>> {
+    create_basis_hex(ceed, 1, 1, num_qpts_1d, |_, _| (1., [0.; 3]))
+}
+
+// -----------------------------------------------------------------------------
+// QFunctions
+//   qdata holds the symmetric w J^T J / |det(J)|, for the contravariant Piola
+//   transform, in Voigt order followed by w sign(det(J))
+// -----------------------------------------------------------------------------
+fn jacobian_determinant(dx: &[f64], q: usize, i: usize) -> f64 {
+    let j = |c: usize, d: usize| dx[(d * 3 + c) * q + i];
+    j(0, 0) * (j(1, 1) * j(2, 2) - j(1, 2) * j(2, 1))
+        - j(0, 1) * (j(1, 0) * j(2, 2) - j(1, 2) * j(2, 0))
+        + j(0, 2) * (j(1, 0) * j(2, 1) - j(1, 1) * j(2, 0))
+}
+
+fn setup_q_function<'b>(ceed: &libceed::Ceed) -> crate::Result<QFunction<'b>> {
+    let setup = |[dx, weights, ..]: QFunctionInputs, [q_data, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            let j = |c: usize, d: usize| dx[(d * 3 + c) * q + i];
+            let det_j = jacobian_determinant(dx, q, i);
+            let voigt = [(0, 0), (1, 1), (2, 2), (1, 2), (0, 2), (0, 1)];
+            for (k, &(a, b)) in voigt.iter().enumerate() {
+                let jtj: f64 = (0..3).map(|c| j(c, a) * j(c, b)).sum();
+                q_data[k * q + i] = weights[i] * jtj / det_j.abs();
+            }
+            q_data[6 * q + i] = weights[i] * det_j.signum();
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(setup))?
+        .input("dx", 9, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("qdata", Q_DATA_SIZE, EvalMode::None)?;
+    Ok(qf)
+}
+
+// Block operator, v = M u + B^T p and q = B u, with B = -(div u, q)
+fn apply_q_function<'b>(ceed: &libceed::Ceed) -> crate::Result<QFunction<'b>> {
+    let apply = |[u, div_u, p, q_data, ..]: QFunctionInputs,
+                 [v, div_v, q_out, ..]: QFunctionOutputs| {
+        let q = q_data.len() / Q_DATA_SIZE;
+        for i in 0..q {
+            let g = |k: usize| q_data[k * q + i];
+            let m = [[g(0), g(5), g(4)], [g(5), g(1), g(3)], [g(4), g(3), g(2)]];
+            for a in 0..3 {
+                v[a * q + i] = (0..3).map(|b| m[a][b] * u[b * q + i]).sum();
+            }
+            div_v[i] = -g(6) * p[i];
+            q_out[i] = -g(6) * div_u[i];
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("u", 3, EvalMode::Grad)?
+        .input("div_u", 1, EvalMode::Interp)?
+        .input("p", 1, EvalMode::Interp)?
+        .input("qdata", Q_DATA_SIZE, EvalMode::None)?
+        .output("v", 3, EvalMode::Grad)?
+        .output("div_v", 1, EvalMode::Interp)?
+        .output("q", 1, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+// Pressure right hand side, -(f, q) with f = -lap p
+fn rhs_q_function<'b>(
+    ceed: &libceed::Ceed,
+    solution: crate::manufactured::ManufacturedSolution,
+) -> crate::Result<QFunction<'b>> {
+    let rhs = move |[x, dx, weights, ..]: QFunctionInputs, [q_out, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            let point = [x[i], x[q + i], x[2 * q + i]];
+            let w_det_j = weights[i] * jacobian_determinant(dx, q, i).abs();
+            q_out[i] = w_det_j * solution.laplacian(&point);
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(rhs))?
+        .input("x", 3, EvalMode::Interp)?
+        .input("dx", 9, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("q", 1, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+// Squared pressure error at each quadrature point
+fn error_q_function<'b>(
+    ceed: &libceed::Ceed,
+    solution: crate::manufactured::ManufacturedSolution,
+) -> crate::Result<QFunction<'b>> {
+    let error = move |[p, x, dx, weights, ..]: QFunctionInputs, [error, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            let point = [x[i], x[q + i], x[2 * q + i]];
+            let w_det_j = weights[i] * jacobian_determinant(dx, q, i).abs();
+            error[i] = w_det_j * (p[i] - solution.value(&point)).powi(2);
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(error))?
+        .input("p", 1, EvalMode::Interp)?
+        .input("x", 3, EvalMode::Interp)?
+        .input("dx", 9, EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("error", 1, EvalMode::None)?;
+    Ok(qf)
+}
+
+// -----------------------------------------------------------------------------
+// Block operator
+// -----------------------------------------------------------------------------
+impl<'a> DarcyProblem<'a> {
+    /// Returns the DM with the flux and pressure fields
+    pub fn dm(&self) -> &DM<'a, 'a> {
+        &self.dm
+    }
+
+    /// Returns the right hand side of the block system
+    pub fn rhs(&self) -> &petsc::vector::Vector<'a> {
+        &self.rhs
+    }
+
+    // Gather local DoFs to element DoFs, orienting face fluxes outward
+    fn gather(
+        &self,
+        x_loc: &petsc::vector::Vector<'a>,
+        x_elem: &mut libceed::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        let x_loc_view = x_loc.view()?;
+        let mut x_elem_view = x_elem.view_mut()?;
+        for (i, (&offset, &sign)) in self.face_offsets.iter().zip(&self.face_signs).enumerate() {
+            x_elem_view[i] = sign * x_loc_view[offset];
+        }
+        let pressure_start = NUM_FACES * self.num_elements;
+        for (e, &offset) in self.cell_offsets.iter().enumerate() {
+            x_elem_view[pressure_start + e] = x_loc_view[offset];
+        }
+        Ok(())
+    }
+
+    // Sum element DoFs into local DoFs
+    fn scatter(
+        &self,
+        y_elem: &libceed::vector::Vector<'a>,
+        y_loc: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        let y_elem_view = y_elem.view()?;
+        let mut y_loc_view = y_loc.view_mut()?;
+        for (i, (&offset, &sign)) in self.face_offsets.iter().zip(&self.face_signs).enumerate() {
+            y_loc_view[offset] += sign * y_elem_view[i];
+        }
+        let pressure_start = NUM_FACES * self.num_elements;
+        for (e, &offset) in self.cell_offsets.iter().enumerate() {
+            y_loc_view[offset] += y_elem_view[pressure_start + e];
+        }
+        Ok(())
+    }
+
+    /// Apply the block operator, `y = A x`
+    ///
+    /// # arguments
+    ///
+    /// * `x` - Global input vector
+    /// * `y` - Global output vector
+    pub fn apply(
+        &self,
+        x: &petsc::vector::Vector<'a>,
+        y: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.apply_raw(x.as_raw(), y.as_raw())
+    }
+
+    fn apply_raw(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        let mut x_loc = self.x_loc.borrow_mut();
+        let mut y_loc = self.y_loc.borrow_mut();
+        let mut x_elem = self.x_elem.borrow_mut();
+        let mut y_elem = self.y_elem.borrow_mut();
+        let dm = self.dm.as_raw();
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::DMGlobalToLocal(dm, x, InsertMode::INSERT_VALUES, x_loc.as_raw())
+            },
+            "DMGlobalToLocal",
+        )?;
+        self.gather(&x_loc, &mut x_elem)?;
+        self.op_ceed.apply(&x_elem, &mut y_elem)?;
+        y_loc.zero_entries()?;
+        self.scatter(&y_elem, &mut y_loc)?;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(VecZeroEntries(y), "VecZeroEntries")?;
+            check(
+                DMLocalToGlobal(dm, y_loc.as_raw(), InsertMode::ADD_VALUES, y),
+                "DMLocalToGlobal",
+            )
+        }
+    }
+
+    /// Returns the L2 error of the pressure of a solution against the
+    /// manufactured solution, reduced over all ranks
+    ///
+    /// # arguments
+    ///
+    /// * `solution` - Global solution vector for the DM
+    pub fn pressure_error(&self, solution: &petsc::vector::Vector<'a>) -> crate::Result<f64> {
+        let mut x_loc = self.x_loc.borrow_mut();
+        let mut x_elem = self.x_elem.borrow_mut();
+        let mut error_qpts = self.error_qpts.borrow_mut();
+        self.dm
+            .global_to_local(solution, InsertMode::INSERT_VALUES, &mut x_loc)?;
+        self.gather(&x_loc, &mut x_elem)?;
+        self.op_error.apply(&x_elem, &mut error_qpts)?;
+        let local_error_squared: f64 = error_qpts.view()?.iter().sum();
+        let mut error_squared = 0.0;
+        {
+            use mpi::collective::{CommunicatorCollectives, SystemOperation};
+            self.comm.all_reduce_into(
+                &local_error_squared,
+                &mut error_squared,
+                SystemOperation::sum(),
+            );
+        }
+        Ok(error_squared.sqrt())
+    }
+
+    /// Returns a MatShell applying the block operator
+    pub fn mat_shell(
+        self: &std::rc::Rc<Self>,
+    ) -> crate::Result<petsc::mat::MatShell<'a, 'a, std::rc::Rc<DarcyProblem<'a>>>> {
+        let mut mat = petsc::mat::Mat::create(self.comm)?;
+        let local_size = self.rhs.local_size()?;
+        let global_size = self.rhs.global_size()?;
+        mat.set_sizes(
+            Some(local_size),
+            Some(local_size),
+            Some(global_size),
+            Some(global_size),
+        )?;
+        let mat = mat.into_shell(Box::new(self.clone()))?;
+
+        // Set operations
+        crate::petsc_ops::set_shell_operations(&mat, &[MatOperation::MATOP_MULT])?;
+
+        Ok(mat)
+    }
+}
+
+impl crate::petsc_ops::ShellOperations for DarcyProblem<'_> {
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        self.apply_raw(x, y)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup DM with one flux DoF per face and one pressure DoF per cell
+// -----------------------------------------------------------------------------
+fn setup_dm_darcy_section<'a>(dm: &mut DM<'a, 'a>) -> crate::Result<()> {
+    let dm_raw = dm.as_raw();
+    let field_names = [
+        std::ffi::CString::new("flux").unwrap(),
+        std::ffi::CString::new("pressure").unwrap(),
+    ];
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(dm_raw as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut section = std::ptr::null_mut();
+        check(PetscSectionCreate(comm, &mut section), "PetscSectionCreate")?;
+        check(
+            PetscSectionSetNumFields(section, 2),
+            "PetscSectionSetNumFields",
+        )?;
+        for (field, name) in field_names.iter().enumerate() {
+            check(
+                PetscSectionSetFieldName(section, field as petsc::Int, name.as_ptr()),
+                "PetscSectionSetFieldName",
+            )?;
+            check(
+                PetscSectionSetFieldComponents(section, field as petsc::Int, 1),
+                "PetscSectionSetFieldComponents",
+            )?;
+        }
+        let (mut point_start, mut point_end) = (0, 0);
+        check(
+            DMPlexGetChart(dm_raw, &mut point_start, &mut point_end),
+            "DMPlexGetChart",
+        )?;
+        check(
+            PetscSectionSetChart(section, point_start, point_end),
+            "PetscSectionSetChart",
+        )?;
+        // Field 0 on faces, height 1, and field 1 on cells, height 0
+        for field in 0..2 {
+            let (mut stratum_start, mut stratum_end) = (0, 0);
+            check(
+                DMPlexGetHeightStratum(
+                    dm_raw,
+                    1 - field as petsc::Int,
+                    &mut stratum_start,
+                    &mut stratum_end,
+                ),
+                "DMPlexGetHeightStratum",
+            )?;
+            for point in stratum_start..stratum_end {
+                check(PetscSectionSetDof(section, point, 1), "PetscSectionSetDof")?;
+                check(
+                    PetscSectionSetFieldDof(section, point, field, 1),
+                    "PetscSectionSetFieldDof",
+                )?;
+            }
+        }
+        check(PetscSectionSetUp(section), "PetscSectionSetUp")?;
+        check(DMSetLocalSection(dm_raw, section), "DMSetLocalSection")?;
+        check(PetscSectionDestroy(&mut section), "PetscSectionDestroy")?;
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Setup mixed Darcy problem
+// -----------------------------------------------------------------------------
+pub(crate) fn darcy_problem<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<DarcyProblem<'a>> {
+    let crate::ceed_bps::Opt {
+        problem: _,
+        order: _,
+        q_extra,
+        on_the_fly_geometry: _,
+        emulate_single_precision: _,
+        mesh_type: _,
+        refine: _,
+        local_dofs: _,
+        periodic: _,
+        mesh_transform: _,
+        kershaw_eps: _,
+        neumann: _,
+        essential_boundaries: _,
+        boundary_functions: _,
+        boundary_conditions: _,
+        neumann_boundaries: _,
+        neumann_flux: _,
+        u_bc: _,
+        coefficient: _,
+        operator: _,
+        diffusion_tensor: _,
+        diffusion_label: _,
+        wavenumber: _,
+        solution,
+    } = *options;
+    crate::backend::require_closure_q_functions(meles, "mixed Darcy problems")?;
+    // Diffusion problems default to the trigonometric solution
+    let solution = solution.resolve(crate::ceed_bps::CeedBP::BP3, false);
+
+    // Element vertex coordinates
+    let coordinates = crate::dm::hex_vertex_coordinates(meles, options, "mixed Darcy problems")?;
+    let num_elements = coordinates.len() / 24;
+
+    // Flux and pressure DM
+    let mut dm = meles.dm.borrow().clone();
+    setup_dm_darcy_section(&mut dm)?;
+    if !dm.has_label("marker")? {
+        dm.create_label("marker")?;
+        let mut label = dm.label("marker")?.unwrap();
+        dm.plex_mark_boundary_faces(1, &mut label)?;
+    }
+
+    // Element faces, matching reference faces to the cone of each cell by
+    //   centroid, and outward orientation of the face normals
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(&dm)?;
+    let mut face_offsets = Vec::with_capacity(NUM_FACES * num_elements);
+    let mut face_signs = Vec::with_capacity(NUM_FACES * num_elements);
+    let mut cell_offsets = Vec::with_capacity(num_elements);
+    let mut rhs_loc = dm.create_local_vector()?;
+    rhs_loc.zero_entries()?;
+    {
+        let marker = std::ffi::CString::new("marker").unwrap();
+        let coordinates_view = coordinates.view()?;
+        let mut rhs_loc_view = rhs_loc.view_mut()?;
+        let mut section = std::ptr::null_mut();
+        crate::check_petsc_raw(
+            unsafe { petsc::petsc_raw::DMGetLocalSection(dm.as_raw(), &mut section) },
+            "DMGetLocalSection",
+        )?;
+        let offset = |point: petsc::Int| -> crate::Result<usize> {
+            let mut offset = 0;
+            crate::check_petsc_raw(
+                unsafe { petsc::petsc_raw::PetscSectionGetOffset(section, point, &mut offset) },
+                "PetscSectionGetOffset",
+            )?;
+            Ok(offset as usize)
+        };
+        for (e, cell) in (cell_start..cell_end).enumerate() {
+            let vertex = |node: usize| -> [f64; 3] {
+                let mut x = [0.; 3];
+                for (c, x_c) in x.iter_mut().enumerate() {
+                    *x_c = coordinates_view[(e * 3 + c) * 8 + node];
+                }
+                x
+            };
+            let center = |nodes: &mut dyn Iterator<Item = usize>| -> [f64; 3] {
+                let (mut sum, mut count) = ([0.; 3], 0.);
+                for node in nodes {
+                    let x = vertex(node);
+                    (0..3).for_each(|c| sum[c] += x[c]);
+                    count += 1.;
+                }
+                [sum[0] / count, sum[1] / count, sum[2] / count]
+            };
+            let cell_center = center(&mut (0..8));
+            let reference_centers: Vec<[f64; 3]> = (0..NUM_FACES)
+                .map(|f| center(&mut (0..8).filter(|node| (node >> (f / 2)) & 1 == f % 2)))
+                .collect();
+            // -- Orientation of the element map, from the Jacobian at the center
+            let det_j_sign = {
+                let j = |c: usize, d: usize| {
+                    reference_centers[2 * d + 1][c] - reference_centers[2 * d][c]
+                };
+                let det_j = j(0, 0) * (j(1, 1) * j(2, 2) - j(1, 2) * j(2, 1))
+                    - j(0, 1) * (j(1, 0) * j(2, 2) - j(1, 2) * j(2, 0))
+                    + j(0, 2) * (j(1, 0) * j(2, 1) - j(1, 1) * j(2, 0));
+                det_j.signum()
+            };
+            // -- Cone faces
+            let cone = unsafe {
+                let mut cone_size = 0;
+                let mut cone = std::ptr::null();
+                crate::check_petsc_raw(
+                    petsc::petsc_raw::DMPlexGetConeSize(dm.as_raw(), cell, &mut cone_size),
+                    "DMPlexGetConeSize",
+                )?;
+                crate::check_petsc_raw(
+                    petsc::petsc_raw::DMPlexGetCone(dm.as_raw(), cell, &mut cone),
+                    "DMPlexGetCone",
+                )?;
+                std::slice::from_raw_parts(cone, cone_size as usize).to_vec()
+            };
+            let cone_geometry = cone
+                .iter()
+                .map(|&face| crate::dm::plex_point_geometry(&dm, face))
+                .collect::<crate::Result<Vec<_>>>()?;
+            for reference_center in &reference_centers {
+                let distance = |x: &[f64; 3]| -> f64 {
+                    (0..3).map(|c| (x[c] - reference_center[c]).powi(2)).sum()
+                };
+                let (k, (centroid, normal)) = cone_geometry
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, (a, _)), (_, (b, _))| {
+                        distance(a).partial_cmp(&distance(b)).unwrap()
+                    })
+                    .unwrap();
+                let outward: f64 = (0..3)
+                    .map(|c| normal[c] * (reference_center[c] - cell_center[c]))
+                    .sum();
+                let face_offset = offset(cone[k])?;
+                face_offsets.push(face_offset);
+                face_signs.push(outward.signum() * det_j_sign);
+                // ---- Natural pressure boundary condition, -<p, v.n>
+                let mut value = 0;
+                crate::check_petsc_raw(
+                    unsafe {
+                        petsc::petsc_raw::DMGetLabelValue(
+                            dm.as_raw(),
+                            marker.as_ptr(),
+                            cone[k],
+                            &mut value,
+                        )
+                    },
+                    "DMGetLabelValue",
+                )?;
+                if value == 1 {
+                    rhs_loc_view[face_offset] -= outward.signum() * solution.value(centroid);
+                }
+            }
+            cell_offsets.push(offset(cell)?);
+        }
+    }
+
+    // Bases and restrictions
+    let num_qpts_1d = 2 + q_extra;
+    let basis_x = create_basis_geometry(&meles.ceed, num_qpts_1d)?;
+    let basis_u = create_basis_raviart_thomas(&meles.ceed, num_qpts_1d)?;
+    let basis_p = create_basis_constant(&meles.ceed, num_qpts_1d)?;
+    let num_quadrature_points = basis_x.num_quadrature_points();
+    let elem_size = (NUM_FACES + 1) * num_elements;
+    let restr_u = {
+        let offsets: Vec<i32> = (0..NUM_FACES * num_elements).map(|i| i as i32).collect();
+        meles.ceed.elem_restriction(
+            num_elements,
+            NUM_FACES,
+            1,
+            1,
+            elem_size,
+            MemType::Host,
+            &offsets,
+        )?
+    };
+    let restr_p = {
+        let offsets: Vec<i32> = (0..num_elements)
+            .map(|e| (NUM_FACES * num_elements + e) as i32)
+            .collect();
+        meles
+            .ceed
+            .elem_restriction(num_elements, 1, 1, 1, elem_size, MemType::Host, &offsets)?
+    };
+    let restr_x_elem = crate::qdata::QDataVector::restriction(&meles.ceed, num_elements, 8, 3)?;
+    let restr_qdata = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        num_elements,
+        num_quadrature_points,
+        Q_DATA_SIZE,
+    )?;
+    let restr_error = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        num_elements,
+        num_quadrature_points,
+        1,
+    )?;
+
+    // Geometric setup data
+    let mut qdata = restr_qdata.create_lvector()?;
+    let qf_setup = setup_q_function(&meles.ceed)?;
+    meles
+        .ceed
+        .operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("dx", &restr_x_elem, &basis_x, VectorOpt::Active)?
+        .field(
+            "weights",
+            ElemRestrictionOpt::None,
+            &basis_x,
+            VectorOpt::None,
+        )?
+        .field(
+            "qdata",
+            &restr_qdata,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?
+        .apply(&coordinates, &mut qdata)?;
+
+    // Pressure right hand side
+    let mut rhs_elem = meles.ceed.vector(elem_size)?;
+    rhs_elem.set_value(0.0)?;
+    let qf_rhs = rhs_q_function(&meles.ceed, solution)?;
+    meles
+        .ceed
+        .operator(&qf_rhs, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("x", &restr_x_elem, &basis_x, VectorOpt::Active)?
+        .field("dx", &restr_x_elem, &basis_x, VectorOpt::Active)?
+        .field(
+            "weights",
+            ElemRestrictionOpt::None,
+            &basis_x,
+            VectorOpt::None,
+        )?
+        .field("q", &restr_p, &basis_p, VectorOpt::Active)?
+        .check()?
+        .apply(&coordinates, &mut rhs_elem)?;
+    {
+        let rhs_elem_view = rhs_elem.view()?;
+        let mut rhs_loc_view = rhs_loc.view_mut()?;
+        for (e, &offset) in cell_offsets.iter().enumerate() {
+            rhs_loc_view[offset] += rhs_elem_view[NUM_FACES * num_elements + e];
+        }
+    }
+    let mut rhs = dm.create_global_vector()?;
+    rhs.zero_entries()?;
+    dm.local_to_global(&rhs_loc, InsertMode::ADD_VALUES, &mut rhs)?;
+
+    // Operators
+    let qf_apply = apply_q_function(&meles.ceed)?;
+    let op_ceed = meles
+        .ceed
+        .operator(&qf_apply, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("u", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("div_u", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("p", &restr_p, &basis_p, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &qdata)?
+        .field("v", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("div_v", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("q", &restr_p, &basis_p, VectorOpt::Active)?
+        .check()?;
+    let qf_error = error_q_function(&meles.ceed, solution)?;
+    let op_error = meles
+        .ceed
+        .operator(&qf_error, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("p", &restr_p, &basis_p, VectorOpt::Active)?
+        .field("x", &restr_x_elem, &basis_x, &coordinates)?
+        .field("dx", &restr_x_elem, &basis_x, &coordinates)?
+        .field(
+            "weights",
+            ElemRestrictionOpt::None,
+            &basis_x,
+            VectorOpt::None,
+        )?
+        .field(
+            "error",
+            &restr_error,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?;
+
+    Ok(DarcyProblem {
+        comm: meles.comm,
+        x_loc: RefCell::new(dm.create_local_vector()?),
+        y_loc: RefCell::new(dm.create_local_vector()?),
+        dm,
+        op_ceed,
+        op_error,
+        num_elements,
+        face_offsets,
+        face_signs,
+        cell_offsets,
+        rhs,
+        x_elem: RefCell::new(meles.ceed.vector(elem_size)?),
+        y_elem: RefCell::new(meles.ceed.vector(elem_size)?),
+        error_qpts: RefCell::new(restr_error.create_lvector()?),
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,173 @@
+use crate::prelude::*;
+use mpi::traits::Communicator;
+
+// -----------------------------------------------------------------------------
+// Deflation space
+// -----------------------------------------------------------------------------
+/// Deflation vectors for a coarse space corrected Krylov solve
+///
+/// The vectors are assembled into the columns of the deflation matrix used by
+/// PETSc's `PCDEFLATION`. Constants per subdomain are a robust default for
+/// highly heterogeneous coefficients; low order modes may be added with
+/// `with_vector`.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+/// let meles = meles::Meles::new(
+///     &petsc,
+///     "./examples/meles.yml",
+///     meles::MethodType::BenchmarkProblem,
+/// )?;
+///
+/// // deflated CG
+/// let mat = meles.mat_shell(&petsc)?;
+/// let mut ksp = petsc.ksp_create()?;
+/// ksp.set_operators(&mat, &mat)?;
+/// meles.deflation_space(&petsc)?.set_up_ksp(&mut ksp)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DeflationSpace<'a> {
+    pub(crate) vectors: Vec<petsc::vector::Vector<'a>>,
+}
+
+impl<'a> DeflationSpace<'a> {
+    /// Returns an empty deflation space
+    pub fn new() -> Self {
+        Self { vectors: vec![] }
+    }
+
+    /// Add a deflation vector
+    ///
+    /// # arguments
+    ///
+    /// * `vector` - Global vector compatible with the operator
+    pub fn with_vector(mut self, vector: petsc::vector::Vector<'a>) -> Self {
+        self.vectors.push(vector);
+        self
+    }
+
+    /// Returns the deflation vectors
+    pub fn vectors(&self) -> &[petsc::vector::Vector<'a>] {
+        &self.vectors
+    }
+
+    /// Configure a KSP for a deflated solve with this space
+    ///
+    /// The KSP defaults to CG with `PCDEFLATION`; both may be overridden from
+    /// the options database.
+    ///
+    /// # arguments
+    ///
+    /// * `ksp` - KSP with operators already set
+    pub fn set_up_ksp(&self, ksp: &mut KSP<'a, '_>) -> crate::Result<()> {
+        if self.vectors.is_empty() {
+            return Err(crate::Error {
+                message: "deflation space has no vectors".to_string(),
+            });
+        }
+        ksp.set_type(KSPType::KSPCG)?;
+        let deflation_type = std::ffi::CString::new("deflation").unwrap();
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut comm = std::mem::zeroed();
+            check(
+                PetscObjectGetComm(ksp.as_raw() as PetscObject, &mut comm),
+                "PetscObjectGetComm",
+            )?;
+
+            // Deflation matrix with one column per vector
+            let local_size = self.vectors[0].local_size()?;
+            let global_size = self.vectors[0].global_size()?;
+            let mut w = std::ptr::null_mut();
+            check(
+                MatCreateDense(
+                    comm,
+                    local_size,
+                    PETSC_DECIDE,
+                    global_size,
+                    self.vectors.len() as petsc::Int,
+                    std::ptr::null_mut(),
+                    &mut w,
+                ),
+                "MatCreateDense",
+            )?;
+            let result = (|| {
+                for (column, vector) in self.vectors.iter().enumerate() {
+                    let start = vector.get_ownership_range()?.start;
+                    let view = vector.view()?;
+                    for i in 0..local_size as usize {
+                        check(
+                            MatSetValue(
+                                w,
+                                start + i as petsc::Int,
+                                column as petsc::Int,
+                                view[i],
+                                InsertMode::INSERT_VALUES,
+                            ),
+                            "MatSetValue",
+                        )?;
+                    }
+                }
+                check(
+                    MatAssemblyBegin(w, MatAssemblyType::MAT_FINAL_ASSEMBLY),
+                    "MatAssemblyBegin",
+                )?;
+                check(
+                    MatAssemblyEnd(w, MatAssemblyType::MAT_FINAL_ASSEMBLY),
+                    "MatAssemblyEnd",
+                )?;
+
+                // Deflation preconditioner, referencing the matrix
+                let mut pc = std::ptr::null_mut();
+                check(KSPGetPC(ksp.as_raw(), &mut pc), "KSPGetPC")?;
+                check(PCSetType(pc, deflation_type.as_ptr()), "PCSetType")?;
+                check(
+                    PCDeflationSetSpace(pc, w, PetscBool::PETSC_FALSE),
+                    "PCDeflationSetSpace",
+                )
+            })();
+            check(MatDestroy(&mut w), "MatDestroy")?;
+            result?;
+        }
+        let _options_lock = crate::config::options_lock();
+        ksp.set_from_options()?;
+        Ok(())
+    }
+}
+
+impl<'a> Default for DeflationSpace<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Constants per subdomain
+//   One vector per MPI rank, equal to one on the DoFs owned by that rank
+// -----------------------------------------------------------------------------
+pub(crate) fn subdomain_constants<'a>(
+    meles: &crate::Meles<'a>,
+) -> crate::Result<DeflationSpace<'a>> {
+    let rank = meles.comm.rank();
+    let mut space = DeflationSpace::new();
+    for subdomain in 0..meles.comm.size() {
+        let mut vector = meles.dm.borrow().create_global_vector()?;
+        vector.set_all(0.0)?;
+        if subdomain == rank {
+            let local_size = vector.local_size()? as usize;
+            let mut view = vector.view_mut()?;
+            for i in 0..local_size {
+                view[i] = 1.0;
+            }
+        }
+        space = space.with_vector(vector);
+    }
+    Ok(space)
+}
+
+// -----------------------------------------------------------------------------
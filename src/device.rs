@@ -0,0 +1,232 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Device binding
+//   Each rank on a node is bound to a visible device round robin by its rank
+//   on the node, with the visible devices counted from the vendor environment
+//   variables or, in containers, the device files exposed to the process
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Binding of an MPI rank to a device on its node
+pub struct DeviceBinding {
+    /// Rank among the ranks sharing the node
+    pub node_rank: usize,
+    /// Number of ranks sharing the node
+    pub node_size: usize,
+    /// Number of devices visible to the rank
+    pub num_devices: usize,
+    /// Index of the bound device among the visible devices
+    pub device_id: usize,
+}
+
+impl fmt::Display for DeviceBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} visible, node rank {} of {}",
+            self.device_id, self.num_devices, self.node_rank, self.node_size
+        )
+    }
+}
+
+const DEVICE_ID: &str = "device_id=";
+
+// Environment variables listing the visible devices, device file prefixes,
+//   and, for DRM render nodes, the PCI vendor ID of the devices of a libCEED
+//   resource
+fn visibility(resource: &str) -> (&'static [&'static str], &'static str, Option<&'static str>) {
+    if resource.starts_with("/gpu/cuda") {
+        (&["CUDA_VISIBLE_DEVICES"], "nvidia", None)
+    } else if resource.starts_with("/gpu/hip") {
+        (
+            &["HIP_VISIBLE_DEVICES", "ROCR_VISIBLE_DEVICES"],
+            "dri/renderD",
+            Some("0x1002"),
+        )
+    } else {
+        (&["ZE_AFFINITY_MASK"], "dri/renderD", Some("0x8086"))
+    }
+}
+
+// Discrete GPU of a vendor behind a DRM render node
+//   Render nodes also expose integrated GPUs, which sit on PCI bus 0 next to
+//   the host bridge, while discrete GPUs are behind PCIe ports
+fn is_discrete_render_node(name: &str, vendor: &str) -> bool {
+    let device = std::path::Path::new("/sys/class/drm")
+        .join(name)
+        .join("device");
+    let vendor_matches =
+        std::fs::read_to_string(device.join("vendor")).is_ok_and(|id| id.trim() == vendor);
+    let on_root_bus = std::fs::canonicalize(&device)
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .and_then(|address| address.to_str())
+                .map(|address| address.split(':').nth(1) == Some("00"))
+        })
+        .unwrap_or(true);
+    vendor_matches && !on_root_bus
+}
+
+// Number of devices visible to the process
+fn num_visible_devices(resource: &str) -> usize {
+    let (variables, file_prefix, vendor) = visibility(resource);
+    if let Some(devices) = variables
+        .iter()
+        .find_map(|variable| std::env::var(variable).ok())
+    {
+        return devices
+            .split(',')
+            .filter(|device| !device.trim().is_empty())
+            .count();
+    }
+    // Device files are numbered, such as /dev/nvidia0, unlike /dev/nvidiactl
+    let (directory, prefix) = match file_prefix.rsplit_once('/') {
+        Some((directory, prefix)) => (format!("/dev/{}", directory), prefix),
+        None => ("/dev".to_string(), file_prefix),
+    };
+    std::fs::read_dir(directory).map_or(0, |entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.file_name().to_str().is_some_and(|name| {
+                    name.strip_prefix(prefix).is_some_and(|number| {
+                        !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+                    }) && vendor.is_none_or(|vendor| is_discrete_render_node(name, vendor))
+                })
+            })
+            .count()
+    })
+}
+
+// Device of a resource with an explicit device_id
+fn explicit_device_id(resource: &str) -> crate::Result<Option<usize>> {
+    match resource.split_once(DEVICE_ID) {
+        Some((_, id)) => id
+            .split(|c| c == ':' || c == ',')
+            .next()
+            .unwrap_or("")
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| crate::Error {
+                message: format!("invalid device_id in libCEED resource {}", resource),
+            }),
+        None => Ok(None),
+    }
+}
+
+// Bind the rank to a device for a libCEED resource, None for host resources
+//   Collective, as the ranks on each node are counted
+fn bind(
+    comm: &mpi::topology::UserCommunicator,
+    resource: &str,
+) -> crate::Result<Option<DeviceBinding>> {
+    use mpi::traits::Communicator;
+    if !resource.starts_with("/gpu") {
+        return Ok(None);
+    }
+    let node = comm.split_shared(comm.rank());
+    let (node_rank, node_size) = (node.rank() as usize, node.size() as usize);
+    let num_devices = num_visible_devices(resource);
+    if num_devices == 0 {
+        return Err(crate::Error {
+            message: format!(
+                "no devices are visible for libCEED resource {}, check {}",
+                resource,
+                visibility(resource).0.join(" and ")
+            ),
+        });
+    }
+    let device_id = match explicit_device_id(resource)? {
+        Some(device_id) if device_id >= num_devices => {
+            return Err(crate::Error {
+                message: format!(
+                    "libCEED resource {} requests device {}, but only {} devices are visible",
+                    resource, device_id, num_devices
+                ),
+            })
+        }
+        Some(device_id) => device_id,
+        None => node_rank % num_devices,
+    };
+    Ok(Some(DeviceBinding {
+        node_rank,
+        node_size,
+        num_devices,
+        device_id,
+    }))
+}
+
+// Resource with the device of a binding
+pub(crate) fn bound_resource(resource: &str, binding: &Option<DeviceBinding>) -> String {
+    match binding {
+        Some(binding) if !resource.contains(DEVICE_ID) => {
+            format!("{}:{}{}", resource, DEVICE_ID, binding.device_id)
+        }
+        _ => resource.to_string(),
+    }
+}
+
+// Select the bound device for PETSc device vectors too, unless set explicitly
+//   with -device_select_cuda or -device_select_hip, so PETSc and libCEED
+//   arrays are on the same device
+//   PETSc reads those options in PetscInitialize, so the device is configured
+//   directly, making it current for later PETSc device allocations
+fn select_petsc_device(resource: &str, binding: &DeviceBinding) -> crate::Result<()> {
+    use petsc::petsc_raw::*;
+    let (vendor, device_type) = if resource.starts_with("/gpu/cuda") {
+        ("cuda", PetscDeviceType::PETSC_DEVICE_CUDA)
+    } else if resource.starts_with("/gpu/hip") {
+        ("hip", PetscDeviceType::PETSC_DEVICE_HIP)
+    } else {
+        return Ok(());
+    };
+    let name = std::ffi::CString::new(format!("-device_select_{}", vendor)).unwrap();
+    unsafe {
+        let check = crate::check_petsc_raw;
+        let mut set = PetscBool::PETSC_FALSE;
+        check(
+            PetscOptionsHasName(
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                name.as_ptr(),
+                &mut set,
+            ),
+            "PetscOptionsHasName",
+        )?;
+        if set == PetscBool::PETSC_TRUE {
+            return Ok(());
+        }
+        let mut device = std::ptr::null_mut();
+        check(
+            PetscDeviceCreate(device_type, binding.device_id as petsc::Int, &mut device),
+            "PetscDeviceCreate",
+        )?;
+        let result = check(PetscDeviceConfigure(device), "PetscDeviceConfigure");
+        check(PetscDeviceDestroy(&mut device), "PetscDeviceDestroy")?;
+        result
+    }
+}
+
+// Create a Ceed on the device bound to the rank, noting once per node when
+//   ranks share devices
+//   Collective, as ranks are bound by their rank on the node
+pub(crate) fn init_ceed(
+    comm: &mpi::topology::UserCommunicator,
+    resource: &str,
+) -> crate::Result<(libceed::Ceed, Option<DeviceBinding>)> {
+    let binding = bind(comm, resource)?;
+    if let Some(binding) = &binding {
+        select_petsc_device(resource, binding)?;
+        if binding.node_rank == 0 && binding.node_size > binding.num_devices {
+            crate::petsc_info(&format!(
+                "{} ranks share {} visible devices on a node for libCEED resource {}",
+                binding.node_size, binding.num_devices, resource
+            ))?;
+        }
+    }
+    let ceed = libceed::Ceed::init(&bound_resource(resource, &binding));
+    Ok((ceed, binding))
+}
+
+// -----------------------------------------------------------------------------
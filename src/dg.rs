@@ -0,0 +1,675 @@
+use crate::prelude::*;
+use std::rc::Rc;
+
+// -----------------------------------------------------------------------------
+// Flux hooks
+// -----------------------------------------------------------------------------
+/// Physical and numerical fluxes of a hyperbolic system, `u_t + div F(u) = 0`,
+/// for a DG discretization
+///
+/// The physical flux `F(u)`, with one row of three values per component, is
+/// integrated against the test function gradients in each element. The
+/// numerical flux `F*(u-, u+, n)`, the flux through a face with unit normal
+/// `n` pointing from the `u-` side to the `u+` side, couples neighboring
+/// elements. On the boundary, the exterior state `u+` is given by the
+/// boundary state hook from the point, the interior state, and the outward
+/// unit normal, and defaults to the interior state. Hooks run in Rust closure
+/// QFunctions on the host.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// // linear advection with the upwind flux and zero inflow
+/// let wind = [1.0, 0.5, 0.25];
+/// let flux = meles::DgFlux::new(
+///     1,
+///     move |u, f| f[0] = [wind[0] * u[0], wind[1] * u[0], wind[2] * u[0]],
+///     move |u_minus, u_plus, n, f| {
+///         let a_n = wind[0] * n[0] + wind[1] * n[1] + wind[2] * n[2];
+///         f[0] = a_n * if a_n > 0.0 { u_minus[0] } else { u_plus[0] };
+///     },
+/// )
+/// .boundary_state(|_x, _u_minus, _n, u_plus| u_plus[0] = 0.0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DgFlux {
+    pub(crate) num_components: usize,
+    pub(crate) flux: Rc<dyn Fn(&[f64], &mut [[f64; 3]])>,
+    pub(crate) numerical_flux: Rc<dyn Fn(&[f64], &[f64], [f64; 3], &mut [f64])>,
+    pub(crate) boundary_state: Rc<dyn Fn([f64; 3], &[f64], [f64; 3], &mut [f64])>,
+}
+
+impl DgFlux {
+    /// Returns flux hooks with the interior state as the boundary state
+    ///
+    /// # arguments
+    ///
+    /// * `num_components` - Number of components of the state
+    /// * `flux` - Physical flux `F(u)`, written as one row per component
+    /// * `numerical_flux` - Numerical flux `F*(u-, u+, n)` through a face
+    pub fn new(
+        num_components: usize,
+        flux: impl Fn(&[f64], &mut [[f64; 3]]) + 'static,
+        numerical_flux: impl Fn(&[f64], &[f64], [f64; 3], &mut [f64]) + 'static,
+    ) -> Self {
+        Self {
+            num_components,
+            flux: Rc::new(flux),
+            numerical_flux: Rc::new(numerical_flux),
+            boundary_state: Rc::new(
+                |_: [f64; 3], u_minus: &[f64], _: [f64; 3], u_plus: &mut [f64]| {
+                    u_plus.copy_from_slice(u_minus)
+                },
+            ),
+        }
+    }
+
+    /// Set the exterior state on the boundary
+    ///
+    /// # arguments
+    ///
+    /// * `boundary_state` - Exterior state `u+` from the point, the interior
+    ///                        state `u-`, and the outward unit normal
+    pub fn boundary_state(
+        mut self,
+        boundary_state: impl Fn([f64; 3], &[f64], [f64; 3], &mut [f64]) + 'static,
+    ) -> Self {
+        self.boundary_state = Rc::new(boundary_state);
+        self
+    }
+}
+
+// -----------------------------------------------------------------------------
+// DG problem
+// -----------------------------------------------------------------------------
+/// Discontinuous Galerkin spectral element (DGSEM) discretization of a
+/// hyperbolic system
+///
+/// Discontinuous Lagrange elements of the benchmark problem order use
+/// Gauss-Lobatto nodes collocated with the quadrature points, so the mass
+/// matrix is diagonal. The residual MatShell applies the volume and face
+/// operators together, `R(u) = -(F(u), grad v) + <F*(u-, u+, n), v>`, and the
+/// semi-discrete system is `M u_t = -R(u)`. For linear fluxes and boundary
+/// states linear in `u`, `R` is linear and the MatShell is also the operator
+/// of implicit time integrators.
+///
+/// Note: Requires a plex mesh with a single hexahedral cell type on a single
+/// rank, since neighboring elements across a partition are not ghosted. The
+/// flux hooks are Rust closures, so with device libCEED resources the DG
+/// operators are applied on the host.
+pub struct DgProblem<'a> {
+    /// Residual `R(u)`, with volume and face terms
+    pub residual: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    /// Diagonal of the mass matrix
+    pub mass: petsc::vector::Vector<'a>,
+}
+
+// -----------------------------------------------------------------------------
+// Reference element
+//   Nodes are ordered with the first direction fastest, and reference face
+//   2 d + s is normal to the d-th reference direction at xi_d = -1 for s = 0
+//   and xi_d = 1 for s = 1
+// -----------------------------------------------------------------------------
+const NUM_FACES: usize = 6;
+const FACE_Q_DATA_SIZE: usize = 6;
+
+// Gauss-Lobatto-Legendre nodes and weights on [-1, 1] by Newton iteration
+pub(crate) fn gauss_lobatto_quadrature(p: usize) -> (Vec<f64>, Vec<f64>) {
+    let n = p - 1;
+    let mut points = vec![0.; p];
+    let mut weights = vec![0.; p];
+    for i in 0..p {
+        let mut x = -(std::f64::consts::PI * i as f64 / n as f64).cos();
+        let mut p_n = 1.;
+        for _ in 0..100 {
+            let (mut p_prev, mut p_curr) = (1., x);
+            for k in 2..=n {
+                let p_next = ((2 * k - 1) as f64 * x * p_curr - (k - 1) as f64 * p_prev) / k as f64;
+                p_prev = p_curr;
+                p_curr = p_next;
+            }
+            p_n = p_curr;
+            let dx = (x * p_curr - p_prev) / ((n + 1) as f64 * p_curr);
+            x -= dx;
+            if dx.abs() < 1e-15 {
+                break;
+            }
+        }
+        points[i] = x;
+        weights[i] = 2. / ((n * (n + 1)) as f64 * p_n * p_n);
+    }
+    (points, weights)
+}
+
+// Trilinear map of a hexahedron and its derivative in each reference direction
+fn trilinear(vertices: &[[f64; 3]; 8], xi: [f64; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut x = [0.; 3];
+    let mut dx = [[0.; 3]; 3];
+    for (v, vertex) in vertices.iter().enumerate() {
+        let side = |d: usize| if (v >> d) & 1 == 1 { 1. } else { -1. };
+        let phi = |d: usize| (1. + side(d) * xi[d]) / 2.;
+        for c in 0..3 {
+            x[c] += phi(0) * phi(1) * phi(2) * vertex[c];
+            for (d, dx_d) in dx.iter_mut().enumerate() {
+                let others: f64 = (0..3).filter(|&e| e != d).map(phi).product();
+                dx_d[c] += side(d) / 2. * others * vertex[c];
+            }
+        }
+    }
+    (x, dx)
+}
+
+// Tangential reference directions of a reference face
+fn face_tangents(face: usize) -> (usize, usize) {
+    match face / 2 {
+        0 => (1, 2),
+        1 => (0, 2),
+        _ => (0, 1),
+    }
+}
+
+// Element nodes on a reference face, with the first tangential direction
+//   fastest
+fn face_nodes(p: usize, face: usize) -> Vec<usize> {
+    let (t_1, t_2) = face_tangents(face);
+    let mut nodes = Vec::with_capacity(p * p);
+    for b in 0..p {
+        for a in 0..p {
+            let mut index = [0; 3];
+            index[face / 2] = (face % 2) * (p - 1);
+            index[t_1] = a;
+            index[t_2] = b;
+            nodes.push(index[0] + p * (index[1] + p * index[2]));
+        }
+    }
+    nodes
+}
+
+// Reference coordinates of an element node
+fn node_reference_coordinates(points: &[f64], node: usize) -> [f64; 3] {
+    let p = points.len();
+    [
+        points[node % p],
+        points[(node / p) % p],
+        points[node / (p * p)],
+    ]
+}
+
+// Reference face of an element nearest to a point
+fn nearest_reference_face(vertices: &[[f64; 3]; 8], x: [f64; 3]) -> usize {
+    let distance = |face: usize| -> f64 {
+        let mut xi = [0.; 3];
+        xi[face / 2] = if face % 2 == 1 { 1. } else { -1. };
+        let (center, _) = trilinear(vertices, xi);
+        (0..3).map(|c| (center[c] - x[c]).powi(2)).sum()
+    };
+    (0..NUM_FACES)
+        .min_by(|&a, &b| distance(a).partial_cmp(&distance(b)).unwrap())
+        .unwrap()
+}
+
+// -----------------------------------------------------------------------------
+// QFunctions
+//   Volume qdata is that of advection-diffusion, w det(J) followed by the
+//   entries of J^{-1}, and face qdata holds the outward normal scaled by the
+//   surface quadrature weight followed by the physical coordinates
+// -----------------------------------------------------------------------------
+use crate::advection::Q_DATA_SIZE;
+
+fn volume_q_function<'b>(ceed: &libceed::Ceed, flux: &DgFlux) -> crate::Result<QFunction<'b>> {
+    let num_components = flux.num_components;
+    let physical_flux = flux.flux.clone();
+    let apply = move |[u, q_data, ..]: QFunctionInputs, [dv, ..]: QFunctionOutputs| {
+        let q = q_data.len() / Q_DATA_SIZE;
+        let mut u_i = vec![0.; num_components];
+        let mut f_i = vec![[0.; 3]; num_components];
+        for i in 0..q {
+            for (c, u_c) in u_i.iter_mut().enumerate() {
+                *u_c = u[c * q + i];
+            }
+            physical_flux(&u_i, &mut f_i);
+            for d in 0..3 {
+                for (c, f_c) in f_i.iter().enumerate() {
+                    let f_d: f64 = (0..3)
+                        .map(|j| q_data[(1 + d * 3 + j) * q + i] * f_c[j])
+                        .sum();
+                    dv[(d * num_components + c) * q + i] = -q_data[i] * f_d;
+                }
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("u", num_components, EvalMode::Interp)?
+        .input("qdata", Q_DATA_SIZE, EvalMode::None)?
+        .output("dv", 3 * num_components, EvalMode::Grad)?;
+    Ok(qf)
+}
+
+// Unit normal and surface measure from face qdata
+fn face_normal(q_data: &[f64], q: usize, i: usize) -> ([f64; 3], f64) {
+    let normal = [q_data[i], q_data[q + i], q_data[2 * q + i]];
+    let area = normal.iter().map(|n| n * n).sum::<f64>().sqrt();
+    ([normal[0] / area, normal[1] / area, normal[2] / area], area)
+}
+
+fn interior_face_q_function<'b>(
+    ceed: &libceed::Ceed,
+    flux: &DgFlux,
+) -> crate::Result<QFunction<'b>> {
+    let num_components = flux.num_components;
+    let numerical_flux = flux.numerical_flux.clone();
+    let apply = move |[u_minus, u_plus, q_data, ..]: QFunctionInputs,
+                      [v_minus, v_plus, ..]: QFunctionOutputs| {
+        let q = q_data.len() / FACE_Q_DATA_SIZE;
+        let mut u_minus_i = vec![0.; num_components];
+        let mut u_plus_i = vec![0.; num_components];
+        let mut f_i = vec![0.; num_components];
+        for i in 0..q {
+            for c in 0..num_components {
+                u_minus_i[c] = u_minus[c * q + i];
+                u_plus_i[c] = u_plus[c * q + i];
+            }
+            let (normal, area) = face_normal(q_data, q, i);
+            numerical_flux(&u_minus_i, &u_plus_i, normal, &mut f_i);
+            for (c, f_c) in f_i.iter().enumerate() {
+                v_minus[c * q + i] = area * f_c;
+                v_plus[c * q + i] = -area * f_c;
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("u_minus", num_components, EvalMode::Interp)?
+        .input("u_plus", num_components, EvalMode::Interp)?
+        .input("qdata", FACE_Q_DATA_SIZE, EvalMode::None)?
+        .output("v_minus", num_components, EvalMode::Interp)?
+        .output("v_plus", num_components, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+fn boundary_face_q_function<'b>(
+    ceed: &libceed::Ceed,
+    flux: &DgFlux,
+) -> crate::Result<QFunction<'b>> {
+    let num_components = flux.num_components;
+    let numerical_flux = flux.numerical_flux.clone();
+    let boundary_state = flux.boundary_state.clone();
+    let apply = move |[u_minus, q_data, ..]: QFunctionInputs, [v_minus, ..]: QFunctionOutputs| {
+        let q = q_data.len() / FACE_Q_DATA_SIZE;
+        let mut u_minus_i = vec![0.; num_components];
+        let mut u_plus_i = vec![0.; num_components];
+        let mut f_i = vec![0.; num_components];
+        for i in 0..q {
+            for (c, u_c) in u_minus_i.iter_mut().enumerate() {
+                *u_c = u_minus[c * q + i];
+            }
+            let x = [q_data[3 * q + i], q_data[4 * q + i], q_data[5 * q + i]];
+            let (normal, area) = face_normal(q_data, q, i);
+            boundary_state(x, &u_minus_i, normal, &mut u_plus_i);
+            numerical_flux(&u_minus_i, &u_plus_i, normal, &mut f_i);
+            for (c, f_c) in f_i.iter().enumerate() {
+                v_minus[c * q + i] = area * f_c;
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("u_minus", num_components, EvalMode::Interp)?
+        .input("qdata", FACE_Q_DATA_SIZE, EvalMode::None)?
+        .output("v_minus", num_components, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+// -----------------------------------------------------------------------------
+// Face restrictions
+//   Nodes of the u+ side are matched to the nodes of the u- side by their
+//   physical coordinates, so both sides see the same face quadrature points
+// -----------------------------------------------------------------------------
+#[derive(Default)]
+struct FaceSet {
+    num_faces: usize,
+    offsets_minus: Vec<i32>,
+    offsets_plus: Vec<i32>,
+    qdata: Vec<f64>,
+}
+
+// -----------------------------------------------------------------------------
+// Setup DG problem
+// -----------------------------------------------------------------------------
+pub(crate) fn dg_problem<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    flux: DgFlux,
+) -> crate::Result<DgProblem<'a>> {
+    use mpi::traits::Communicator;
+    if meles.comm.size() > 1 {
+        return Err(crate::Error {
+            message: "DG problems require a single rank, as neighboring elements across a partition are not ghosted".to_string(),
+        });
+    }
+    // The flux hooks are Rust closures, so device resources apply the DG
+    //   operators on the host Ceed
+    let ceed = crate::backend::host_ceed(meles);
+    let num_components = flux.num_components;
+    let p = options.order + 1;
+    let num_nodes = p * p * p;
+    let num_face_nodes = p * p;
+    let (points, weights) = gauss_lobatto_quadrature(p);
+
+    // Element vertex coordinates
+    let coordinates = {
+        let coordinates = crate::dm::hex_vertex_coordinates(meles, options, "DG problems")?;
+        let coordinates_view = coordinates.view()?;
+        ceed.vector_from_slice(&coordinates_view)?
+    };
+    let num_elements = coordinates.len() / 24;
+    let vertices: Vec<[[f64; 3]; 8]> = {
+        let coordinates_view = coordinates.view()?;
+        (0..num_elements)
+            .map(|e| {
+                let mut vertices = [[0.; 3]; 8];
+                for (v, vertex) in vertices.iter_mut().enumerate() {
+                    for (c, x_c) in vertex.iter_mut().enumerate() {
+                        *x_c = coordinates_view[(e * 3 + c) * 8 + v];
+                    }
+                }
+                vertices
+            })
+            .collect()
+    };
+    let node_coordinates = |e: usize, node: usize| {
+        trilinear(&vertices[e], node_reference_coordinates(&points, node)).0
+    };
+
+    // Discontinuous DM
+    let mut dm = meles.dm.borrow().clone();
+    crate::dm::setup_dm_discontinuous(&mut dm, options.order, num_components)?;
+    let x_loc = dm.create_local_vector()?;
+    let l_size = x_loc.local_size()? as usize;
+    let (cell_start, _) = crate::dm::plex_cell_range(&dm)?;
+    let mut section = std::ptr::null_mut();
+    crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::DMGetLocalSection(dm.as_raw(), &mut section) },
+        "DMGetLocalSection",
+    )?;
+    let mut cell_offsets = Vec::with_capacity(num_elements);
+    for e in 0..num_elements {
+        let mut offset = 0;
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::PetscSectionGetOffset(
+                    section,
+                    cell_start + e as petsc::Int,
+                    &mut offset,
+                )
+            },
+            "PetscSectionGetOffset",
+        )?;
+        cell_offsets.push(offset as usize);
+    }
+    let node_offset =
+        |e: usize, node: usize| (cell_offsets[e] + node * num_components) as petsc::Int;
+
+    // Interior and boundary faces
+    let (face_start, face_end) = {
+        let (mut face_start, mut face_end) = (0, 0);
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::DMPlexGetHeightStratum(
+                    dm.as_raw(),
+                    1,
+                    &mut face_start,
+                    &mut face_end,
+                )
+            },
+            "DMPlexGetHeightStratum",
+        )?;
+        (face_start, face_end)
+    };
+    let mut interior = FaceSet::default();
+    let mut boundary = FaceSet::default();
+    for face in face_start..face_end {
+        let support = unsafe {
+            let mut support_size = 0;
+            let mut support = std::ptr::null();
+            crate::check_petsc_raw(
+                petsc::petsc_raw::DMPlexGetSupportSize(dm.as_raw(), face, &mut support_size),
+                "DMPlexGetSupportSize",
+            )?;
+            crate::check_petsc_raw(
+                petsc::petsc_raw::DMPlexGetSupport(dm.as_raw(), face, &mut support),
+                "DMPlexGetSupport",
+            )?;
+            std::slice::from_raw_parts(support, support_size as usize).to_vec()
+        };
+        let (centroid, _) = crate::dm::plex_point_geometry(&dm, face)?;
+        let e_minus = (support[0] - cell_start) as usize;
+        let face_minus = nearest_reference_face(&vertices[e_minus], centroid);
+        let nodes_minus = face_nodes(p, face_minus);
+        let face_set = if support.len() == 2 {
+            &mut interior
+        } else {
+            &mut boundary
+        };
+
+        // -- Outward normal of the u- side, scaled by the quadrature weight
+        let (cell_center, _) = trilinear(&vertices[e_minus], [0.; 3]);
+        let outward: Vec<f64> = (0..3).map(|c| centroid[c] - cell_center[c]).collect();
+        let (t_1, t_2) = face_tangents(face_minus);
+        let mut qdata = vec![0.; FACE_Q_DATA_SIZE * num_face_nodes];
+        for (k, &node) in nodes_minus.iter().enumerate() {
+            let (x, dx) = trilinear(
+                &vertices[e_minus],
+                node_reference_coordinates(&points, node),
+            );
+            let normal = [
+                dx[t_1][1] * dx[t_2][2] - dx[t_1][2] * dx[t_2][1],
+                dx[t_1][2] * dx[t_2][0] - dx[t_1][0] * dx[t_2][2],
+                dx[t_1][0] * dx[t_2][1] - dx[t_1][1] * dx[t_2][0],
+            ];
+            let orientation: f64 = (0..3).map(|c| normal[c] * outward[c]).sum();
+            let scale = orientation.signum() * weights[k % p] * weights[k / p];
+            for c in 0..3 {
+                qdata[c * num_face_nodes + k] = scale * normal[c];
+                qdata[(3 + c) * num_face_nodes + k] = x[c];
+            }
+        }
+        face_set.qdata.extend(qdata);
+        face_set.num_faces += 1;
+        face_set
+            .offsets_minus
+            .extend(nodes_minus.iter().map(|&node| node_offset(e_minus, node)));
+
+        // -- Matching nodes of the u+ side
+        if support.len() == 2 {
+            let e_plus = (support[1] - cell_start) as usize;
+            let face_plus = nearest_reference_face(&vertices[e_plus], centroid);
+            let nodes_plus = face_nodes(p, face_plus);
+            for &node in &nodes_minus {
+                let x = node_coordinates(e_minus, node);
+                let distance = |node_plus: usize| -> f64 {
+                    let x_plus = node_coordinates(e_plus, node_plus);
+                    (0..3).map(|c| (x_plus[c] - x[c]).powi(2)).sum()
+                };
+                let node_plus = *nodes_plus
+                    .iter()
+                    .min_by(|&&a, &&b| distance(a).partial_cmp(&distance(b)).unwrap())
+                    .unwrap();
+                face_set.offsets_plus.push(node_offset(e_plus, node_plus));
+            }
+        }
+    }
+
+    // Volume geometric setup data
+    let basis_x = ceed.basis_tensor_H1_Lagrange(3, 3, 2, p, libceed::QuadMode::GaussLobatto)?;
+    let basis_u =
+        ceed.basis_tensor_H1_Lagrange(3, num_components, p, p, libceed::QuadMode::GaussLobatto)?;
+    let basis_face =
+        ceed.basis_tensor_H1_Lagrange(2, num_components, p, p, libceed::QuadMode::GaussLobatto)?;
+    let restr_x_elem = crate::qdata::QDataVector::restriction(&ceed, num_elements, 8, 3)?;
+    let restr_qdata =
+        crate::qdata::QDataVector::restriction(&ceed, num_elements, num_nodes, Q_DATA_SIZE)?;
+    let mut qdata = restr_qdata.create_lvector()?;
+    let qf_setup = crate::advection::setup_q_function(&ceed)?;
+    ceed.operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("dx", &restr_x_elem, &basis_x, VectorOpt::Active)?
+        .field(
+            "weights",
+            ElemRestrictionOpt::None,
+            &basis_x,
+            VectorOpt::None,
+        )?
+        .field(
+            "qdata",
+            &restr_qdata,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?
+        .apply(&coordinates, &mut qdata)?;
+
+    // Diagonal mass matrix, w det(J) at the collocated nodes
+    let mut mass = dm.create_global_vector()?;
+    {
+        let mut mass_loc = dm.create_local_vector()?;
+        {
+            let qdata_view = qdata.view()?;
+            let mut mass_loc_view = mass_loc.view_mut()?;
+            for e in 0..num_elements {
+                for node in 0..num_nodes {
+                    let w_det_j = qdata_view[e * num_nodes * Q_DATA_SIZE + node];
+                    for c in 0..num_components {
+                        mass_loc_view[node_offset(e, node) as usize + c] = w_det_j;
+                    }
+                }
+            }
+        }
+        mass.zero_entries()?;
+        dm.local_to_global(&mass_loc, InsertMode::ADD_VALUES, &mut mass)?;
+    }
+
+    // Volume operator
+    let restr_u = {
+        let offsets: Vec<i32> = (0..num_elements)
+            .flat_map(|e| (0..num_nodes).map(move |node| (e, node)))
+            .map(|(e, node)| node_offset(e, node))
+            .collect();
+        ceed.elem_restriction(
+            num_elements,
+            num_nodes,
+            num_components,
+            1,
+            l_size,
+            MemType::Host,
+            &offsets,
+        )?
+    };
+    let qf_volume = volume_q_function(&ceed, &flux)?;
+    let op_volume = ceed
+        .operator(&qf_volume, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("u", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &qdata)?
+        .field("dv", &restr_u, &basis_u, VectorOpt::Active)?
+        .check()?;
+    let mut op_ceed = ceed.composite_operator()?;
+    op_ceed = op_ceed.sub_operator(&op_volume)?;
+
+    // Face operators
+    let face_restriction = |num_faces: usize, offsets: &[i32]| {
+        ceed.elem_restriction(
+            num_faces,
+            num_face_nodes,
+            num_components,
+            1,
+            l_size,
+            MemType::Host,
+            offsets,
+        )
+    };
+    if interior.num_faces > 0 {
+        let restr_minus = face_restriction(interior.num_faces, &interior.offsets_minus)?;
+        let restr_plus = face_restriction(interior.num_faces, &interior.offsets_plus)?;
+        let restr_face_qdata = crate::qdata::QDataVector::restriction(
+            &ceed,
+            interior.num_faces,
+            num_face_nodes,
+            FACE_Q_DATA_SIZE,
+        )?;
+        let face_qdata = ceed.vector_from_slice(&interior.qdata)?;
+        let qf_interior = interior_face_q_function(&ceed, &flux)?;
+        let op_interior = ceed
+            .operator(&qf_interior, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("u_minus", &restr_minus, &basis_face, VectorOpt::Active)?
+            .field("u_plus", &restr_plus, &basis_face, VectorOpt::Active)?
+            .field(
+                "qdata",
+                &restr_face_qdata,
+                BasisOpt::Collocated,
+                &face_qdata,
+            )?
+            .field("v_minus", &restr_minus, &basis_face, VectorOpt::Active)?
+            .field("v_plus", &restr_plus, &basis_face, VectorOpt::Active)?
+            .check()?;
+        op_ceed = op_ceed.sub_operator(&op_interior)?;
+    }
+    if boundary.num_faces > 0 {
+        let restr_minus = face_restriction(boundary.num_faces, &boundary.offsets_minus)?;
+        let restr_face_qdata = crate::qdata::QDataVector::restriction(
+            &ceed,
+            boundary.num_faces,
+            num_face_nodes,
+            FACE_Q_DATA_SIZE,
+        )?;
+        let face_qdata = ceed.vector_from_slice(&boundary.qdata)?;
+        let qf_boundary = boundary_face_q_function(&ceed, &flux)?;
+        let op_boundary = ceed
+            .operator(&qf_boundary, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("u_minus", &restr_minus, &basis_face, VectorOpt::Active)?
+            .field(
+                "qdata",
+                &restr_face_qdata,
+                BasisOpt::Collocated,
+                &face_qdata,
+            )?
+            .field("v_minus", &restr_minus, &basis_face, VectorOpt::Active)?
+            .check()?;
+        op_ceed = op_ceed.sub_operator(&op_boundary)?;
+    }
+
+    // Residual MatShell
+    let y_loc = dm.create_local_vector()?;
+    let mut rhs = dm.create_global_vector()?;
+    rhs.zero_entries()?;
+    let residual = crate::petsc_ops::create_mat_shell(crate::MelesMatShellContext {
+        ceed: ceed.clone(),
+        dm: RefCell::new(dm),
+        x_loc: RefCell::new(x_loc),
+        y_loc: RefCell::new(y_loc),
+        x_loc_ceed: RefCell::new(ceed.vector(l_size)?),
+        y_loc_ceed: RefCell::new(ceed.vector(l_size)?),
+        op_ceed: RefCell::new(op_ceed.into()),
+        diagonal: RefCell::new(None),
+        num_components,
+        point_block_inverse: RefCell::new(None),
+        rhs,
+        qdata: vec![crate::qdata::QDataVector {
+            vector: Rc::new(qdata),
+            num_elements,
+            num_quadrature_points: num_nodes,
+            size: Q_DATA_SIZE,
+        }],
+        true_solution: vec![],
+        error_ops: vec![],
+        single_precision: false,
+    })?;
+
+    Ok(DgProblem { residual, mass })
+}
+
+// -----------------------------------------------------------------------------
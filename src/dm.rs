@@ -1,14 +1,110 @@
 use crate::prelude::*;
 
+// -----------------------------------------------------------------------------
+// Mesh type enum
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MeshType {
+    Plex,
+    Stag,
+    Da,
+}
+
+impl std::str::FromStr for MeshType {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<MeshType> {
+        match s {
+            "plex" => Ok(MeshType::Plex),
+            "stag" => Ok(MeshType::Stag),
+            "da" => Ok(MeshType::Da),
+            _ => Err(crate::Error {
+                message: "failed to parse mesh type option".to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for MeshType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshType::Plex => write!(f, "plex"),
+            MeshType::Stag => write!(f, "stag"),
+            MeshType::Da => write!(f, "da"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Mesh transformations
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MeshTransform {
+    None,
+    Kershaw,
+}
+
+impl std::str::FromStr for MeshTransform {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<MeshTransform> {
+        match s {
+            "none" => Ok(MeshTransform::None),
+            "kershaw" => Ok(MeshTransform::Kershaw),
+            _ => Err(crate::Error {
+                message: "failed to parse mesh transform option".to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for MeshTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshTransform::None => write!(f, "none"),
+            MeshTransform::Kershaw => write!(f, "kershaw"),
+        }
+    }
+}
+
+// Apply the mesh transformation selected by the options to a new mesh
+pub(crate) fn transform_mesh(dm: &mut DM, options: &crate::ceed_bps::Opt) -> crate::Result<()> {
+    match options.mesh_transform {
+        MeshTransform::None => Ok(()),
+        MeshTransform::Kershaw => kershaw_transformation(dm, options.kershaw_eps.0),
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Apply 3D Kershaw mesh transformation
-// The eps parameters are in (0, 1]
-// Uniform mesh is recovered for eps=1
 // -----------------------------------------------------------------------------
-pub(crate) fn kershaw_transformation<'a>(
-    mut dm: DM<'a, 'a>,
-    eps: petsc::Scalar,
-) -> crate::Result<()> {
+/// Apply the Kershaw transformation of the CEED benchmark problems to a 3D
+///   mesh of the unit cube
+///
+/// The mesh is split into six layers in `x`, and the `y` and `z` coordinates
+/// of each layer are compressed towards alternating boundaries, giving
+/// increasingly distorted elements as `eps` decreases. The uniform mesh is
+/// recovered for `eps = 1`.
+///
+/// The transformation moves the vertex coordinates, so it must be applied
+/// before a discretization is set up on the mesh. Benchmark problem meshes
+/// are transformed with the `-mesh_transform kershaw -kershaw_eps <eps>`
+/// options.
+///
+/// # arguments
+///
+/// * `dm` - DMPlex mesh of the unit cube
+/// * `eps` - Distortion parameter, in (0, 1]
+pub fn kershaw_transformation(dm: &mut DM, eps: petsc::Scalar) -> crate::Result<()> {
+    if !(eps > 0. && eps <= 1.) {
+        return Err(crate::Error {
+            message: format!("Kershaw eps must be in (0, 1], not {}", eps),
+        });
+    }
+    if dm.dimension()? != 3 {
+        return Err(crate::Error {
+            message: "Kershaw transformation requires a 3D mesh".to_string(),
+        });
+    }
+
     // Transition from a value of "a" for x=0, to a value of "b" for x=1.  Optionally
     // smooth -- see the commented versions at the end.
     fn step(a: petsc::Scalar, b: petsc::Scalar, x: petsc::Scalar) -> petsc::Scalar {
@@ -37,38 +133,46 @@ pub(crate) fn kershaw_transformation<'a>(
 
     let mut coords = dm.coordinates_local()?;
     let num_coords = coords.local_size()?;
-    let mut coord_view = coords.view_mut()?;
-
-    // Apply transformations based upon layer
-    for i in (0..num_coords as usize).step_by(3) {
-        let (x, y, z) = (coord_view[i], coord_view[i + 1], coord_view[i + 2]);
-        let layer = 6 * x as i32;
-        let lambda = (x - layer as f64 / 6.0) * 6.0;
-
-        match layer {
-            0 => {
-                coord_view[i + 1] = left(eps, y);
-                coord_view[i + 2] = left(eps, z);
-            }
-            1 | 4 => {
-                coord_view[i + 1] = step(left(eps, y), right(eps, y), lambda);
-                coord_view[i + 2] = step(left(eps, z), right(eps, z), lambda);
-            }
-            2 => {
-                coord_view[i + 1] = step(right(eps, y), left(eps, y), lambda / 2.0);
-                coord_view[i + 2] = step(right(eps, z), left(eps, z), lambda / 2.0);
-            }
-            3 => {
-                coord_view[i + 1] = step(right(eps, y), left(eps, y), (1.0 + lambda) / 2.0);
-                coord_view[i + 2] = step(right(eps, z), left(eps, z), (1.0 + lambda) / 2.0);
-            }
-            _ => {
-                coord_view[i + 1] = right(eps, y);
-                coord_view[i + 2] = right(eps, z);
+    {
+        let mut coord_view = coords.view_mut()?;
+
+        // Apply transformations based upon layer
+        for i in (0..num_coords as usize).step_by(3) {
+            let (x, y, z) = (coord_view[i], coord_view[i + 1], coord_view[i + 2]);
+            let layer = 6 * x as i32;
+            let lambda = (x - layer as f64 / 6.0) * 6.0;
+
+            match layer {
+                0 => {
+                    coord_view[i + 1] = left(eps, y);
+                    coord_view[i + 2] = left(eps, z);
+                }
+                1 | 4 => {
+                    coord_view[i + 1] = step(left(eps, y), right(eps, y), lambda);
+                    coord_view[i + 2] = step(left(eps, z), right(eps, z), lambda);
+                }
+                2 => {
+                    coord_view[i + 1] = step(right(eps, y), left(eps, y), lambda / 2.0);
+                    coord_view[i + 2] = step(right(eps, z), left(eps, z), lambda / 2.0);
+                }
+                3 => {
+                    coord_view[i + 1] = step(right(eps, y), left(eps, y), (1.0 + lambda) / 2.0);
+                    coord_view[i + 2] = step(right(eps, z), left(eps, z), (1.0 + lambda) / 2.0);
+                }
+                _ => {
+                    coord_view[i + 1] = right(eps, y);
+                    coord_view[i + 2] = right(eps, z);
+                }
             }
         }
     }
 
+    // Set the local coordinates, so the global coordinates are updated
+    crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::DMSetCoordinatesLocal(dm.as_raw(), coords.as_raw()) },
+        "DMSetCoordinatesLocal",
+    )?;
+
     Ok(())
 }
 
@@ -80,30 +184,50 @@ pub(crate) fn setup_dm_by_order<'a, BcFn>(
     order: usize,
     num_components: usize,
     enforce_boundary_conditions: bool,
+    essential_boundaries: &[String],
     user_boundary_function: Option<BcFn>,
+    labeled_boundary_functions: &[(String, std::rc::Rc<crate::ceed_bps::BoundaryFunction>)],
 ) -> crate::Result<()>
 where
     BcFn: Fn(petsc::Int, Real, &[Real], petsc::Int, &mut [petsc::Scalar]) -> petsc::Result<()> + 'a,
 {
-    // Setup FE
+    // Setup FE, on simplices or tensor product cells as the mesh
     let dimemsion = dm.dimension()?;
-    let fe = FEDisc::create_lagrange(dm.comm(), dimemsion, num_components, false, order, None)?;
+    let is_simplex = plex_is_simplex(dm)?;
+    let fe = FEDisc::create_lagrange(
+        dm.comm(),
+        dimemsion,
+        num_components,
+        is_simplex,
+        order,
+        None,
+    )?;
     dm.add_field(None, fe)?;
 
     // Coordinate FE
-    let fe_coords = FEDisc::create_lagrange(dm.comm(), dimemsion, dimemsion, false, 1, None)?;
+    let fe_coords = FEDisc::create_lagrange(dm.comm(), dimemsion, dimemsion, is_simplex, 1, None)?;
     dm.project_coordinates(fe_coords)?;
 
     // Setup DM
     let _ = dm.create_ds()?;
     if enforce_boundary_conditions {
-        let has_label = dm.has_label("marker")?;
+        // Named boundaries, such as Gmsh physical groups, or the whole boundary
+        let label_name = if essential_boundaries.is_empty() {
+            "marker".to_string()
+        } else {
+            format!("essential {}", essential_boundaries.join(","))
+        };
+        let has_label = dm.has_label(&label_name)?;
         if !has_label {
-            dm.create_label("marker")?;
-            let mut label = dm.label("marker")?.unwrap();
-            dm.plex_mark_boundary_faces(1, &mut label)?;
+            dm.create_label(&label_name)?;
+            if essential_boundaries.is_empty() {
+                let mut label = dm.label(&label_name)?.unwrap();
+                dm.plex_mark_boundary_faces(1, &mut label)?;
+            } else {
+                mark_named_boundaries(dm, &label_name, essential_boundaries)?;
+            }
         }
-        let mut label = dm.label("marker")?.unwrap();
+        let mut label = dm.label(&label_name)?.unwrap();
         dm.add_boundary_essential(
             "wall",
             &mut label,
@@ -112,14 +236,351 @@ where
             &[],
             user_boundary_function.unwrap(),
         )?;
+
+        // User functions on their own boundaries, added after the default so
+        //   their values take precedence on shared points
+        for (boundary, function) in labeled_boundary_functions {
+            let label_name = format!("essential {}", boundary);
+            if !dm.has_label(&label_name)? {
+                dm.create_label(&label_name)?;
+                mark_named_boundaries(dm, &label_name, std::slice::from_ref(boundary))?;
+            }
+            let mut label = dm.label(&label_name)?.unwrap();
+            let function = function.clone();
+            dm.add_boundary_essential(
+                boundary,
+                &mut label,
+                &[],
+                1,
+                &[],
+                move |_dim: petsc::Int,
+                      t: Real,
+                      x: &[Real],
+                      _num_components: petsc::Int,
+                      u: &mut [petsc::Scalar]| {
+                    function(x, t, u);
+                    Ok(())
+                },
+            )?;
+        }
+    }
+    if !is_simplex {
+        dm.plex_set_closure_permutation_tensor_default(None)?;
+    }
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Mark the points of named labels, such as the labels created from Gmsh
+//   physical names with -dm_plex_gmsh_use_regions, with value 1 in a label,
+//   completed so edges and vertices on the boundaries are included
+//   Boundaries given as "label:value", such as "Face Sets:2" for an Exodus
+//   sideset, only mark the points with that value
+// -----------------------------------------------------------------------------
+fn mark_named_boundaries(dm: &DM, label_name: &str, boundaries: &[String]) -> crate::Result<()> {
+    let label_name = std::ffi::CString::new(label_name).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut marker = std::ptr::null_mut();
+        check(
+            DMGetLabel(dm.as_raw(), label_name.as_ptr(), &mut marker),
+            "DMGetLabel",
+        )?;
+        for boundary in boundaries {
+            let (name, value) = match boundary.rsplit_once(':') {
+                Some((name, value)) => (
+                    name,
+                    Some(
+                        value
+                            .trim()
+                            .parse::<petsc::Int>()
+                            .map_err(|_| crate::Error {
+                                message: format!("invalid boundary label value in {}", boundary),
+                            })?,
+                    ),
+                ),
+                None => (boundary.as_str(), None),
+            };
+            let name_c = std::ffi::CString::new(name).map_err(|_| crate::Error {
+                message: format!("invalid boundary label name {}", name),
+            })?;
+            let mut label = std::ptr::null_mut();
+            check(
+                DMGetLabel(dm.as_raw(), name_c.as_ptr(), &mut label),
+                "DMGetLabel",
+            )?;
+            if label.is_null() {
+                return Err(crate::Error {
+                    message: format!(
+                        "missing boundary label {}; Gmsh physical names are labels with -dm_plex_gmsh_use_regions and Exodus sidesets are \"Face Sets\"",
+                        name
+                    ),
+                });
+            }
+            let mut values_is = std::ptr::null_mut();
+            check(
+                DMLabelGetValueIS(label, &mut values_is),
+                "DMLabelGetValueIS",
+            )?;
+            let mut num_values = 0;
+            check(ISGetLocalSize(values_is, &mut num_values), "ISGetLocalSize")?;
+            let mut values = std::ptr::null();
+            check(ISGetIndices(values_is, &mut values), "ISGetIndices")?;
+            let values_vec = match value {
+                Some(value) => vec![value],
+                None => std::slice::from_raw_parts(values, num_values as usize).to_vec(),
+            };
+            check(ISRestoreIndices(values_is, &mut values), "ISRestoreIndices")?;
+            check(ISDestroy(&mut values_is), "ISDestroy")?;
+            for value in values_vec {
+                let mut points_is = std::ptr::null_mut();
+                check(
+                    DMLabelGetStratumIS(label, value, &mut points_is),
+                    "DMLabelGetStratumIS",
+                )?;
+                if points_is.is_null() {
+                    continue;
+                }
+                let mut num_points = 0;
+                check(ISGetLocalSize(points_is, &mut num_points), "ISGetLocalSize")?;
+                let mut points = std::ptr::null();
+                check(ISGetIndices(points_is, &mut points), "ISGetIndices")?;
+                for &point in std::slice::from_raw_parts(points, num_points as usize) {
+                    check(DMLabelSetValue(marker, point, 1), "DMLabelSetValue")?;
+                }
+                check(ISRestoreIndices(points_is, &mut points), "ISRestoreIndices")?;
+                check(ISDestroy(&mut points_is), "ISDestroy")?;
+            }
+        }
+        check(
+            DMPlexLabelComplete(dm.as_raw(), marker),
+            "DMPlexLabelComplete",
+        )?;
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Create a plex mesh from the options database
+//   Uniform refinements from -refine are added to -dm_refine, so they are
+//   applied by DMSetFromOptions after any file, box, or extrusion setup
+//   Box faces from -local_dofs replace -dm_plex_box_faces
+//   Periodic boxes are Z-order boxes with isoperiodic faces, so the topology
+//   and vertex coordinates are those of the non-periodic box and periodic
+//   faces are identified in the point SF; local vectors hold both copies of
+//   the periodic DoFs, so the closure permutation and the restrictions from
+//   the local section are unchanged, and the geometry needs no localized
+//   coordinates
+// -----------------------------------------------------------------------------
+pub(crate) fn create_plex<'a>(
+    comm: &'a mpi::topology::UserCommunicator,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<DM<'a, 'a>> {
+    check_mesh_file()?;
+    let mut dm = DM::create(comm)?;
+    dm.set_type(DMType::DMPLEX)?;
+
+    // Options for this mesh, restored after DMSetFromOptions
+    let mut overrides = crate::config::OptionOverrides::new();
+    if options.local_dofs.0 > 0.0 {
+        let faces = weak_scaling_box_faces(comm, options)?
+            .iter()
+            .map(|faces| faces.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        overrides.set("-dm_plex_box_faces", Some(&faces))?;
+    }
+    if options.refine > 0 {
+        let name = std::ffi::CString::new("-dm_refine").unwrap();
+        let dm_refine = crate::config::options_get_int(&name)?.unwrap_or(0) as usize;
+        overrides.set(
+            "-dm_refine",
+            Some(&(dm_refine + options.refine).to_string()),
+        )?;
+    }
+    if options.periodic {
+        let filename = std::ffi::CString::new("-dm_plex_filename").unwrap();
+        if let Some(filename) = crate::config::options_get_string(&filename)? {
+            return Err(crate::Error {
+                message: format!(
+                    "-periodic requires a box mesh, not the mesh file {}",
+                    filename
+                ),
+            });
+        }
+        let dimension = std::ffi::CString::new("-dm_plex_dim").unwrap();
+        let dimension = crate::config::options_get_int(&dimension)?.unwrap_or(3) as usize;
+        overrides.set("-dm_plex_shape", Some("zbox"))?;
+        overrides.set(
+            "-dm_plex_box_bd",
+            Some(&vec!["periodic"; dimension].join(",")),
+        )?;
+    }
+    dm.set_from_options()?;
+    drop(overrides);
+    Ok(dm)
+}
+
+// -----------------------------------------------------------------------------
+// Weak scaling box meshes
+//   As in the PETSc BP miniapps, the ranks are split into a process grid as
+//   close to cubic as possible and each rank gets the same number of elements
+//   in each direction, with the elements sized so the rank holds about
+//   -local_dofs DoFs after any refinement
+// -----------------------------------------------------------------------------
+// Split a number of ranks into a process grid, largest factors first
+fn process_grid(num_ranks: usize, dimension: usize) -> Vec<usize> {
+    let mut factors = vec![];
+    let (mut remaining, mut factor) = (num_ranks, 2);
+    while remaining > 1 {
+        while remaining % factor == 0 {
+            factors.push(factor);
+            remaining /= factor;
+        }
+        factor += 1;
+    }
+    let mut grid = vec![1; dimension];
+    for factor in factors.iter().rev() {
+        let smallest = (0..dimension).min_by_key(|&i| grid[i]).unwrap();
+        grid[smallest] *= factor;
+    }
+    grid.sort_unstable_by(|a, b| b.cmp(a));
+    grid
+}
+
+// Box faces in each direction for the target DoFs per rank
+pub(crate) fn weak_scaling_box_faces(
+    comm: &mpi::topology::UserCommunicator,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<Vec<usize>> {
+    use mpi::traits::Communicator;
+    let filename = std::ffi::CString::new("-dm_plex_filename").unwrap();
+    if let Some(filename) = crate::config::options_get_string(&filename)? {
+        return Err(crate::Error {
+            message: format!(
+                "-local_dofs requires a box mesh, not the mesh file {}",
+                filename
+            ),
+        });
+    }
+    let dimension = std::ffi::CString::new("-dm_plex_dim").unwrap();
+    let dimension = crate::config::options_get_int(&dimension)?.unwrap_or(3) as usize;
+    let dm_refine = std::ffi::CString::new("-dm_refine").unwrap();
+    let dm_refine = crate::config::options_get_int(&dm_refine)?.unwrap_or(0) as usize;
+    let refinements = dm_refine + options.refine;
+    let num_components = crate::ceed_bps::num_components(options)?;
+
+    // Elements per rank in each direction, before refinement
+    let local_nodes = options.local_dofs.0 / num_components as f64;
+    let local_elements = local_nodes.powf(1.0 / dimension as f64) / options.order as f64;
+    let local_faces = (local_elements / 2.0_f64.powi(refinements as i32))
+        .round()
+        .max(1.0) as usize;
+    Ok(process_grid(comm.size() as usize, dimension)
+        .iter()
+        .map(|ranks| ranks * local_faces)
+        .collect())
+}
+
+// -----------------------------------------------------------------------------
+// Check that PETSc can read the mesh file given by -dm_plex_filename, if any
+//   Exodus II files are read through the exodusii package, with sidesets as
+//   "Face Sets", nodesets as "Vertex Sets", and element blocks as "Cell Sets",
+//   and CGNS files through the cgns package
+// -----------------------------------------------------------------------------
+pub(crate) fn check_mesh_file() -> crate::Result<()> {
+    let option_name = std::ffi::CString::new("-dm_plex_filename").unwrap();
+    let filename = match crate::config::options_get_string(&option_name)? {
+        Some(filename) => filename,
+        None => return Ok(()),
+    };
+
+    let package = match std::path::Path::new(&filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("exo") | Some("exii") | Some("e") | Some("gen") => "exodusii",
+        Some("cgns") => "cgns",
+        _ => return Ok(()),
+    };
+    if !crate::has_petsc_package(package)? {
+        return Err(crate::Error {
+            message: format!(
+                "mesh file {} requires PETSc configured with {}",
+                filename, package
+            ),
+        });
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Setup DM for a discontinuous Lagrange space
+//   Every DoF belongs to a cell, ordered by node with the first direction
+//   fastest and interleaved components, so elements share no DoFs and
+//   neighboring elements couple only through face terms
+// -----------------------------------------------------------------------------
+pub(crate) fn setup_dm_discontinuous<'a>(
+    dm: &mut DM<'a, 'a>,
+    order: usize,
+    num_components: usize,
+) -> crate::Result<()> {
+    let dm_raw = dm.as_raw();
+    let dimension = dm.dimension()? as u32;
+    let num_cell_dofs = (num_components * (order + 1).pow(dimension)) as petsc::Int;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(dm_raw as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut section = std::ptr::null_mut();
+        check(PetscSectionCreate(comm, &mut section), "PetscSectionCreate")?;
+        check(
+            PetscSectionSetNumFields(section, 1),
+            "PetscSectionSetNumFields",
+        )?;
+        check(
+            PetscSectionSetFieldComponents(section, 0, num_components as petsc::Int),
+            "PetscSectionSetFieldComponents",
+        )?;
+        let (mut point_start, mut point_end) = (0, 0);
+        check(
+            DMPlexGetChart(dm_raw, &mut point_start, &mut point_end),
+            "DMPlexGetChart",
+        )?;
+        check(
+            PetscSectionSetChart(section, point_start, point_end),
+            "PetscSectionSetChart",
+        )?;
+        let (cell_start, cell_end) = plex_cell_range(dm)?;
+        for cell in cell_start..cell_end {
+            check(
+                PetscSectionSetDof(section, cell, num_cell_dofs),
+                "PetscSectionSetDof",
+            )?;
+            check(
+                PetscSectionSetFieldDof(section, cell, 0, num_cell_dofs),
+                "PetscSectionSetFieldDof",
+            )?;
+        }
+        check(PetscSectionSetUp(section), "PetscSectionSetUp")?;
+        check(DMSetLocalSection(dm_raw, section), "DMSetLocalSection")?;
+        check(PetscSectionDestroy(&mut section), "PetscSectionDestroy")?;
     }
-    dm.plex_set_closure_permutation_tensor_default(None)?;
 
     Ok(())
 }
 
 // -----------------------------------------------------------------------------
 // Setup Restriction from DMPlex
+//   Height 0 restricts to cells, optionally only those with the label value,
+//   and height 1 restricts to the faces with the label value, such as a
+//   boundary given by "Face Sets"
 // -----------------------------------------------------------------------------
 pub(crate) fn create_restriction_from_dm_plex<'a, 'b, 'c>(
     dm: &'a DM<'b, '_>,
@@ -128,6 +589,18 @@ pub(crate) fn create_restriction_from_dm_plex<'a, 'b, 'c>(
     label: impl Into<Option<&'b DMLabel<'b>>>,
     value: usize,
 ) -> crate::Result<ElemRestriction<'c>> {
+    let _event = crate::events::Event::Restriction.begin();
+    let label = label.into();
+    if height > 1 {
+        return Err(crate::Error {
+            message: format!("restrictions of height {} are not supported", height),
+        });
+    }
+    if height == 1 && label.is_none() {
+        return Err(crate::Error {
+            message: "face restrictions require a label selecting the faces".to_string(),
+        });
+    }
     let DMPlexLocalOffsets {
         num_cells,
         cell_size,
@@ -148,3 +621,894 @@ pub(crate) fn create_restriction_from_dm_plex<'a, 'b, 'c>(
 }
 
 // -----------------------------------------------------------------------------
+
+// -----------------------------------------------------------------------------
+// Create DMStag with vertex DoFs on the unit cube
+// -----------------------------------------------------------------------------
+pub(crate) fn create_dm_stag<'a>(
+    comm: &'a mpi::topology::UserCommunicator,
+    dimension: usize,
+    num_components: usize,
+) -> crate::Result<DM<'a, 'a>> {
+    let mut dm = DM::create(comm)?;
+    dm.set_type(DMType::DMSTAG)?;
+    dm.set_dimension(dimension as petsc::Int)?;
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMStagSetDOF(dm.as_raw(), num_components as petsc::Int, 0, 0, 0),
+            "DMStagSetDOF",
+        )?;
+    }
+    dm.set_from_options()?;
+    dm.set_up()?;
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMStagSetUniformCoordinatesExplicit(
+                dm.as_raw(),
+                0.,
+                1.,
+                0.,
+                1.,
+                0.,
+                1.,
+            ),
+            "DMStagSetUniformCoordinatesExplicit",
+        )?;
+    }
+    Ok(dm)
+}
+
+// Element vertex locations in lexicographic order, matching libCEED tensor
+// product bases
+pub(crate) fn stag_element_vertices(
+    dimension: usize,
+) -> Vec<petsc::petsc_raw::DMStagStencilLocation> {
+    use petsc::petsc_raw::DMStagStencilLocation::*;
+    match dimension {
+        1 => vec![DMSTAG_LEFT, DMSTAG_RIGHT],
+        2 => vec![
+            DMSTAG_DOWN_LEFT,
+            DMSTAG_DOWN_RIGHT,
+            DMSTAG_UP_LEFT,
+            DMSTAG_UP_RIGHT,
+        ],
+        _ => vec![
+            DMSTAG_BACK_DOWN_LEFT,
+            DMSTAG_BACK_DOWN_RIGHT,
+            DMSTAG_BACK_UP_LEFT,
+            DMSTAG_BACK_UP_RIGHT,
+            DMSTAG_FRONT_DOWN_LEFT,
+            DMSTAG_FRONT_DOWN_RIGHT,
+            DMSTAG_FRONT_UP_LEFT,
+            DMSTAG_FRONT_UP_RIGHT,
+        ],
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup Restriction from DMStag
+//   Offsets are computed directly from the ghosted local layout, where each
+//   element stores its DoFs contiguously in lexicographic element order
+// -----------------------------------------------------------------------------
+pub(crate) fn create_restriction_from_dm_stag<'c>(
+    dm: &DM,
+    ceed: &libceed::Ceed,
+    locations: &[petsc::petsc_raw::DMStagStencilLocation],
+    num_components: usize,
+) -> crate::Result<ElemRestriction<'c>> {
+    let dm_raw = dm.as_raw();
+    let dimension = dm.dimension()?;
+    let (mut start, mut size, mut extra) = ([0; 3], [1; 3], [0; 3]);
+    let (mut ghost_start, mut ghost_size) = ([0; 3], [1; 3]);
+    let mut entries_per_element = 0;
+    let mut slots = Vec::with_capacity(locations.len());
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMStagGetCorners(
+                dm_raw,
+                &mut start[0],
+                &mut start[1],
+                &mut start[2],
+                &mut size[0],
+                &mut size[1],
+                &mut size[2],
+                &mut extra[0],
+                &mut extra[1],
+                &mut extra[2],
+            ),
+            "DMStagGetCorners",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMStagGetGhostCorners(
+                dm_raw,
+                &mut ghost_start[0],
+                &mut ghost_start[1],
+                &mut ghost_start[2],
+                &mut ghost_size[0],
+                &mut ghost_size[1],
+                &mut ghost_size[2],
+            ),
+            "DMStagGetGhostCorners",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMStagGetEntriesPerElement(dm_raw, &mut entries_per_element),
+            "DMStagGetEntriesPerElement",
+        )?;
+        for location in locations {
+            let mut slot = 0;
+            crate::check_petsc_raw(
+                petsc::petsc_raw::DMStagGetLocationSlot(dm_raw, *location, 0, &mut slot),
+                "DMStagGetLocationSlot",
+            )?;
+            slots.push(slot);
+        }
+    }
+    for d in dimension..3 {
+        size[d] = 1;
+        ghost_size[d] = 1;
+    }
+
+    // Element offsets
+    let num_elements = (size[0] * size[1] * size[2]) as usize;
+    let mut offsets = Vec::with_capacity(num_elements * locations.len());
+    for k in start[2]..start[2] + size[2] {
+        for j in start[1]..start[1] + size[1] {
+            for i in start[0]..start[0] + size[0] {
+                let element = ((k - ghost_start[2]) * ghost_size[1] + (j - ghost_start[1]))
+                    * ghost_size[0]
+                    + (i - ghost_start[0]);
+                for slot in &slots {
+                    offsets.push((element * entries_per_element + slot) as i32);
+                }
+            }
+        }
+    }
+
+    let l_size = (ghost_size[0] * ghost_size[1] * ghost_size[2] * entries_per_element) as usize;
+    let elem_restriction = ceed.elem_restriction(
+        num_elements,
+        locations.len(),
+        num_components,
+        1,
+        l_size,
+        MemType::Host,
+        &offsets,
+    )?;
+    Ok(elem_restriction)
+}
+
+// -----------------------------------------------------------------------------
+
+// -----------------------------------------------------------------------------
+// Create DMDA for tensor product elements of the given order on the unit cube
+//   The grid must have `order * n + 1` nodes in each direction
+// -----------------------------------------------------------------------------
+pub(crate) fn create_dm_da<'a>(
+    comm: &'a mpi::topology::UserCommunicator,
+    dimension: usize,
+    num_components: usize,
+    order: usize,
+) -> crate::Result<DM<'a, 'a>> {
+    let mut dm = DM::create(comm)?;
+    dm.set_type(DMType::DMDA)?;
+    dm.set_dimension(dimension as petsc::Int)?;
+    let dm_raw = dm.as_raw();
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDASetDof(dm_raw, num_components as petsc::Int),
+            "DMDASetDof",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDASetStencilType(
+                dm_raw,
+                petsc::petsc_raw::DMDAStencilType::DMDA_STENCIL_BOX,
+            ),
+            "DMDASetStencilType",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDASetStencilWidth(dm_raw, order as petsc::Int),
+            "DMDASetStencilWidth",
+        )?;
+    }
+    dm.set_from_options()?;
+    dm.set_up()?;
+
+    // Check grid sizes
+    let global_size = da_global_size(&dm)?;
+    for d in 0..dimension {
+        if (global_size[d] - 1) % order as petsc::Int != 0 {
+            return Err(crate::Error {
+                message: format!(
+                    "DMDA grid size {} in direction {} is not a multiple of order {} plus one",
+                    global_size[d], d, order
+                ),
+            });
+        }
+    }
+
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDASetUniformCoordinates(dm_raw, 0., 1., 0., 1., 0., 1.),
+            "DMDASetUniformCoordinates",
+        )?;
+    }
+    Ok(dm)
+}
+
+// Global number of DMDA nodes in each direction
+fn da_global_size(dm: &DM) -> crate::Result<[petsc::Int; 3]> {
+    let mut global_size = [1; 3];
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDAGetInfo(
+                dm.as_raw(),
+                std::ptr::null_mut(),
+                &mut global_size[0],
+                &mut global_size[1],
+                &mut global_size[2],
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            ),
+            "DMDAGetInfo",
+        )?;
+    }
+    Ok(global_size)
+}
+
+// -----------------------------------------------------------------------------
+// Setup Restriction from DMDA
+//   Offsets are generated analytically from the structured layout. Each
+//   element spans `order + 1` nodes per direction, starting at a node owned by
+//   this rank. With `vertices_only`, only the element corner nodes are
+//   included, as needed for linear coordinate bases.
+// -----------------------------------------------------------------------------
+pub(crate) fn create_restriction_from_dm_da<'c>(
+    dm: &DM,
+    ceed: &libceed::Ceed,
+    order: usize,
+    vertices_only: bool,
+) -> crate::Result<ElemRestriction<'c>> {
+    let dm_raw = dm.as_raw();
+    let dimension = dm.dimension()?;
+    let global_size = da_global_size(dm)?;
+    let (mut start, mut size) = ([0; 3], [1; 3]);
+    let (mut ghost_start, mut ghost_size) = ([0; 3], [1; 3]);
+    let mut num_components = 0;
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDAGetCorners(
+                dm_raw,
+                &mut start[0],
+                &mut start[1],
+                &mut start[2],
+                &mut size[0],
+                &mut size[1],
+                &mut size[2],
+            ),
+            "DMDAGetCorners",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDAGetGhostCorners(
+                dm_raw,
+                &mut ghost_start[0],
+                &mut ghost_start[1],
+                &mut ghost_start[2],
+                &mut ghost_size[0],
+                &mut ghost_size[1],
+                &mut ghost_size[2],
+            ),
+            "DMDAGetGhostCorners",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMDAGetDof(dm_raw, &mut num_components),
+            "DMDAGetDof",
+        )?;
+    }
+    for d in dimension..3 {
+        start[d] = 0;
+        size[d] = 1;
+        ghost_start[d] = 0;
+        ghost_size[d] = 1;
+    }
+
+    // Element first nodes owned by this rank, in each direction
+    let order = order as petsc::Int;
+    let element_starts = |d: usize| -> Vec<petsc::Int> {
+        if d >= dimension {
+            return vec![0];
+        }
+        (start[d]..start[d] + size[d])
+            .filter(|i| i % order == 0 && i + order < global_size[d])
+            .collect()
+    };
+    let (starts_x, starts_y, starts_z) = (element_starts(0), element_starts(1), element_starts(2));
+
+    // Node offsets within an element, in lexicographic order
+    let nodes_1d: Vec<petsc::Int> = if vertices_only {
+        vec![0, order]
+    } else {
+        (0..=order).collect()
+    };
+    let nodes = |d: usize| {
+        if d < dimension {
+            nodes_1d.clone()
+        } else {
+            vec![0]
+        }
+    };
+    let (nodes_x, nodes_y, nodes_z) = (nodes(0), nodes(1), nodes(2));
+    let element_size = nodes_x.len() * nodes_y.len() * nodes_z.len();
+
+    // Element offsets
+    let num_elements = starts_x.len() * starts_y.len() * starts_z.len();
+    let mut offsets = Vec::with_capacity(num_elements * element_size);
+    for k0 in &starts_z {
+        for j0 in &starts_y {
+            for i0 in &starts_x {
+                for c in &nodes_z {
+                    for b in &nodes_y {
+                        for a in &nodes_x {
+                            let node = ((k0 + c - ghost_start[2]) * ghost_size[1]
+                                + (j0 + b - ghost_start[1]))
+                                * ghost_size[0]
+                                + (i0 + a - ghost_start[0]);
+                            offsets.push((node * num_components) as i32);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let l_size = (ghost_size[0] * ghost_size[1] * ghost_size[2] * num_components) as usize;
+    let elem_restriction = ceed.elem_restriction(
+        num_elements,
+        element_size,
+        num_components as usize,
+        1,
+        l_size,
+        MemType::Host,
+        &offsets,
+    )?;
+    Ok(elem_restriction)
+}
+
+// -----------------------------------------------------------------------------
+// Cell types present in a DMPlex, in increasing order
+//   The set is reduced over the DM communicator, so every rank takes the same
+//   collective setup path, even when a rank owns cells of only some types or
+//   no cells at all
+// -----------------------------------------------------------------------------
+const CELL_TYPES: [petsc::petsc_raw::DMPolytopeType; 8] = {
+    use petsc::petsc_raw::DMPolytopeType::*;
+    [
+        DM_POLYTOPE_SEGMENT,
+        DM_POLYTOPE_TRIANGLE,
+        DM_POLYTOPE_QUADRILATERAL,
+        DM_POLYTOPE_TETRAHEDRON,
+        DM_POLYTOPE_HEXAHEDRON,
+        DM_POLYTOPE_TRI_PRISM,
+        DM_POLYTOPE_PYRAMID,
+        DM_POLYTOPE_POINT,
+    ]
+};
+
+pub(crate) fn plex_cell_types(dm: &DM) -> crate::Result<Vec<petsc::petsc_raw::DMPolytopeType>> {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    let dm_raw = dm.as_raw();
+    let (mut cell_start, mut cell_end) = (0, 0);
+    let mut local_cell_types: u64 = 0;
+    unsafe {
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMPlexGetHeightStratum(dm_raw, 0, &mut cell_start, &mut cell_end),
+            "DMPlexGetHeightStratum",
+        )?;
+        for cell in cell_start..cell_end {
+            let mut cell_type = std::mem::zeroed();
+            crate::check_petsc_raw(
+                petsc::petsc_raw::DMPlexGetCellType(dm_raw, cell, &mut cell_type),
+                "DMPlexGetCellType",
+            )?;
+            // Cell types outside the known set are reported on every rank
+            //   below, so no rank returns early
+            let bit = CELL_TYPES
+                .iter()
+                .position(|&known| known == cell_type)
+                .unwrap_or(CELL_TYPES.len());
+            local_cell_types |= 1 << bit;
+        }
+    }
+    let mut cell_types_mask: u64 = 0;
+    dm.comm().all_reduce_into(
+        &local_cell_types,
+        &mut cell_types_mask,
+        SystemOperation::bitwise_or(),
+    );
+    if cell_types_mask & (1 << CELL_TYPES.len()) != 0 {
+        return Err(crate::Error {
+            message: "unsupported DMPlex cell type".to_string(),
+        });
+    }
+    let mut cell_types = CELL_TYPES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| cell_types_mask & (1 << bit) != 0)
+        .map(|(_, &cell_type)| cell_type)
+        .collect::<Vec<_>>();
+    cell_types.sort_by_key(|cell_type| *cell_type as i32);
+    Ok(cell_types)
+}
+
+// -----------------------------------------------------------------------------
+// Setup DM with vertex DoFs only
+//   Used for linear elements on mixed topology meshes, where a single PETSc
+//   FE cannot describe every cell
+// -----------------------------------------------------------------------------
+pub(crate) fn setup_dm_vertex_section<'a>(
+    dm: &mut DM<'a, 'a>,
+    num_components: usize,
+) -> crate::Result<()> {
+    let dm_raw = dm.as_raw();
+    let num_components = num_components as petsc::Int;
+    unsafe {
+        let mut comm = std::mem::zeroed();
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscObjectGetComm(
+                dm_raw as petsc::petsc_raw::PetscObject,
+                &mut comm,
+            ),
+            "PetscObjectGetComm",
+        )?;
+        let mut section = std::ptr::null_mut();
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscSectionCreate(comm, &mut section),
+            "PetscSectionCreate",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscSectionSetNumFields(section, 1),
+            "PetscSectionSetNumFields",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscSectionSetFieldComponents(section, 0, num_components),
+            "PetscSectionSetFieldComponents",
+        )?;
+        let (mut point_start, mut point_end) = (0, 0);
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMPlexGetChart(dm_raw, &mut point_start, &mut point_end),
+            "DMPlexGetChart",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscSectionSetChart(section, point_start, point_end),
+            "PetscSectionSetChart",
+        )?;
+        let (mut vertex_start, mut vertex_end) = (0, 0);
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMPlexGetDepthStratum(dm_raw, 0, &mut vertex_start, &mut vertex_end),
+            "DMPlexGetDepthStratum",
+        )?;
+        for vertex in vertex_start..vertex_end {
+            crate::check_petsc_raw(
+                petsc::petsc_raw::PetscSectionSetDof(section, vertex, num_components),
+                "PetscSectionSetDof",
+            )?;
+            crate::check_petsc_raw(
+                petsc::petsc_raw::PetscSectionSetFieldDof(section, vertex, 0, num_components),
+                "PetscSectionSetFieldDof",
+            )?;
+        }
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscSectionSetUp(section),
+            "PetscSectionSetUp",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::DMSetLocalSection(dm_raw, section),
+            "DMSetLocalSection",
+        )?;
+        crate::check_petsc_raw(
+            petsc::petsc_raw::PetscSectionDestroy(&mut section),
+            "PetscSectionDestroy",
+        )?;
+    }
+    dm.plex_set_closure_permutation_tensor_default(None)?;
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Linear tetrahedral basis
+//   Nodes follow the PETSc reference tetrahedron vertex order, (-1, -1, -1),
+//   (-1, 1, -1), (1, -1, -1), (-1, -1, 1), so closure order from DMPlex gives
+//   positively oriented elements. Uses a 4 point quadrature exact for degree 2.
+// -----------------------------------------------------------------------------
+pub(crate) fn create_basis_tet_p1<'b>(
+    ceed: &libceed::Ceed,
+    num_components: usize,
+) -> crate::Result<Basis<'b>> {
+    const NUM_NODES: usize = 4;
+    const NUM_QPTS: usize = 4;
+    const DIM: usize = 3;
+
+    // Quadrature in barycentric coordinates
+    let (a, b) = (0.5854101966249685, 0.1381966011250105);
+    let barycentric = [[a, b, b], [b, a, b], [b, b, a], [b, b, b]];
+    let mut q_ref = [0.; DIM * NUM_QPTS];
+    let mut q_weight = [0.; NUM_QPTS];
+    let mut interp = [0.; NUM_QPTS * NUM_NODES];
+    let mut grad = [0.; DIM * NUM_QPTS * NUM_NODES];
+    for (q, [xi, eta, zeta]) in barycentric.iter().enumerate() {
+        // Reference coordinates on [-1, 1]
+        q_ref[q] = 2. * xi - 1.;
+        q_ref[NUM_QPTS + q] = 2. * eta - 1.;
+        q_ref[2 * NUM_QPTS + q] = 2. * zeta - 1.;
+        // Reference tetrahedron volume is 4/3
+        q_weight[q] = 1. / 3.;
+        // Shape functions for vertex order v0, v1 (y), v2 (x), v3 (z)
+        let phi = [1. - xi - eta - zeta, *eta, *xi, *zeta];
+        let dphi = [
+            [-0.5, -0.5, -0.5],
+            [0., 0.5, 0.],
+            [0.5, 0., 0.],
+            [0., 0., 0.5],
+        ];
+        for node in 0..NUM_NODES {
+            interp[q * NUM_NODES + node] = phi[node];
+            for d in 0..DIM {
+                grad[(d * NUM_QPTS + q) * NUM_NODES + node] = dphi[node][d];
+            }
+        }
+    }
+
+    let basis = ceed.basis_H1(
+        ElemTopology::Tet,
+        num_components,
+        NUM_NODES,
+        NUM_QPTS,
+        &interp,
+        &grad,
+        &q_ref,
+        &q_weight,
+    )?;
+    Ok(basis)
+}
+
+// -----------------------------------------------------------------------------
+// Check if a DMPlex has simplex cells
+// -----------------------------------------------------------------------------
+pub(crate) fn plex_is_simplex(dm: &DM) -> crate::Result<bool> {
+    let mut is_simplex = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+    crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::DMPlexIsSimplex(dm.as_raw(), &mut is_simplex) },
+        "DMPlexIsSimplex",
+    )?;
+    Ok(is_simplex == petsc::petsc_raw::PetscBool::PETSC_TRUE)
+}
+
+// -----------------------------------------------------------------------------
+// Raw PETSc object destroyed when dropped, so early returns do not leak it
+// -----------------------------------------------------------------------------
+struct RawGuard<T> {
+    object: T,
+    destroy: unsafe extern "C" fn(*mut T) -> petsc::petsc_raw::PetscErrorCode,
+}
+
+impl<T> Drop for RawGuard<T> {
+    fn drop(&mut self) {
+        // Errors cannot be propagated from drop
+        unsafe {
+            (self.destroy)(&mut self.object);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Simplex Lagrange basis of any order
+//   Tabulated from a scalar PETSc Lagrange FE, so nodes follow the closure
+//   order of the DMPlex section. Uses the Stroud conical quadrature with the
+//   given number of points in each direction, exact for degree 2 q - 1, as
+//   Gauss quadrature on tensor product cells.
+// -----------------------------------------------------------------------------
+pub(crate) fn create_basis_simplex<'b>(
+    ceed: &libceed::Ceed,
+    dimension: usize,
+    num_components: usize,
+    order: usize,
+    num_qpts_1d: usize,
+) -> crate::Result<Basis<'b>> {
+    let topology = match dimension {
+        2 => ElemTopology::Triangle,
+        3 => ElemTopology::Tet,
+        _ => {
+            return Err(crate::Error {
+                message: format!("simplex bases require dimension 2 or 3, not {}", dimension),
+            })
+        }
+    };
+    let (q_ref, q_weight, interp, grad) = unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let dim = dimension as petsc::Int;
+
+        // Quadrature on the reference simplex
+        let mut quadrature = std::ptr::null_mut();
+        check(
+            PetscDTStroudConicalQuadrature(
+                dim,
+                1,
+                num_qpts_1d as petsc::Int,
+                -1.0,
+                1.0,
+                &mut quadrature,
+            ),
+            "PetscDTStroudConicalQuadrature",
+        )?;
+        let quadrature = RawGuard {
+            object: quadrature,
+            destroy: PetscQuadratureDestroy,
+        };
+        let (mut q_dim, mut q_num_components, mut num_qpts) = (0, 0, 0);
+        let (mut points, mut weights) = (std::ptr::null(), std::ptr::null());
+        check(
+            PetscQuadratureGetData(
+                quadrature.object,
+                &mut q_dim,
+                &mut q_num_components,
+                &mut num_qpts,
+                &mut points,
+                &mut weights,
+            ),
+            "PetscQuadratureGetData",
+        )?;
+        let num_qpts = num_qpts as usize;
+
+        // Scalar Lagrange FE, tabulated with first derivatives
+        let mut fe = std::ptr::null_mut();
+        check(
+            PetscFECreateLagrange(
+                PETSC_COMM_SELF,
+                dim,
+                1,
+                PetscBool::PETSC_TRUE,
+                order as petsc::Int,
+                PETSC_DETERMINE,
+                &mut fe,
+            ),
+            "PetscFECreateLagrange",
+        )?;
+        let fe = RawGuard {
+            object: fe,
+            destroy: PetscFEDestroy,
+        };
+        let mut num_nodes = 0;
+        check(
+            PetscFEGetDimension(fe.object, &mut num_nodes),
+            "PetscFEGetDimension",
+        )?;
+        let num_nodes = num_nodes as usize;
+        let mut tabulation = std::ptr::null_mut();
+        check(
+            PetscFECreateTabulation(
+                fe.object,
+                1,
+                num_qpts as petsc::Int,
+                points,
+                1,
+                &mut tabulation,
+            ),
+            "PetscFECreateTabulation",
+        )?;
+        let tabulation = RawGuard {
+            object: tabulation,
+            destroy: PetscTabulationDestroy,
+        };
+
+        // libCEED layouts, interp by point then node, and grad and reference
+        //   points by direction first
+        let values = std::slice::from_raw_parts(*(*tabulation.object).T, num_qpts * num_nodes);
+        let derivatives = std::slice::from_raw_parts(
+            *(*tabulation.object).T.offset(1),
+            num_qpts * num_nodes * dimension,
+        );
+        let points = std::slice::from_raw_parts(points, num_qpts * dimension);
+        let q_weight = std::slice::from_raw_parts(weights, num_qpts).to_vec();
+        let interp = values.to_vec();
+        let mut grad = vec![0.; dimension * num_qpts * num_nodes];
+        let mut q_ref = vec![0.; dimension * num_qpts];
+        for q in 0..num_qpts {
+            for d in 0..dimension {
+                q_ref[d * num_qpts + q] = points[q * dimension + d];
+                for node in 0..num_nodes {
+                    grad[(d * num_qpts + q) * num_nodes + node] =
+                        derivatives[(q * num_nodes + node) * dimension + d];
+                }
+            }
+        }
+        (q_ref, q_weight, interp, grad)
+    };
+
+    let basis = ceed.basis_H1(
+        topology,
+        num_components,
+        interp.len() / q_weight.len(),
+        q_weight.len(),
+        &interp,
+        &grad,
+        &q_ref,
+        &q_weight,
+    )?;
+    Ok(basis)
+}
+
+// -----------------------------------------------------------------------------
+// Number of quadrature points of create_basis_simplex
+// -----------------------------------------------------------------------------
+pub(crate) fn simplex_num_quadrature_points(
+    dimension: usize,
+    num_qpts_1d: usize,
+) -> crate::Result<usize> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut quadrature = std::ptr::null_mut();
+        check(
+            PetscDTStroudConicalQuadrature(
+                dimension as petsc::Int,
+                1,
+                num_qpts_1d as petsc::Int,
+                -1.0,
+                1.0,
+                &mut quadrature,
+            ),
+            "PetscDTStroudConicalQuadrature",
+        )?;
+        let quadrature = RawGuard {
+            object: quadrature,
+            destroy: PetscQuadratureDestroy,
+        };
+        let (mut q_dim, mut q_num_components, mut num_qpts) = (0, 0, 0);
+        let (mut points, mut weights) = (std::ptr::null(), std::ptr::null());
+        check(
+            PetscQuadratureGetData(
+                quadrature.object,
+                &mut q_dim,
+                &mut q_num_components,
+                &mut num_qpts,
+                &mut points,
+                &mut weights,
+            ),
+            "PetscQuadratureGetData",
+        )?;
+        Ok(num_qpts as usize)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Global index of each local DoF, negative for constrained DoFs
+// -----------------------------------------------------------------------------
+pub(crate) fn local_to_global_indices(dm: &DM) -> crate::Result<Vec<petsc::Int>> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut mapping = std::ptr::null_mut();
+        check(
+            DMGetLocalToGlobalMapping(dm.as_raw(), &mut mapping),
+            "DMGetLocalToGlobalMapping",
+        )?;
+        let mut size = 0;
+        check(
+            ISLocalToGlobalMappingGetSize(mapping, &mut size),
+            "ISLocalToGlobalMappingGetSize",
+        )?;
+        let mut indices = std::ptr::null();
+        check(
+            ISLocalToGlobalMappingGetIndices(mapping, &mut indices),
+            "ISLocalToGlobalMappingGetIndices",
+        )?;
+        let local_to_global = std::slice::from_raw_parts(indices, size as usize).to_vec();
+        check(
+            ISLocalToGlobalMappingRestoreIndices(mapping, &mut indices),
+            "ISLocalToGlobalMappingRestoreIndices",
+        )?;
+        Ok(local_to_global)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+// -----------------------------------------------------------------------------
+// Range of local cells in a DMPlex
+// -----------------------------------------------------------------------------
+pub(crate) fn plex_cell_range(dm: &DM) -> crate::Result<(petsc::Int, petsc::Int)> {
+    let (mut cell_start, mut cell_end) = (0, 0);
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::DMPlexGetHeightStratum(dm.as_raw(), 0, &mut cell_start, &mut cell_end)
+        },
+        "DMPlexGetHeightStratum",
+    )?;
+    Ok((cell_start, cell_end))
+}
+
+// Number of local cells, for mesh types with a cell numbering
+pub(crate) fn local_num_cells(dm: &DM, mesh_type: MeshType) -> crate::Result<Option<usize>> {
+    match mesh_type {
+        MeshType::Plex => {
+            let (cell_start, cell_end) = plex_cell_range(dm)?;
+            Ok(Some((cell_end - cell_start) as usize))
+        }
+        _ => Ok(None),
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Vertex coordinates of each hexahedral element
+//   Returned as an E-vector ordered by element, then component, then vertex,
+//   with vertices in tensor closure order, the first direction fastest
+// -----------------------------------------------------------------------------
+pub(crate) fn hex_vertex_coordinates<'a, 'b>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    feature: &str,
+) -> crate::Result<libceed::vector::Vector<'b>> {
+    // Geometry from the benchmark problem DM
+    let mut geometry_dm = meles.dm.borrow().clone();
+    crate::ceed_bps::setup_dm(&mut geometry_dm, options)?;
+    if options.mesh_type != MeshType::Plex
+        || plex_cell_types(&geometry_dm)?
+            != vec![petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_HEXAHEDRON]
+    {
+        return Err(crate::Error {
+            message: format!(
+                "{} require a plex mesh with a single hexahedral cell type",
+                feature
+            ),
+        });
+    }
+    let restr_x = {
+        let mesh_coord_dm = geometry_dm.coordinate_dm()?;
+        create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+    };
+    let mut coordinates = restr_x.create_evector()?;
+    let mut coord_loc = {
+        let mut dm = meles.dm.borrow_mut();
+        dm.coordinates_local()?
+    };
+    let mut coord_loc_ceed = meles.ceed.vector(coord_loc.local_size()?)?;
+    crate::transfer::with_wrapped(&mut coord_loc, &mut coord_loc_ceed, |coord_loc_ceed| {
+        Ok(restr_x.apply(TransposeMode::NoTranspose, coord_loc_ceed, &mut coordinates)?)
+    })?;
+    Ok(coordinates)
+}
+
+// -----------------------------------------------------------------------------
+// Centroid and normal of a DMPlex face, or centroid of a cell
+// -----------------------------------------------------------------------------
+pub(crate) fn plex_point_geometry(
+    dm: &DM,
+    point: petsc::Int,
+) -> crate::Result<([f64; 3], [f64; 3])> {
+    let mut volume = 0.0;
+    let mut centroid = [0.0; 3];
+    let mut normal = [0.0; 3];
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::DMPlexComputeCellGeometryFVM(
+                dm.as_raw(),
+                point,
+                &mut volume,
+                centroid.as_mut_ptr(),
+                normal.as_mut_ptr(),
+            )
+        },
+        "DMPlexComputeCellGeometryFVM",
+    )?;
+    Ok((centroid, normal))
+}
+
+// -----------------------------------------------------------------------------
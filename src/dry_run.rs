@@ -0,0 +1,149 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Setup report
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Discretization and memory of a fully set up benchmark problem, to
+///   validate a configuration before queueing a large job
+pub struct SetupReport {
+    /// Run manifest, with the problem, order, mesh, and DoF counts
+    pub manifest: crate::RunManifest,
+    /// Number of quadrature points over all ranks
+    pub global_quadrature_points: usize,
+    /// Bytes of stored quadrature data over all ranks
+    pub qdata_bytes: usize,
+    /// Bytes of local and global work vectors over all ranks
+    pub vector_bytes: usize,
+    /// Resident memory of the largest rank after setup, in bytes
+    pub max_rank_memory: f64,
+    /// Resident memory of all ranks after setup, in bytes
+    pub total_memory: f64,
+    /// Wall-clock time of the operator setup
+    pub setup_time: std::time::Duration,
+}
+
+impl fmt::Display for SetupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mib = |bytes: f64| bytes / (1024.0 * 1024.0);
+        write!(f, "{}", self.manifest)?;
+        writeln!(f, "Setup Report:")?;
+        writeln!(f, "  Quadrature points : {}", self.global_quadrature_points)?;
+        writeln!(
+            f,
+            "  QData memory      : {:.2} MiB",
+            mib(self.qdata_bytes as f64)
+        )?;
+        writeln!(
+            f,
+            "  Vector memory     : {:.2} MiB",
+            mib(self.vector_bytes as f64)
+        )?;
+        writeln!(
+            f,
+            "  Max rank memory   : {:.2} MiB",
+            mib(self.max_rank_memory)
+        )?;
+        writeln!(f, "  Total memory      : {:.2} MiB", mib(self.total_memory))?;
+        write!(f, "  Setup time        : {:?}", self.setup_time)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Dry run command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) dry_run: bool,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let dry_run = pob.options_bool(
+            "-dry_run",
+            "Set up the mesh, bases, restrictions, and operators and report them without solving",
+            "",
+            false,
+        )?;
+        Ok(Opt { dry_run })
+    }
+}
+
+pub(crate) fn is_dry_run(petsc: &Petsc) -> crate::Result<bool> {
+    let Opt { dry_run } = {
+        let _options_lock = crate::config::options_lock();
+        petsc.options()?
+    };
+    Ok(dry_run)
+}
+
+// -----------------------------------------------------------------------------
+// Set up the operator and report the discretization and memory
+//   The MatShell creates the bases, restrictions, libCEED operator, and
+//   quadrature data exactly as a solve would, and is dropped after the report
+// -----------------------------------------------------------------------------
+pub(crate) fn setup_report<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<SetupReport> {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+
+    // Operator setup
+    let setup_start = std::time::Instant::now();
+    let mat = meles.mat_shell(petsc)?;
+    let setup_time = setup_start.elapsed();
+    let context = mat.mat_data().unwrap();
+
+    // Local sizes
+    let (local_quadrature_points, local_qdata_bytes) =
+        context.qdata.iter().fold((0, 0), |(points, bytes), qdata| {
+            let num_points = qdata.num_elements * qdata.num_quadrature_points;
+            (
+                points + num_points,
+                bytes + num_points * qdata.size * std::mem::size_of::<f64>(),
+            )
+        });
+    let local_vector_bytes = {
+        let scalar_bytes = std::mem::size_of::<petsc::Scalar>();
+        let local_size = context.x_loc.borrow().local_size()? as usize;
+        let global_size = context.rhs.local_size()? as usize;
+        // PETSc and libCEED local vectors, input and output, and the RHS
+        (4 * local_size + global_size) * scalar_bytes
+    };
+    let mut local_memory = 0.0;
+    crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::PetscMemoryGetCurrentUsage(&mut local_memory) },
+        "PetscMemoryGetCurrentUsage",
+    )?;
+
+    // Global sums
+    let global_sum = |value: usize| {
+        let mut sum = 0u64;
+        meles
+            .comm
+            .all_reduce_into(&(value as u64), &mut sum, SystemOperation::sum());
+        sum as usize
+    };
+    let global_quadrature_points = global_sum(local_quadrature_points);
+    let qdata_bytes = global_sum(local_qdata_bytes);
+    let vector_bytes = global_sum(local_vector_bytes);
+    let mut max_rank_memory = 0.0;
+    meles
+        .comm
+        .all_reduce_into(&local_memory, &mut max_rank_memory, SystemOperation::max());
+    let mut total_memory = 0.0;
+    meles
+        .comm
+        .all_reduce_into(&local_memory, &mut total_memory, SystemOperation::sum());
+
+    Ok(SetupReport {
+        manifest: crate::manifest::run_manifest(meles)?,
+        global_quadrature_points,
+        qdata_bytes,
+        vector_bytes,
+        max_rank_memory,
+        total_memory,
+        setup_time,
+    })
+}
+
+// -----------------------------------------------------------------------------
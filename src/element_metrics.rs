@@ -0,0 +1,304 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Element metrics
+// -----------------------------------------------------------------------------
+const METRIC_NAMES: [&str; 3] = ["min_det_j", "condition", "qdata_norm"];
+
+#[derive(Clone, Debug, PartialEq)]
+/// Geometric and quadrature data quality of each local element, to find the
+///   elements of distorted meshes that break solver convergence
+///
+/// Values are ordered by local cell, as in cell-wise coefficients.
+pub struct ElementMetrics {
+    /// Minimum Jacobian determinant over the quadrature points
+    pub min_det_j: Vec<f64>,
+    /// Maximum condition number of the geometric Jacobian over the
+    ///   quadrature points, |J| |J^{-1}| / dim in the Frobenius norm, so 1
+    ///   for scaled copies of the reference cell
+    ///
+    /// This measures the shape of the element only; it is not an estimate of
+    /// the condition number of the element matrix.
+    pub condition: Vec<f64>,
+    /// Frobenius norm of the operator quadrature data of the element, zero
+    ///   with on-the-fly geometric factors
+    pub qdata_norm: Vec<f64>,
+}
+
+impl ElementMetrics {
+    /// Returns the local indices of inverted elements, with a non-positive
+    ///   Jacobian determinant at some quadrature point
+    pub fn inverted(&self) -> Vec<usize> {
+        self.min_det_j
+            .iter()
+            .enumerate()
+            .filter(|(_, &det_j)| det_j <= 0.0)
+            .map(|(element, _)| element)
+            .collect()
+    }
+
+    /// Returns the local index of the element with the largest condition
+    ///   number, if any
+    pub fn worst_conditioned(&self) -> Option<usize> {
+        self.condition
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(element, _)| element)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Jacobian metrics QFunction
+//   metrics = [det(J), |J| |J^{-1}| / dim] at each quadrature point
+// -----------------------------------------------------------------------------
+fn metrics_q_function<'b>(ceed: &libceed::Ceed, dimension: usize) -> crate::Result<QFunction<'b>> {
+    let evaluate = move |[dx, ..]: QFunctionInputs, [metrics, ..]: QFunctionOutputs| {
+        let q = metrics.len() / 2;
+        for i in 0..q {
+            let jacobian = crate::jacobian::Jacobian::at_point(dx, dimension, q, i);
+            let norm_j: f64 = (0..dimension * dimension)
+                .map(|k| dx[k * q + i] * dx[k * q + i])
+                .sum();
+            let norm_inverse: f64 = jacobian.inverse[..dimension]
+                .iter()
+                .flat_map(|row| &row[..dimension])
+                .map(|entry| entry * entry)
+                .sum();
+            metrics[i] = jacobian.det;
+            metrics[q + i] = if jacobian.det != 0.0 {
+                (norm_j * norm_inverse).sqrt() / dimension as f64
+            } else {
+                f64::INFINITY
+            };
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(evaluate))?
+        .input("dx", dimension * dimension, EvalMode::Grad)?
+        .output("metrics", 2, EvalMode::None)?;
+    Ok(qf)
+}
+
+// -----------------------------------------------------------------------------
+// Compute element metrics
+//   The Jacobian metrics use the quadrature of the problem operator, and the
+//   quadrature data norms include any coefficients scaling the operator; the
+//   metrics operator is a Rust closure QFunction, so it runs on the host Ceed
+// -----------------------------------------------------------------------------
+pub(crate) fn element_metrics<'a>(meles: &'a crate::Meles<'a>) -> crate::Result<ElementMetrics> {
+    let options = &meles.bp_options;
+    if options.mesh_type != crate::dm::MeshType::Plex
+        || crate::dm::plex_cell_types(&meles.dm.borrow())?.len() != 1
+    {
+        return Err(crate::Error {
+            message: "element metrics require a plex mesh with a single cell type".to_string(),
+        });
+    }
+    let context = crate::ceed_bps::mat_shell_context(meles, options)?;
+    let dm = context.dm.borrow();
+    let dimension = dm.dimension()?;
+    let num_elements = {
+        let (cell_start, cell_end) = crate::dm::plex_cell_range(&dm)?;
+        (cell_end - cell_start) as usize
+    };
+
+    // Jacobian metrics at the operator quadrature points
+    let ceed = crate::backend::host_ceed(meles);
+    let p = options.order + 1;
+    let q = p + options.q_extra;
+    let basis_x = if crate::dm::plex_is_simplex(&dm)? {
+        crate::dm::create_basis_simplex(&ceed, dimension, dimension, 1, q)?
+    } else {
+        let q_mode = crate::backend::quadrature_mode(
+            meles,
+            crate::ceed_bps::bp_data(options.problem)?.q_mode,
+        );
+        ceed.basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?
+    };
+    let num_quadrature_points = basis_x.num_quadrature_points();
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &ceed, 0, None, 0)?
+    };
+    let restr_metrics =
+        crate::qdata::QDataVector::restriction(&ceed, num_elements, num_quadrature_points, 2)?;
+    let coord_loc_ceed = {
+        let coord_loc = {
+            let mut dm = meles.dm.borrow_mut();
+            dm.coordinates_local()?
+        };
+        crate::transfer::owned_copy(&ceed, &coord_loc)?
+    };
+    let mut metrics = restr_metrics.create_lvector()?;
+    let qf_metrics = metrics_q_function(&ceed, dimension)?;
+    ceed.operator(&qf_metrics, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("dx", &restr_x, &basis_x, VectorOpt::Active)?
+        .field(
+            "metrics",
+            &restr_metrics,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?
+        .apply(&coord_loc_ceed, &mut metrics)?;
+
+    // Reduce over the quadrature points of each element
+    let (min_det_j, condition) = {
+        let metrics_view = metrics.view()?;
+        metrics_view
+            .chunks_exact(2 * num_quadrature_points)
+            .map(|element| {
+                let (det_j, condition) = element.split_at(num_quadrature_points);
+                (
+                    det_j.iter().cloned().fold(f64::INFINITY, f64::min),
+                    condition.iter().cloned().fold(0.0, f64::max),
+                )
+            })
+            .unzip()
+    };
+
+    // Quadrature data norms, over every operator of the problem, with no
+    //   stored quadrature data for on-the-fly geometric factors
+    let mut qdata_norm = vec![0.0; num_elements];
+    for qdata in context.qdata()? {
+        let element_size = qdata.size * qdata.num_quadrature_points;
+        for (norm, values) in qdata_norm
+            .iter_mut()
+            .zip(qdata.values.chunks_exact(element_size))
+        {
+            *norm += values.iter().map(|value| value * value).sum::<f64>();
+        }
+    }
+    for norm in &mut qdata_norm {
+        *norm = norm.sqrt();
+    }
+
+    Ok(ElementMetrics {
+        min_det_j,
+        condition,
+        qdata_norm,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Write element metrics to VTU
+//   The metrics are fields of a section with one DoF per cell on a clone of
+//   the mesh, so the VTU writer stores them as cell data
+// -----------------------------------------------------------------------------
+pub(crate) fn write_vtu<'a>(
+    meles: &'a crate::Meles<'a>,
+    path: &std::path::Path,
+) -> crate::Result<()> {
+    let metrics = element_metrics(meles)?;
+    let filename =
+        std::ffi::CString::new(path.to_string_lossy().as_ref()).map_err(|_| crate::Error {
+            message: format!("invalid VTU filename {}", path.display()),
+        })?;
+    let vector_name = std::ffi::CString::new("element_metrics").unwrap();
+    let field_names = METRIC_NAMES
+        .iter()
+        .map(|name| std::ffi::CString::new(*name).unwrap())
+        .collect::<Vec<_>>();
+    let fields = [&metrics.min_det_j, &metrics.condition, &metrics.qdata_norm];
+
+    let dm = meles.dm.borrow().clone();
+    let dm_raw = dm.as_raw();
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(&dm)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+
+        // Cell section, one field per metric, destroyed on every path
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(dm_raw as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut section = std::ptr::null_mut();
+        check(PetscSectionCreate(comm, &mut section), "PetscSectionCreate")?;
+        let metrics_loc = (|| -> crate::Result<petsc::vector::Vector> {
+            check(
+                PetscSectionSetNumFields(section, fields.len() as petsc::Int),
+                "PetscSectionSetNumFields",
+            )?;
+            for (field, name) in field_names.iter().enumerate() {
+                check(
+                    PetscSectionSetFieldName(section, field as petsc::Int, name.as_ptr()),
+                    "PetscSectionSetFieldName",
+                )?;
+            }
+            let (mut point_start, mut point_end) = (0, 0);
+            check(
+                DMPlexGetChart(dm_raw, &mut point_start, &mut point_end),
+                "DMPlexGetChart",
+            )?;
+            check(
+                PetscSectionSetChart(section, point_start, point_end),
+                "PetscSectionSetChart",
+            )?;
+            for cell in cell_start..cell_end {
+                check(
+                    PetscSectionSetDof(section, cell, fields.len() as petsc::Int),
+                    "PetscSectionSetDof",
+                )?;
+                for field in 0..fields.len() {
+                    check(
+                        PetscSectionSetFieldDof(section, cell, field as petsc::Int, 1),
+                        "PetscSectionSetFieldDof",
+                    )?;
+                }
+            }
+            check(PetscSectionSetUp(section), "PetscSectionSetUp")?;
+            check(DMSetLocalSection(dm_raw, section), "DMSetLocalSection")?;
+
+            // Cell values
+            let mut metrics_loc = dm.create_local_vector()?;
+            {
+                let mut view = metrics_loc.view_mut()?;
+                for cell in cell_start..cell_end {
+                    for (field, values) in fields.iter().enumerate() {
+                        let mut offset = 0;
+                        check(
+                            PetscSectionGetFieldOffset(
+                                section,
+                                cell,
+                                field as petsc::Int,
+                                &mut offset,
+                            ),
+                            "PetscSectionGetFieldOffset",
+                        )?;
+                        view[offset as usize] = values[(cell - cell_start) as usize];
+                    }
+                }
+            }
+            Ok(metrics_loc)
+        })();
+        check(PetscSectionDestroy(&mut section), "PetscSectionDestroy")?;
+        let metrics_loc = metrics_loc?;
+        let mut metrics_global = dm.create_global_vector()?;
+        dm.local_to_global(&metrics_loc, InsertMode::INSERT_VALUES, &mut metrics_global)?;
+        check(
+            PetscObjectSetName(metrics_global.as_raw() as PetscObject, vector_name.as_ptr()),
+            "PetscObjectSetName",
+        )?;
+
+        // The VTK viewer writes the file when destroyed
+        let mut viewer = std::ptr::null_mut();
+        check(
+            PetscViewerVTKOpen(
+                comm,
+                filename.as_ptr(),
+                PetscFileMode::FILE_MODE_WRITE,
+                &mut viewer,
+            ),
+            "PetscViewerVTKOpen",
+        )?;
+        let result = check(VecView(metrics_global.as_raw(), viewer), "VecView");
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+// -----------------------------------------------------------------------------
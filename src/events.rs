@@ -0,0 +1,160 @@
+// -----------------------------------------------------------------------------
+// PETSc log events and stages
+//   Registered once, on first use after PETSc is initialized, so -log_view
+//   breaks Meles time down by phase instead of reporting anonymous MatMult
+//   time; guards end the event or pop the stage when dropped, including on
+//   early returns
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Event {
+    DmSetup,
+    Restriction,
+    QDataSetup,
+    Rhs,
+    OperatorApply,
+    Diagonal,
+    PointBlockDiagonal,
+}
+
+const EVENT_NAMES: [&str; 7] = [
+    "MelesDMSetup",
+    "MelesRestrict",
+    "MelesQData",
+    "MelesRHS",
+    "MelesOpApply",
+    "MelesOpDiag",
+    "MelesOpPBDiag",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stage {
+    Setup,
+    Solve,
+}
+
+const STAGE_NAMES: [&str; 2] = ["Meles Setup", "Meles Solve"];
+
+struct Registry {
+    events: [petsc::petsc_raw::PetscLogEvent; EVENT_NAMES.len()],
+    stages: [petsc::petsc_raw::PetscLogStage; STAGE_NAMES.len()],
+}
+
+// Registered events and stages, None if registration failed
+fn registry() -> Option<&'static Registry> {
+    static REGISTRY: std::sync::OnceLock<Option<Registry>> = std::sync::OnceLock::new();
+    let registry = REGISTRY.get_or_init(|| {
+        let register = || -> crate::Result<Registry> {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let class_name = std::ffi::CString::new("Meles").unwrap();
+            let mut class_id = 0;
+            let mut registry = Registry {
+                events: [0; EVENT_NAMES.len()],
+                stages: [0; STAGE_NAMES.len()],
+            };
+            unsafe {
+                check(
+                    PetscClassIdRegister(class_name.as_ptr(), &mut class_id),
+                    "PetscClassIdRegister",
+                )?;
+                for (event, name) in registry.events.iter_mut().zip(EVENT_NAMES.iter()) {
+                    let name = std::ffi::CString::new(*name).unwrap();
+                    check(
+                        PetscLogEventRegister(name.as_ptr(), class_id, event),
+                        "PetscLogEventRegister",
+                    )?;
+                }
+                for (stage, name) in registry.stages.iter_mut().zip(STAGE_NAMES.iter()) {
+                    let name = std::ffi::CString::new(*name).unwrap();
+                    check(
+                        PetscLogStageRegister(name.as_ptr(), stage),
+                        "PetscLogStageRegister",
+                    )?;
+                }
+            }
+            Ok(registry)
+        };
+        register().ok()
+    });
+    registry.as_ref()
+}
+
+// -----------------------------------------------------------------------------
+// PetscLogEventBegin and PetscLogEventEnd
+//   These are C macros, so they are not in the bindings; as the macros do,
+//   call the logging handlers, which are only set when logging is active
+// -----------------------------------------------------------------------------
+fn petsc_log_event_begin(event: petsc::petsc_raw::PetscLogEvent) -> crate::Result<()> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        match PetscLogPLB {
+            Some(begin) => {
+                let null = std::ptr::null_mut();
+                crate::check_petsc_raw(
+                    begin(event, 0, null, null, null, null),
+                    "PetscLogEventBegin",
+                )
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn petsc_log_event_end(event: petsc::petsc_raw::PetscLogEvent) -> crate::Result<()> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        match PetscLogPLE {
+            Some(end) => {
+                let null = std::ptr::null_mut();
+                crate::check_petsc_raw(end(event, 0, null, null, null, null), "PetscLogEventEnd")
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Event and stage guards
+// -----------------------------------------------------------------------------
+pub(crate) struct EventGuard(Option<petsc::petsc_raw::PetscLogEvent>);
+
+impl Event {
+    // Begin the event, ending it when the guard is dropped
+    pub(crate) fn begin(self) -> EventGuard {
+        let event = registry()
+            .map(|registry| registry.events[self as usize])
+            .filter(|&event| petsc_log_event_begin(event).is_ok());
+        EventGuard(event)
+    }
+}
+
+impl Drop for EventGuard {
+    fn drop(&mut self) {
+        if let Some(event) = self.0 {
+            // Errors cannot be propagated from drop
+            let _ = petsc_log_event_end(event);
+        }
+    }
+}
+
+pub(crate) struct StageGuard(bool);
+
+impl Stage {
+    // Push the stage, popping it when the guard is dropped
+    pub(crate) fn push(self) -> StageGuard {
+        let pushed = registry().is_some_and(|registry| unsafe {
+            petsc::petsc_raw::PetscLogStagePush(registry.stages[self as usize]) == 0
+        });
+        StageGuard(pushed)
+    }
+}
+
+impl Drop for StageGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            unsafe { petsc::petsc_raw::PetscLogStagePop() };
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
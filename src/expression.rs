@@ -0,0 +1,405 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Expression instructions
+//   Expressions are compiled to postfix instructions evaluated on a stack
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy)]
+enum Instruction {
+    Constant(f64),
+    Variable(usize),
+    Function(fn(f64) -> f64),
+    Negate,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+// Variables, indexed in the evaluation point
+const VARIABLES: [&str; 4] = ["x", "y", "z", "t"];
+
+// Named constants
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(std::f64::consts::PI),
+        "e" => Some(std::f64::consts::E),
+        _ => None,
+    }
+}
+
+// Named functions of one argument
+fn function(name: &str) -> Option<fn(f64) -> f64> {
+    Some(match name {
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        "sinh" => f64::sinh,
+        "cosh" => f64::cosh,
+        "tanh" => f64::tanh,
+        "exp" => f64::exp,
+        "log" => f64::ln,
+        "sqrt" => f64::sqrt,
+        "abs" => f64::abs,
+        _ => return None,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Tokens
+// -----------------------------------------------------------------------------
+#[derive(Clone, PartialEq)]
+pub(crate) enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+}
+
+pub(crate) fn tokenize(source: &str) -> crate::Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse().map_err(|_| crate::Error {
+                message: format!("invalid number {} in expression {}", text, source),
+            })?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+        } else if c == '*' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            tokens.push(Token::Operator('^'));
+            i += 2;
+        } else if "+-*/^".contains(c) {
+            tokens.push(Token::Operator(c));
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::OpenParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::CloseParen);
+            i += 1;
+        } else {
+            return Err(crate::Error {
+                message: format!("unexpected character {} in expression {}", c, source),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+// -----------------------------------------------------------------------------
+// Recursive descent parser
+//   expression := term (('+' | '-') term)*
+//   term       := unary (('*' | '/') unary)*
+//   unary      := '-' unary | '+' unary | power
+//   power      := atom ('^' unary)?
+//   atom       := number | constant | variable | function '(' expression ')'
+//               | '(' expression ')'
+// -----------------------------------------------------------------------------
+struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<Token>,
+    position: usize,
+    instructions: Vec<Instruction>,
+}
+
+impl<'s> Parser<'s> {
+    fn error(&self, message: &str) -> crate::Error {
+        crate::Error {
+            message: format!("{} in expression {}", message, self.source),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expression(&mut self) -> crate::Result<()> {
+        self.term()?;
+        while let Some(Token::Operator(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.position += 1;
+            self.term()?;
+            self.instructions.push(if op == '+' {
+                Instruction::Add
+            } else {
+                Instruction::Subtract
+            });
+        }
+        Ok(())
+    }
+
+    fn term(&mut self) -> crate::Result<()> {
+        self.unary()?;
+        while let Some(Token::Operator(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.position += 1;
+            self.unary()?;
+            self.instructions.push(if op == '*' {
+                Instruction::Multiply
+            } else {
+                Instruction::Divide
+            });
+        }
+        Ok(())
+    }
+
+    fn unary(&mut self) -> crate::Result<()> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.position += 1;
+                self.unary()?;
+                self.instructions.push(Instruction::Negate);
+                Ok(())
+            }
+            Some(Token::Operator('+')) => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> crate::Result<()> {
+        self.atom()?;
+        if let Some(Token::Operator('^')) = self.peek() {
+            self.position += 1;
+            self.unary()?;
+            self.instructions.push(Instruction::Power);
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self) -> crate::Result<()> {
+        match self.next() {
+            Some(Token::Number(value)) => self.instructions.push(Instruction::Constant(value)),
+            Some(Token::OpenParen) => {
+                self.expression()?;
+                if self.next() != Some(Token::CloseParen) {
+                    return Err(self.error("missing closing parenthesis"));
+                }
+            }
+            Some(Token::Identifier(name)) => {
+                if let Some(f) = function(&name) {
+                    if self.next() != Some(Token::OpenParen) {
+                        return Err(self.error(&format!("missing argument for {}", name)));
+                    }
+                    self.expression()?;
+                    if self.next() != Some(Token::CloseParen) {
+                        return Err(self.error("missing closing parenthesis"));
+                    }
+                    self.instructions.push(Instruction::Function(f));
+                } else if let Some(value) = constant(&name) {
+                    self.instructions.push(Instruction::Constant(value));
+                } else if let Some(index) = VARIABLES.iter().position(|v| *v == name) {
+                    self.instructions.push(Instruction::Variable(index));
+                } else {
+                    return Err(self.error(&format!("unknown name {}", name)));
+                }
+            }
+            _ => return Err(self.error("expected a value")),
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Expression
+// -----------------------------------------------------------------------------
+// Central difference steps, near the cube and fourth roots of machine epsilon
+//   that balance truncation and rounding error for first and second derivatives
+const GRADIENT_STEP: f64 = 6e-6;
+const LAPLACIAN_STEP: f64 = 1e-4;
+
+#[derive(Clone)]
+/// Analytic function of space and time parsed at runtime
+///
+/// Expressions use the variables `x`, `y`, `z`, and `t`, the constants `pi`
+/// and `e`, the operators `+`, `-`, `*`, `/`, and `^` or `**`, and the
+/// functions `sin`, `cos`, `tan`, `asin`, `acos`, `atan`, `sinh`, `cosh`,
+/// `tanh`, `exp`, `log`, `sqrt`, and `abs`.
+///
+/// ```
+/// # fn main() -> meles::Result<()> {
+/// let expression = meles::Expression::parse("sin(pi*x)*sin(pi*y) + 2*t^2")?;
+/// let value = expression.evaluate(&[0.5, 0.5, 0.0], 1.0);
+/// assert!((value - 3.0).abs() < 1e-14, "incorrect value");
+///
+/// // invalid expressions are rejected when parsed
+/// assert!(meles::Expression::parse("sin(pi*w)").is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct Expression {
+    source: String,
+    instructions: Vec<Instruction>,
+    max_stack_size: usize,
+}
+
+impl Expression {
+    /// Parse and compile an expression
+    ///
+    /// # arguments
+    ///
+    /// * `source` - Expression to parse
+    pub fn parse(source: &str) -> crate::Result<Self> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+            instructions: vec![],
+        };
+        parser.expression()?;
+        if parser.position < parser.tokens.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+
+        // Stack depth needed for evaluation
+        let mut depth: usize = 0;
+        let mut max_stack_size = 0;
+        for instruction in &parser.instructions {
+            match instruction {
+                Instruction::Constant(_) | Instruction::Variable(_) => depth += 1,
+                Instruction::Function(_) | Instruction::Negate => {}
+                _ => depth -= 1,
+            }
+            max_stack_size = max_stack_size.max(depth);
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            instructions: parser.instructions,
+            max_stack_size,
+        })
+    }
+
+    /// Returns the source of the expression
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluate the expression at a point and time
+    ///
+    /// # arguments
+    ///
+    /// * `x` - Coordinates of the point, with missing coordinates taken as zero
+    /// * `t` - Time
+    pub fn evaluate(&self, x: &[f64], t: f64) -> f64 {
+        let point = [
+            x.first().copied().unwrap_or(0.0),
+            x.get(1).copied().unwrap_or(0.0),
+            x.get(2).copied().unwrap_or(0.0),
+            t,
+        ];
+        let mut stack = Vec::with_capacity(self.max_stack_size);
+        for instruction in &self.instructions {
+            match *instruction {
+                Instruction::Constant(value) => stack.push(value),
+                Instruction::Variable(index) => stack.push(point[index]),
+                Instruction::Function(f) => {
+                    let a = stack.pop().unwrap();
+                    stack.push(f(a));
+                }
+                Instruction::Negate => {
+                    let a = stack.pop().unwrap();
+                    stack.push(-a);
+                }
+                binary => {
+                    let b = stack.pop().unwrap();
+                    let a = stack.pop().unwrap();
+                    stack.push(match binary {
+                        Instruction::Add => a + b,
+                        Instruction::Subtract => a - b,
+                        Instruction::Multiply => a * b,
+                        Instruction::Divide => a / b,
+                        _ => a.powf(b),
+                    });
+                }
+            }
+        }
+        stack[0]
+    }
+
+    // Gradient at a point, by central differences
+    pub(crate) fn gradient(&self, x: &[f64], t: f64) -> [f64; 3] {
+        let mut gradient = [0.0; 3];
+        for (d, gradient_d) in gradient.iter_mut().enumerate().take(x.len()) {
+            let h = GRADIENT_STEP * x[d].abs().max(1.0);
+            let (mut x_plus, mut x_minus) = (x.to_vec(), x.to_vec());
+            x_plus[d] += h;
+            x_minus[d] -= h;
+            *gradient_d = (self.evaluate(&x_plus, t) - self.evaluate(&x_minus, t)) / (2.0 * h);
+        }
+        gradient
+    }
+
+    // Laplacian at a point, by central differences
+    pub(crate) fn laplacian(&self, x: &[f64], t: f64) -> f64 {
+        let center = self.evaluate(x, t);
+        (0..x.len())
+            .map(|d| {
+                let h = LAPLACIAN_STEP * x[d].abs().max(1.0);
+                let (mut x_plus, mut x_minus) = (x.to_vec(), x.to_vec());
+                x_plus[d] += h;
+                x_minus[d] -= h;
+                (self.evaluate(&x_plus, t) - 2.0 * center + self.evaluate(&x_minus, t)) / (h * h)
+            })
+            .sum()
+    }
+}
+
+impl fmt::Debug for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expression({:?})", self.source)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+// -----------------------------------------------------------------------------
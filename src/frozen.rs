@@ -0,0 +1,217 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Frozen operator
+// -----------------------------------------------------------------------------
+/// Immutable libCEED operator handle
+///
+/// A frozen operator owns its DM, libCEED operator, and assembled diagonal
+/// and holds no interior mutability. Local work vectors are allocated per
+/// application, so `apply` takes `&self` and the same operator may be shared
+/// by any number of MatShells, e.g. as both the operator and a preconditioner
+/// component, without aliasing panics.
+pub struct FrozenOperator<'a> {
+    pub(crate) ceed: libceed::Ceed,
+    pub(crate) dm: DM<'a, 'a>,
+    pub(crate) op_ceed: crate::MelesOperator<'a>,
+    pub(crate) diagonal: petsc::vector::Vector<'a>,
+    pub(crate) essential_boundary: bool,
+}
+
+impl<'a> FrozenOperator<'a> {
+    /// Apply the operator, `y = A x`
+    ///
+    /// # arguments
+    ///
+    /// * `x` - Global input vector
+    /// * `y` - Global output vector
+    pub fn apply(
+        &self,
+        x: &petsc::vector::Vector<'a>,
+        y: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.apply_raw(x.as_raw(), y.as_raw())
+    }
+
+    // Apply the operator to raw global vectors, as given to MatShell callbacks
+    pub(crate) fn apply_raw(
+        &self,
+        x: petsc::petsc_raw::Vec,
+        y: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        let (mut x_loc, mut x_loc_ceed, mut y_loc, mut y_loc_ceed) = self.work_vectors()?;
+        crate::petsc_ops::apply_local_ceed_op_with(
+            x,
+            y,
+            &self.dm,
+            &self.op_ceed,
+            &mut x_loc,
+            &mut x_loc_ceed,
+            &mut y_loc,
+            &mut y_loc_ceed,
+        )
+    }
+
+    // Local PETSc and libCEED work vectors for a single application
+    fn work_vectors(
+        &self,
+    ) -> crate::Result<(
+        petsc::vector::Vector<'a>,
+        libceed::vector::Vector<'_>,
+        petsc::vector::Vector<'a>,
+        libceed::vector::Vector<'_>,
+    )> {
+        let x_loc = self.dm.create_local_vector()?;
+        let y_loc = x_loc.duplicate()?;
+        let local_size = x_loc.local_size()? as usize;
+        let x_loc_ceed = self.ceed.vector(local_size)?;
+        let y_loc_ceed = self.ceed.vector(local_size)?;
+        Ok((x_loc, x_loc_ceed, y_loc, y_loc_ceed))
+    }
+
+    /// Returns the residual, `r = b - A x`
+    ///
+    /// Essential boundary values are inserted into the local input before
+    /// applying the operator, so the residual is consistent with the boundary
+    /// conditions of the problem. Constrained DoFs are not part of the global
+    /// vectors.
+    ///
+    /// # arguments
+    ///
+    /// * `x` - Global solution vector
+    /// * `b` - Global right hand side vector
+    pub fn residual(
+        &self,
+        x: &petsc::vector::Vector<'a>,
+        b: &petsc::vector::Vector<'a>,
+    ) -> crate::Result<petsc::vector::Vector<'a>> {
+        let (mut x_loc, mut x_loc_ceed, mut y_loc, mut y_loc_ceed) = self.work_vectors()?;
+        residual_with(
+            &self.dm,
+            &self.op_ceed,
+            self.essential_boundary,
+            (&mut x_loc, &mut x_loc_ceed, &mut y_loc, &mut y_loc_ceed),
+            x,
+            b,
+        )
+    }
+
+    /// Returns the assembled diagonal of the operator
+    pub fn diagonal(&self) -> &petsc::vector::Vector<'a> {
+        &self.diagonal
+    }
+
+    /// Returns the DM the operator acts on
+    pub fn dm(&self) -> &DM<'a, 'a> {
+        &self.dm
+    }
+
+    /// Returns the symmetrically Jacobi scaled operator, `D^{-1/2} A D^{-1/2}`
+    pub fn scaled(
+        self: &std::rc::Rc<Self>,
+    ) -> crate::Result<std::rc::Rc<crate::ScaledOperator<'a>>> {
+        Ok(std::rc::Rc::new(crate::scaling::scale(self.clone())?))
+    }
+
+    /// Returns a MatShell sharing this operator
+    ///
+    /// Each MatShell holds a reference count on the operator, so the same
+    /// frozen operator may back several MatShells at once.
+    pub fn mat_shell(
+        self: &std::rc::Rc<Self>,
+    ) -> crate::Result<petsc::mat::MatShell<'a, 'a, std::rc::Rc<FrozenOperator<'a>>>> {
+        let mat = self.dm.create_matrix()?;
+        let mat = mat.into_shell(Box::new(self.clone()))?;
+
+        // Set operations
+        crate::petsc_ops::set_shell_operations(
+            &mat,
+            &[MatOperation::MATOP_MULT, MatOperation::MATOP_GET_DIAGONAL],
+        )?;
+
+        Ok(mat)
+    }
+}
+
+impl crate::petsc_ops::ShellOperations for FrozenOperator<'_> {
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        self.apply_raw(x, y)
+    }
+
+    fn get_diagonal(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        crate::check_petsc_raw(
+            unsafe { petsc::petsc_raw::VecCopy(self.diagonal.as_raw(), d) },
+            "VecCopy",
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Residual, r = b - A x, with essential boundary values inserted into the
+//   local input
+// -----------------------------------------------------------------------------
+pub(crate) fn residual_with<'a>(
+    dm: &DM<'a, 'a>,
+    op_ceed: &crate::MelesOperator,
+    essential_boundary: bool,
+    (x_loc, x_loc_ceed, y_loc, y_loc_ceed): (
+        &mut petsc::vector::Vector<'a>,
+        &mut libceed::vector::Vector,
+        &mut petsc::vector::Vector<'a>,
+        &mut libceed::vector::Vector,
+    ),
+    x: &petsc::vector::Vector<'a>,
+    b: &petsc::vector::Vector<'a>,
+) -> crate::Result<petsc::vector::Vector<'a>> {
+    // Global to local, with boundary values
+    dm.global_to_local(x, InsertMode::INSERT_VALUES, x_loc)?;
+    if essential_boundary {
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::DMPlexInsertBoundaryValues(
+                    dm.as_raw(),
+                    petsc::petsc_raw::PetscBool::PETSC_TRUE,
+                    x_loc.as_raw(),
+                    0.0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            },
+            "DMPlexInsertBoundaryValues",
+        )?;
+    }
+
+    // Apply libCEED operator
+    crate::petsc_ops::apply_ceed_op_local(op_ceed, x_loc, x_loc_ceed, y_loc, y_loc_ceed)?;
+
+    // r = b - A x
+    let mut r = b.duplicate()?;
+    r.zero_entries()?;
+    dm.local_to_global(y_loc, InsertMode::ADD_VALUES, &mut r)?;
+    r.aypx(-1.0, b)?;
+    Ok(r)
+}
+
+// -----------------------------------------------------------------------------
+// Freeze a MatShell context
+//   The diagonal is assembled once so no state is mutated after freezing
+// -----------------------------------------------------------------------------
+pub(crate) fn freeze<'a>(
+    ceed: libceed::Ceed,
+    context: crate::MelesMatShellContext<'a>,
+    essential_boundary: bool,
+) -> crate::Result<FrozenOperator<'a>> {
+    let diagonal = context.dm.borrow().create_global_vector()?;
+    crate::petsc_ops::compute_diagonal_ceed(diagonal.as_raw(), &context)?;
+    let crate::MelesMatShellContext { dm, op_ceed, .. } = context;
+    Ok(FrozenOperator {
+        ceed,
+        dm: dm.into_inner(),
+        op_ceed: op_ceed.into_inner(),
+        diagonal,
+        essential_boundary,
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,199 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Mass matrix inner product
+// -----------------------------------------------------------------------------
+/// Mass matrix for Krylov solves in the `M` inner product
+///
+/// The KSP preconditioner is replaced by the Riesz map `M^{-1}`, applied with
+/// an inner CG solve on the mass MatShell, so the Krylov method measures
+/// residuals in the `M^{-1}` norm and orthogonalizes in the `M` inner
+/// product. The inner solve is created once and may be configured with the
+/// `-mass_` options prefix, e.g. `-mass_ksp_rtol`; its relative tolerance
+/// defaults to `1e-12`. The Riesz map is only applied to the inner tolerance,
+/// so the outer KSP defaults to flexible CG.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+/// let meles = meles::Meles::new(
+///     &petsc,
+///     "./examples/meles.yml",
+///     meles::MethodType::BenchmarkProblem,
+/// )?;
+///
+/// // solve in the M inner product
+/// let mat = meles.mat_shell(&petsc)?;
+/// let mass = meles.mass_inner_product(&petsc)?;
+/// let mut ksp = petsc.ksp_create()?;
+/// ksp.set_operators(&mat, &mat)?;
+/// mass.set_up_ksp(&mut ksp)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MassInnerProduct<'a> {
+    pub(crate) mass: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    // Inner solve on the mass MatShell, holding a reference to the Mat
+    pub(crate) ksp: petsc::petsc_raw::KSP,
+}
+
+impl<'a> MassInnerProduct<'a> {
+    /// Returns the mass MatShell
+    pub fn mat_shell(&self) -> &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>> {
+        &self.mass
+    }
+
+    /// Apply the Riesz map, `z = M^{-1} r`
+    ///
+    /// # arguments
+    ///
+    /// * `r` - Global residual vector
+    /// * `z` - Global output vector
+    pub fn apply_inverse(
+        &self,
+        r: &petsc::vector::Vector<'a>,
+        z: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.apply_inverse_raw(r.as_raw(), z.as_raw())
+    }
+
+    // Apply the Riesz map to raw global vectors, as given to PCShell callbacks
+    fn apply_inverse_raw(
+        &self,
+        r: petsc::petsc_raw::Vec,
+        z: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        crate::check_petsc_raw(
+            unsafe { petsc::petsc_raw::KSPSolve(self.ksp, r, z) },
+            "KSPSolve",
+        )
+    }
+
+    /// Configure a KSP to use the `M` inner product
+    ///
+    /// The KSP defaults to flexible CG, as the Riesz map is applied with an
+    /// inner solve; the type may be overridden from the options database,
+    /// but the preconditioner is always the Riesz map.
+    ///
+    /// # arguments
+    ///
+    /// * `ksp` - KSP with operators already set
+    pub fn set_up_ksp(&'a self, ksp: &mut KSP<'a, 'a>) -> crate::Result<()> {
+        ksp.set_type(KSPType::KSPFCG)?;
+        {
+            let _options_lock = crate::config::options_lock();
+            ksp.set_from_options()?;
+        }
+        let pc = ksp.get_pc_or_create()?;
+        pc.set_type(PCType::PCSHELL)?;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(
+                PCShellSetContext(
+                    pc.as_raw(),
+                    self as *const Self as *mut std::os::raw::c_void,
+                ),
+                "PCShellSetContext",
+            )?;
+            check(
+                PCShellSetApply(pc.as_raw(), Some(riesz_map_apply)),
+                "PCShellSetApply",
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MassInnerProduct<'_> {
+    fn drop(&mut self) {
+        // Errors cannot be propagated from drop
+        let _ = unsafe { petsc::petsc_raw::KSPDestroy(&mut self.ksp) };
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Riesz map PCShell callback
+//   The context is the MassInnerProduct, which outlives the KSP it is set up
+//   on
+// -----------------------------------------------------------------------------
+unsafe extern "C" fn riesz_map_apply(
+    pc: petsc::petsc_raw::PC,
+    r: petsc::petsc_raw::Vec,
+    z: petsc::petsc_raw::Vec,
+) -> petsc::petsc_raw::PetscErrorCode {
+    let mut ctx: *mut std::os::raw::c_void = std::ptr::null_mut();
+    let ierr = petsc::petsc_raw::PCShellGetContext(pc, &mut ctx as *mut _ as _);
+    if ierr != 0 {
+        return ierr;
+    }
+    let mass = &*(ctx as *const MassInnerProduct);
+    match mass.apply_inverse_raw(r, z) {
+        Ok(()) => 0,
+        Err(error) => crate::petsc_callback_error("riesz_map_apply", error),
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Create the mass MatShell on the problem DM
+//   The DM setup, including boundary constraints, matches the problem
+//   operator so both act on the same global vectors
+// -----------------------------------------------------------------------------
+pub(crate) fn mass_inner_product<'a>(
+    meles: &'a crate::Meles<'a>,
+) -> crate::Result<MassInnerProduct<'a>> {
+    let kernel = crate::ceed_bps::mass_problem(&meles.bp_options)?;
+    let context = crate::ceed_bps::mat_shell_context_with_kernel(meles, &meles.bp_options, kernel)?;
+    let mass = crate::petsc_ops::create_mat_shell(context)?;
+
+    // Inner solve, created once for all applications of the Riesz map
+    let ksp = unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(mass.as_raw() as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut ksp = std::ptr::null_mut();
+        check(KSPCreate(comm, &mut ksp), "KSPCreate")?;
+        let prefix = std::ffi::CString::new("mass_").unwrap();
+        let cg_type = std::ffi::CString::new("cg").unwrap();
+        let jacobi_type = std::ffi::CString::new("jacobi").unwrap();
+        let result = (|| {
+            check(
+                KSPSetOptionsPrefix(ksp, prefix.as_ptr()),
+                "KSPSetOptionsPrefix",
+            )?;
+            check(
+                KSPSetOperators(ksp, mass.as_raw(), mass.as_raw()),
+                "KSPSetOperators",
+            )?;
+            check(KSPSetType(ksp, cg_type.as_ptr()), "KSPSetType")?;
+            let mut pc = std::ptr::null_mut();
+            check(KSPGetPC(ksp, &mut pc), "KSPGetPC")?;
+            check(PCSetType(pc, jacobi_type.as_ptr()), "PCSetType")?;
+            let (mut rtol, mut abstol, mut dtol, mut max_it) = (0.0, 0.0, 0.0, 0);
+            check(
+                KSPGetTolerances(ksp, &mut rtol, &mut abstol, &mut dtol, &mut max_it),
+                "KSPGetTolerances",
+            )?;
+            check(
+                KSPSetTolerances(ksp, 1e-12, abstol, dtol, max_it),
+                "KSPSetTolerances",
+            )?;
+            let _options_lock = crate::config::options_lock();
+            check(KSPSetFromOptions(ksp), "KSPSetFromOptions")
+        })();
+        if let Err(error) = result {
+            check(KSPDestroy(&mut ksp), "KSPDestroy")?;
+            return Err(error);
+        }
+        ksp
+    };
+    Ok(MassInnerProduct { mass, ksp })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,51 @@
+// -----------------------------------------------------------------------------
+// Jacobian of the map from reference to physical coordinates at a quadrature
+//   point, with its determinant and inverse computed once, for meshes of
+//   dimension 1, 2, or 3
+// -----------------------------------------------------------------------------
+pub(crate) struct Jacobian {
+    pub(crate) det: f64,
+    // inverse[d][k] = dX_d/dx_k
+    pub(crate) inverse: [[f64; 3]; 3],
+}
+
+impl Jacobian {
+    // From the coordinate gradient of a libCEED QFunction input,
+    //   dx[(d * dim + c) * q + i] = dx_c/dX_d at point i of q
+    pub(crate) fn at_point(dx: &[f64], dim: usize, q: usize, i: usize) -> Self {
+        let j = |c: usize, d: usize| dx[(d * dim + c) * q + i];
+        let mut inverse = [[0.0; 3]; 3];
+        let det = match dim {
+            1 => {
+                let det = j(0, 0);
+                inverse[0][0] = 1.0 / det;
+                det
+            }
+            2 => {
+                let det = j(0, 0) * j(1, 1) - j(0, 1) * j(1, 0);
+                inverse[0][0] = j(1, 1) / det;
+                inverse[0][1] = -j(0, 1) / det;
+                inverse[1][0] = -j(1, 0) / det;
+                inverse[1][1] = j(0, 0) / det;
+                det
+            }
+            _ => {
+                let cofactor = |c: usize, d: usize| {
+                    let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+                    let (d1, d2) = ((d + 1) % 3, (d + 2) % 3);
+                    j(c1, d1) * j(c2, d2) - j(c1, d2) * j(c2, d1)
+                };
+                let det = (0..3).map(|d| j(0, d) * cofactor(0, d)).sum::<f64>();
+                for (d, row) in inverse.iter_mut().enumerate() {
+                    for (k, entry) in row.iter_mut().enumerate() {
+                        *entry = cofactor(k, d) / det;
+                    }
+                }
+                det
+            }
+        };
+        Self { det, inverse }
+    }
+}
+
+// -----------------------------------------------------------------------------
@@ -7,7 +7,20 @@
 use crate::prelude::*;
 
 pub mod prelude {
-    pub use crate::{Meles, MelesMatShellContext, MethodType};
+    pub use crate::{
+        AdvectionDiffusion, AmrMarking, AmrStep, AssembledOperator, BackendAudition, BackendInfo,
+        BackendTiming, BenchmarkReport, CoefficientField, Config, ConfigSource, ConvergenceEntry,
+        ConvergenceStudy, CrossValidation, DarcyProblem, DeflationSpace, DeviceBinding, DgFlux,
+        DgProblem, ElementMetrics, Expression, FieldTransfer, FrozenOperator, GeometricFactors,
+        InitialGuess, MassInnerProduct, Meles, MelesMatShellContext, MelesOperator, MethodType,
+        NonlinearSolver, OperatorSum, OrderSweep, OrderSweepEntry, PMultigrid, PreconditionerReuse,
+        QData, QFunctionSource, QuadratureFields, Quasistatic, Reconfigured, Recycling,
+        RegressionCheck, RegressionReference, RegressionReport, RowSums, RunManifest, RunSummary,
+        ScaledOperator, SegregatedResult, SegregatedSolver, SetupPhase, SetupProgress, SetupReport,
+        ShutdownReason, SolutionError, SolveResult, SolveSession, SolverState, SourceOperator,
+        SplitOperator, StaticCondensation, StepStatistics, SubdomainSplitting, ToleranceProfile,
+        ToleranceProfiles, TransientSolver, UserQFunction,
+    };
     pub(crate) use libceed::prelude::*;
     pub(crate) use petsc::prelude::*;
     pub(crate) use std::cell::RefCell;
@@ -17,9 +30,112 @@ pub mod prelude {
 // -----------------------------------------------------------------------------
 // Modules
 // -----------------------------------------------------------------------------
+pub(crate) mod advection;
+pub(crate) mod amr;
+pub(crate) mod assembly;
+pub(crate) mod backend;
+pub(crate) mod benchmark;
+pub(crate) mod boundary_conditions;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub(crate) mod ceed_bps;
+pub(crate) mod ceed_io;
+pub(crate) mod checkpoint;
+pub(crate) mod coefficients;
+pub(crate) mod composition;
+pub(crate) mod condensation;
+pub(crate) mod config;
+pub(crate) mod convergence;
+pub(crate) mod darcy;
+pub(crate) mod deflation;
+pub(crate) mod device;
+pub(crate) mod dg;
 pub(crate) mod dm;
+pub(crate) mod dry_run;
+pub(crate) mod element_metrics;
+pub(crate) mod events;
+pub(crate) mod expression;
+pub(crate) mod frozen;
+pub(crate) mod inner_product;
+pub(crate) mod jacobian;
+pub(crate) mod manifest;
+pub(crate) mod manufactured;
+pub(crate) mod multigrid;
+pub(crate) mod nonlinear;
+pub(crate) mod norms;
+pub(crate) mod output;
 pub(crate) mod petsc_ops;
+pub(crate) mod point_blocks;
+pub(crate) mod probing;
+pub(crate) mod progress;
+pub(crate) mod qdata;
+pub(crate) mod qfields;
+pub(crate) mod qfunction_source;
+pub(crate) mod quasistatic;
+pub(crate) mod registry;
+pub(crate) mod row_sums;
+pub(crate) mod scaling;
+pub(crate) mod segregated;
+pub(crate) mod shutdown;
+pub(crate) mod snapshot;
+pub(crate) mod solve;
+pub(crate) mod split;
+pub(crate) mod statistics;
+pub(crate) mod subdomain;
+pub(crate) mod surface;
+pub(crate) mod sweep;
+pub(crate) mod transfer;
+pub(crate) mod transient;
+pub(crate) mod user_qfunction;
+pub(crate) mod verification;
+
+pub use crate::advection::AdvectionDiffusion;
+pub use crate::amr::{AmrMarking, AmrStep};
+pub use crate::assembly::AssembledOperator;
+pub use crate::backend::{BackendAudition, BackendInfo, BackendTiming};
+pub use crate::benchmark::BenchmarkReport;
+pub use crate::ceed_io::{read_ceed_vector, write_ceed_vector};
+pub use crate::checkpoint::SolverState;
+pub use crate::coefficients::CoefficientField;
+pub use crate::composition::OperatorSum;
+pub use crate::condensation::StaticCondensation;
+pub use crate::config::{Config, ConfigSource};
+pub use crate::convergence::{assert_convergence_rate, ConvergenceEntry, ConvergenceStudy};
+pub use crate::darcy::DarcyProblem;
+pub use crate::deflation::DeflationSpace;
+pub use crate::device::DeviceBinding;
+pub use crate::dg::{DgFlux, DgProblem};
+pub use crate::dm::kershaw_transformation;
+pub use crate::dry_run::SetupReport;
+pub use crate::element_metrics::ElementMetrics;
+pub use crate::expression::Expression;
+pub use crate::frozen::FrozenOperator;
+pub use crate::inner_product::MassInnerProduct;
+pub use crate::manifest::RunManifest;
+pub use crate::multigrid::PMultigrid;
+pub use crate::nonlinear::NonlinearSolver;
+pub use crate::norms::SolutionError;
+pub use crate::progress::{SetupPhase, SetupProgress};
+pub use crate::qdata::QData;
+pub use crate::qfields::QuadratureFields;
+pub use crate::qfunction_source::{QFunctionSource, SourceOperator};
+pub use crate::quasistatic::Quasistatic;
+pub use crate::row_sums::RowSums;
+pub use crate::scaling::ScaledOperator;
+pub use crate::segregated::{SegregatedResult, SegregatedSolver};
+pub use crate::shutdown::{install_shutdown_handler, ShutdownReason};
+pub use crate::solve::{InitialGuess, PreconditionerReuse, Recycling, SolveResult, SolveSession};
+pub use crate::split::SplitOperator;
+pub use crate::statistics::StepStatistics;
+pub use crate::subdomain::SubdomainSplitting;
+pub use crate::sweep::{OrderSweep, OrderSweepEntry};
+pub use crate::transfer::FieldTransfer;
+pub use crate::transient::TransientSolver;
+pub use crate::user_qfunction::{GeometricFactors, UserQFunction};
+pub use crate::verification::{
+    CrossValidation, RegressionCheck, RegressionReference, RegressionReport, RunSummary,
+    ToleranceProfile, ToleranceProfiles,
+};
 
 // -----------------------------------------------------------------------------
 // Error handling
@@ -53,26 +169,335 @@ impl From<petsc::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(io_error: std::io::Error) -> Self {
+        Self {
+            message: io_error.to_string(),
+        }
+    }
+}
+
+// Check the error code returned by a raw PETSc function
+pub(crate) fn check_petsc_raw(
+    ierr: petsc::petsc_raw::PetscErrorCode,
+    function: &str,
+) -> Result<()> {
+    if ierr == 0 {
+        Ok(())
+    } else {
+        Err(Error {
+            message: format!("{} failed with error code {}", function, ierr),
+        })
+    }
+}
+
+// Check if PETSc was configured with an external package
+pub(crate) fn has_petsc_package(package: &str) -> Result<bool> {
+    let package = std::ffi::CString::new(package).unwrap();
+    let mut has_package = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+    check_petsc_raw(
+        unsafe { petsc::petsc_raw::PetscHasExternalPackage(package.as_ptr(), &mut has_package) },
+        "PetscHasExternalPackage",
+    )?;
+    Ok(has_package == petsc::petsc_raw::PetscBool::PETSC_TRUE)
+}
+
+// Report a diagnostic through PETSc -info logging, like PetscInfo
+pub(crate) fn petsc_info(message: &str) -> Result<()> {
+    let function = std::ffi::CString::new("meles").unwrap();
+    let format = std::ffi::CString::new("%s\n").unwrap();
+    let message = std::ffi::CString::new(message).map_err(|_| Error {
+        message: "PetscInfo message contains a NUL byte".to_string(),
+    })?;
+    check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::PetscInfo_Private(
+                function.as_ptr(),
+                std::ptr::null_mut(),
+                format.as_ptr(),
+                message.as_ptr(),
+            )
+        },
+        "PetscInfo",
+    )
+}
+
+// Report an error from a PETSc callback through the PETSc error handler,
+//   returning the error code for the callback
+pub(crate) fn petsc_callback_error(
+    function: &str,
+    error: Error,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    let function = std::ffi::CString::new(function).unwrap();
+    let file = std::ffi::CString::new(file!()).unwrap();
+    let format = std::ffi::CString::new("%s").unwrap();
+    let message = std::ffi::CString::new(error.message.replace('\0', " ")).unwrap();
+    unsafe {
+        PetscError(
+            PETSC_COMM_SELF,
+            line!() as std::os::raw::c_int,
+            function.as_ptr(),
+            file.as_ptr(),
+            PETSC_ERR_LIB as PetscErrorCode,
+            PetscErrorType::PETSC_ERROR_INITIAL,
+            format.as_ptr(),
+            message.as_ptr(),
+        )
+    }
+}
+
+// Check the error code returned by a raw libCEED function
+pub(crate) fn check_ceed_raw(ierr: std::os::raw::c_int, function: &str) -> Result<()> {
+    if ierr == 0 {
+        Ok(())
+    } else {
+        Err(Error {
+            message: format!("{} failed with error code {}", function, ierr),
+        })
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Enums
 // -----------------------------------------------------------------------------
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-/// This enum is used to specify if a Benchmark problem or (eventually) Ratel
-/// problem is being solved
+/// This enum is used to specify if a Benchmark problem or Ratel solid
+/// mechanics problem is being solved
 pub enum MethodType {
     BenchmarkProblem,
+    RatelQuasistatic,
+}
+
+// -----------------------------------------------------------------------------
+// Reconfiguration report
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Objects rebuilt by `Meles::reconfigure`
+pub struct Reconfigured {
+    pub ceed: bool,
+    pub mesh: bool,
+    pub discretization: bool,
+}
+
+// -----------------------------------------------------------------------------
+// Meles libCEED operator
+// -----------------------------------------------------------------------------
+/// libCEED operator applied by a Meles MatShell
+pub enum MelesOperator<'a> {
+    Single(libceed::operator::Operator<'a>),
+    Composite(libceed::operator::CompositeOperator<'a>),
+    Source(crate::SourceOperator),
+    Sum(crate::OperatorSum<'a>),
+    Split(crate::SplitOperator<'a>),
+}
+
+impl<'a> From<libceed::operator::Operator<'a>> for MelesOperator<'a> {
+    fn from(op: libceed::operator::Operator<'a>) -> Self {
+        Self::Single(op)
+    }
+}
+
+impl<'a> From<libceed::operator::CompositeOperator<'a>> for MelesOperator<'a> {
+    fn from(op: libceed::operator::CompositeOperator<'a>) -> Self {
+        Self::Composite(op)
+    }
+}
+
+impl<'a> MelesOperator<'a> {
+    /// Apply the operator, `y = A x`
+    pub fn apply(
+        &self,
+        x: &libceed::vector::Vector,
+        y: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        match self {
+            Self::Single(op) => op.apply(x, y),
+            Self::Composite(op) => op.apply(x, y),
+            Self::Source(op) => op.apply(x, y),
+            Self::Sum(op) => op.apply(x, y),
+            Self::Split(op) => op.apply(x, y),
+        }
+    }
+
+    /// Assemble the diagonal of the operator
+    pub fn linear_assemble_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        match self {
+            Self::Single(op) => op.linear_assemble_diagonal(assembled),
+            Self::Composite(op) => op.linear_assemble_diagonal(assembled),
+            Self::Source(op) => op.linear_assemble_diagonal(assembled),
+            Self::Sum(op) => op.linear_assemble_diagonal(assembled),
+            Self::Split(op) => op.linear_assemble_diagonal(assembled),
+        }
+    }
+
+    /// Assemble the point block diagonal of the operator
+    pub fn linear_assemble_point_block_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        match self {
+            Self::Single(op) => op.linear_assemble_point_block_diagonal(assembled),
+            Self::Composite(op) => op.linear_assemble_point_block_diagonal(assembled),
+            Self::Source(op) => op.linear_assemble_point_block_diagonal(assembled),
+            Self::Sum(op) => op.linear_assemble_point_block_diagonal(assembled),
+            Self::Split(op) => op.linear_assemble_point_block_diagonal(assembled),
+        }
+    }
+}
+
+impl<'a> From<crate::SplitOperator<'a>> for MelesOperator<'a> {
+    fn from(op: crate::SplitOperator<'a>) -> Self {
+        Self::Split(op)
+    }
 }
 
 // -----------------------------------------------------------------------------
 // Meles MatShell context
 // -----------------------------------------------------------------------------
 pub struct MelesMatShellContext<'a> {
+    pub(crate) ceed: libceed::Ceed,
     pub(crate) dm: RefCell<DM<'a, 'a>>,
     pub(crate) x_loc: RefCell<petsc::vector::Vector<'a>>,
     pub(crate) y_loc: RefCell<petsc::vector::Vector<'a>>,
     pub(crate) x_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
     pub(crate) y_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
-    pub(crate) op_ceed: RefCell<libceed::operator::Operator<'a>>,
+    pub(crate) op_ceed: RefCell<crate::MelesOperator<'a>>,
+    pub(crate) diagonal: RefCell<Option<petsc::vector::Vector<'a>>>,
+    pub(crate) num_components: usize,
+    pub(crate) point_block_inverse: RefCell<Option<Vec<petsc::Scalar>>>,
+    pub(crate) rhs: petsc::vector::Vector<'a>,
+    pub(crate) qdata: Vec<crate::qdata::QDataVector<'a>>,
+    pub(crate) true_solution: Vec<crate::qdata::QDataVector<'a>>,
+    pub(crate) error_ops: Vec<crate::norms::ErrorOperator<'a>>,
+    // Round operator inputs and outputs to single precision, see
+    //   petsc_ops::round_to_single
+    pub(crate) single_precision: bool,
+}
+
+impl<'a> MelesMatShellContext<'a> {
+    /// Replace the libCEED operator applied by the MatShell
+    ///
+    /// Any cached diagonal is invalidated. The new operator must act on the
+    /// same local vectors as the operator it replaces. Call
+    /// `KSP::set_operators` again after swapping so the preconditioner is
+    /// rebuilt.
+    ///
+    /// # arguments
+    ///
+    /// * `op_ceed` - libCEED operator or composite operator to apply
+    pub fn set_operator(&self, op_ceed: impl Into<crate::MelesOperator<'a>>) {
+        *self.op_ceed.borrow_mut() = op_ceed.into();
+        self.invalidate_diagonal();
+    }
+
+    /// Set the weights of an operator that is a weighted sum of operators
+    ///
+    /// Any cached diagonal is invalidated, as for `set_operator`.
+    ///
+    /// # arguments
+    ///
+    /// * `weights` - Weights of the terms of the sum, in order
+    pub fn set_operator_weights(&self, weights: &[f64]) -> Result<()> {
+        match &*self.op_ceed.borrow() {
+            crate::MelesOperator::Sum(sum) => sum.set_weights(weights)?,
+            _ => {
+                return Err(crate::Error {
+                    message: "operator is not a weighted sum of operators".to_string(),
+                })
+            }
+        }
+        self.invalidate_diagonal();
+        Ok(())
+    }
+
+    /// Returns the DM of the operator, with the essential boundary
+    /// conditions of the problem
+    pub fn dm(&self) -> std::cell::Ref<'_, DM<'a, 'a>> {
+        self.dm.borrow()
+    }
+
+    /// Returns a transfer between global vectors and local libCEED vectors on
+    /// the DM of the operator, for custom libCEED operators
+    pub fn field_transfer(&self) -> Result<crate::FieldTransfer<'a>> {
+        crate::FieldTransfer::new(&self.ceed, &self.dm.borrow())
+    }
+
+    /// Discard the cached diagonal and point block diagonal inverse so they
+    /// are recomputed on next use
+    pub fn invalidate_diagonal(&self) {
+        self.diagonal.borrow_mut().take();
+        self.point_block_inverse.borrow_mut().take();
+    }
+
+    /// Returns the global RHS for the forcing term of the benchmark problem
+    ///
+    /// The RHS is consistent with the operator, including the DoFs removed
+    /// by essential boundary conditions.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve with the benchmark RHS
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let rhs = mat.mat_data().unwrap().rhs();
+    /// let mut x = rhs.duplicate()?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &mat)?;
+    /// ksp.solve(rhs, &mut x)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rhs(&self) -> &petsc::vector::Vector<'a> {
+        &self.rhs
+    }
+
+    /// Returns host copies of the exact solution at quadrature points, one
+    /// per sub-operator
+    pub fn true_solution(&self) -> Result<Vec<crate::QData>> {
+        self.true_solution
+            .iter()
+            .map(|true_solution| true_solution.to_host())
+            .collect()
+    }
+
+    /// Returns host copies of the quadrature data produced by the setup
+    /// operator, one per sub-operator, or none with on-the-fly geometric
+    /// factors
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // inspect geometric factors, w det(J) for BP1
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let qdata = mat.mat_data().unwrap().qdata()?;
+    /// let volume: f64 = qdata[0].values.iter().sum();
+    /// assert!((volume - 1.0).abs() < 1e-10);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn qdata(&self) -> Result<Vec<crate::QData>> {
+        self.qdata.iter().map(|qdata| qdata.to_host()).collect()
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -80,8 +505,54 @@ pub struct MelesMatShellContext<'a> {
 // -----------------------------------------------------------------------------
 pub struct Meles<'a> {
     pub(crate) ceed: libceed::Ceed,
+    pub(crate) ceed_resource: String,
+    pub(crate) host_ceed: RefCell<Option<libceed::Ceed>>,
+    pub(crate) backend_info: RefCell<Option<crate::BackendInfo>>,
+    pub(crate) device: Option<crate::DeviceBinding>,
+    pub(crate) split: Option<crate::split::CeedSplit>,
+    pub(crate) config_source: crate::ConfigSource,
     pub(crate) method: crate::MethodType,
+    pub(crate) comm: &'a mpi::topology::UserCommunicator,
+    pub(crate) bp_options: crate::ceed_bps::Opt,
+    pub(crate) quasistatic_options: crate::quasistatic::Opt,
+    pub(crate) mesh_setup_time: std::time::Duration,
+    pub(crate) progress: crate::progress::ProgressReporter<'a>,
     pub dm: RefCell<DM<'a, 'a>>,
+    pub(crate) amr_history: RefCell<Vec<Vec<usize>>>,
+    pub(crate) operators: RefCell<crate::registry::OperatorRegistry>,
+    pub(crate) operator_cache: RefCell<crate::registry::OperatorCache<'a>>,
+    pub(crate) budget: crate::shutdown::Budget,
+    pub(crate) options: crate::config::ScopedOptions<'a>,
+}
+
+// State built by `Meles::reconfigure`, committed to the context only after
+//   every step succeeds
+struct Reconfiguration<'a> {
+    config_source: crate::ConfigSource,
+    ceed_resource: String,
+    ceed: Option<(libceed::Ceed, Option<crate::DeviceBinding>)>,
+    split: Option<crate::split::CeedSplit>,
+    log: bool,
+    bp_options: crate::ceed_bps::Opt,
+    quasistatic_options: crate::quasistatic::Opt,
+    dm: Option<DM<'a, 'a>>,
+    mesh_setup_time: Option<std::time::Duration>,
+    rebuilt: crate::Reconfigured,
+}
+
+// -----------------------------------------------------------------------------
+// Ceed command line options
+// -----------------------------------------------------------------------------
+struct CeedOpt {
+    ceed_resource: String,
+}
+
+impl petsc::Opt for CeedOpt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let ceed_resource =
+            pob.options_string("-ceed", "libceed::Ceed resource specifier", "", "/cpu/self")?;
+        Ok(CeedOpt { ceed_resource })
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -89,17 +560,17 @@ pub struct Meles<'a> {
 // -----------------------------------------------------------------------------
 impl<'a> Drop for Meles<'a> {
     fn drop(&mut self) {
-        // TODO: Eventually this will deallocate Ratel
+        // libCEED and PETSc objects are released by their own destructors
     }
 }
 
 impl<'a> Meles<'a> {
-    /// Returns a Meles context initialized with the specified yml filepath
+    /// Returns a Meles context initialized with the specified yml configuration
     ///
     /// # arguments
     ///
     /// * `petsc` - PETSc context to use
-    /// * `yml` - Filepath to specification yml
+    /// * `yml` - Filepath to specification yml or `Config` with search paths
     /// * `method` - Type of meles problem to setup
     ///
     /// ```
@@ -115,52 +586,558 @@ impl<'a> Meles<'a> {
     ///
     /// // mesh DM can be borrowed immutably
     /// let vec = meles.dm.borrow().create_global_vector()?;
+    ///
+    /// // search directories for the yml
+    /// let config = meles::Config::new()
+    ///     .search_path("./examples")
+    ///     .file("meles.yml");
+    /// let meles = meles::Meles::new(&petsc, config, meles::MethodType::BenchmarkProblem)?;
     /// # Ok(())
     /// # }
     /// ```
     pub fn new(
         petsc: &'a Petsc,
-        yml: impl Into<String> + Clone,
+        yml: impl Into<crate::Config>,
+        method: crate::MethodType,
+    ) -> Result<Self> {
+        Self::new_on_comm(petsc, petsc.world(), yml, method)
+    }
+
+    /// Returns a Meles context on the given communicator initialized with the
+    /// specified yml configuration
+    ///
+    /// Independent contexts may be created on disjoint communicators, such as
+    /// for ensemble runs. Contexts created from threads other than the main
+    /// thread require MPI to be initialized with `MPI_THREAD_MULTIPLE`.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `comm` - MPI communicator for the mesh and solvers
+    /// * `yml` - Filepath to specification yml or `Config` with search paths
+    /// * `method` - Type of meles problem to setup
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new_on_comm(
+    ///     &petsc,
+    ///     petsc.world(),
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // contexts on split communicators, each created from a spawned thread
+    /// use mpi::topology::{Color, Communicator};
+    /// let comms = (0..2)
+    ///     .map(|_| petsc.world().split_by_color(Color::with_value(0)).unwrap())
+    ///     .collect::<Vec<_>>();
+    /// let thread_multiple = mpi::environment::threading_support() == mpi::Threading::Multiple;
+    /// std::thread::scope(|scope| {
+    ///     for comm in &comms {
+    ///         let petsc = &petsc;
+    ///         scope.spawn(move || {
+    ///             let meles = meles::Meles::new_on_comm(
+    ///                 petsc,
+    ///                 comm,
+    ///                 "./examples/meles.yml",
+    ///                 meles::MethodType::BenchmarkProblem,
+    ///             );
+    ///             assert_eq!(meles.is_ok(), thread_multiple, "incorrect thread check");
+    ///         });
+    ///     }
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_on_comm(
+        petsc: &'a Petsc,
+        comm: &'a mpi::topology::UserCommunicator,
+        yml: impl Into<crate::Config>,
         method: crate::MethodType,
     ) -> Result<Self> {
+        // Check MPI thread support
+        if !mpi::environment::is_main_thread()
+            && mpi::environment::threading_support() != mpi::Threading::Multiple
+        {
+            return Err(Error {
+                message: "Meles contexts created off the main thread require MPI_THREAD_MULTIPLE"
+                    .to_string(),
+            });
+        }
+
+        // The options database is global, so hold the lock while reading it;
+        //   the inserted options are declared first so they are dropped after
+        //   the lock is released on early returns
+        let options;
+        let _options_lock = crate::config::options_lock();
+        let _stage = crate::events::Stage::Setup.push();
+
         // Insert yaml into options database
-        let yml = yml.into().clone();
-        petsc.options_insert_file(&yml)?;
+        let (config_source, inserted) = yml.into().insert(petsc)?;
+        options = inserted;
+        let budget = crate::shutdown::Budget::new(petsc.options()?);
 
         // Create Ceed
-        struct Opt {
-            ceed_resource: String,
-        }
-        impl petsc::Opt for Opt {
-            fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
-                let ceed_resource = pob.options_string(
-                    "-ceed",
-                    "libceed::Ceed resource specifier",
-                    "",
-                    "/cpu/self",
-                )?;
-                Ok(Opt { ceed_resource })
-            }
-        }
-        let Opt { ceed_resource } = petsc.options()?;
-        let ceed = libceed::Ceed::init(&ceed_resource);
+        let CeedOpt { ceed_resource } = petsc.options()?;
+        let (ceed, device) = crate::device::init_ceed(comm, &ceed_resource)?;
+        let split = crate::split::CeedSplit::new(comm, petsc.options()?)?;
 
         // Create DM
-        let dm = match method {
-            crate::MethodType::BenchmarkProblem => crate::ceed_bps::create_dm(&petsc)?,
-            // TODO: Ratel methods
+        let progress = crate::progress::ProgressReporter::new(comm, petsc.options()?);
+        let mesh_setup_start = std::time::Instant::now();
+        let mut bp_options = crate::ceed_bps::Opt {
+            boundary_conditions: options.boundary_conditions.clone(),
+            ..petsc.options()?
         };
+        let quasistatic_options: crate::quasistatic::Opt = petsc.options()?;
+        let mut dm = match method {
+            crate::MethodType::BenchmarkProblem => crate::ceed_bps::create_dm(comm, &bp_options)?,
+            crate::MethodType::RatelQuasistatic => {
+                crate::quasistatic::create_dm(comm, &bp_options, &quasistatic_options)?
+            }
+        };
+        crate::backend::set_dm_types(comm, &mut dm, &ceed_resource)?;
+        if method == crate::MethodType::BenchmarkProblem {
+            crate::qdata::fit_budget(comm, &dm, &mut bp_options, petsc.options()?)?;
+        }
+        let mesh_setup_time = mesh_setup_start.elapsed();
+        progress.report(
+            crate::progress::SetupPhase::Mesh,
+            crate::dm::local_num_cells(&dm, bp_options.mesh_type)?,
+            mesh_setup_start,
+        );
 
         // Return self
         Ok(Self {
             ceed: ceed,
-            method: crate::MethodType::BenchmarkProblem,
+            ceed_resource: ceed_resource,
+            host_ceed: RefCell::new(None),
+            backend_info: RefCell::new(None),
+            device,
+            split: split,
+            config_source: config_source,
+            method,
+            comm: comm,
+            bp_options: bp_options,
+            quasistatic_options,
+            mesh_setup_time: mesh_setup_time,
+            progress,
             dm: RefCell::new(dm),
+            amr_history: RefCell::new(vec![]),
+            operators: RefCell::new(crate::registry::OperatorRegistry::default()),
+            operator_cache: RefCell::new(crate::registry::OperatorCache::default()),
+            budget: budget,
+            options: options,
         })
     }
 
+    // Context for another discretization of the same problem, such as on a
+    //   refined mesh or another libCEED resource, sharing the configuration,
+    //   communicator, budget, and registered operators
+    pub(crate) fn derived(
+        &self,
+        petsc: &'a Petsc,
+        (ceed, ceed_resource, device): (libceed::Ceed, String, Option<crate::DeviceBinding>),
+        bp_options: crate::ceed_bps::Opt,
+        dm: DM<'a, 'a>,
+        progress: crate::progress::ProgressReporter<'a>,
+        mesh_setup_time: std::time::Duration,
+    ) -> Self {
+        // Backend information is cached per resource
+        let backend_info = if ceed_resource == self.ceed_resource {
+            self.backend_info.borrow().clone()
+        } else {
+            None
+        };
+        Self {
+            ceed,
+            ceed_resource,
+            host_ceed: self.host_ceed.clone(),
+            backend_info: RefCell::new(backend_info),
+            device,
+            split: self.split.clone(),
+            config_source: self.config_source.clone(),
+            method: self.method,
+            comm: self.comm,
+            bp_options,
+            quasistatic_options: self.quasistatic_options.clone(),
+            mesh_setup_time,
+            progress,
+            dm: RefCell::new(dm),
+            amr_history: RefCell::new(vec![]),
+            operators: RefCell::new(self.operators.borrow().clone()),
+            operator_cache: RefCell::new(crate::registry::OperatorCache::default()),
+            budget: self.budget.clone(),
+            options: crate::config::ScopedOptions::empty(petsc),
+        }
+    }
+
+    /// Apply a new yml configuration, rebuilding only the affected objects
+    ///
+    /// The Ceed is rebuilt if the resource changed, the mesh is rebuilt if any
+    /// `dm_`, `stag_`, `da_`, or mesh transform option or the mesh type
+    /// changed, and the discretization is rebuilt on the existing mesh if the
+    /// problem or order changed. MatShells created before reconfiguration must
+    /// be recreated with `Meles::mat_shell`. If any step fails, the context
+    /// and its options are unchanged.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `yml` - Filepath to specification yml or `Config` with search paths
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let mut meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // same configuration rebuilds nothing
+    /// let rebuilt = meles.reconfigure(&petsc, "./examples/meles.yml")?;
+    /// assert_eq!(rebuilt, meles::Reconfigured::default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reconfigure(
+        &mut self,
+        petsc: &'a Petsc,
+        yml: impl Into<crate::Config>,
+    ) -> Result<crate::Reconfigured> {
+        let config = yml.into();
+        config.resolve()?;
+
+        // Clear previous options before taking the options lock
+        let old_options = std::mem::replace(
+            &mut self.options,
+            crate::config::ScopedOptions::empty(petsc),
+        );
+        let mesh_values = |options: &crate::config::ScopedOptions| {
+            options
+                .values
+                .iter()
+                .filter(|(name, _)| {
+                    [
+                        "-dm_",
+                        "-stag_",
+                        "-da_",
+                        "-refine",
+                        "-local_dofs",
+                        "-periodic",
+                        "-mesh_transform",
+                        "-kershaw_",
+                    ]
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let old_mesh_values = mesh_values(&old_options);
+        let old_values = old_options.values.clone();
+        let old_boundary_conditions = old_options.boundary_conditions.clone();
+        drop(old_options);
+
+        // Build the new state into locals, so a failure leaves the context
+        //   unchanged; the new options are declared before the lock so they
+        //   are dropped after it is released
+        let mut inserted = None;
+        let options_lock = crate::config::options_lock();
+        let result = (|| -> Result<Reconfiguration<'a>> {
+            let (config_source, options) = config.insert(petsc)?;
+            inserted = Some(options);
+            let options = inserted.as_ref().unwrap();
+            let mut rebuilt = crate::Reconfigured::default();
+
+            // Rebuild Ceed
+            let CeedOpt { ceed_resource } = petsc.options()?;
+            let ceed = if ceed_resource != self.ceed_resource {
+                rebuilt.ceed = true;
+                Some(crate::device::init_ceed(self.comm, &ceed_resource)?)
+            } else {
+                None
+            };
+            let split = crate::split::CeedSplit::new(self.comm, petsc.options()?)?;
+
+            // Rebuild mesh or discretization
+            let progress_options: crate::progress::Opt = petsc.options()?;
+            let mut bp_options = crate::ceed_bps::Opt {
+                boundary_conditions: options.boundary_conditions.clone(),
+                boundary_functions: self.bp_options.boundary_functions.clone(),
+                ..petsc.options()?
+            };
+            let quasistatic_options: crate::quasistatic::Opt = petsc.options()?;
+            // DMStag DoFs are fixed at creation, so any discretization change
+            // rebuilds the mesh
+            let rebuild_mesh = mesh_values(options) != old_mesh_values
+                || bp_options.mesh_type != self.bp_options.mesh_type
+                || (bp_options.local_dofs.0 > 0.0
+                    && (bp_options.order != self.bp_options.order
+                        || bp_options.problem != self.bp_options.problem))
+                || (bp_options.mesh_type == crate::dm::MeshType::Stag
+                    && bp_options != self.bp_options);
+            let (method, comm) = (self.method, self.comm);
+            let fit_qdata_budget = |dm: &DM, bp_options: &mut crate::ceed_bps::Opt| match method {
+                crate::MethodType::BenchmarkProblem => {
+                    crate::qdata::fit_budget(comm, dm, bp_options, petsc.options()?)
+                }
+                crate::MethodType::RatelQuasistatic => Ok(()),
+            };
+            let mut dm = None;
+            let mut mesh_setup_time = None;
+            if rebuild_mesh {
+                let mesh_setup_start = std::time::Instant::now();
+                let mut mesh_dm = match self.method {
+                    crate::MethodType::BenchmarkProblem => {
+                        crate::ceed_bps::create_dm(self.comm, &bp_options)?
+                    }
+                    crate::MethodType::RatelQuasistatic => {
+                        crate::quasistatic::create_dm(self.comm, &bp_options, &quasistatic_options)?
+                    }
+                };
+                crate::backend::set_dm_types(self.comm, &mut mesh_dm, &ceed_resource)?;
+                self.progress.report(
+                    crate::progress::SetupPhase::Mesh,
+                    crate::dm::local_num_cells(&mesh_dm, bp_options.mesh_type)?,
+                    mesh_setup_start,
+                );
+                fit_qdata_budget(&mesh_dm, &mut bp_options)?;
+                dm = Some(mesh_dm);
+                mesh_setup_time = Some(mesh_setup_start.elapsed());
+                rebuilt.mesh = true;
+                rebuilt.discretization = true;
+            } else {
+                fit_qdata_budget(&self.dm.borrow(), &mut bp_options)?;
+                if bp_options != self.bp_options || quasistatic_options != self.quasistatic_options
+                {
+                    let section_setup_start = std::time::Instant::now();
+                    let mut section_dm = self.dm.borrow().clone();
+                    match self.method {
+                        crate::MethodType::BenchmarkProblem => {
+                            crate::ceed_bps::setup_dm(&mut section_dm, &bp_options)?
+                        }
+                        crate::MethodType::RatelQuasistatic => crate::quasistatic::setup_dm(
+                            &mut section_dm,
+                            &bp_options,
+                            &quasistatic_options,
+                        )?,
+                    };
+                    self.progress.report(
+                        crate::progress::SetupPhase::Section,
+                        None,
+                        section_setup_start,
+                    );
+                    dm = Some(section_dm);
+                    rebuilt.discretization = true;
+                }
+                // The DM types follow the Ceed resource
+                if rebuilt.ceed {
+                    let mut typed_dm = dm.take().unwrap_or_else(|| self.dm.borrow().clone());
+                    crate::backend::set_dm_types(self.comm, &mut typed_dm, &ceed_resource)?;
+                    dm = Some(typed_dm);
+                }
+            }
+
+            Ok(Reconfiguration {
+                config_source,
+                ceed_resource,
+                ceed,
+                split,
+                log: progress_options.log,
+                bp_options,
+                quasistatic_options,
+                dm,
+                mesh_setup_time,
+                rebuilt,
+            })
+        })();
+
+        // Release the lock before replacing the options, as dropping the
+        //   replaced options takes the lock
+        drop(options_lock);
+        let reconfiguration = match result {
+            Ok(reconfiguration) => reconfiguration,
+            Err(error) => {
+                // Clear the new options and restore the previous ones
+                drop(inserted);
+                let _options_lock = crate::config::options_lock();
+                self.options = crate::config::ScopedOptions::set_values(
+                    petsc,
+                    old_values,
+                    old_boundary_conditions,
+                )?;
+                return Err(error);
+            }
+        };
+
+        // Commit
+        let Reconfiguration {
+            config_source,
+            ceed_resource,
+            ceed,
+            split,
+            log,
+            bp_options,
+            quasistatic_options,
+            dm,
+            mesh_setup_time,
+            rebuilt,
+        } = reconfiguration;
+        if let Some((ceed, device)) = ceed {
+            self.ceed = ceed;
+            self.device = device;
+            self.ceed_resource = ceed_resource;
+            self.backend_info.get_mut().take();
+        }
+        self.split = split;
+        self.progress.log = log;
+        if let Some(dm) = dm {
+            *self.dm.borrow_mut() = dm;
+        }
+        self.operator_cache.borrow_mut().clear();
+        if let Some(mesh_setup_time) = mesh_setup_time {
+            self.amr_history.borrow_mut().clear();
+            self.mesh_setup_time = mesh_setup_time;
+        }
+        self.bp_options = bp_options;
+        self.quasistatic_options = quasistatic_options;
+        self.config_source = config_source;
+        if let Some(options) = inserted {
+            self.options = options;
+        }
+        Ok(rebuilt)
+    }
+
+    /// Set the essential boundary values on a boundary from a function of
+    ///   the coordinates and time
+    ///
+    /// The boundary is a label name, such as a Gmsh physical name, or
+    /// `label:value` for one value, such as `Face Sets:1` for one face of a box
+    /// mesh, and becomes an essential boundary in addition to
+    /// `essential_boundaries`. The function replaces the manufactured
+    /// solution or `u_bc` on its boundary, with later functions taking
+    /// precedence on shared points. Transient solves evaluate the function at
+    /// the time of each stage. Problems without essential boundary
+    /// conditions, such as mass problems or with `-neumann`, return an error.
+    /// The discretization is rebuilt, so MatShells created before must be
+    /// recreated with `Meles::mat_shell`; if the rebuild fails, the problem
+    /// is unchanged.
+    ///
+    /// # arguments
+    ///
+    /// * `boundary` - Label, or `label:value`, of the boundary
+    /// * `function` - Function of the coordinates and time setting the value
+    ///                  of each component
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let mut meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/kershaw.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // heated face
+    /// meles.set_boundary_function("Face Sets:1", |x, _t, u| u.fill(1.0 + x[0] * x[1]))?;
+    /// let result = meles.solve(&petsc)?;
+    /// println!("{}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_boundary_function(
+        &mut self,
+        boundary: impl Into<String>,
+        function: impl Fn(&[f64], f64, &mut [f64]) + 'static,
+    ) -> Result<()> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        // Rebuild the discretization with the new boundary, keeping the
+        //   function only if the rebuild succeeds
+        let boundary = boundary.into();
+        let mut options = self.bp_options.clone();
+        options
+            .boundary_functions
+            .0
+            .push((boundary.clone(), std::rc::Rc::new(function)));
+        if !crate::ceed_bps::has_essential_boundary(&options)? {
+            return Err(crate::Error {
+                message: format!(
+                    "boundary function on {} requires essential boundary conditions, which {} does not impose with these options",
+                    boundary, options.problem
+                ),
+            });
+        }
+        let section_setup_start = std::time::Instant::now();
+        let mut dm = self.dm.borrow().clone();
+        crate::ceed_bps::setup_dm(&mut dm, &options)?;
+        self.progress.report(
+            crate::progress::SetupPhase::Section,
+            None,
+            section_setup_start,
+        );
+        *self.dm.borrow_mut() = dm;
+        self.operator_cache.borrow_mut().clear();
+        self.bp_options = options;
+        Ok(())
+    }
+
+    /// Set a callback invoked as each setup phase completes, with the number
+    ///   of local elements processed
+    ///
+    /// The callback is invoked on every rank, so long setups on large meshes
+    /// can be distinguished from hangs. Use `-meles_progress` to log each
+    /// phase on rank 0 instead, which also covers mesh creation in
+    /// `Meles::new`.
+    ///
+    /// # arguments
+    ///
+    /// * `callback` - Function to call with each completed phase
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // record completed phases
+    /// let phases = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+    /// let recorded = phases.clone();
+    /// meles.set_progress_callback(move |progress| recorded.borrow_mut().push(progress.phase));
+    /// let _mat = meles.mat_shell(&petsc)?;
+    /// assert!(phases.borrow().contains(&meles::SetupPhase::QData));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_progress_callback(&self, callback: impl Fn(&crate::SetupProgress) + 'a) {
+        self.progress.set_callback(Some(Box::new(callback)));
+    }
+
     /// Return a PETSc MatShell for the DM that uses a libCEED operator
     ///
+    /// The benchmark problem operators are symmetric, so the MatShell also
+    /// applies its transpose, for adjoint solves and KSP types such as BiCG.
+    /// `MatMultAdd` and `MatMultTransposeAdd`, used by PCMG residuals and
+    /// MatNest blocks, sum the action into the output without a work vector.
+    ///
     /// Note: Can only directly create a MatShell for `BenchmarkProblem`s
     ///
     /// ```
@@ -178,12 +1155,19 @@ impl<'a> Meles<'a> {
     /// let mat = meles.mat_shell(&petsc)?;
     /// let mut ksp = petsc.ksp_create()?;
     /// ksp.set_operators(&mat, &mat)?;
+    ///
+    /// // BiCG applies the transpose
+    /// ksp.set_type(KSPType::KSPBICG)?;
+    /// let rhs = mat.mat_data().unwrap().rhs();
+    /// let mut solution = rhs.duplicate()?;
+    /// ksp.solve(rhs, &mut solution)?;
+    /// assert!(ksp.get_iteration_number()? > 0, "BiCG not run");
     /// # Ok(())
     /// # }
     /// ```
     pub fn mat_shell(
         &'a self,
-        petsc: &'a Petsc,
+        _petsc: &'a Petsc,
     ) -> Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
         // Check setup
         assert!(
@@ -192,29 +1176,2542 @@ impl<'a> Meles<'a> {
         );
 
         // Create MatShellContext
-        let context = crate::ceed_bps::mat_shell_context(&self, &petsc)?;
-
-        // Create MatShell from DM
-        let mut mat = self
-            .dm
-            .borrow()
-            .create_matrix()?
-            .into_shell(Box::new(context))?;
-
-        // Set operations
-        mat.shell_set_operation_mvv(MatOperation::MATOP_MULT, |m, x, y| {
-            let context = m.mat_data().unwrap();
-            crate::petsc_ops::apply_local_ceed_op(x, y, context)?;
-            Ok(())
-        })?;
-        mat.shell_set_operation_mv(MatOperation::MATOP_GET_DIAGONAL, |m, d| {
-            let context = m.mat_data().unwrap();
-            crate::petsc_ops::compute_diagonal_ceed(d, context)?;
-            Ok(())
-        })?;
+        let context = crate::ceed_bps::mat_shell_context(self, &self.bp_options)?;
 
+        // Create MatShell
+        let mat = crate::petsc_ops::create_symmetric_mat_shell(context)?;
+        if let Some(num_components) =
+            crate::ceed_bps::constant_nullspace_components(&self.bp_options)?
+        {
+            crate::petsc_ops::set_constant_nullspace(
+                mat.as_raw(),
+                &mat.mat_data().unwrap().dm.borrow(),
+                num_components,
+            )?;
+        }
         Ok(mat)
     }
+
+    /// Return a PETSc MatShell for the DM that applies a user QFunction in
+    ///   place of the benchmark problem QFunction
+    ///
+    /// The DM, basis, and restrictions match `Meles::mat_shell`, so custom
+    /// physics can be prototyped in Rust on the benchmark problem
+    /// infrastructure. The RHS is the benchmark problem RHS.
+    ///
+    /// On `/gpu` libCEED resources the QFunction is JiT compiled from the C
+    /// source given with `UserQFunction::source`, on a 3D hexahedral plex
+    /// mesh. These MatShells have a zero RHS, as the forcing QFunctions are
+    /// Rust closures.
+    ///
+    /// # arguments
+    ///
+    /// * `q_function` - User QFunction to apply
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // mass matrix scaled by two
+    /// let apply = |[u, q_data, ..]: libceed::qfunction::QFunctionInputs,
+    ///              [v, ..]: libceed::qfunction::QFunctionOutputs| {
+    ///     for i in 0..q_data.len() {
+    ///         v[i] = 2.0 * q_data[i] * u[i];
+    ///     }
+    ///     0
+    /// };
+    /// let q_function = meles::UserQFunction::new(meles::GeometricFactors::Volume, apply)
+    ///     .input(libceed::EvalMode::Interp)
+    ///     .output(libceed::EvalMode::Interp);
+    /// let mat = meles.mat_shell_with_q_function(&petsc, q_function)?;
+    /// let mut u = mat.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// mat.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 2.0).abs() < 1e-10, "incorrect scaled mass");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mat_shell_with_q_function(
+        &'a self,
+        _petsc: &'a Petsc,
+        q_function: crate::UserQFunction,
+    ) -> Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        // Closures on the host, C source on devices
+        let context = if crate::backend::capabilities(&self.ceed_resource).closure_q_functions {
+            crate::ceed_bps::mat_shell_context_with_q_function(
+                self,
+                &self.bp_options,
+                self.bp_options.problem,
+                Some(q_function),
+                true,
+                None,
+            )?
+        } else {
+            crate::qfunction_source::mat_shell_context(self, &self.bp_options, q_function)?
+        };
+        crate::petsc_ops::create_mat_shell(context)
+    }
+
+    /// Return a PETSc MatShell for a named operator on the DM
+    ///
+    /// The `problem`, `mass`, and `stiffness` operators are always available,
+    /// and more are added with `Meles::register_operator` and
+    /// `Meles::register_boundary_operator`, so drivers can use several
+    /// operators on the same discretization. The MatShell is built on the
+    /// current mesh and discretization, and later calls return the same
+    /// MatShell while it is held, until the operator is registered again or
+    /// the mesh or discretization changes.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `name` - Name of the operator
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // volume of the unit cube
+    /// let mass = meles.operator(&petsc, "mass")?;
+    /// let mut u = mass.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// mass.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 1.0).abs() < 1e-10, "incorrect volume");
+    /// let mass_again = meles.operator(&petsc, "mass")?;
+    /// assert!(std::rc::Rc::ptr_eq(&mass, &mass_again), "operator not shared");
+    ///
+    /// // named operators from expressions and boundary faces
+    /// meles.register_operator("reaction_diffusion", "mass + 0.1*stiffness")?;
+    /// meles.register_boundary_operator("boundary_mass", "Face Sets", &[1, 2, 3, 4, 5, 6])?;
+    /// let boundary_mass = meles.operator(&petsc, "boundary_mass")?;
+    /// let mut u = boundary_mass.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// boundary_mass.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 6.0).abs() < 1e-10, "incorrect area");
+    /// assert!(meles.operator_names().contains(&"reaction_diffusion".to_string()));
+    /// assert!(meles.operator(&petsc, "not_an_operator").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn operator(
+        &'a self,
+        _petsc: &'a Petsc,
+        name: &str,
+    ) -> Result<std::rc::Rc<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::registry::operator(self, name)
+    }
+
+    /// Register a named operator from an operator expression in the mass and
+    ///   stiffness operators of the problem, such as `mass + 0.1*stiffness`
+    ///
+    /// An operator of the same name is replaced. See the `operator` option
+    /// for the expressions supported.
+    ///
+    /// # arguments
+    ///
+    /// * `name` - Name of the operator
+    /// * `expression` - Operator expression
+    pub fn register_operator(&self, name: &str, expression: &str) -> Result<()> {
+        crate::composition::OperatorExpression::parse(expression)?;
+        self.operator_cache.borrow_mut().invalidate(name);
+        self.operators.borrow_mut().register(
+            name,
+            crate::registry::OperatorDefinition::Expression(expression.to_string()),
+        );
+        Ok(())
+    }
+
+    /// Register a named mass operator on labeled boundary faces
+    ///
+    /// An operator of the same name is replaced. Faces are selected as for
+    /// `Meles::boundary_mass`.
+    ///
+    /// # arguments
+    ///
+    /// * `name` - Name of the operator
+    /// * `label` - Name of the face label
+    /// * `values` - Label values of the faces to include
+    pub fn register_boundary_operator(
+        &self,
+        name: &str,
+        label: &str,
+        values: &[usize],
+    ) -> Result<()> {
+        self.operator_cache.borrow_mut().invalidate(name);
+        self.operators.borrow_mut().register(
+            name,
+            crate::registry::OperatorDefinition::BoundaryMass {
+                label: label.to_string(),
+                values: values.to_vec(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns the names of the operators available with `Meles::operator`
+    pub fn operator_names(&self) -> Vec<String> {
+        self.operators.borrow().names()
+    }
+
+    /// Return a PETSc MatShell for the weighted sum `a M + b K` of the mass
+    ///   and stiffness operators of the problem
+    ///
+    /// The weights scale the actions of the operators rather than their
+    /// quadrature data, so `MelesMatShellContext::set_operator_weights`
+    /// changes them without setting up the operators again, such as for
+    /// shifted systems and implicit time steps.
+    ///
+    /// The forcing of the benchmark problem does not match the weighted sum,
+    /// so these MatShells have a zero RHS and no exact solution; build the
+    /// RHS for the system being solved.
+    ///
+    /// # arguments
+    ///
+    /// * `mass_weight` - Weight `a` of the mass operator
+    /// * `stiffness_weight` - Weight `b` of the stiffness operator
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // constants are in the nullspace of the stiffness operator
+    /// let mat = meles.weighted_operator(&petsc, 1.0, 0.1)?;
+    /// let mut u = mat.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// mat.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 1.0).abs() < 1e-8, "incorrect volume");
+    ///
+    /// // change the weights
+    /// mat.mat_data().unwrap().set_operator_weights(&[2.0, 0.1])?;
+    /// mat.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 2.0).abs() < 1e-8, "incorrect weights");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn weighted_operator(
+        &'a self,
+        _petsc: &'a Petsc,
+        mass_weight: f64,
+        stiffness_weight: f64,
+    ) -> Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let context = crate::composition::weighted_mat_shell_context(
+            self,
+            &self.bp_options,
+            &[
+                (mass_weight, crate::composition::OperatorTerm::Mass),
+                (
+                    stiffness_weight,
+                    crate::composition::OperatorTerm::Stiffness,
+                ),
+            ],
+        )?;
+        crate::petsc_ops::create_symmetric_mat_shell(context)
+    }
+
+    /// Returns a cell-wise vector with one entry per local mesh cell,
+    ///   initialized to one, for piecewise constant coefficients
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // one entry per cell of the 5x5x5 mesh
+    /// let coefficient = meles.create_cell_vector(&petsc)?;
+    /// assert_eq!(coefficient.global_size()?, 125, "incorrect number of cells");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_cell_vector(&'a self, _petsc: &'a Petsc) -> Result<petsc::vector::Vector<'a>> {
+        crate::coefficients::create_cell_vector(self)
+    }
+
+    /// Returns a cell-wise coefficient loaded from a file
+    ///
+    /// Files are PETSc binary, or HDF5 with a `.h5` extension, holding a
+    /// vector named `coefficient`. Values are either one per cell, or one
+    /// per vertex, which are projected onto piecewise constants by averaging
+    /// over each cell. Values are ordered by the global numbering of the mesh,
+    /// as written by `save_coefficient`.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `path` - Filepath of the coefficient file
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // round trip a coefficient through a file
+    /// let path = std::env::temp_dir().join("meles_coefficient.bin");
+    /// let mut coefficient = meles.create_cell_vector(&petsc)?;
+    /// coefficient.set_all(2.0)?;
+    /// meles.save_coefficient(&coefficient, &path)?;
+    /// let coefficient = meles.load_cell_coefficient(&petsc, &path)?;
+    ///
+    /// // use for setup
+    /// let mat = meles.mat_shell_with_cell_coefficient(&petsc, &coefficient)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_cell_coefficient(
+        &'a self,
+        _petsc: &'a Petsc,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<petsc::vector::Vector<'a>> {
+        crate::coefficients::load_cell_coefficient(self, path.as_ref())
+    }
+
+    /// Write a coefficient vector to a file readable by `load_cell_coefficient`
+    ///
+    /// # arguments
+    ///
+    /// * `coefficient` - Coefficient vector to write
+    /// * `path` - Filepath of the coefficient file, HDF5 for a `.h5` extension
+    pub fn save_coefficient(
+        &self,
+        coefficient: &petsc::vector::Vector<'a>,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<()> {
+        crate::coefficients::save_coefficient(coefficient, path.as_ref())
+    }
+
+    /// Returns a PETSc MatShell for the benchmark problem with a piecewise
+    ///   constant coefficient, such as material properties by cell
+    ///
+    /// The coefficient scales the quadrature data of each cell. The RHS of
+    /// the MatShell context is still computed for a unit coefficient.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `coefficient` - Cell-wise vector from `create_cell_vector`
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // doubling the coefficient doubles the mass of the unit cube
+    /// let mut coefficient = meles.create_cell_vector(&petsc)?;
+    /// coefficient.set_all(2.0)?;
+    /// let mat = meles.mat_shell_with_cell_coefficient(&petsc, &coefficient)?;
+    /// let mut u = mat.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// mat.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 2.0).abs() < 1e-10, "incorrect scaled mass");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mat_shell_with_cell_coefficient(
+        &'a self,
+        _petsc: &'a Petsc,
+        coefficient: &petsc::vector::Vector<'a>,
+    ) -> Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        // Create MatShellContext with scaled qdata
+        crate::qdata::require_stored_qdata(&self.bp_options, "cell-wise coefficients")?;
+        let mut context = crate::ceed_bps::mat_shell_context(self, &self.bp_options)?;
+        crate::coefficients::scale_qdata_by_cell(&mut context, coefficient)?;
+
+        // Create MatShell
+        let mat = crate::petsc_ops::create_symmetric_mat_shell(context)?;
+        if let Some(num_components) =
+            crate::ceed_bps::constant_nullspace_components(&self.bp_options)?
+        {
+            crate::petsc_ops::set_constant_nullspace(
+                mat.as_raw(),
+                &mat.mat_data().unwrap().dm.borrow(),
+                num_components,
+            )?;
+        }
+        Ok(mat)
+    }
+
+    /// Returns a PETSc MatShell for the benchmark problem with a spatially
+    ///   varying coefficient, such as a variable diffusivity for BP3
+    ///
+    /// The coefficient is evaluated at each quadrature point and scales the
+    /// quadrature data from the setup operator. The RHS of the MatShell
+    /// context is still computed for a unit coefficient.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `coefficient` - Function of the coordinates or global vector on the
+    ///                     problem DM
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // a coefficient of 1 + x has mean 1.5 over the unit cube
+    /// let coefficient = meles::CoefficientField::Function(Box::new(|x| 1.0 + x[0]));
+    /// let mat = meles.mat_shell_with_coefficient(&petsc, coefficient)?;
+    /// let mut u = mat.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// mat.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 1.5).abs() < 1e-10, "incorrect weighted mass");
+    ///
+    /// // the same coefficient as a vector
+    /// let mut field = u.duplicate()?;
+    /// field.set_all(2.0)?;
+    /// let coefficient = meles::CoefficientField::Vector(&field);
+    /// let mat = meles.mat_shell_with_coefficient(&petsc, coefficient)?;
+    /// mat.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 2.0).abs() < 1e-10, "incorrect scaled mass");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mat_shell_with_coefficient(
+        &'a self,
+        _petsc: &'a Petsc,
+        coefficient: crate::CoefficientField,
+    ) -> Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        // Create MatShellContext with scaled qdata
+        crate::qdata::require_stored_qdata(&self.bp_options, "coefficient fields")?;
+        let mut context = crate::ceed_bps::mat_shell_context(self, &self.bp_options)?;
+        let values = crate::coefficients::quadrature_coefficient(self, &context, coefficient)?;
+        crate::coefficients::scale_qdata_by_quadrature_point(&mut context, &values)?;
+
+        // Create MatShell
+        let mat = crate::petsc_ops::create_symmetric_mat_shell(context)?;
+        if let Some(num_components) =
+            crate::ceed_bps::constant_nullspace_components(&self.bp_options)?
+        {
+            crate::petsc_ops::set_constant_nullspace(
+                mat.as_raw(),
+                &mat.mat_data().unwrap().dm.borrow(),
+                num_components,
+            )?;
+        }
+        Ok(mat)
+    }
+
+    /// Set up a KSP with Jacobi preconditioning from the operator diagonal
+    ///
+    /// Scalar problems use `jacobi` and multicomponent problems, such as BP2
+    /// and BP4, use `pbjacobi` with the point block diagonal of the operator.
+    /// Both are computed matrix-free, so `-pc_type jacobi` and
+    /// `-pc_type pbjacobi` also work directly with any Meles MatShell.
+    /// `-pc_type vpbjacobi` uses one block per node of each field, without
+    /// the components constrained by essential boundary conditions, for
+    /// multi-field DMs and partially constrained nodes; the block sizes are
+    /// set on MatShells created with `-pc_type vpbjacobi` in the options.
+    ///
+    /// # arguments
+    ///
+    /// * `ksp` - KSP to set up, with a Meles MatShell as its operator
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve with diagonal preconditioning
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &mat)?;
+    /// meles.set_up_jacobi(&mut ksp)?;
+    /// let rhs = mat.mat_data().unwrap().rhs();
+    /// let mut x = rhs.duplicate()?;
+    /// ksp.solve(rhs, &mut x)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_up_jacobi(&self, ksp: &mut KSP<'a, '_>) -> Result<()> {
+        let pc_type = if crate::ceed_bps::num_components(&self.bp_options)? > 1 {
+            PCType::PCPBJACOBI
+        } else {
+            PCType::PCJACOBI
+        };
+        ksp.get_pc_or_create()?.set_type(pc_type)?;
+        let _options_lock = crate::config::options_lock();
+        ksp.set_from_options()?;
+        Ok(())
+    }
+
+    /// Return an immutable handle to the libCEED operator for the DM
+    ///
+    /// The frozen operator applies through `&self`, so it may back several
+    /// MatShells used by different KSPs at the same time.
+    ///
+    /// Note: Can only directly create a frozen operator for
+    /// `BenchmarkProblem`s
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // share operator between matshells
+    /// let frozen = meles.frozen_operator(&petsc)?;
+    /// let mat = frozen.mat_shell()?;
+    /// let pmat = frozen.mat_shell()?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &pmat)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn frozen_operator(
+        &'a self,
+        _petsc: &'a Petsc,
+    ) -> Result<std::rc::Rc<crate::FrozenOperator<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        // Create and freeze MatShellContext
+        let context = crate::ceed_bps::mat_shell_context(self, &self.bp_options)?;
+        let essential_boundary = crate::ceed_bps::has_essential_boundary(&self.bp_options)?;
+        let frozen = crate::frozen::freeze(self.ceed.clone(), context, essential_boundary)?;
+        Ok(std::rc::Rc::new(frozen))
+    }
+
+    /// Return a deflation space of constants per subdomain
+    ///
+    /// One vector is created per MPI rank. Additional vectors, such as low
+    /// order modes, may be added with `DeflationSpace::with_vector`.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // deflated solve
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &mat)?;
+    /// let space = meles.deflation_space(&petsc)?;
+    /// space.set_up_ksp(&mut ksp)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deflation_space(&self, _petsc: &'a Petsc) -> Result<crate::DeflationSpace<'a>> {
+        crate::deflation::subdomain_constants(self)
+    }
+
+    /// Returns the residual, `r = b - A x`, evaluated matrix-free
+    ///
+    /// Essential boundary values are inserted before applying the operator.
+    /// The operator is set up on the first call and reused until the mesh or
+    /// discretization changes.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `x` - Global solution vector
+    /// * `b` - Global right hand side vector
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // residual of initial guess
+    /// let mut b = meles.dm.borrow().create_global_vector()?;
+    /// b.set_all(1.0)?;
+    /// let mut x = b.duplicate()?;
+    /// x.zero_entries()?;
+    /// let r = meles.residual(&petsc, &x, &b)?;
+    /// println!("residual norm: {}", r.norm(NormType::NORM_2)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn residual(
+        &'a self,
+        _petsc: &'a Petsc,
+        x: &petsc::vector::Vector<'a>,
+        b: &petsc::vector::Vector<'a>,
+    ) -> Result<petsc::vector::Vector<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let context = crate::registry::residual_context(self)?;
+        let essential_boundary = crate::ceed_bps::has_essential_boundary(&self.bp_options)?;
+        let result = crate::frozen::residual_with(
+            &context.dm.borrow(),
+            &context.op_ceed.borrow(),
+            essential_boundary,
+            (
+                &mut context.x_loc.borrow_mut(),
+                &mut context.x_loc_ceed.borrow_mut(),
+                &mut context.y_loc.borrow_mut(),
+                &mut context.y_loc_ceed.borrow_mut(),
+            ),
+            x,
+            b,
+        );
+        result
+    }
+
+    /// Return the mass matrix of the problem for solves in the `M` inner
+    /// product
+    ///
+    /// Note: Can only directly create a mass matrix for `BenchmarkProblem`s
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // Riesz map preconditioned CG
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let mass = meles.mass_inner_product(&petsc)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &mat)?;
+    /// mass.set_up_ksp(&mut ksp)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mass_inner_product(&'a self, _petsc: &'a Petsc) -> Result<crate::MassInnerProduct<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::inner_product::mass_inner_product(self)
+    }
+
+    /// Return the element-wise static condensation of the operator
+    ///
+    /// Interior DoFs are eliminated element-by-element, so Krylov vectors
+    /// only span the trace space.
+    ///
+    /// Note: Requires a plex mesh with a single hexahedral cell type and order
+    /// of at least two
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve on the trace space
+    /// let condensation = meles.static_condensation(&petsc)?;
+    /// let mat = condensation.mat_shell()?;
+    /// let mut b = condensation.dm().create_global_vector()?;
+    /// b.set_all(1.0)?;
+    /// let b_b = condensation.condense_rhs(&b)?;
+    /// let mut x_b = condensation.create_trace_vector()?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &mat)?;
+    /// ksp.get_pc_or_create()?.set_type(PCType::PCJACOBI)?;
+    /// ksp.solve(&b_b, &mut x_b)?;
+    ///
+    /// // recover interior DoFs
+    /// let x = condensation.back_substitute(&x_b, &b)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn static_condensation(
+        &'a self,
+        _petsc: &'a Petsc,
+    ) -> Result<std::rc::Rc<crate::StaticCondensation<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let condensation = crate::condensation::static_condensation(self, &self.bp_options)?;
+        Ok(std::rc::Rc::new(condensation))
+    }
+
+    /// Return the interface and interior splitting of the subdomain DoFs
+    /// with the unassembled subdomain operator
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // split subdomain DoFs
+    /// let splitting = meles.subdomain_splitting(&petsc)?;
+    /// println!(
+    ///     "interface DoFs: {}, interior DoFs: {}",
+    ///     splitting.interface().len(),
+    ///     splitting.interior().len()
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subdomain_splitting(
+        &'a self,
+        petsc: &'a Petsc,
+    ) -> Result<crate::SubdomainSplitting<'a>> {
+        crate::subdomain::subdomain_splitting(self.frozen_operator(petsc)?)
+    }
+
+    /// Return the operator assembled as an AIJ matrix, for use as the
+    /// preconditioning matrix alongside the matrix-free MatShell
+    ///
+    /// Preallocation follows the element restriction, and the matrix is
+    /// marked symmetric for preconditioners such as GAMG.
+    ///
+    /// Note: Requires a plex mesh with a single cell type
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // matrix-free operator, assembled preconditioner
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let pmat = meles.assembled_matrix(&petsc)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &pmat)?;
+    /// ksp.get_pc_or_create()?.set_type(PCType::PCGAMG)?;
+    /// let rhs = mat.mat_data().unwrap().rhs();
+    /// let mut x = rhs.duplicate()?;
+    /// ksp.solve(rhs, &mut x)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn assembled_matrix(&'a self, _petsc: &'a Petsc) -> Result<petsc::mat::Mat<'a, 'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::assembly::assembled_matrix(self, &self.bp_options)
+    }
+
+    /// Returns the row, column, and absolute row sums and the diagonal of
+    /// the operator, from its element matrices
+    ///
+    /// Absolute row sums are for l1-Jacobi smoothing, and the smallest
+    /// diagonal dominance ratio is reported for diagnostics.
+    ///
+    /// Note: Requires a plex mesh with a single cell type
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // row sums of the mass matrix sum to the volume
+    /// let sums = meles.row_sums(&petsc)?;
+    /// let mut ones = sums.row.duplicate()?;
+    /// ones.set_all(1.0)?;
+    /// assert!((sums.row.dot(&ones)? - 1.0).abs() < 1e-10, "incorrect row sums");
+    /// assert!((sums.column.dot(&ones)? - 1.0).abs() < 1e-10, "incorrect column sums");
+    /// println!("min diagonal dominance: {}", sums.min_dominance);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn row_sums(&'a self, _petsc: &'a Petsc) -> Result<crate::RowSums<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::row_sums::row_sums(self, &self.bp_options)
+    }
+
+    /// Returns an AIJ approximation of the operator, recovered by probing the
+    ///   matrix-free operator
+    ///
+    /// Global DoFs are colored by a distance 2 coloring of the sparsity
+    /// pattern from the DM adjacency, so the operator is applied once per
+    /// color and every entry of the pattern is recovered exactly. Off-diagonal
+    /// entries smaller than `drop_tolerance` times the diagonal of the row
+    /// are dropped, sparsifying the approximation.
+    ///
+    /// This gives a preconditioning matrix without assembling element
+    /// matrices, for operators without element access.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `drop_tolerance` - Relative size below which off-diagonals are dropped
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // without dropping, the probed matrix is the operator
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let probed = meles.probed_matrix(&petsc, 0.0)?;
+    /// let mut u = mat.mat_data().unwrap().rhs().duplicate()?;
+    /// for (i, u_i) in u.view_mut()?.iter_mut().enumerate() {
+    ///     *u_i = (i as f64).sin();
+    /// }
+    /// let mut v = u.duplicate()?;
+    /// let mut w = u.duplicate()?;
+    /// mat.mult(&u, &mut v)?;
+    /// probed.mult(&u, &mut w)?;
+    /// w.axpy(-1.0, &v)?;
+    /// assert!(
+    ///     w.norm(NormType::NORM_MAX)? < 1e-12 * v.norm(NormType::NORM_MAX)?,
+    ///     "incorrect probed matrix"
+    /// );
+    ///
+    /// // matrix-free operator, probed preconditioner
+    /// let pmat = meles.probed_matrix(&petsc, 1e-3)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &pmat)?;
+    /// ksp.get_pc_or_create()?.set_type(PCType::PCILU)?;
+    /// let rhs = mat.mat_data().unwrap().rhs();
+    /// let mut x = rhs.duplicate()?;
+    /// ksp.solve(rhs, &mut x)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn probed_matrix(
+        &'a self,
+        _petsc: &'a Petsc,
+        drop_tolerance: f64,
+    ) -> Result<petsc::mat::Mat<'a, 'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::probing::probed_matrix(self, &self.bp_options, drop_tolerance)
+    }
+
+    /// Return the mixed Darcy formulation of the Poisson problem, with a
+    /// lowest order Raviart-Thomas flux and a piecewise constant pressure
+    ///
+    /// Note: Requires a plex mesh with a single hexahedral cell type and a
+    /// /cpu libCEED resource
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/darcy.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve the symmetric indefinite block system
+    /// let darcy = meles.darcy_problem(&petsc)?;
+    /// let mat = darcy.mat_shell()?;
+    /// let mut x = darcy.dm().create_global_vector()?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&mat, &mat)?;
+    /// ksp.set_from_options()?;
+    /// ksp.solve(darcy.rhs(), &mut x)?;
+    ///
+    /// // check pressure error
+    /// let error = darcy.pressure_error(&x)?;
+    /// assert!(error < 5e-3, "incorrect pressure error");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn darcy_problem(
+        &'a self,
+        _petsc: &'a Petsc,
+    ) -> Result<std::rc::Rc<crate::DarcyProblem<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let darcy = crate::darcy::darcy_problem(self, &self.bp_options)?;
+        Ok(std::rc::Rc::new(darcy))
+    }
+
+    /// Return a discontinuous Galerkin (DGSEM) discretization of a hyperbolic
+    /// system with the given fluxes, using the benchmark problem order
+    ///
+    /// Note: Requires a plex mesh with a single hexahedral cell type on a
+    /// single rank; device libCEED resources apply the DG operators on the
+    /// host
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `flux` - Physical and numerical flux hooks
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // linear advection with the upwind flux
+    /// let wind = [1.0, 0.5, 0.25];
+    /// let flux = meles::DgFlux::new(
+    ///     1,
+    ///     move |u, f| f[0] = [wind[0] * u[0], wind[1] * u[0], wind[2] * u[0]],
+    ///     move |u_minus, u_plus, n, f| {
+    ///         let a_n = wind[0] * n[0] + wind[1] * n[1] + wind[2] * n[2];
+    ///         f[0] = a_n * if a_n > 0.0 { u_minus[0] } else { u_plus[0] };
+    ///     },
+    /// );
+    /// let dg = meles.dg_problem(&petsc, flux)?;
+    ///
+    /// // a constant state is preserved
+    /// let mut u = dg.mass.duplicate()?;
+    /// u.set_all(1.0)?;
+    /// let mut r = u.duplicate()?;
+    /// dg.residual.mult(&u, &mut r)?;
+    /// assert!(r.norm(NormType::NORM_2)? < 1e-10, "constant state not preserved");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dg_problem(
+        &'a self,
+        _petsc: &'a Petsc,
+        flux: crate::DgFlux,
+    ) -> Result<crate::DgProblem<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::dg::dg_problem(self, &self.bp_options, flux)
+    }
+
+    /// Return an AIJ assembly of the operator that caches its sparsity
+    /// pattern for repeated numeric re-assembly
+    ///
+    /// Note: Requires a plex mesh with a single cell type
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // assemble, then reassemble numeric values only
+    /// let mut assembled = meles.assembled_operator(&petsc)?;
+    /// assembled.reassemble()?;
+    /// assert_eq!(assembled.num_numeric_assemblies(), 2);
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(assembled.mat(), assembled.mat())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn assembled_operator(&'a self, _petsc: &'a Petsc) -> Result<crate::AssembledOperator<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::assembly::assembled_operator(self, &self.bp_options, None)
+    }
+
+    /// Return an AIJ assembly of the operator restricted to the cells in a
+    /// label stratum
+    ///
+    /// The rest of the operator stays matrix-free, so a small stiff region,
+    /// such as boundary layer cells, can be direct-solved within a hybrid
+    /// preconditioner.
+    ///
+    /// Note: Requires a plex mesh with a single cell type
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `label` - Name of the DM label marking the region
+    /// * `value` - Label value of the cells to assemble
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // assemble hexahedra, marked by the celltype label
+    /// let assembled = meles.assembled_operator_on_label(&petsc, "celltype", 7)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(assembled.mat(), assembled.mat())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn assembled_operator_on_label(
+        &'a self,
+        _petsc: &'a Petsc,
+        label: &str,
+        value: usize,
+    ) -> Result<crate::AssembledOperator<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::assembly::assembled_operator(self, &self.bp_options, Some((label, value)))
+    }
+
+    /// Returns the error indicator of each local element, the Kelly estimate
+    ///   from the jumps of the normal derivative of the solution across the
+    ///   interior faces of the element
+    ///
+    /// Gradients are evaluated at the element centers. Faces on partition
+    /// boundaries only contribute for meshes distributed with overlap.
+    ///
+    /// Note: Requires a `plex` mesh
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `solution` - Global solution vector
+    pub fn error_indicators(
+        &'a self,
+        _petsc: &'a Petsc,
+        solution: &petsc::vector::Vector<'a>,
+    ) -> Result<Vec<f64>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+        if self.bp_options.mesh_type != crate::dm::MeshType::Plex {
+            return Err(crate::Error {
+                message: "error indicators require a plex mesh".to_string(),
+            });
+        }
+
+        crate::amr::error_indicators(self, solution)
+    }
+
+    /// Refine the elements marked from their error indicators and
+    ///   interpolate the solution onto the refined mesh
+    ///
+    /// The mesh of this problem is replaced, so operators, solvers, and
+    /// vectors from the previous mesh must be recreated. Marked elements are
+    /// refined with the DMPlex transform given by the `amr_transform` option,
+    /// the conforming Alfeld split by default.
+    ///
+    /// Note: Requires a `plex` mesh of simplices
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `solution` - Global solution vector on the current mesh
+    /// * `marking` - Strategy for marking elements
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/amr.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve, estimate, mark, and refine
+    /// let mut global_elements = 0;
+    /// for _ in 0..2 {
+    ///     let result = meles.solve(&petsc)?;
+    ///     let step = meles.adapt(&petsc, &result.solution, meles::AmrMarking::Dorfler(0.5))?;
+    ///     assert!(step.global_elements > global_elements, "mesh not refined");
+    ///     global_elements = step.global_elements;
+    ///     println!("{}", step);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn adapt(
+        &'a self,
+        petsc: &'a Petsc,
+        solution: &petsc::vector::Vector<'a>,
+        marking: crate::AmrMarking,
+    ) -> Result<crate::AmrStep<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::amr::adapt(self, petsc, solution, marking)
+    }
+
+    /// Returns a sequence of progressively refined problems, for
+    ///   h-convergence studies
+    ///
+    /// Level `k` of the sequence has `k + 1` more uniform refinements than
+    /// this problem. Every level shares the Ceed context, configuration, and
+    /// options of this problem, and only its mesh and discretization are
+    /// created, so the levels must not outlive this problem.
+    ///
+    /// Note: Requires a `plex` mesh
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `num_levels` - Number of refined problems
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // errors decrease with refinement
+    /// let mut previous_error = {
+    ///     let result = meles.solve(&petsc)?;
+    ///     meles.compute_error(&petsc, &result.solution)?.l2
+    /// };
+    /// for level in meles.refined_sequence(&petsc, 1)? {
+    ///     let result = level.solve(&petsc)?;
+    ///     let error = level.compute_error(&petsc, &result.solution)?.l2;
+    ///     assert!(error < previous_error, "error did not decrease");
+    ///     previous_error = error;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn refined_sequence(
+        &'a self,
+        petsc: &'a Petsc,
+        num_levels: usize,
+    ) -> Result<Vec<crate::Meles<'a>>> {
+        crate::convergence::refined_sequence(self, petsc, num_levels)
+    }
+
+    /// Sweep polynomial order on the existing mesh
+    ///
+    /// The mesh DM, coordinates, and partitioning are reused, and only the
+    /// bases, restrictions, and qdata are rebuilt for each order. The
+    /// callback is run with the MatShell for each order, and the setup cost
+    /// for each order, including its share of the mesh setup, is reported.
+    ///
+    /// Note: Can only sweep order for `BenchmarkProblem`s on `plex` meshes
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `orders` - Polynomial orders to sweep
+    /// * `f` - Callback run with each order and MatShell
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // sweep orders
+    /// let sweep = meles.order_sweep(&petsc, &[1, 2, 3], |order, mat| {
+    ///     let mut ksp = petsc.ksp_create()?;
+    ///     ksp.set_operators(mat, mat)?;
+    ///     println!("order {} setup", order);
+    ///     Ok(())
+    /// })?;
+    /// assert_eq!(sweep.entries.len(), 3);
+    /// println!("{}", sweep);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn order_sweep<F>(
+        &'a self,
+        _petsc: &'a Petsc,
+        orders: &[usize],
+        f: F,
+    ) -> Result<crate::OrderSweep>
+    where
+        F: FnMut(
+            usize,
+            &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+        ) -> Result<()>,
+    {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+        assert!(
+            self.bp_options.mesh_type == crate::dm::MeshType::Plex,
+            "only supported for plex meshes"
+        );
+
+        crate::sweep::order_sweep(self, orders, f)
+    }
+
+    /// Return diffusive, advective, and combined advection-diffusion
+    /// MatShells built from the same setup data
+    ///
+    /// Note: Requires a scalar problem on a plex mesh with a single cell type
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `wind` - Constant advection velocity
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // precondition advection-diffusion with the diffusive part
+    /// let operators = meles.advection_diffusion(&petsc, [1.0, 0.0, 0.0])?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&operators.combined, &operators.diffusive)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn advection_diffusion(
+        &'a self,
+        _petsc: &'a Petsc,
+        wind: [f64; 3],
+    ) -> Result<crate::AdvectionDiffusion<'a>> {
+        crate::advection::advection_diffusion(self, &self.bp_options, wind)
+    }
+
+    /// Return the residual and Jacobian of a quasistatic hyperelasticity
+    /// problem
+    ///
+    /// Note: Can only be created for `RatelQuasistatic` problems
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/quasistatic.yml",
+    ///     meles::MethodType::RatelQuasistatic,
+    /// )?;
+    ///
+    /// // residual at the initial configuration is the body force
+    /// let problem = meles.quasistatic(&petsc)?;
+    /// let mut u = problem.dm().create_global_vector()?;
+    /// let mut r = u.duplicate()?;
+    /// u.zero_entries()?;
+    /// problem.residual(&u, &mut r)?;
+    /// assert!(r.norm(NormType::NORM_2)? > 0.0, "incorrect residual");
+    ///
+    /// // Jacobian linearized about the last residual evaluation
+    /// problem.update_jacobian()?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// ksp.set_operators(&problem.jacobian, &problem.jacobian)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quasistatic(&'a self, _petsc: &'a Petsc) -> Result<crate::Quasistatic<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::RatelQuasistatic,
+            "only supported for RatelQuasistatic problems"
+        );
+
+        crate::quasistatic::quasistatic(self, &self.bp_options, &self.quasistatic_options)
+    }
+
+    /// Return a SNES with a residual backed by a libCEED operator apply and a
+    /// matrix-free Jacobian MatShell
+    ///
+    /// The SNES is configured from the options database.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/quasistatic.yml",
+    ///     meles::MethodType::RatelQuasistatic,
+    /// )?;
+    ///
+    /// // Newton solve from the initial configuration
+    /// let mut solver = meles.snes(&petsc)?;
+    /// let mut u = solver.create_global_vector()?;
+    /// u.zero_entries()?;
+    /// solver.solve(&mut u)?;
+    /// assert!(u.norm(NormType::NORM_2)? > 0.0, "incorrect displacement");
+    ///
+    /// // rebuild the PC every other Newton step
+    /// solver.set_preconditioner_reuse(meles::PreconditionerReuse::Lag(2))?;
+    /// solver.solve(&mut u)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snes(&'a self, petsc: &'a Petsc) -> Result<crate::NonlinearSolver<'a>> {
+        crate::nonlinear::snes(self, petsc)
+    }
+
+    /// Return an empty segregated solver for multi-field problems, with
+    ///   tolerances from the `-segregated_rtol` and `-segregated_max_it`
+    ///   options
+    ///
+    /// Fields are added with their own KSP and coupled with off-diagonal
+    /// operators, and each outer iteration is a block Gauss-Seidel sweep over
+    /// the fields in the order they were added.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // second field coupled to the first through the boundary mass
+    /// let mat = meles.mat_shell(&petsc)?;
+    /// let coupling = meles.boundary_mass(&petsc, "Face Sets", &[1, 2, 3, 4, 5, 6])?;
+    /// let mut segregated = meles.segregated_solver(&petsc)?;
+    /// for _ in 0..2 {
+    ///     let mut ksp = petsc.ksp_create()?;
+    ///     ksp.set_operators(&mat, &mat)?;
+    ///     ksp.set_tolerances(Some(1e-12), None, None, None)?;
+    ///     segregated.add_field(ksp)?;
+    /// }
+    /// segregated.add_coupling(1, 0, &coupling)?;
+    ///
+    /// // lower block triangular system converges in one sweep
+    /// let rhs = mat.mat_data().unwrap().rhs();
+    /// let mut rhs_fields = [rhs.duplicate()?, rhs.duplicate()?];
+    /// rhs_fields[0].copy_data_from(rhs)?;
+    /// rhs_fields[1].copy_data_from(rhs)?;
+    /// let mut solution = [rhs.duplicate()?, rhs.duplicate()?];
+    /// solution[0].zero_entries()?;
+    /// solution[1].zero_entries()?;
+    /// segregated.set_tolerances(1e-8, 10);
+    /// let result = segregated.solve(&rhs_fields, &mut solution)?;
+    /// assert_eq!(result.iterations, 1, "incorrect number of outer iterations");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn segregated_solver<'b>(
+        &'a self,
+        petsc: &'a Petsc,
+    ) -> Result<crate::SegregatedSolver<'a, 'b>> {
+        crate::segregated::segregated_solver(petsc)
+    }
+
+    /// Return a TS for the transient benchmark problem `M u_t + K u = b`, with
+    /// libCEED mass and stiffness operators in the IFunction and a
+    /// matrix-free IJacobian MatShell
+    ///
+    /// The TS defaults to backward Euler and is configured from the options
+    /// database, e.g. `-ts_type`, `-ts_dt`, and `-ts_max_time`.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/heat.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // transient heat equation from a zero initial condition
+    /// let mut solver = meles.ts(&petsc)?;
+    /// let mut u = solver.create_global_vector()?;
+    /// u.zero_entries()?;
+    /// solver.solve(&mut u)?;
+    /// assert!(u.norm(NormType::NORM_2)? > 0.0, "incorrect solution");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ts(&'a self, petsc: &'a Petsc) -> Result<crate::TransientSolver<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::transient::ts(self, petsc)
+    }
+
+    /// Write a checkpoint of the full solver state to an HDF5 file
+    ///
+    /// The checkpoint holds the solution, the time integrator or load
+    /// stepping state, and the accepted values of any quadrature fields, such
+    /// as history variables, so a run can be resumed with `read_checkpoint`.
+    /// The run manifest and configuration are stored as string attributes, so
+    /// the checkpoint is self-describing. The name of `solution` is unchanged.
+    ///
+    /// Only the state above is stored. A resumed transient solve repeats the
+    /// original steps for one-step integrators with a fixed step size, but the
+    /// history of multistep integrators, such as BDF, the state of adaptive
+    /// step size controllers, and the previous solutions used for initial
+    /// guesses start over, so those runs continue from the checkpoint without
+    /// matching an uninterrupted run to round-off.
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the HDF5 checkpoint
+    /// * `solution` - Global solution vector
+    /// * `state` - Time integrator or load stepping state
+    /// * `fields` - Quadrature fields to store, if any
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/heat.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // checkpoint after the transient solve
+    /// let mut solver = meles.ts(&petsc)?;
+    /// let mut u = solver.create_global_vector()?;
+    /// u.zero_entries()?;
+    /// solver.solve(&mut u)?;
+    /// let state = meles::SolverState::from_ts(&solver.ts)?;
+    /// let path = std::env::temp_dir().join("meles_checkpoint.h5");
+    /// meles.write_checkpoint(&path, &u, &state, None)?;
+    ///
+    /// // resume from the checkpoint
+    /// let mut restart = meles.ts(&petsc)?;
+    /// let mut v = restart.create_global_vector()?;
+    /// let restored = meles.read_checkpoint(&path, &mut v, None)?;
+    /// assert_eq!(restored, state, "incorrect solver state");
+    /// restored.restore_ts(&mut restart.ts)?;
+    /// v.axpy(-1.0, &u)?;
+    /// assert!(v.norm(NormType::NORM_MAX)? < 1e-14, "incorrect solution");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_checkpoint(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        solution: &petsc::vector::Vector<'a>,
+        state: &crate::SolverState,
+        fields: Option<&crate::QuadratureFields<'a>>,
+    ) -> Result<()> {
+        crate::checkpoint::write_checkpoint(self, path.as_ref(), solution, state, fields)
+    }
+
+    /// Read a checkpoint written by `write_checkpoint`, returning the solver
+    ///   state
+    ///
+    /// The solution vector and the declared quadrature fields are
+    /// overwritten, and must match the discretization of the checkpoint.
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the HDF5 checkpoint
+    /// * `solution` - Global solution vector to overwrite
+    /// * `fields` - Quadrature fields to restore, if any
+    pub fn read_checkpoint(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        solution: &mut petsc::vector::Vector<'a>,
+        fields: Option<&mut crate::QuadratureFields<'a>>,
+    ) -> Result<crate::SolverState> {
+        crate::checkpoint::read_checkpoint(path.as_ref(), solution, fields)
+    }
+
+    /// Returns the reason solves stop early, if any rank received SIGTERM
+    ///   after `install_shutdown_handler` or the `max_walltime` budget from
+    ///   setup is exhausted
+    ///
+    /// This is collective, so every rank gets the same answer and can take
+    /// the same path to a clean exit.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    /// meles::install_shutdown_handler()?;
+    ///
+    /// // solve, stopping early on SIGTERM
+    /// let result = meles.solve(&petsc)?;
+    /// assert_eq!(meles.shutdown_reason(), None, "no signal sent");
+    /// assert_eq!(result.interrupted, None, "no signal sent");
+    ///
+    /// // partial results
+    /// let directory = std::env::temp_dir().join("meles_partial");
+    /// let state = meles::SolverState::default();
+    /// meles.write_partial_results(&directory, &result.solution, &state, Some(&result))?;
+    /// assert!(directory.join("checkpoint.h5").exists(), "no checkpoint");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shutdown_reason(&self) -> Option<crate::ShutdownReason> {
+        crate::shutdown::shutdown_reason(self.comm, &self.budget)
+    }
+
+    /// Write a checkpoint and partial report, such as after SIGTERM
+    ///
+    /// The directory gets `checkpoint.h5`, as from `write_checkpoint`, and
+    /// `report.yml`, with the run manifest, the status of the run, the solver
+    /// state, and the iterations and residual norm of a solve, if any. Solves
+    /// that stop early write these to the `partial_results` directory
+    /// themselves. This is collective.
+    ///
+    /// # arguments
+    ///
+    /// * `directory` - Directory for the checkpoint and report
+    /// * `solution` - Global solution vector
+    /// * `state` - Time integrator or load stepping state
+    /// * `result` - Result of the interrupted solve, if any
+    pub fn write_partial_results(
+        &'a self,
+        directory: impl AsRef<std::path::Path>,
+        solution: &petsc::vector::Vector<'a>,
+        state: &crate::SolverState,
+        result: Option<&crate::SolveResult<'a>>,
+    ) -> Result<()> {
+        crate::shutdown::write_partial_results(self, directory.as_ref(), solution, state, result)
+    }
+
+    /// Returns the minimum Jacobian determinant, geometric Jacobian condition
+    ///   number, and quadrature data norm of each local element
+    ///
+    /// The Jacobian metrics are evaluated at the quadrature points of the
+    /// problem operator, so inverted or badly shaped elements of distorted
+    /// meshes, such as Kershaw meshes, can be found before or after a solve
+    /// fails to converge. The condition number measures element shape only,
+    /// not the conditioning of the element matrix. Requires a plex mesh with
+    /// a single cell type; the metrics are computed on the host for device
+    /// resources.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // uniform cube mesh
+    /// let metrics = meles.element_metrics()?;
+    /// assert_eq!(metrics.min_det_j.len(), 125, "incorrect number of elements");
+    /// assert!(metrics.inverted().is_empty(), "inverted elements");
+    /// for condition in &metrics.condition {
+    ///     assert!((condition - 1.0).abs() < 1e-10, "incorrect condition number");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn element_metrics(&'a self) -> Result<crate::ElementMetrics> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::element_metrics::element_metrics(self)
+    }
+
+    /// Write the element metrics to a VTU file as cell data, for
+    ///   visualization of distorted elements
+    ///
+    /// The cell fields are `min_det_j`, `condition`, and `qdata_norm`; see
+    /// `Meles::element_metrics`.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/kershaw.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // write metrics
+    /// let path = std::env::temp_dir().join("meles_element_metrics.vtu");
+    /// meles.write_element_metrics(&path)?;
+    /// assert!(path.exists(), "missing VTU file");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the VTU file to write
+    pub fn write_element_metrics(&'a self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::element_metrics::write_vtu(self, path.as_ref())
+    }
+
+    /// Write the mesh and a solution to a CGNS file, for visualization and
+    ///   exchange with CFD tools
+    ///
+    /// Requires a plex mesh and PETSc configured with CGNS. CGNS meshes are
+    /// read with the `dm_plex_filename` option.
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the CGNS file to write
+    /// * `solution` - Global solution vector
+    pub fn write_cgns(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        solution: &petsc::vector::Vector<'a>,
+    ) -> Result<()> {
+        crate::output::write_cgns(self, path.as_ref(), solution)
+    }
+
+    /// Return a MatShell for the boundary mass matrix on labeled faces
+    ///
+    /// The MatShell acts on the same global vectors as the problem operator.
+    /// Faces are selected by the values of a face label, such as the
+    /// `Face Sets` label of box meshes, and require a 2D plex mesh of
+    /// quadrilaterals or a 3D plex mesh of hexahedra.
+    ///
+    /// # arguments
+    ///
+    /// * `label` - Name of the face label
+    /// * `values` - Label values of the faces to include
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // area of the boundary of the unit cube
+    /// let mass = meles.boundary_mass(&petsc, "Face Sets", &[1, 2, 3, 4, 5, 6])?;
+    /// let mut u = mass.mat_data().unwrap().dm.borrow().create_global_vector()?;
+    /// u.set_all(1.0)?;
+    /// let mut v = u.duplicate()?;
+    /// mass.mult(&u, &mut v)?;
+    /// assert!((v.dot(&u)? - 6.0).abs() < 1e-10, "incorrect area");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn boundary_mass(
+        &'a self,
+        _petsc: &'a Petsc,
+        label: &str,
+        values: &[usize],
+    ) -> Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let context = crate::surface::boundary_mass_context(self, label, values)?;
+        crate::petsc_ops::create_mat_shell(context)
+    }
+
+    /// Returns the Neumann term, the integral of a flux against the test
+    ///   functions over labeled faces, as a global vector
+    ///
+    /// The vector can be added to the benchmark RHS for natural boundary
+    /// conditions. Faces are selected as for `Meles::boundary_mass`.
+    ///
+    /// # arguments
+    ///
+    /// * `label` - Name of the face label
+    /// * `values` - Label values of the faces to include
+    /// * `flux` - Boundary flux, as an expression in `x`, `y`, `z`, and `t`
+    /// * `time` - Time at which to evaluate the flux
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // unit flux over one face
+    /// let flux = meles::Expression::parse("1")?;
+    /// let neumann = meles.neumann_rhs(&petsc, "Face Sets", &[1], &flux, 0.0)?;
+    /// let mut u = neumann.duplicate()?;
+    /// u.set_all(1.0)?;
+    /// assert!((neumann.dot(&u)? - 1.0).abs() < 1e-10, "incorrect flux");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn neumann_rhs(
+        &'a self,
+        _petsc: &'a Petsc,
+        label: &str,
+        values: &[usize],
+        flux: &crate::Expression,
+        time: f64,
+    ) -> Result<petsc::vector::Vector<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::surface::boundary_integral(self, &self.bp_options, label, values, flux, time)
+    }
+
+    /// Solve the benchmark problem with the RHS from its forcing term
+    ///
+    /// The KSP is configured from the options database.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve
+    /// let result = meles.solve(&petsc)?;
+    /// println!("{}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn solve(&'a self, petsc: &'a Petsc) -> Result<crate::SolveResult<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::solve::solve(self, petsc)
+    }
+
+    /// Returns a solve session for repeated solves of the benchmark problem
+    ///   operator with different RHS
+    ///
+    /// The KSP and PC are set up once, and each solve starts from the
+    /// previous solution by default; see `SolveSession::set_initial_guess`.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // a repeated solve starts from the solution
+    /// let mut session = meles.solve_session(&petsc)?;
+    /// let mut rhs = session.rhs().duplicate()?;
+    /// rhs.copy_data_from(session.rhs())?;
+    /// let first = session.solve(&rhs)?;
+    /// let second = session.solve(&rhs)?;
+    /// assert!(second.iterations <= first.iterations, "initial guess not used");
+    ///
+    /// // recycle the span of previous solutions
+    /// session.set_recycling(meles::Recycling::SolutionSpace(4))?;
+    /// let third = session.solve(&rhs)?;
+    /// assert!(third.iterations <= first.iterations, "solution space not used");
+    ///
+    /// // rebuild the PC after changing the operator
+    /// session.mark_operator_changed();
+    /// session.solve(&rhs)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn solve_session(&'a self, petsc: &'a Petsc) -> Result<crate::SolveSession<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::solve::solve_session(self, petsc)
+    }
+
+    /// Returns a p-multigrid hierarchy for the benchmark problem, halving the
+    ///   polynomial order on each level down to order 1
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // order 3 coarsens to order 1
+    /// let multigrid = meles.p_multigrid(&petsc)?;
+    /// assert_eq!(multigrid.orders(), &[1, 3]);
+    ///
+    /// // solve with PCMG
+    /// let mut ksp = petsc.ksp_create()?;
+    /// multigrid.set_up_ksp(&mut ksp)?;
+    /// let rhs = multigrid.operator(1).mat_data().unwrap().rhs();
+    /// let mut x = rhs.duplicate()?;
+    /// ksp.solve(rhs, &mut x)?;
+    ///
+    /// // l1-Jacobi smoothing
+    /// petsc.options_insert_string("-p_multigrid_smoother l1_jacobi")?;
+    /// let multigrid = meles.p_multigrid(&petsc)?;
+    /// let mut ksp = petsc.ksp_create()?;
+    /// multigrid.set_up_ksp(&mut ksp)?;
+    /// ksp.solve(rhs, &mut x)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn p_multigrid(&'a self, petsc: &'a Petsc) -> Result<crate::PMultigrid<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let mg_options: crate::multigrid::Opt = {
+            let _options_lock = crate::config::options_lock();
+            petsc.options()?
+        };
+        crate::multigrid::p_multigrid(self, &mg_options)
+    }
+
+    /// Returns the L2 and H1 error of a solution against the exact solution of
+    ///   the benchmark problem, reduced over all ranks
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `solution` - Global solution vector for the benchmark problem DM
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // solve and check error
+    /// let result = meles.solve(&petsc)?;
+    /// let error = meles.compute_error(&petsc, &result.solution)?;
+    /// assert!(error.l2 < 1e-2, "incorrect L2 error");
+    /// println!("{}", error);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compute_error(
+        &'a self,
+        _petsc: &'a Petsc,
+        solution: &petsc::vector::Vector<'a>,
+    ) -> Result<crate::SolutionError> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        let context = crate::ceed_bps::mat_shell_context(self, &self.bp_options)?;
+        crate::norms::compute_error(self, &context, solution)
+    }
+
+    /// Returns an empty set of quadrature point fields matching the
+    ///   discretization of the benchmark problem
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // declare a history variable and take a step
+    /// let mut fields = meles.quadrature_fields(&petsc)?;
+    /// fields.declare("plastic_strain", 6)?;
+    /// let length = fields.values("plastic_strain")?[0].values.len();
+    /// fields.set_values("plastic_strain", 0, &vec![1.0; length])?;
+    /// fields.accept_step()?;
+    ///
+    /// // rejected steps roll back to the last accepted step
+    /// fields.set_values("plastic_strain", 0, &vec![2.0; length])?;
+    /// fields.reject_step()?;
+    /// assert_eq!(fields.values("plastic_strain")?[0].values[0], 1.0);
+    ///
+    /// // checkpoint and restore
+    /// let path = std::env::temp_dir().join("meles_quadrature_fields.bin");
+    /// fields.write_checkpoint(&path)?;
+    /// let mut restored = meles.quadrature_fields(&petsc)?;
+    /// restored.read_checkpoint(&path)?;
+    /// assert_eq!(restored.names(), vec!["plastic_strain"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quadrature_fields(&'a self, _petsc: &'a Petsc) -> Result<crate::QuadratureFields<'a>> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::qdata::require_stored_qdata(&self.bp_options, "quadrature fields")?;
+        crate::qfields::quadrature_fields(self)
+    }
+
+    /// Solve the problem for a manufactured solution of all ones and return
+    /// the iteration count, final residual norm, and max error
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // record results
+    /// let summary = meles.run_summary(&petsc)?;
+    /// println!("{}", summary);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_summary(&'a self, petsc: &'a Petsc) -> Result<crate::RunSummary> {
+        crate::verification::run_summary(self, petsc)
+    }
+
+    /// Solve the problem and write the mesh, RHS, and solution for
+    ///   cross-validation against the C libCEED PETSc BP example
+    ///
+    /// The RHS and solution are written to `rhs.bin` and `solution.bin` as
+    /// PETSc binary vectors, and the mesh to `mesh.h5`, the files the C
+    /// example writes with `-ksp_view_rhs binary:rhs.bin`,
+    /// `-ksp_view_solution binary:solution.bin`, and `-dm_view hdf5:mesh.h5`.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `directory` - Directory of the files to write, created if needed
+    pub fn write_cross_validation(
+        &'a self,
+        petsc: &'a Petsc,
+        directory: impl AsRef<std::path::Path>,
+    ) -> Result<()> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::verification::write_cross_validation(self, petsc, directory.as_ref())
+    }
+
+    /// Solve the problem and compare the RHS and solution against the files
+    ///   of a reference run of the C libCEED PETSc BP example
+    ///
+    /// The reference run must use the same mesh, problem, order, and
+    /// quadrature, so the global DoF orderings match; see
+    /// `write_cross_validation` for the file names. The mesh is compared by
+    /// its coordinates and requires a plex mesh. The problem is set up and
+    /// solved once.
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `reference` - Directory of the reference files
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // reference files from the reference libCEED backend
+    /// let reference = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/cross_validation.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    /// let directory = std::env::temp_dir().join("meles_cross_validation");
+    /// reference.write_cross_validation(&petsc, &directory)?;
+    ///
+    /// // compare
+    /// let cross_validation = meles.cross_validate(&petsc, &directory)?;
+    /// println!("{}", cross_validation);
+    /// assert!(cross_validation.passed(1e-6), "cross-validation failed");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cross_validate(
+        &'a self,
+        petsc: &'a Petsc,
+        reference: impl AsRef<std::path::Path>,
+    ) -> Result<crate::CrossValidation> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::verification::cross_validate(self, petsc, reference.as_ref())
+    }
+
+    /// Run the problem and compare the results against a stored reference
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context to use
+    /// * `reference` - Filepath to reference results
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // store reference
+    /// let reference = std::env::temp_dir().join("meles_reference.yml");
+    /// let profiles = ToleranceProfiles::default();
+    /// meles
+    ///     .run_summary(&petsc)?
+    ///     .write_reference(&reference, &profiles)?;
+    ///
+    /// // compare against reference
+    /// let report = meles.regression_check(&petsc, &reference)?;
+    /// assert!(report.passed(), "{}", report);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn regression_check(
+        &'a self,
+        petsc: &'a Petsc,
+        reference: impl AsRef<std::path::Path>,
+    ) -> Result<crate::RegressionReport> {
+        let reference = crate::RegressionReference::from_file(reference)?;
+        let summary = crate::verification::run_summary(self, petsc)?;
+        Ok(reference.compare(&summary, &self.ceed_resource))
+    }
+
+    /// Returns a manifest of the conditions of the run, with the configuration
+    /// hash, mesh statistics, PETSc and libCEED versions, libCEED resource,
+    /// rank count, and source revision
+    ///
+    /// The manifest should be written alongside benchmark results so they can
+    /// be traced back to the exact conditions that produced them.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // record conditions
+    /// let manifest = meles.run_manifest(&petsc)?;
+    /// manifest.write(std::env::temp_dir().join("meles_manifest.yml"))?;
+    /// println!("{}", manifest);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_manifest(&'a self, _petsc: &'a Petsc) -> Result<crate::RunManifest> {
+        crate::manifest::run_manifest(self)
+    }
+
+    /// Returns true if the `dry_run` option is set, so drivers should report
+    ///   the setup with `Meles::setup_report` and exit without solving
+    pub fn is_dry_run(&self, petsc: &'a Petsc) -> Result<bool> {
+        crate::dry_run::is_dry_run(petsc)
+    }
+
+    /// Set up the benchmark problem operator and report the discretization
+    ///   and memory, without solving
+    ///
+    /// The mesh, bases, restrictions, libCEED operator, and quadrature data
+    /// are created as for a solve, so a large job configuration can be
+    /// validated cheaply before it is queued, such as with the `dry_run`
+    /// option.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // validate setup
+    /// let report = meles.setup_report(&petsc)?;
+    /// assert_eq!(report.manifest.global_cells, Some(125), "incorrect cell count");
+    /// assert_eq!(report.global_quadrature_points, 125 * 5 * 5 * 5, "incorrect quadrature points");
+    /// println!("{}", report);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn setup_report(&'a self, petsc: &'a Petsc) -> Result<crate::SetupReport> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::dry_run::setup_report(self, petsc)
+    }
+
+    /// Returns the resolved libCEED resource, memory type, determinism, and
+    ///   supported features of the libCEED backend
+    ///
+    /// Drivers can branch on these, such as choosing host or device I/O paths
+    /// or requiring deterministic results for regression tests.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // host backend
+    /// let info = meles.backend_info()?;
+    /// assert_eq!(info.memory_type, libceed::MemType::Host, "incorrect memory type");
+    /// assert!(info.closure_q_functions, "closure QFunctions unsupported");
+    /// println!("{}", info);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn backend_info(&self) -> Result<crate::BackendInfo> {
+        crate::backend::backend_info(self)
+    }
+
+    /// Time operator applies of the benchmark problem on candidate libCEED
+    ///   resources
+    ///
+    /// Each resource gets its own mesh and operator from the problem options;
+    /// after an untimed warm up apply, `num_applies` applies are timed and
+    /// the slowest rank is reported. Resources that are unavailable or
+    /// unsupported for the problem are reported with their error rather than
+    /// failing the audition. Resources are first matched against the libCEED
+    /// backend registry, since libCEED aborts on resources without a backend;
+    /// registered backends that fail to initialize, such as device backends
+    /// without a device, still abort.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // time host backends
+    /// let audition =
+    ///     meles.audition_backends(&petsc, &["/cpu/self/ref/serial", "/cpu/self/not_a_backend"], 3)?;
+    /// assert!(audition.timings[0].seconds_per_apply.is_some(), "reference backend failed");
+    /// assert!(audition.timings[1].error.is_some(), "invalid backend accepted");
+    /// assert_eq!(
+    ///     audition.fastest().unwrap().resource,
+    ///     "/cpu/self/ref/serial",
+    ///     "incorrect fastest backend"
+    /// );
+    /// println!("{}", audition);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context
+    /// * `resources` - Candidate libCEED resources
+    /// * `num_applies` - Number of timed operator applies per resource
+    pub fn audition_backends(
+        &self,
+        petsc: &Petsc,
+        resources: &[&str],
+        num_applies: usize,
+    ) -> Result<crate::BackendAudition> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::backend::audition(self, petsc, resources, num_applies)
+    }
+
+    /// Benchmark operator applies and full solves of the benchmark problem
+    ///
+    /// The operator, KSP, and PC are set up once, and after an untimed warm
+    /// up, `num_applies` operator applies and `num_solves` KSPSolve calls from
+    /// a zero initial guess are timed, waiting on pending device work before
+    /// reading the clock, and the slowest rank is reported.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // time applies and solves
+    /// let report = meles.benchmark(&petsc, 5, 2)?;
+    /// assert!(report.seconds_per_apply > 0.0, "applies not timed");
+    /// assert!(report.iterations > 0, "solve not run");
+    /// assert!(report.apply_mdofs_per_second() > 0.0, "incorrect throughput");
+    /// println!("{}", report);
+    ///
+    /// // machine readable reports
+    /// assert!(report.to_json().contains("\"global_dofs\""), "incorrect JSON report");
+    /// assert!(report.to_csv().starts_with("ceed_resource,"), "incorrect CSV report");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context
+    /// * `num_applies` - Number of timed operator applies
+    /// * `num_solves` - Number of timed solves
+    pub fn benchmark(
+        &'a self,
+        petsc: &'a Petsc,
+        num_applies: usize,
+        num_solves: usize,
+    ) -> Result<crate::BenchmarkReport> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::benchmark::benchmark(self, petsc, num_applies, num_solves)
+    }
+
+    /// Returns true if the `benchmark` option is set, so drivers should print
+    ///   `Meles::benchmark_from_options` instead of solving
+    pub fn is_benchmark(&self, petsc: &'a Petsc) -> Result<bool> {
+        crate::benchmark::is_benchmark(petsc)
+    }
+
+    /// Benchmark operator applies and solves of the benchmark problem with
+    ///   the `benchmark_applies` and `benchmark_solves` options, as
+    ///   `Meles::benchmark`, writing the report on rank 0 to the
+    ///   `benchmark_report` file, if set, as JSON for a `.json` extension and
+    ///   CSV otherwise
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/benchmark.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // benchmark as configured
+    /// assert!(meles.is_benchmark(&petsc)?, "benchmark mode not set");
+    /// let report = meles.benchmark_from_options(&petsc)?;
+    /// assert_eq!(report.num_solves, 2, "incorrect solve count");
+    /// println!("{}", report);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn benchmark_from_options(&'a self, petsc: &'a Petsc) -> Result<crate::BenchmarkReport> {
+        // Check setup
+        assert!(
+            self.method == crate::MethodType::BenchmarkProblem,
+            "only supported for BenchmarkProblems"
+        );
+
+        crate::benchmark::benchmark_from_options(self, petsc)
+    }
+
+    /// Audition candidate libCEED resources and switch to the fastest
+    ///
+    /// See `Meles::audition_backends`; the current resource is kept if no
+    /// candidate succeeds.
+    ///
+    /// ```
+    /// # use meles::prelude::*;
+    /// # use petsc::prelude::*;
+    /// # fn main() -> meles::Result<()> {
+    /// let petsc = petsc::Petsc::init_no_args()?;
+    /// let mut meles = meles::Meles::new(
+    ///     &petsc,
+    ///     "./examples/meles.yml",
+    ///     meles::MethodType::BenchmarkProblem,
+    /// )?;
+    ///
+    /// // select the fastest
+    /// let audition =
+    ///     meles.select_backend(&petsc, &["/cpu/self/ref/serial", "/cpu/self/opt/blocked"], 3)?;
+    /// assert_eq!(
+    ///     meles.backend_info()?.requested_resource,
+    ///     audition.fastest().unwrap().resource,
+    ///     "fastest backend not selected"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # arguments
+    ///
+    /// * `petsc` - PETSc context
+    /// * `resources` - Candidate libCEED resources
+    /// * `num_applies` - Number of timed operator applies per resource
+    pub fn select_backend(
+        &mut self,
+        petsc: &Petsc,
+        resources: &[&str],
+        num_applies: usize,
+    ) -> Result<crate::BackendAudition> {
+        let audition = self.audition_backends(petsc, resources, num_applies)?;
+        if let Some(fastest) = audition.fastest() {
+            if fastest.resource != self.ceed_resource {
+                (self.ceed, self.device) = crate::device::init_ceed(self.comm, &fastest.resource)?;
+                self.ceed_resource = fastest.resource.clone();
+                self.backend_info.get_mut().take();
+                let _options_lock = crate::config::options_lock();
+                crate::backend::set_dm_types(
+                    self.comm,
+                    &mut self.dm.borrow_mut(),
+                    &self.ceed_resource,
+                )?;
+            }
+        }
+        Ok(audition)
+    }
 }
 
 // -----------------------------------------------------------------------------
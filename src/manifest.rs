@@ -0,0 +1,344 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Run manifest
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Conditions of a run, written alongside results so benchmark numbers can be
+/// traced back to the exact configuration, mesh, libraries, and source
+pub struct RunManifest {
+    pub config_source: crate::ConfigSource,
+    pub config_hash: String,
+    pub method: crate::MethodType,
+    pub problem: String,
+    pub order: usize,
+    pub mesh_type: String,
+    pub dimension: usize,
+    pub global_cells: Option<usize>,
+    pub global_dofs: usize,
+    pub num_ranks: usize,
+    pub ceed_resource: String,
+    pub libceed_version: String,
+    pub libceed_build_configuration: Option<String>,
+    pub petsc_version: String,
+    pub petsc_arch: String,
+    pub petsc_configure_options: Option<String>,
+    pub petsc_scalar_bytes: usize,
+    pub petsc_int_bytes: usize,
+    pub meles_version: String,
+    pub meles_debug: bool,
+    pub git_describe: Option<String>,
+}
+
+impl RunManifest {
+    /// Write the manifest as flat `key: value` yml
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the manifest to write
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        std::fs::write(path, self.to_yml())?;
+        Ok(())
+    }
+
+    /// Returns the manifest as flat `key: value` yml
+    pub fn to_yml(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+        [
+            ("config_source", self.config_source.to_string()),
+            ("config_hash", self.config_hash.clone()),
+            ("method", format!("{:?}", self.method)),
+            ("problem", self.problem.clone()),
+            ("order", self.order.to_string()),
+            ("mesh_type", self.mesh_type.clone()),
+            ("dimension", self.dimension.to_string()),
+            (
+                "global_cells",
+                optional(self.global_cells.map(|cells| cells.to_string())),
+            ),
+            ("global_dofs", self.global_dofs.to_string()),
+            ("num_ranks", self.num_ranks.to_string()),
+            ("ceed_resource", self.ceed_resource.clone()),
+            ("libceed_version", self.libceed_version.clone()),
+            (
+                "libceed_build_configuration",
+                optional(self.libceed_build_configuration.clone()),
+            ),
+            ("petsc_version", self.petsc_version.clone()),
+            ("petsc_arch", self.petsc_arch.clone()),
+            (
+                "petsc_configure_options",
+                optional(self.petsc_configure_options.clone()),
+            ),
+            ("petsc_scalar_bytes", self.petsc_scalar_bytes.to_string()),
+            ("petsc_int_bytes", self.petsc_int_bytes.to_string()),
+            ("meles_version", self.meles_version.clone()),
+            ("meles_debug", self.meles_debug.to_string()),
+            ("git_describe", optional(self.git_describe.clone())),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}: {}\n", key, value))
+        .collect()
+    }
+}
+
+impl fmt::Display for RunManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Run Manifest:")?;
+        for line in self.to_yml().lines() {
+            writeln!(f, "  {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Library versions and source
+// -----------------------------------------------------------------------------
+// 64-bit FNV-1a hash, stable across platforms and Rust versions
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Read a string from a raw PETSc function filling a fixed size buffer
+fn petsc_string(
+    function: unsafe extern "C" fn(
+        *mut std::os::raw::c_char,
+        usize,
+    ) -> petsc::petsc_raw::PetscErrorCode,
+    name: &str,
+) -> crate::Result<String> {
+    let mut buffer = [0 as std::os::raw::c_char; 256];
+    crate::check_petsc_raw(unsafe { function(buffer.as_mut_ptr(), buffer.len()) }, name)?;
+    let value = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+    Ok(value.to_string_lossy().into_owned())
+}
+
+// Configure options of the PETSc build, from the petscvariables file of the
+//   PETSc directory and arch, or of a prefix install
+fn petsc_configure_options(petsc_arch: &str) -> crate::Result<Option<String>> {
+    let petsc_dir = {
+        let mut petsc_dir = std::ptr::null();
+        crate::check_petsc_raw(
+            unsafe { petsc::petsc_raw::PetscGetPetscDir(&mut petsc_dir) },
+            "PetscGetPetscDir",
+        )?;
+        std::path::PathBuf::from(
+            unsafe { std::ffi::CStr::from_ptr(petsc_dir) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    };
+    let variables = [
+        petsc_dir
+            .join(petsc_arch)
+            .join("lib/petsc/conf/petscvariables"),
+        petsc_dir.join("lib/petsc/conf/petscvariables"),
+    ]
+    .iter()
+    .find_map(|path| std::fs::read_to_string(path).ok());
+    Ok(variables.and_then(|variables| {
+        variables.lines().find_map(|line| {
+            line.strip_prefix("CONFIGURE_OPTIONS")
+                .and_then(|rest| rest.trim_start().strip_prefix('='))
+                .map(|options| options.trim().to_string())
+        })
+    }))
+}
+
+// Build configuration of libCEED, on one line
+fn libceed_build_configuration() -> Option<String> {
+    let mut build_configuration = std::ptr::null();
+    let ierr =
+        unsafe { libceed_sys::bind_ceed::CeedGetBuildConfiguration(&mut build_configuration) };
+    if ierr != 0 || build_configuration.is_null() {
+        return None;
+    }
+    let build_configuration = unsafe { std::ffi::CStr::from_ptr(build_configuration) };
+    Some(
+        build_configuration
+            .to_string_lossy()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("; "),
+    )
+}
+
+// Contents of the options database, so options set on the command line or
+//   through the API are hashed with the configuration
+fn options_table() -> crate::Result<String> {
+    let _options_lock = crate::config::options_lock();
+    let mut buffer = vec![0 as std::os::raw::c_char; OPTIONS_TABLE_SIZE];
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut viewer = std::ptr::null_mut();
+        check(
+            PetscViewerStringOpen(
+                PETSC_COMM_SELF,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut viewer,
+            ),
+            "PetscViewerStringOpen",
+        )?;
+        let view = check(
+            PetscOptionsView(std::ptr::null_mut(), viewer),
+            "PetscOptionsView",
+        );
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        view?;
+    }
+    let table = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }
+        .to_string_lossy()
+        .into_owned();
+    if table.len() + 1 >= OPTIONS_TABLE_SIZE {
+        return Err(crate::Error {
+            message: format!(
+                "options database exceeds {} bytes and cannot be hashed",
+                OPTIONS_TABLE_SIZE
+            ),
+        });
+    }
+    Ok(table)
+}
+
+const OPTIONS_TABLE_SIZE: usize = 1 << 20;
+
+// Description of the source tree, if built from a git checkout
+fn git_describe() -> Option<String> {
+    let output = std::process::Command::new("git")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["describe", "--always", "--dirty", "--tags"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let describe = String::from_utf8(output.stdout).ok()?;
+    Some(describe.trim().to_string()).filter(|describe| !describe.is_empty())
+}
+
+// -----------------------------------------------------------------------------
+// Collect the manifest for a Meles context
+// -----------------------------------------------------------------------------
+// Contents of the configuration file, or the default configuration
+fn config_yml(meles: &crate::Meles) -> crate::Result<String> {
+    Ok(match &meles.config_source {
+        crate::ConfigSource::File(path) => std::fs::read_to_string(path)?,
+        crate::ConfigSource::Default => crate::config::DEFAULT_CONFIG.to_string(),
+    })
+}
+
+pub(crate) fn run_manifest(meles: &crate::Meles) -> crate::Result<RunManifest> {
+    use mpi::traits::Communicator;
+
+    // Configuration
+    let yml = config_yml(meles)?;
+
+    // Mesh statistics
+    let dm = meles.dm.borrow();
+    let dimension = dm.dimension()?;
+    let global_dofs = dm.create_global_vector()?.global_size()? as usize;
+    let global_cells = match crate::dm::local_num_cells(&dm, meles.bp_options.mesh_type)? {
+        Some(local_cells) => {
+            use mpi::collective::{CommunicatorCollectives, SystemOperation};
+            let mut global_cells = 0u64;
+            meles.comm.all_reduce_into(
+                &(local_cells as u64),
+                &mut global_cells,
+                SystemOperation::sum(),
+            );
+            Some(global_cells as usize)
+        }
+        None => None,
+    };
+
+    // Libraries
+    let libceed_version = {
+        let version = libceed::version();
+        format!(
+            "{}.{}.{}{}",
+            version.major,
+            version.minor,
+            version.patch,
+            if version.release { "" } else { "-dev" }
+        )
+    };
+    let petsc_version = petsc_string(petsc::petsc_raw::PetscGetVersion, "PetscGetVersion")?;
+    let petsc_arch = petsc_string(petsc::petsc_raw::PetscGetArchType, "PetscGetArchType")?;
+    let petsc_configure_options = petsc_configure_options(&petsc_arch)?;
+
+    Ok(RunManifest {
+        config_source: meles.config_source.clone(),
+        config_hash: format!(
+            "{:016x}",
+            fnv1a(format!("{}\n{}", yml, options_table()?).as_bytes())
+        ),
+        method: meles.method,
+        problem: meles.bp_options.problem.to_string(),
+        order: meles.bp_options.order,
+        mesh_type: meles.bp_options.mesh_type.to_string(),
+        dimension,
+        global_cells,
+        global_dofs,
+        num_ranks: meles.comm.size() as usize,
+        ceed_resource: meles.ceed_resource.clone(),
+        libceed_version,
+        libceed_build_configuration: libceed_build_configuration(),
+        petsc_version,
+        petsc_arch,
+        petsc_configure_options,
+        petsc_scalar_bytes: std::mem::size_of::<petsc::Scalar>(),
+        petsc_int_bytes: std::mem::size_of::<petsc::Int>(),
+        meles_version: env!("CARGO_PKG_VERSION").to_string(),
+        meles_debug: cfg!(debug_assertions),
+        git_describe: git_describe(),
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Provenance attributes of HDF5 outputs
+//   Each manifest entry is a string attribute prefixed with "meles_", and the
+//   configuration file is stored whole as "meles_config", so output files
+//   are self-describing with h5dump or any HDF5 reader
+// -----------------------------------------------------------------------------
+pub(crate) fn write_hdf5_provenance(
+    meles: &crate::Meles,
+    viewer: petsc::petsc_raw::PetscViewer,
+) -> crate::Result<()> {
+    let manifest = run_manifest(meles)?;
+    let mut attributes: Vec<(String, String)> = manifest
+        .to_yml()
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .map(|(key, value)| (format!("meles_{}", key), value.to_string()))
+        .collect();
+    attributes.push(("meles_config".to_string(), config_yml(meles)?));
+
+    for (name, value) in attributes {
+        let name = std::ffi::CString::new(name).unwrap();
+        let value = std::ffi::CString::new(value).map_err(|_| crate::Error {
+            message: "invalid provenance attribute value".to_string(),
+        })?;
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::PetscViewerHDF5WriteAttribute(
+                    viewer,
+                    std::ptr::null(),
+                    name.as_ptr(),
+                    petsc::petsc_raw::PetscDataType::PETSC_STRING,
+                    value.as_ptr() as *const std::os::raw::c_void,
+                )
+            },
+            "PetscViewerHDF5WriteAttribute",
+        )?;
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,285 @@
+use crate::prelude::*;
+use std::f64::consts::PI;
+
+// -----------------------------------------------------------------------------
+// Manufactured solutions
+//   Each solution provides its value, gradient, and Laplacian so matching
+//   forcing terms and boundary values can be generated for any problem
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ManufacturedSolution {
+    Default,
+    Radial,
+    Trig,
+    Polynomial,
+    Gaussian,
+    Periodic,
+}
+
+impl std::str::FromStr for ManufacturedSolution {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<ManufacturedSolution> {
+        match s {
+            "default" => Ok(ManufacturedSolution::Default),
+            "radial" => Ok(ManufacturedSolution::Radial),
+            "trig" => Ok(ManufacturedSolution::Trig),
+            "polynomial" => Ok(ManufacturedSolution::Polynomial),
+            "gaussian" => Ok(ManufacturedSolution::Gaussian),
+            "periodic" => Ok(ManufacturedSolution::Periodic),
+            _ => Err(crate::Error {
+                message: "failed to parse solution option".to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for ManufacturedSolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManufacturedSolution::Default => write!(f, "default"),
+            ManufacturedSolution::Radial => write!(f, "radial"),
+            ManufacturedSolution::Trig => write!(f, "trig"),
+            ManufacturedSolution::Polynomial => write!(f, "polynomial"),
+            ManufacturedSolution::Gaussian => write!(f, "gaussian"),
+            ManufacturedSolution::Periodic => write!(f, "periodic"),
+        }
+    }
+}
+
+// Trigonometric solution phase and wavenumber in each direction
+//   The solution vanishes on the boundary of the unit cube
+const TRIG_PHASE: [f64; 3] = [0., 1., 2.];
+const TRIG_WAVENUMBER: [f64; 3] = [1., 2., 3.];
+
+// Gaussian bump center and width
+const GAUSSIAN_CENTER: f64 = 0.5;
+const GAUSSIAN_WIDTH: f64 = 0.15;
+
+// Periodic solution wavenumber
+//   The solution has period 1 and mean zero in each direction, so it is
+//   periodic on the unit cube and orthogonal to the constant nullspace
+const PERIODIC_WAVENUMBER: f64 = 2.0 * PI;
+
+impl ManufacturedSolution {
+    // Resolve the default solution for a problem
+    //   Mass problems use the radial solution, diffusion problems use the
+    //   trigonometric solution, and problems on periodic meshes use the
+    //   periodic solution
+    pub(crate) fn resolve(self, problem: crate::ceed_bps::CeedBP, periodic: bool) -> Self {
+        use crate::ceed_bps::CeedBP;
+        match (self, problem) {
+            (ManufacturedSolution::Default, _) if periodic => ManufacturedSolution::Periodic,
+            (ManufacturedSolution::Default, CeedBP::BP1 | CeedBP::BP2) => {
+                ManufacturedSolution::Radial
+            }
+            (ManufacturedSolution::Default, _) => ManufacturedSolution::Trig,
+            (solution, _) => solution,
+        }
+    }
+
+    // Value at a point
+    pub(crate) fn value(self, x: &[Real]) -> petsc::Scalar {
+        match self {
+            ManufacturedSolution::Default | ManufacturedSolution::Radial => {
+                x.iter().map(|x_d| x_d * x_d).sum::<f64>().sqrt()
+            }
+            ManufacturedSolution::Trig => x
+                .iter()
+                .enumerate()
+                .map(|(d, x_d)| (PI * (TRIG_PHASE[d] + TRIG_WAVENUMBER[d] * x_d)).sin())
+                .product(),
+            ManufacturedSolution::Polynomial => x.iter().map(|x_d| x_d * (1.0 - x_d)).product(),
+            ManufacturedSolution::Gaussian => {
+                let r2: f64 = x.iter().map(|x_d| (x_d - GAUSSIAN_CENTER).powi(2)).sum();
+                (-r2 / (2.0 * GAUSSIAN_WIDTH * GAUSSIAN_WIDTH)).exp()
+            }
+            ManufacturedSolution::Periodic => x
+                .iter()
+                .map(|x_d| (PERIODIC_WAVENUMBER * x_d).sin())
+                .product(),
+        }
+    }
+
+    // Gradient at a point
+    pub(crate) fn gradient(self, x: &[Real]) -> [petsc::Scalar; 3] {
+        let mut gradient = [0.0; 3];
+        match self {
+            ManufacturedSolution::Default | ManufacturedSolution::Radial => {
+                let r = self.value(x);
+                if r > 0.0 {
+                    for (d, x_d) in x.iter().enumerate() {
+                        gradient[d] = x_d / r;
+                    }
+                }
+            }
+            ManufacturedSolution::Trig => {
+                for d in 0..x.len() {
+                    gradient[d] = (0..x.len())
+                        .map(|e| {
+                            let theta = PI * (TRIG_PHASE[e] + TRIG_WAVENUMBER[e] * x[e]);
+                            if e == d {
+                                PI * TRIG_WAVENUMBER[e] * theta.cos()
+                            } else {
+                                theta.sin()
+                            }
+                        })
+                        .product();
+                }
+            }
+            ManufacturedSolution::Polynomial => {
+                for d in 0..x.len() {
+                    gradient[d] = (0..x.len())
+                        .map(|e| {
+                            if e == d {
+                                1.0 - 2.0 * x[e]
+                            } else {
+                                x[e] * (1.0 - x[e])
+                            }
+                        })
+                        .product();
+                }
+            }
+            ManufacturedSolution::Gaussian => {
+                let u = self.value(x);
+                for (d, x_d) in x.iter().enumerate() {
+                    gradient[d] = -(x_d - GAUSSIAN_CENTER) / (GAUSSIAN_WIDTH * GAUSSIAN_WIDTH) * u;
+                }
+            }
+            ManufacturedSolution::Periodic => {
+                for d in 0..x.len() {
+                    gradient[d] = (0..x.len())
+                        .map(|e| {
+                            if e == d {
+                                PERIODIC_WAVENUMBER * (PERIODIC_WAVENUMBER * x[e]).cos()
+                            } else {
+                                (PERIODIC_WAVENUMBER * x[e]).sin()
+                            }
+                        })
+                        .product();
+                }
+            }
+        }
+        gradient
+    }
+
+    // Laplacian at a point
+    pub(crate) fn laplacian(self, x: &[Real]) -> petsc::Scalar {
+        let dim = x.len() as f64;
+        match self {
+            ManufacturedSolution::Default | ManufacturedSolution::Radial => {
+                let r = self.value(x);
+                if r > 0.0 {
+                    (dim - 1.0) / r
+                } else {
+                    0.0
+                }
+            }
+            ManufacturedSolution::Trig => {
+                let k2: f64 = TRIG_WAVENUMBER[..x.len()].iter().map(|k| k * k).sum();
+                -PI * PI * k2 * self.value(x)
+            }
+            ManufacturedSolution::Polynomial => (0..x.len())
+                .map(|d| {
+                    -2.0 * (0..x.len())
+                        .filter(|&e| e != d)
+                        .map(|e| x[e] * (1.0 - x[e]))
+                        .product::<f64>()
+                })
+                .sum(),
+            ManufacturedSolution::Gaussian => {
+                let s2 = GAUSSIAN_WIDTH * GAUSSIAN_WIDTH;
+                let r2: f64 = x.iter().map(|x_d| (x_d - GAUSSIAN_CENTER).powi(2)).sum();
+                (r2 / (s2 * s2) - dim / s2) * self.value(x)
+            }
+            ManufacturedSolution::Periodic => {
+                -dim * PERIODIC_WAVENUMBER * PERIODIC_WAVENUMBER * self.value(x)
+            }
+        }
+    }
+
+    // Forcing term for a problem
+    //   Mass problems solve u = f, diffusion problems solve -lap u = f, and
+    //   Helmholtz problems solve -lap u - k^2 u = f
+    pub(crate) fn forcing(
+        self,
+        problem: crate::ceed_bps::CeedBP,
+        wavenumber: f64,
+        x: &[Real],
+    ) -> petsc::Scalar {
+        use crate::ceed_bps::CeedBP;
+        match problem {
+            CeedBP::BP1 | CeedBP::BP2 => self.value(x),
+            CeedBP::Helmholtz => -self.laplacian(x) - wavenumber * wavenumber * self.value(x),
+            _ => -self.laplacian(x),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Exact solution of a problem
+//   The manufactured solution, or the essential boundary value expression
+//   when given, so the forcing and error match the boundary values; the
+//   coefficient expression, if any, scales the operator the forcing is for
+// -----------------------------------------------------------------------------
+#[derive(Clone)]
+pub(crate) enum ExactSolution {
+    Manufactured(ManufacturedSolution),
+    Expression(crate::Expression),
+}
+
+impl ExactSolution {
+    // Value at a point and time
+    pub(crate) fn value(&self, x: &[Real], t: Real) -> petsc::Scalar {
+        match self {
+            ExactSolution::Manufactured(solution) => solution.value(x),
+            ExactSolution::Expression(expression) => expression.evaluate(x, t),
+        }
+    }
+
+    // Gradient at a point and time
+    pub(crate) fn gradient(&self, x: &[Real], t: Real) -> [petsc::Scalar; 3] {
+        match self {
+            ExactSolution::Manufactured(solution) => solution.gradient(x),
+            ExactSolution::Expression(expression) => expression.gradient(x, t),
+        }
+    }
+
+    // Laplacian at a point and time
+    pub(crate) fn laplacian(&self, x: &[Real], t: Real) -> petsc::Scalar {
+        match self {
+            ExactSolution::Manufactured(solution) => solution.laplacian(x),
+            ExactSolution::Expression(expression) => expression.laplacian(x, t),
+        }
+    }
+
+    // Forcing term for a problem with a coefficient c
+    //   Mass problems solve c u = f, diffusion problems solve
+    //   -div(c grad u) = -c lap u - grad c . grad u = f, and Helmholtz
+    //   problems solve -div(c grad u) - k^2 u = f
+    pub(crate) fn forcing(
+        &self,
+        problem: crate::ceed_bps::CeedBP,
+        wavenumber: f64,
+        coefficient: Option<&crate::Expression>,
+        x: &[Real],
+        t: Real,
+    ) -> petsc::Scalar {
+        use crate::ceed_bps::CeedBP;
+        if let (ExactSolution::Manufactured(solution), None) = (self, coefficient) {
+            return solution.forcing(problem, wavenumber, x);
+        }
+        let c = coefficient.map_or(1.0, |coefficient| coefficient.evaluate(x, t));
+        let diffusion = || {
+            let grad_c = coefficient.map_or([0.0; 3], |coefficient| coefficient.gradient(x, t));
+            let grad_u = self.gradient(x, t);
+            -c * self.laplacian(x, t) - grad_c.iter().zip(&grad_u).map(|(a, b)| a * b).sum::<f64>()
+        };
+        match problem {
+            CeedBP::BP1 | CeedBP::BP2 => c * self.value(x, t),
+            CeedBP::Helmholtz => diffusion() - wavenumber * wavenumber * self.value(x, t),
+            _ => diffusion(),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
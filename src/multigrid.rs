@@ -0,0 +1,675 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Level smoothers
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LevelSmoother {
+    Jacobi,
+    L1Jacobi,
+    WeightedJacobi,
+}
+
+impl std::str::FromStr for LevelSmoother {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<LevelSmoother> {
+        match s {
+            "jacobi" => Ok(LevelSmoother::Jacobi),
+            "l1_jacobi" => Ok(LevelSmoother::L1Jacobi),
+            "weighted_jacobi" => Ok(LevelSmoother::WeightedJacobi),
+            _ => Err(crate::Error {
+                message: "failed to parse p-multigrid smoother option".to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for LevelSmoother {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelSmoother::Jacobi => write!(f, "jacobi"),
+            LevelSmoother::L1Jacobi => write!(f, "l1_jacobi"),
+            LevelSmoother::WeightedJacobi => write!(f, "weighted_jacobi"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// p-multigrid command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) smoother: LevelSmoother,
+    pub(crate) smoother_weight: f64,
+    pub(crate) lower_order_pc_order: usize,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let smoother = pob.options_from_string(
+            "-p_multigrid_smoother",
+            "Smoother on levels above the coarsest, jacobi, l1_jacobi, or weighted_jacobi",
+            "",
+            LevelSmoother::Jacobi,
+        )?;
+        let smoother_weight = pob.options_real(
+            "-p_multigrid_smoother_weight",
+            "Damping weight of the weighted_jacobi smoother",
+            "",
+            2.0 / 3.0,
+        )?;
+        let lower_order_pc_order = pob.options_usize(
+            "-lower_order_pc_order",
+            "Order of the assembled rediscretization preconditioning solves, 0 to disable",
+            "",
+            0,
+        )?;
+        Ok(Opt {
+            smoother,
+            smoother_weight,
+            lower_order_pc_order,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Transfer between polynomial orders on the same mesh
+//   Prolongation interpolates coarse element values to the fine nodes and
+//   averages shared nodes by their multiplicity; restriction is the transpose
+// -----------------------------------------------------------------------------
+pub(crate) struct LevelTransfer<'a> {
+    coarse_dm: DM<'a, 'a>,
+    fine_dm: DM<'a, 'a>,
+    op_prolong: crate::MelesOperator<'a>,
+    op_restrict: crate::MelesOperator<'a>,
+    multiplicity_inverse: petsc::vector::Vector<'a>,
+    coarse_loc: RefCell<petsc::vector::Vector<'a>>,
+    coarse_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
+    fine_loc: RefCell<petsc::vector::Vector<'a>>,
+    fine_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
+}
+
+impl crate::petsc_ops::ShellOperations for LevelTransfer<'_> {
+    // Coarse to fine
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut coarse_loc = self.coarse_loc.borrow_mut();
+        let mut fine_loc = self.fine_loc.borrow_mut();
+        coarse_loc.zero_entries()?;
+        check(
+            unsafe {
+                DMGlobalToLocal(
+                    self.coarse_dm.as_raw(),
+                    x,
+                    InsertMode::INSERT_VALUES,
+                    coarse_loc.as_raw(),
+                )
+            },
+            "DMGlobalToLocal",
+        )?;
+        crate::petsc_ops::apply_ceed_op_local(
+            &self.op_prolong,
+            &mut coarse_loc,
+            &mut self.coarse_loc_ceed.borrow_mut(),
+            &mut fine_loc,
+            &mut self.fine_loc_ceed.borrow_mut(),
+        )?;
+        crate::scaling::pointwise_scale(&mut fine_loc, &self.multiplicity_inverse)?;
+        check(unsafe { VecZeroEntries(y) }, "VecZeroEntries")?;
+        check(
+            unsafe {
+                DMLocalToGlobal(
+                    self.fine_dm.as_raw(),
+                    fine_loc.as_raw(),
+                    InsertMode::ADD_VALUES,
+                    y,
+                )
+            },
+            "DMLocalToGlobal",
+        )
+    }
+
+    // Fine to coarse
+    fn mult_transpose(
+        &self,
+        x: petsc::petsc_raw::Vec,
+        y: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut coarse_loc = self.coarse_loc.borrow_mut();
+        let mut fine_loc = self.fine_loc.borrow_mut();
+        fine_loc.zero_entries()?;
+        check(
+            unsafe {
+                DMGlobalToLocal(
+                    self.fine_dm.as_raw(),
+                    x,
+                    InsertMode::INSERT_VALUES,
+                    fine_loc.as_raw(),
+                )
+            },
+            "DMGlobalToLocal",
+        )?;
+        crate::scaling::pointwise_scale(&mut fine_loc, &self.multiplicity_inverse)?;
+        crate::petsc_ops::apply_ceed_op_local(
+            &self.op_restrict,
+            &mut fine_loc,
+            &mut self.fine_loc_ceed.borrow_mut(),
+            &mut coarse_loc,
+            &mut self.coarse_loc_ceed.borrow_mut(),
+        )?;
+        check(unsafe { VecZeroEntries(y) }, "VecZeroEntries")?;
+        check(
+            unsafe {
+                DMLocalToGlobal(
+                    self.coarse_dm.as_raw(),
+                    coarse_loc.as_raw(),
+                    InsertMode::ADD_VALUES,
+                    y,
+                )
+            },
+            "DMLocalToGlobal",
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// p-multigrid hierarchy
+// -----------------------------------------------------------------------------
+/// Hierarchy of polynomial orders for the benchmark problem, with a
+/// matrix-free operator on each level and transfers between levels
+///
+/// Levels are ordered from coarsest, order 1, to finest, as in PCMG. The
+/// hierarchy must outlive any KSP it is set up on.
+pub struct PMultigrid<'a> {
+    orders: Vec<usize>,
+    operators: Vec<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>>,
+    transfers: Vec<petsc::mat::MatShell<'a, 'a, LevelTransfer<'a>>>,
+    smoother: LevelSmoother,
+    smoother_weight: f64,
+    l1_diagonals: Vec<petsc::mat::MatShell<'a, 'a, petsc::vector::Vector<'a>>>,
+}
+
+impl<'a> PMultigrid<'a> {
+    /// Returns the polynomial order of each level, coarsest first
+    pub fn orders(&self) -> &[usize] {
+        &self.orders
+    }
+
+    /// Returns the number of levels
+    pub fn num_levels(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Returns the operator on a level
+    ///
+    /// # arguments
+    ///
+    /// * `level` - Level index, with 0 the coarsest
+    pub fn operator(
+        &self,
+        level: usize,
+    ) -> &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>> {
+        &self.operators[level]
+    }
+
+    /// Set up a KSP to solve on the finest level with PCMG
+    ///
+    /// Levels are smoothed with Chebyshev and Jacobi, and the coarse level
+    /// is solved with CG and Jacobi, so no level needs an assembled matrix.
+    /// With `-p_multigrid_smoother l1_jacobi`, levels are instead smoothed
+    /// with Richardson and Jacobi on the l1 diagonal, the absolute row sums,
+    /// which converges without eigenvalue estimates even on distorted meshes.
+    /// With `-p_multigrid_smoother weighted_jacobi`, levels are smoothed with
+    /// Richardson and Jacobi damped by `-p_multigrid_smoother_weight`.
+    /// Options set from the command line or yml take precedence.
+    ///
+    /// # arguments
+    ///
+    /// * `ksp` - KSP to set up
+    pub fn set_up_ksp(&self, ksp: &mut KSP<'a, '_>) -> crate::Result<()> {
+        let finest = self.operators.last().expect("multigrid has no levels");
+        ksp.set_operators(finest, finest)?;
+        ksp.set_type(KSPType::KSPCG)?;
+        let mg_type = std::ffi::CString::new("mg").unwrap();
+        let chebyshev_type = std::ffi::CString::new("chebyshev").unwrap();
+        let richardson_type = std::ffi::CString::new("richardson").unwrap();
+        let cg_type = std::ffi::CString::new("cg").unwrap();
+        let jacobi_type = std::ffi::CString::new("jacobi").unwrap();
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut pc = std::ptr::null_mut();
+            check(KSPGetPC(ksp.as_raw(), &mut pc), "KSPGetPC")?;
+            check(PCSetType(pc, mg_type.as_ptr()), "PCSetType")?;
+            check(
+                PCMGSetLevels(pc, self.num_levels() as petsc::Int, std::ptr::null_mut()),
+                "PCMGSetLevels",
+            )?;
+            for (level, operator) in self.operators.iter().enumerate() {
+                let mut smoother = std::ptr::null_mut();
+                check(
+                    PCMGGetSmoother(pc, level as petsc::Int, &mut smoother),
+                    "PCMGGetSmoother",
+                )?;
+                // Jacobi on the l1 diagonal takes the diagonal from the
+                //   preconditioning matrix
+                let preconditioning = if level > 0 && self.smoother == LevelSmoother::L1Jacobi {
+                    self.l1_diagonals[level - 1].as_raw()
+                } else {
+                    operator.as_raw()
+                };
+                check(
+                    KSPSetOperators(smoother, operator.as_raw(), preconditioning),
+                    "KSPSetOperators",
+                )?;
+                let ksp_type = match (level, self.smoother) {
+                    (0, _) => &cg_type,
+                    (_, LevelSmoother::Jacobi) => &chebyshev_type,
+                    (_, LevelSmoother::L1Jacobi) | (_, LevelSmoother::WeightedJacobi) => {
+                        &richardson_type
+                    }
+                };
+                check(KSPSetType(smoother, ksp_type.as_ptr()), "KSPSetType")?;
+                if level > 0 && self.smoother == LevelSmoother::WeightedJacobi {
+                    check(
+                        KSPRichardsonSetScale(smoother, self.smoother_weight),
+                        "KSPRichardsonSetScale",
+                    )?;
+                }
+                let mut smoother_pc = std::ptr::null_mut();
+                check(KSPGetPC(smoother, &mut smoother_pc), "KSPGetPC")?;
+                check(PCSetType(smoother_pc, jacobi_type.as_ptr()), "PCSetType")?;
+                if level > 0 {
+                    let transfer = self.transfers[level - 1].as_raw();
+                    check(
+                        PCMGSetInterpolation(pc, level as petsc::Int, transfer),
+                        "PCMGSetInterpolation",
+                    )?;
+                    check(
+                        PCMGSetRestriction(pc, level as petsc::Int, transfer),
+                        "PCMGSetRestriction",
+                    )?;
+                }
+            }
+        }
+        let _options_lock = crate::config::options_lock();
+        ksp.set_from_options()?;
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Polynomial orders of the hierarchy, halving down to order 1, coarsest first
+// -----------------------------------------------------------------------------
+pub(crate) fn level_orders(order: usize) -> Vec<usize> {
+    let mut orders = vec![order];
+    let mut order = order;
+    while order > 1 {
+        order /= 2;
+        orders.push(order);
+    }
+    orders.reverse();
+    orders
+}
+
+// -----------------------------------------------------------------------------
+// Setup the transfer between two orders
+// -----------------------------------------------------------------------------
+fn level_transfer<'a>(
+    meles: &'a crate::Meles<'a>,
+    coarse_options: &crate::ceed_bps::Opt,
+    fine_options: &crate::ceed_bps::Opt,
+) -> crate::Result<petsc::mat::MatShell<'a, 'a, LevelTransfer<'a>>> {
+    let num_components = crate::ceed_bps::num_components(fine_options)?;
+
+    // DMs for each order
+    let mut coarse_dm = meles.dm.borrow().clone();
+    crate::ceed_bps::setup_dm(&mut coarse_dm, coarse_options)?;
+    let mut fine_dm = meles.dm.borrow().clone();
+    crate::ceed_bps::setup_dm(&mut fine_dm, fine_options)?;
+
+    // Restrictions and interpolation from coarse nodes to fine nodes
+    let restr_coarse =
+        crate::dm::create_restriction_from_dm_plex(&coarse_dm, &meles.ceed, 0, None, 0)?;
+    let restr_fine = crate::dm::create_restriction_from_dm_plex(&fine_dm, &meles.ceed, 0, None, 0)?;
+    let dimension = fine_dm.dimension()?;
+    let basis_coarse_to_fine = meles.ceed.basis_tensor_H1_Lagrange(
+        dimension,
+        num_components,
+        coarse_options.order + 1,
+        fine_options.order + 1,
+        QuadMode::GaussLobatto,
+    )?;
+
+    // Identity QFunctions, interpolating and collocated at fine nodes
+    let identity = |[input, ..]: QFunctionInputs, [output, ..]: QFunctionOutputs| {
+        output.copy_from_slice(input);
+        0
+    };
+    let qf_prolong = meles
+        .ceed
+        .q_function_interior(1, Box::new(identity))?
+        .input("input", num_components, EvalMode::Interp)?
+        .output("output", num_components, EvalMode::None)?;
+    let qf_restrict = meles
+        .ceed
+        .q_function_interior(1, Box::new(identity))?
+        .input("input", num_components, EvalMode::None)?
+        .output("output", num_components, EvalMode::Interp)?;
+    let op_prolong = meles
+        .ceed
+        .operator(&qf_prolong, QFunctionOpt::None, QFunctionOpt::None)?
+        .field(
+            "input",
+            &restr_coarse,
+            &basis_coarse_to_fine,
+            VectorOpt::Active,
+        )?
+        .field(
+            "output",
+            &restr_fine,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .check()?;
+    let op_restrict = meles
+        .ceed
+        .operator(&qf_restrict, QFunctionOpt::None, QFunctionOpt::None)?
+        .field(
+            "input",
+            &restr_fine,
+            BasisOpt::Collocated,
+            VectorOpt::Active,
+        )?
+        .field(
+            "output",
+            &restr_coarse,
+            &basis_coarse_to_fine,
+            VectorOpt::Active,
+        )?
+        .check()?;
+
+    // Inverse multiplicity of the fine nodes, over elements and ranks
+    let mut multiplicity_inverse = fine_dm.create_local_vector()?;
+    {
+        let mut multiplicity_ceed = restr_fine.create_lvector()?;
+        let mut ones = restr_fine.create_evector()?;
+        ones.set_value(1.0)?;
+        multiplicity_ceed.set_value(0.0)?;
+        restr_fine.apply(TransposeMode::Transpose, &ones, &mut multiplicity_ceed)?;
+        let mut view = multiplicity_inverse.view_mut()?;
+        view.as_slice_mut()
+            .ok_or_else(|| crate::Error {
+                message: "failed to access local multiplicity values".to_string(),
+            })?
+            .copy_from_slice(&multiplicity_ceed.view()?);
+    }
+    let mut multiplicity = fine_dm.create_global_vector()?;
+    multiplicity.zero_entries()?;
+    fine_dm.local_to_global(
+        &multiplicity_inverse,
+        InsertMode::ADD_VALUES,
+        &mut multiplicity,
+    )?;
+    fine_dm.global_to_local(
+        &multiplicity,
+        InsertMode::INSERT_VALUES,
+        &mut multiplicity_inverse,
+    )?;
+    {
+        let local_size = multiplicity_inverse.local_size()? as usize;
+        let mut view = multiplicity_inverse.view_mut()?;
+        for i in 0..local_size {
+            view[i] = if view[i] > 0.0 { 1.0 / view[i] } else { 0.0 };
+        }
+    }
+
+    // Work vectors
+    let coarse_loc = coarse_dm.create_local_vector()?;
+    let fine_loc = fine_dm.create_local_vector()?;
+    let coarse_loc_ceed = meles.ceed.vector(coarse_loc.local_size()? as usize)?;
+    let fine_loc_ceed = meles.ceed.vector(fine_loc.local_size()? as usize)?;
+
+    // MatShell, fine rows by coarse columns
+    let fine_global = fine_dm.create_global_vector()?;
+    let coarse_global = coarse_dm.create_global_vector()?;
+    let mut mat = petsc::mat::Mat::create(meles.comm)?;
+    mat.set_sizes(
+        Some(fine_global.local_size()?),
+        Some(coarse_global.local_size()?),
+        Some(fine_global.global_size()?),
+        Some(coarse_global.global_size()?),
+    )?;
+    let mat = mat.into_shell(Box::new(LevelTransfer {
+        coarse_dm,
+        fine_dm,
+        op_prolong: op_prolong.into(),
+        op_restrict: op_restrict.into(),
+        multiplicity_inverse,
+        coarse_loc: RefCell::new(coarse_loc),
+        coarse_loc_ceed: RefCell::new(coarse_loc_ceed),
+        fine_loc: RefCell::new(fine_loc),
+        fine_loc_ceed: RefCell::new(fine_loc_ceed),
+    }))?;
+    crate::petsc_ops::set_shell_operations(
+        &mat,
+        &[MatOperation::MATOP_MULT, MatOperation::MATOP_MULT_TRANSPOSE],
+    )?;
+    Ok(mat)
+}
+
+// -----------------------------------------------------------------------------
+// Setup the l1 diagonal on a level
+//   Only the diagonal of the MatShell is defined, for the Jacobi PC
+// -----------------------------------------------------------------------------
+fn l1_diagonal<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<petsc::mat::MatShell<'a, 'a, petsc::vector::Vector<'a>>> {
+    // For an operator with a positive diagonal, a_ii + sum_{j != i} |a_ij|
+    let diagonal = crate::row_sums::row_sums(meles, options)?.absolute_row;
+    let mut mat = petsc::mat::Mat::create(meles.comm)?;
+    mat.set_sizes(
+        Some(diagonal.local_size()?),
+        Some(diagonal.local_size()?),
+        Some(diagonal.global_size()?),
+        Some(diagonal.global_size()?),
+    )?;
+    let mut mat = mat.into_shell(Box::new(diagonal))?;
+    mat.shell_set_operation_mv(MatOperation::MATOP_GET_DIAGONAL, |m, d| {
+        d.copy_data_from(m.mat_data().unwrap())?;
+        Ok(())
+    })?;
+    Ok(mat)
+}
+
+// -----------------------------------------------------------------------------
+// Setup the p-multigrid hierarchy for the benchmark problem
+// -----------------------------------------------------------------------------
+pub(crate) fn p_multigrid<'a>(
+    meles: &'a crate::Meles<'a>,
+    mg_options: &Opt,
+) -> crate::Result<PMultigrid<'a>> {
+    if meles.bp_options.mesh_type != crate::dm::MeshType::Plex
+        || crate::dm::plex_cell_types(&meles.dm.borrow())?.len() > 1
+        || crate::dm::plex_is_simplex(&meles.dm.borrow())?
+    {
+        return Err(crate::Error {
+            message: "p-multigrid requires a plex mesh with a single tensor product cell type"
+                .to_string(),
+        });
+    }
+    crate::backend::require_closure_q_functions(meles, "p-multigrid transfer operators")?;
+
+    let orders = level_orders(meles.bp_options.order);
+    let level_options: Vec<crate::ceed_bps::Opt> = orders
+        .iter()
+        .map(|&order| crate::ceed_bps::Opt {
+            order,
+            ..meles.bp_options.clone()
+        })
+        .collect();
+
+    // Operators on each level
+    let mut operators = vec![];
+    for options in &level_options {
+        let context = crate::ceed_bps::mat_shell_context(meles, options)?;
+        let mat = crate::petsc_ops::create_symmetric_mat_shell(context)?;
+        if let Some(num_components) = crate::ceed_bps::constant_nullspace_components(options)? {
+            crate::petsc_ops::set_constant_nullspace(
+                mat.as_raw(),
+                &mat.mat_data().unwrap().dm.borrow(),
+                num_components,
+            )?;
+        }
+        operators.push(mat);
+    }
+
+    // Transfers between consecutive levels
+    let transfers = level_options
+        .windows(2)
+        .map(|options| level_transfer(meles, &options[0], &options[1]))
+        .collect::<crate::Result<Vec<_>>>()?;
+
+    // l1 diagonals on levels above the coarsest
+    let l1_diagonals = if mg_options.smoother == LevelSmoother::L1Jacobi {
+        level_options[1..]
+            .iter()
+            .map(|options| l1_diagonal(meles, options))
+            .collect::<crate::Result<Vec<_>>>()?
+    } else {
+        vec![]
+    };
+
+    Ok(PMultigrid {
+        orders,
+        operators,
+        transfers,
+        smoother: mg_options.smoother,
+        smoother_weight: mg_options.smoother_weight,
+        l1_diagonals,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Lower order preconditioner
+//   Two level PCMG, smoothing the matrix-free operator and correcting with an
+//   assembled rediscretization at a lower order on the same mesh, so the
+//   operator of the outer KSP is the fine level operator
+// -----------------------------------------------------------------------------
+pub(crate) struct LowerOrderPreconditioner<'a> {
+    coarse: crate::AssembledOperator<'a>,
+    coarse_pc: crate::assembly::AssembledPC,
+    transfer: petsc::mat::MatShell<'a, 'a, LevelTransfer<'a>>,
+}
+
+impl<'a> LowerOrderPreconditioner<'a> {
+    // Set the PC of a KSP, before the KSP is set from options
+    //   The fine level is smoothed with Chebyshev and Jacobi, and the coarse
+    //   level is solved with one application of the assembled preconditioner,
+    //   GAMG by default
+    pub(crate) fn set_up_ksp(&self, ksp: &mut KSP<'a, '_>) -> crate::Result<()> {
+        let mg_type = std::ffi::CString::new("mg").unwrap();
+        let chebyshev_type = std::ffi::CString::new("chebyshev").unwrap();
+        let preonly_type = std::ffi::CString::new("preonly").unwrap();
+        let jacobi_type = std::ffi::CString::new("jacobi").unwrap();
+        let gamg_type = std::ffi::CString::new("gamg").unwrap();
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut pc = std::ptr::null_mut();
+            check(KSPGetPC(ksp.as_raw(), &mut pc), "KSPGetPC")?;
+            check(PCSetType(pc, mg_type.as_ptr()), "PCSetType")?;
+            check(PCMGSetLevels(pc, 2, std::ptr::null_mut()), "PCMGSetLevels")?;
+            for (level, (ksp_type, pc_type)) in
+                [(&preonly_type, &gamg_type), (&chebyshev_type, &jacobi_type)]
+                    .iter()
+                    .enumerate()
+            {
+                let mut smoother = std::ptr::null_mut();
+                check(
+                    PCMGGetSmoother(pc, level as petsc::Int, &mut smoother),
+                    "PCMGGetSmoother",
+                )?;
+                // Level 0 is the coarse level, with the assembled lower order
+                //   operator; the fine level takes the operators of the outer
+                //   KSP
+                if level == 0 {
+                    check(
+                        KSPSetOperators(
+                            smoother,
+                            self.coarse.mat.as_raw(),
+                            self.coarse.mat.as_raw(),
+                        ),
+                        "KSPSetOperators",
+                    )?;
+                }
+                check(KSPSetType(smoother, ksp_type.as_ptr()), "KSPSetType")?;
+                let mut smoother_pc = std::ptr::null_mut();
+                check(KSPGetPC(smoother, &mut smoother_pc), "KSPGetPC")?;
+                check(PCSetType(smoother_pc, pc_type.as_ptr()), "PCSetType")?;
+                if level == 0 {
+                    self.coarse_pc.set_up_pc(smoother_pc)?;
+                }
+            }
+            check(
+                PCMGSetInterpolation(pc, 1, self.transfer.as_raw()),
+                "PCMGSetInterpolation",
+            )?;
+            check(
+                PCMGSetRestriction(pc, 1, self.transfer.as_raw()),
+                "PCMGSetRestriction",
+            )?;
+        }
+        Ok(())
+    }
+
+    // Re-assemble the coarse operator, for a rebuild of the PC
+    pub(crate) fn reassemble(&mut self) -> crate::Result<()> {
+        self.coarse.reassemble()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup the lower order preconditioner for the benchmark problem
+// -----------------------------------------------------------------------------
+pub(crate) fn lower_order_preconditioner<'a>(
+    meles: &'a crate::Meles<'a>,
+    order: usize,
+    coarse_pc: crate::assembly::AssembledPC,
+) -> crate::Result<LowerOrderPreconditioner<'a>> {
+    coarse_pc.check_available()?;
+    if order == 0 || order >= meles.bp_options.order {
+        return Err(crate::Error {
+            message: format!(
+                "lower order preconditioner order must be between 1 and {}, not {}",
+                meles.bp_options.order - 1,
+                order
+            ),
+        });
+    }
+    crate::backend::require_closure_q_functions(meles, "lower order preconditioner transfers")?;
+    let coarse_options = crate::ceed_bps::Opt {
+        order,
+        ..meles.bp_options.clone()
+    };
+
+    // Assembled rediscretization, with the nullspace of the fine operator
+    let coarse = crate::assembly::assembled_operator(meles, &coarse_options, None)?;
+    if let Some(num_components) = crate::ceed_bps::constant_nullspace_components(&coarse_options)? {
+        crate::petsc_ops::set_constant_nullspace(coarse.mat.as_raw(), &coarse.dm, num_components)?;
+    }
+
+    // Transfer between the orders
+    let transfer = level_transfer(meles, &coarse_options, &meles.bp_options)?;
+
+    Ok(LowerOrderPreconditioner {
+        coarse,
+        coarse_pc,
+        transfer,
+    })
+}
+
+// -----------------------------------------------------------------------------
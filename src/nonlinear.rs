@@ -0,0 +1,188 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Nonlinear solver
+// -----------------------------------------------------------------------------
+// Problem owning the residual and Jacobian referenced by the SNES
+enum NonlinearProblem<'a> {
+    Benchmark(std::rc::Rc<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>>),
+    Quasistatic(std::rc::Rc<crate::Quasistatic<'a>>),
+}
+
+/// SNES with a residual backed by a libCEED operator apply and a matrix-free
+/// Jacobian MatShell
+///
+/// For `BenchmarkProblem`s the residual is `A u - b`, with the forcing term
+/// RHS, so the Newton iteration converges in a single step. For
+/// `RatelQuasistatic` problems the residual and Jacobian are those of
+/// `Quasistatic`. The PC is rebuilt every Newton step unless `pc_reuse` or
+/// `NonlinearSolver::set_preconditioner_reuse` lag it.
+pub struct NonlinearSolver<'a> {
+    pub snes: SNES<'a, 'a>,
+    // Dropped after the SNES that references it
+    problem: NonlinearProblem<'a>,
+    pc_reuse: crate::PreconditionerReuse,
+}
+
+// Set the SNES preconditioner lag for a reuse policy
+//   Lags persist across solves, and -1 never rebuilds after the first setup
+fn set_lag(
+    snes: petsc::petsc_raw::SNES,
+    pc_reuse: crate::PreconditionerReuse,
+) -> crate::Result<()> {
+    let lag = match pc_reuse {
+        crate::PreconditionerReuse::Rebuild => 1,
+        crate::PreconditionerReuse::Lag(lag) => lag as petsc::Int,
+        crate::PreconditionerReuse::OnChange => -1,
+    };
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        check(
+            SNESSetLagPreconditioner(snes, lag),
+            "SNESSetLagPreconditioner",
+        )?;
+        check(
+            SNESSetLagPreconditionerPersists(snes, PetscBool::PETSC_TRUE),
+            "SNESSetLagPreconditionerPersists",
+        )
+    }
+}
+
+// Set the quasistatic residual as the SNES function
+//   The context is the address of the problem, which the solver keeps alive
+//   for the lifetime of the SNES
+fn set_snes_function(
+    snes: petsc::petsc_raw::SNES,
+    problem: &std::rc::Rc<crate::Quasistatic<'_>>,
+) -> crate::Result<()> {
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::SNESSetFunction(
+                snes,
+                std::ptr::null_mut(),
+                Some(quasistatic_residual),
+                std::rc::Rc::as_ptr(problem) as *mut std::os::raw::c_void,
+            )
+        },
+        "SNESSetFunction",
+    )
+}
+
+unsafe extern "C" fn quasistatic_residual(
+    _snes: petsc::petsc_raw::SNES,
+    u: petsc::petsc_raw::Vec,
+    r: petsc::petsc_raw::Vec,
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    let problem = &*(ctx as *const crate::Quasistatic);
+    match problem.residual_raw(u, r) {
+        Ok(()) => 0,
+        Err(error) => crate::petsc_callback_error("SNESComputeFunction", error),
+    }
+}
+
+impl<'a> NonlinearSolver<'a> {
+    /// Returns the quasistatic problem, for `RatelQuasistatic` problems
+    pub fn quasistatic(&self) -> Option<&crate::Quasistatic<'a>> {
+        match &self.problem {
+            NonlinearProblem::Quasistatic(problem) => Some(problem),
+            NonlinearProblem::Benchmark(_) => None,
+        }
+    }
+
+    /// Returns a global vector for the solution
+    pub fn create_global_vector(&self) -> petsc::Result<petsc::vector::Vector<'a>> {
+        match &self.problem {
+            NonlinearProblem::Benchmark(mat) => {
+                mat.mat_data().unwrap().dm.borrow().create_global_vector()
+            }
+            NonlinearProblem::Quasistatic(problem) => problem.dm().create_global_vector(),
+        }
+    }
+
+    /// Set when the PC is rebuilt from the Jacobian
+    ///
+    /// `PreconditionerReuse::Lag` rebuilds every this many Newton steps,
+    /// counted across solves.
+    pub fn set_preconditioner_reuse(
+        &mut self,
+        pc_reuse: crate::PreconditionerReuse,
+    ) -> crate::Result<()> {
+        set_lag(self.snes.as_raw(), pc_reuse)?;
+        self.pc_reuse = pc_reuse;
+        Ok(())
+    }
+
+    /// Mark the operator changed, such as after updating a coefficient
+    ///
+    /// With `PreconditionerReuse::OnChange` the PC is rebuilt at the next
+    /// Newton step; other policies rebuild on their own schedule.
+    pub fn mark_operator_changed(&mut self) -> crate::Result<()> {
+        if self.pc_reuse == crate::PreconditionerReuse::OnChange {
+            // -2 rebuilds at the next Newton step, then never again
+            crate::check_petsc_raw(
+                unsafe { petsc::petsc_raw::SNESSetLagPreconditioner(self.snes.as_raw(), -2) },
+                "SNESSetLagPreconditioner",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Solve the nonlinear problem, using the given vector as the initial
+    /// guess
+    ///
+    /// # arguments
+    ///
+    /// * `u` - Initial guess, overwritten with the solution
+    pub fn solve(&mut self, u: &mut petsc::vector::Vector<'a>) -> petsc::Result<()> {
+        self.snes.solve(None, u)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup SNES for the problem of a Meles context
+// -----------------------------------------------------------------------------
+pub(crate) fn snes<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<NonlinearSolver<'a>> {
+    let mut snes = SNES::create(meles.comm)?;
+
+    let problem = match meles.method {
+        crate::MethodType::BenchmarkProblem => {
+            let mat = std::rc::Rc::new(meles.mat_shell(petsc)?);
+            let residual_mat = mat.clone();
+            snes.set_function(None, move |_snes, u, r| {
+                residual_mat.mult(u, r)?;
+                r.axpy(-1.0, residual_mat.mat_data().unwrap().rhs())?;
+                Ok(())
+            })?;
+            crate::petsc_ops::set_snes_jacobian(snes.as_raw(), &mat)?;
+            NonlinearProblem::Benchmark(mat)
+        }
+        crate::MethodType::RatelQuasistatic => {
+            let problem = std::rc::Rc::new(meles.quasistatic(petsc)?);
+            set_snes_function(snes.as_raw(), &problem)?;
+            crate::petsc_ops::set_snes_jacobian(snes.as_raw(), &problem.jacobian)?;
+            NonlinearProblem::Quasistatic(problem)
+        }
+    };
+    let reuse_options: crate::solve::ReuseOpt = {
+        let _options_lock = crate::config::options_lock();
+        snes.set_from_options()?;
+        petsc.options()?
+    };
+    let pc_reuse = reuse_options.pc_reuse(crate::PreconditionerReuse::Rebuild)?;
+    if pc_reuse != crate::PreconditionerReuse::Rebuild {
+        set_lag(snes.as_raw(), pc_reuse)?;
+    }
+
+    Ok(NonlinearSolver {
+        snes,
+        problem,
+        pc_reuse,
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,100 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Solution error norms
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Error of a discrete solution against the exact benchmark problem solution
+pub struct SolutionError {
+    pub l2: f64,
+    pub h1_seminorm: f64,
+    pub h1: f64,
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Solution Error:")?;
+        writeln!(f, "  L2 error          : {:e}", self.l2)?;
+        writeln!(f, "  H1 seminorm error : {:e}", self.h1_seminorm)?;
+        write!(f, "  H1 error          : {:e}", self.h1)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Error operator for a single sub-operator
+//   Maps the local solution to the squared L2 and H1 seminorm error
+//   contributions at each quadrature point
+// -----------------------------------------------------------------------------
+pub(crate) struct ErrorOperator<'a> {
+    pub(crate) op: libceed::operator::Operator<'a>,
+    pub(crate) num_values: usize,
+}
+
+// -----------------------------------------------------------------------------
+// Compute the error of a global solution, reduced over all ranks
+// -----------------------------------------------------------------------------
+pub(crate) fn compute_error<'a>(
+    meles: &'a crate::Meles<'a>,
+    context: &crate::MelesMatShellContext<'a>,
+    solution: &petsc::vector::Vector<'a>,
+) -> crate::Result<SolutionError> {
+    let dm = context.dm.borrow();
+    let mut x_loc = context.x_loc.borrow_mut();
+    // The error operators run on the host Ceed
+    let host_ceed = crate::backend::host_ceed(meles);
+    let mut x_loc_ceed = host_ceed.vector(x_loc.local_size()?)?;
+
+    // Global to local, with essential boundary values
+    dm.global_to_local(solution, InsertMode::INSERT_VALUES, &mut x_loc)?;
+    if crate::ceed_bps::has_essential_boundary(&meles.bp_options)? {
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::DMPlexInsertBoundaryValues(
+                    dm.as_raw(),
+                    petsc::petsc_raw::PetscBool::PETSC_TRUE,
+                    x_loc.as_raw(),
+                    0.0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            },
+            "DMPlexInsertBoundaryValues",
+        )?;
+    }
+
+    // Sum error contributions at quadrature points
+    let mut local_error = [0.0; 2];
+    crate::transfer::with_wrapped(&mut x_loc, &mut x_loc_ceed, |x_loc_ceed| {
+        for error_op in &context.error_ops {
+            let mut error = host_ceed.vector(error_op.num_values)?;
+            error.set_value(0.0)?;
+            error_op.op.apply(x_loc_ceed, &mut error)?;
+            let view = error.view()?;
+            let num_points = error_op.num_values / 2;
+            local_error[0] += view[..num_points].iter().sum::<f64>();
+            local_error[1] += view[num_points..].iter().sum::<f64>();
+        }
+        Ok(())
+    })?;
+
+    // Reduce over ranks
+    let mut global_error = [0.0; 2];
+    {
+        use mpi::collective::{CommunicatorCollectives, SystemOperation};
+        meles.comm.all_reduce_into(
+            &local_error[..],
+            &mut global_error[..],
+            SystemOperation::sum(),
+        );
+    }
+
+    let [l2_squared, h1_seminorm_squared] = global_error;
+    Ok(SolutionError {
+        l2: l2_squared.sqrt(),
+        h1_seminorm: h1_seminorm_squared.sqrt(),
+        h1: (l2_squared + h1_seminorm_squared).sqrt(),
+    })
+}
+
+// -----------------------------------------------------------------------------
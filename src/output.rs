@@ -0,0 +1,72 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// CGNS output
+//   The mesh is written with the first vector, through the DM of the vector,
+//   and the solution as a flow solution on the vertices, through a named
+//   duplicate so the name of the caller's vector is unchanged
+// -----------------------------------------------------------------------------
+const SOLUTION_NAME: &str = "solution";
+
+pub(crate) fn write_cgns(
+    meles: &crate::Meles,
+    path: &std::path::Path,
+    solution: &petsc::vector::Vector,
+) -> crate::Result<()> {
+    if meles.bp_options.mesh_type != crate::dm::MeshType::Plex {
+        return Err(crate::Error {
+            message: format!(
+                "CGNS output requires a plex mesh, not {}",
+                meles.bp_options.mesh_type
+            ),
+        });
+    }
+    if !crate::has_petsc_package("cgns")? {
+        return Err(crate::Error {
+            message: "CGNS output requires PETSc configured with cgns".to_string(),
+        });
+    }
+    let filename =
+        std::ffi::CString::new(path.to_string_lossy().as_ref()).map_err(|_| crate::Error {
+            message: format!("invalid CGNS filename {}", path.display()),
+        })?;
+    let solution_name = std::ffi::CString::new(SOLUTION_NAME).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(solution.as_raw() as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut dm = std::ptr::null_mut();
+        check(VecGetDM(solution.as_raw(), &mut dm), "VecGetDM")?;
+        if dm.is_null() {
+            return Err(crate::Error {
+                message: "CGNS output requires a global vector of the problem DM".to_string(),
+            });
+        }
+        let mut viewer = std::ptr::null_mut();
+        check(
+            PetscViewerCGNSOpen(
+                comm,
+                filename.as_ptr(),
+                PetscFileMode::FILE_MODE_WRITE,
+                &mut viewer,
+            ),
+            "PetscViewerCGNSOpen",
+        )?;
+        let result = (|| {
+            check(DMView(dm, viewer), "DMView")?;
+            let mut named = crate::checkpoint::named_duplicate(solution, &solution_name)?;
+            let result = check(VecCopy(solution.as_raw(), named), "VecCopy")
+                .and_then(|_| check(VecView(named, viewer), "VecView"));
+            check(VecDestroy(&mut named), "VecDestroy")?;
+            result
+        })();
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+// -----------------------------------------------------------------------------
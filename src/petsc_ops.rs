@@ -1,80 +1,765 @@
 use crate::prelude::*;
 
+// -----------------------------------------------------------------------------
+// Create a MatShell from a Meles MatShell context
+// -----------------------------------------------------------------------------
+pub(crate) fn create_mat_shell<'a>(
+    context: crate::MelesMatShellContext<'a>,
+) -> crate::Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+    // Create MatShell from DM
+    let mat = context.dm.borrow().create_matrix()?;
+    let mat = mat.into_shell(Box::new(context))?;
+
+    // Set operations
+    set_shell_operations(
+        &mat,
+        &[MatOperation::MATOP_MULT, MatOperation::MATOP_GET_DIAGONAL],
+    )?;
+    set_mult_add_operation(mat.as_raw(), MatOperation::MATOP_MULT_ADD)?;
+    attach_point_block_inverse(&mat)?;
+
+    Ok(mat)
+}
+
+// Create a MatShell from the context of a symmetric operator
+//   libCEED operators have no transpose apply, so the transpose is registered
+//   only for symmetric operators, such as the benchmark problems, where it is
+//   the apply; KSP types and adjoint solves that require the transpose of
+//   other operators fail as unsupported
+pub(crate) fn create_symmetric_mat_shell<'a>(
+    context: crate::MelesMatShellContext<'a>,
+) -> crate::Result<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>> {
+    let mat = create_mat_shell(context)?;
+    set_shell_operations(&mat, &[MatOperation::MATOP_MULT_TRANSPOSE])?;
+    set_mult_add_operation(mat.as_raw(), MatOperation::MATOP_MULT_TRANSPOSE_ADD)?;
+    Ok(mat)
+}
+
+impl ShellOperations for crate::MelesMatShellContext<'_> {
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        apply_local_ceed_op(x, y, self)
+    }
+
+    fn mult_transpose(
+        &self,
+        x: petsc::petsc_raw::Vec,
+        y: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        apply_local_ceed_op(x, y, self)
+    }
+
+    fn get_diagonal(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        compute_diagonal_ceed(d, self)
+    }
+}
+
+// Register the apply and add, `w = y + A x`, for MatMultAdd or, for symmetric
+//   operators, MatMultTransposeAdd; the operation reads the context composed
+//   with the MatShell, as the point block inverse does
+fn set_mult_add_operation(
+    mat: petsc::petsc_raw::Mat,
+    operation: petsc::petsc_raw::MatOperation,
+) -> crate::Result<()> {
+    use petsc::petsc_raw::*;
+    crate::check_petsc_raw(
+        unsafe {
+            MatShellSetOperation(
+                mat,
+                operation,
+                Some(std::mem::transmute(
+                    mult_add as unsafe extern "C" fn(Mat, Vec, Vec, Vec) -> PetscErrorCode,
+                )),
+            )
+        },
+        "MatShellSetOperation",
+    )
+}
+
+unsafe extern "C" fn mult_add(
+    mat: petsc::petsc_raw::Mat,
+    x: petsc::petsc_raw::Vec,
+    y: petsc::petsc_raw::Vec,
+    w: petsc::petsc_raw::Vec,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    match composed_context::<crate::MelesMatShellContext>(mat) {
+        Some(context) => callback_result("MatMultAdd", apply_add_local_ceed_op(x, y, w, context)),
+        None => PETSC_ERR_PLIB as PetscErrorCode,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// MatShell operations
+//   Operations are registered with MatShellSetOperation and read the context
+//   composed with the MatShell, borrowed for the lifetime of the MatShell, so
+//   errors are reported through PetscError and returned as error codes rather
+//   than unwinding across PETSc
+// -----------------------------------------------------------------------------
+const CONTEXT_NAME: &str = "meles_mat_shell_context";
+
+pub(crate) trait ShellOperations {
+    // y = A x
+    fn mult(&self, _x: petsc::petsc_raw::Vec, _y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        Err(unsupported_operation("MatMult"))
+    }
+
+    // y = A^T x
+    fn mult_transpose(
+        &self,
+        _x: petsc::petsc_raw::Vec,
+        _y: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        Err(unsupported_operation("MatMultTranspose"))
+    }
+
+    // d = diag(A)
+    fn get_diagonal(&self, _d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        Err(unsupported_operation("MatGetDiagonal"))
+    }
+}
+
+fn unsupported_operation(function: &str) -> crate::Error {
+    crate::Error {
+        message: format!("{} is not supported by this MatShell", function),
+    }
+}
+
+impl<T: ShellOperations> ShellOperations for std::rc::Rc<T> {
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        (**self).mult(x, y)
+    }
+
+    fn mult_transpose(
+        &self,
+        x: petsc::petsc_raw::Vec,
+        y: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        (**self).mult_transpose(x, y)
+    }
+
+    fn get_diagonal(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        (**self).get_diagonal(d)
+    }
+}
+
+// Compose the context of a MatShell and register its operations, one of
+//   MATOP_MULT, MATOP_MULT_TRANSPOSE, and MATOP_GET_DIAGONAL each
+pub(crate) fn set_shell_operations<T: ShellOperations>(
+    mat: &petsc::mat::MatShell<'_, '_, T>,
+    operations: &[petsc::petsc_raw::MatOperation],
+) -> crate::Result<()> {
+    let context = mat.mat_data().unwrap();
+    let name = std::ffi::CString::new(CONTEXT_NAME).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mat = mat.as_raw();
+
+        // Context, borrowed for the lifetime of the MatShell
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(mat as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut container = std::ptr::null_mut();
+        check(
+            PetscContainerCreate(comm, &mut container),
+            "PetscContainerCreate",
+        )?;
+        check(
+            PetscContainerSetPointer(container, context as *const T as *mut std::os::raw::c_void),
+            "PetscContainerSetPointer",
+        )?;
+        check(
+            PetscObjectCompose(mat as PetscObject, name.as_ptr(), container as PetscObject),
+            "PetscObjectCompose",
+        )?;
+        check(
+            PetscContainerDestroy(&mut container),
+            "PetscContainerDestroy",
+        )?;
+
+        // Operations
+        for &operation in operations {
+            let function: unsafe extern "C" fn() = match operation {
+                MatOperation::MATOP_MULT => std::mem::transmute(
+                    shell_mult::<T> as unsafe extern "C" fn(Mat, Vec, Vec) -> PetscErrorCode,
+                ),
+                MatOperation::MATOP_MULT_TRANSPOSE => std::mem::transmute(
+                    shell_mult_transpose::<T>
+                        as unsafe extern "C" fn(Mat, Vec, Vec) -> PetscErrorCode,
+                ),
+                MatOperation::MATOP_GET_DIAGONAL => std::mem::transmute(
+                    shell_get_diagonal::<T> as unsafe extern "C" fn(Mat, Vec) -> PetscErrorCode,
+                ),
+                _ => {
+                    return Err(crate::Error {
+                        message: "unsupported MatShell operation".to_string(),
+                    })
+                }
+            };
+            check(
+                MatShellSetOperation(mat, operation, Some(function)),
+                "MatShellSetOperation",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Context composed with a MatShell
+unsafe fn composed_context<'c, T>(mat: petsc::petsc_raw::Mat) -> Option<&'c T> {
+    use petsc::petsc_raw::*;
+    let name = std::ffi::CString::new(CONTEXT_NAME).unwrap();
+    let mut container = std::ptr::null_mut();
+    let mut pointer = std::ptr::null_mut();
+    if PetscObjectQuery(mat as PetscObject, name.as_ptr(), &mut container) != 0
+        || container.is_null()
+        || PetscContainerGetPointer(container as PetscContainer, &mut pointer) != 0
+    {
+        return None;
+    }
+    Some(&*(pointer as *const T))
+}
+
+// Error code of a callback, reporting any error through PetscError
+fn callback_result(function: &str, result: crate::Result<()>) -> petsc::petsc_raw::PetscErrorCode {
+    match result {
+        Ok(()) => 0,
+        Err(error) => crate::petsc_callback_error(function, error),
+    }
+}
+
+unsafe extern "C" fn shell_mult<T: ShellOperations>(
+    mat: petsc::petsc_raw::Mat,
+    x: petsc::petsc_raw::Vec,
+    y: petsc::petsc_raw::Vec,
+) -> petsc::petsc_raw::PetscErrorCode {
+    match composed_context::<T>(mat) {
+        Some(context) => callback_result("MatMult", context.mult(x, y)),
+        None => petsc::petsc_raw::PETSC_ERR_PLIB as petsc::petsc_raw::PetscErrorCode,
+    }
+}
+
+unsafe extern "C" fn shell_mult_transpose<T: ShellOperations>(
+    mat: petsc::petsc_raw::Mat,
+    x: petsc::petsc_raw::Vec,
+    y: petsc::petsc_raw::Vec,
+) -> petsc::petsc_raw::PetscErrorCode {
+    match composed_context::<T>(mat) {
+        Some(context) => callback_result("MatMultTranspose", context.mult_transpose(x, y)),
+        None => petsc::petsc_raw::PETSC_ERR_PLIB as petsc::petsc_raw::PetscErrorCode,
+    }
+}
+
+unsafe extern "C" fn shell_get_diagonal<T: ShellOperations>(
+    mat: petsc::petsc_raw::Mat,
+    d: petsc::petsc_raw::Vec,
+) -> petsc::petsc_raw::PetscErrorCode {
+    match composed_context::<T>(mat) {
+        Some(context) => callback_result("MatGetDiagonal", context.get_diagonal(d)),
+        None => petsc::petsc_raw::PETSC_ERR_PLIB as petsc::petsc_raw::PetscErrorCode,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Attach the inverse point block diagonal for PCPBJACOBI and PCVPBJACOBI
+//   PETSc queries the context composed with the MatShell, and the inverse is
+//   computed on first use and cached with the diagonal; the variable block
+//   sizes are set with the MatShell when -pc_type vpbjacobi is selected, and
+//   PCVPBJACOBI provides the storage of their inverse
+// -----------------------------------------------------------------------------
+fn attach_point_block_inverse(
+    mat: &petsc::mat::MatShell<'_, '_, crate::MelesMatShellContext<'_>>,
+) -> crate::Result<()> {
+    let context = mat.mat_data().unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mat = mat.as_raw();
+
+        // Block size
+        let mut block_size = 0;
+        check(MatGetBlockSize(mat, &mut block_size), "MatGetBlockSize")?;
+        if block_size as usize != context.num_components {
+            check(
+                MatSetBlockSize(mat, context.num_components as petsc::Int),
+                "MatSetBlockSize",
+            )?;
+        }
+
+        // Variable block sizes, read when PCVPBJACOBI is set up, so only
+        //   computed when it is selected
+        if crate::point_blocks::variable_blocks_selected()? {
+            let mut block_sizes = crate::point_blocks::block_sizes(context)?;
+            check(
+                MatSetVariableBlockSizes(
+                    mat,
+                    block_sizes.len() as PetscInt,
+                    block_sizes.as_mut_ptr(),
+                ),
+                "MatSetVariableBlockSizes",
+            )?;
+        }
+
+        // Operations
+        check(
+            MatShellSetOperation(
+                mat,
+                MatOperation::MATOP_INVERT_BLOCK_DIAGONAL,
+                Some(std::mem::transmute(
+                    invert_block_diagonal
+                        as unsafe extern "C" fn(Mat, *mut *const PetscScalar) -> PetscErrorCode,
+                )),
+            ),
+            "MatShellSetOperation",
+        )?;
+        check(
+            MatShellSetOperation(
+                mat,
+                MatOperation::MATOP_INVERT_VBLOCK_DIAGONAL,
+                Some(std::mem::transmute(
+                    invert_variable_block_diagonal
+                        as unsafe extern "C" fn(
+                            Mat,
+                            PetscInt,
+                            *const PetscInt,
+                            *mut PetscScalar,
+                        ) -> PetscErrorCode,
+                )),
+            ),
+            "MatShellSetOperation",
+        )
+    }
+}
+
+unsafe extern "C" fn invert_block_diagonal(
+    mat: petsc::petsc_raw::Mat,
+    values: *mut *const petsc::Scalar,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    let context = match composed_context::<crate::MelesMatShellContext>(mat) {
+        Some(context) => context,
+        None => return PETSC_ERR_PLIB as PetscErrorCode,
+    };
+    let result = compute_point_block_inverse(context).map(|_| {
+        if let Some(inverse) = context.point_block_inverse.borrow().as_ref() {
+            *values = inverse.as_ptr();
+        }
+    });
+    callback_result("MatInvertBlockDiagonal", result)
+}
+
+unsafe extern "C" fn invert_variable_block_diagonal(
+    mat: petsc::petsc_raw::Mat,
+    num_blocks: petsc::Int,
+    block_sizes: *const petsc::Int,
+    values: *mut petsc::Scalar,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    let context = match composed_context::<crate::MelesMatShellContext>(mat) {
+        Some(context) => context,
+        None => return PETSC_ERR_PLIB as PetscErrorCode,
+    };
+    let block_sizes = std::slice::from_raw_parts(block_sizes, num_blocks as usize);
+    let num_values = block_sizes.iter().map(|&size| (size * size) as usize).sum();
+    let values = std::slice::from_raw_parts_mut(values, num_values);
+    callback_result(
+        "MatInvertVariableBlockDiagonal",
+        crate::point_blocks::compute_variable_block_inverse(context, block_sizes, values),
+    )
+}
+
+// -----------------------------------------------------------------------------
+// Use a Meles MatShell as the SNES Jacobian
+//   The libCEED operator is linearized about the state stored by the last
+//   residual evaluation, so the Jacobian callback only discards the cached
+//   diagonals
+// -----------------------------------------------------------------------------
+pub(crate) fn set_snes_jacobian(
+    snes: petsc::petsc_raw::SNES,
+    mat: &petsc::mat::MatShell<'_, '_, crate::MelesMatShellContext<'_>>,
+) -> crate::Result<()> {
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::SNESSetJacobian(
+                snes,
+                mat.as_raw(),
+                mat.as_raw(),
+                Some(update_jacobian),
+                std::ptr::null_mut(),
+            )
+        },
+        "SNESSetJacobian",
+    )
+}
+
+unsafe extern "C" fn update_jacobian(
+    _snes: petsc::petsc_raw::SNES,
+    _x: petsc::petsc_raw::Vec,
+    a: petsc::petsc_raw::Mat,
+    _p: petsc::petsc_raw::Mat,
+    _ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    match composed_context::<crate::MelesMatShellContext>(a) {
+        Some(context) => {
+            context.invalidate_diagonal();
+            0
+        }
+        None => PETSC_ERR_PLIB as PetscErrorCode,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Attach the constant nullspace of a singular operator
+//   Set as both the nullspace and transpose nullspace so KSP removes it from
+//   the RHS and the solution
+// -----------------------------------------------------------------------------
+pub(crate) fn set_constant_nullspace(
+    mat: petsc::petsc_raw::Mat,
+    dm: &DM,
+    num_components: usize,
+) -> crate::Result<()> {
+    let check = crate::check_petsc_raw;
+
+    // One normalized constant vector per component for vector problems
+    let mut vectors = vec![];
+    if num_components > 1 {
+        for component in 0..num_components {
+            let mut vector = dm.create_global_vector()?;
+            {
+                let local_size = vector.local_size()? as usize;
+                let mut view = vector.view_mut()?;
+                for i in 0..local_size {
+                    view[i] = if i % num_components == component {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                }
+            }
+            let norm = vector.norm(NormType::NORM_2)?;
+            vector.scale(1.0 / norm)?;
+            vectors.push(vector);
+        }
+    }
+    let mut raw_vectors: Vec<petsc::petsc_raw::Vec> =
+        vectors.iter().map(|vector| vector.as_raw()).collect();
+
+    unsafe {
+        use petsc::petsc_raw::*;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(mat as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let has_constant = if num_components == 1 {
+            PetscBool::PETSC_TRUE
+        } else {
+            PetscBool::PETSC_FALSE
+        };
+        let mut nullspace = std::ptr::null_mut();
+        check(
+            MatNullSpaceCreate(
+                comm,
+                has_constant,
+                raw_vectors.len() as petsc::Int,
+                raw_vectors.as_mut_ptr(),
+                &mut nullspace,
+            ),
+            "MatNullSpaceCreate",
+        )?;
+        let result = check(MatSetNullSpace(mat, nullspace), "MatSetNullSpace").and_then(|_| {
+            check(
+                MatSetTransposeNullSpace(mat, nullspace),
+                "MatSetTransposeNullSpace",
+            )
+        });
+        check(MatNullSpaceDestroy(&mut nullspace), "MatNullSpaceDestroy")?;
+        result
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Apply the local action of a libCEED operator and store result in PETSc vector
 //   i.e. compute A X = Y
 // -----------------------------------------------------------------------------
-pub(crate) fn apply_local_ceed_op<'a>(
-    x: &petsc::vector::Vector<'a>,
-    y: &mut petsc::vector::Vector<'a>,
+pub(crate) fn apply_local_ceed_op(
+    x: petsc::petsc_raw::Vec,
+    y: petsc::petsc_raw::Vec,
     context: &crate::MelesMatShellContext,
-) -> petsc::Result<()> {
+) -> crate::Result<()> {
+    crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::VecZeroEntries(y) },
+        "VecZeroEntries",
+    )?;
+    apply_add_local_ceed_op(x, y, y, context)
+}
+
+// -----------------------------------------------------------------------------
+// Apply the local action of a libCEED operator and add it to a PETSc vector
+//   i.e. compute W = Y + A X
+//   The local action is summed into W by the local to global scatter, so no
+//   global work vector is needed; Y is copied into W only if they differ, as
+//   they do not for the in-place residual updates of PCMG
+// -----------------------------------------------------------------------------
+pub(crate) fn apply_add_local_ceed_op(
+    x: petsc::petsc_raw::Vec,
+    y: petsc::petsc_raw::Vec,
+    w: petsc::petsc_raw::Vec,
+    context: &crate::MelesMatShellContext,
+) -> crate::Result<()> {
+    use petsc::petsc_raw::*;
+    let check = crate::check_petsc_raw;
+    let dm = context.dm.borrow();
     let mut x_loc = context.x_loc.borrow_mut();
-    let mut x_loc_ceed = context.x_loc_ceed.borrow_mut();
     let mut y_loc = context.y_loc.borrow_mut();
-    let mut y_loc_ceed = context.y_loc_ceed.borrow_mut();
-    // Global to local
-    context
-        .dm
-        .borrow()
-        .global_to_local(x, InsertMode::INSERT_VALUES, &mut x_loc)?;
-    // Apply libCEED operator
-    {
-        let mut x_loc_view = x_loc.view_mut()?;
-        let mut x_loc_view_slice = x_loc_view.as_slice_mut().expect("failed to deref to slice");
-        let _x_loc_wrapper = x_loc_ceed
-            .wrap_slice_mut(&mut x_loc_view_slice)
-            .expect("failed to wrap slice");
-        let mut y_loc_view = y_loc.view_mut()?;
-        let mut y_loc_view_slice = y_loc_view.as_slice_mut().expect("failed to deref to slice");
-        let _y_loc_wrapper = y_loc_ceed
-            .wrap_slice_mut(&mut y_loc_view_slice)
-            .expect("failed to wrap slice");
-
-        context
-            .op_ceed
-            .borrow()
-            .apply(&x_loc_ceed, &mut y_loc_ceed)
-            .expect("failed to apply libCEED operator");
-    }
-    // Local to global
-    y.zero_entries()?;
-    context
-        .dm
-        .borrow()
-        .local_to_global(&y_loc, InsertMode::ADD_VALUES, y)?;
+    unsafe {
+        if y != w {
+            check(VecCopy(y, w), "VecCopy")?;
+        }
+        check(
+            DMGlobalToLocal(dm.as_raw(), x, InsertMode::INSERT_VALUES, x_loc.as_raw()),
+            "DMGlobalToLocal",
+        )?;
+    }
+    if context.single_precision {
+        round_local_to_single(&mut x_loc)?;
+    }
+    y_loc.zero_entries()?;
+    apply_ceed_op_local(
+        &context.op_ceed.borrow(),
+        &mut x_loc,
+        &mut context.x_loc_ceed.borrow_mut(),
+        &mut y_loc,
+        &mut context.y_loc_ceed.borrow_mut(),
+    )?;
+    if context.single_precision {
+        round_local_to_single(&mut y_loc)?;
+    }
+    unsafe {
+        check(
+            DMLocalToGlobal(dm.as_raw(), y_loc.as_raw(), InsertMode::ADD_VALUES, w),
+            "DMLocalToGlobal",
+        )
+    }
+}
+
+// Emulate single precision operators by rounding values to single precision
+//   The libCEED Rust bindings are built with CeedScalar double, so operators
+//   always apply in double; rounding the quadrature data and the local input
+//   and output of each apply gives the accuracy of a single precision apply,
+//   but not its bandwidth savings
+pub(crate) fn round_to_single(vector: &mut libceed::vector::Vector) -> libceed::Result<()> {
+    for value in vector.view_mut()?.iter_mut() {
+        *value = *value as f32 as f64;
+    }
+    Ok(())
+}
+
+fn round_local_to_single(loc: &mut petsc::vector::Vector) -> crate::Result<()> {
+    for value in loc.view_mut()?.iter_mut() {
+        *value = *value as f32 as f64;
+    }
+    Ok(())
+}
+
+// Apply the local action of a libCEED operator using the given work vectors
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_local_ceed_op_with(
+    x: petsc::petsc_raw::Vec,
+    y: petsc::petsc_raw::Vec,
+    dm: &DM,
+    op_ceed: &crate::MelesOperator,
+    x_loc: &mut petsc::vector::Vector,
+    x_loc_ceed: &mut libceed::vector::Vector,
+    y_loc: &mut petsc::vector::Vector,
+    y_loc_ceed: &mut libceed::vector::Vector,
+) -> crate::Result<()> {
+    crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::VecZeroEntries(y) },
+        "VecZeroEntries",
+    )?;
+    crate::transfer::read_local(dm, x, x_loc, x_loc_ceed, |x_loc_ceed| {
+        crate::transfer::add_local(dm, y, y_loc, y_loc_ceed, |y_loc_ceed| {
+            apply_ceed_op(op_ceed, x_loc_ceed, y_loc_ceed)
+        })
+    })
+}
+
+// Apply a libCEED operator to local PETSc vectors
+pub(crate) fn apply_ceed_op_local(
+    op_ceed: &crate::MelesOperator,
+    x_loc: &mut petsc::vector::Vector,
+    x_loc_ceed: &mut libceed::vector::Vector,
+    y_loc: &mut petsc::vector::Vector,
+    y_loc_ceed: &mut libceed::vector::Vector,
+) -> crate::Result<()> {
+    crate::transfer::with_wrapped(x_loc, x_loc_ceed, |x_loc_ceed| {
+        crate::transfer::with_wrapped(y_loc, y_loc_ceed, |y_loc_ceed| {
+            apply_ceed_op(op_ceed, x_loc_ceed, y_loc_ceed)
+        })
+    })
+}
+
+fn apply_ceed_op(
+    op_ceed: &crate::MelesOperator,
+    x_loc_ceed: &libceed::vector::Vector,
+    y_loc_ceed: &mut libceed::vector::Vector,
+) -> crate::Result<()> {
+    let _event = crate::events::Event::OperatorApply.begin();
+    op_ceed.apply(x_loc_ceed, y_loc_ceed)?;
     Ok(())
 }
 
 // -----------------------------------------------------------------------------
 // Compute the diagonal of an operator via libCEED
+//   The diagonal is cached until the operator is replaced
 // -----------------------------------------------------------------------------
-pub(crate) fn compute_diagonal_ceed<'a>(
-    d: &mut petsc::vector::Vector<'a>,
+pub(crate) fn compute_diagonal_ceed(
+    d: petsc::petsc_raw::Vec,
     context: &crate::MelesMatShellContext,
-) -> petsc::Result<()> {
-    let mut x_loc = context.x_loc.borrow_mut();
-    let mut x_loc_ceed = context.x_loc_ceed.borrow_mut();
+) -> crate::Result<()> {
+    use petsc::petsc_raw::*;
+    let check = crate::check_petsc_raw;
+
+    // Use cached diagonal
+    if let Some(diagonal) = context.diagonal.borrow().as_ref() {
+        return check(unsafe { VecCopy(diagonal.as_raw(), d) }, "VecCopy");
+    }
+
     // Get libCEED operator diagonal
-    {
-        let mut x_loc_view = x_loc.view_mut()?;
-        let mut x_loc_view_slice = x_loc_view.as_slice_mut().expect("failed to deref to slice");
-        let _x_loc_wrapper = x_loc_ceed
-            .wrap_slice_mut(&mut x_loc_view_slice)
-            .expect("failed to wrap slice");
-
-        context
-            .op_ceed
-            .borrow()
-            .linear_assemble_diagonal(&mut x_loc_ceed)
-            .expect("failed to compute diagonal of libCEED operator");
-    }
-    // Local to global
-    d.zero_entries()?;
+    let _event = crate::events::Event::Diagonal.begin();
+    let dm = context.dm.borrow();
+    check(unsafe { VecZeroEntries(d) }, "VecZeroEntries")?;
+    crate::transfer::add_local(
+        &dm,
+        d,
+        &mut context.x_loc.borrow_mut(),
+        &mut context.x_loc_ceed.borrow_mut(),
+        |x_loc_ceed| {
+            context
+                .op_ceed
+                .borrow()
+                .linear_assemble_diagonal(x_loc_ceed)?;
+            Ok(())
+        },
+    )?;
+
+    // Cache diagonal
+    let diagonal = dm.create_global_vector()?;
+    check(unsafe { VecCopy(d, diagonal.as_raw()) }, "VecCopy")?;
+    *context.diagonal.borrow_mut() = Some(diagonal);
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Compute the inverse point block diagonal of an operator via libCEED
+//   Blocks for owned nodes are stored contiguously, as PETSc expects from
+//   MatInvertBlockDiagonal
+// -----------------------------------------------------------------------------
+pub(crate) fn compute_point_block_inverse(
+    context: &crate::MelesMatShellContext,
+) -> crate::Result<()> {
+    if context.point_block_inverse.borrow().is_some() {
+        return Ok(());
+    }
+    let _event = crate::events::Event::PointBlockDiagonal.begin();
+    let num_components = context.num_components;
+    let block_size = num_components * num_components;
+    let dm = context.dm.borrow();
+
+    // Local point block diagonal, ordered by node, component out, component in
+    let x_loc_size = context.x_loc.borrow().local_size()? as usize;
+    let mut blocks_loc = context.ceed.vector(x_loc_size * num_components)?;
     context
-        .dm
+        .op_ceed
         .borrow()
-        .local_to_global(&x_loc, InsertMode::ADD_VALUES, d)?;
+        .linear_assemble_point_block_diagonal(&mut blocks_loc)?;
+
+    // Sum over ranks into owned blocks
+    let global = dm.create_global_vector()?;
+    let mut blocks = petsc::vector::Vector::create(dm.comm())?;
+    blocks.set_sizes(
+        Some(global.local_size()? * num_components as petsc::Int),
+        None,
+    )?;
+    blocks.set_up()?;
+    blocks.zero_entries()?;
+    let local_to_global = crate::dm::local_to_global_indices(&dm)?;
+    {
+        let blocks_loc_view = blocks_loc.view()?;
+        let mut indices = vec![];
+        let mut values = vec![];
+        for (dof, &global_dof) in local_to_global.iter().enumerate() {
+            if global_dof < 0 {
+                continue;
+            }
+            for c_in in 0..num_components {
+                indices.push(global_dof * num_components as petsc::Int + c_in as petsc::Int);
+                values.push(blocks_loc_view[dof * num_components + c_in]);
+            }
+        }
+        blocks.set_values(&indices, &values, InsertMode::ADD_VALUES)?;
+    }
+    blocks.assembly_begin()?;
+    blocks.assembly_end()?;
+
+    // Invert each block, stored column major
+    let mut inverse = blocks.view()?.to_vec();
+    for block in inverse.chunks_exact_mut(block_size) {
+        invert_dense(block, num_components).ok_or_else(|| crate::Error {
+            message: "singular point block diagonal".to_string(),
+        })?;
+        for i in 0..num_components {
+            for j in 0..i {
+                block.swap(i * num_components + j, j * num_components + i);
+            }
+        }
+    }
+    *context.point_block_inverse.borrow_mut() = Some(inverse);
     Ok(())
 }
+
+// Invert a small dense matrix in place by Gauss-Jordan elimination with
+//   partial pivoting, returning None if singular
+pub(crate) fn invert_dense(a: &mut [petsc::Scalar], n: usize) -> Option<()> {
+    let mut inverse = vec![0.0; n * n];
+    for i in 0..n {
+        inverse[i * n + i] = 1.0;
+    }
+    for column in 0..n {
+        let pivot = (column..n).max_by(|&i, &j| {
+            a[i * n + column]
+                .abs()
+                .partial_cmp(&a[j * n + column].abs())
+                .unwrap()
+        })?;
+        if a[pivot * n + column] == 0.0 {
+            return None;
+        }
+        for k in 0..n {
+            a.swap(column * n + k, pivot * n + k);
+            inverse.swap(column * n + k, pivot * n + k);
+        }
+        let scale = 1.0 / a[column * n + column];
+        for k in 0..n {
+            a[column * n + k] *= scale;
+            inverse[column * n + k] *= scale;
+        }
+        for row in 0..n {
+            if row != column {
+                let factor = a[row * n + column];
+                for k in 0..n {
+                    a[row * n + k] -= factor * a[column * n + k];
+                    inverse[row * n + k] -= factor * inverse[column * n + k];
+                }
+            }
+        }
+    }
+    a.copy_from_slice(&inverse);
+    Some(())
+}
+
 // -----------------------------------------------------------------------------
@@ -0,0 +1,461 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Variable point blocks for PCVPBJACOBI
+//   One block per node of each field of the local section, holding the
+//   components of the field that are not constrained by essential boundary
+//   conditions, so block sizes follow the component counts of multi-field DMs
+//   and partially constrained nodes; blocks of owned nodes tile the owned rows
+//   in order, as PETSc requires of variable block sizes, and local nodes that
+//   share global DoFs, such as across the faces of periodic meshes, give one
+//   block, with the local entries of each summed
+// -----------------------------------------------------------------------------
+struct NodeBlock {
+    // Mesh point of the node
+    point: petsc::Int,
+    // Local DoF of the first component of the node
+    node_start: usize,
+    // Unconstrained components of the node
+    components: Vec<usize>,
+    // Global DoFs of the unconstrained components
+    global_dofs: Vec<petsc::Int>,
+    owned: bool,
+}
+
+impl NodeBlock {
+    fn dofs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.components
+            .iter()
+            .map(move |component| self.node_start + component)
+    }
+}
+
+// Local section layout of the node blocks
+struct Layout {
+    blocks: Vec<NodeBlock>,
+    // Indices of the blocks of owned nodes, one per node, in the global
+    //   ordering
+    owned: Vec<usize>,
+    // Local DoF offset and number of DoFs of each point with DoFs
+    points: Vec<(petsc::Int, usize, usize)>,
+    // Largest number of components of a field
+    max_components: usize,
+    // True if every field has the components of the libCEED operator
+    uniform: bool,
+}
+
+fn is_plex(dm: &DM) -> crate::Result<bool> {
+    let plex = std::ffi::CString::new("plex").unwrap();
+    let mut same = petsc::petsc_raw::PetscBool::PETSC_FALSE;
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::PetscObjectTypeCompare(
+                dm.as_raw() as petsc::petsc_raw::PetscObject,
+                plex.as_ptr(),
+                &mut same,
+            )
+        },
+        "PetscObjectTypeCompare",
+    )?;
+    Ok(same == petsc::petsc_raw::PetscBool::PETSC_TRUE)
+}
+
+fn layout(dm: &DM, num_components: usize) -> crate::Result<Layout> {
+    let local_to_global = crate::dm::local_to_global_indices(dm)?;
+    let owned_range = dm.create_global_vector()?.get_ownership_range()?;
+    let mut layout = Layout {
+        blocks: vec![],
+        owned: vec![],
+        points: vec![],
+        max_components: 0,
+        uniform: true,
+    };
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut section = std::ptr::null_mut();
+        check(
+            DMGetLocalSection(dm.as_raw(), &mut section),
+            "DMGetLocalSection",
+        )?;
+        let (mut chart_start, mut chart_end) = (0, 0);
+        check(
+            PetscSectionGetChart(section, &mut chart_start, &mut chart_end),
+            "PetscSectionGetChart",
+        )?;
+        let mut num_fields = 0;
+        check(
+            PetscSectionGetNumFields(section, &mut num_fields),
+            "PetscSectionGetNumFields",
+        )?;
+
+        // Components of each field, or of the operator for sections without
+        //   fields
+        let mut field_components = vec![];
+        for field in 0..num_fields {
+            let mut components = 0;
+            check(
+                PetscSectionGetFieldComponents(section, field, &mut components),
+                "PetscSectionGetFieldComponents",
+            )?;
+            field_components.push(components as usize);
+        }
+        if field_components.is_empty() {
+            field_components.push(num_components);
+        }
+        layout.max_components = field_components.iter().cloned().max().unwrap_or(1);
+        layout.uniform = field_components.iter().all(|&c| c == num_components);
+
+        for point in chart_start..chart_end {
+            let (mut dof, mut offset) = (0, 0);
+            check(
+                PetscSectionGetDof(section, point, &mut dof),
+                "PetscSectionGetDof",
+            )?;
+            if dof == 0 {
+                continue;
+            }
+            check(
+                PetscSectionGetOffset(section, point, &mut offset),
+                "PetscSectionGetOffset",
+            )?;
+            layout.points.push((point, offset as usize, dof as usize));
+            for (field, &components) in field_components.iter().enumerate() {
+                let (mut field_dof, mut field_offset) = (dof, offset);
+                if num_fields > 0 {
+                    check(
+                        PetscSectionGetFieldDof(section, point, field as PetscInt, &mut field_dof),
+                        "PetscSectionGetFieldDof",
+                    )?;
+                    check(
+                        PetscSectionGetFieldOffset(
+                            section,
+                            point,
+                            field as PetscInt,
+                            &mut field_offset,
+                        ),
+                        "PetscSectionGetFieldOffset",
+                    )?;
+                }
+                for node in 0..field_dof as usize / components {
+                    let node_start = field_offset as usize + node * components;
+                    let components = (0..components)
+                        .filter(|component| local_to_global[node_start + component] >= 0)
+                        .collect::<Vec<_>>();
+                    if components.is_empty() {
+                        continue;
+                    }
+                    let global_dofs = components
+                        .iter()
+                        .map(|component| local_to_global[node_start + component])
+                        .collect::<Vec<_>>();
+                    let owned = owned_range.contains(&global_dofs[0]);
+                    layout.blocks.push(NodeBlock {
+                        point,
+                        node_start,
+                        components,
+                        global_dofs,
+                        owned,
+                    });
+                }
+            }
+        }
+    }
+
+    // Owned blocks, one per global node, must tile the owned rows
+    let mut owned = (0..layout.blocks.len())
+        .filter(|&b| layout.blocks[b].owned)
+        .collect::<Vec<_>>();
+    owned.sort_by_key(|&b| layout.blocks[b].global_dofs[0]);
+    owned.dedup_by_key(|b| layout.blocks[*b].global_dofs[0]);
+    let mut next = owned_range.start;
+    for &b in &owned {
+        for &global_dof in &layout.blocks[b].global_dofs {
+            if global_dof != next {
+                return Err(crate::Error {
+                    message: "node DoFs are not contiguous in the global ordering".to_string(),
+                });
+            }
+            next += 1;
+        }
+    }
+    if next != owned_range.end {
+        return Err(crate::Error {
+            message: "node blocks do not cover the owned DoFs".to_string(),
+        });
+    }
+    layout.owned = owned;
+    Ok(layout)
+}
+
+// Returns true if -pc_type vpbjacobi is selected, so MatShells set their
+//   variable block sizes
+pub(crate) fn variable_blocks_selected() -> crate::Result<bool> {
+    let pc_type_name = std::ffi::CString::new("-pc_type").unwrap();
+    let pc_type = {
+        let _options_lock = crate::config::options_lock();
+        crate::config::options_get_string(&pc_type_name)?
+    };
+    Ok(pc_type.as_deref() == Some("vpbjacobi"))
+}
+
+// Sizes of the owned blocks, in the global ordering; DMDA and DMStag meshes
+//   use blocks of every component of each node
+pub(crate) fn block_sizes(context: &crate::MelesMatShellContext) -> crate::Result<Vec<petsc::Int>> {
+    let dm = context.dm.borrow();
+    if !is_plex(&dm)? {
+        let local_size = dm.create_global_vector()?.local_size()? as usize;
+        return Ok(vec![
+            context.num_components as petsc::Int;
+            local_size / context.num_components
+        ]);
+    }
+    let layout = layout(&dm, context.num_components)?;
+    Ok(layout
+        .owned
+        .iter()
+        .map(|&b| layout.blocks[b].components.len() as petsc::Int)
+        .collect())
+}
+
+// -----------------------------------------------------------------------------
+// Local node block entries
+//   Operators with the components of every field use the libCEED point block
+//   diagonal; otherwise, the diagonal blocks are probed through the local
+//   operator, with points colored so that no two points of one color share a
+//   local cell and one probe for each DoF of a point in each color
+// -----------------------------------------------------------------------------
+// Entries of each local block, row major
+fn local_block_entries(
+    context: &crate::MelesMatShellContext,
+    dm: &DM,
+    layout: &Layout,
+) -> crate::Result<Vec<Vec<petsc::Scalar>>> {
+    let mut entries = layout
+        .blocks
+        .iter()
+        .map(|block| vec![0.0; block.components.len().pow(2)])
+        .collect::<Vec<_>>();
+
+    // libCEED point block diagonal, ordered by node, component out, component in
+    if layout.uniform {
+        let num_components = context.num_components;
+        let x_loc_size = context.x_loc.borrow().local_size()? as usize;
+        let mut blocks_loc = context.ceed.vector(x_loc_size * num_components)?;
+        context
+            .op_ceed
+            .borrow()
+            .linear_assemble_point_block_diagonal(&mut blocks_loc)?;
+        let blocks_loc_view = blocks_loc.view()?;
+        for (block, entries) in layout.blocks.iter().zip(entries.iter_mut()) {
+            let size = block.components.len();
+            for (i, row) in block.dofs().enumerate() {
+                for (j, &component) in block.components.iter().enumerate() {
+                    entries[i * size + j] = blocks_loc_view[row * num_components + component];
+                }
+            }
+        }
+        return Ok(entries);
+    }
+
+    // Greedy coloring of points with DoFs by the local cells sharing them
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(dm)?;
+    let point_index = layout
+        .points
+        .iter()
+        .enumerate()
+        .map(|(index, &(point, _, _))| (point, index))
+        .collect::<std::collections::HashMap<_, _>>();
+    let mut cell_points = vec![];
+    let mut point_cells = vec![vec![]; layout.points.len()];
+    for cell in cell_start..cell_end {
+        let closure = unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut closure_size = 0;
+            let mut closure = std::ptr::null_mut();
+            check(
+                DMPlexGetTransitiveClosure(
+                    dm.as_raw(),
+                    cell,
+                    PetscBool::PETSC_TRUE,
+                    &mut closure_size,
+                    &mut closure,
+                ),
+                "DMPlexGetTransitiveClosure",
+            )?;
+            let points = std::slice::from_raw_parts(closure, 2 * closure_size as usize)
+                .iter()
+                .step_by(2)
+                .filter_map(|point| point_index.get(point).cloned())
+                .collect::<Vec<_>>();
+            check(
+                DMPlexRestoreTransitiveClosure(
+                    dm.as_raw(),
+                    cell,
+                    PetscBool::PETSC_TRUE,
+                    &mut closure_size,
+                    &mut closure,
+                ),
+                "DMPlexRestoreTransitiveClosure",
+            )?;
+            points
+        };
+        for &index in &closure {
+            point_cells[index].push(cell_points.len());
+        }
+        cell_points.push(closure);
+    }
+    let mut colors: Vec<Option<usize>> = vec![None; layout.points.len()];
+    for index in 0..layout.points.len() {
+        let taken = point_cells[index]
+            .iter()
+            .flat_map(|&cell| cell_points[cell].iter())
+            .filter_map(|&neighbor| colors[neighbor])
+            .collect::<std::collections::HashSet<_>>();
+        colors[index] = (0..).find(|color| !taken.contains(color));
+    }
+    let num_colors = colors.iter().flatten().max().map_or(0, |color| color + 1);
+    let max_point_dofs = layout
+        .points
+        .iter()
+        .map(|&(_, _, dof)| dof)
+        .max()
+        .unwrap_or(0);
+
+    // Blocks by point
+    let mut point_blocks = vec![vec![]; layout.points.len()];
+    for (b, block) in layout.blocks.iter().enumerate() {
+        point_blocks[point_index[&block.point]].push(b);
+    }
+
+    // Probe the k-th DoF of every point of each color
+    let mut x_loc = context.x_loc.borrow_mut();
+    let mut y_loc = context.y_loc.borrow_mut();
+    for color in 0..num_colors {
+        for k in 0..max_point_dofs {
+            let probed = (0..layout.points.len())
+                .filter(|&index| colors[index] == Some(color) && k < layout.points[index].2)
+                .collect::<Vec<_>>();
+            if probed.is_empty() {
+                continue;
+            }
+            x_loc.zero_entries()?;
+            {
+                let mut x_loc_view = x_loc.view_mut()?;
+                for &index in &probed {
+                    x_loc_view[layout.points[index].1 + k] = 1.0;
+                }
+            }
+            crate::petsc_ops::apply_ceed_op_local(
+                &context.op_ceed.borrow(),
+                &mut x_loc,
+                &mut context.x_loc_ceed.borrow_mut(),
+                &mut y_loc,
+                &mut context.y_loc_ceed.borrow_mut(),
+            )?;
+            let y_loc_view = y_loc.view()?;
+            for &index in &probed {
+                let column_dof = layout.points[index].1 + k;
+                for &b in &point_blocks[index] {
+                    let block = &layout.blocks[b];
+                    let size = block.components.len();
+                    if let Some(j) = block.dofs().position(|dof| dof == column_dof) {
+                        for (i, row) in block.dofs().enumerate() {
+                            entries[b][i * size + j] = y_loc_view[row];
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// -----------------------------------------------------------------------------
+// Compute the inverse variable point block diagonal of an operator
+//   Local blocks are summed over ranks into rows of a global vector, padded to
+//   the largest field, and each owned block is inverted and stored column
+//   major, as PETSc expects from MatInvertVariableBlockDiagonal
+// -----------------------------------------------------------------------------
+pub(crate) fn compute_variable_block_inverse(
+    context: &crate::MelesMatShellContext,
+    block_sizes: &[petsc::Int],
+    inverse: &mut [petsc::Scalar],
+) -> crate::Result<()> {
+    // Uniform blocks share the point block diagonal inverse
+    if block_sizes
+        .iter()
+        .all(|&size| size as usize == context.num_components)
+    {
+        crate::petsc_ops::compute_point_block_inverse(context)?;
+        inverse.copy_from_slice(
+            context
+                .point_block_inverse
+                .borrow()
+                .as_ref()
+                .expect("point block inverse computed"),
+        );
+        return Ok(());
+    }
+    let _event = crate::events::Event::PointBlockDiagonal.begin();
+    let dm = context.dm.borrow();
+    let layout = layout(&dm, context.num_components)?;
+    let entries = local_block_entries(context, &dm, &layout)?;
+
+    // Sum over ranks into owned rows
+    let width = layout.max_components;
+    let global = dm.create_global_vector()?;
+    let start = global.get_ownership_range()?.start;
+    let mut rows = petsc::vector::Vector::create(dm.comm())?;
+    rows.set_sizes(Some(global.local_size()? * width as petsc::Int), None)?;
+    rows.set_up()?;
+    rows.zero_entries()?;
+    {
+        let mut indices = vec![];
+        let mut values = vec![];
+        for (block, entries) in layout.blocks.iter().zip(&entries) {
+            let size = block.components.len();
+            for (i, &global_dof) in block.global_dofs.iter().enumerate() {
+                for j in 0..size {
+                    indices.push(global_dof * width as petsc::Int + j as petsc::Int);
+                    values.push(entries[i * size + j]);
+                }
+            }
+        }
+        rows.set_values(&indices, &values, InsertMode::ADD_VALUES)?;
+    }
+    rows.assembly_begin()?;
+    rows.assembly_end()?;
+
+    // Invert each owned block, stored column major
+    let rows_view = rows.view()?;
+    let mut offset = 0;
+    for (block, &size) in layout
+        .owned
+        .iter()
+        .map(|&b| &layout.blocks[b])
+        .zip(block_sizes)
+    {
+        let size = size as usize;
+        if size != block.components.len() {
+            return Err(crate::Error {
+                message: "variable block sizes do not match the DM".to_string(),
+            });
+        }
+        let first_row = (block.global_dofs[0] - start) as usize;
+        let values = &mut inverse[offset..offset + size * size];
+        for i in 0..size {
+            for j in 0..size {
+                values[j * size + i] = rows_view[(first_row + i) * width + j];
+            }
+        }
+        crate::petsc_ops::invert_dense(values, size).ok_or_else(|| crate::Error {
+            message: "singular variable point block diagonal".to_string(),
+        })?;
+        offset += size * size;
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,281 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Probing
+//   Columns are colored by a distance 2 coloring of the sparsity pattern from
+//   the DM adjacency, so no two columns of one color share a row; one
+//   operator application per color recovers every entry of the pattern
+// -----------------------------------------------------------------------------
+// Colors of the owned columns from a distance 2 coloring of a matrix, with
+//   the global number of colors
+fn distance_two_coloring(mat: petsc::petsc_raw::Mat) -> crate::Result<(Vec<usize>, usize)> {
+    let coloring_type = std::ffi::CString::new("greedy").unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut coloring = std::ptr::null_mut();
+        check(MatColoringCreate(mat, &mut coloring), "MatColoringCreate")?;
+        let mut is_coloring = std::ptr::null_mut();
+        let result = check(
+            MatColoringSetDistance(coloring, 2),
+            "MatColoringSetDistance",
+        )
+        .and_then(|_| {
+            check(
+                MatColoringSetType(coloring, coloring_type.as_ptr()),
+                "MatColoringSetType",
+            )
+        })
+        .and_then(|_| {
+            check(
+                MatColoringApply(coloring, &mut is_coloring),
+                "MatColoringApply",
+            )
+        });
+        check(MatColoringDestroy(&mut coloring), "MatColoringDestroy")?;
+        result?;
+        let mut num_local = 0;
+        let mut num_colors = 0;
+        let mut colors = std::ptr::null();
+        let result = check(
+            ISColoringGetColors(is_coloring, &mut num_local, &mut num_colors, &mut colors),
+            "ISColoringGetColors",
+        )
+        .map(|_| {
+            std::slice::from_raw_parts(colors, num_local as usize)
+                .iter()
+                .map(|&color| color as usize)
+                .collect::<Vec<_>>()
+        });
+        check(ISColoringDestroy(&mut is_coloring), "ISColoringDestroy")?;
+        Ok((result?, num_colors as usize))
+    }
+}
+
+// Global columns of each owned row of an assembled matrix
+fn row_columns(mat: petsc::petsc_raw::Mat) -> crate::Result<Vec<Vec<petsc::Int>>> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let (mut row_start, mut row_end) = (0, 0);
+        check(
+            MatGetOwnershipRange(mat, &mut row_start, &mut row_end),
+            "MatGetOwnershipRange",
+        )?;
+        let mut columns = Vec::with_capacity((row_end - row_start) as usize);
+        for row in row_start..row_end {
+            let mut num_columns = 0;
+            let mut row_columns = std::ptr::null();
+            check(
+                MatGetRow(
+                    mat,
+                    row,
+                    &mut num_columns,
+                    &mut row_columns,
+                    std::ptr::null_mut(),
+                ),
+                "MatGetRow",
+            )?;
+            columns.push(std::slice::from_raw_parts(row_columns, num_columns as usize).to_vec());
+            check(
+                MatRestoreRow(
+                    mat,
+                    row,
+                    &mut num_columns,
+                    &mut row_columns,
+                    std::ptr::null_mut(),
+                ),
+                "MatRestoreRow",
+            )?;
+        }
+        Ok(columns)
+    }
+}
+
+// Values of a global vector at global indices, including off-rank indices
+fn gather(vector: &petsc::vector::Vector, indices: &[petsc::Int]) -> crate::Result<Vec<f64>> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut index_set = std::ptr::null_mut();
+        check(
+            ISCreateGeneral(
+                PETSC_COMM_SELF,
+                indices.len() as petsc::Int,
+                indices.as_ptr(),
+                PetscCopyMode::PETSC_COPY_VALUES,
+                &mut index_set,
+            ),
+            "ISCreateGeneral",
+        )?;
+        let mut values = std::ptr::null_mut();
+        let result = check(
+            VecCreateSeq(PETSC_COMM_SELF, indices.len() as petsc::Int, &mut values),
+            "VecCreateSeq",
+        );
+        if let Err(err) = result {
+            check(ISDestroy(&mut index_set), "ISDestroy")?;
+            return Err(err);
+        }
+        let mut scatter = std::ptr::null_mut();
+        let result = check(
+            VecScatterCreate(
+                vector.as_raw(),
+                index_set,
+                values,
+                std::ptr::null_mut(),
+                &mut scatter,
+            ),
+            "VecScatterCreate",
+        )
+        .and_then(|_| {
+            check(
+                VecScatterBegin(
+                    scatter,
+                    vector.as_raw(),
+                    values,
+                    InsertMode::INSERT_VALUES,
+                    ScatterMode::SCATTER_FORWARD,
+                ),
+                "VecScatterBegin",
+            )
+        })
+        .and_then(|_| {
+            check(
+                VecScatterEnd(
+                    scatter,
+                    vector.as_raw(),
+                    values,
+                    InsertMode::INSERT_VALUES,
+                    ScatterMode::SCATTER_FORWARD,
+                ),
+                "VecScatterEnd",
+            )
+        })
+        .and_then(|_| {
+            let mut array = std::ptr::null();
+            check(VecGetArrayRead(values, &mut array), "VecGetArrayRead")?;
+            let gathered = std::slice::from_raw_parts(array, indices.len()).to_vec();
+            check(
+                VecRestoreArrayRead(values, &mut array),
+                "VecRestoreArrayRead",
+            )?;
+            Ok(gathered)
+        });
+        if !scatter.is_null() {
+            check(VecScatterDestroy(&mut scatter), "VecScatterDestroy")?;
+        }
+        check(VecDestroy(&mut values), "VecDestroy")?;
+        check(ISDestroy(&mut index_set), "ISDestroy")?;
+        result
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup the probed AIJ matrix
+// -----------------------------------------------------------------------------
+pub(crate) fn probed_matrix<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    drop_tolerance: f64,
+) -> crate::Result<petsc::mat::Mat<'a, 'a>> {
+    let context = crate::ceed_bps::mat_shell_context(meles, options)?;
+    // Sparsity pattern of the operator from the DM adjacency, with zeros
+    let pattern = context.dm.borrow().create_matrix()?;
+    let operator = crate::petsc_ops::create_mat_shell(context)?;
+    let mut x = operator.mat_data().unwrap().rhs().duplicate()?;
+    let mut y = x.duplicate()?;
+    let start = x.get_ownership_range()?.start as usize;
+    let local_size = x.local_size()? as usize;
+    let global_size = x.global_size()? as usize;
+
+    // Colors of the columns in each owned row
+    let (colors, num_colors) = distance_two_coloring(pattern.as_raw())?;
+    let columns = row_columns(pattern.as_raw())?;
+    drop(pattern);
+    let column_colors = {
+        let mut color_vector = x.duplicate()?;
+        {
+            let mut color_view = color_vector.view_mut()?;
+            for (value, &color) in color_view.iter_mut().zip(&colors) {
+                *value = color as f64;
+            }
+        }
+        let indices: Vec<petsc::Int> = columns.iter().flatten().copied().collect();
+        gather(&color_vector, &indices)?
+    };
+
+    // Probe each color, storing the entry of that color in each owned row
+    let mut entries: Vec<Vec<(petsc::Int, f64)>> = vec![vec![]; local_size];
+    for color in 0..num_colors {
+        {
+            let mut x_view = x.view_mut()?;
+            for (value, &column_color) in x_view.iter_mut().zip(&colors) {
+                *value = if column_color == color { 1.0 } else { 0.0 };
+            }
+        }
+        operator.mult(&x, &mut y)?;
+        let y_view = y.view()?;
+        let mut row_colors = column_colors.iter();
+        for ((row_entries, row_columns), &value) in
+            entries.iter_mut().zip(&columns).zip(y_view.iter())
+        {
+            for &column in row_columns {
+                if *row_colors.next().unwrap() as usize == color {
+                    row_entries.push((column, value));
+                }
+            }
+        }
+    }
+
+    // Drop small off-diagonal entries, relative to the diagonal of the row
+    let mut rows: Vec<petsc::Int> = vec![];
+    let mut cols: Vec<petsc::Int> = vec![];
+    let mut values: Vec<f64> = vec![];
+    for (k, row_entries) in entries.iter().enumerate() {
+        let row = (start + k) as petsc::Int;
+        let diagonal = row_entries
+            .iter()
+            .find(|&&(column, _)| column == row)
+            .map(|&(_, value)| value.abs())
+            .unwrap_or(0.0);
+        for &(column, value) in row_entries {
+            if column == row || value.abs() > drop_tolerance * diagonal {
+                rows.push(row);
+                cols.push(column);
+                values.push(value);
+            }
+        }
+    }
+
+    // AIJ matrix with the kept entries
+    let aij_type = std::ffi::CString::new("aij").unwrap();
+    let mut mat = petsc::mat::Mat::create(meles.comm)?;
+    mat.set_sizes(
+        Some(local_size as petsc::Int),
+        Some(local_size as petsc::Int),
+        Some(global_size as petsc::Int),
+        Some(global_size as petsc::Int),
+    )?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        check(MatSetType(mat.as_raw(), aij_type.as_ptr()), "MatSetType")?;
+        check(
+            MatSetPreallocationCOO(
+                mat.as_raw(),
+                rows.len() as PetscCount,
+                rows.as_mut_ptr(),
+                cols.as_mut_ptr(),
+            ),
+            "MatSetPreallocationCOO",
+        )?;
+        check(
+            MatSetValuesCOO(mat.as_raw(), values.as_ptr(), InsertMode::INSERT_VALUES),
+            "MatSetValuesCOO",
+        )?;
+    }
+    Ok(mat)
+}
+
+// -----------------------------------------------------------------------------
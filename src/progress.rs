@@ -0,0 +1,116 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Setup phases
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Phase of problem setup reported to progress callbacks
+pub enum SetupPhase {
+    /// Mesh creation, distribution, and refinement
+    Mesh,
+    /// Finite element space and boundary condition setup on the mesh
+    Section,
+    /// libCEED element restriction construction
+    Restrictions,
+    /// Geometric quadrature data assembly
+    QData,
+    /// Forcing term and exact solution assembly
+    Rhs,
+}
+
+impl fmt::Display for SetupPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetupPhase::Mesh => write!(f, "mesh"),
+            SetupPhase::Section => write!(f, "section"),
+            SetupPhase::Restrictions => write!(f, "restrictions"),
+            SetupPhase::QData => write!(f, "qdata"),
+            SetupPhase::Rhs => write!(f, "rhs"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Completed setup phase, with the number of local elements processed
+pub struct SetupProgress {
+    pub phase: SetupPhase,
+    pub elements: Option<usize>,
+    pub elapsed: std::time::Duration,
+}
+
+impl fmt::Display for SetupProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "setup {}", self.phase)?;
+        if let Some(elements) = self.elements {
+            write!(f, ", {} elements", elements)?;
+        }
+        write!(f, ", {:.3} s", self.elapsed.as_secs_f64())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Progress command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) log: bool,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let log = pob.options_bool(
+            "-meles_progress",
+            "Log each completed setup phase on rank 0",
+            "",
+            false,
+        )?;
+        Ok(Opt { log })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Progress reporter
+//   Each rank reports its local element counts to the callback, and only rank
+//   0 logs so large runs are not flooded with messages
+// -----------------------------------------------------------------------------
+pub(crate) struct ProgressReporter<'a> {
+    pub(crate) log: bool,
+    rank: i32,
+    callback: RefCell<Option<Box<dyn Fn(&SetupProgress) + 'a>>>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub(crate) fn new(comm: &mpi::topology::UserCommunicator, options: Opt) -> Self {
+        use mpi::traits::Communicator;
+        Self {
+            log: options.log,
+            rank: comm.rank(),
+            callback: RefCell::new(None),
+        }
+    }
+
+    pub(crate) fn set_callback(&self, callback: Option<Box<dyn Fn(&SetupProgress) + 'a>>) {
+        *self.callback.borrow_mut() = callback;
+    }
+
+    // Report a completed phase, timed from the given start
+    pub(crate) fn report(
+        &self,
+        phase: SetupPhase,
+        elements: impl Into<Option<usize>>,
+        start: std::time::Instant,
+    ) {
+        let progress = SetupProgress {
+            phase,
+            elements: elements.into(),
+            elapsed: start.elapsed(),
+        };
+        if self.log && self.rank == 0 {
+            println!("Meles: {}", progress);
+        }
+        if let Some(callback) = self.callback.borrow().as_ref() {
+            callback(&progress);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,240 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Quadrature data
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Host copy of the quadrature data produced by a setup operator
+///
+/// Values are ordered by element, then component, then quadrature point.
+pub struct QData {
+    pub num_elements: usize,
+    pub num_quadrature_points: usize,
+    pub size: usize,
+    pub values: Vec<f64>,
+}
+
+impl QData {
+    /// Returns a single quadrature data value
+    ///
+    /// # arguments
+    ///
+    /// * `element` - Element index
+    /// * `point` - Quadrature point index within the element
+    /// * `component` - Quadrature data component
+    pub fn value(&self, element: usize, point: usize, component: usize) -> f64 {
+        self.values[(element * self.size + component) * self.num_quadrature_points + point]
+    }
+
+    /// Returns the values of a component at every quadrature point of an
+    /// element
+    pub fn element_component(&self, element: usize, component: usize) -> &[f64] {
+        let start = (element * self.size + component) * self.num_quadrature_points;
+        &self.values[start..start + self.num_quadrature_points]
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Quadrature data held by an operator
+//   Stored with strides [1, Q, Q size] so the layout is backend independent
+// -----------------------------------------------------------------------------
+pub(crate) struct QDataVector<'a> {
+    pub(crate) vector: std::rc::Rc<libceed::vector::Vector<'a>>,
+    pub(crate) num_elements: usize,
+    pub(crate) num_quadrature_points: usize,
+    pub(crate) size: usize,
+}
+
+impl<'a> QDataVector<'a> {
+    // Strided restriction for quadrature data with a known layout
+    pub(crate) fn restriction(
+        ceed: &libceed::Ceed,
+        num_elements: usize,
+        num_quadrature_points: usize,
+        size: usize,
+    ) -> crate::Result<ElemRestriction<'a>> {
+        Ok(ceed.strided_elem_restriction(
+            num_elements,
+            num_quadrature_points,
+            size,
+            num_elements * num_quadrature_points * size,
+            [
+                1,
+                num_quadrature_points as i32,
+                (num_quadrature_points * size) as i32,
+            ],
+        )?)
+    }
+
+    // Mutable access for scaling in place, before the vector is shared
+    pub(crate) fn vector_mut(&mut self) -> crate::Result<&mut libceed::vector::Vector<'a>> {
+        std::rc::Rc::get_mut(&mut self.vector).ok_or_else(|| crate::Error {
+            message: "Quadrature data is shared and cannot be modified in place".to_string(),
+        })
+    }
+
+    // Copy to host
+    pub(crate) fn to_host(&self) -> crate::Result<QData> {
+        let view = self.vector.view()?;
+        Ok(QData {
+            num_elements: self.num_elements,
+            num_quadrature_points: self.num_quadrature_points,
+            size: self.size,
+            values: view.to_vec(),
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Quadrature data memory budget
+//   Quadrature data dominates the memory of matrix-free operators at high
+//   order, so the budget bounds the quadrature data held by each rank
+// -----------------------------------------------------------------------------
+pub(crate) struct BudgetOpt {
+    pub(crate) qdata_budget: f64,
+}
+
+impl petsc::Opt for BudgetOpt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let qdata_budget = pob.options_real(
+            "-qdata_budget",
+            "Quadrature data memory per rank in MiB, 0 for none",
+            "",
+            0.0,
+        )?;
+        Ok(BudgetOpt { qdata_budget })
+    }
+}
+
+// Bytes of quadrature data for a number of cells, with the quadrature of the
+//   bases the benchmark operator builds
+fn qdata_bytes(
+    options: &crate::ceed_bps::Opt,
+    dimension: usize,
+    simplex: bool,
+    num_cells: usize,
+    q_extra: usize,
+) -> crate::Result<f64> {
+    let q = options.order + 1 + q_extra;
+    let num_quadrature_points = if simplex {
+        crate::dm::simplex_num_quadrature_points(dimension, q)?
+    } else {
+        q.pow(dimension as u32)
+    };
+    let size = crate::ceed_bps::qdata_size(options)?;
+    Ok((num_cells * num_quadrature_points * size * std::mem::size_of::<f64>()) as f64)
+}
+
+// Feature that reads or scales the stored quadrature data, if any, so it is
+//   unavailable with geometric factors computed on the fly
+pub(crate) fn stored_qdata_feature(options: &crate::ceed_bps::Opt) -> Option<&'static str> {
+    if options.coefficient.is_some() {
+        Some("coefficients")
+    } else if options.diffusion_tensor.is_some() {
+        Some("diffusion tensors")
+    } else if options.problem == crate::ceed_bps::CeedBP::Helmholtz {
+        Some("the Helmholtz problem")
+    } else if options.operator.is_some() {
+        Some("operator expressions")
+    } else {
+        None
+    }
+}
+
+pub(crate) fn require_stored_qdata(
+    options: &crate::ceed_bps::Opt,
+    feature: &str,
+) -> crate::Result<()> {
+    if options.on_the_fly_geometry {
+        return Err(crate::Error {
+            message: format!(
+                "{} require stored quadrature data, not on-the-fly geometric factors",
+                feature
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Fit the quadrature data of the rank with the most cells in the budget, if
+//   any, computing the geometric factors on the fly with the requested qextra
+//   when nothing reads the stored quadrature data, and lowering qextra
+//   otherwise
+//   Collective, so every rank chooses the same discretization
+pub(crate) fn fit_budget(
+    comm: &mpi::topology::UserCommunicator,
+    dm: &DM,
+    options: &mut crate::ceed_bps::Opt,
+    budget: BudgetOpt,
+) -> crate::Result<()> {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    use mpi::traits::Communicator;
+    if budget.qdata_budget <= 0.0 || options.on_the_fly_geometry {
+        return Ok(());
+    }
+    let local_cells =
+        crate::dm::local_num_cells(dm, options.mesh_type)?.ok_or_else(|| crate::Error {
+            message: format!(
+                "quadrature data budgets require a plex mesh, not {}",
+                options.mesh_type
+            ),
+        })?;
+    let mut max_cells = 0u64;
+    comm.all_reduce_into(
+        &(local_cells as u64),
+        &mut max_cells,
+        SystemOperation::max(),
+    );
+    let max_cells = max_cells as usize;
+    let dimension = dm.dimension()?;
+    let simplex = crate::dm::plex_is_simplex(dm)?;
+    let budget_bytes = budget.qdata_budget * 1024.0 * 1024.0;
+    let bytes = |q_extra| qdata_bytes(options, dimension, simplex, max_cells, q_extra);
+
+    let requested = options.q_extra;
+    if bytes(requested)? <= budget_bytes {
+        return Ok(());
+    }
+
+    // No stored quadrature data at all, keeping the requested qextra
+    let feature = match stored_qdata_feature(options) {
+        Some(feature) => feature,
+        None => {
+            if comm.rank() == 0 {
+                crate::petsc_info(&format!(
+                    "quadrature data exceeds the {} MiB budget per rank, computing geometric factors on the fly",
+                    budget.qdata_budget
+                ))?;
+            }
+            options.on_the_fly_geometry = true;
+            return Ok(());
+        }
+    };
+
+    // Fewer quadrature points, trading accuracy of the quadrature for memory
+    let mut q_extra = requested;
+    while q_extra > 0 && bytes(q_extra)? > budget_bytes {
+        q_extra -= 1;
+    }
+    let fitted = bytes(q_extra)?;
+    if fitted > budget_bytes {
+        return Err(crate::Error {
+            message: format!(
+                "quadrature data of {:.2} MiB per rank with qextra 0 exceeds the {} MiB budget, and {} require stored quadrature data",
+                fitted / (1024.0 * 1024.0),
+                budget.qdata_budget,
+                feature
+            ),
+        });
+    }
+    if comm.rank() == 0 {
+        crate::petsc_info(&format!(
+            "quadrature data exceeds the {} MiB budget per rank, using qextra {} instead of {} for {}",
+            budget.qdata_budget, q_extra, requested, feature
+        ))?;
+    }
+    options.q_extra = q_extra;
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
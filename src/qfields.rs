@@ -0,0 +1,405 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Quadrature point fields
+// -----------------------------------------------------------------------------
+const CHECKPOINT_MAGIC: &[u8; 8] = b"MELESQF1";
+
+// Storage for a single named field, one block per sub-operator
+struct QuadratureField<'a> {
+    name: String,
+    size: usize,
+    current: Vec<libceed::vector::Vector<'a>>,
+    accepted: Vec<Vec<f64>>,
+}
+
+/// Named fields stored at quadrature points, such as state variables or
+/// history data for nonlinear models
+///
+/// Each field holds one block per sub-operator of the benchmark problem
+/// operator, laid out like `QData`, with strides `[1, Q, Q size]`. Fields keep
+/// the values from the last accepted step so a rejected step can be rolled
+/// back, and all fields can be written to and restored from a checkpoint.
+pub struct QuadratureFields<'a> {
+    ceed: libceed::Ceed,
+    rank: usize,
+    num_ranks: usize,
+    layout: Vec<(usize, usize)>,
+    fields: Vec<QuadratureField<'a>>,
+}
+
+impl<'a> QuadratureFields<'a> {
+    // Field by name
+    fn field(&self, name: &str) -> crate::Result<&QuadratureField<'a>> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| crate::Error {
+                message: format!("no quadrature field named {}", name),
+            })
+    }
+
+    fn field_mut(&mut self, name: &str) -> crate::Result<&mut QuadratureField<'a>> {
+        self.fields
+            .iter_mut()
+            .find(|field| field.name == name)
+            .ok_or_else(|| crate::Error {
+                message: format!("no quadrature field named {}", name),
+            })
+    }
+
+    // Layout of a block, number of elements and quadrature points
+    fn block_layout(&self, block: usize) -> crate::Result<(usize, usize)> {
+        self.layout.get(block).copied().ok_or_else(|| crate::Error {
+            message: format!(
+                "quadrature field block {} out of range, fields hold {} blocks",
+                block,
+                self.layout.len()
+            ),
+        })
+    }
+
+    // Checkpoint file of this rank, with the rank appended to the path on
+    //   more than one rank
+    fn rank_path(&self, path: &std::path::Path) -> std::path::PathBuf {
+        if self.num_ranks == 1 {
+            path.to_path_buf()
+        } else {
+            let mut rank_path = path.as_os_str().to_owned();
+            rank_path.push(format!(".{}", self.rank));
+            rank_path.into()
+        }
+    }
+
+    /// Returns the number of sub-operator blocks each field holds
+    pub fn num_blocks(&self) -> usize {
+        self.layout.len()
+    }
+
+    /// Returns the names of the declared fields, in declaration order
+    pub fn names(&self) -> Vec<&str> {
+        self.fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect()
+    }
+
+    /// Declare a new field, initialized to zero
+    ///
+    /// # arguments
+    ///
+    /// * `name` - Unique name of the field
+    /// * `size` - Number of components at each quadrature point
+    pub fn declare(&mut self, name: impl Into<String>, size: usize) -> crate::Result<()> {
+        let name = name.into();
+        if self.fields.iter().any(|field| field.name == name) {
+            return Err(crate::Error {
+                message: format!("quadrature field {} already declared", name),
+            });
+        }
+        let mut current = vec![];
+        let mut accepted = vec![];
+        for &(num_elements, num_quadrature_points) in &self.layout {
+            let mut vector = self
+                .ceed
+                .vector(num_elements * num_quadrature_points * size)?;
+            vector.set_value(0.0)?;
+            current.push(vector);
+            accepted.push(vec![0.0; num_elements * num_quadrature_points * size]);
+        }
+        self.fields.push(QuadratureField {
+            name,
+            size,
+            current,
+            accepted,
+        });
+        Ok(())
+    }
+
+    /// Returns the strided restriction for a block of a field, for use as a
+    ///   passive field with `BasisOpt::Collocated` in a libCEED operator
+    pub fn restriction(&self, name: &str, block: usize) -> crate::Result<ElemRestriction<'a>> {
+        let size = self.field(name)?.size;
+        let (num_elements, num_quadrature_points) = self.block_layout(block)?;
+        crate::qdata::QDataVector::restriction(
+            &self.ceed,
+            num_elements,
+            num_quadrature_points,
+            size,
+        )
+    }
+
+    /// Returns the libCEED vector holding the current values of a block of
+    ///   a field
+    pub fn vector(&self, name: &str, block: usize) -> crate::Result<&libceed::vector::Vector<'a>> {
+        self.block_layout(block)?;
+        Ok(&self.field(name)?.current[block])
+    }
+
+    /// Returns host copies of the current values of a field, one per block
+    pub fn values(&self, name: &str) -> crate::Result<Vec<crate::QData>> {
+        let field = self.field(name)?;
+        field
+            .current
+            .iter()
+            .zip(&self.layout)
+            .map(
+                |(vector, &(num_elements, num_quadrature_points))| -> crate::Result<crate::QData> {
+                    Ok(crate::QData {
+                        num_elements,
+                        num_quadrature_points,
+                        size: field.size,
+                        values: vector.view()?.to_vec(),
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Set the current values of a block of a field
+    ///
+    /// # arguments
+    ///
+    /// * `name` - Name of the field
+    /// * `block` - Sub-operator block index
+    /// * `values` - Values ordered by element, then component, then
+    ///                quadrature point
+    pub fn set_values(&mut self, name: &str, block: usize, values: &[f64]) -> crate::Result<()> {
+        let (num_elements, num_quadrature_points) = self.block_layout(block)?;
+        let field = self.field_mut(name)?;
+        let length = num_elements * num_quadrature_points * field.size;
+        if values.len() != length {
+            return Err(crate::Error {
+                message: format!(
+                    "quadrature field {} block {} has {} values, not {}",
+                    name,
+                    block,
+                    length,
+                    values.len()
+                ),
+            });
+        }
+        field.current[block].view_mut()?.copy_from_slice(values);
+        Ok(())
+    }
+
+    /// Accept the current values of every field as the start of the next step
+    pub fn accept_step(&mut self) -> crate::Result<()> {
+        for field in &mut self.fields {
+            for (current, accepted) in field.current.iter().zip(field.accepted.iter_mut()) {
+                accepted.copy_from_slice(&current.view()?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore every field to the values of the last accepted step
+    pub fn reject_step(&mut self) -> crate::Result<()> {
+        for field in &mut self.fields {
+            for (current, accepted) in field.current.iter_mut().zip(field.accepted.iter()) {
+                current.view_mut()?.copy_from_slice(accepted);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the accepted values of every field to a checkpoint file
+    ///
+    /// Fields hold the values of the local elements, so on more than one rank
+    /// each rank writes its own file, with its rank appended to the path, such
+    /// as `fields.bin.0` and `fields.bin.1`.
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the checkpoint to write
+    pub fn write_checkpoint(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        let mut bytes = CHECKPOINT_MAGIC.to_vec();
+        let mut push = |value: usize| bytes.extend_from_slice(&(value as u64).to_le_bytes());
+        push(self.layout.len());
+        for &(num_elements, num_quadrature_points) in &self.layout {
+            push(num_elements);
+            push(num_quadrature_points);
+        }
+        push(self.fields.len());
+        for field in &self.fields {
+            bytes.extend_from_slice(&(field.name.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(field.name.as_bytes());
+            bytes.extend_from_slice(&(field.size as u64).to_le_bytes());
+            for block in &field.accepted {
+                for value in block {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        let path = self.rank_path(path.as_ref());
+        std::fs::write(&path, bytes).map_err(|err| crate::Error {
+            message: format!(
+                "failed to write quadrature field checkpoint {}: {}",
+                path.display(),
+                err
+            ),
+        })
+    }
+
+    /// Restore fields from a checkpoint file, declaring any missing fields
+    ///
+    /// The checkpoint must have been written for the same mesh and order, on
+    /// the same number of ranks. Restored values become both the current and
+    /// accepted values.
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the checkpoint to read
+    pub fn read_checkpoint(&mut self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        let path = self.rank_path(path.as_ref());
+        let bytes = std::fs::read(&path).map_err(|err| crate::Error {
+            message: format!(
+                "failed to read quadrature field checkpoint {}: {}",
+                path.display(),
+                err
+            ),
+        })?;
+        let mut reader = CheckpointReader {
+            bytes,
+            offset: 0,
+            path: &path,
+        };
+        if reader.take(CHECKPOINT_MAGIC.len())? != CHECKPOINT_MAGIC {
+            return Err(reader.corrupt());
+        }
+
+        // Layout must match
+        let num_blocks = reader.take_usize()?;
+        let mut layout = vec![];
+        for _ in 0..num_blocks {
+            layout.push((reader.take_usize()?, reader.take_usize()?));
+        }
+        if layout != self.layout {
+            return Err(crate::Error {
+                message: format!(
+                    "quadrature field checkpoint {} was written for a different discretization",
+                    path.display()
+                ),
+            });
+        }
+
+        // Fields
+        let num_fields = reader.take_usize()?;
+        for _ in 0..num_fields {
+            let name_length = reader.take_usize()?;
+            let name = String::from_utf8(reader.take(name_length)?.to_vec())
+                .map_err(|_| reader.corrupt())?;
+            let size = reader.take_usize()?;
+            let mut blocks = vec![];
+            for &(num_elements, num_quadrature_points) in &layout {
+                let length = num_elements * num_quadrature_points * size;
+                let values: Vec<f64> = reader
+                    .take(8 * length)?
+                    .chunks_exact(8)
+                    .map(|chunk| {
+                        let mut buffer = [0; 8];
+                        buffer.copy_from_slice(chunk);
+                        f64::from_le_bytes(buffer)
+                    })
+                    .collect();
+                blocks.push(values);
+            }
+            match self.fields.iter().find(|field| field.name == name) {
+                Some(field) if field.size != size => {
+                    return Err(crate::Error {
+                        message: format!(
+                            "quadrature field {} has size {}, not {} in checkpoint",
+                            name, field.size, size
+                        ),
+                    });
+                }
+                Some(_) => {}
+                None => self.declare(name.clone(), size)?,
+            }
+            self.restore(&name, blocks)?;
+        }
+        Ok(())
+    }
+
+    // Layout of each block, number of elements and quadrature points
+    pub(crate) fn layout(&self) -> &[(usize, usize)] {
+        &self.layout
+    }
+
+    // Size and accepted values of a field
+    pub(crate) fn accepted(&self, name: &str) -> crate::Result<(usize, &[Vec<f64>])> {
+        let field = self.field(name)?;
+        Ok((field.size, &field.accepted))
+    }
+
+    // Set both the current and accepted values of a field
+    pub(crate) fn restore(&mut self, name: &str, blocks: Vec<Vec<f64>>) -> crate::Result<()> {
+        let field = self.field_mut(name)?;
+        for ((current, accepted), values) in field
+            .current
+            .iter_mut()
+            .zip(field.accepted.iter_mut())
+            .zip(blocks)
+        {
+            current.view_mut()?.copy_from_slice(&values);
+            *accepted = values;
+        }
+        Ok(())
+    }
+}
+
+// Sequential reader for checkpoint files
+struct CheckpointReader<'p> {
+    bytes: Vec<u8>,
+    offset: usize,
+    path: &'p std::path::Path,
+}
+
+impl<'p> CheckpointReader<'p> {
+    fn corrupt(&self) -> crate::Error {
+        crate::Error {
+            message: format!(
+                "corrupt quadrature field checkpoint {}",
+                self.path.display()
+            ),
+        }
+    }
+
+    fn take(&mut self, length: usize) -> crate::Result<&[u8]> {
+        if self.offset + length > self.bytes.len() {
+            return Err(self.corrupt());
+        }
+        self.offset += length;
+        Ok(&self.bytes[self.offset - length..self.offset])
+    }
+
+    fn take_usize(&mut self) -> crate::Result<usize> {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buffer) as usize)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Create empty quadrature fields matching the benchmark problem discretization
+// -----------------------------------------------------------------------------
+pub(crate) fn quadrature_fields<'a>(
+    meles: &'a crate::Meles<'a>,
+) -> crate::Result<QuadratureFields<'a>> {
+    let context = crate::ceed_bps::mat_shell_context(meles, &meles.bp_options)?;
+    use mpi::traits::Communicator;
+    let layout = context
+        .qdata
+        .iter()
+        .map(|qdata| (qdata.num_elements, qdata.num_quadrature_points))
+        .collect();
+    Ok(QuadratureFields {
+        ceed: meles.ceed.clone(),
+        rank: meles.comm.rank() as usize,
+        num_ranks: meles.comm.size() as usize,
+        layout,
+        fields: vec![],
+    })
+}
+
+// -----------------------------------------------------------------------------
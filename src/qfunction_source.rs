@@ -0,0 +1,889 @@
+use crate::prelude::*;
+use libceed_sys::bind_ceed;
+use std::ffi::CString;
+
+// -----------------------------------------------------------------------------
+// QFunction source
+//   libCEED device backends JiT compile QFunctions from a C source file and
+//   function name, so source given as a string is written to a header file
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// C source of a QFunction, for JiT compilation on `/gpu` libCEED resources
+///
+/// ```
+/// # fn main() -> meles::Result<()> {
+/// let source = meles::QFunctionSource::string(
+///     "CEED_QFUNCTION(ScaledMass)(void *ctx, const CeedInt Q,
+///                                 const CeedScalar *const *in,
+///                                 CeedScalar *const *out) {
+///        for (CeedInt i = 0; i < Q; i++) out[0][i] = 2.0 * in[1][i] * in[0][i];
+///        return 0;
+///      }",
+///     "ScaledMass",
+/// )?;
+/// assert!(source.path().exists(), "source header not written");
+///
+/// // function names are C identifiers
+/// assert!(meles::QFunctionSource::string("", "../ScaledMass").is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct QFunctionSource {
+    path: std::path::PathBuf,
+    function: String,
+}
+
+impl QFunctionSource {
+    /// Returns QFunction source from a C header file
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Path to the header defining the QFunction
+    /// * `function` - Name of the QFunction in the header
+    pub fn file(path: impl AsRef<std::path::Path>, function: &str) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let path = path.canonicalize().map_err(|err| crate::Error {
+            message: format!("QFunction source {} not found: {}", path.display(), err),
+        })?;
+        check_function_name(function)?;
+        Ok(Self {
+            path,
+            function: function.to_string(),
+        })
+    }
+
+    /// Returns QFunction source from a string of C code, written to a header
+    ///   in the temporary directory
+    ///
+    /// The header is named by the function and a hash of the source, and is
+    /// written to a unique file then renamed into place, so ranks and threads
+    /// writing the same source never observe a partial header.
+    ///
+    /// # arguments
+    ///
+    /// * `source` - C code defining the QFunction
+    /// * `function` - Name of the QFunction in the code
+    pub fn string(source: &str, function: &str) -> crate::Result<Self> {
+        use std::hash::{Hash, Hasher};
+        use std::io::Write;
+        static NUM_WRITES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        check_function_name(function)?;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        source.hash(&mut hasher);
+        let path = std::env::temp_dir().join(format!(
+            "meles-qfunction-{}-{:016x}.h",
+            function,
+            hasher.finish()
+        ));
+        // Unique file, created new so an existing file or link is never
+        //   followed, then renamed over any header from another writer
+        let temporary = path.with_extension(format!(
+            "h.{}.{}.tmp",
+            std::process::id(),
+            NUM_WRITES.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)
+            .and_then(|mut file| file.write_all(source.as_bytes()))
+            .and_then(|_| std::fs::rename(&temporary, &path));
+        if let Err(err) = written {
+            let _ = std::fs::remove_file(&temporary);
+            return Err(crate::Error {
+                message: format!(
+                    "failed to write QFunction source {}: {}",
+                    path.display(),
+                    err
+                ),
+            });
+        }
+        Self::file(path, function)
+    }
+
+    /// Returns the path to the header defining the QFunction
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Returns the name of the QFunction
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    // libCEED QFunction source specifier, "path:function"
+    fn specifier(&self) -> crate::Result<CString> {
+        CString::new(format!("{}:{}", self.path.display(), self.function)).map_err(|_| {
+            crate::Error {
+                message: format!("invalid QFunction source path {}", self.path.display()),
+            }
+        })
+    }
+}
+
+// QFunction names are C identifiers, and name the header of string source
+fn check_function_name(function: &str) -> crate::Result<()> {
+    let mut chars = function.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        return Err(crate::Error {
+            message: format!(
+                "QFunction source requires a C identifier as the function name, not \"{}\"",
+                function
+            ),
+        });
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Raw libCEED objects
+//   libceed-rs only creates QFunctions from Rust closures, so operators with
+//   user source are built with the C interface, on a reference to the Ceed
+//   context of the problem so errors are returned with its error handler and
+//   operators apply to libCEED vectors in place
+// -----------------------------------------------------------------------------
+fn eval_mode_raw(eval_mode: libceed::EvalMode) -> bind_ceed::CeedEvalMode {
+    match eval_mode {
+        libceed::EvalMode::None => bind_ceed::CeedEvalMode_CEED_EVAL_NONE,
+        libceed::EvalMode::Interp => bind_ceed::CeedEvalMode_CEED_EVAL_INTERP,
+        libceed::EvalMode::Grad => bind_ceed::CeedEvalMode_CEED_EVAL_GRAD,
+        libceed::EvalMode::Div => bind_ceed::CeedEvalMode_CEED_EVAL_DIV,
+        libceed::EvalMode::Curl => bind_ceed::CeedEvalMode_CEED_EVAL_CURL,
+        libceed::EvalMode::Weight => bind_ceed::CeedEvalMode_CEED_EVAL_WEIGHT,
+    }
+}
+
+fn quad_mode_raw(q_mode: libceed::QuadMode) -> bind_ceed::CeedQuadMode {
+    match q_mode {
+        libceed::QuadMode::Gauss => bind_ceed::CeedQuadMode_CEED_GAUSS,
+        libceed::QuadMode::GaussLobatto => bind_ceed::CeedQuadMode_CEED_GAUSS_LOBATTO,
+    }
+}
+
+// Host entry point required by libCEED, never called by device backends
+unsafe extern "C" fn device_only(
+    _ctx: *mut std::os::raw::c_void,
+    _q: bind_ceed::CeedInt,
+    _inputs: *const *const bind_ceed::CeedScalar,
+    _outputs: *const *mut bind_ceed::CeedScalar,
+) -> std::os::raw::c_int {
+    1
+}
+
+// -----------------------------------------------------------------------------
+// Operator with a user source QFunction
+// -----------------------------------------------------------------------------
+/// libCEED operator applying a QFunction compiled from user source
+pub struct SourceOperator {
+    ceed: bind_ceed::Ceed,
+    restr_u: bind_ceed::CeedElemRestriction,
+    restr_qdata: bind_ceed::CeedElemRestriction,
+    restr_x: bind_ceed::CeedElemRestriction,
+    basis_u: bind_ceed::CeedBasis,
+    basis_x: bind_ceed::CeedBasis,
+    qdata: bind_ceed::CeedVector,
+    coordinates: bind_ceed::CeedVector,
+    qf: bind_ceed::CeedQFunction,
+    op: bind_ceed::CeedOperator,
+}
+
+impl SourceOperator {
+    /// Apply the operator, `y = A x`
+    pub fn apply(
+        &self,
+        x: &libceed::vector::Vector,
+        y: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        crate::check_ceed_raw(
+            unsafe {
+                bind_ceed::CeedOperatorApply(
+                    self.op,
+                    x.as_raw(),
+                    y.as_raw(),
+                    bind_ceed::CEED_REQUEST_IMMEDIATE,
+                )
+            },
+            "CeedOperatorApply",
+        )
+        .map_err(|err| libceed::Error {
+            message: err.message,
+        })?;
+        Ok(0)
+    }
+
+    // Assemble into a libCEED vector, in the memory the backend prefers
+    fn assemble(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+        assemble: unsafe extern "C" fn(
+            bind_ceed::CeedOperator,
+            bind_ceed::CeedVector,
+            *mut bind_ceed::CeedRequest,
+        ) -> std::os::raw::c_int,
+        function: &str,
+    ) -> libceed::Result<i32> {
+        crate::check_ceed_raw(
+            unsafe {
+                assemble(
+                    self.op,
+                    assembled.as_raw(),
+                    bind_ceed::CEED_REQUEST_IMMEDIATE,
+                )
+            },
+            function,
+        )
+        .map_err(|err| libceed::Error {
+            message: err.message,
+        })?;
+        Ok(0)
+    }
+
+    /// Assemble the diagonal of the operator
+    pub fn linear_assemble_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.assemble(
+            assembled,
+            bind_ceed::CeedOperatorLinearAssembleDiagonal,
+            "CeedOperatorLinearAssembleDiagonal",
+        )
+    }
+
+    /// Assemble the point block diagonal of the operator
+    pub fn linear_assemble_point_block_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.assemble(
+            assembled,
+            bind_ceed::CeedOperatorLinearAssemblePointBlockDiagonal,
+            "CeedOperatorLinearAssemblePointBlockDiagonal",
+        )
+    }
+}
+
+impl Drop for SourceOperator {
+    fn drop(&mut self) {
+        unsafe {
+            bind_ceed::CeedOperatorDestroy(&mut self.op);
+            bind_ceed::CeedQFunctionDestroy(&mut self.qf);
+            bind_ceed::CeedVectorDestroy(&mut self.coordinates);
+            bind_ceed::CeedVectorDestroy(&mut self.qdata);
+            bind_ceed::CeedBasisDestroy(&mut self.basis_x);
+            bind_ceed::CeedBasisDestroy(&mut self.basis_u);
+            bind_ceed::CeedElemRestrictionDestroy(&mut self.restr_x);
+            bind_ceed::CeedElemRestrictionDestroy(&mut self.restr_qdata);
+            bind_ceed::CeedElemRestrictionDestroy(&mut self.restr_u);
+            bind_ceed::CeedDestroy(&mut self.ceed);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Build the operator from raw objects
+//   Each object is owned by the operator as soon as it is created, so an error
+//   part way through releases everything created so far
+// -----------------------------------------------------------------------------
+// Geometry inputs of the QFunction, after the active inputs
+pub(crate) enum SourceGeometry<'b> {
+    // Stored geometric factors, the `qdata` input
+    QData(&'b crate::qdata::QDataVector<'b>),
+    // Local mesh coordinates, the `dx` and `weights` inputs, with the geometric
+    //   factors computed by the QFunction at every application
+    Coordinates {
+        offsets: &'b [petsc::Int],
+        l_size: usize,
+        values: &'b [petsc::Scalar],
+    },
+}
+
+#[allow(clippy::too_many_arguments)]
+fn source_operator(
+    ceed: &libceed::Ceed,
+    source: &crate::QFunctionSource,
+    offsets: &[petsc::Int],
+    num_elements: usize,
+    element_size: usize,
+    l_size: usize,
+    num_components: usize,
+    (dimension, p, q, q_mode): (usize, usize, usize, libceed::QuadMode),
+    inputs: &[(String, usize, libceed::EvalMode)],
+    outputs: &[(String, usize, libceed::EvalMode)],
+    geometry: SourceGeometry,
+) -> crate::Result<SourceOperator> {
+    let null = std::ptr::null_mut;
+    let mut op = SourceOperator {
+        ceed: null(),
+        restr_u: null(),
+        restr_qdata: null(),
+        restr_x: null(),
+        basis_u: null(),
+        basis_x: null(),
+        qdata: null(),
+        coordinates: null(),
+        qf: null(),
+        op: null(),
+    };
+    let cstring = |s: &str| {
+        CString::new(s).map_err(|_| crate::Error {
+            message: format!("invalid libCEED string {}", s),
+        })
+    };
+    let offsets: Vec<bind_ceed::CeedInt> = offsets.iter().map(|&o| o as _).collect();
+    unsafe {
+        // -- Ceed
+        crate::check_ceed_raw(
+            bind_ceed::CeedReferenceCopy(ceed.as_raw(), &mut op.ceed),
+            "CeedReferenceCopy",
+        )?;
+        // -- Restrictions and basis
+        crate::check_ceed_raw(
+            bind_ceed::CeedElemRestrictionCreate(
+                op.ceed,
+                num_elements as _,
+                element_size as _,
+                num_components as _,
+                1,
+                l_size as _,
+                bind_ceed::CeedMemType_CEED_MEM_HOST,
+                bind_ceed::CeedCopyMode_CEED_COPY_VALUES,
+                offsets.as_ptr(),
+                &mut op.restr_u,
+            ),
+            "CeedElemRestrictionCreate",
+        )?;
+        crate::check_ceed_raw(
+            bind_ceed::CeedBasisCreateTensorH1Lagrange(
+                op.ceed,
+                dimension as _,
+                num_components as _,
+                p as _,
+                q as _,
+                quad_mode_raw(q_mode),
+                &mut op.basis_u,
+            ),
+            "CeedBasisCreateTensorH1Lagrange",
+        )?;
+        // -- Geometry
+        let mut geometry_inputs = vec![];
+        match geometry {
+            // ---- Geometric factors, copied from the setup operator
+            SourceGeometry::QData(qdata) => {
+                let num_quadrature_points = qdata.num_quadrature_points;
+                let q_data_size = qdata.size;
+                let num_values = num_elements * num_quadrature_points * q_data_size;
+                let strides = [
+                    1,
+                    num_quadrature_points as bind_ceed::CeedInt,
+                    (num_quadrature_points * q_data_size) as bind_ceed::CeedInt,
+                ];
+                let mut qdata_values = qdata.vector.view()?.to_vec();
+                crate::check_ceed_raw(
+                    bind_ceed::CeedElemRestrictionCreateStrided(
+                        op.ceed,
+                        num_elements as _,
+                        num_quadrature_points as _,
+                        q_data_size as _,
+                        num_values as _,
+                        strides.as_ptr(),
+                        &mut op.restr_qdata,
+                    ),
+                    "CeedElemRestrictionCreateStrided",
+                )?;
+                crate::check_ceed_raw(
+                    bind_ceed::CeedVectorCreate(op.ceed, num_values as _, &mut op.qdata),
+                    "CeedVectorCreate",
+                )?;
+                crate::check_ceed_raw(
+                    bind_ceed::CeedVectorSetArray(
+                        op.qdata,
+                        bind_ceed::CeedMemType_CEED_MEM_HOST,
+                        bind_ceed::CeedCopyMode_CEED_COPY_VALUES,
+                        qdata_values.as_mut_ptr(),
+                    ),
+                    "CeedVectorSetArray",
+                )?;
+                geometry_inputs.push((
+                    cstring("qdata")?,
+                    q_data_size,
+                    bind_ceed::CeedEvalMode_CEED_EVAL_NONE,
+                    op.restr_qdata,
+                    bind_ceed::CEED_BASIS_COLLOCATED,
+                    op.qdata,
+                ));
+            }
+            // ---- Coordinates, with the linear coordinate basis
+            SourceGeometry::Coordinates {
+                offsets: coordinate_offsets,
+                l_size: coordinates_l_size,
+                values,
+            } => {
+                let coordinate_offsets: Vec<bind_ceed::CeedInt> =
+                    coordinate_offsets.iter().map(|&o| o as _).collect();
+                let mut values = values.to_vec();
+                crate::check_ceed_raw(
+                    bind_ceed::CeedElemRestrictionCreate(
+                        op.ceed,
+                        num_elements as _,
+                        (coordinate_offsets.len() / num_elements) as _,
+                        dimension as _,
+                        1,
+                        coordinates_l_size as _,
+                        bind_ceed::CeedMemType_CEED_MEM_HOST,
+                        bind_ceed::CeedCopyMode_CEED_COPY_VALUES,
+                        coordinate_offsets.as_ptr(),
+                        &mut op.restr_x,
+                    ),
+                    "CeedElemRestrictionCreate",
+                )?;
+                crate::check_ceed_raw(
+                    bind_ceed::CeedBasisCreateTensorH1Lagrange(
+                        op.ceed,
+                        dimension as _,
+                        dimension as _,
+                        2,
+                        q as _,
+                        quad_mode_raw(q_mode),
+                        &mut op.basis_x,
+                    ),
+                    "CeedBasisCreateTensorH1Lagrange",
+                )?;
+                crate::check_ceed_raw(
+                    bind_ceed::CeedVectorCreate(
+                        op.ceed,
+                        coordinates_l_size as _,
+                        &mut op.coordinates,
+                    ),
+                    "CeedVectorCreate",
+                )?;
+                crate::check_ceed_raw(
+                    bind_ceed::CeedVectorSetArray(
+                        op.coordinates,
+                        bind_ceed::CeedMemType_CEED_MEM_HOST,
+                        bind_ceed::CeedCopyMode_CEED_COPY_VALUES,
+                        values.as_mut_ptr(),
+                    ),
+                    "CeedVectorSetArray",
+                )?;
+                geometry_inputs.push((
+                    cstring("dx")?,
+                    dimension * dimension,
+                    bind_ceed::CeedEvalMode_CEED_EVAL_GRAD,
+                    op.restr_x,
+                    op.basis_x,
+                    op.coordinates,
+                ));
+                geometry_inputs.push((
+                    cstring("weights")?,
+                    1,
+                    bind_ceed::CeedEvalMode_CEED_EVAL_WEIGHT,
+                    bind_ceed::CEED_ELEMRESTRICTION_NONE,
+                    op.basis_x,
+                    bind_ceed::CEED_VECTOR_NONE,
+                ));
+            }
+        }
+        // -- QFunction
+        let specifier = source.specifier()?;
+        crate::check_ceed_raw(
+            bind_ceed::CeedQFunctionCreateInterior(
+                op.ceed,
+                1,
+                Some(device_only),
+                specifier.as_ptr(),
+                &mut op.qf,
+            ),
+            "CeedQFunctionCreateInterior",
+        )?;
+        for (name, size, eval_mode) in inputs {
+            let name = cstring(name)?;
+            crate::check_ceed_raw(
+                bind_ceed::CeedQFunctionAddInput(
+                    op.qf,
+                    name.as_ptr(),
+                    *size as _,
+                    eval_mode_raw(*eval_mode),
+                ),
+                "CeedQFunctionAddInput",
+            )?;
+        }
+        for (name, size, eval_mode, _, _, _) in &geometry_inputs {
+            crate::check_ceed_raw(
+                bind_ceed::CeedQFunctionAddInput(op.qf, name.as_ptr(), *size as _, *eval_mode),
+                "CeedQFunctionAddInput",
+            )?;
+        }
+        for (name, size, eval_mode) in outputs {
+            let name = cstring(name)?;
+            crate::check_ceed_raw(
+                bind_ceed::CeedQFunctionAddOutput(
+                    op.qf,
+                    name.as_ptr(),
+                    *size as _,
+                    eval_mode_raw(*eval_mode),
+                ),
+                "CeedQFunctionAddOutput",
+            )?;
+        }
+        // -- Operator
+        crate::check_ceed_raw(
+            bind_ceed::CeedOperatorCreate(
+                op.ceed,
+                op.qf,
+                bind_ceed::CEED_QFUNCTION_NONE,
+                bind_ceed::CEED_QFUNCTION_NONE,
+                &mut op.op,
+            ),
+            "CeedOperatorCreate",
+        )?;
+        for (name, _, _) in inputs {
+            let name = cstring(name)?;
+            crate::check_ceed_raw(
+                bind_ceed::CeedOperatorSetField(
+                    op.op,
+                    name.as_ptr(),
+                    op.restr_u,
+                    op.basis_u,
+                    bind_ceed::CEED_VECTOR_ACTIVE,
+                ),
+                "CeedOperatorSetField",
+            )?;
+        }
+        for (name, _, _, restriction, basis, vector) in &geometry_inputs {
+            crate::check_ceed_raw(
+                bind_ceed::CeedOperatorSetField(
+                    op.op,
+                    name.as_ptr(),
+                    *restriction,
+                    *basis,
+                    *vector,
+                ),
+                "CeedOperatorSetField",
+            )?;
+        }
+        for (name, _, _) in outputs {
+            let name = cstring(name)?;
+            crate::check_ceed_raw(
+                bind_ceed::CeedOperatorSetField(
+                    op.op,
+                    name.as_ptr(),
+                    op.restr_u,
+                    op.basis_u,
+                    bind_ceed::CEED_VECTOR_ACTIVE,
+                ),
+                "CeedOperatorSetField",
+            )?;
+        }
+        crate::check_ceed_raw(
+            bind_ceed::CeedOperatorCheckReady(op.op),
+            "CeedOperatorCheckReady",
+        )?;
+    }
+    Ok(op)
+}
+
+// -----------------------------------------------------------------------------
+// Setup dm and a source QFunction operator for the benchmark problem space
+//   Geometric factors come from the gallery setup QFunctions, which libCEED
+//   compiles for every backend; the MatShell has no forcing term
+// -----------------------------------------------------------------------------
+pub(crate) fn mat_shell_context<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    user_q_function: crate::UserQFunction,
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    let source = user_q_function.source.clone().ok_or_else(|| crate::Error {
+        message: format!(
+            "user QFunctions on libCEED resource {} require C source, set with UserQFunction::source",
+            meles.ceed_resource
+        ),
+    })?;
+    let num_components = crate::ceed_bps::num_components(options)?;
+
+    // Duplicate DM
+    let section_setup_start = std::time::Instant::now();
+    let mut dm = meles.dm.borrow().clone();
+    crate::ceed_bps::setup_dm(&mut dm, options)?;
+    meles.progress.report(
+        crate::progress::SetupPhase::Section,
+        None,
+        section_setup_start,
+    );
+    let dimension = dm.dimension()?;
+    let hexahedral = options.mesh_type == crate::dm::MeshType::Plex
+        && crate::dm::plex_cell_types(&dm)?
+            == vec![petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_HEXAHEDRON];
+    if dimension != 3 || !hexahedral {
+        return Err(crate::Error {
+            message: "QFunction source operators require a 3D plex mesh of hexahedra".to_string(),
+        });
+    }
+    let (inputs, outputs) = user_q_function.active_fields(num_components, dimension)?;
+    let (setup_name, q_data_size) = user_q_function.geometric_factors.setup(dimension);
+
+    // Basis and restrictions
+    let p = options.order + 1;
+    let q = p + options.q_extra;
+    let q_mode = crate::backend::quadrature_mode(meles, libceed::QuadMode::Gauss);
+    let restrictions_start = std::time::Instant::now();
+    let basis_x = meles
+        .ceed
+        .basis_tensor_H1_Lagrange(dimension, dimension, 2, q, q_mode)?;
+    let basis_u = meles
+        .ceed
+        .basis_tensor_H1_Lagrange(dimension, num_components, p, q, q_mode)?;
+    let restr_u = crate::dm::create_restriction_from_dm_plex(&dm, &meles.ceed, 0, None, 0)?;
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+    };
+    let DMPlexLocalOffsets {
+        num_cells,
+        cell_size,
+        num_components: _,
+        l_size,
+        offsets,
+    } = dm.plex_local_offsets(None, 0, 0, 0)?;
+    meles.progress.report(
+        crate::progress::SetupPhase::Restrictions,
+        num_cells,
+        restrictions_start,
+    );
+
+    // Geometric factors
+    let qdata_start = std::time::Instant::now();
+    let coord_loc_ceed = {
+        let coord_loc = {
+            let mut dm = meles.dm.borrow_mut();
+            dm.coordinates_local()?
+        };
+        crate::transfer::owned_copy(&meles.ceed, &coord_loc)?
+    };
+    let qf_setup = meles.ceed.q_function_interior_by_name(setup_name)?;
+    let (_, qdata) = crate::ceed_bps::create_qdata(
+        &meles.ceed,
+        &qf_setup,
+        q_data_size,
+        &basis_x,
+        &basis_u,
+        &restr_x,
+        &restr_u,
+        &coord_loc_ceed,
+    )?;
+    meles
+        .progress
+        .report(crate::progress::SetupPhase::QData, num_cells, qdata_start);
+
+    // Operator
+    let op_ceed = source_operator(
+        &meles.ceed,
+        &source,
+        &offsets,
+        num_cells,
+        cell_size,
+        l_size,
+        num_components,
+        (dimension, p, q, q_mode),
+        &inputs,
+        &outputs,
+        SourceGeometry::QData(&qdata),
+    )?;
+
+    // Work vectors
+    let x_loc = dm.create_local_vector()?;
+    let y_loc = dm.create_local_vector()?;
+    let x_loc_size = x_loc.local_size()?;
+    let mut rhs = dm.create_global_vector()?;
+    rhs.zero_entries()?;
+
+    Ok(crate::MelesMatShellContext {
+        ceed: meles.ceed.clone(),
+        dm: RefCell::new(dm),
+        x_loc: RefCell::new(x_loc),
+        y_loc: RefCell::new(y_loc),
+        x_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        y_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        op_ceed: RefCell::new(crate::MelesOperator::Source(op_ceed)),
+        diagonal: RefCell::new(None),
+        num_components,
+        point_block_inverse: RefCell::new(None),
+        rhs,
+        qdata: vec![qdata],
+        true_solution: vec![],
+        error_ops: vec![],
+        single_precision: false,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// On-the-fly geometric factors on device resources
+//   The apply QFunctions of ceed_bps::on_the_fly_q_function as C source, so
+//   libCEED JiT compiles them for the device; the Jacobian is inverted once
+//   per quadrature point
+// -----------------------------------------------------------------------------
+const ON_THE_FLY_SOURCE: &str = "
+#define NUM_COMP MELES_NUM_COMP
+#define DIM MELES_DIM
+
+// Determinant and inverse, J_inv[d][k] = dX_d/dx_k, of the Jacobian at point i
+CEED_QFUNCTION_HELPER CeedScalar MelesJacobian(const CeedScalar *dx, const CeedInt Q,
+                                               const CeedInt i, CeedScalar J_inv[DIM][DIM]) {
+  CeedScalar J[3][3];
+  for (CeedInt c = 0; c < DIM; c++) {
+    for (CeedInt d = 0; d < DIM; d++) J[c][d] = dx[(d * DIM + c) * Q + i];
+  }
+  CeedScalar det_J;
+  if (DIM == 1) {
+    det_J = J[0][0];
+    J_inv[0][0] = 1. / det_J;
+  } else if (DIM == 2) {
+    det_J = J[0][0] * J[1][1] - J[0][1] * J[1][0];
+    J_inv[0][0] = J[1][1] / det_J;
+    J_inv[0][1] = -J[0][1] / det_J;
+    J_inv[1][0] = -J[1][0] / det_J;
+    J_inv[1][1] = J[0][0] / det_J;
+  } else {
+    det_J = J[0][0] * (J[1][1] * J[2][2] - J[1][2] * J[2][1]) -
+            J[0][1] * (J[1][0] * J[2][2] - J[1][2] * J[2][0]) +
+            J[0][2] * (J[1][0] * J[2][1] - J[1][1] * J[2][0]);
+    // J^{-1}[d][k] = cofactor(k, d) / det(J)
+    for (CeedInt d = 0; d < DIM; d++) {
+      for (CeedInt k = 0; k < DIM; k++) {
+        const CeedInt k1 = (k + 1) % 3, k2 = (k + 2) % 3;
+        const CeedInt d1 = (d + 1) % 3, d2 = (d + 2) % 3;
+        J_inv[d][k] = (J[k1][d1] * J[k2][d2] - J[k1][d2] * J[k2][d1]) / det_J;
+      }
+    }
+  }
+  return det_J;
+}
+
+CEED_QFUNCTION(MelesOnTheFlyMass)(void *ctx, const CeedInt Q,
+                                  const CeedScalar *const *in,
+                                  CeedScalar *const *out) {
+  const CeedScalar *u = in[0], *dx = in[1], *w = in[2];
+  CeedScalar *v = out[0];
+  for (CeedInt i = 0; i < Q; i++) {
+    CeedScalar J_inv[DIM][DIM];
+    const CeedScalar w_det_J = w[i] * MelesJacobian(dx, Q, i, J_inv);
+    for (CeedInt c = 0; c < NUM_COMP; c++) v[c * Q + i] = w_det_J * u[c * Q + i];
+  }
+  return 0;
+}
+
+CEED_QFUNCTION(MelesOnTheFlyDiffusion)(void *ctx, const CeedInt Q,
+                                       const CeedScalar *const *in,
+                                       CeedScalar *const *out) {
+  const CeedScalar *du = in[0], *dx = in[1], *w = in[2];
+  CeedScalar *dv = out[0];
+  for (CeedInt i = 0; i < Q; i++) {
+    CeedScalar J_inv[DIM][DIM];
+    const CeedScalar w_det_J = w[i] * MelesJacobian(dx, Q, i, J_inv);
+    for (CeedInt c = 0; c < NUM_COMP; c++) {
+      CeedScalar grad_u[DIM];
+      for (CeedInt k = 0; k < DIM; k++) {
+        grad_u[k] = 0.;
+        for (CeedInt d = 0; d < DIM; d++) grad_u[k] += du[(d * NUM_COMP + c) * Q + i] * J_inv[d][k];
+      }
+      for (CeedInt d = 0; d < DIM; d++) {
+        CeedScalar dv_d = 0.;
+        for (CeedInt k = 0; k < DIM; k++) dv_d += J_inv[d][k] * grad_u[k];
+        dv[(d * NUM_COMP + c) * Q + i] = w_det_J * dv_d;
+      }
+    }
+  }
+  return 0;
+}
+";
+
+// Operator applying the benchmark problem kernel with on-the-fly geometric
+//   factors, on the DM set up for the problem
+pub(crate) fn on_the_fly_operator(
+    meles: &crate::Meles,
+    dm: &DM,
+    kernel: crate::ceed_bps::CeedBP,
+    num_components: usize,
+    (p, q, q_mode): (usize, usize, libceed::QuadMode),
+) -> crate::Result<SourceOperator> {
+    let dimension = dm.dimension()?;
+    let tensor_cell = match dimension {
+        2 => petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_QUADRILATERAL,
+        _ => petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_HEXAHEDRON,
+    };
+    if !(2..=3).contains(&dimension) || crate::dm::plex_cell_types(dm)? != vec![tensor_cell] {
+        return Err(crate::Error {
+            message: format!(
+                "on-the-fly geometric factors on libCEED resource {} require a 2D plex mesh of quadrilaterals or a 3D plex mesh of hexahedra",
+                meles.ceed_resource
+            ),
+        });
+    }
+    let mass = matches!(
+        kernel,
+        crate::ceed_bps::CeedBP::BP1 | crate::ceed_bps::CeedBP::BP2
+    );
+    let (function, field) = if mass {
+        (
+            "MelesOnTheFlyMass",
+            (num_components, libceed::EvalMode::Interp),
+        )
+    } else {
+        (
+            "MelesOnTheFlyDiffusion",
+            (num_components * dimension, libceed::EvalMode::Grad),
+        )
+    };
+    let source = crate::QFunctionSource::string(
+        &ON_THE_FLY_SOURCE
+            .replace("MELES_NUM_COMP", &num_components.to_string())
+            .replace("MELES_DIM", &dimension.to_string()),
+        function,
+    )?;
+    let (input_name, output_name) = if mass { ("u", "v") } else { ("du", "dv") };
+    let inputs = [(input_name.to_string(), field.0, field.1)];
+    let outputs = [(output_name.to_string(), field.0, field.1)];
+
+    // Offsets and coordinates
+    let DMPlexLocalOffsets {
+        num_cells,
+        cell_size,
+        num_components: _,
+        l_size,
+        offsets,
+    } = dm.plex_local_offsets(None, 0, 0, 0)?;
+    let DMPlexLocalOffsets {
+        l_size: coordinates_l_size,
+        offsets: coordinate_offsets,
+        ..
+    } = dm.coordinate_dm()?.plex_local_offsets(None, 0, 0, 0)?;
+    let coord_loc = meles.dm.borrow_mut().coordinates_local()?;
+    let coord_loc_view = coord_loc.view()?;
+    let coordinates = coord_loc_view.as_slice().ok_or_else(|| crate::Error {
+        message: "failed to access local coordinate values".to_string(),
+    })?;
+
+    source_operator(
+        &meles.ceed,
+        &source,
+        &offsets,
+        num_cells,
+        cell_size,
+        l_size,
+        num_components,
+        (dimension, p, q, q_mode),
+        &inputs,
+        &outputs,
+        SourceGeometry::Coordinates {
+            offsets: &coordinate_offsets,
+            l_size: coordinates_l_size,
+            values: coordinates,
+        },
+    )
+}
+
+// -----------------------------------------------------------------------------
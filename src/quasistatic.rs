@@ -0,0 +1,630 @@
+use crate::prelude::*;
+use std::convert::TryFrom;
+
+// -----------------------------------------------------------------------------
+// Quasistatic solid mechanics command line options
+// -----------------------------------------------------------------------------
+#[derive(Clone, PartialEq)]
+pub(crate) struct Opt {
+    pub(crate) youngs_modulus: f64,
+    pub(crate) poisson_ratio: f64,
+    pub(crate) bc_displacement: String,
+    pub(crate) body_force: String,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let youngs_modulus = pob.options_real("-E", "Young's modulus", "", 1.0)?;
+        let poisson_ratio = pob.options_real("-nu", "Poisson's ratio", "", 0.3)?;
+        let bc_displacement = pob.options_string(
+            "-bc_displacement",
+            "Comma separated expressions for the boundary displacement, in x, y, z, and load factor t",
+            "",
+            "0,0,0",
+        )?;
+        let body_force = pob.options_string(
+            "-body_force",
+            "Comma separated expressions for the body force, in x, y, z",
+            "",
+            "0,0,-1",
+        )?;
+        Ok(Opt {
+            youngs_modulus,
+            poisson_ratio,
+            bc_displacement,
+            body_force,
+        })
+    }
+}
+
+impl Opt {
+    // Lame parameters
+    fn lame_parameters(&self) -> crate::Result<(f64, f64)> {
+        let (e, nu) = (self.youngs_modulus, self.poisson_ratio);
+        if e <= 0.0 || nu <= -1.0 || nu >= 0.5 {
+            return Err(crate::Error {
+                message: format!(
+                    "invalid material parameters E = {} and nu = {}; require E > 0 and -1 < nu < 0.5",
+                    e, nu
+                ),
+            });
+        }
+        Ok((
+            e / (2.0 * (1.0 + nu)),
+            e * nu / ((1.0 + nu) * (1.0 - 2.0 * nu)),
+        ))
+    }
+}
+
+// Parse comma separated expressions for the components of a vector field
+fn vector_expression(source: &str) -> crate::Result<[crate::Expression; 3]> {
+    let components = source
+        .split(',')
+        .map(crate::Expression::parse)
+        .collect::<crate::Result<Vec<_>>>()?;
+    match <[crate::Expression; 3]>::try_from(components) {
+        Ok(components) => Ok(components),
+        Err(components) => Err(crate::Error {
+            message: format!(
+                "expected 3 comma separated expressions, not {} in {}",
+                components.len(),
+                source
+            ),
+        }),
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup dm with displacement fields
+// -----------------------------------------------------------------------------
+pub(crate) fn create_dm<'a>(
+    comm: &'a mpi::topology::UserCommunicator,
+    bp_options: &crate::ceed_bps::Opt,
+    options: &Opt,
+) -> crate::Result<DM<'a, 'a>> {
+    if bp_options.mesh_type != crate::dm::MeshType::Plex {
+        return Err(crate::Error {
+            message: format!(
+                "quasistatic solid mechanics requires a plex mesh, not {}",
+                bp_options.mesh_type
+            ),
+        });
+    }
+    let mut dm = crate::dm::create_plex(comm, bp_options)?;
+    crate::dm::transform_mesh(&mut dm, bp_options)?;
+    setup_dm(&mut dm, bp_options, options)?;
+
+    Ok(dm)
+}
+
+pub(crate) fn setup_dm<'a>(
+    dm: &mut DM<'a, 'a>,
+    bp_options: &crate::ceed_bps::Opt,
+    options: &Opt,
+) -> crate::Result<()> {
+    if crate::dm::plex_cell_types(dm)?.len() > 1 {
+        return Err(crate::Error {
+            message: "quasistatic solid mechanics requires a mesh with a single cell type"
+                .to_string(),
+        });
+    }
+
+    // Boundary displacement, scaled by the load factor through t
+    let bc_displacement = vector_expression(&options.bc_displacement)?;
+    let user_boundary_function = move |_dim: petsc::Int,
+                                       t: Real,
+                                       x: &[Real],
+                                       _num_components: petsc::Int,
+                                       u: &mut [petsc::Scalar]| {
+        for (u_i, expression) in u.iter_mut().zip(&bc_displacement) {
+            *u_i = expression.evaluate(x, t);
+        }
+        Ok(())
+    };
+    crate::dm::setup_dm_by_order(
+        dm,
+        bp_options.order,
+        NUM_COMPONENTS,
+        true,
+        &bp_options.essential_labels(),
+        Some(user_boundary_function),
+        &[],
+    )?;
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Neo-Hookean hyperelasticity in the initial configuration
+//   P = mu (F - F^{-T}) + lambda log(J) F^{-T}
+//   dP = mu dF + (mu - lambda log(J)) F^{-T} dF^T F^{-T}
+//        + lambda (F^{-T} : dF) F^{-T}
+// -----------------------------------------------------------------------------
+const NUM_COMPONENTS: usize = 3;
+const GRAD_U_SIZE: usize = 9;
+
+type Matrix3 = [[f64; 3]; 3];
+
+fn determinant(a: &Matrix3) -> f64 {
+    a[0][0] * (a[1][1] * a[2][2] - a[1][2] * a[2][1])
+        - a[0][1] * (a[1][0] * a[2][2] - a[1][2] * a[2][0])
+        + a[0][2] * (a[1][0] * a[2][1] - a[1][1] * a[2][0])
+}
+
+// Inverse transpose, A^{-T}[i][j] = cofactor(i, j) / det(A)
+fn inverse_transpose(a: &Matrix3, det_a: f64) -> Matrix3 {
+    let mut a_inv_t = [[0.0; 3]; 3];
+    for (i, row) in a_inv_t.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+            *entry = (a[i1][j1] * a[i2][j2] - a[i1][j2] * a[i2][j1]) / det_a;
+        }
+    }
+    a_inv_t
+}
+
+// Gradient with respect to initial coordinates, du/dX[i][j], from reference
+//   gradients and dxi/dX stored in qdata
+fn initial_gradient(du: &[f64], q_data: &[f64], q: usize, k: usize) -> Matrix3 {
+    let mut grad = [[0.0; 3]; 3];
+    for (i, row) in grad.iter_mut().enumerate() {
+        for (j, g) in row.iter_mut().enumerate() {
+            *g = (0..3)
+                .map(|d| du[(d * NUM_COMPONENTS + i) * q + k] * q_data[(1 + d * 3 + j) * q + k])
+                .sum();
+        }
+    }
+    grad
+}
+
+// Apply the test function gradient, dv = w det(J) P dxi/dX^T
+fn apply_test_gradient(p: &Matrix3, q_data: &[f64], q: usize, k: usize, dv: &mut [f64]) {
+    for d in 0..3 {
+        for (i, p_i) in p.iter().enumerate() {
+            dv[(d * NUM_COMPONENTS + i) * q + k] = q_data[k]
+                * p_i
+                    .iter()
+                    .enumerate()
+                    .map(|(j, p_ij)| p_ij * q_data[(1 + d * 3 + j) * q + k])
+                    .sum::<f64>();
+        }
+    }
+}
+
+fn residual_q_function<'b>(
+    ceed: &libceed::Ceed,
+    mu: f64,
+    lambda: f64,
+) -> crate::Result<QFunction<'b>> {
+    let apply = move |[du, q_data, ..]: QFunctionInputs, [dv, grad_u, ..]: QFunctionOutputs| {
+        let q = q_data.len() / crate::advection::Q_DATA_SIZE;
+        for k in 0..q {
+            // -- Deformation gradient, stored for the Jacobian
+            let h = initial_gradient(du, q_data, q, k);
+            let mut f = h;
+            for (i, (f_i, h_i)) in f.iter_mut().zip(&h).enumerate() {
+                f_i[i] += 1.0;
+                for (j, h_ij) in h_i.iter().enumerate() {
+                    grad_u[(i * 3 + j) * q + k] = *h_ij;
+                }
+            }
+            // -- First Piola-Kirchhoff stress
+            let det_f = determinant(&f);
+            let f_inv_t = inverse_transpose(&f, det_f);
+            let log_j = det_f.ln();
+            let mut p = [[0.0; 3]; 3];
+            for (p_i, (f_i, f_inv_t_i)) in p.iter_mut().zip(f.iter().zip(&f_inv_t)) {
+                for (p_ij, (f_ij, f_inv_t_ij)) in p_i.iter_mut().zip(f_i.iter().zip(f_inv_t_i)) {
+                    *p_ij = mu * (f_ij - f_inv_t_ij) + lambda * log_j * f_inv_t_ij;
+                }
+            }
+            apply_test_gradient(&p, q_data, q, k, dv);
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("du", NUM_COMPONENTS * 3, EvalMode::Grad)?
+        .input("qdata", crate::advection::Q_DATA_SIZE, EvalMode::None)?
+        .output("dv", NUM_COMPONENTS * 3, EvalMode::Grad)?
+        .output("grad_u", GRAD_U_SIZE, EvalMode::None)?;
+    Ok(qf)
+}
+
+fn jacobian_q_function<'b>(
+    ceed: &libceed::Ceed,
+    mu: f64,
+    lambda: f64,
+) -> crate::Result<QFunction<'b>> {
+    let apply = move |[ddu, q_data, grad_u, ..]: QFunctionInputs, [ddv, ..]: QFunctionOutputs| {
+        let q = q_data.len() / crate::advection::Q_DATA_SIZE;
+        for k in 0..q {
+            // -- Deformation gradient at the linearization point
+            let mut f = [[0.0; 3]; 3];
+            for (i, f_i) in f.iter_mut().enumerate() {
+                for (j, f_ij) in f_i.iter_mut().enumerate() {
+                    *f_ij = grad_u[(i * 3 + j) * q + k] + if i == j { 1.0 } else { 0.0 };
+                }
+            }
+            let det_f = determinant(&f);
+            let f_inv_t = inverse_transpose(&f, det_f);
+            let log_j = det_f.ln();
+            // -- Linearized stress
+            let df = initial_gradient(ddu, q_data, q, k);
+            let trace: f64 = (0..3)
+                .flat_map(|i| (0..3).map(move |j| (i, j)))
+                .map(|(i, j)| f_inv_t[i][j] * df[i][j])
+                .sum();
+            let mut dp = [[0.0; 3]; 3];
+            for (i, dp_i) in dp.iter_mut().enumerate() {
+                for (j, dp_ij) in dp_i.iter_mut().enumerate() {
+                    // (F^{-T} dF^T F^{-T})[i][j] = sum_ab F^{-T}[i][a] dF[b][a] F^{-T}[b][j]
+                    let rotated: f64 = (0..3)
+                        .flat_map(|a| (0..3).map(move |b| (a, b)))
+                        .map(|(a, b)| f_inv_t[i][a] * df[b][a] * f_inv_t[b][j])
+                        .sum();
+                    *dp_ij = mu * df[i][j]
+                        + (mu - lambda * log_j) * rotated
+                        + lambda * trace * f_inv_t[i][j];
+                }
+            }
+            apply_test_gradient(&dp, q_data, q, k, ddv);
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("ddu", NUM_COMPONENTS * 3, EvalMode::Grad)?
+        .input("qdata", crate::advection::Q_DATA_SIZE, EvalMode::None)?
+        .input("grad_u", GRAD_U_SIZE, EvalMode::None)?
+        .output("ddv", NUM_COMPONENTS * 3, EvalMode::Grad)?;
+    Ok(qf)
+}
+
+fn body_force_q_function<'b>(
+    ceed: &libceed::Ceed,
+    body_force: [crate::Expression; 3],
+) -> crate::Result<QFunction<'b>> {
+    let apply = move |[x, q_data, ..]: QFunctionInputs, [v, ..]: QFunctionOutputs| {
+        let q = q_data.len() / crate::advection::Q_DATA_SIZE;
+        for k in 0..q {
+            let x_k = [x[k], x[q + k], x[2 * q + k]];
+            for (i, expression) in body_force.iter().enumerate() {
+                v[i * q + k] = q_data[k] * expression.evaluate(&x_k, 0.0);
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("x", 3, EvalMode::Interp)?
+        .input("qdata", crate::advection::Q_DATA_SIZE, EvalMode::None)?
+        .output("v", NUM_COMPONENTS, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+// -----------------------------------------------------------------------------
+// Quasistatic problem
+// -----------------------------------------------------------------------------
+/// Residual and Jacobian of a quasistatic Neo-Hookean hyperelasticity problem
+///
+/// The residual is `F(u) = R(u) - t b`, where `R` is the internal force, `b`
+/// the body force, and `t` the load factor. The load factor also scales the
+/// boundary displacement through `t` in its expressions, so a sequence of
+/// increasing load factors gives load stepping.
+///
+/// The Jacobian MatShell is linearized about the state of the last residual
+/// evaluation, matching the SNES calling sequence.
+pub struct Quasistatic<'a> {
+    pub jacobian: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    dm: DM<'a, 'a>,
+    op_residual: crate::MelesOperator<'a>,
+    u_loc: RefCell<petsc::vector::Vector<'a>>,
+    r_loc: RefCell<petsc::vector::Vector<'a>>,
+    u_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
+    r_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
+    body_force: petsc::vector::Vector<'a>,
+    grad_u: crate::qdata::QDataVector<'a>,
+    load_factor: std::cell::Cell<f64>,
+}
+
+impl<'a> Quasistatic<'a> {
+    /// Returns the DM with the displacement field
+    pub fn dm(&self) -> &DM<'a, 'a> {
+        &self.dm
+    }
+
+    /// Returns a host copy of the displacement gradient at quadrature points
+    ///   from the last residual evaluation
+    pub fn displacement_gradient(&self) -> crate::Result<crate::QData> {
+        self.grad_u.to_host()
+    }
+
+    /// Returns the current load factor
+    pub fn load_factor(&self) -> f64 {
+        self.load_factor.get()
+    }
+
+    /// Set the load factor for subsequent residual evaluations
+    ///
+    /// # arguments
+    ///
+    /// * `load_factor` - Scaling of the body force and time in the boundary
+    ///                     displacement expressions
+    pub fn set_load_factor(&self, load_factor: f64) {
+        self.load_factor.set(load_factor);
+    }
+
+    /// Evaluate the residual `F(u)`, for use as the SNES function
+    ///
+    /// The state at quadrature points is stored for the Jacobian.
+    ///
+    /// # arguments
+    ///
+    /// * `u` - Global displacement, without essential boundary values
+    /// * `r` - Global residual
+    pub fn residual(
+        &self,
+        u: &petsc::vector::Vector<'a>,
+        r: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.residual_raw(u.as_raw(), r.as_raw())
+    }
+
+    // Residual on raw PETSc vectors, shared with the SNES function callback
+    pub(crate) fn residual_raw(
+        &self,
+        u: petsc::petsc_raw::Vec,
+        r: petsc::petsc_raw::Vec,
+    ) -> crate::Result<()> {
+        let mut u_loc = self.u_loc.borrow_mut();
+        let mut r_loc = self.r_loc.borrow_mut();
+
+        // Global to local, with the boundary displacement for the load factor
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let dm = self.dm.as_raw();
+            check(
+                DMGlobalToLocal(dm, u, InsertMode::INSERT_VALUES, u_loc.as_raw()),
+                "DMGlobalToLocal",
+            )?;
+            check(
+                DMPlexInsertBoundaryValues(
+                    dm,
+                    PetscBool::PETSC_TRUE,
+                    u_loc.as_raw(),
+                    self.load_factor.get(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                ),
+                "DMPlexInsertBoundaryValues",
+            )?;
+        }
+
+        // Internal force
+        crate::petsc_ops::apply_ceed_op_local(
+            &self.op_residual,
+            &mut u_loc,
+            &mut self.u_loc_ceed.borrow_mut(),
+            &mut r_loc,
+            &mut self.r_loc_ceed.borrow_mut(),
+        )?;
+
+        // Local to global, less the body force
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(VecZeroEntries(r), "VecZeroEntries")?;
+            check(
+                DMLocalToGlobal(self.dm.as_raw(), r_loc.as_raw(), InsertMode::ADD_VALUES, r),
+                "DMLocalToGlobal",
+            )?;
+            check(
+                VecAXPY(r, -self.load_factor.get(), self.body_force.as_raw()),
+                "VecAXPY",
+            )?;
+        }
+
+        // Jacobian state changed
+        if let Some(context) = self.jacobian.mat_data() {
+            context.invalidate_diagonal();
+        }
+        Ok(())
+    }
+
+    /// Update the Jacobian after a residual evaluation, for use as the SNES
+    /// Jacobian callback
+    pub fn update_jacobian(&self) -> crate::Result<()> {
+        self.jacobian
+            .mat_data()
+            .ok_or_else(|| crate::Error {
+                message: "Jacobian MatShell has no context".to_string(),
+            })?
+            .invalidate_diagonal();
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup quasistatic residual and Jacobian operators
+// -----------------------------------------------------------------------------
+pub(crate) fn quasistatic<'a>(
+    meles: &'a crate::Meles<'a>,
+    bp_options: &crate::ceed_bps::Opt,
+    options: &Opt,
+) -> crate::Result<Quasistatic<'a>> {
+    let (mu, lambda) = options.lame_parameters()?;
+    let body_force = vector_expression(&options.body_force)?;
+    crate::backend::require_closure_q_functions(meles, "Neo-Hookean hyperelasticity operators")?;
+
+    // Duplicate DM
+    let mut dm = meles.dm.borrow().clone();
+    setup_dm(&mut dm, bp_options, options)?;
+
+    // Basis and restrictions
+    let p = bp_options.order + 1;
+    let q = p + bp_options.q_extra;
+    let dimension = dm.dimension()?;
+    let basis_x = meles.ceed.basis_tensor_H1_Lagrange(
+        dimension,
+        dimension,
+        2,
+        q,
+        libceed::QuadMode::Gauss,
+    )?;
+    let basis_u = meles.ceed.basis_tensor_H1_Lagrange(
+        dimension,
+        NUM_COMPONENTS,
+        p,
+        q,
+        libceed::QuadMode::Gauss,
+    )?;
+    let restr_u = crate::dm::create_restriction_from_dm_plex(&dm, &meles.ceed, 0, None, 0)?;
+    let restr_x = {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        crate::dm::create_restriction_from_dm_plex(&mesh_coord_dm, &meles.ceed, 0, None, 0)?
+    };
+    let num_elements = restr_u.num_elements();
+    let num_quadrature_points = basis_u.num_quadrature_points();
+    let restr_qdata = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        num_elements,
+        num_quadrature_points,
+        crate::advection::Q_DATA_SIZE,
+    )?;
+    let restr_grad_u = crate::qdata::QDataVector::restriction(
+        &meles.ceed,
+        num_elements,
+        num_quadrature_points,
+        GRAD_U_SIZE,
+    )?;
+
+    // Geometric setup data and body force
+    let mut qdata = restr_qdata.create_lvector()?;
+    let mut body_force_loc = dm.create_local_vector()?;
+    body_force_loc.zero_entries()?;
+    {
+        let mut coord_loc = {
+            let mut dm = meles.dm.borrow_mut();
+            dm.coordinates_local()?
+        };
+        let mut coord_loc_ceed = meles.ceed.vector(coord_loc.local_size()?)?;
+        let mut body_force_loc_ceed = meles.ceed.vector(body_force_loc.local_size()?)?;
+        let qf_setup = crate::advection::setup_q_function(&meles.ceed)?;
+        let op_setup = meles
+            .ceed
+            .operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("dx", &restr_x, &basis_x, VectorOpt::Active)?
+            .field(
+                "weights",
+                ElemRestrictionOpt::None,
+                &basis_x,
+                VectorOpt::None,
+            )?
+            .field(
+                "qdata",
+                &restr_qdata,
+                BasisOpt::Collocated,
+                VectorOpt::Active,
+            )?
+            .check()?;
+        let qf_body_force = body_force_q_function(&meles.ceed, body_force)?;
+        crate::transfer::with_wrapped(&mut coord_loc, &mut coord_loc_ceed, |coord_loc_ceed| {
+            op_setup.apply(coord_loc_ceed, &mut qdata)?;
+            let op_body_force = meles
+                .ceed
+                .operator(&qf_body_force, QFunctionOpt::None, QFunctionOpt::None)?
+                .field("x", &restr_x, &basis_x, VectorOpt::Active)?
+                .field("qdata", &restr_qdata, BasisOpt::Collocated, &qdata)?
+                .field("v", &restr_u, &basis_u, VectorOpt::Active)?
+                .check()?;
+            crate::transfer::with_wrapped(
+                &mut body_force_loc,
+                &mut body_force_loc_ceed,
+                |body_force_loc_ceed| Ok(op_body_force.apply(coord_loc_ceed, body_force_loc_ceed)?),
+            )
+        })?;
+    }
+    let mut body_force = dm.create_global_vector()?;
+    body_force.zero_entries()?;
+    dm.local_to_global(&body_force_loc, InsertMode::ADD_VALUES, &mut body_force)?;
+    let qdata = std::rc::Rc::new(qdata);
+
+    // Residual and Jacobian operators, sharing the deformation gradient
+    let grad_u = restr_grad_u.create_lvector()?;
+    let qf_residual = residual_q_function(&meles.ceed, mu, lambda)?;
+    let qf_jacobian = jacobian_q_function(&meles.ceed, mu, lambda)?;
+    let op_residual = meles
+        .ceed
+        .operator(&qf_residual, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("du", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &*qdata)?
+        .field("dv", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("grad_u", &restr_grad_u, BasisOpt::Collocated, &grad_u)?
+        .check()?;
+    let op_jacobian = meles
+        .ceed
+        .operator(&qf_jacobian, QFunctionOpt::None, QFunctionOpt::None)?
+        .field("ddu", &restr_u, &basis_u, VectorOpt::Active)?
+        .field("qdata", &restr_qdata, BasisOpt::Collocated, &*qdata)?
+        .field("grad_u", &restr_grad_u, BasisOpt::Collocated, &grad_u)?
+        .field("ddv", &restr_u, &basis_u, VectorOpt::Active)?
+        .check()?;
+
+    // Work vectors
+    let u_loc = dm.create_local_vector()?;
+    let mut r_loc = dm.create_local_vector()?;
+    r_loc.zero_entries()?;
+    let x_loc_size = u_loc.local_size()?;
+
+    // Jacobian MatShell
+    let x_loc = dm.create_local_vector()?;
+    let y_loc = dm.create_local_vector()?;
+    let mut rhs = dm.create_global_vector()?;
+    rhs.zero_entries()?;
+    let jacobian = crate::petsc_ops::create_mat_shell(crate::MelesMatShellContext {
+        ceed: meles.ceed.clone(),
+        dm: RefCell::new(dm.clone()),
+        x_loc: RefCell::new(x_loc),
+        y_loc: RefCell::new(y_loc),
+        x_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        y_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        op_ceed: RefCell::new(op_jacobian.into()),
+        diagonal: RefCell::new(None),
+        num_components: NUM_COMPONENTS,
+        point_block_inverse: RefCell::new(None),
+        rhs,
+        qdata: vec![crate::qdata::QDataVector {
+            vector: qdata,
+            num_elements,
+            num_quadrature_points,
+            size: crate::advection::Q_DATA_SIZE,
+        }],
+        true_solution: vec![],
+        error_ops: vec![],
+        single_precision: false,
+    })?;
+
+    Ok(Quasistatic {
+        jacobian,
+        dm,
+        op_residual: op_residual.into(),
+        u_loc: RefCell::new(u_loc),
+        r_loc: RefCell::new(r_loc),
+        u_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        r_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        body_force,
+        grad_u: crate::qdata::QDataVector {
+            vector: std::rc::Rc::new(grad_u),
+            num_elements,
+            num_quadrature_points,
+            size: GRAD_U_SIZE,
+        },
+        load_factor: std::cell::Cell::new(1.0),
+    })
+}
+
+// -----------------------------------------------------------------------------
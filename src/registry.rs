@@ -0,0 +1,184 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Named operators
+//   The registry holds definitions rather than MatShells, which borrow the
+//   Meles context, so each operator is built on demand on the current mesh
+//   and discretization; built operators are cached without keeping them
+//   alive, so requests share an operator while any caller holds it
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum OperatorDefinition {
+    // Operator of the benchmark problem, as for Meles::mat_shell
+    Problem,
+    // Mass matrix with the components of the problem
+    Mass,
+    // Operator expression in the mass and stiffness operators
+    Expression(String),
+    // Mass matrix on labeled boundary faces
+    BoundaryMass { label: String, values: Vec<usize> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OperatorRegistry {
+    definitions: Vec<(String, OperatorDefinition)>,
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        Self {
+            definitions: vec![
+                ("problem".to_string(), OperatorDefinition::Problem),
+                ("mass".to_string(), OperatorDefinition::Mass),
+                (
+                    "stiffness".to_string(),
+                    OperatorDefinition::Expression("stiffness".to_string()),
+                ),
+            ],
+        }
+    }
+}
+
+impl OperatorRegistry {
+    // Add a definition, replacing any operator of the same name
+    pub(crate) fn register(&mut self, name: &str, definition: OperatorDefinition) {
+        match self
+            .definitions
+            .iter_mut()
+            .find(|(existing, _)| existing == name)
+        {
+            Some((_, existing)) => *existing = definition,
+            None => self.definitions.push((name.to_string(), definition)),
+        }
+    }
+
+    pub(crate) fn definition(&self, name: &str) -> crate::Result<&OperatorDefinition> {
+        self.definitions
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, definition)| definition)
+            .ok_or_else(|| crate::Error {
+                message: format!(
+                    "unknown operator {}, expected one of {}",
+                    name,
+                    self.names().join(", ")
+                ),
+            })
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.definitions
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+type NamedOperator<'a> = petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>;
+
+// Operators built from the registry, cleared when a definition, the mesh, or
+//   the discretization changes; the context for Meles::residual is held
+//   until the cache is cleared, as no caller keeps it alive between calls
+#[derive(Default)]
+pub(crate) struct OperatorCache<'a> {
+    operators: Vec<(String, std::rc::Weak<NamedOperator<'a>>)>,
+    residual: Option<std::rc::Rc<crate::MelesMatShellContext<'a>>>,
+}
+
+impl<'a> OperatorCache<'a> {
+    fn get(&self, name: &str) -> Option<std::rc::Rc<NamedOperator<'a>>> {
+        self.operators
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .and_then(|(_, operator)| operator.upgrade())
+    }
+
+    fn insert(&mut self, name: &str, operator: &std::rc::Rc<NamedOperator<'a>>) {
+        self.operators
+            .retain(|(existing, operator)| existing != name && operator.strong_count() > 0);
+        self.operators
+            .push((name.to_string(), std::rc::Rc::downgrade(operator)));
+    }
+
+    pub(crate) fn invalidate(&mut self, name: &str) {
+        self.operators.retain(|(existing, _)| existing != name);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.operators.clear();
+        self.residual = None;
+    }
+}
+
+// Return the MatShell of a named operator, building it if it is not cached
+pub(crate) fn operator<'a>(
+    meles: &'a crate::Meles<'a>,
+    name: &str,
+) -> crate::Result<std::rc::Rc<NamedOperator<'a>>> {
+    if let Some(operator) = meles.operator_cache.borrow().get(name) {
+        return Ok(operator);
+    }
+    let operator = std::rc::Rc::new(build_operator(meles, name)?);
+    meles.operator_cache.borrow_mut().insert(name, &operator);
+    Ok(operator)
+}
+
+// Return the context of the problem operator for residual evaluation,
+//   building it without assembling the diagonal if it is not cached
+pub(crate) fn residual_context<'a>(
+    meles: &'a crate::Meles<'a>,
+) -> crate::Result<std::rc::Rc<crate::MelesMatShellContext<'a>>> {
+    if let Some(context) = &meles.operator_cache.borrow().residual {
+        return Ok(context.clone());
+    }
+    let context = std::rc::Rc::new(crate::ceed_bps::mat_shell_context(
+        meles,
+        &meles.bp_options,
+    )?);
+    meles.operator_cache.borrow_mut().residual = Some(context.clone());
+    Ok(context)
+}
+
+// Build the MatShell of a named operator
+fn build_operator<'a>(meles: &'a crate::Meles<'a>, name: &str) -> crate::Result<NamedOperator<'a>> {
+    let definition = meles.operators.borrow().definition(name)?.clone();
+    let (context, options) = match definition {
+        OperatorDefinition::Problem => (
+            crate::ceed_bps::mat_shell_context(meles, &meles.bp_options)?,
+            meles.bp_options.clone(),
+        ),
+        OperatorDefinition::Mass => {
+            let kernel = crate::ceed_bps::mass_problem(&meles.bp_options)?;
+            let context =
+                crate::ceed_bps::mat_shell_context_with_kernel(meles, &meles.bp_options, kernel)?;
+            return crate::petsc_ops::create_symmetric_mat_shell(context);
+        }
+        OperatorDefinition::Expression(expression) => {
+            let options = crate::ceed_bps::Opt {
+                operator: Some(expression),
+                ..meles.bp_options.clone()
+            };
+            (
+                crate::ceed_bps::mat_shell_context(meles, &options)?,
+                options,
+            )
+        }
+        OperatorDefinition::BoundaryMass { label, values } => {
+            let context = crate::surface::boundary_mass_context(meles, &label, &values)?;
+            return crate::petsc_ops::create_symmetric_mat_shell(context);
+        }
+    };
+
+    // Singular diffusion operators
+    let mat = crate::petsc_ops::create_symmetric_mat_shell(context)?;
+    if let Some(num_components) = crate::ceed_bps::constant_nullspace_components(&options)? {
+        crate::petsc_ops::set_constant_nullspace(
+            mat.as_raw(),
+            &mat.mat_data().unwrap().dm.borrow(),
+            num_components,
+        )?;
+    }
+    Ok(mat)
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,174 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Row sums
+// -----------------------------------------------------------------------------
+/// Row, column, and absolute row sums of the operator, from element matrices
+///
+/// Row and column sums, and the diagonal, are exact. Absolute row sums are
+/// summed from the absolute values of the element matrices, an upper bound
+/// on the absolute row sums of the assembled operator that is exact unless
+/// contributions of neighboring elements cancel, as l1-Jacobi smoothers
+/// require. Constrained DoFs are excluded.
+///
+/// Note: Requires a plex mesh with a single cell type
+pub struct RowSums<'a> {
+    /// Row sums, `sum_j a_ij`
+    pub row: petsc::vector::Vector<'a>,
+    /// Column sums, `sum_i a_ij`
+    pub column: petsc::vector::Vector<'a>,
+    /// Absolute row sums, `sum_j |a_ij|`
+    pub absolute_row: petsc::vector::Vector<'a>,
+    /// Diagonal, `a_ii`
+    pub diagonal: petsc::vector::Vector<'a>,
+    /// Smallest ratio of the absolute diagonal to the absolute off-diagonal
+    /// row sum of the assembled operator over all rows, at least one for a
+    /// diagonally dominant operator
+    pub min_dominance: f64,
+}
+
+impl<'a> RowSums<'a> {
+    /// Returns true if the operator is diagonally dominant in every row
+    pub fn is_diagonally_dominant(&self) -> bool {
+        self.min_dominance >= 1.0
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Compute row sums
+// -----------------------------------------------------------------------------
+pub(crate) fn row_sums<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+) -> crate::Result<RowSums<'a>> {
+    let crate::ceed_bps::ElementOperator {
+        dm,
+        restr_u: _,
+        op_elem,
+        num_elements,
+        num_nodes_1d,
+        num_components,
+    } = crate::ceed_bps::element_operator(meles, options, None)?;
+    let dimension = dm.dimension()? as u32;
+    let elem_size = num_nodes_1d.pow(dimension) * num_components;
+
+    // Element contributions, skipping constrained rows and columns
+    let element_dofs = crate::assembly::element_global_dofs(&dm, num_components, None)?;
+    let element_matrices =
+        crate::ceed_bps::probe_element_matrices(&meles.ceed, &op_elem, num_elements, elem_size)?;
+    let mut indices = vec![];
+    let mut row: Vec<f64> = vec![];
+    let mut column: Vec<f64> = vec![];
+    let mut absolute_row: Vec<f64> = vec![];
+    let mut diagonal: Vec<f64> = vec![];
+    for (dofs, matrix) in element_dofs
+        .chunks_exact(elem_size)
+        .zip(element_matrices.chunks_exact(elem_size * elem_size))
+    {
+        let a = |i: usize, j: usize| matrix[i * elem_size + j];
+        let active = || (0..elem_size).filter(|&j| dofs[j] >= 0);
+        for (i, &dof) in dofs.iter().enumerate() {
+            if dof < 0 {
+                continue;
+            }
+            indices.push(dof);
+            row.push(active().map(|j| a(i, j)).sum());
+            column.push(active().map(|j| a(j, i)).sum());
+            absolute_row.push(active().map(|j| a(i, j).abs()).sum());
+            diagonal.push(a(i, i));
+        }
+    }
+
+    // Sum over elements and ranks
+    let assemble = |values: &[f64]| -> crate::Result<petsc::vector::Vector<'a>> {
+        let mut vector = dm.create_global_vector()?;
+        vector.zero_entries()?;
+        vector.set_values(&indices, values, InsertMode::ADD_VALUES)?;
+        vector.assembly_begin()?;
+        vector.assembly_end()?;
+        Ok(vector)
+    };
+    let row = assemble(&row)?;
+    let column = assemble(&column)?;
+    let absolute_row = assemble(&absolute_row)?;
+    let diagonal = assemble(&diagonal)?;
+
+    // Diagonal dominance, from the assembled rows, as neighboring element
+    //   contributions to off-diagonal entries may cancel
+    let local_min_dominance = {
+        let mat = dm.create_matrix()?;
+        let (mut rows, mut cols) =
+            crate::assembly::element_coo_indices(&dm, num_components, None, elem_size)?;
+        let mut min_dominance = f64::INFINITY;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(
+                MatSetPreallocationCOO(
+                    mat.as_raw(),
+                    rows.len() as PetscCount,
+                    rows.as_mut_ptr(),
+                    cols.as_mut_ptr(),
+                ),
+                "MatSetPreallocationCOO",
+            )?;
+            check(
+                MatSetValuesCOO(
+                    mat.as_raw(),
+                    element_matrices.as_ptr(),
+                    InsertMode::INSERT_VALUES,
+                ),
+                "MatSetValuesCOO",
+            )?;
+            let (mut row_start, mut row_end) = (0, 0);
+            check(
+                MatGetOwnershipRange(mat.as_raw(), &mut row_start, &mut row_end),
+                "MatGetOwnershipRange",
+            )?;
+            for i in row_start..row_end {
+                let mut num_columns = 0;
+                let mut columns = std::ptr::null();
+                let mut values = std::ptr::null();
+                check(
+                    MatGetRow(mat.as_raw(), i, &mut num_columns, &mut columns, &mut values),
+                    "MatGetRow",
+                )?;
+                let (mut a_ii, mut off_diagonal) = (0.0, 0.0);
+                for k in 0..num_columns as usize {
+                    if *columns.add(k) == i {
+                        a_ii = (*values.add(k)).abs();
+                    } else {
+                        off_diagonal += (*values.add(k)).abs();
+                    }
+                }
+                check(
+                    MatRestoreRow(mat.as_raw(), i, &mut num_columns, &mut columns, &mut values),
+                    "MatRestoreRow",
+                )?;
+                if off_diagonal > 0.0 {
+                    min_dominance = min_dominance.min(a_ii / off_diagonal);
+                }
+            }
+        }
+        min_dominance
+    };
+    let mut min_dominance = f64::INFINITY;
+    {
+        use mpi::collective::{CommunicatorCollectives, SystemOperation};
+        meles.comm.all_reduce_into(
+            &local_min_dominance,
+            &mut min_dominance,
+            SystemOperation::min(),
+        );
+    }
+
+    Ok(RowSums {
+        row,
+        column,
+        absolute_row,
+        diagonal,
+        min_dominance,
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,180 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Diagonally scaled operator
+// -----------------------------------------------------------------------------
+/// Symmetric Jacobi scaling of a frozen operator, `D^{-1/2} A D^{-1/2}`
+///
+/// To solve `A x = b`, solve the scaled system `Â y = D^{-1/2} b` and recover
+/// `x = D^{-1/2} y`.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+/// let meles = meles::Meles::new(
+///     &petsc,
+///     "./examples/meles.yml",
+///     meles::MethodType::BenchmarkProblem,
+/// )?;
+///
+/// // scale operator
+/// let scaled = meles.frozen_operator(&petsc)?.scaled()?;
+/// let mat = scaled.mat_shell()?;
+///
+/// // solve scaled system
+/// let mut b = scaled.frozen().dm().create_global_vector()?;
+/// b.set_all(1.0)?;
+/// let b_scaled = scaled.scale_rhs(&b)?;
+/// let mut y = b_scaled.duplicate()?;
+/// let mut ksp = petsc.ksp_create()?;
+/// ksp.set_operators(&mat, &mat)?;
+/// ksp.solve(&b_scaled, &mut y)?;
+/// let x = scaled.unscale_solution(&y)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScaledOperator<'a> {
+    pub(crate) frozen: std::rc::Rc<crate::FrozenOperator<'a>>,
+    pub(crate) inv_sqrt_diagonal: petsc::vector::Vector<'a>,
+}
+
+// Multiply a vector pointwise by a scaling vector
+pub(crate) fn pointwise_scale(
+    v: &mut petsc::vector::Vector,
+    scaling: &petsc::vector::Vector,
+) -> petsc::Result<()> {
+    let n = v.local_size()? as usize;
+    let scaling_view = scaling.view()?;
+    let mut v_view = v.view_mut()?;
+    for i in 0..n {
+        v_view[i] *= scaling_view[i];
+    }
+    Ok(())
+}
+
+impl<'a> ScaledOperator<'a> {
+    /// Apply the scaled operator, `y = D^{-1/2} A D^{-1/2} x`
+    ///
+    /// # arguments
+    ///
+    /// * `x` - Global input vector
+    /// * `y` - Global output vector
+    pub fn apply(
+        &self,
+        x: &petsc::vector::Vector<'a>,
+        y: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.apply_raw(x.as_raw(), y.as_raw())
+    }
+
+    // Apply the scaled operator to raw global vectors, as given to MatShell
+    //   callbacks
+    fn apply_raw(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let scaling = self.inv_sqrt_diagonal.as_raw();
+        let x_scaled = self.inv_sqrt_diagonal.duplicate()?;
+        check(
+            unsafe { VecPointwiseMult(x_scaled.as_raw(), x, scaling) },
+            "VecPointwiseMult",
+        )?;
+        self.frozen.apply_raw(x_scaled.as_raw(), y)?;
+        check(
+            unsafe { VecPointwiseMult(y, y, scaling) },
+            "VecPointwiseMult",
+        )
+    }
+
+    /// Returns the unscaled operator
+    pub fn frozen(&self) -> &std::rc::Rc<crate::FrozenOperator<'a>> {
+        &self.frozen
+    }
+
+    /// Returns the scaled right hand side, `D^{-1/2} b`
+    ///
+    /// # arguments
+    ///
+    /// * `b` - Right hand side of the unscaled system
+    pub fn scale_rhs(
+        &self,
+        b: &petsc::vector::Vector<'a>,
+    ) -> crate::Result<petsc::vector::Vector<'a>> {
+        let mut b_scaled = b.duplicate()?;
+        b_scaled.copy_data_from(b)?;
+        pointwise_scale(&mut b_scaled, &self.inv_sqrt_diagonal)?;
+        Ok(b_scaled)
+    }
+
+    /// Returns the solution of the unscaled system, `D^{-1/2} y`
+    ///
+    /// # arguments
+    ///
+    /// * `y` - Solution of the scaled system
+    pub fn unscale_solution(
+        &self,
+        y: &petsc::vector::Vector<'a>,
+    ) -> crate::Result<petsc::vector::Vector<'a>> {
+        // D^{-1/2} is applied to both the RHS and the solution
+        self.scale_rhs(y)
+    }
+
+    /// Returns a MatShell applying the scaled operator
+    pub fn mat_shell(
+        self: &std::rc::Rc<Self>,
+    ) -> crate::Result<petsc::mat::MatShell<'a, 'a, std::rc::Rc<ScaledOperator<'a>>>> {
+        let mat = self.frozen.dm.create_matrix()?;
+        let mat = mat.into_shell(Box::new(self.clone()))?;
+
+        // Set operations
+        crate::petsc_ops::set_shell_operations(
+            &mat,
+            &[MatOperation::MATOP_MULT, MatOperation::MATOP_GET_DIAGONAL],
+        )?;
+
+        Ok(mat)
+    }
+}
+
+impl crate::petsc_ops::ShellOperations for ScaledOperator<'_> {
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        self.apply_raw(x, y)
+    }
+
+    fn get_diagonal(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        // Unit diagonal by construction
+        crate::check_petsc_raw(unsafe { petsc::petsc_raw::VecSet(d, 1.0) }, "VecSet")
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Build the scaling from the assembled diagonal
+// -----------------------------------------------------------------------------
+pub(crate) fn scale<'a>(
+    frozen: std::rc::Rc<crate::FrozenOperator<'a>>,
+) -> crate::Result<ScaledOperator<'a>> {
+    let mut inv_sqrt_diagonal = frozen.diagonal.duplicate()?;
+    inv_sqrt_diagonal.copy_data_from(&frozen.diagonal)?;
+    {
+        let n = inv_sqrt_diagonal.local_size()? as usize;
+        let mut view = inv_sqrt_diagonal.view_mut()?;
+        for i in 0..n {
+            if view[i] <= 0.0 {
+                return Err(crate::Error {
+                    message: format!(
+                        "diagonal scaling requires a positive diagonal, found {} at local index {}",
+                        view[i], i
+                    ),
+                });
+            }
+            view[i] = 1.0 / view[i].sqrt();
+        }
+    }
+    Ok(ScaledOperator {
+        frozen,
+        inv_sqrt_diagonal,
+    })
+}
+
+// -----------------------------------------------------------------------------
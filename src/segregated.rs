@@ -0,0 +1,275 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Segregated solve results
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Results of a segregated solve
+pub struct SegregatedResult {
+    /// Number of outer block Gauss-Seidel iterations
+    pub iterations: usize,
+    /// Norm of the coupled residual after the last outer iteration
+    pub residual_norm: f64,
+    /// Total number of inner Krylov iterations of each field
+    pub field_iterations: Vec<usize>,
+}
+
+impl fmt::Display for SegregatedResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Segregated Solve Result:")?;
+        writeln!(f, "  Iterations       : {}", self.iterations)?;
+        writeln!(f, "  Field iterations : {:?}", self.field_iterations)?;
+        write!(f, "  Residual norm    : {:e}", self.residual_norm)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Segregated solver command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) rtol: f64,
+    pub(crate) max_iterations: usize,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let rtol = pob.options_real(
+            "-segregated_rtol",
+            "Relative tolerance of the coupled residual for segregated solves",
+            "",
+            1e-8,
+        )?;
+        let max_iterations = pob.options_usize(
+            "-segregated_max_it",
+            "Maximum number of outer iterations for segregated solves",
+            "",
+            50,
+        )?;
+        Ok(Opt {
+            rtol,
+            max_iterations,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Segregated solver
+//   Each outer iteration sweeps the fields in order, solving each field with
+//   its own KSP against its RHS minus the couplings to the latest values of
+//   the other fields
+// -----------------------------------------------------------------------------
+// Off-diagonal block, acting on the column field and adding to the row field
+struct Coupling<'a, 'b> {
+    row: usize,
+    column: usize,
+    mat: &'b petsc::mat::Mat<'a, 'a>,
+}
+
+/// Block Gauss-Seidel outer iteration for multi-field problems
+///
+/// Each field is solved with its own KSP, so the MatShell and preconditioner
+/// of each field are reused as for a single field solve, and fields are
+/// coupled through off-diagonal operators. This is an alternative to fully
+/// coupled fieldsplit preconditioning when the fields are weakly coupled or
+/// their solvers are already tuned.
+pub struct SegregatedSolver<'a, 'b> {
+    fields: Vec<KSP<'a, 'a>>,
+    couplings: Vec<Coupling<'a, 'b>>,
+    rtol: f64,
+    max_iterations: usize,
+}
+
+impl<'a, 'b> SegregatedSolver<'a, 'b> {
+    /// Add a field solved with a KSP, returning the index of the field
+    ///
+    /// The KSP operator is the diagonal block of the field, and the KSP is
+    /// started from the current value of the field in each outer iteration.
+    ///
+    /// # arguments
+    ///
+    /// * `ksp` - Solver for the field, with operators set
+    pub fn add_field(&mut self, ksp: KSP<'a, 'a>) -> crate::Result<usize> {
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::KSPSetInitialGuessNonzero(
+                    ksp.as_raw(),
+                    petsc::petsc_raw::PetscBool::PETSC_TRUE,
+                )
+            },
+            "KSPSetInitialGuessNonzero",
+        )?;
+        self.fields.push(ksp);
+        Ok(self.fields.len() - 1)
+    }
+
+    /// Add an off-diagonal operator coupling two fields
+    ///
+    /// # arguments
+    ///
+    /// * `row` - Index of the field the coupling contributes to
+    /// * `column` - Index of the field the coupling acts on
+    /// * `mat` - Operator from the column field to the row field
+    pub fn add_coupling(
+        &mut self,
+        row: usize,
+        column: usize,
+        mat: &'b petsc::mat::Mat<'a, 'a>,
+    ) -> crate::Result<()> {
+        if row == column || row >= self.fields.len() || column >= self.fields.len() {
+            return Err(crate::Error {
+                message: format!(
+                    "invalid coupling from field {} to field {} with {} fields",
+                    column,
+                    row,
+                    self.fields.len()
+                ),
+            });
+        }
+        self.couplings.push(Coupling { row, column, mat });
+        Ok(())
+    }
+
+    /// Set the relative tolerance of the coupled residual and the maximum
+    /// number of outer iterations
+    ///
+    /// # arguments
+    ///
+    /// * `rtol` - Relative tolerance of the coupled residual
+    /// * `max_iterations` - Maximum number of outer iterations
+    pub fn set_tolerances(&mut self, rtol: f64, max_iterations: usize) {
+        self.rtol = rtol;
+        self.max_iterations = max_iterations;
+    }
+
+    /// Returns the KSP of a field
+    pub fn field_ksp(&self, field: usize) -> &KSP<'a, 'a> {
+        &self.fields[field]
+    }
+
+    // Residual of the field, b_i - sum_j A_ij x_j, with only the couplings
+    //   when the diagonal block is skipped
+    fn field_residual(
+        &self,
+        field: usize,
+        rhs: &petsc::vector::Vector<'a>,
+        solution: &[petsc::vector::Vector<'a>],
+        include_diagonal: bool,
+        residual: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        residual.copy_data_from(rhs)?;
+        let mut product = rhs.duplicate()?;
+        if include_diagonal {
+            unsafe {
+                use petsc::petsc_raw::*;
+                let check = crate::check_petsc_raw;
+                let mut operator = std::ptr::null_mut();
+                check(
+                    KSPGetOperators(
+                        self.fields[field].as_raw(),
+                        &mut operator,
+                        std::ptr::null_mut(),
+                    ),
+                    "KSPGetOperators",
+                )?;
+                check(
+                    MatMult(operator, solution[field].as_raw(), product.as_raw()),
+                    "MatMult",
+                )?;
+            }
+            residual.axpy(-1.0, &product)?;
+        }
+        for coupling in self.couplings.iter().filter(|c| c.row == field) {
+            coupling
+                .mat
+                .mult(&solution[coupling.column], &mut product)?;
+            residual.axpy(-1.0, &product)?;
+        }
+        Ok(())
+    }
+
+    // Norm of the coupled residual
+    fn residual_norm(
+        &self,
+        rhs: &[petsc::vector::Vector<'a>],
+        solution: &[petsc::vector::Vector<'a>],
+    ) -> crate::Result<f64> {
+        let mut norm_squared = 0.0;
+        for (field, rhs) in rhs.iter().enumerate() {
+            let mut residual = rhs.duplicate()?;
+            self.field_residual(field, rhs, solution, true, &mut residual)?;
+            norm_squared += residual.norm(NormType::NORM_2)?.powi(2);
+        }
+        Ok(norm_squared.sqrt())
+    }
+
+    /// Solve the coupled problem, using the given vectors as the initial
+    /// guess
+    ///
+    /// Outer iterations stop when the coupled residual norm is reduced by
+    /// the relative tolerance or the maximum number of iterations is reached.
+    ///
+    /// # arguments
+    ///
+    /// * `rhs` - RHS of each field
+    /// * `solution` - Initial guess of each field, overwritten with the solution
+    pub fn solve(
+        &mut self,
+        rhs: &[petsc::vector::Vector<'a>],
+        solution: &mut [petsc::vector::Vector<'a>],
+    ) -> crate::Result<SegregatedResult> {
+        if rhs.len() != self.fields.len() || solution.len() != self.fields.len() {
+            return Err(crate::Error {
+                message: format!(
+                    "segregated solve with {} fields given {} RHS and {} solution vectors",
+                    self.fields.len(),
+                    rhs.len(),
+                    solution.len()
+                ),
+            });
+        }
+
+        let initial_norm = self.residual_norm(rhs, solution)?;
+        let mut residual_norm = initial_norm;
+        let mut field_iterations = vec![0; self.fields.len()];
+        let mut iterations = 0;
+        while iterations < self.max_iterations && residual_norm > self.rtol * initial_norm {
+            for field in 0..self.fields.len() {
+                let mut field_rhs = rhs[field].duplicate()?;
+                self.field_residual(field, &rhs[field], solution, false, &mut field_rhs)?;
+                self.fields[field].solve(&field_rhs, &mut solution[field])?;
+                field_iterations[field] += self.fields[field].get_iteration_number()? as usize;
+            }
+            iterations += 1;
+            residual_norm = self.residual_norm(rhs, solution)?;
+        }
+
+        Ok(SegregatedResult {
+            iterations,
+            residual_norm,
+            field_iterations,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup an empty segregated solver with tolerances from the options database
+// -----------------------------------------------------------------------------
+pub(crate) fn segregated_solver<'a, 'b>(
+    petsc: &'a Petsc,
+) -> crate::Result<SegregatedSolver<'a, 'b>> {
+    let Opt {
+        rtol,
+        max_iterations,
+    } = {
+        let _options_lock = crate::config::options_lock();
+        petsc.options()?
+    };
+    Ok(SegregatedSolver {
+        fields: vec![],
+        couplings: vec![],
+        rtol,
+        max_iterations,
+    })
+}
+
+// -----------------------------------------------------------------------------
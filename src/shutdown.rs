@@ -0,0 +1,431 @@
+use crate::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// -----------------------------------------------------------------------------
+// Shutdown reasons
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// Reason a solve stopped early
+pub enum ShutdownReason {
+    /// The `max_walltime` budget was exhausted
+    WalltimeBudget,
+    /// SIGTERM was received, see `install_shutdown_handler`
+    Signal,
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShutdownReason::WalltimeBudget => write!(f, "budget exhausted"),
+            ShutdownReason::Signal => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Shutdown command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) max_walltime: f64,
+    pub(crate) partial_results: String,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let max_walltime = pob.options_real(
+            "-max_walltime",
+            "Wall-clock budget in seconds from setup, after which solves stop with partial results, 0 for none",
+            "",
+            0.0,
+        )?;
+        let partial_results = pob.options_string(
+            "-partial_results",
+            "Directory for the checkpoint and report of solves that stop early",
+            "",
+            "partial_results",
+        )?;
+        Ok(Opt {
+            max_walltime,
+            partial_results,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Budget {
+    pub(crate) deadline: Option<std::time::Instant>,
+    pub(crate) partial_results: std::path::PathBuf,
+}
+
+impl Budget {
+    // The budget starts when the options are read, during setup
+    pub(crate) fn new(options: Opt) -> Self {
+        Self {
+            deadline: if options.max_walltime > 0.0 {
+                Some(
+                    std::time::Instant::now()
+                        + std::time::Duration::from_secs_f64(options.max_walltime),
+                )
+            } else {
+                None
+            },
+            partial_results: options.partial_results.into(),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Shutdown signal
+//   The handler only stores to an atomic, which is async-signal-safe; solvers
+//   poll the flag and the walltime budget between iterations and agree on
+//   them with a reduction, so all ranks stop at the same iteration even when
+//   the batch system signals the ranks at different times
+// -----------------------------------------------------------------------------
+// SIGTERM, the same value on every POSIX platform PETSc supports
+const SIGTERM: std::os::raw::c_int = 15;
+
+static SIGNAL_RECEIVED: AtomicBool = AtomicBool::new(false);
+static HANDLER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+// PETSc signal handler, recording SIGTERM and deferring to the default PETSc
+//   handler for every other signal
+unsafe extern "C" fn signal_handler(
+    signal: std::os::raw::c_int,
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    if signal == SIGTERM {
+        SIGNAL_RECEIVED.store(true, Ordering::SeqCst);
+        0
+    } else {
+        petsc::petsc_raw::PetscSignalHandlerDefault(signal, ctx)
+    }
+}
+
+/// Install a SIGTERM handler for graceful shutdown
+///
+/// After SIGTERM, such as batch system preemption, benchmark problem solves
+/// stop within ten Krylov iterations, reporting `KSP_CONVERGED_USER`, and
+/// time integrators finish the current time step, then write a checkpoint
+/// and partial report to the `partial_results` directory and return as
+/// usual, with the reason in `SolveResult::interrupted` and
+/// `Meles::shutdown_reason` on every rank, so the run can exit cleanly. Every
+/// other signal is handled by PETSc as before.
+///
+/// The handler is process wide, so it must be installed on every rank, after
+/// PETSc is initialized; installing it again has no effect.
+pub fn install_shutdown_handler() -> crate::Result<()> {
+    if HANDLER_INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::PetscPushSignalHandler(Some(signal_handler), std::ptr::null_mut())
+        },
+        "PetscPushSignalHandler",
+    )
+}
+
+// Check if polling is needed, the same on every rank
+fn is_active(budget: &Budget) -> bool {
+    HANDLER_INSTALLED.load(Ordering::SeqCst) || budget.deadline.is_some()
+}
+
+// Collective check if any rank received SIGTERM or exhausted the budget,
+//   without communication when neither is possible
+pub(crate) fn shutdown_reason(
+    comm: &mpi::topology::UserCommunicator,
+    budget: &Budget,
+) -> Option<ShutdownReason> {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    if !is_active(budget) {
+        return None;
+    }
+    let local = if SIGNAL_RECEIVED.load(Ordering::SeqCst) {
+        2
+    } else if budget
+        .deadline
+        .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+    {
+        1
+    } else {
+        0
+    };
+    let mut global: i32 = 0;
+    comm.all_reduce_into(&local, &mut global, SystemOperation::max());
+    match global {
+        0 => None,
+        1 => Some(ShutdownReason::WalltimeBudget),
+        _ => Some(ShutdownReason::Signal),
+    }
+}
+
+// Polling context of PETSc callbacks
+struct ShutdownContext<'a> {
+    comm: &'a mpi::topology::UserCommunicator,
+    budget: Budget,
+    default_context: *mut std::os::raw::c_void,
+}
+
+// -----------------------------------------------------------------------------
+// Stop Krylov iterations on shutdown
+//   The default convergence test is wrapped, so the iteration in progress
+//   completes and the KSP reports user convergence; the reduction is only
+//   every few iterations, the same on every rank, so it stays cheap relative
+//   to the inner products of the Krylov method
+// -----------------------------------------------------------------------------
+const POLL_ITERATIONS: petsc::Int = 10;
+
+unsafe extern "C" fn converged_or_shutdown(
+    ksp: petsc::petsc_raw::KSP,
+    iteration: petsc::Int,
+    residual_norm: petsc::Real,
+    reason: *mut petsc::petsc_raw::KSPConvergedReason,
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    let context = &*(ctx as *const ShutdownContext);
+    let ierr = KSPConvergedDefault(
+        ksp,
+        iteration,
+        residual_norm,
+        reason,
+        context.default_context,
+    );
+    if ierr != 0 {
+        return ierr;
+    }
+    if *reason == KSPConvergedReason::KSP_CONVERGED_ITERATING
+        && iteration > 0
+        && iteration % POLL_ITERATIONS == 0
+        && shutdown_reason(context.comm, &context.budget).is_some()
+    {
+        *reason = KSPConvergedReason::KSP_CONVERGED_USER;
+    }
+    0
+}
+
+unsafe extern "C" fn destroy_convergence_context(
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    let context = Box::from_raw(ctx as *mut ShutdownContext);
+    petsc::petsc_raw::KSPConvergedDefaultDestroy(context.default_context)
+}
+
+pub(crate) fn set_up_ksp<'a>(ksp: &KSP<'a, 'a>, meles: &'a crate::Meles<'a>) -> crate::Result<()> {
+    if !is_active(&meles.budget) {
+        return Ok(());
+    }
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut default_context = std::ptr::null_mut();
+        check(
+            KSPConvergedDefaultCreate(&mut default_context),
+            "KSPConvergedDefaultCreate",
+        )?;
+        let context = Box::into_raw(Box::new(ShutdownContext {
+            comm: meles.comm,
+            budget: meles.budget.clone(),
+            default_context,
+        }));
+        check(
+            KSPSetConvergenceTest(
+                ksp.as_raw(),
+                Some(converged_or_shutdown),
+                context as *mut std::os::raw::c_void,
+                Some(destroy_convergence_context),
+            ),
+            "KSPSetConvergenceTest",
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Stop time integration on shutdown
+//   The post-step hook runs after each completed time step and sets the user
+//   converged reason, so TSSolve returns without starting another step; the
+//   hook has no context argument, so the context is composed with the TS
+// -----------------------------------------------------------------------------
+const SHUTDOWN_CONTEXT_NAME: &str = "meles_shutdown";
+
+unsafe extern "C" fn post_step_shutdown(
+    ts: petsc::petsc_raw::TS,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    let name = std::ffi::CString::new(SHUTDOWN_CONTEXT_NAME).unwrap();
+    let mut container = std::ptr::null_mut();
+    let mut pointer = std::ptr::null_mut();
+    if PetscObjectQuery(ts as PetscObject, name.as_ptr(), &mut container) != 0
+        || container.is_null()
+        || PetscContainerGetPointer(container as PetscContainer, &mut pointer) != 0
+    {
+        return PETSC_ERR_PLIB as PetscErrorCode;
+    }
+    let context = &*(pointer as *const ShutdownContext);
+    if shutdown_reason(context.comm, &context.budget).is_some() {
+        TSSetConvergedReason(ts, TSConvergedReason::TS_CONVERGED_USER)
+    } else {
+        0
+    }
+}
+
+unsafe extern "C" fn destroy_post_step_context(
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    drop(Box::from_raw(ctx as *mut ShutdownContext));
+    0
+}
+
+pub(crate) fn set_up_ts<'a>(ts: &TS<'a, 'a>, meles: &'a crate::Meles<'a>) -> crate::Result<()> {
+    if !is_active(&meles.budget) {
+        return Ok(());
+    }
+    let name = std::ffi::CString::new(SHUTDOWN_CONTEXT_NAME).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(ts.as_raw() as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut container = std::ptr::null_mut();
+        check(
+            PetscContainerCreate(comm, &mut container),
+            "PetscContainerCreate",
+        )?;
+        let context = Box::into_raw(Box::new(ShutdownContext {
+            comm: meles.comm,
+            budget: meles.budget.clone(),
+            default_context: std::ptr::null_mut(),
+        }));
+        let result = check(
+            PetscContainerSetPointer(container, context as *mut std::os::raw::c_void),
+            "PetscContainerSetPointer",
+        )
+        .and_then(|_| {
+            check(
+                PetscContainerSetUserDestroy(container, Some(destroy_post_step_context)),
+                "PetscContainerSetUserDestroy",
+            )
+        });
+        if let Err(err) = result {
+            drop(Box::from_raw(context));
+            check(
+                PetscContainerDestroy(&mut container),
+                "PetscContainerDestroy",
+            )?;
+            return Err(err);
+        }
+        let result = check(
+            PetscObjectCompose(
+                ts.as_raw() as PetscObject,
+                name.as_ptr(),
+                container as PetscObject,
+            ),
+            "PetscObjectCompose",
+        );
+        check(
+            PetscContainerDestroy(&mut container),
+            "PetscContainerDestroy",
+        )?;
+        result?;
+        check(
+            TSSetPostStep(ts.as_raw(), Some(post_step_shutdown)),
+            "TSSetPostStep",
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Partial results
+//   A checkpoint to restart from and a report of where the run stopped, with
+//   the run manifest so the partial numbers are traceable
+// -----------------------------------------------------------------------------
+pub(crate) fn write_partial_results<'a>(
+    meles: &'a crate::Meles<'a>,
+    directory: &std::path::Path,
+    solution: &petsc::vector::Vector<'a>,
+    state: &crate::SolverState,
+    result: Option<&crate::SolveResult<'a>>,
+) -> crate::Result<()> {
+    use mpi::traits::Communicator;
+    let status = shutdown_reason(meles.comm, &meles.budget)
+        .map_or_else(|| "running".to_string(), |reason| reason.to_string());
+    std::fs::create_dir_all(directory)?;
+    crate::checkpoint::write_checkpoint(
+        meles,
+        &directory.join("checkpoint.h5"),
+        solution,
+        state,
+        None,
+    )?;
+
+    // Report, from the first rank
+    let mut report = crate::manifest::run_manifest(meles)?.to_yml();
+    report += &format!(
+        "status: {}\ntime: {:e}\ntime_step: {:e}\nstep: {}\nload_step: {}\nload_factor: {:e}\n",
+        status, state.time, state.time_step, state.step, state.load_step, state.load_factor
+    );
+    if let Some(result) = result {
+        report += &format!(
+            "iterations: {}\nresidual_norm: {:e}\n",
+            result.iterations, result.residual_norm
+        );
+    }
+    if meles.comm.rank() == 0 {
+        std::fs::write(directory.join("report.yml"), report)?;
+    }
+    Ok(())
+}
+
+// Check if a KSP stopped for shutdown, rather than converging or diverging
+pub(crate) fn ksp_stopped(ksp: &KSP) -> crate::Result<bool> {
+    use petsc::petsc_raw::*;
+    let mut reason = KSPConvergedReason::KSP_CONVERGED_ITERATING;
+    crate::check_petsc_raw(
+        unsafe { KSPGetConvergedReason(ksp.as_raw(), &mut reason) },
+        "KSPGetConvergedReason",
+    )?;
+    Ok(reason == KSPConvergedReason::KSP_CONVERGED_USER)
+}
+
+// Check if a TS stopped for shutdown, rather than reaching the final time
+pub(crate) fn ts_stopped(ts: &TS) -> crate::Result<bool> {
+    use petsc::petsc_raw::*;
+    let mut reason = TSConvergedReason::TS_CONVERGED_ITERATING;
+    crate::check_petsc_raw(
+        unsafe { TSGetConvergedReason(ts.as_raw(), &mut reason) },
+        "TSGetConvergedReason",
+    )?;
+    Ok(reason == TSConvergedReason::TS_CONVERGED_USER)
+}
+
+// Write partial results to the partial results directory if the solve
+//   stopped early
+//   The converged reason is the same on every rank, so solves that finished
+//   are not reported as stopped, even if the budget ran out afterwards
+pub(crate) fn finish_solve<'a>(
+    meles: &'a crate::Meles<'a>,
+    stopped: bool,
+    solution: &petsc::vector::Vector<'a>,
+    state: &crate::SolverState,
+    result: Option<&crate::SolveResult<'a>>,
+) -> crate::Result<Option<ShutdownReason>> {
+    if !stopped {
+        return Ok(None);
+    }
+    let reason = shutdown_reason(meles.comm, &meles.budget);
+    if reason.is_some() {
+        write_partial_results(
+            meles,
+            &meles.budget.partial_results,
+            solution,
+            state,
+            result,
+        )?;
+    }
+    Ok(reason)
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,810 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Snapshot format enum
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SnapshotFormat {
+    Vtu,
+    Hdf5,
+}
+
+impl std::str::FromStr for SnapshotFormat {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "vtu" => Ok(SnapshotFormat::Vtu),
+            "hdf5" => Ok(SnapshotFormat::Hdf5),
+            _ => Err(crate::Error {
+                message: format!(
+                    "failed to parse snapshot format {}, expected vtu or hdf5",
+                    s
+                ),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotFormat::Vtu => write!(f, "vtu"),
+            SnapshotFormat::Hdf5 => write!(f, "hdf5"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Snapshot command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) snapshot_interval: usize,
+    pub(crate) snapshot_directory: String,
+    pub(crate) snapshot_format: SnapshotFormat,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let snapshot_interval = pob.options_usize(
+            "-ts_snapshot_interval",
+            "Write a snapshot of the solution every this many time steps, 0 for none",
+            "",
+            0,
+        )?;
+        let snapshot_directory = pob.options_string(
+            "-ts_snapshot_directory",
+            "Directory for transient solution snapshots",
+            "",
+            "snapshots",
+        )?;
+        let snapshot_format = pob.options_from_string(
+            "-ts_snapshot_format",
+            "Format of transient solution snapshots, vtu or hdf5",
+            "",
+            SnapshotFormat::Vtu,
+        )?;
+        Ok(Opt {
+            snapshot_interval,
+            snapshot_directory,
+            snapshot_format,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Snapshot mesh
+//   The local points and cells of a plex mesh, staged once on the host with
+//   cell points in VTK order, so the writer thread needs no PETSc calls;
+//   linear meshes write the vertices, and higher order tensor cells write every
+//   node, with each cell subdivided into linear cells between its nodes
+// -----------------------------------------------------------------------------
+struct SnapshotMesh {
+    coordinates: Vec<f64>,
+    connectivity: Vec<usize>,
+    offsets: Vec<usize>,
+    cell_types: Vec<u8>,
+    // Offsets of the DoFs of each point in the local solution vector
+    point_dofs: Vec<usize>,
+    num_components: usize,
+}
+
+// VTK cell type of a DMPlex polytope
+fn vtk_cell_type(cell_type: petsc::petsc_raw::DMPolytopeType) -> crate::Result<u8> {
+    use petsc::petsc_raw::DMPolytopeType::*;
+    match cell_type {
+        DM_POLYTOPE_SEGMENT => Ok(3),
+        DM_POLYTOPE_TRIANGLE => Ok(5),
+        DM_POLYTOPE_QUADRILATERAL => Ok(9),
+        DM_POLYTOPE_TETRAHEDRON => Ok(10),
+        DM_POLYTOPE_HEXAHEDRON => Ok(12),
+        DM_POLYTOPE_TRI_PRISM => Ok(13),
+        _ => Err(crate::Error {
+            message: format!("snapshots do not support cell type {:?}", cell_type),
+        }),
+    }
+}
+
+impl SnapshotMesh {
+    fn new(dm: &DM, order: usize) -> crate::Result<Self> {
+        if order == 1 {
+            Self::vertices(dm)
+        } else {
+            Self::nodes(dm, order)
+        }
+    }
+
+    // Vertices of cells of any type, for linear meshes
+    fn vertices(dm: &DM) -> crate::Result<Self> {
+        let (cell_start, cell_end) = crate::dm::plex_cell_range(dm)?;
+        let mut mesh = SnapshotMesh {
+            coordinates: vec![],
+            connectivity: vec![],
+            offsets: vec![],
+            cell_types: vec![],
+            point_dofs: vec![],
+            num_components: 0,
+        };
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let (mut vertex_start, mut vertex_end) = (0, 0);
+            check(
+                DMPlexGetDepthStratum(dm.as_raw(), 0, &mut vertex_start, &mut vertex_end),
+                "DMPlexGetDepthStratum",
+            )?;
+
+            // Vertex coordinates, padded to 3D
+            let mut coordinate_dim = 0;
+            check(
+                DMGetCoordinateDim(dm.as_raw(), &mut coordinate_dim),
+                "DMGetCoordinateDim",
+            )?;
+            let mut coordinate_section = std::ptr::null_mut();
+            check(
+                DMGetCoordinateSection(dm.as_raw(), &mut coordinate_section),
+                "DMGetCoordinateSection",
+            )?;
+            let mut coord_loc = std::ptr::null_mut();
+            check(
+                DMGetCoordinatesLocal(dm.as_raw(), &mut coord_loc),
+                "DMGetCoordinatesLocal",
+            )?;
+            let mut coord_array = std::ptr::null();
+            check(
+                VecGetArrayRead(coord_loc, &mut coord_array),
+                "VecGetArrayRead",
+            )?;
+            let mut section = std::ptr::null_mut();
+            check(
+                DMGetLocalSection(dm.as_raw(), &mut section),
+                "DMGetLocalSection",
+            )?;
+            for vertex in vertex_start..vertex_end {
+                let mut offset = 0;
+                check(
+                    PetscSectionGetOffset(coordinate_section, vertex, &mut offset),
+                    "PetscSectionGetOffset",
+                )?;
+                for d in 0..3 {
+                    mesh.coordinates.push(if d < coordinate_dim {
+                        *coord_array.offset((offset + d) as isize)
+                    } else {
+                        0.0
+                    });
+                }
+                let (mut dof, mut dof_offset) = (0, 0);
+                check(
+                    PetscSectionGetDof(section, vertex, &mut dof),
+                    "PetscSectionGetDof",
+                )?;
+                check(
+                    PetscSectionGetOffset(section, vertex, &mut dof_offset),
+                    "PetscSectionGetOffset",
+                )?;
+                mesh.num_components = mesh.num_components.max(dof as usize);
+                mesh.point_dofs.push(dof_offset as usize);
+            }
+            check(
+                VecRestoreArrayRead(coord_loc, &mut coord_array),
+                "VecRestoreArrayRead",
+            )?;
+
+            // Cell vertices, inverted to VTK order as by the PETSc VTU viewer
+            for cell in cell_start..cell_end {
+                let mut cell_type = DMPolytopeType::DM_POLYTOPE_UNKNOWN;
+                check(
+                    DMPlexGetCellType(dm.as_raw(), cell, &mut cell_type),
+                    "DMPlexGetCellType",
+                )?;
+                mesh.cell_types.push(vtk_cell_type(cell_type)?);
+                let mut closure_size = 0;
+                let mut closure = std::ptr::null_mut();
+                check(
+                    DMPlexGetTransitiveClosure(
+                        dm.as_raw(),
+                        cell,
+                        PetscBool::PETSC_TRUE,
+                        &mut closure_size,
+                        &mut closure,
+                    ),
+                    "DMPlexGetTransitiveClosure",
+                )?;
+                let mut vertices = std::slice::from_raw_parts(closure, 2 * closure_size as usize)
+                    .iter()
+                    .step_by(2)
+                    .cloned()
+                    .filter(|point| (vertex_start..vertex_end).contains(point))
+                    .collect::<Vec<_>>();
+                check(
+                    DMPlexRestoreTransitiveClosure(
+                        dm.as_raw(),
+                        cell,
+                        PetscBool::PETSC_TRUE,
+                        &mut closure_size,
+                        &mut closure,
+                    ),
+                    "DMPlexRestoreTransitiveClosure",
+                )?;
+                check(
+                    DMPlexInvertCell(cell_type, vertices.as_mut_ptr()),
+                    "DMPlexInvertCell",
+                )?;
+                mesh.connectivity.extend(
+                    vertices
+                        .iter()
+                        .map(|vertex| (vertex - vertex_start) as usize),
+                );
+                mesh.offsets.push(mesh.connectivity.len());
+            }
+        }
+        Ok(mesh)
+    }
+
+    // Nodes of tensor cells of higher order, at the Gauss-Lobatto points of the
+    //   libCEED bases, in the tensor closure order of the problem operators
+    fn nodes(dm: &DM, order: usize) -> crate::Result<Self> {
+        let dimension = dm.dimension()?;
+        let tensor_cell = match dimension {
+            2 => petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_QUADRILATERAL,
+            _ => petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_HEXAHEDRON,
+        };
+        if !(2..=3).contains(&dimension) || crate::dm::plex_cell_types(dm)? != vec![tensor_cell] {
+            return Err(crate::Error {
+                message: format!(
+                    "snapshots of order {} require a 2D plex mesh of quadrilaterals or a 3D plex mesh of hexahedra",
+                    order
+                ),
+            });
+        }
+        let DMPlexLocalOffsets {
+            num_cells,
+            cell_size,
+            num_components,
+            offsets,
+            ..
+        } = dm.plex_local_offsets(None, 0, 0, 0)?;
+        let DMPlexLocalOffsets {
+            cell_size: num_vertices,
+            num_components: coordinate_dim,
+            offsets: coordinate_offsets,
+            ..
+        } = dm.coordinate_dm()?.plex_local_offsets(None, 0, 0, 0)?;
+        let num_nodes_1d = order + 1;
+        let (gll_points, _) = crate::dg::gauss_lobatto_quadrature(num_nodes_1d);
+        let reference = gll_points
+            .iter()
+            .map(|xi| (xi + 1.0) / 2.0)
+            .collect::<Vec<_>>();
+        let node_index = |i: [usize; 3]| i[0] + num_nodes_1d * (i[1] + num_nodes_1d * i[2]);
+        let mut mesh = SnapshotMesh {
+            coordinates: Vec::with_capacity(3 * num_cells * cell_size),
+            connectivity: vec![],
+            offsets: vec![],
+            cell_types: vec![],
+            point_dofs: Vec::with_capacity(num_cells * cell_size),
+            num_components,
+        };
+        let coordinates = unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut coord_loc = std::ptr::null_mut();
+            check(
+                DMGetCoordinatesLocal(dm.as_raw(), &mut coord_loc),
+                "DMGetCoordinatesLocal",
+            )?;
+            let mut size = 0;
+            check(VecGetLocalSize(coord_loc, &mut size), "VecGetLocalSize")?;
+            let mut coord_array = std::ptr::null();
+            check(
+                VecGetArrayRead(coord_loc, &mut coord_array),
+                "VecGetArrayRead",
+            )?;
+            let coordinates = std::slice::from_raw_parts(coord_array, size as usize).to_vec();
+            check(
+                VecRestoreArrayRead(coord_loc, &mut coord_array),
+                "VecRestoreArrayRead",
+            )?;
+            coordinates
+        };
+        for cell in 0..num_cells {
+            // Nodes, mapped multilinearly from the cell vertices
+            let first_point = mesh.point_dofs.len();
+            for node in 0..cell_size {
+                let i = [
+                    node % num_nodes_1d,
+                    (node / num_nodes_1d) % num_nodes_1d,
+                    node / (num_nodes_1d * num_nodes_1d),
+                ];
+                let mut x = [0.0; 3];
+                for vertex in 0..num_vertices {
+                    let weight = (0..dimension)
+                        .map(|d| {
+                            let s = reference[i[d]];
+                            if (vertex >> d) & 1 == 1 {
+                                s
+                            } else {
+                                1.0 - s
+                            }
+                        })
+                        .product::<f64>();
+                    let offset = coordinate_offsets[cell * num_vertices + vertex] as usize;
+                    for (d, x_d) in x.iter_mut().take(coordinate_dim).enumerate() {
+                        *x_d += weight * coordinates[offset + d];
+                    }
+                }
+                mesh.coordinates.extend_from_slice(&x);
+                mesh.point_dofs
+                    .push(offsets[cell * cell_size + node] as usize);
+            }
+
+            // Linear cells between the nodes, with vertices in VTK order
+            let num_sub = if dimension == 3 { order } else { 1 };
+            for c in 0..num_sub {
+                for b in 0..order {
+                    for a in 0..order {
+                        let corners: &[[usize; 3]] = if dimension == 3 {
+                            &[
+                                [a, b, c],
+                                [a + 1, b, c],
+                                [a + 1, b + 1, c],
+                                [a, b + 1, c],
+                                [a, b, c + 1],
+                                [a + 1, b, c + 1],
+                                [a + 1, b + 1, c + 1],
+                                [a, b + 1, c + 1],
+                            ]
+                        } else {
+                            &[[a, b, 0], [a + 1, b, 0], [a + 1, b + 1, 0], [a, b + 1, 0]]
+                        };
+                        mesh.connectivity.extend(
+                            corners
+                                .iter()
+                                .map(|&corner| first_point + node_index(corner)),
+                        );
+                        mesh.offsets.push(mesh.connectivity.len());
+                        mesh.cell_types.push(vtk_cell_type(tensor_cell)?);
+                    }
+                }
+            }
+        }
+        Ok(mesh)
+    }
+
+    // Write the point values of a snapshot as a VTU file
+    fn write_vtu(&self, path: &std::path::Path, values: &[f64]) -> crate::Result<()> {
+        use std::fmt::Write;
+        let join = |values: &mut dyn Iterator<Item = String>| values.collect::<Vec<_>>().join(" ");
+        let mut vtu = String::new();
+        writeln!(
+            vtu,
+            "<?xml version=\"1.0\"?>\n<VTKFile type=\"UnstructuredGrid\" version=\"0.1\" byte_order=\"LittleEndian\">\n<UnstructuredGrid>\n<Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">",
+            self.point_dofs.len(),
+            self.cell_types.len()
+        )
+        .unwrap();
+        writeln!(
+            vtu,
+            "<PointData>\n<DataArray type=\"Float64\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"ascii\">\n{}\n</DataArray>\n</PointData>",
+            SOLUTION_NAME,
+            self.num_components,
+            join(&mut values.iter().map(|value| format!("{:e}", value)))
+        )
+        .unwrap();
+        writeln!(
+            vtu,
+            "<Points>\n<DataArray type=\"Float64\" NumberOfComponents=\"3\" format=\"ascii\">\n{}\n</DataArray>\n</Points>",
+            join(&mut self.coordinates.iter().map(|x| format!("{:e}", x)))
+        )
+        .unwrap();
+        writeln!(
+            vtu,
+            "<Cells>\n<DataArray type=\"Int64\" Name=\"connectivity\" format=\"ascii\">\n{}\n</DataArray>\n<DataArray type=\"Int64\" Name=\"offsets\" format=\"ascii\">\n{}\n</DataArray>\n<DataArray type=\"UInt8\" Name=\"types\" format=\"ascii\">\n{}\n</DataArray>\n</Cells>",
+            join(&mut self.connectivity.iter().map(|i| i.to_string())),
+            join(&mut self.offsets.iter().map(|i| i.to_string())),
+            join(&mut self.cell_types.iter().map(|i| i.to_string()))
+        )
+        .unwrap();
+        vtu += "</Piece>\n</UnstructuredGrid>\n</VTKFile>\n";
+        std::fs::write(path, vtu)?;
+        Ok(())
+    }
+}
+
+const SOLUTION_NAME: &str = "solution";
+
+// -----------------------------------------------------------------------------
+// Snapshot writer
+//   Each rank writes its piece of each snapshot from a helper thread, so the
+//   time loop only stages point values into a host buffer; rank 0 also
+//   writes the parallel VTU of each snapshot and a PVD time series
+//   At most a few snapshots are staged, so the time loop waits on a slow
+//   filesystem rather than staging without bound
+// -----------------------------------------------------------------------------
+const STAGED_SNAPSHOTS: usize = 2;
+
+enum Message {
+    Write {
+        step: usize,
+        time: f64,
+        values: Vec<f64>,
+    },
+    Flush(std::sync::mpsc::Sender<crate::Result<()>>),
+}
+
+pub(crate) struct SnapshotWriter {
+    sender: Option<std::sync::mpsc::SyncSender<Message>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl SnapshotWriter {
+    fn new(
+        mesh: SnapshotMesh,
+        directory: std::path::PathBuf,
+        rank: usize,
+        size: usize,
+    ) -> crate::Result<Self> {
+        std::fs::create_dir_all(&directory)?;
+        let (sender, receiver) = std::sync::mpsc::sync_channel(STAGED_SNAPSHOTS);
+        let thread = std::thread::Builder::new()
+            .name(format!("meles-snapshots-{}", rank))
+            .spawn(move || {
+                let mut error = None;
+                let mut series = vec![];
+                for message in receiver {
+                    match message {
+                        Message::Write { step, time, values } => {
+                            if error.is_some() {
+                                continue;
+                            }
+                            let name = format!("{}_{:06}", SOLUTION_NAME, step);
+                            let piece = |rank: usize| format!("{}_{}.vtu", name, rank);
+                            let mut result = mesh.write_vtu(&directory.join(piece(rank)), &values);
+                            if rank == 0 && result.is_ok() {
+                                series.push((time, format!("{}.pvtu", name)));
+                                result = write_pvtu(
+                                    &directory.join(format!("{}.pvtu", name)),
+                                    mesh.num_components,
+                                    &(0..size).map(piece).collect::<Vec<_>>(),
+                                )
+                                .and_then(|_| {
+                                    write_pvd(
+                                        &directory.join(format!("{}.pvd", SOLUTION_NAME)),
+                                        &series,
+                                    )
+                                });
+                            }
+                            error = result.err();
+                        }
+                        Message::Flush(done) => {
+                            let _ = done.send(match error.take() {
+                                Some(error) => Err(error),
+                                None => Ok(()),
+                            });
+                        }
+                    }
+                }
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    fn send(&self, message: Message) -> crate::Result<()> {
+        self.sender
+            .as_ref()
+            .and_then(|sender| sender.send(message).ok())
+            .ok_or_else(|| crate::Error {
+                message: "snapshot writer thread stopped".to_string(),
+            })
+    }
+
+    // Wait for the pending snapshots, returning the first write error
+    pub(crate) fn flush(&self) -> crate::Result<()> {
+        let (done, wait) = std::sync::mpsc::channel();
+        self.send(Message::Flush(done))?;
+        wait.recv().map_err(|_| crate::Error {
+            message: "snapshot writer thread stopped".to_string(),
+        })?
+    }
+}
+
+impl Drop for SnapshotWriter {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Parallel VTU referencing the piece of each rank
+fn write_pvtu(
+    path: &std::path::Path,
+    num_components: usize,
+    pieces: &[String],
+) -> crate::Result<()> {
+    let mut pvtu = format!(
+        "<?xml version=\"1.0\"?>\n<VTKFile type=\"PUnstructuredGrid\" version=\"0.1\" byte_order=\"LittleEndian\">\n<PUnstructuredGrid GhostLevel=\"0\">\n<PPointData>\n<PDataArray type=\"Float64\" Name=\"{}\" NumberOfComponents=\"{}\"/>\n</PPointData>\n<PPoints>\n<PDataArray type=\"Float64\" NumberOfComponents=\"3\"/>\n</PPoints>\n",
+        SOLUTION_NAME, num_components
+    );
+    for piece in pieces {
+        pvtu += &format!("<Piece Source=\"{}\"/>\n", piece);
+    }
+    pvtu += "</PUnstructuredGrid>\n</VTKFile>\n";
+    std::fs::write(path, pvtu)?;
+    Ok(())
+}
+
+// PVD collection of the snapshots written so far, with their times
+fn write_pvd(path: &std::path::Path, series: &[(f64, String)]) -> crate::Result<()> {
+    let mut pvd =
+        "<?xml version=\"1.0\"?>\n<VTKFile type=\"Collection\" version=\"0.1\">\n<Collection>\n"
+            .to_string();
+    for (time, file) in series {
+        pvd += &format!("<DataSet timestep=\"{:e}\" file=\"{}\"/>\n", time, file);
+    }
+    pvd += "</Collection>\n</VTKFile>\n";
+    std::fs::write(path, pvd)?;
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// HDF5 snapshots
+//   The DM is written once, and the global solution of each snapshot is a
+//   timestep of the solution dataset, with its time in the root attribute
+//   time_<timestep>; PETSc writes HDF5 collectively, so snapshots are written
+//   in the time loop, and the file is closed with the TS
+// -----------------------------------------------------------------------------
+struct Hdf5Snapshots {
+    viewer: petsc::petsc_raw::PetscViewer,
+    named: petsc::petsc_raw::Vec,
+    count: std::cell::Cell<usize>,
+}
+
+impl Hdf5Snapshots {
+    fn new(
+        dm: &DM,
+        template: &petsc::vector::Vector,
+        directory: &std::path::Path,
+    ) -> crate::Result<Self> {
+        if !crate::has_petsc_package("hdf5")? {
+            return Err(crate::Error {
+                message: "HDF5 snapshots require PETSc configured with hdf5".to_string(),
+            });
+        }
+        std::fs::create_dir_all(directory)?;
+        let path = directory.join(format!("{}.h5", SOLUTION_NAME));
+        let filename =
+            std::ffi::CString::new(path.to_string_lossy().as_ref()).map_err(|_| crate::Error {
+                message: format!("invalid HDF5 snapshot filename {}", path.display()),
+            })?;
+        let solution_name = std::ffi::CString::new(SOLUTION_NAME).unwrap();
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut comm = std::mem::zeroed();
+            check(
+                PetscObjectGetComm(dm.as_raw() as PetscObject, &mut comm),
+                "PetscObjectGetComm",
+            )?;
+            let mut viewer = std::ptr::null_mut();
+            check(
+                PetscViewerHDF5Open(
+                    comm,
+                    filename.as_ptr(),
+                    PetscFileMode::FILE_MODE_WRITE,
+                    &mut viewer,
+                ),
+                "PetscViewerHDF5Open",
+            )?;
+            let result = (|| -> crate::Result<Vec> {
+                check(DMView(dm.as_raw(), viewer), "DMView")?;
+                check(
+                    PetscViewerHDF5PushTimestepping(viewer),
+                    "PetscViewerHDF5PushTimestepping",
+                )?;
+                crate::checkpoint::named_duplicate(template, &solution_name)
+            })();
+            match result {
+                Ok(named) => Ok(Self {
+                    viewer,
+                    named,
+                    count: std::cell::Cell::new(0),
+                }),
+                Err(err) => {
+                    check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    fn write(&self, u: petsc::petsc_raw::Vec, time: f64) -> crate::Result<()> {
+        let timestep = self.count.get();
+        let root = std::ffi::CString::new("/").unwrap();
+        let attribute = std::ffi::CString::new(format!("time_{}", timestep)).unwrap();
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            check(
+                PetscViewerHDF5SetTimestep(self.viewer, timestep as petsc::Int),
+                "PetscViewerHDF5SetTimestep",
+            )?;
+            check(VecCopy(u, self.named), "VecCopy")?;
+            check(VecView(self.named, self.viewer), "VecView")?;
+            check(
+                PetscViewerHDF5WriteAttribute(
+                    self.viewer,
+                    root.as_ptr(),
+                    attribute.as_ptr(),
+                    PetscDataType::PETSC_REAL,
+                    &time as *const f64 as *const std::os::raw::c_void,
+                ),
+                "PetscViewerHDF5WriteAttribute",
+            )?;
+        }
+        self.count.set(timestep + 1);
+        Ok(())
+    }
+}
+
+impl Drop for Hdf5Snapshots {
+    fn drop(&mut self) {
+        // Errors cannot be propagated from drop
+        unsafe {
+            petsc::petsc_raw::VecDestroy(&mut self.named);
+            petsc::petsc_raw::PetscViewerDestroy(&mut self.viewer);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Snapshot monitor
+//   VTU snapshots stage the local solution, with essential boundary values at
+//   the step time, into a host buffer of point values in a work vector of the
+//   monitor, and hand it to the writer; the operator context, which provides
+//   the DM, outlives the TS, as for the IJacobian
+// -----------------------------------------------------------------------------
+enum SnapshotOutput<'a> {
+    Vtu {
+        loc: RefCell<petsc::vector::Vector<'a>>,
+        point_dofs: Vec<usize>,
+        num_components: usize,
+        writer: std::rc::Rc<SnapshotWriter>,
+    },
+    Hdf5(Hdf5Snapshots),
+}
+
+struct SnapshotMonitor<'a> {
+    meles: &'a crate::Meles<'a>,
+    context: *const crate::MelesMatShellContext<'a>,
+    interval: usize,
+    output: SnapshotOutput<'a>,
+}
+
+impl<'a> SnapshotMonitor<'a> {
+    fn write(&self, u: petsc::petsc_raw::Vec, step: usize, time: f64) -> crate::Result<()> {
+        match &self.output {
+            SnapshotOutput::Vtu {
+                loc,
+                point_dofs,
+                num_components,
+                writer,
+            } => {
+                let context = unsafe { &*self.context };
+                let dm = context.dm.borrow();
+                let mut loc = loc.borrow_mut();
+                crate::transfer::global_to_local_raw(
+                    &self.meles.bp_options,
+                    &dm,
+                    u,
+                    &mut loc,
+                    time,
+                )?;
+                let values = {
+                    let loc_view = loc.view()?;
+                    point_dofs
+                        .iter()
+                        .flat_map(|&offset| (0..*num_components).map(move |i| offset + i))
+                        .map(|i| loc_view[i])
+                        .collect()
+                };
+                writer.send(Message::Write { step, time, values })
+            }
+            SnapshotOutput::Hdf5(snapshots) => snapshots.write(u, time),
+        }
+    }
+}
+
+unsafe extern "C" fn monitor_snapshot(
+    _ts: petsc::petsc_raw::TS,
+    step: petsc::Int,
+    time: petsc::Real,
+    u: petsc::petsc_raw::Vec,
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    let monitor = &*(ctx as *const SnapshotMonitor);
+    if step as usize % monitor.interval != 0 {
+        return 0;
+    }
+    match monitor.write(u, step as usize, time) {
+        Ok(()) => 0,
+        Err(err) => crate::petsc_callback_error("monitor_snapshot", err),
+    }
+}
+
+unsafe extern "C" fn destroy_monitor_context(
+    ctx: *mut *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    drop(Box::from_raw(*ctx as *mut SnapshotMonitor));
+    *ctx = std::ptr::null_mut();
+    0
+}
+
+// Write snapshots of the solution of a TS, returning the VTU writer to flush
+//   after the solve, or None if VTU snapshots are off
+pub(crate) fn set_up_ts<'a>(
+    ts: &TS<'a, 'a>,
+    meles: &'a crate::Meles<'a>,
+    context: &crate::MelesMatShellContext<'a>,
+    options: Opt,
+) -> crate::Result<Option<std::rc::Rc<SnapshotWriter>>> {
+    use mpi::traits::Communicator;
+    if options.snapshot_interval == 0 {
+        return Ok(None);
+    }
+    if meles.bp_options.mesh_type != crate::dm::MeshType::Plex {
+        return Err(crate::Error {
+            message: format!(
+                "snapshots require a plex mesh, not {}",
+                meles.bp_options.mesh_type
+            ),
+        });
+    }
+    let directory = std::path::PathBuf::from(options.snapshot_directory);
+    let (output, writer) = match options.snapshot_format {
+        SnapshotFormat::Vtu => {
+            let dm = context.dm.borrow();
+            let mesh = SnapshotMesh::new(&dm, meles.bp_options.order)?;
+            let (point_dofs, num_components) = (mesh.point_dofs.clone(), mesh.num_components);
+            let writer = std::rc::Rc::new(SnapshotWriter::new(
+                mesh,
+                directory,
+                meles.comm.rank() as usize,
+                meles.comm.size() as usize,
+            )?);
+            let output = SnapshotOutput::Vtu {
+                loc: RefCell::new(dm.create_local_vector()?),
+                point_dofs,
+                num_components,
+                writer: writer.clone(),
+            };
+            (output, Some(writer))
+        }
+        SnapshotFormat::Hdf5 => {
+            let snapshots = Hdf5Snapshots::new(&context.dm.borrow(), &context.rhs, &directory)?;
+            (SnapshotOutput::Hdf5(snapshots), None)
+        }
+    };
+    let context = Box::into_raw(Box::new(SnapshotMonitor {
+        meles,
+        context: context as *const crate::MelesMatShellContext<'a>,
+        interval: options.snapshot_interval,
+        output,
+    }));
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::TSMonitorSet(
+                ts.as_raw(),
+                Some(monitor_snapshot),
+                context as *mut std::os::raw::c_void,
+                Some(destroy_monitor_context),
+            )
+        },
+        "TSMonitorSet",
+    )?;
+    Ok(writer)
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,670 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Solve results
+// -----------------------------------------------------------------------------
+/// Results of a full benchmark problem solve
+pub struct SolveResult<'a> {
+    pub solution: petsc::vector::Vector<'a>,
+    pub iterations: usize,
+    pub residual_norm: f64,
+    /// Reason the solve stopped early, with partial results written
+    pub interrupted: Option<crate::ShutdownReason>,
+}
+
+impl<'a> fmt::Display for SolveResult<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Solve Result:")?;
+        writeln!(f, "  Iterations    : {}", self.iterations)?;
+        write!(f, "  Residual norm : {:e}", self.residual_norm)?;
+        if let Some(reason) = self.interrupted {
+            write!(f, "\n  Interrupted   : {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Solve the benchmark problem with the RHS from its forcing term
+// -----------------------------------------------------------------------------
+pub(crate) fn solve<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<SolveResult<'a>> {
+    let _stage = crate::events::Stage::Solve.push();
+
+    // Operator and solver
+    let mat = meles.mat_shell(petsc)?;
+    let preconditioner = preconditioner(meles, petsc)?;
+    let mut ksp = KSP::create(meles.comm)?;
+    ksp.set_operators(&mat, &mat)?;
+    if let Some(preconditioner) = &preconditioner {
+        preconditioner.set_up_ksp(&mut ksp)?;
+    }
+    {
+        let _options_lock = crate::config::options_lock();
+        ksp.set_from_options()?;
+    }
+    crate::shutdown::set_up_ksp(&ksp, meles)?;
+
+    // Solve
+    let rhs = &mat.mat_data().unwrap().rhs;
+    let mut solution = rhs.duplicate()?;
+    solution.zero_entries()?;
+    ksp.solve(rhs, &mut solution)?;
+    let iterations = ksp.get_iteration_number()? as usize;
+    let residual_norm = ksp.get_residual_norm()?;
+    let stopped = crate::shutdown::ksp_stopped(&ksp)?;
+
+    // Partial results if stopped early
+    let mut result = SolveResult {
+        solution,
+        iterations,
+        residual_norm,
+        interrupted: None,
+    };
+    result.interrupted = crate::shutdown::finish_solve(
+        meles,
+        stopped,
+        &result.solution,
+        &crate::SolverState::default(),
+        Some(&result),
+    )?;
+    Ok(result)
+}
+
+// -----------------------------------------------------------------------------
+// Preconditioners from the options database
+//   A lower order rediscretization, with the assembled preconditioner on its
+//   coarse level, or the assembled preconditioner on the full order matrix
+// -----------------------------------------------------------------------------
+enum Preconditioner<'a> {
+    LowerOrder(crate::multigrid::LowerOrderPreconditioner<'a>),
+    Assembled(crate::AssembledOperator<'a>, crate::assembly::AssembledPC),
+}
+
+impl<'a> Preconditioner<'a> {
+    // Set the PC of a KSP, before the KSP is set from options
+    fn set_up_ksp(&self, ksp: &mut KSP<'a, 'a>) -> crate::Result<()> {
+        match self {
+            Preconditioner::LowerOrder(lower_order) => lower_order.set_up_ksp(ksp),
+            Preconditioner::Assembled(pmat, assembled_pc) => {
+                let mut pc = std::ptr::null_mut();
+                unsafe {
+                    use petsc::petsc_raw::*;
+                    let check = crate::check_petsc_raw;
+                    let mut amat = std::ptr::null_mut();
+                    check(
+                        KSPGetOperators(ksp.as_raw(), &mut amat, std::ptr::null_mut()),
+                        "KSPGetOperators",
+                    )?;
+                    check(
+                        KSPSetOperators(ksp.as_raw(), amat, pmat.mat.as_raw()),
+                        "KSPSetOperators",
+                    )?;
+                    check(KSPGetPC(ksp.as_raw(), &mut pc), "KSPGetPC")?;
+                }
+                assembled_pc.set_up_pc(pc)
+            }
+        }
+    }
+
+    // Re-assemble the preconditioning matrix, for a rebuild of the PC
+    fn reassemble(&mut self) -> crate::Result<()> {
+        match self {
+            Preconditioner::LowerOrder(lower_order) => lower_order.reassemble(),
+            Preconditioner::Assembled(pmat, _) => pmat.reassemble(),
+        }
+    }
+}
+
+fn preconditioner<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<Option<Preconditioner<'a>>> {
+    let pc_type_name = std::ffi::CString::new("-pc_type").unwrap();
+    let (mg_options, assembled_options, pc_type): (
+        crate::multigrid::Opt,
+        crate::assembly::Opt,
+        Option<String>,
+    ) = {
+        let _options_lock = crate::config::options_lock();
+        (
+            petsc.options()?,
+            petsc.options()?,
+            crate::config::options_get_string(&pc_type_name)?,
+        )
+    };
+    let assembled_pc = assembled_options.assembled_pc;
+
+    // The KSP is set from options after the PC is set up, so a PC type would
+    //   silently replace the selected preconditioner
+    if let Some(pc_type) = pc_type {
+        let selected = match (mg_options.lower_order_pc_order, assembled_pc) {
+            (0, crate::assembly::AssembledPC::None) => None,
+            (0, _) => Some(format!("assembled_pc {}", assembled_pc)),
+            (order, _) => Some(format!("lower_order_pc_order {}", order)),
+        };
+        if let Some(selected) = selected {
+            return Err(crate::Error {
+                message: format!(
+                    "pc_type {} conflicts with {}; set only one of them",
+                    pc_type, selected
+                ),
+            });
+        }
+    }
+    match (mg_options.lower_order_pc_order, assembled_pc) {
+        (0, crate::assembly::AssembledPC::None) => Ok(None),
+        (0, _) => {
+            assembled_pc.check_available()?;
+            let pmat = crate::assembly::assembled_pmat(meles, &meles.bp_options)?;
+            Ok(Some(Preconditioner::Assembled(pmat, assembled_pc)))
+        }
+        (order, crate::assembly::AssembledPC::None) => Ok(Some(Preconditioner::LowerOrder(
+            crate::multigrid::lower_order_preconditioner(
+                meles,
+                order,
+                crate::assembly::AssembledPC::Gamg,
+            )?,
+        ))),
+        (order, coarse_pc) => Ok(Some(Preconditioner::LowerOrder(
+            crate::multigrid::lower_order_preconditioner(meles, order, coarse_pc)?,
+        ))),
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Solve sessions
+//   The KSP holds the operator through PETSc reference counting, so the
+//   session keeps the MatShell alive and the PC is set up once for all solves
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Initial guess for each solve of a `SolveSession`
+pub enum InitialGuess {
+    /// Start from zero
+    Zero,
+    /// Start from the previous solution
+    Previous,
+    /// Start from the linear extrapolation of the previous two solutions,
+    ///   `2 u_{n-1} - u_{n-2}`, as for time steps with a constant step size
+    Extrapolation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Krylov subspace recycling between the solves of a `SolveSession`
+pub enum Recycling {
+    /// No recycling
+    None,
+    /// GCRO-DR from HPDDM, keeping this many harmonic Ritz vectors from each
+    ///   solve to deflate the next, for slowly varying operators
+    Gcrodr(usize),
+    /// Initial guess from the projection of the RHS onto the span of up to
+    ///   this many previous solutions, PETSc's Fischer `KSPGuess`, for many
+    ///   RHS with the same operator; this replaces the initial guess strategy
+    SolutionSpace(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// When the preconditioner is rebuilt from the operator
+///
+/// MatShell operators do not change between solves unless replaced, so the
+/// policy decides whether a changed operator, such as a nonlinear Jacobian or
+/// a slowly varying coefficient, also rebuilds the preconditioner.
+pub enum PreconditionerReuse {
+    /// Rebuild for every solve or Newton step
+    Rebuild,
+    /// Rebuild every this many solves or Newton steps
+    Lag(usize),
+    /// Rebuild only after the operator is marked changed
+    OnChange,
+}
+
+impl std::str::FromStr for PreconditionerReuse {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "rebuild" => Ok(PreconditionerReuse::Rebuild),
+            "on_change" => Ok(PreconditionerReuse::OnChange),
+            _ => s
+                .strip_prefix("lag:")
+                .and_then(|lag| lag.parse::<usize>().ok())
+                .filter(|&lag| lag > 0)
+                .map(PreconditionerReuse::Lag)
+                .ok_or_else(|| crate::Error {
+                    message: format!(
+                        "failed to parse preconditioner reuse {}, expected rebuild, lag:N, or on_change",
+                        s
+                    ),
+                }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Preconditioner reuse command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct ReuseOpt {
+    pc_reuse: String,
+}
+
+impl petsc::Opt for ReuseOpt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let pc_reuse = pob.options_string(
+            "-pc_reuse",
+            "When the preconditioner is rebuilt, rebuild, lag:N for every N solves or Newton steps, or on_change, defaulting to on_change for solve sessions and rebuild for nonlinear solves",
+            "",
+            "",
+        )?;
+        Ok(ReuseOpt { pc_reuse })
+    }
+}
+
+impl ReuseOpt {
+    // Policy from the options database, or the default of the solver
+    pub(crate) fn pc_reuse(
+        &self,
+        default: PreconditionerReuse,
+    ) -> crate::Result<PreconditionerReuse> {
+        match self.pc_reuse.trim() {
+            "" => Ok(default),
+            pc_reuse => pc_reuse.parse(),
+        }
+    }
+}
+
+// Mark the operators of a KSP changed, so PCSetUp rebuilds from them
+//   PETSc compares the object state of the operators with the state at the
+//   last setup; PetscObjectStateIncrease is a C macro, so the state is set
+//   directly, as assembly would reset the scale and shift of a MatShell
+pub(crate) fn mark_changed(ksp: petsc::petsc_raw::KSP) -> crate::Result<()> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let (mut amat, mut pmat) = (std::ptr::null_mut(), std::ptr::null_mut());
+        check(
+            KSPGetOperators(ksp, &mut amat, &mut pmat),
+            "KSPGetOperators",
+        )?;
+        let mats = if pmat == amat {
+            vec![amat]
+        } else {
+            vec![amat, pmat]
+        };
+        for mat in mats {
+            let mut state = 0;
+            check(
+                PetscObjectStateGet(mat as PetscObject, &mut state),
+                "PetscObjectStateGet",
+            )?;
+            check(
+                PetscObjectStateSet(mat as PetscObject, state + 1),
+                "PetscObjectStateSet",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Repeated solves of the benchmark problem operator with many RHS
+///
+/// The KSP and PC are set up once and reused for every solve, so parameter
+/// sweeps and time steps do not rebuild the solver, unless the
+/// `PreconditionerReuse` policy set with `pc_reuse` rebuilds the PC. The KSP
+/// is configured from the options database when the session is created.
+pub struct SolveSession<'a> {
+    pub ksp: KSP<'a, 'a>,
+    // Dropped after the KSP that references them
+    mat: std::rc::Rc<petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>>,
+    preconditioner: Option<Preconditioner<'a>>,
+    initial_guess: InitialGuess,
+    recycling: Recycling,
+    // KSP type replaced by GCRO-DR recycling
+    ksp_type: Option<std::ffi::CString>,
+    // Previous solutions, most recent last
+    history: std::collections::VecDeque<petsc::vector::Vector<'a>>,
+    pc_reuse: PreconditionerReuse,
+    // Solves since the PC was last rebuilt, and changes since
+    solves_since_setup: usize,
+    operator_changed: bool,
+    meles: &'a crate::Meles<'a>,
+}
+
+impl<'a> SolveSession<'a> {
+    /// Returns the operator MatShell
+    pub fn mat_shell(&self) -> &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>> {
+        &self.mat
+    }
+
+    /// Returns the RHS from the benchmark problem forcing term
+    pub fn rhs(&self) -> &petsc::vector::Vector<'a> {
+        self.mat.mat_data().unwrap().rhs()
+    }
+
+    /// Set the initial guess strategy for subsequent solves
+    pub fn set_initial_guess(&mut self, initial_guess: InitialGuess) {
+        self.initial_guess = initial_guess;
+    }
+
+    /// Set Krylov subspace recycling for subsequent solves
+    ///
+    /// `Recycling::Gcrodr` changes the KSP type to HPDDM GCRO-DR, which
+    /// requires PETSc configured with HPDDM. Changing the recycling, including
+    /// to `Recycling::None`, undoes the previous setting first.
+    pub fn set_recycling(&mut self, recycling: Recycling) -> crate::Result<()> {
+        self.clear_recycling()?;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let ksp = self.ksp.as_raw();
+            match recycling {
+                Recycling::None => {}
+                Recycling::Gcrodr(num_vectors) => {
+                    // Restored when recycling is changed
+                    let mut ksp_type = std::ptr::null();
+                    check(KSPGetType(ksp, &mut ksp_type), "KSPGetType")?;
+                    self.ksp_type = Some(std::ffi::CStr::from_ptr(ksp_type).to_owned());
+
+                    let hpddm = std::ffi::CString::new("hpddm").unwrap();
+                    check(KSPSetType(ksp, hpddm.as_ptr()), "KSPSetType")?;
+                    check(
+                        KSPHPDDMSetType(ksp, KSPHPDDMType::KSP_HPDDM_TYPE_GCRODR),
+                        "KSPHPDDMSetType",
+                    )?;
+                    // The recycled subspace size is only set from options, so
+                    //   the option is set only while the KSP reads it
+                    let mut prefix = std::ptr::null();
+                    check(KSPGetOptionsPrefix(ksp, &mut prefix), "KSPGetOptionsPrefix")?;
+                    let prefix = if prefix.is_null() {
+                        String::new()
+                    } else {
+                        std::ffi::CStr::from_ptr(prefix)
+                            .to_string_lossy()
+                            .into_owned()
+                    };
+                    let _options_lock = crate::config::options_lock();
+                    let mut overrides = crate::config::OptionOverrides::new();
+                    overrides.set(
+                        &format!("-{}ksp_hpddm_recycle", prefix),
+                        Some(&num_vectors.to_string()),
+                    )?;
+                    check(KSPSetFromOptions(ksp), "KSPSetFromOptions")?;
+                    drop(overrides);
+                }
+                Recycling::SolutionSpace(num_vectors) => {
+                    let guess_type = std::ffi::CString::new("fischer").unwrap();
+                    let mut guess = std::ptr::null_mut();
+                    check(KSPGetGuess(ksp, &mut guess), "KSPGetGuess")?;
+                    check(
+                        KSPGuessSetType(guess, guess_type.as_ptr()),
+                        "KSPGuessSetType",
+                    )?;
+                    check(
+                        KSPGuessFischerSetModel(guess, 1, num_vectors as petsc::Int),
+                        "KSPGuessFischerSetModel",
+                    )?;
+                }
+            }
+        }
+        self.recycling = recycling;
+        Ok(())
+    }
+
+    // Undo the current recycling, restoring the KSP type replaced by GCRO-DR
+    //   or replacing the Fischer guess with an empty guess
+    fn clear_recycling(&mut self) -> crate::Result<()> {
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let ksp = self.ksp.as_raw();
+            match self.recycling {
+                Recycling::None => {}
+                Recycling::Gcrodr(_) => {
+                    if let Some(ksp_type) = self.ksp_type.take() {
+                        check(KSPSetType(ksp, ksp_type.as_ptr()), "KSPSetType")?;
+                    }
+                }
+                Recycling::SolutionSpace(_) => {
+                    let mut comm = std::mem::zeroed();
+                    check(
+                        PetscObjectGetComm(ksp as PetscObject, &mut comm),
+                        "PetscObjectGetComm",
+                    )?;
+                    let mut guess = std::ptr::null_mut();
+                    check(KSPGuessCreate(comm, &mut guess), "KSPGuessCreate")?;
+                    let ierr = KSPSetGuess(ksp, guess);
+                    check(KSPGuessDestroy(&mut guess), "KSPGuessDestroy")?;
+                    check(ierr, "KSPSetGuess")?;
+                }
+            }
+        }
+        self.recycling = Recycling::None;
+        Ok(())
+    }
+
+    /// Replace the operator, such as a slowly varying operator in
+    ///   continuation or time stepping
+    ///
+    /// The PC is not rebuilt, so it is reused for the new operator, unless
+    /// marked changed with `SolveSession::mark_operator_changed` or rebuilt by
+    /// the `PreconditionerReuse` policy, and any recycled subspace is kept.
+    ///
+    /// # arguments
+    ///
+    /// * `mat` - New operator, on the same DM
+    pub fn set_mat_shell(
+        &mut self,
+        mat: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    ) -> crate::Result<()> {
+        // An assembled preconditioning matrix is kept
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut pmat = std::ptr::null_mut();
+            check(
+                KSPGetOperators(self.ksp.as_raw(), std::ptr::null_mut(), &mut pmat),
+                "KSPGetOperators",
+            )?;
+            if pmat == self.mat.as_raw() {
+                pmat = mat.as_raw();
+            }
+            check(
+                KSPSetOperators(self.ksp.as_raw(), mat.as_raw(), pmat),
+                "KSPSetOperators",
+            )?;
+        }
+        self.mat = std::rc::Rc::new(mat);
+        Ok(())
+    }
+
+    /// Set when the PC is rebuilt for subsequent solves
+    ///
+    /// `PreconditionerReuse::Lag` rebuilds every this many solves.
+    pub fn set_preconditioner_reuse(&mut self, pc_reuse: PreconditionerReuse) {
+        self.pc_reuse = pc_reuse;
+    }
+
+    /// Mark the operator changed, such as after updating its quadrature data
+    ///   in place
+    ///
+    /// The cached diagonal is discarded, and with
+    /// `PreconditionerReuse::OnChange` the PC is rebuilt for the next solve,
+    /// re-assembling any assembled or lower order preconditioning matrix.
+    pub fn mark_operator_changed(&mut self) {
+        self.mat.mat_data().unwrap().invalidate_diagonal();
+        self.operator_changed = true;
+    }
+
+    /// Forget previous solutions, such as when the sequence of RHS restarts
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    // Initial guess from the previous solutions, or None for zero
+    fn guess(&self) -> crate::Result<Option<petsc::vector::Vector<'a>>> {
+        let mut previous = self.history.iter().rev();
+        let guess = match (self.initial_guess, previous.next(), previous.next()) {
+            (InitialGuess::Zero, _, _) | (_, None, _) => None,
+            (InitialGuess::Previous, Some(u_1), _)
+            | (InitialGuess::Extrapolation, Some(u_1), None) => {
+                let mut u = u_1.duplicate()?;
+                u.copy_data_from(u_1)?;
+                Some(u)
+            }
+            (InitialGuess::Extrapolation, Some(u_1), Some(u_2)) => {
+                let mut u = u_1.duplicate()?;
+                u.copy_data_from(u_1)?;
+                u.scale(2.0)?;
+                u.axpy(-1.0, u_2)?;
+                Some(u)
+            }
+        };
+        Ok(guess)
+    }
+
+    /// Solve with the given RHS, reusing the KSP and PC setup
+    ///
+    /// # arguments
+    ///
+    /// * `rhs` - Global RHS vector
+    pub fn solve(&mut self, rhs: &petsc::vector::Vector<'a>) -> crate::Result<SolveResult<'a>> {
+        // The Fischer guess forms its own initial guess from a zero vector
+        let guess = match self.recycling {
+            Recycling::SolutionSpace(_) => None,
+            _ => self.guess()?,
+        };
+        let (mut solution, nonzero) = match guess {
+            Some(guess) => (guess, true),
+            None => {
+                let mut solution = rhs.duplicate()?;
+                solution.zero_entries()?;
+                (solution, false)
+            }
+        };
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::KSPSetInitialGuessNonzero(
+                    self.ksp.as_raw(),
+                    if nonzero {
+                        petsc::petsc_raw::PetscBool::PETSC_TRUE
+                    } else {
+                        petsc::petsc_raw::PetscBool::PETSC_FALSE
+                    },
+                )
+            },
+            "KSPSetInitialGuessNonzero",
+        )?;
+
+        // Rebuild the PC by policy, after the first solve sets it up
+        let rebuild = match self.pc_reuse {
+            PreconditionerReuse::Rebuild => true,
+            PreconditionerReuse::Lag(lag) => self.solves_since_setup >= lag,
+            PreconditionerReuse::OnChange => self.operator_changed,
+        };
+        if rebuild {
+            // The operator may have changed in place, so the cached diagonal
+            //   and any assembled preconditioning matrix are recomputed,
+            //   except for the first setup
+            self.mat.mat_data().unwrap().invalidate_diagonal();
+            if self.solves_since_setup > 0 || self.operator_changed {
+                if let Some(preconditioner) = &mut self.preconditioner {
+                    preconditioner.reassemble()?;
+                }
+            }
+            mark_changed(self.ksp.as_raw())?;
+            self.solves_since_setup = 0;
+            self.operator_changed = false;
+        }
+        crate::check_petsc_raw(
+            unsafe {
+                petsc::petsc_raw::KSPSetReusePreconditioner(
+                    self.ksp.as_raw(),
+                    if rebuild {
+                        petsc::petsc_raw::PetscBool::PETSC_FALSE
+                    } else {
+                        petsc::petsc_raw::PetscBool::PETSC_TRUE
+                    },
+                )
+            },
+            "KSPSetReusePreconditioner",
+        )?;
+        self.ksp.solve(rhs, &mut solution)?;
+        self.solves_since_setup += 1;
+        let iterations = self.ksp.get_iteration_number()? as usize;
+        let residual_norm = self.ksp.get_residual_norm()?;
+        let stopped = crate::shutdown::ksp_stopped(&self.ksp)?;
+
+        // Keep the two most recent solutions for the initial guess
+        let mut previous = if self.history.len() == 2 {
+            self.history.pop_front().unwrap()
+        } else {
+            solution.duplicate()?
+        };
+        previous.copy_data_from(&solution)?;
+        self.history.push_back(previous);
+
+        // Partial results if stopped early
+        let mut result = SolveResult {
+            solution,
+            iterations,
+            residual_norm,
+            interrupted: None,
+        };
+        result.interrupted = crate::shutdown::finish_solve(
+            self.meles,
+            stopped,
+            &result.solution,
+            &crate::SolverState::default(),
+            Some(&result),
+        )?;
+        Ok(result)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Create a solve session for the benchmark problem
+// -----------------------------------------------------------------------------
+pub(crate) fn solve_session<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<SolveSession<'a>> {
+    let mat = std::rc::Rc::new(meles.mat_shell(petsc)?);
+    let preconditioner = preconditioner(meles, petsc)?;
+    let mut ksp = KSP::create(meles.comm)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        crate::check_petsc_raw(
+            KSPSetOperators(ksp.as_raw(), mat.as_raw(), mat.as_raw()),
+            "KSPSetOperators",
+        )?;
+        crate::check_petsc_raw(
+            KSPSetReusePreconditioner(ksp.as_raw(), PetscBool::PETSC_TRUE),
+            "KSPSetReusePreconditioner",
+        )?;
+    }
+    if let Some(preconditioner) = &preconditioner {
+        preconditioner.set_up_ksp(&mut ksp)?;
+    }
+    let reuse_options: ReuseOpt = {
+        let _options_lock = crate::config::options_lock();
+        ksp.set_from_options()?;
+        petsc.options()?
+    };
+    crate::shutdown::set_up_ksp(&ksp, meles)?;
+
+    Ok(SolveSession {
+        ksp,
+        mat,
+        preconditioner,
+        initial_guess: InitialGuess::Previous,
+        recycling: Recycling::None,
+        ksp_type: None,
+        history: std::collections::VecDeque::with_capacity(2),
+        pc_reuse: reuse_options.pc_reuse(PreconditionerReuse::OnChange)?,
+        solves_since_setup: 0,
+        operator_changed: false,
+        meles,
+    })
+}
+
+// -----------------------------------------------------------------------------
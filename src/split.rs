@@ -0,0 +1,296 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Split operator
+//   The secondary part is applied first, so hosts can apply the primary part
+//   while a device backend completes the kernels it launched; libCEED vectors
+//   belong to one Ceed, so the secondary part uses its own work vectors and
+//   its output is added on the host
+// -----------------------------------------------------------------------------
+/// libCEED operator with the local elements split between two Ceeds, such as
+///   a host and a device backend, `y = A_primary x + A_secondary x`
+///
+/// The `ceed_split` option names the Ceed resource of the secondary part,
+/// which applies the `ceed_split_fraction` of the local elements of benchmark
+/// problem operators, and the `ceed` resource applies the rest.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+/// let meles = meles::Meles::new(
+///     &petsc,
+///     "./examples/split.yml",
+///     meles::MethodType::BenchmarkProblem,
+/// )?;
+///
+/// // solve and check error
+/// let result = meles.solve(&petsc)?;
+/// let error = meles.compute_error(&petsc, &result.solution)?;
+/// assert!(error.l2 < 1e-2, "incorrect L2 error");
+/// # Ok(())
+/// # }
+/// ```
+pub struct SplitOperator<'a> {
+    primary: Option<crate::MelesOperator<'a>>,
+    ceed: libceed::Ceed,
+    secondary: Option<crate::MelesOperator<'a>>,
+    work: RefCell<Option<(libceed::vector::Vector<'a>, libceed::vector::Vector<'a>)>>,
+}
+
+impl<'a> SplitOperator<'a> {
+    /// Returns the split operator from its parts, either of which may have no
+    ///   local elements
+    ///
+    /// # arguments
+    ///
+    /// * `ceed` - libCEED context of the secondary part, for work vectors
+    /// * `primary` - Operator on the local vectors of the MatShell Ceed
+    /// * `secondary` - Operator of the secondary Ceed
+    pub fn new(
+        ceed: &libceed::Ceed,
+        primary: Option<crate::MelesOperator<'a>>,
+        secondary: Option<crate::MelesOperator<'a>>,
+    ) -> Self {
+        Self {
+            primary,
+            ceed: ceed.clone(),
+            secondary,
+            work: RefCell::new(None),
+        }
+    }
+
+    // Evaluate both parts into the output, copying the input and output of the
+    //   secondary part through host views
+    fn evaluate(
+        &self,
+        x: Option<&libceed::vector::Vector>,
+        y: &mut libceed::vector::Vector,
+        term: impl Fn(
+            &crate::MelesOperator<'a>,
+            Option<&libceed::vector::Vector>,
+            &mut libceed::vector::Vector,
+        ) -> libceed::Result<i32>,
+    ) -> libceed::Result<i32> {
+        let mut work = self.work.borrow_mut();
+        if let Some(op) = &self.secondary {
+            if work.as_ref().map(|(_, y_work)| y_work.len()) != Some(y.len()) {
+                *work = Some((self.ceed.vector(y.len())?, self.ceed.vector(y.len())?));
+            }
+            let (x_work, y_work) = work.as_mut().unwrap();
+            if let Some(x) = x {
+                x_work.view_mut()?.copy_from_slice(&x.view()?);
+            }
+            term(op, x.map(|_| &*x_work), y_work)?;
+        }
+        match &self.primary {
+            Some(op) => term(op, x, y)?,
+            None => y.set_value(0.0)?,
+        };
+        if let Some((_, y_work)) = work.as_ref().filter(|_| self.secondary.is_some()) {
+            let y_work_view = y_work.view()?;
+            let mut y_view = y.view_mut()?;
+            for (y_i, y_work_i) in y_view.iter_mut().zip(y_work_view.iter()) {
+                *y_i += y_work_i;
+            }
+        }
+        Ok(0)
+    }
+
+    /// Apply the operator, `y = A x`
+    pub fn apply(
+        &self,
+        x: &libceed::vector::Vector,
+        y: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.evaluate(Some(x), y, |op, x, y| op.apply(x.unwrap(), y))
+    }
+
+    /// Assemble the diagonal of the operator
+    pub fn linear_assemble_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.evaluate(None, assembled, |op, _, assembled| {
+            op.linear_assemble_diagonal(assembled)
+        })
+    }
+
+    /// Assemble the point block diagonal of the operator
+    pub fn linear_assemble_point_block_diagonal(
+        &self,
+        assembled: &mut libceed::vector::Vector,
+    ) -> libceed::Result<i32> {
+        self.evaluate(None, assembled, |op, _, assembled| {
+            op.linear_assemble_point_block_diagonal(assembled)
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Split command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) ceed_split: String,
+    pub(crate) ceed_split_fraction: f64,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let ceed_split = pob.options_string(
+            "-ceed_split",
+            "libceed::Ceed resource applying -ceed_split_fraction of the local elements, such as /gpu/cuda with a /cpu -ceed, empty for none",
+            "",
+            "",
+        )?;
+        let ceed_split_fraction = pob.options_real(
+            "-ceed_split_fraction",
+            "Fraction of the local elements applied by the -ceed_split resource, in [0, 1]",
+            "",
+            0.5,
+        )?;
+        Ok(Opt {
+            ceed_split,
+            ceed_split_fraction,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CeedSplit {
+    pub(crate) ceed: libceed::Ceed,
+    pub(crate) resource: String,
+    pub(crate) fraction: f64,
+}
+
+impl CeedSplit {
+    pub(crate) fn new(
+        comm: &mpi::topology::UserCommunicator,
+        options: Opt,
+    ) -> crate::Result<Option<Self>> {
+        if options.ceed_split.is_empty() {
+            return Ok(None);
+        }
+        if !(0.0..=1.0).contains(&options.ceed_split_fraction) {
+            return Err(crate::Error {
+                message: format!(
+                    "-ceed_split_fraction must be in [0, 1], not {}",
+                    options.ceed_split_fraction
+                ),
+            });
+        }
+        Ok(Some(Self {
+            ceed: crate::device::init_ceed(comm, &options.ceed_split)?.0,
+            resource: options.ceed_split,
+            fraction: options.ceed_split_fraction,
+        }))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Mark the local cells of each part
+//   The last fraction of the local cells, a contiguous block of elements, has
+//   value 1 for the secondary Ceed and the rest value 0; the label is not
+//   added to the DM, as cloned DMs share their labels
+// -----------------------------------------------------------------------------
+const SPLIT_LABEL: &str = "ceed split";
+
+fn mark_split<'b>(dm: &DM<'b, '_>, fraction: f64) -> crate::Result<(DMLabel<'b>, [usize; 2])> {
+    let (cell_start, cell_end) = crate::dm::plex_cell_range(dm)?;
+    let num_cells = (cell_end - cell_start) as usize;
+    let num_secondary = (fraction * num_cells as f64).round() as usize;
+    let num_primary = num_cells - num_secondary;
+    let mut label = DMLabel::create(dm.comm(), SPLIT_LABEL)?;
+    for (i, cell) in (cell_start..cell_end).enumerate() {
+        let value = if i < num_primary { 0 } else { 1 };
+        label.set_value(cell, value)?;
+    }
+    Ok((label, [num_primary, num_secondary]))
+}
+
+// -----------------------------------------------------------------------------
+// Check that the benchmark problem operator of a DM can be split
+// -----------------------------------------------------------------------------
+pub(crate) fn check_split(
+    meles: &crate::Meles,
+    options: &crate::ceed_bps::Opt,
+    dm: &DM,
+    split: &CeedSplit,
+) -> crate::Result<()> {
+    if options.mesh_type != crate::dm::MeshType::Plex || crate::dm::plex_cell_types(dm)?.len() != 1
+    {
+        return Err(crate::Error {
+            message: "split execution requires a plex mesh with a single cell type".to_string(),
+        });
+    }
+    if let Some(feature) = crate::qdata::stored_qdata_feature(options) {
+        return Err(crate::Error {
+            message: format!("split execution does not support {}", feature),
+        });
+    }
+    if options.emulate_single_precision {
+        return Err(crate::Error {
+            message: "single precision emulation does not support split execution".to_string(),
+        });
+    }
+    if options.on_the_fly_geometry {
+        for resource in [&meles.ceed_resource, &split.resource] {
+            if !crate::backend::capabilities(resource).closure_q_functions {
+                return Err(crate::Error {
+                    message: format!(
+                        "on-the-fly geometric factors use Rust closure QFunctions with split execution, which libCEED resource {} does not support",
+                        resource
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Split operator of a benchmark problem, with the quadrature data of its parts
+//   Only the parts are built, on the DM set up for the problem and checked by
+//   check_split; the right hand side and error operators do not depend on the
+//   Ceed applying each element, so they are built as without a split
+// -----------------------------------------------------------------------------
+pub(crate) fn split_operator<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    split: &CeedSplit,
+    dm: &DM,
+    coordinates: &[f64],
+) -> crate::Result<(crate::MelesOperator<'a>, Vec<crate::qdata::QDataVector<'a>>)> {
+    let capabilities = crate::backend::capabilities(&split.resource);
+    let (label, num_cells) = mark_split(dm, split.fraction)?;
+    let q_mode =
+        crate::backend::quadrature_mode(meles, crate::ceed_bps::bp_data(options.problem)?.q_mode);
+    let mut qdata = vec![];
+    let mut part = |ceed: &libceed::Ceed,
+                    value: usize,
+                    q_mode: libceed::QuadMode|
+     -> crate::Result<Option<crate::MelesOperator<'a>>> {
+        if num_cells[value] == 0 {
+            return Ok(None);
+        }
+        let (op, op_qdata) =
+            crate::ceed_bps::label_operator(ceed, options, dm, coordinates, q_mode, &label, value)?;
+        qdata.extend(op_qdata);
+        Ok(Some(op.into()))
+    };
+    let primary = part(&meles.ceed, 0, q_mode)?;
+    let secondary = part(
+        &split.ceed,
+        1,
+        if capabilities.collocated_gradients {
+            q_mode
+        } else {
+            libceed::QuadMode::Gauss
+        },
+    )?;
+    let op = crate::MelesOperator::Split(SplitOperator::new(&split.ceed, primary, secondary));
+    Ok((op, qdata))
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,355 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// In-situ statistics
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Statistic {
+    Min,
+    Max,
+    Mean,
+    Integral,
+}
+
+impl std::str::FromStr for Statistic {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "min" => Ok(Statistic::Min),
+            "max" => Ok(Statistic::Max),
+            "mean" => Ok(Statistic::Mean),
+            "integral" => Ok(Statistic::Integral),
+            _ => Err(crate::Error {
+                message: format!(
+                    "failed to parse statistic {}, expected min, max, mean, or integral",
+                    s
+                ),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Statistic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statistic::Min => write!(f, "min"),
+            Statistic::Max => write!(f, "max"),
+            Statistic::Mean => write!(f, "mean"),
+            Statistic::Integral => write!(f, "integral"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Reductions of the solution at one time step, over all components, for the
+///   statistics selected with `-ts_statistics`
+pub struct StepStatistics {
+    pub step: usize,
+    pub time: f64,
+    /// Minimum nodal value
+    pub min: Option<f64>,
+    /// Maximum nodal value
+    pub max: Option<f64>,
+    /// Integral over the domain divided by its volume
+    pub mean: Option<f64>,
+    /// Integral over the domain
+    pub integral: Option<f64>,
+}
+
+impl StepStatistics {
+    fn value(&self, statistic: Statistic) -> Option<f64> {
+        match statistic {
+            Statistic::Min => self.min,
+            Statistic::Max => self.max,
+            Statistic::Mean => self.mean,
+            Statistic::Integral => self.integral,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Statistics command line options
+// -----------------------------------------------------------------------------
+pub(crate) struct Opt {
+    pub(crate) statistics: String,
+    pub(crate) statistics_file: String,
+}
+
+impl petsc::Opt for Opt {
+    fn from_opt_builder(pob: &mut petsc::OptBuilder) -> petsc::Result<Self> {
+        let statistics = pob.options_string(
+            "-ts_statistics",
+            "Comma separated solution statistics computed each time step, from min, max, mean, and integral",
+            "",
+            "",
+        )?;
+        let statistics_file = pob.options_string(
+            "-ts_statistics_file",
+            "File the statistics of each time step are appended to, JSON lines for a .json extension and CSV otherwise",
+            "",
+            "statistics.csv",
+        )?;
+        Ok(Opt {
+            statistics,
+            statistics_file,
+        })
+    }
+}
+
+impl Opt {
+    fn statistics(&self) -> crate::Result<Vec<Statistic>> {
+        self.statistics
+            .split(',')
+            .map(|statistic| statistic.trim())
+            .filter(|statistic| !statistic.is_empty())
+            .map(|statistic| statistic.parse())
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Statistics time series
+//   Rank 0 appends one record per step, so a run can be monitored with tail
+//   and partial series survive an interrupted run
+// -----------------------------------------------------------------------------
+struct StatisticsFile {
+    path: std::path::PathBuf,
+    json: bool,
+}
+
+impl StatisticsFile {
+    fn create(path: &str, statistics: &[Statistic]) -> crate::Result<Self> {
+        let path = std::path::PathBuf::from(path);
+        let json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+        let header = if json {
+            String::new()
+        } else {
+            std::iter::once("step".to_string())
+                .chain(std::iter::once("time".to_string()))
+                .chain(statistics.iter().map(|statistic| statistic.to_string()))
+                .collect::<Vec<_>>()
+                .join(",")
+                + "\n"
+        };
+        std::fs::write(&path, header)?;
+        Ok(Self { path, json })
+    }
+
+    fn append(&self, record: &StepStatistics, statistics: &[Statistic]) -> crate::Result<()> {
+        use std::io::Write;
+        let values = statistics
+            .iter()
+            .map(|&statistic| (statistic, record.value(statistic).unwrap_or(f64::NAN)));
+        let line = if self.json {
+            let fields = values
+                .map(|(statistic, value)| format!(", \"{}\": {}", statistic, json_number(value)))
+                .collect::<String>();
+            format!(
+                "{{\"step\": {}, \"time\": {}{}}}\n",
+                record.step,
+                json_number(record.time),
+                fields
+            )
+        } else {
+            let fields = values
+                .map(|(_, value)| format!(",{:e}", value))
+                .collect::<String>();
+            format!("{},{:e}{}\n", record.step, record.time, fields)
+        };
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+// JSON has no NaN or infinity, so non-finite values are written as null
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{:e}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Statistics monitor
+//   Nodal extrema come from the local solution, with essential boundary
+//   values at the step time, and the integral is the sum of the local mass
+//   operator action, 1^T M u, since the local vector sums the contributions of
+//   local elements only; the operator context outlives the TS, as for the
+//   IJacobian
+// -----------------------------------------------------------------------------
+struct StatisticsMonitor<'a> {
+    meles: &'a crate::Meles<'a>,
+    mass: *const crate::MelesMatShellContext<'a>,
+    statistics: Vec<Statistic>,
+    volume: f64,
+    file: Option<StatisticsFile>,
+    records: std::rc::Rc<RefCell<Vec<StepStatistics>>>,
+}
+
+// Global integral of the local vector of a mass context, 1^T M u
+fn integral(meles: &crate::Meles, mass: &crate::MelesMatShellContext) -> crate::Result<f64> {
+    use mpi::collective::{CommunicatorCollectives, SystemOperation};
+    let mut y_loc = mass.y_loc.borrow_mut();
+    crate::petsc_ops::apply_ceed_op_local(
+        &mass.op_ceed.borrow(),
+        &mut mass.x_loc.borrow_mut(),
+        &mut mass.x_loc_ceed.borrow_mut(),
+        &mut y_loc,
+        &mut mass.y_loc_ceed.borrow_mut(),
+    )?;
+    let local_integral: f64 = y_loc.view()?.iter().sum();
+    let mut integral = 0.0;
+    meles
+        .comm
+        .all_reduce_into(&local_integral, &mut integral, SystemOperation::sum());
+    Ok(integral)
+}
+
+impl<'a> StatisticsMonitor<'a> {
+    fn compute(
+        &self,
+        u: petsc::petsc_raw::Vec,
+        step: usize,
+        time: f64,
+    ) -> crate::Result<StepStatistics> {
+        use mpi::collective::{CommunicatorCollectives, SystemOperation};
+        let mass = unsafe { &*self.mass };
+        let selected = |statistic| self.statistics.contains(&statistic);
+        let (local_min, local_max) = {
+            let dm = mass.dm.borrow();
+            let mut x_loc = mass.x_loc.borrow_mut();
+            crate::transfer::global_to_local_raw(&self.meles.bp_options, &dm, u, &mut x_loc, time)?;
+            if selected(Statistic::Min) || selected(Statistic::Max) {
+                let x_loc_view = x_loc.view()?;
+                x_loc_view
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| {
+                        (min.min(x), max.max(x))
+                    })
+            } else {
+                (f64::INFINITY, f64::NEG_INFINITY)
+            }
+        };
+        let min = if selected(Statistic::Min) {
+            let mut min = 0.0;
+            self.meles
+                .comm
+                .all_reduce_into(&local_min, &mut min, SystemOperation::min());
+            Some(min)
+        } else {
+            None
+        };
+        let max = if selected(Statistic::Max) {
+            let mut max = 0.0;
+            self.meles
+                .comm
+                .all_reduce_into(&local_max, &mut max, SystemOperation::max());
+            Some(max)
+        } else {
+            None
+        };
+        let integral = if selected(Statistic::Mean) || selected(Statistic::Integral) {
+            Some(integral(self.meles, mass)?)
+        } else {
+            None
+        };
+        Ok(StepStatistics {
+            step,
+            time,
+            min,
+            max,
+            mean: integral
+                .map(|integral| integral / self.volume)
+                .filter(|_| selected(Statistic::Mean)),
+            integral: integral.filter(|_| selected(Statistic::Integral)),
+        })
+    }
+
+    fn record(&self, u: petsc::petsc_raw::Vec, step: usize, time: f64) -> crate::Result<()> {
+        let record = self.compute(u, step, time)?;
+        if let Some(file) = &self.file {
+            file.append(&record, &self.statistics)?;
+        }
+        self.records.borrow_mut().push(record);
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn monitor_statistics(
+    _ts: petsc::petsc_raw::TS,
+    step: petsc::Int,
+    time: petsc::Real,
+    u: petsc::petsc_raw::Vec,
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    let monitor = &*(ctx as *const StatisticsMonitor);
+    match monitor.record(u, step as usize, time) {
+        Ok(()) => 0,
+        Err(err) => crate::petsc_callback_error("monitor_statistics", err),
+    }
+}
+
+unsafe extern "C" fn destroy_monitor_context(
+    ctx: *mut *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    drop(Box::from_raw(*ctx as *mut StatisticsMonitor));
+    *ctx = std::ptr::null_mut();
+    0
+}
+
+// Record statistics of the solution of a TS, returning the shared records, or
+//   None if no statistics are selected
+pub(crate) fn set_up_ts<'a>(
+    ts: &TS<'a, 'a>,
+    meles: &'a crate::Meles<'a>,
+    mass: &crate::MelesMatShellContext<'a>,
+    options: Opt,
+) -> crate::Result<Option<std::rc::Rc<RefCell<Vec<StepStatistics>>>>> {
+    use mpi::traits::Communicator;
+    let statistics = options.statistics()?;
+    if statistics.is_empty() {
+        return Ok(None);
+    }
+    let file = if meles.comm.rank() == 0 {
+        Some(StatisticsFile::create(
+            &options.statistics_file,
+            &statistics,
+        )?)
+    } else {
+        None
+    };
+
+    // Domain volume, the integral of one
+    mass.x_loc.borrow_mut().set_all(1.0)?;
+    let volume = integral(meles, mass)?;
+
+    let records = std::rc::Rc::new(RefCell::new(vec![]));
+    let context = Box::into_raw(Box::new(StatisticsMonitor {
+        meles,
+        mass: mass as *const crate::MelesMatShellContext<'a>,
+        statistics,
+        volume,
+        file,
+        records: records.clone(),
+    }));
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::TSMonitorSet(
+                ts.as_raw(),
+                Some(monitor_statistics),
+                context as *mut std::os::raw::c_void,
+                Some(destroy_monitor_context),
+            )
+        },
+        "TSMonitorSet",
+    )?;
+    Ok(Some(records))
+}
+
+// -----------------------------------------------------------------------------
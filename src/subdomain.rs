@@ -0,0 +1,120 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Subdomain splitting
+// -----------------------------------------------------------------------------
+/// Interface and interior splitting of the local subdomain DoFs
+///
+/// Each MPI rank's subdomain is the set of elements it owns. The local
+/// operator is the unassembled subdomain operator acting on local vectors,
+/// i.e. the Neumann matrix of the subdomain, as used by PCBDDC with MATIS or
+/// a hand-rolled FETI-DP. Local indices refer to entries of local vectors
+/// from the operator DM; DoFs constrained by essential boundary conditions
+/// belong to neither the interface nor the interior.
+pub struct SubdomainSplitting<'a> {
+    pub(crate) frozen: std::rc::Rc<crate::FrozenOperator<'a>>,
+    pub(crate) local_to_global: Vec<petsc::Int>,
+    pub(crate) interface: Vec<petsc::Int>,
+    pub(crate) interior: Vec<petsc::Int>,
+}
+
+impl<'a> SubdomainSplitting<'a> {
+    /// Returns the operator the splitting was built from
+    pub fn frozen(&self) -> &std::rc::Rc<crate::FrozenOperator<'a>> {
+        &self.frozen
+    }
+
+    /// Returns the global index of each local DoF, negative if constrained
+    pub fn local_to_global(&self) -> &[petsc::Int] {
+        &self.local_to_global
+    }
+
+    /// Returns the local indices of DoFs shared with other subdomains
+    pub fn interface(&self) -> &[petsc::Int] {
+        &self.interface
+    }
+
+    /// Returns the local indices of DoFs belonging only to this subdomain
+    pub fn interior(&self) -> &[petsc::Int] {
+        &self.interior
+    }
+
+    /// Returns the global indices of interface DoFs
+    pub fn interface_global(&self) -> Vec<petsc::Int> {
+        self.interface
+            .iter()
+            .map(|&i| self.local_to_global[i as usize])
+            .collect()
+    }
+
+    /// Apply the unassembled subdomain operator, `y_loc = A_s x_loc`
+    ///
+    /// # arguments
+    ///
+    /// * `x_loc` - Local input vector
+    /// * `y_loc` - Local output vector
+    pub fn apply_local(
+        &self,
+        x_loc: &mut petsc::vector::Vector<'a>,
+        y_loc: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        let local_size = x_loc.local_size()? as usize;
+        let mut x_loc_ceed = self.frozen.ceed.vector(local_size)?;
+        let mut y_loc_ceed = self.frozen.ceed.vector(local_size)?;
+        crate::petsc_ops::apply_ceed_op_local(
+            &self.frozen.op_ceed,
+            x_loc,
+            &mut x_loc_ceed,
+            y_loc,
+            &mut y_loc_ceed,
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Split local DoFs by multiplicity
+//   DoFs counted by more than one subdomain lie on the interface
+// -----------------------------------------------------------------------------
+pub(crate) fn subdomain_splitting<'a>(
+    frozen: std::rc::Rc<crate::FrozenOperator<'a>>,
+) -> crate::Result<SubdomainSplitting<'a>> {
+    let dm = &frozen.dm;
+
+    // Local to global mapping
+    let local_to_global = crate::dm::local_to_global_indices(dm)?;
+
+    // Multiplicity of each local DoF
+    let mut multiplicity_loc = dm.create_local_vector()?;
+    multiplicity_loc.set_all(1.0)?;
+    let mut multiplicity = dm.create_global_vector()?;
+    multiplicity.zero_entries()?;
+    dm.local_to_global(&multiplicity_loc, InsertMode::ADD_VALUES, &mut multiplicity)?;
+    dm.global_to_local(
+        &multiplicity,
+        InsertMode::INSERT_VALUES,
+        &mut multiplicity_loc,
+    )?;
+
+    let multiplicity_view = multiplicity_loc.view()?;
+    let (mut interface, mut interior) = (vec![], vec![]);
+    for (i, &global) in local_to_global.iter().enumerate() {
+        if global < 0 {
+            continue;
+        }
+        if multiplicity_view[i] > 1.5 {
+            interface.push(i as petsc::Int);
+        } else {
+            interior.push(i as petsc::Int);
+        }
+    }
+    drop(multiplicity_view);
+
+    Ok(SubdomainSplitting {
+        frozen,
+        local_to_global,
+        interface,
+        interior,
+    })
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,403 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Surface QFunctions
+//   Faces of hexahedral cells are quadrilaterals embedded in 3D, so the face
+//   Jacobian is 3x2 and the area element is the norm of the cross product of
+//   its columns; faces of quadrilateral cells are segments embedded in 2D, so
+//   the face Jacobian is 2x1 and the length element is the norm of its column
+// -----------------------------------------------------------------------------
+fn area_element(dx: &[f64], dim: usize, q: usize, i: usize) -> f64 {
+    let j = |c: usize, d: usize| dx[(d * dim + c) * q + i];
+    if dim == 2 {
+        return (j(0, 0) * j(0, 0) + j(1, 0) * j(1, 0)).sqrt();
+    }
+    let normal = [
+        j(1, 0) * j(2, 1) - j(2, 0) * j(1, 1),
+        j(2, 0) * j(0, 1) - j(0, 0) * j(2, 1),
+        j(0, 0) * j(1, 1) - j(1, 0) * j(0, 1),
+    ];
+    normal.iter().map(|n| n * n).sum::<f64>().sqrt()
+}
+
+fn setup_q_function<'b>(ceed: &libceed::Ceed, dim: usize) -> crate::Result<QFunction<'b>> {
+    let setup = move |[dx, weights, ..]: QFunctionInputs, [q_data, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        for i in 0..q {
+            q_data[i] = weights[i] * area_element(dx, dim, q, i);
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(setup))?
+        .input("dx", dim * (dim - 1), EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("qdata", 1, EvalMode::None)?;
+    Ok(qf)
+}
+
+fn mass_q_function<'b>(
+    ceed: &libceed::Ceed,
+    num_components: usize,
+) -> crate::Result<QFunction<'b>> {
+    let apply = move |[u, q_data, ..]: QFunctionInputs, [v, ..]: QFunctionOutputs| {
+        let q = q_data.len();
+        for c in 0..num_components {
+            for i in 0..q {
+                v[c * q + i] = q_data[i] * u[c * q + i];
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("u", num_components, EvalMode::Interp)?
+        .input("qdata", 1, EvalMode::None)?
+        .output("v", num_components, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+// Boundary integral of an expression, the same for every component
+fn flux_q_function<'b>(
+    ceed: &libceed::Ceed,
+    dim: usize,
+    flux: crate::Expression,
+    time: std::rc::Rc<std::cell::Cell<f64>>,
+    num_components: usize,
+) -> crate::Result<QFunction<'b>> {
+    let apply = move |[x, dx, weights, ..]: QFunctionInputs, [v, ..]: QFunctionOutputs| {
+        let q = weights.len();
+        let time = time.get();
+        for i in 0..q {
+            let x_i = (0..dim).map(|d| x[d * q + i]).collect::<Vec<_>>();
+            let g = weights[i] * area_element(dx, dim, q, i) * flux.evaluate(&x_i, time);
+            for c in 0..num_components {
+                v[c * q + i] = g;
+            }
+        }
+        0
+    };
+    let qf = ceed
+        .q_function_interior(1, Box::new(apply))?
+        .input("x", dim, EvalMode::Interp)?
+        .input("dx", dim * (dim - 1), EvalMode::Grad)?
+        .input("weights", 1, EvalMode::Weight)?
+        .output("v", num_components, EvalMode::Interp)?;
+    Ok(qf)
+}
+
+// -----------------------------------------------------------------------------
+// Faces of a boundary
+//   One restriction pair per label value, on the problem DM
+// -----------------------------------------------------------------------------
+struct BoundaryFaces<'a> {
+    dm: DM<'a, 'a>,
+    dimension: usize,
+    num_components: usize,
+    basis_x: Basis<'a>,
+    basis_u: Basis<'a>,
+    restrictions: Vec<(ElemRestriction<'a>, ElemRestriction<'a>)>,
+    coord_loc_ceed: libceed::vector::Vector<'a>,
+}
+
+fn boundary_faces<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    label_name: &str,
+    values: &[usize],
+) -> crate::Result<BoundaryFaces<'a>> {
+    crate::backend::require_closure_q_functions(meles, "boundary operators")?;
+
+    // Duplicate DM
+    let mut dm = meles.dm.borrow().clone();
+    crate::ceed_bps::setup_dm(&mut dm, options)?;
+    let dimension = dm.dimension()?;
+    let tensor_cell = match dimension {
+        2 => petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_QUADRILATERAL,
+        _ => petsc::petsc_raw::DMPolytopeType::DM_POLYTOPE_HEXAHEDRON,
+    };
+    let tensor = options.mesh_type == crate::dm::MeshType::Plex
+        && crate::dm::plex_cell_types(&dm)? == vec![tensor_cell];
+    if !(2..=3).contains(&dimension) || !tensor {
+        return Err(crate::Error {
+            message:
+                "boundary operators require a 2D plex mesh of quadrilaterals or a 3D plex mesh of hexahedra"
+                    .to_string(),
+        });
+    }
+    let label = dm.label(label_name)?.ok_or_else(|| crate::Error {
+        message: format!("missing label {} for boundary", label_name),
+    })?;
+
+    // Basis and restrictions on faces
+    let num_components = crate::ceed_bps::num_components(options)?;
+    let p = options.order + 1;
+    let q = p + options.q_extra;
+    let basis_x = meles.ceed.basis_tensor_H1_Lagrange(
+        dimension - 1,
+        dimension,
+        2,
+        q,
+        libceed::QuadMode::Gauss,
+    )?;
+    let basis_u = meles.ceed.basis_tensor_H1_Lagrange(
+        dimension - 1,
+        num_components,
+        p,
+        q,
+        libceed::QuadMode::Gauss,
+    )?;
+    let mut restrictions = Vec::with_capacity(values.len());
+    {
+        let mesh_coord_dm = dm.coordinate_dm()?;
+        for &value in values {
+            let restr_u =
+                crate::dm::create_restriction_from_dm_plex(&dm, &meles.ceed, 1, &label, value)?;
+            let restr_x = crate::dm::create_restriction_from_dm_plex(
+                &mesh_coord_dm,
+                &meles.ceed,
+                1,
+                &label,
+                value,
+            )?;
+            restrictions.push((restr_x, restr_u));
+        }
+    }
+
+    // Owned copy of the coordinates
+    let coord_loc_ceed = {
+        let coord_loc = {
+            let mut dm = meles.dm.borrow_mut();
+            dm.coordinates_local()?
+        };
+        crate::transfer::owned_copy(&meles.ceed, &coord_loc)?
+    };
+
+    Ok(BoundaryFaces {
+        dm,
+        dimension,
+        num_components,
+        basis_x,
+        basis_u,
+        restrictions,
+        coord_loc_ceed,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Boundary mass matrix
+//   The MatShell acts on the same global vectors as the problem operator, so
+//   it can be added to the problem operator for Robin terms
+// -----------------------------------------------------------------------------
+pub(crate) fn boundary_mass_context<'a>(
+    meles: &'a crate::Meles<'a>,
+    label_name: &str,
+    values: &[usize],
+) -> crate::Result<crate::MelesMatShellContext<'a>> {
+    let BoundaryFaces {
+        dm,
+        dimension,
+        num_components,
+        basis_x,
+        basis_u,
+        restrictions,
+        coord_loc_ceed,
+    } = boundary_faces(meles, &meles.bp_options, label_name, values)?;
+    let num_quadrature_points = basis_u.num_quadrature_points();
+
+    // One sub-operator per label value
+    let qf_setup = setup_q_function(&meles.ceed, dimension)?;
+    let qf_mass = mass_q_function(&meles.ceed, num_components)?;
+    let mut op_ceed = meles.ceed.composite_operator()?;
+    let mut qdata = Vec::with_capacity(restrictions.len());
+    for (restr_x, restr_u) in &restrictions {
+        let num_elements = restr_u.num_elements();
+        let restr_qdata = crate::qdata::QDataVector::restriction(
+            &meles.ceed,
+            num_elements,
+            num_quadrature_points,
+            1,
+        )?;
+        let mut face_qdata = restr_qdata.create_lvector()?;
+        meles
+            .ceed
+            .operator(&qf_setup, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("dx", restr_x, &basis_x, VectorOpt::Active)?
+            .field(
+                "weights",
+                ElemRestrictionOpt::None,
+                &basis_x,
+                VectorOpt::None,
+            )?
+            .field(
+                "qdata",
+                &restr_qdata,
+                BasisOpt::Collocated,
+                VectorOpt::Active,
+            )?
+            .check()?
+            .apply(&coord_loc_ceed, &mut face_qdata)?;
+        let face_qdata = std::rc::Rc::new(face_qdata);
+        let op_mass = meles
+            .ceed
+            .operator(&qf_mass, QFunctionOpt::None, QFunctionOpt::None)?
+            .field("u", restr_u, &basis_u, VectorOpt::Active)?
+            .field("qdata", &restr_qdata, BasisOpt::Collocated, &*face_qdata)?
+            .field("v", restr_u, &basis_u, VectorOpt::Active)?
+            .check()?;
+        op_ceed = op_ceed.sub_operator(&op_mass)?;
+        qdata.push(crate::qdata::QDataVector {
+            vector: face_qdata,
+            num_elements,
+            num_quadrature_points,
+            size: 1,
+        });
+    }
+
+    // Work vectors
+    let x_loc = dm.create_local_vector()?;
+    let y_loc = dm.create_local_vector()?;
+    let x_loc_size = x_loc.local_size()?;
+    let mut rhs = dm.create_global_vector()?;
+    rhs.zero_entries()?;
+
+    Ok(crate::MelesMatShellContext {
+        ceed: meles.ceed.clone(),
+        dm: RefCell::new(dm),
+        x_loc: RefCell::new(x_loc),
+        y_loc: RefCell::new(y_loc),
+        x_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        y_loc_ceed: RefCell::new(meles.ceed.vector(x_loc_size)?),
+        op_ceed: RefCell::new(op_ceed.into()),
+        diagonal: RefCell::new(None),
+        num_components,
+        point_block_inverse: RefCell::new(None),
+        rhs,
+        qdata,
+        true_solution: vec![],
+        error_ops: vec![],
+        single_precision: false,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Boundary integral of a flux against the test functions
+//   i.e. the Neumann term, integral of g v over the faces, for the
+//   discretization of the given options; the operators are built once and
+//   evaluated at any time, for time dependent fluxes
+// -----------------------------------------------------------------------------
+pub(crate) struct BoundaryIntegral<'a> {
+    dm: DM<'a, 'a>,
+    operators: Vec<libceed::operator::Operator<'a>>,
+    coord_loc_ceed: libceed::vector::Vector<'a>,
+    time: std::rc::Rc<std::cell::Cell<f64>>,
+    v_loc: RefCell<petsc::vector::Vector<'a>>,
+    v_loc_ceed: RefCell<libceed::vector::Vector<'a>>,
+}
+
+impl<'a> BoundaryIntegral<'a> {
+    pub(crate) fn new(
+        meles: &'a crate::Meles<'a>,
+        options: &crate::ceed_bps::Opt,
+        label_name: &str,
+        values: &[usize],
+        flux: &crate::Expression,
+    ) -> crate::Result<Self> {
+        let BoundaryFaces {
+            dm,
+            dimension,
+            num_components,
+            basis_x,
+            basis_u,
+            restrictions,
+            coord_loc_ceed,
+        } = boundary_faces(meles, options, label_name, values)?;
+
+        // One operator for each label value
+        let time = std::rc::Rc::new(std::cell::Cell::new(0.0));
+        let qf_flux = flux_q_function(
+            &meles.ceed,
+            dimension,
+            flux.clone(),
+            time.clone(),
+            num_components,
+        )?;
+        let operators = restrictions
+            .iter()
+            .map(|(restr_x, restr_u)| {
+                let op = meles
+                    .ceed
+                    .operator(&qf_flux, QFunctionOpt::None, QFunctionOpt::None)?
+                    .field("x", restr_x, &basis_x, VectorOpt::Active)?
+                    .field("dx", restr_x, &basis_x, VectorOpt::Active)?
+                    .field(
+                        "weights",
+                        ElemRestrictionOpt::None,
+                        &basis_x,
+                        VectorOpt::None,
+                    )?
+                    .field("v", restr_u, &basis_u, VectorOpt::Active)?
+                    .check()?;
+                Ok(op)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        let v_loc = dm.create_local_vector()?;
+        let v_loc_ceed = meles.ceed.vector(v_loc.local_size()?)?;
+        Ok(Self {
+            dm,
+            operators,
+            coord_loc_ceed,
+            time,
+            v_loc: RefCell::new(v_loc),
+            v_loc_ceed: RefCell::new(v_loc_ceed),
+        })
+    }
+
+    // Global vector of the problem DM
+    pub(crate) fn create_global_vector(&self) -> crate::Result<petsc::vector::Vector<'a>> {
+        let mut v = self.dm.create_global_vector()?;
+        v.zero_entries()?;
+        Ok(v)
+    }
+
+    // Add alpha times the integral at a time to a global vector
+    pub(crate) fn add_to(
+        &self,
+        time: f64,
+        alpha: f64,
+        v: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<()> {
+        self.time.set(time);
+        let mut v_loc = self.v_loc.borrow_mut();
+        v_loc.zero_entries()?;
+        crate::transfer::with_wrapped(
+            &mut v_loc,
+            &mut self.v_loc_ceed.borrow_mut(),
+            |v_loc_ceed| {
+                for op in &self.operators {
+                    op.apply_add(&self.coord_loc_ceed, v_loc_ceed)?;
+                }
+                Ok(())
+            },
+        )?;
+        v_loc.scale(alpha)?;
+        self.dm.local_to_global(&v_loc, InsertMode::ADD_VALUES, v)?;
+        Ok(())
+    }
+}
+
+pub(crate) fn boundary_integral<'a>(
+    meles: &'a crate::Meles<'a>,
+    options: &crate::ceed_bps::Opt,
+    label_name: &str,
+    values: &[usize],
+    flux: &crate::Expression,
+    time: f64,
+) -> crate::Result<petsc::vector::Vector<'a>> {
+    let integral = BoundaryIntegral::new(meles, options, label_name, values, flux)?;
+    let mut v = integral.create_global_vector()?;
+    integral.add_to(time, 1.0, &mut v)?;
+    Ok(v)
+}
+
+// -----------------------------------------------------------------------------
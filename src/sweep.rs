@@ -0,0 +1,103 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Order sweep results
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Setup cost for a single order in a sweep
+pub struct OrderSweepEntry {
+    pub order: usize,
+    pub global_size: usize,
+    pub setup_seconds: f64,
+    pub amortized_setup_seconds: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Setup costs for a polynomial order sweep on a fixed mesh
+///
+/// The mesh setup cost is paid once and amortized over every order in the
+/// sweep.
+pub struct OrderSweep {
+    pub mesh_setup_seconds: f64,
+    pub entries: Vec<OrderSweepEntry>,
+}
+
+impl fmt::Display for OrderSweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Order Sweep:")?;
+        writeln!(f, "  Mesh setup (s)  : {:.6}", self.mesh_setup_seconds)?;
+        writeln!(
+            f,
+            "  {:>5} {:>12} {:>14} {:>18}",
+            "Order", "Global DoFs", "Setup (s)", "Amortized (s)"
+        )?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "  {:>5} {:>12} {:>14.6} {:>18.6}",
+                entry.order, entry.global_size, entry.setup_seconds, entry.amortized_setup_seconds
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Sweep polynomial order on the existing mesh
+//   Only bases, restrictions, and qdata are rebuilt for each order
+// -----------------------------------------------------------------------------
+pub(crate) fn order_sweep<'a, F>(
+    meles: &'a crate::Meles<'a>,
+    orders: &[usize],
+    mut f: F,
+) -> crate::Result<OrderSweep>
+where
+    F: FnMut(
+        usize,
+        &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    ) -> crate::Result<()>,
+{
+    let mesh_setup_seconds = meles.mesh_setup_time.as_secs_f64();
+    let mut entries = Vec::with_capacity(orders.len());
+    for &order in orders {
+        let options = crate::ceed_bps::Opt {
+            order,
+            ..meles.bp_options.clone()
+        };
+
+        // Rebuild discretization on a clone of the mesh DM
+        let start = std::time::Instant::now();
+        let context = crate::ceed_bps::mat_shell_context(meles, &options)?;
+        let mat = crate::petsc_ops::create_mat_shell(context)?;
+        if let Some(num_components) = crate::ceed_bps::constant_nullspace_components(&options)? {
+            crate::petsc_ops::set_constant_nullspace(
+                mat.as_raw(),
+                &mat.mat_data().unwrap().dm.borrow(),
+                num_components,
+            )?;
+        }
+        let setup_seconds = start.elapsed().as_secs_f64();
+        let global_size = mat
+            .mat_data()
+            .unwrap()
+            .dm
+            .borrow()
+            .create_global_vector()?
+            .global_size()? as usize;
+
+        f(order, &mat)?;
+
+        entries.push(OrderSweepEntry {
+            order,
+            global_size,
+            setup_seconds,
+            amortized_setup_seconds: setup_seconds + mesh_setup_seconds / orders.len() as f64,
+        });
+    }
+    Ok(OrderSweep {
+        mesh_setup_seconds,
+        entries,
+    })
+}
+
+// -----------------------------------------------------------------------------
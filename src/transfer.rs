@@ -0,0 +1,311 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Local vector transfer
+//   Local PETSc vectors are wrapped as libCEED vectors for the duration of a
+//   closure, so libCEED reads and writes the PETSc storage without copies;
+//   device arrays of CUDA, HIP, and Kokkos PETSc vectors are given to libCEED
+//   contexts preferring device memory, so GPU applies stay resident; errors
+//   are returned as crate::Error, so PETSc callbacks report them through
+//   PetscError rather than unwinding
+// -----------------------------------------------------------------------------
+// Run a closure on a local PETSc vector wrapped as a libCEED vector
+pub(crate) fn with_wrapped<R>(
+    loc: &mut petsc::vector::Vector,
+    loc_ceed: &mut libceed::vector::Vector,
+    f: impl FnOnce(&mut libceed::vector::Vector) -> crate::Result<R>,
+) -> crate::Result<R> {
+    if let Some(array) = device_array(loc, loc_ceed) {
+        return with_wrapped_device(loc, loc_ceed, array, f);
+    }
+    let mut loc_view = loc.view_mut()?;
+    let mut loc_view_slice = loc_view.as_slice_mut().ok_or_else(|| crate::Error {
+        message: "failed to access local vector values".to_string(),
+    })?;
+    let _loc_wrapper = loc_ceed.wrap_slice_mut(&mut loc_view_slice)?;
+    f(loc_ceed)
+}
+
+// Owned libCEED copy of a local PETSc vector, for passive fields read after
+//   the PETSc vector is restored
+pub(crate) fn owned_copy<'c>(
+    ceed: &libceed::Ceed,
+    loc: &petsc::vector::Vector,
+) -> crate::Result<libceed::vector::Vector<'c>> {
+    let loc_view = loc.view()?;
+    let loc_view_slice = loc_view.as_slice().ok_or_else(|| crate::Error {
+        message: "failed to access local vector values".to_string(),
+    })?;
+    Ok(ceed.vector_from_slice(loc_view_slice)?)
+}
+
+// Device array of a local PETSc vector, if the vector stores its values on the
+//   device and the Ceed of the libCEED vector prefers device memory
+//   Any failure leaves the array restored, and the host wrap is used instead
+fn device_array(
+    loc: &mut petsc::vector::Vector,
+    loc_ceed: &libceed::vector::Vector,
+) -> Option<*mut petsc::Scalar> {
+    use libceed_sys::bind_ceed;
+    let loc_ceed_raw = loc_ceed.as_raw();
+    let mut ceed = std::ptr::null_mut();
+    let mut memory_type = bind_ceed::CeedMemType_CEED_MEM_HOST;
+    let prefers_device = unsafe {
+        crate::check_ceed_raw(
+            bind_ceed::CeedVectorGetCeed(loc_ceed_raw, &mut ceed),
+            "CeedVectorGetCeed",
+        )
+        .and_then(|_| {
+            crate::check_ceed_raw(
+                bind_ceed::CeedGetPreferredMemType(ceed, &mut memory_type),
+                "CeedGetPreferredMemType",
+            )
+        })
+        .is_ok_and(|_| memory_type == bind_ceed::CeedMemType_CEED_MEM_DEVICE)
+    };
+    if !prefers_device {
+        return None;
+    }
+    unsafe {
+        use petsc::petsc_raw::*;
+        let mut array = std::ptr::null_mut();
+        let mut petsc_memory_type = PetscMemType::PETSC_MEMTYPE_HOST;
+        if VecGetArrayAndMemType(loc.as_raw(), &mut array, &mut petsc_memory_type) != 0 {
+            return None;
+        }
+        if petsc_memory_type == PetscMemType::PETSC_MEMTYPE_HOST {
+            VecRestoreArrayAndMemType(loc.as_raw(), &mut array);
+            return None;
+        }
+        Some(array)
+    }
+}
+
+// Run a closure on the device array of a local PETSc vector, given to the
+//   libCEED vector with device memory and taken back before the array is
+//   restored to PETSc; the array is restored even if a step fails
+fn with_wrapped_device<R>(
+    loc: &mut petsc::vector::Vector,
+    loc_ceed: &mut libceed::vector::Vector,
+    mut array: *mut petsc::Scalar,
+    f: impl FnOnce(&mut libceed::vector::Vector) -> crate::Result<R>,
+) -> crate::Result<R> {
+    use libceed_sys::bind_ceed;
+    let loc_ceed_raw = loc_ceed.as_raw();
+    let result = (|| -> crate::Result<R> {
+        crate::check_ceed_raw(
+            unsafe {
+                bind_ceed::CeedVectorSetArray(
+                    loc_ceed_raw,
+                    bind_ceed::CeedMemType_CEED_MEM_DEVICE,
+                    bind_ceed::CeedCopyMode_CEED_USE_POINTER,
+                    array,
+                )
+            },
+            "CeedVectorSetArray",
+        )?;
+        let result = f(loc_ceed);
+        let mut taken_array = std::ptr::null_mut();
+        let taken = crate::check_ceed_raw(
+            unsafe {
+                bind_ceed::CeedVectorTakeArray(
+                    loc_ceed_raw,
+                    bind_ceed::CeedMemType_CEED_MEM_DEVICE,
+                    &mut taken_array,
+                )
+            },
+            "CeedVectorTakeArray",
+        );
+        let value = result?;
+        taken?;
+        Ok(value)
+    })();
+    let restored = crate::check_petsc_raw(
+        unsafe { petsc::petsc_raw::VecRestoreArrayAndMemType(loc.as_raw(), &mut array) },
+        "VecRestoreArrayAndMemType",
+    );
+    let value = result?;
+    restored?;
+    Ok(value)
+}
+
+// Global to local, inserting values, then run a closure on the local vector
+//   Global vectors are raw, so MatShell callbacks pass the vectors PETSc gives
+//   them
+pub(crate) fn read_local<R>(
+    dm: &DM,
+    x: petsc::petsc_raw::Vec,
+    x_loc: &mut petsc::vector::Vector,
+    x_loc_ceed: &mut libceed::vector::Vector,
+    f: impl FnOnce(&mut libceed::vector::Vector) -> crate::Result<R>,
+) -> crate::Result<R> {
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::DMGlobalToLocal(
+                dm.as_raw(),
+                x,
+                InsertMode::INSERT_VALUES,
+                x_loc.as_raw(),
+            )
+        },
+        "DMGlobalToLocal",
+    )?;
+    with_wrapped(x_loc, x_loc_ceed, f)
+}
+
+// Global to local for a raw global vector, such as the state passed to a TS
+//   monitor, with essential boundary values at a time
+pub(crate) fn global_to_local_raw(
+    options: &crate::ceed_bps::Opt,
+    dm: &DM,
+    x: petsc::petsc_raw::Vec,
+    x_loc: &mut petsc::vector::Vector,
+    time: f64,
+) -> crate::Result<()> {
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        check(
+            DMGlobalToLocal(dm.as_raw(), x, InsertMode::INSERT_VALUES, x_loc.as_raw()),
+            "DMGlobalToLocal",
+        )?;
+        if crate::ceed_bps::has_essential_boundary(options)? {
+            check(
+                DMPlexInsertBoundaryValues(
+                    dm.as_raw(),
+                    PetscBool::PETSC_TRUE,
+                    x_loc.as_raw(),
+                    time,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                ),
+                "DMPlexInsertBoundaryValues",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Run a closure on a zeroed local vector, then local to global, adding values
+pub(crate) fn add_local<R>(
+    dm: &DM,
+    y: petsc::petsc_raw::Vec,
+    y_loc: &mut petsc::vector::Vector,
+    y_loc_ceed: &mut libceed::vector::Vector,
+    f: impl FnOnce(&mut libceed::vector::Vector) -> crate::Result<R>,
+) -> crate::Result<R> {
+    y_loc.zero_entries()?;
+    let result = with_wrapped(y_loc, y_loc_ceed, f)?;
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::DMLocalToGlobal(
+                dm.as_raw(),
+                y_loc.as_raw(),
+                InsertMode::ADD_VALUES,
+                y,
+            )
+        },
+        "DMLocalToGlobal",
+    )?;
+    Ok(result)
+}
+
+// -----------------------------------------------------------------------------
+// Field transfer
+// -----------------------------------------------------------------------------
+/// Transfer between global PETSc vectors and local libCEED vectors on a DM
+///
+/// This is the transfer used by Meles MatShells, so custom libCEED operators
+/// on the same DM see the same local vectors, including essential boundary
+/// values, as the benchmark problem operators.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// let petsc = petsc::Petsc::init_no_args()?;
+/// let meles = meles::Meles::new(
+///     &petsc,
+///     "./examples/meles.yml",
+///     meles::MethodType::BenchmarkProblem,
+/// )?;
+///
+/// // read a global vector and add local values back
+/// let mat = meles.mat_shell(&petsc)?;
+/// let context = mat.mat_data().unwrap();
+/// let mut transfer = context.field_transfer()?;
+/// let mut x = context.rhs().duplicate()?;
+/// x.set_all(1.0)?;
+/// let mut y = x.duplicate()?;
+/// y.zero_entries()?;
+/// transfer.read(&context.dm(), &x, |x_loc| {
+///     assert!(x_loc.view()?.iter().all(|&x_i| x_i == 1.0), "incorrect read");
+///     Ok(())
+/// })?;
+/// transfer.add(&context.dm(), &mut y, |y_loc| {
+///     y_loc.set_value(1.0)?;
+///     Ok(())
+/// })?;
+/// assert_eq!(x.dot(&y)?, x.global_size()? as f64, "incorrect add");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FieldTransfer<'a> {
+    loc: petsc::vector::Vector<'a>,
+    loc_ceed: libceed::vector::Vector<'a>,
+}
+
+impl<'a> FieldTransfer<'a> {
+    /// Returns a transfer with local work vectors for a DM
+    ///
+    /// # arguments
+    ///
+    /// * `ceed` - libCEED context for the local libCEED vector
+    /// * `dm` - DM with the local and global layouts
+    pub fn new(ceed: &libceed::Ceed, dm: &DM<'a, 'a>) -> crate::Result<Self> {
+        let loc = dm.create_local_vector()?;
+        let loc_ceed = ceed.vector(loc.local_size()? as usize)?;
+        Ok(Self { loc, loc_ceed })
+    }
+
+    /// Scatter a global vector to the local vector, inserting values, and
+    /// run a closure reading the local vector as a libCEED vector
+    ///
+    /// # arguments
+    ///
+    /// * `dm` - DM the transfer was created for
+    /// * `x` - Global vector to read
+    /// * `f` - Closure reading the local libCEED vector
+    pub fn read<R>(
+        &mut self,
+        dm: &DM,
+        x: &petsc::vector::Vector,
+        f: impl FnOnce(&libceed::vector::Vector) -> crate::Result<R>,
+    ) -> crate::Result<R> {
+        read_local(dm, x.as_raw(), &mut self.loc, &mut self.loc_ceed, |x_loc| {
+            f(x_loc)
+        })
+    }
+
+    /// Run a closure writing the zeroed local vector as a libCEED vector, and
+    /// add the local values into a global vector
+    ///
+    /// The global vector is not zeroed, so contributions accumulate.
+    ///
+    /// # arguments
+    ///
+    /// * `dm` - DM the transfer was created for
+    /// * `y` - Global vector to add into
+    /// * `f` - Closure writing the local libCEED vector
+    pub fn add<R>(
+        &mut self,
+        dm: &DM,
+        y: &mut petsc::vector::Vector,
+        f: impl FnOnce(&mut libceed::vector::Vector) -> crate::Result<R>,
+    ) -> crate::Result<R> {
+        add_local(dm, y.as_raw(), &mut self.loc, &mut self.loc_ceed, f)
+    }
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,460 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Transient operators
+//   The implicit form of M u_t + K u = b is F(t, u, u_t) = M u_t + K u - b,
+//   with Jacobian dF/du + shift dF/du_t = K + shift M
+// -----------------------------------------------------------------------------
+pub(crate) struct TransientJacobian<'a> {
+    pub(crate) mass: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    pub(crate) stiffness: petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>>,
+    pub(crate) shift: std::cell::Cell<petsc::Scalar>,
+    pub(crate) work: RefCell<petsc::vector::Vector<'a>>,
+    // Boundary data at the current stage time, see update_boundary
+    pub(crate) boundary: RefCell<petsc::vector::Vector<'a>>,
+    pub(crate) boundary_work: BoundaryWork<'a>,
+}
+
+// Work for the boundary data, built once for every stage
+pub(crate) struct BoundaryWork<'a> {
+    // Local vectors for the essential boundary values, if any
+    essential: Option<RefCell<[petsc::vector::Vector<'a>; 2]>>,
+    // Neumann fluxes and their sum at t = 0
+    fluxes: Vec<crate::surface::BoundaryIntegral<'a>>,
+    flux_initial: petsc::vector::Vector<'a>,
+}
+
+impl<'a> BoundaryWork<'a> {
+    pub(crate) fn new(
+        meles: &'a crate::Meles<'a>,
+        stiffness: &crate::MelesMatShellContext<'a>,
+    ) -> crate::Result<Self> {
+        let options = &meles.bp_options;
+        let essential = if crate::ceed_bps::has_essential_boundary(options)? {
+            let dm = stiffness.dm.borrow();
+            Some(RefCell::new([
+                dm.create_local_vector()?,
+                dm.create_local_vector()?,
+            ]))
+        } else {
+            None
+        };
+        let mut flux_initial = stiffness.rhs().duplicate()?;
+        flux_initial.zero_entries()?;
+        let fluxes = options
+            .neumann_fluxes()?
+            .iter()
+            .map(|(label, values, flux)| {
+                let integral =
+                    crate::surface::BoundaryIntegral::new(meles, options, label, values, flux)?;
+                integral.add_to(0.0, 1.0, &mut flux_initial)?;
+                Ok(integral)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Self {
+            essential,
+            fluxes,
+            flux_initial,
+        })
+    }
+}
+
+// Essential boundary values at a time, zero elsewhere
+fn insert_boundary_values(
+    dm: &DM<'_, '_>,
+    g_loc: &mut petsc::vector::Vector<'_>,
+    time: f64,
+) -> crate::Result<()> {
+    g_loc.zero_entries()?;
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::DMPlexInsertBoundaryValues(
+                dm.as_raw(),
+                petsc::petsc_raw::PetscBool::PETSC_TRUE,
+                g_loc.as_raw(),
+                time,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        },
+        "DMPlexInsertBoundaryValues",
+    )
+}
+
+// Add an operator applied to a local vector to a global vector
+fn add_local_op<'a>(
+    context: &crate::MelesMatShellContext<'a>,
+    x_loc: &mut petsc::vector::Vector<'a>,
+    y: &mut petsc::vector::Vector<'a>,
+) -> crate::Result<()> {
+    let mut y_loc = context.y_loc.borrow_mut();
+    crate::petsc_ops::apply_ceed_op_local(
+        &context.op_ceed.borrow(),
+        x_loc,
+        &mut context.x_loc_ceed.borrow_mut(),
+        &mut y_loc,
+        &mut context.y_loc_ceed.borrow_mut(),
+    )?;
+    context
+        .dm
+        .borrow()
+        .local_to_global(&y_loc, InsertMode::ADD_VALUES, y)?;
+    Ok(())
+}
+
+impl crate::petsc_ops::ShellOperations for TransientJacobian<'_> {
+    // Apply the Jacobian, y = K x + shift M x
+    fn mult(&self, x: petsc::petsc_raw::Vec, y: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let work = self.work.borrow_mut();
+        check(unsafe { MatMult(self.stiffness.as_raw(), x, y) }, "MatMult")?;
+        check(
+            unsafe { MatMult(self.mass.as_raw(), x, work.as_raw()) },
+            "MatMult",
+        )?;
+        check(
+            unsafe { VecAXPY(y, self.shift.get(), work.as_raw()) },
+            "VecAXPY",
+        )
+    }
+
+    // Jacobian diagonal, diag(K) + shift diag(M)
+    fn get_diagonal(&self, d: petsc::petsc_raw::Vec) -> crate::Result<()> {
+        let work = self.work.borrow_mut();
+        crate::petsc_ops::compute_diagonal_ceed(d, self.stiffness.mat_data().unwrap())?;
+        crate::petsc_ops::compute_diagonal_ceed(work.as_raw(), self.mass.mat_data().unwrap())?;
+        crate::check_petsc_raw(
+            unsafe { petsc::petsc_raw::VecAXPY(d, self.shift.get(), work.as_raw()) },
+            "VecAXPY",
+        )
+    }
+}
+
+impl<'a> TransientJacobian<'a> {
+    // Implicit residual, F = M u_t + K u - b + boundary terms
+    fn residual(
+        &self,
+        u: &petsc::vector::Vector<'a>,
+        u_t: &petsc::vector::Vector<'a>,
+        f: &mut petsc::vector::Vector<'a>,
+    ) -> petsc::Result<()> {
+        let mut work = self.work.borrow_mut();
+        self.stiffness.mult(u, f)?;
+        self.mass.mult(u_t, &mut work)?;
+        f.axpy(1.0, &work)?;
+        f.axpy(-1.0, self.stiffness.mat_data().unwrap().rhs())?;
+        f.axpy(1.0, &self.boundary.borrow())
+    }
+
+    // Evaluate the boundary data at a time
+    //   The boundary term is K g(t) + M g_t(t) - (n(t) - n(0)), with g(t) the
+    //   essential boundary values inserted into a local vector, g_t(t) their
+    //   centered difference quotient in time, and n(t) the Neumann term, whose
+    //   value at t = 0 is part of the benchmark RHS; the Jacobian does not
+    //   depend on the boundary data
+    fn update_boundary(&self, time: f64) -> crate::Result<()> {
+        let work = &self.boundary_work;
+        let mut boundary = self.boundary.borrow_mut();
+        boundary.zero_entries()?;
+
+        // Essential boundary values, lifted by the stiffness and mass operators
+        if let Some(essential) = &work.essential {
+            let mut essential = essential.borrow_mut();
+            let [g_loc, g_t_loc] = &mut *essential;
+            let stiffness = self.stiffness.mat_data().unwrap();
+            let dm = stiffness.dm.borrow();
+            let delta = f64::EPSILON.sqrt() * time.abs().max(1.0);
+            insert_boundary_values(&dm, g_t_loc, time + delta)?;
+            insert_boundary_values(&dm, g_loc, time - delta)?;
+            g_t_loc.axpy(-1.0, g_loc)?;
+            g_t_loc.scale(0.5 / delta)?;
+            add_local_op(self.mass.mat_data().unwrap(), g_t_loc, &mut boundary)?;
+            insert_boundary_values(&dm, g_loc, time)?;
+            add_local_op(stiffness, g_loc, &mut boundary)?;
+        }
+
+        // Neumann fluxes, replacing their value at t = 0
+        boundary.axpy(1.0, &work.flux_initial)?;
+        for integral in &work.fluxes {
+            integral.add_to(time, -1.0, &mut boundary)?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Transient solver
+// -----------------------------------------------------------------------------
+/// TS for the transient benchmark problem `M u_t + K u = b`
+///
+/// The mass matrix `M` and the benchmark operator `K` are applied with
+/// libCEED in the TS IFunction, and the IJacobian `K + shift M` is a
+/// MatShell, so any implicit time integrator selected with `-ts_type` works
+/// matrix-free. The forcing `b` is the benchmark problem RHS, so the solution
+/// approaches the steady manufactured solution over time. Essential boundary
+/// values, from `u_bc`, the manufactured solution, or boundary functions, and
+/// Neumann fluxes are evaluated at the time of each stage.
+///
+/// With `-ts_snapshot_interval`, snapshots of the solution are written to
+/// `-ts_snapshot_directory`, as VTU from a helper thread on each rank, where
+/// `solve` waits for pending snapshots before returning, or as HDF5 with
+/// `-ts_snapshot_format hdf5`. With `-ts_statistics`, the
+/// selected reductions of the solution are computed matrix-free each step and
+/// appended to `-ts_statistics_file`.
+pub struct TransientSolver<'a> {
+    pub ts: TS<'a, 'a>,
+    // Dropped after the TS that references it
+    jacobian: std::rc::Rc<petsc::mat::MatShell<'a, 'a, TransientJacobian<'a>>>,
+    meles: &'a crate::Meles<'a>,
+    snapshots: Option<std::rc::Rc<crate::snapshot::SnapshotWriter>>,
+    statistics: Option<std::rc::Rc<RefCell<Vec<crate::StepStatistics>>>>,
+}
+
+impl<'a> TransientSolver<'a> {
+    /// Returns the mass MatShell
+    pub fn mass(&self) -> &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>> {
+        &self.jacobian.mat_data().unwrap().mass
+    }
+
+    /// Returns the stiffness MatShell, the benchmark problem operator
+    pub fn stiffness(&self) -> &petsc::mat::MatShell<'a, 'a, crate::MelesMatShellContext<'a>> {
+        &self.jacobian.mat_data().unwrap().stiffness
+    }
+
+    /// Returns a global vector for the solution
+    pub fn create_global_vector(&self) -> petsc::Result<petsc::vector::Vector<'a>> {
+        self.stiffness()
+            .mat_data()
+            .unwrap()
+            .dm
+            .borrow()
+            .create_global_vector()
+    }
+
+    /// Returns the statistics recorded at each time step so far, empty if no
+    ///   statistics were selected with `-ts_statistics`
+    pub fn statistics(&self) -> Vec<crate::StepStatistics> {
+        self.statistics
+            .as_ref()
+            .map_or(vec![], |statistics| statistics.borrow().clone())
+    }
+
+    /// Integrate in time from the given initial condition, returning the
+    ///   reason if integration stopped early
+    ///
+    /// After SIGTERM or when the `max_walltime` budget is exhausted, the time
+    /// step in progress finishes and a checkpoint and partial report are
+    /// written to the `partial_results` directory.
+    ///
+    /// Note: This returns `meles::Result<Option<ShutdownReason>>`, not
+    /// `petsc::Result<()>` as in earlier versions; callers that only used `?`
+    /// are unchanged, and callers matching on the result should match on the
+    /// reason instead.
+    ///
+    /// # arguments
+    ///
+    /// * `u` - Initial condition, overwritten with the solution at the final
+    ///           time
+    pub fn solve(
+        &mut self,
+        u: &mut petsc::vector::Vector<'a>,
+    ) -> crate::Result<Option<crate::ShutdownReason>> {
+        let _stage = crate::events::Stage::Solve.push();
+        let start_time = crate::SolverState::from_ts(&self.ts)?.time;
+        self.jacobian
+            .mat_data()
+            .unwrap()
+            .update_boundary(start_time)?;
+        self.ts.solve(u)?;
+        if let Some(snapshots) = &self.snapshots {
+            snapshots.flush()?;
+        }
+        let state = crate::SolverState::from_ts(&self.ts)?;
+        let stopped = crate::shutdown::ts_stopped(&self.ts)?;
+        crate::shutdown::finish_solve(self.meles, stopped, u, &state, None)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Setup TS for the benchmark problem of a Meles context
+// -----------------------------------------------------------------------------
+pub(crate) fn ts<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<TransientSolver<'a>> {
+    // Operators
+    let stiffness = meles.mat_shell(petsc)?;
+    let kernel = crate::ceed_bps::mass_problem(&meles.bp_options)?;
+    let context = crate::ceed_bps::mat_shell_context_with_kernel(meles, &meles.bp_options, kernel)?;
+    let mass = crate::petsc_ops::create_mat_shell(context)?;
+    let work = RefCell::new(stiffness.mat_data().unwrap().rhs().duplicate()?);
+    let boundary = RefCell::new(stiffness.mat_data().unwrap().rhs().duplicate()?);
+    boundary.borrow_mut().zero_entries()?;
+    let boundary_work = BoundaryWork::new(meles, stiffness.mat_data().unwrap())?;
+
+    // Jacobian MatShell, on the DM of the operators
+    let jacobian = {
+        let mat = stiffness.mat_data().unwrap().dm.borrow().create_matrix()?;
+        let mat = mat.into_shell(Box::new(TransientJacobian {
+            mass,
+            stiffness,
+            shift: std::cell::Cell::new(0.0),
+            work,
+            boundary,
+            boundary_work,
+        }))?;
+        crate::petsc_ops::set_shell_operations(
+            &mat,
+            &[MatOperation::MATOP_MULT, MatOperation::MATOP_GET_DIAGONAL],
+        )?;
+        std::rc::Rc::new(mat)
+    };
+
+    // TS
+    let mut ts = TS::create(meles.comm)?;
+    ts.set_problem_type(TSProblemType::TS_LINEAR)?;
+    ts.set_type(TSType::TSBEULER)?;
+    let residual_jacobian = jacobian.clone();
+    ts.set_ifunction(None, move |_ts, _t, u, u_t, f| {
+        residual_jacobian.mat_data().unwrap().residual(u, u_t, f)
+    })?;
+    set_ts_ijacobian(ts.as_raw(), &jacobian)?;
+    set_ts_pre_stage(ts.as_raw(), &jacobian)?;
+    ts.set_exact_final_time(TSExactFinalTimeOption::TS_EXACTFINALTIME_MATCHSTEP)?;
+    let (snapshot_options, statistics_options) = {
+        let _options_lock = crate::config::options_lock();
+        ts.set_from_options()?;
+        (petsc.options()?, petsc.options()?)
+    };
+    crate::shutdown::set_up_ts(&ts, meles)?;
+    let snapshots = crate::snapshot::set_up_ts(
+        &ts,
+        meles,
+        jacobian.mat_data().unwrap().stiffness.mat_data().unwrap(),
+        snapshot_options,
+    )?;
+    let statistics = crate::statistics::set_up_ts(
+        &ts,
+        meles,
+        jacobian.mat_data().unwrap().mass.mat_data().unwrap(),
+        statistics_options,
+    )?;
+
+    Ok(TransientSolver {
+        ts,
+        jacobian,
+        meles,
+        snapshots,
+        statistics,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Use the Jacobian MatShell as the TS IJacobian
+//   The operators are linear, so the callback only records the shift and the
+//   cached diagonals of M and K remain valid
+// -----------------------------------------------------------------------------
+fn set_ts_ijacobian(
+    ts: petsc::petsc_raw::TS,
+    mat: &petsc::mat::MatShell<'_, '_, TransientJacobian<'_>>,
+) -> crate::Result<()> {
+    let context = mat.mat_data().unwrap() as *const TransientJacobian;
+    crate::check_petsc_raw(
+        unsafe {
+            petsc::petsc_raw::TSSetIJacobian(
+                ts,
+                mat.as_raw(),
+                mat.as_raw(),
+                Some(update_ijacobian),
+                context as *mut std::os::raw::c_void,
+            )
+        },
+        "TSSetIJacobian",
+    )
+}
+
+unsafe extern "C" fn update_ijacobian(
+    _ts: petsc::petsc_raw::TS,
+    _t: petsc::petsc_raw::PetscReal,
+    _u: petsc::petsc_raw::Vec,
+    _u_t: petsc::petsc_raw::Vec,
+    shift: petsc::petsc_raw::PetscReal,
+    _a: petsc::petsc_raw::Mat,
+    _p: petsc::petsc_raw::Mat,
+    ctx: *mut std::os::raw::c_void,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    if ctx.is_null() {
+        return PETSC_ERR_PLIB as PetscErrorCode;
+    }
+    let jacobian = &*(ctx as *const TransientJacobian);
+    jacobian.shift.set(shift);
+    0
+}
+
+// -----------------------------------------------------------------------------
+// Update the boundary data at each stage time
+//   TSSetPreStage has no context argument, so the Jacobian context is
+//   composed with the TS and queried in the hook
+// -----------------------------------------------------------------------------
+const BOUNDARY_CONTEXT_NAME: &str = "meles_transient_boundary";
+
+fn set_ts_pre_stage(
+    ts: petsc::petsc_raw::TS,
+    mat: &petsc::mat::MatShell<'_, '_, TransientJacobian<'_>>,
+) -> crate::Result<()> {
+    let context = mat.mat_data().unwrap() as *const TransientJacobian;
+    let name = std::ffi::CString::new(BOUNDARY_CONTEXT_NAME).unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(ts as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut container = std::ptr::null_mut();
+        check(
+            PetscContainerCreate(comm, &mut container),
+            "PetscContainerCreate",
+        )?;
+        check(
+            PetscContainerSetPointer(container, context as *mut std::os::raw::c_void),
+            "PetscContainerSetPointer",
+        )?;
+        check(
+            PetscObjectCompose(ts as PetscObject, name.as_ptr(), container as PetscObject),
+            "PetscObjectCompose",
+        )?;
+        check(
+            PetscContainerDestroy(&mut container),
+            "PetscContainerDestroy",
+        )?;
+        check(TSSetPreStage(ts, Some(update_boundary)), "TSSetPreStage")
+    }
+}
+
+unsafe extern "C" fn update_boundary(
+    ts: petsc::petsc_raw::TS,
+    stage_time: petsc::petsc_raw::PetscReal,
+) -> petsc::petsc_raw::PetscErrorCode {
+    use petsc::petsc_raw::*;
+    let name = std::ffi::CString::new(BOUNDARY_CONTEXT_NAME).unwrap();
+    let mut container = std::ptr::null_mut();
+    let mut pointer = std::ptr::null_mut();
+    if PetscObjectQuery(ts as PetscObject, name.as_ptr(), &mut container) != 0
+        || container.is_null()
+        || PetscContainerGetPointer(container as PetscContainer, &mut pointer) != 0
+    {
+        return PETSC_ERR_PLIB as PetscErrorCode;
+    }
+    let jacobian = &*(pointer as *const TransientJacobian);
+    match jacobian.update_boundary(stage_time) {
+        Ok(()) => 0,
+        Err(error) => crate::petsc_callback_error("TSPreStage", error),
+    }
+}
+
+// -----------------------------------------------------------------------------
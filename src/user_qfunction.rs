@@ -0,0 +1,193 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Geometric factors
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Geometric factors stored at each quadrature point for a user QFunction
+pub enum GeometricFactors {
+    /// One value, `w det(J)`, as for mass matrices
+    Volume,
+    /// The symmetric `w det(J) J^{-1} J^{-T}`, as for diffusion, with one
+    /// value in 1D, three values in the order `[0,0], [1,1], [0,1]` in 2D,
+    /// and six values in the order `[0,0], [1,1], [2,2], [1,2], [0,2], [0,1]`
+    /// in 3D
+    Diffusion,
+}
+
+impl GeometricFactors {
+    // Gallery QFunction computing the factors for a mesh dimension and the
+    //   number of values
+    pub(crate) fn setup(self, dim: usize) -> (String, usize) {
+        match self {
+            GeometricFactors::Volume => (format!("Mass{}DBuild", dim), 1),
+            GeometricFactors::Diffusion => (format!("Poisson{}DBuild", dim), dim * (dim + 1) / 2),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// User QFunction
+// -----------------------------------------------------------------------------
+/// Rust closure QFunction applied in place of the benchmark problem QFunction
+///
+/// The closure receives the active inputs in the order they were added,
+/// followed by the geometric factors, and writes the active outputs in the
+/// order they were added. Interpolated fields have one value per component
+/// and gradient fields have one value per component for each dimension of
+/// the mesh, ordered by reference direction, then component, then
+/// quadrature point. Closures
+/// run on the host, so `/gpu` libCEED resources require the equivalent C
+/// source, given with `UserQFunction::source`.
+///
+/// ```
+/// # use meles::prelude::*;
+/// # use petsc::prelude::*;
+/// # fn main() -> meles::Result<()> {
+/// // mass matrix scaled by two
+/// let apply = |[u, q_data, ..]: libceed::qfunction::QFunctionInputs,
+///              [v, ..]: libceed::qfunction::QFunctionOutputs| {
+///     for i in 0..q_data.len() {
+///         v[i] = 2.0 * q_data[i] * u[i];
+///     }
+///     0
+/// };
+/// let q_function = meles::UserQFunction::new(meles::GeometricFactors::Volume, apply)
+///     .input(libceed::EvalMode::Interp)
+///     .output(libceed::EvalMode::Interp);
+/// # Ok(())
+/// # }
+/// ```
+pub struct UserQFunction {
+    pub(crate) geometric_factors: GeometricFactors,
+    pub(crate) inputs: Vec<libceed::EvalMode>,
+    pub(crate) outputs: Vec<libceed::EvalMode>,
+    pub(crate) apply: Box<libceed::qfunction::QFunctionUserClosure>,
+    pub(crate) source: Option<crate::QFunctionSource>,
+}
+
+impl UserQFunction {
+    /// Returns a user QFunction with no active fields
+    ///
+    /// # arguments
+    ///
+    /// * `geometric_factors` - Geometric factors passed to the closure
+    /// * `apply` - Closure applying the QFunction at quadrature points
+    pub fn new(
+        geometric_factors: GeometricFactors,
+        apply: impl FnMut(libceed::qfunction::QFunctionInputs, libceed::qfunction::QFunctionOutputs) -> i32
+            + 'static,
+    ) -> Self {
+        Self {
+            geometric_factors,
+            inputs: vec![],
+            outputs: vec![],
+            apply: Box::new(apply),
+            source: None,
+        }
+    }
+
+    /// Add an active input, `EvalMode::Interp` or `EvalMode::Grad`
+    pub fn input(mut self, eval_mode: libceed::EvalMode) -> Self {
+        self.inputs.push(eval_mode);
+        self
+    }
+
+    /// Add an active output, `EvalMode::Interp` or `EvalMode::Grad`
+    pub fn output(mut self, eval_mode: libceed::EvalMode) -> Self {
+        self.outputs.push(eval_mode);
+        self
+    }
+
+    /// Set the C source of the QFunction, used on `/gpu` libCEED resources
+    ///
+    /// The source function takes the same fields, in the same order, as the
+    /// closure, with the input and output field names `u` or `du` and `v` or
+    /// `dv`, and the geometric factors named `qdata`.
+    pub fn source(mut self, source: crate::QFunctionSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    // Field names and sizes for the active inputs or outputs
+    fn fields(
+        eval_modes: &[libceed::EvalMode],
+        kind: &str,
+        prefix: &str,
+        num_components: usize,
+        dim: usize,
+    ) -> crate::Result<Vec<(String, usize, libceed::EvalMode)>> {
+        let mut fields: Vec<(String, usize, libceed::EvalMode)> = vec![];
+        for &eval_mode in eval_modes {
+            let field = match eval_mode {
+                libceed::EvalMode::Interp => (prefix.to_string(), num_components, eval_mode),
+                libceed::EvalMode::Grad => {
+                    (format!("d{}", prefix), dim * num_components, eval_mode)
+                }
+                _ => {
+                    return Err(crate::Error {
+                        message: format!(
+                            "user QFunction {}s must be Interp or Grad, not {:?}",
+                            kind, eval_mode
+                        ),
+                    })
+                }
+            };
+            if fields.iter().any(|(name, _, _)| *name == field.0) {
+                return Err(crate::Error {
+                    message: format!("user QFunction {} {} added more than once", kind, field.0),
+                });
+            }
+            fields.push(field);
+        }
+        if fields.is_empty() {
+            return Err(crate::Error {
+                message: format!("user QFunction requires at least one active {}", kind),
+            });
+        }
+        Ok(fields)
+    }
+
+    // Field names, sizes, and evaluation modes for the active inputs and
+    //   outputs
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn active_fields(
+        &self,
+        num_components: usize,
+        dim: usize,
+    ) -> crate::Result<(
+        Vec<(String, usize, libceed::EvalMode)>,
+        Vec<(String, usize, libceed::EvalMode)>,
+    )> {
+        Ok((
+            Self::fields(&self.inputs, "input", "u", num_components, dim)?,
+            Self::fields(&self.outputs, "output", "v", num_components, dim)?,
+        ))
+    }
+
+    // Create the libCEED QFunction, returning the active input and output
+    //   field names
+    pub(crate) fn create<'b>(
+        self,
+        ceed: &libceed::Ceed,
+        num_components: usize,
+        dim: usize,
+    ) -> crate::Result<(QFunction<'b>, Vec<String>, Vec<String>)> {
+        let (inputs, outputs) = self.active_fields(num_components, dim)?;
+        let (_, q_data_size) = self.geometric_factors.setup(dim);
+        let mut qf = ceed.q_function_interior(1, self.apply)?;
+        for (name, size, eval_mode) in &inputs {
+            qf = qf.input(name, *size, *eval_mode)?;
+        }
+        qf = qf.input("qdata", q_data_size, EvalMode::None)?;
+        for (name, size, eval_mode) in &outputs {
+            qf = qf.output(name, *size, *eval_mode)?;
+        }
+        let names = |fields: Vec<(String, usize, libceed::EvalMode)>| {
+            fields.into_iter().map(|(name, _, _)| name).collect()
+        };
+        Ok((qf, names(inputs), names(outputs)))
+    }
+}
+
+// -----------------------------------------------------------------------------
@@ -0,0 +1,730 @@
+use crate::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Run summary
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Quantities recorded from a single solve, used for regression comparison
+pub struct RunSummary {
+    pub iterations: usize,
+    pub residual_norm: f64,
+    pub error: f64,
+}
+
+impl RunSummary {
+    /// Write the summary as a reference file with the given tolerances
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the reference file to write
+    /// * `profiles` - Tolerance profiles stored with the reference
+    pub fn write_reference(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        profiles: &ToleranceProfiles,
+    ) -> crate::Result<()> {
+        let mut contents = format!(
+            "iterations: {}\nresidual_norm: {:e}\nerror: {:e}\ntolerance: {:e}\niteration_slack: {}\n",
+            self.iterations,
+            self.residual_norm,
+            self.error,
+            profiles.fallback.rtol,
+            profiles.fallback.iteration_slack
+        );
+        for profile in &profiles.profiles {
+            contents += &format!(
+                "tolerance[{}]: {:e}\niteration_slack[{}]: {}\n",
+                profile.pattern, profile.rtol, profile.pattern, profile.iteration_slack
+            );
+        }
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Run Summary:")?;
+        writeln!(f, "  Iterations    : {}", self.iterations)?;
+        writeln!(f, "  Residual norm : {:e}", self.residual_norm)?;
+        write!(f, "  Error         : {:e}", self.error)
+    }
+}
+
+// Solve for a manufactured solution of all ones and record the results
+pub(crate) fn run_summary<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<RunSummary> {
+    // Operator and solver
+    let mat = meles.mat_shell(petsc)?;
+    let mut ksp = KSP::create(meles.comm)?;
+    ksp.set_operators(&mat, &mat)?;
+    {
+        let _options_lock = crate::config::options_lock();
+        ksp.set_from_options()?;
+    }
+
+    // Manufactured solution and RHS
+    let mut x_exact = meles.dm.borrow().create_global_vector()?;
+    x_exact.set_all(1.0)?;
+    let mut rhs = x_exact.duplicate()?;
+    mat.mult(&x_exact, &mut rhs)?;
+
+    // Solve
+    let mut x = x_exact.duplicate()?;
+    ksp.solve(&rhs, &mut x)?;
+    let iterations = ksp.get_iteration_number()? as usize;
+    let residual_norm = ksp.get_residual_norm()?;
+
+    // Error
+    x.axpy(-1.0, &x_exact)?;
+    let error = x.norm(NormType::NORM_MAX)?;
+
+    Ok(RunSummary {
+        iterations,
+        residual_norm,
+        error,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Tolerance profiles
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Tolerances applied to runs on Ceed resources matching a pattern
+///
+/// Patterns may contain `*` wildcards; a pattern without wildcards matches
+/// any resource it is a prefix of, so `/gpu` matches `/gpu/cuda/gen`.
+pub struct ToleranceProfile {
+    pub pattern: String,
+    pub rtol: f64,
+    pub iteration_slack: usize,
+}
+
+impl ToleranceProfile {
+    /// Returns a tolerance profile for the given resource pattern
+    ///
+    /// # arguments
+    ///
+    /// * `pattern` - Ceed resource pattern, such as `/gpu/*`
+    /// * `rtol` - Relative tolerance for norms and errors
+    /// * `iteration_slack` - Allowed difference in iteration counts
+    pub fn new(pattern: impl Into<String>, rtol: f64, iteration_slack: usize) -> Self {
+        Self {
+            pattern: pattern.into(),
+            rtol,
+            iteration_slack,
+        }
+    }
+
+    /// Returns true if the profile applies to the Ceed resource
+    pub fn matches(&self, resource: &str) -> bool {
+        if !self.pattern.contains('*') {
+            return resource.starts_with(&self.pattern);
+        }
+        // Simple glob match, `*` matches any sequence of characters
+        let parts: Vec<&str> = self.pattern.split('*').collect();
+        let mut remainder = resource;
+        for (i, part) in parts.iter().enumerate() {
+            if i == 0 {
+                match remainder.strip_prefix(part) {
+                    Some(rest) => remainder = rest,
+                    None => return false,
+                }
+            } else if i == parts.len() - 1 {
+                return remainder.ends_with(part);
+            } else {
+                match remainder.find(part) {
+                    Some(index) => remainder = &remainder[index + part.len()..],
+                    None => return false,
+                }
+            }
+        }
+        remainder.is_empty()
+    }
+
+    // Number of literal characters, used to prefer the most specific match
+    fn specificity(&self) -> usize {
+        self.pattern.chars().filter(|c| *c != '*').count()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Collection of tolerance profiles with a fallback for unmatched resources
+///
+/// The default profiles hold CPU backends to strict tolerances and allow
+/// looser tolerances for GPU backends, which reorder floating point
+/// reductions.
+pub struct ToleranceProfiles {
+    pub profiles: Vec<ToleranceProfile>,
+    pub fallback: ToleranceProfile,
+}
+
+impl Default for ToleranceProfiles {
+    fn default() -> Self {
+        Self {
+            profiles: vec![
+                ToleranceProfile::new("/cpu/*", 1e-10, 0),
+                ToleranceProfile::new("/gpu/*", 1e-6, 2),
+            ],
+            fallback: ToleranceProfile::new("*", 1e-10, 0),
+        }
+    }
+}
+
+impl ToleranceProfiles {
+    /// Returns a collection with no profiles and the given fallback
+    pub fn new(fallback_rtol: f64, fallback_iteration_slack: usize) -> Self {
+        Self {
+            profiles: vec![],
+            fallback: ToleranceProfile::new("*", fallback_rtol, fallback_iteration_slack),
+        }
+    }
+
+    /// Add a profile, replacing any existing profile with the same pattern
+    pub fn with_profile(mut self, profile: ToleranceProfile) -> Self {
+        self.profiles.retain(|p| p.pattern != profile.pattern);
+        self.profiles.push(profile);
+        self
+    }
+
+    /// Returns the most specific profile matching the Ceed resource
+    pub fn profile_for(&self, resource: &str) -> &ToleranceProfile {
+        self.profiles
+            .iter()
+            .filter(|profile| profile.matches(resource))
+            .max_by_key(|profile| profile.specificity())
+            .unwrap_or(&self.fallback)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Regression reference
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Stored reference results with per-backend tolerance profiles
+///
+/// Reference files use the same `key: value` layout as the options files.
+/// Backend specific tolerances are given as `tolerance[<resource pattern>]`
+/// and `iteration_slack[<resource pattern>]`; see `ToleranceProfile` for the
+/// pattern syntax. Tolerance keys replace the default profiles, and without
+/// any tolerance keys the default profiles are used.
+///
+/// ```text
+/// iterations: 17
+/// residual_norm: 1.2e-9
+/// error: 3.4e-6
+/// tolerance: 1e-10
+/// tolerance[/gpu/*]: 1e-6
+/// iteration_slack[/gpu/*]: 2
+/// ```
+pub struct RegressionReference {
+    pub summary: RunSummary,
+    pub profiles: ToleranceProfiles,
+}
+
+impl RegressionReference {
+    /// Read a regression reference from a file
+    ///
+    /// Pattern profiles take any value they do not set from the fallback
+    /// `tolerance` and `iteration_slack`, wherever those appear in the file.
+    ///
+    /// ```
+    /// # fn main() -> meles::Result<()> {
+    /// let summary = "iterations: 17\nresidual_norm: 1.2e-9\nerror: 3.4e-6\n";
+    /// let fallback = "tolerance: 1e-8\niteration_slack: 1\n";
+    /// let pattern = "tolerance[/gpu/*]: 1e-6\n";
+    ///
+    /// // fallback before and after the pattern profile
+    /// let path = std::env::temp_dir().join("meles_reference.txt");
+    /// let mut profiles = vec![];
+    /// for contents in [
+    ///     format!("{}{}{}", summary, fallback, pattern),
+    ///     format!("{}{}{}", summary, pattern, fallback),
+    /// ] {
+    ///     std::fs::write(&path, contents)?;
+    ///     let reference = meles::RegressionReference::from_file(&path)?;
+    ///     let profile = reference.profiles.profile_for("/gpu/cuda/ref");
+    ///     assert_eq!(profile.rtol, 1e-6, "incorrect pattern tolerance");
+    ///     assert_eq!(profile.iteration_slack, 1, "incorrect fallback slack");
+    ///     profiles.push(reference.profiles);
+    /// }
+    /// assert_eq!(profiles[0], profiles[1], "profiles depend on line order");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # arguments
+    ///
+    /// * `path` - Filepath of the reference file
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| crate::Error {
+            message: format!("failed to read reference {}: {}", path.display(), e),
+        })?;
+
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> crate::Result<T> {
+            value.parse().map_err(|_| crate::Error {
+                message: format!("failed to parse reference value for {}", key),
+            })
+        }
+
+        let (mut iterations, mut residual_norm, mut error) = (None, None, None);
+        // Tolerances are collected first, so unset pattern values take the
+        //   fallback regardless of the order of the lines
+        let (mut fallback_rtol, mut fallback_iteration_slack) = (None, None);
+        let mut patterns: Vec<(&str, Option<f64>, Option<usize>)> = vec![];
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.rsplit_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(crate::Error {
+                        message: format!("malformed reference line: {}", line),
+                    })
+                }
+            };
+            // Split `name[pattern]` keys
+            let (name, pattern) = match key.split_once('[') {
+                Some((name, pattern)) if pattern.ends_with(']') => {
+                    (name, Some(&pattern[..pattern.len() - 1]))
+                }
+                _ => (key, None),
+            };
+            match (name, pattern) {
+                ("iterations", None) => iterations = Some(parse(key, value)?),
+                ("residual_norm", None) => residual_norm = Some(parse(key, value)?),
+                ("error", None) => error = Some(parse(key, value)?),
+                ("tolerance", None) => fallback_rtol = Some(parse(key, value)?),
+                ("iteration_slack", None) => fallback_iteration_slack = Some(parse(key, value)?),
+                ("tolerance", Some(pattern)) | ("iteration_slack", Some(pattern)) => {
+                    let index = match patterns.iter().position(|(p, _, _)| *p == pattern) {
+                        Some(index) => index,
+                        None => {
+                            patterns.push((pattern, None, None));
+                            patterns.len() - 1
+                        }
+                    };
+                    if name == "tolerance" {
+                        patterns[index].1 = Some(parse(key, value)?);
+                    } else {
+                        patterns[index].2 = Some(parse(key, value)?);
+                    }
+                }
+                _ => {
+                    return Err(crate::Error {
+                        message: format!("unknown reference key: {}", key),
+                    })
+                }
+            }
+        }
+
+        // Tolerances from the file replace the default profiles entirely
+        let defaults = ToleranceProfiles::default();
+        let has_tolerances =
+            fallback_rtol.is_some() || fallback_iteration_slack.is_some() || !patterns.is_empty();
+        let profiles = if has_tolerances {
+            let fallback_rtol = fallback_rtol.unwrap_or(defaults.fallback.rtol);
+            let fallback_iteration_slack =
+                fallback_iteration_slack.unwrap_or(defaults.fallback.iteration_slack);
+            patterns.into_iter().fold(
+                ToleranceProfiles::new(fallback_rtol, fallback_iteration_slack),
+                |profiles, (pattern, rtol, iteration_slack)| {
+                    profiles.with_profile(ToleranceProfile::new(
+                        pattern,
+                        rtol.unwrap_or(fallback_rtol),
+                        iteration_slack.unwrap_or(fallback_iteration_slack),
+                    ))
+                },
+            )
+        } else {
+            defaults
+        };
+
+        let missing = |key: &str| crate::Error {
+            message: format!("reference {} missing key {}", path.display(), key),
+        };
+        Ok(Self {
+            summary: RunSummary {
+                iterations: iterations.ok_or_else(|| missing("iterations"))?,
+                residual_norm: residual_norm.ok_or_else(|| missing("residual_norm"))?,
+                error: error.ok_or_else(|| missing("error"))?,
+            },
+            profiles,
+        })
+    }
+
+    /// Replace the tolerance profiles used for comparison
+    pub fn with_profiles(mut self, profiles: ToleranceProfiles) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    /// Compare a run summary against the reference
+    ///
+    /// # arguments
+    ///
+    /// * `summary` - Measured results
+    /// * `resource` - Ceed resource used to produce the results
+    pub fn compare(&self, summary: &RunSummary, resource: &str) -> RegressionReport {
+        let ToleranceProfile {
+            pattern: _,
+            rtol,
+            iteration_slack,
+        } = self.profiles.profile_for(resource).clone();
+        let relative = |reference: f64, measured: f64| {
+            (measured - reference).abs() / reference.abs().max(f64::EPSILON)
+        };
+        let checks = vec![
+            RegressionCheck {
+                name: "iterations".to_string(),
+                reference: self.summary.iterations as f64,
+                measured: summary.iterations as f64,
+                tolerance: iteration_slack as f64,
+                passed: (summary.iterations as i64 - self.summary.iterations as i64).abs()
+                    <= iteration_slack as i64,
+            },
+            RegressionCheck {
+                name: "residual_norm".to_string(),
+                reference: self.summary.residual_norm,
+                measured: summary.residual_norm,
+                tolerance: rtol,
+                passed: relative(self.summary.residual_norm, summary.residual_norm) <= rtol,
+            },
+            RegressionCheck {
+                name: "error".to_string(),
+                reference: self.summary.error,
+                measured: summary.error,
+                tolerance: rtol,
+                passed: relative(self.summary.error, summary.error) <= rtol,
+            },
+        ];
+        RegressionReport {
+            resource: resource.to_string(),
+            checks,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Regression report
+// -----------------------------------------------------------------------------
+#[derive(Clone, Debug, PartialEq)]
+/// Result of comparing a single quantity against the reference
+pub struct RegressionCheck {
+    pub name: String,
+    pub reference: f64,
+    pub measured: f64,
+    pub tolerance: f64,
+    pub passed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Structured pass/fail data from a regression comparison
+pub struct RegressionReport {
+    pub resource: String,
+    pub checks: Vec<RegressionCheck>,
+}
+
+impl RegressionReport {
+    /// Returns true if every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Returns the checks that failed
+    pub fn failures(&self) -> Vec<&RegressionCheck> {
+        self.checks.iter().filter(|check| !check.passed).collect()
+    }
+}
+
+impl fmt::Display for RegressionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Regression Report ({}):", self.resource)?;
+        for check in &self.checks {
+            writeln!(
+                f,
+                "  {:<14}: {} (reference {:e}, measured {:e}, tolerance {:e})",
+                check.name,
+                if check.passed { "PASS" } else { "FAIL" },
+                check.reference,
+                check.measured,
+                check.tolerance
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Cross-validation
+//   The RHS and solution are PETSc binary files and the mesh is an HDF5 file,
+//   the files the C libCEED petsc/bps example writes with
+//   -ksp_view_rhs binary:rhs.bin -ksp_view_solution binary:solution.bin
+//   -dm_view hdf5:mesh.h5 for the same mesh and discretization options
+// -----------------------------------------------------------------------------
+const CROSS_VALIDATION_RHS: &str = "rhs.bin";
+const CROSS_VALIDATION_SOLUTION: &str = "solution.bin";
+const CROSS_VALIDATION_MESH: &str = "mesh.h5";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Differences between meles results and reference results from the C
+///   libCEED PETSc BP example
+pub struct CrossValidation {
+    /// Relative max norm difference of the mesh coordinates
+    pub mesh_difference: f64,
+    /// Relative max norm difference of the RHS
+    pub rhs_difference: f64,
+    /// Relative max norm difference of the solution
+    pub solution_difference: f64,
+}
+
+impl CrossValidation {
+    /// Returns true if all differences are within the tolerance
+    ///
+    /// The solution difference includes the solver tolerances of both runs,
+    /// so the tolerance should be looser than the KSP relative tolerance.
+    pub fn passed(&self, tolerance: f64) -> bool {
+        self.mesh_difference <= tolerance
+            && self.rhs_difference <= tolerance
+            && self.solution_difference <= tolerance
+    }
+}
+
+impl fmt::Display for CrossValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Cross-Validation:")?;
+        writeln!(f, "  Mesh difference     : {:e}", self.mesh_difference)?;
+        writeln!(f, "  RHS difference      : {:e}", self.rhs_difference)?;
+        write!(f, "  Solution difference : {:e}", self.solution_difference)
+    }
+}
+
+// Relative max norm difference of a vector from a reference, consumed
+fn relative_difference(
+    mut reference: petsc::vector::Vector,
+    vector: &petsc::vector::Vector,
+) -> crate::Result<f64> {
+    let reference_norm = reference.norm(NormType::NORM_MAX)?;
+    reference.axpy(-1.0, vector)?;
+    let difference_norm = reference.norm(NormType::NORM_MAX)?;
+    Ok(if reference_norm > 0.0 {
+        difference_norm / reference_norm
+    } else {
+        difference_norm
+    })
+}
+
+// Solve with one operator setup, returning the RHS and solution
+fn solve_once<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+) -> crate::Result<(petsc::vector::Vector<'a>, petsc::vector::Vector<'a>)> {
+    let mut session = crate::solve::solve_session(meles, petsc)?;
+    let mut rhs = session.rhs().duplicate()?;
+    rhs.copy_data_from(session.rhs())?;
+    let crate::SolveResult { solution, .. } = session.solve(&rhs)?;
+    Ok((rhs, solution))
+}
+
+// Relative max norm difference of the mesh coordinates from the reference
+//   mesh, loaded as a plex
+fn mesh_difference(meles: &crate::Meles, path: &std::path::Path) -> crate::Result<f64> {
+    if meles.bp_options.mesh_type != crate::dm::MeshType::Plex {
+        return Err(crate::Error {
+            message: format!(
+                "cross-validation requires a plex mesh, not {}",
+                meles.bp_options.mesh_type
+            ),
+        });
+    }
+    let plex_type = std::ffi::CString::new("plex").unwrap();
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        let dm = meles.dm.borrow();
+        let mut comm = std::mem::zeroed();
+        check(
+            PetscObjectGetComm(dm.as_raw() as PetscObject, &mut comm),
+            "PetscObjectGetComm",
+        )?;
+        let mut reference_dm = std::ptr::null_mut();
+        check(DMCreate(comm, &mut reference_dm), "DMCreate")?;
+        let result = (|| {
+            check(DMSetType(reference_dm, plex_type.as_ptr()), "DMSetType")?;
+            let mut viewer = open_viewer(meles, path, PetscFileMode::FILE_MODE_READ)?;
+            let result = check(DMLoad(reference_dm, viewer), "DMLoad");
+            check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+            result?;
+
+            // Coordinates
+            let (mut coordinates, mut reference_coordinates) =
+                (std::ptr::null_mut(), std::ptr::null_mut());
+            check(
+                DMGetCoordinates(dm.as_raw(), &mut coordinates),
+                "DMGetCoordinates",
+            )?;
+            check(
+                DMGetCoordinates(reference_dm, &mut reference_coordinates),
+                "DMGetCoordinates",
+            )?;
+            let (mut size, mut reference_size) = (0, 0);
+            check(VecGetSize(coordinates, &mut size), "VecGetSize")?;
+            check(
+                VecGetSize(reference_coordinates, &mut reference_size),
+                "VecGetSize",
+            )?;
+            if size != reference_size {
+                return Err(crate::Error {
+                    message: format!(
+                        "cross-validation reference mesh {} has {} coordinates, not {}",
+                        path.display(),
+                        reference_size,
+                        size
+                    ),
+                });
+            }
+            let mut difference = std::ptr::null_mut();
+            check(
+                VecDuplicate(reference_coordinates, &mut difference),
+                "VecDuplicate",
+            )?;
+            let mut norms = [0.0; 2];
+            let result = check(
+                VecNorm(reference_coordinates, NormType::NORM_MAX, &mut norms[0]),
+                "VecNorm",
+            )
+            .and_then(|_| {
+                check(
+                    VecWAXPY(difference, -1.0, coordinates, reference_coordinates),
+                    "VecWAXPY",
+                )
+            })
+            .and_then(|_| {
+                check(
+                    VecNorm(difference, NormType::NORM_MAX, &mut norms[1]),
+                    "VecNorm",
+                )
+            });
+            check(VecDestroy(&mut difference), "VecDestroy")?;
+            result?;
+            Ok(if norms[0] > 0.0 {
+                norms[1] / norms[0]
+            } else {
+                norms[1]
+            })
+        })();
+        check(DMDestroy(&mut reference_dm), "DMDestroy")?;
+        result
+    }
+}
+
+// Open a PETSc binary or HDF5 viewer on the communicator of the problem
+unsafe fn open_viewer(
+    meles: &crate::Meles,
+    path: &std::path::Path,
+    mode: petsc::petsc_raw::PetscFileMode,
+) -> crate::Result<petsc::petsc_raw::PetscViewer> {
+    use petsc::petsc_raw::*;
+    let filename =
+        std::ffi::CString::new(path.to_string_lossy().as_ref()).map_err(|_| crate::Error {
+            message: format!("invalid cross-validation filename {}", path.display()),
+        })?;
+    let mut comm = std::mem::zeroed();
+    crate::check_petsc_raw(
+        PetscObjectGetComm(meles.dm.borrow().as_raw() as PetscObject, &mut comm),
+        "PetscObjectGetComm",
+    )?;
+    let mut viewer = std::ptr::null_mut();
+    if path.extension().and_then(|extension| extension.to_str()) == Some("h5") {
+        crate::check_petsc_raw(
+            PetscViewerHDF5Open(comm, filename.as_ptr(), mode, &mut viewer),
+            "PetscViewerHDF5Open",
+        )?;
+    } else {
+        crate::check_petsc_raw(
+            PetscViewerBinaryOpen(comm, filename.as_ptr(), mode, &mut viewer),
+            "PetscViewerBinaryOpen",
+        )?;
+    }
+    Ok(viewer)
+}
+
+// Write the mesh, RHS, and solution
+pub(crate) fn write_cross_validation<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+    directory: &std::path::Path,
+) -> crate::Result<()> {
+    let (rhs, solution) = solve_once(meles, petsc)?;
+    std::fs::create_dir_all(directory)?;
+    unsafe {
+        use petsc::petsc_raw::*;
+        let check = crate::check_petsc_raw;
+        for &(name, vector) in &[
+            (CROSS_VALIDATION_RHS, &rhs),
+            (CROSS_VALIDATION_SOLUTION, &solution),
+        ] {
+            let mut viewer =
+                open_viewer(meles, &directory.join(name), PetscFileMode::FILE_MODE_WRITE)?;
+            let result = check(VecView(vector.as_raw(), viewer), "VecView");
+            check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+            result?;
+        }
+        let mut viewer = open_viewer(
+            meles,
+            &directory.join(CROSS_VALIDATION_MESH),
+            PetscFileMode::FILE_MODE_WRITE,
+        )?;
+        let result = check(DMView(meles.dm.borrow().as_raw(), viewer), "DMView")
+            .and_then(|_| crate::manifest::write_hdf5_provenance(meles, viewer));
+        check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+        result
+    }
+}
+
+// Solve and compare the mesh, RHS, and solution against reference files
+pub(crate) fn cross_validate<'a>(
+    meles: &'a crate::Meles<'a>,
+    petsc: &'a Petsc,
+    reference: &std::path::Path,
+) -> crate::Result<CrossValidation> {
+    let (rhs, solution) = solve_once(meles, petsc)?;
+
+    // Relative difference from a reference vector file
+    let difference = |name: &str, vector: &petsc::vector::Vector<'a>| -> crate::Result<f64> {
+        let path = reference.join(name);
+        if !path.exists() {
+            return Err(crate::Error {
+                message: format!(
+                    "missing cross-validation reference {}; write it with -ksp_view_rhs binary:{} -ksp_view_solution binary:{}",
+                    path.display(),
+                    reference.join(CROSS_VALIDATION_RHS).display(),
+                    reference.join(CROSS_VALIDATION_SOLUTION).display()
+                ),
+            });
+        }
+        let reference_vector = vector.duplicate()?;
+        unsafe {
+            use petsc::petsc_raw::*;
+            let check = crate::check_petsc_raw;
+            let mut viewer = open_viewer(meles, &path, PetscFileMode::FILE_MODE_READ)?;
+            let result = check(VecLoad(reference_vector.as_raw(), viewer), "VecLoad");
+            check(PetscViewerDestroy(&mut viewer), "PetscViewerDestroy")?;
+            result?;
+        }
+        relative_difference(reference_vector, vector)
+    };
+
+    Ok(CrossValidation {
+        mesh_difference: mesh_difference(meles, &reference.join(CROSS_VALIDATION_MESH))?,
+        rhs_difference: difference(CROSS_VALIDATION_RHS, &rhs)?,
+        solution_difference: difference(CROSS_VALIDATION_SOLUTION, &solution)?,
+    })
+}
+
+// -----------------------------------------------------------------------------